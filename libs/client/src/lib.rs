@@ -1083,11 +1083,30 @@ impl KanidmClient {
 
     #[instrument(level = "debug", skip(self))]
     pub async fn auth_step_init(&self, ident: &str) -> Result<BTreeSet<AuthMech>, ClientError> {
+        self.auth_step_init_inner(ident, false).await
+    }
+
+    /// As per `auth_step_init`, but the issued session is read/write and may never
+    /// re-authenticate to gain privileges.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn auth_step_init_api_only(
+        &self,
+        ident: &str,
+    ) -> Result<BTreeSet<AuthMech>, ClientError> {
+        self.auth_step_init_inner(ident, true).await
+    }
+
+    async fn auth_step_init_inner(
+        &self,
+        ident: &str,
+        api_only: bool,
+    ) -> Result<BTreeSet<AuthMech>, ClientError> {
         let auth_init = AuthRequest {
             step: AuthStep::Init2 {
                 username: ident.to_string(),
                 issue: AuthIssueSession::Token,
                 privileged: false,
+                api_only,
             },
         };
 
@@ -1,7 +1,10 @@
 use crate::{ClientError, KanidmClient};
 use kanidm_proto::constants::*;
 use kanidm_proto::internal::{CredentialStatus, IdentifyUserRequest, IdentifyUserResponse};
-use kanidm_proto::v1::{AccountUnixExtend, Entry, SingleStringRequest, UatStatus};
+use kanidm_proto::v1::{
+    AccountSessionSummary, AccountUnixExtend, Entry, Oauth2ClientSessions,
+    Oauth2ClientSessionsQuery, SingleStringRequest, UatStatus, UatStatusQuery,
+};
 use std::collections::BTreeMap;
 use uuid::Uuid;
 
@@ -256,6 +259,27 @@ impl KanidmClient {
             .await
     }
 
+    /// List the user auth tokens of an account that match `query`.
+    pub async fn idm_account_query_user_auth_token(
+        &self,
+        id: &str,
+        query: UatStatusQuery,
+    ) -> Result<Vec<UatStatus>, ClientError> {
+        self.perform_get_request_query(
+            format!("/v1/account/{id}/_user_auth_token").as_str(),
+            Some(query),
+        )
+        .await
+    }
+
+    pub async fn idm_account_session_summary(
+        &self,
+        id: &str,
+    ) -> Result<AccountSessionSummary, ClientError> {
+        self.perform_get_request(format!("/v1/account/{id}/_session_summary").as_str())
+            .await
+    }
+
     pub async fn idm_account_destroy_user_auth_token(
         &self,
         id: &str,
@@ -272,6 +296,30 @@ impl KanidmClient {
         .await
     }
 
+    /// List the oauth2 sessions of an account, grouped by the client they were issued to.
+    pub async fn idm_account_list_oauth2_sessions(
+        &self,
+        id: &str,
+        query: Oauth2ClientSessionsQuery,
+    ) -> Result<Vec<Oauth2ClientSessions>, ClientError> {
+        self.perform_get_request_query(
+            format!("/v1/account/{id}/_oauth2_session").as_str(),
+            Some(query),
+        )
+        .await
+    }
+
+    /// Revoke every oauth2 session an account holds with `client`, which may be a name or uuid.
+    pub async fn idm_account_revoke_oauth2_client_sessions(
+        &self,
+        id: &str,
+        client: &str,
+    ) -> Result<(), ClientError> {
+        let client = urlencoding::encode(client);
+        self.perform_delete_request(format!("/v1/account/{id}/_oauth2_session/{client}").as_str())
+            .await
+    }
+
     pub async fn idm_person_certificate_list(&self, id: &str) -> Result<Vec<Entry>, ClientError> {
         self.perform_get_request(format!("/v1/person/{id}/_certificate").as_str())
             .await
@@ -259,4 +259,19 @@ impl KanidmClient {
         )
         .await
     }
+
+    /// Destroy the api token with this label. If the label is held by more than one
+    /// token this fails with
+    /// [kanidm_proto::internal::OperationError::AmbiguousLabel] listing the candidates.
+    pub async fn idm_service_account_destroy_api_token_by_label(
+        &self,
+        id: &str,
+        label: &str,
+    ) -> Result<(), ClientError> {
+        let label = urlencoding::encode(label);
+        self.perform_delete_request(
+            format!("/v1/service_account/{id}/_api_token/_label/{label}").as_str(),
+        )
+        .await
+    }
 }
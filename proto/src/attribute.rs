@@ -39,6 +39,11 @@ pub enum Attribute {
     AttributeName,
     AttributeType,
     AuthSessionExpiry,
+    AuthSessionIdleTimeout,
+    AuthSessionExpirySingleFactor,
+    AuthSessionExpiryMultiFactor,
+    AuthSessionIssueLimit,
+    AuthSessionIssueWindow,
     AuthPasswordMinimumLength,
     BadlistPassword,
     Certificate,
@@ -114,6 +119,7 @@ pub enum Attribute {
     LoginShell,
     Mail,
     MailDestination,
+    MaximumValues,
     May,
     Member,
     MemberCreateOnce,
@@ -167,6 +173,9 @@ pub enum Attribute {
     RecycledDirectMemberOf,
     Refers,
     Replicated,
+    RetainCurrentSessionOnPasswordChange,
+    RetainSessionsOnPasswordChange,
+    RevokeSessionsOnPasswordChange,
     Rs256PrivateKeyDer,
     S256,
     /// A set of scim schemas. This is similar to a kanidm class.
@@ -315,6 +324,11 @@ impl Attribute {
             Attribute::AttributeName => ATTR_ATTRIBUTENAME,
             Attribute::AttributeType => ATTR_ATTRIBUTETYPE,
             Attribute::AuthSessionExpiry => ATTR_AUTH_SESSION_EXPIRY,
+            Attribute::AuthSessionIdleTimeout => ATTR_AUTH_SESSION_IDLE_TIMEOUT,
+            Attribute::AuthSessionExpirySingleFactor => ATTR_AUTH_SESSION_EXPIRY_SINGLE_FACTOR,
+            Attribute::AuthSessionExpiryMultiFactor => ATTR_AUTH_SESSION_EXPIRY_MULTI_FACTOR,
+            Attribute::AuthSessionIssueLimit => ATTR_AUTH_SESSION_ISSUE_LIMIT,
+            Attribute::AuthSessionIssueWindow => ATTR_AUTH_SESSION_ISSUE_WINDOW,
             Attribute::AuthPasswordMinimumLength => ATTR_AUTH_PASSWORD_MINIMUM_LENGTH,
             Attribute::BadlistPassword => ATTR_BADLIST_PASSWORD,
             Attribute::Certificate => ATTR_CERTIFICATE,
@@ -389,6 +403,7 @@ impl Attribute {
             Attribute::LoginShell => ATTR_LOGINSHELL,
             Attribute::Mail => ATTR_MAIL,
             Attribute::MailDestination => ATTR_MAIL_DESTINATION,
+            Attribute::MaximumValues => ATTR_MAXIMUM_VALUES,
             Attribute::May => ATTR_MAY,
             Attribute::Member => ATTR_MEMBER,
             Attribute::MemberCreateOnce => ATTR_MEMBER_CREATE_ONCE,
@@ -444,6 +459,11 @@ impl Attribute {
             Attribute::RecycledDirectMemberOf => ATTR_RECYCLEDDIRECTMEMBEROF,
             Attribute::Refers => ATTR_REFERS,
             Attribute::Replicated => ATTR_REPLICATED,
+            Attribute::RetainCurrentSessionOnPasswordChange => {
+                ATTR_RETAIN_CURRENT_SESSION_ON_PASSWORD_CHANGE
+            }
+            Attribute::RetainSessionsOnPasswordChange => ATTR_RETAIN_SESSIONS_ON_PASSWORD_CHANGE,
+            Attribute::RevokeSessionsOnPasswordChange => ATTR_REVOKE_SESSIONS_ON_PASSWORD_CHANGE,
             Attribute::Rs256PrivateKeyDer => ATTR_RS256_PRIVATE_KEY_DER,
             Attribute::S256 => ATTR_S256,
             Attribute::Scope => ATTR_SCOPE,
@@ -539,6 +559,11 @@ impl Attribute {
             ATTR_ATTRIBUTENAME => Attribute::AttributeName,
             ATTR_ATTRIBUTETYPE => Attribute::AttributeType,
             ATTR_AUTH_SESSION_EXPIRY => Attribute::AuthSessionExpiry,
+            ATTR_AUTH_SESSION_IDLE_TIMEOUT => Attribute::AuthSessionIdleTimeout,
+            ATTR_AUTH_SESSION_EXPIRY_SINGLE_FACTOR => Attribute::AuthSessionExpirySingleFactor,
+            ATTR_AUTH_SESSION_EXPIRY_MULTI_FACTOR => Attribute::AuthSessionExpiryMultiFactor,
+            ATTR_AUTH_SESSION_ISSUE_LIMIT => Attribute::AuthSessionIssueLimit,
+            ATTR_AUTH_SESSION_ISSUE_WINDOW => Attribute::AuthSessionIssueWindow,
             ATTR_AUTH_PASSWORD_MINIMUM_LENGTH => Attribute::AuthPasswordMinimumLength,
             ATTR_BADLIST_PASSWORD => Attribute::BadlistPassword,
             ATTR_CERTIFICATE => Attribute::Certificate,
@@ -613,6 +638,7 @@ impl Attribute {
             ATTR_LIMIT_SEARCH_MAX_FILTER_TEST => Attribute::LimitSearchMaxFilterTest,
             ATTR_MAIL => Attribute::Mail,
             ATTR_MAIL_DESTINATION => Attribute::MailDestination,
+            ATTR_MAXIMUM_VALUES => Attribute::MaximumValues,
             ATTR_MAY => Attribute::May,
             ATTR_MEMBER => Attribute::Member,
             ATTR_MEMBER_CREATE_ONCE => Attribute::MemberCreateOnce,
@@ -668,6 +694,11 @@ impl Attribute {
             ATTR_RECYCLEDDIRECTMEMBEROF => Attribute::RecycledDirectMemberOf,
             ATTR_REFERS => Attribute::Refers,
             ATTR_REPLICATED => Attribute::Replicated,
+            ATTR_RETAIN_CURRENT_SESSION_ON_PASSWORD_CHANGE => {
+                Attribute::RetainCurrentSessionOnPasswordChange
+            }
+            ATTR_RETAIN_SESSIONS_ON_PASSWORD_CHANGE => Attribute::RetainSessionsOnPasswordChange,
+            ATTR_REVOKE_SESSIONS_ON_PASSWORD_CHANGE => Attribute::RevokeSessionsOnPasswordChange,
             ATTR_RS256_PRIVATE_KEY_DER => Attribute::Rs256PrivateKeyDer,
             ATTR_S256 => Attribute::S256,
             ATTR_SCIM_SCHEMAS => Attribute::ScimSchemas,
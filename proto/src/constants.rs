@@ -80,6 +80,11 @@ pub const ATTR_ATTR: &str = "attr";
 pub const ATTR_ATTRIBUTENAME: &str = "attributename";
 pub const ATTR_ATTRIBUTETYPE: &str = "attributetype";
 pub const ATTR_AUTH_SESSION_EXPIRY: &str = "authsession_expiry";
+pub const ATTR_AUTH_SESSION_IDLE_TIMEOUT: &str = "authsession_idle_timeout";
+pub const ATTR_AUTH_SESSION_EXPIRY_SINGLE_FACTOR: &str = "authsession_expiry_single_factor";
+pub const ATTR_AUTH_SESSION_EXPIRY_MULTI_FACTOR: &str = "authsession_expiry_multi_factor";
+pub const ATTR_AUTH_SESSION_ISSUE_LIMIT: &str = "authsession_issue_limit";
+pub const ATTR_AUTH_SESSION_ISSUE_WINDOW: &str = "authsession_issue_window";
 pub const ATTR_AUTH_PASSWORD_MINIMUM_LENGTH: &str = "auth_password_minimum_length";
 pub const ATTR_BADLIST_PASSWORD: &str = "badlist_password";
 pub const ATTR_CASCADE_DELETED: &str = "cascade_deleted";
@@ -152,6 +157,7 @@ pub const ATTR_LINKEDGROUP: &str = "linked_group";
 pub const ATTR_LOGINSHELL: &str = "loginshell";
 pub const ATTR_MAIL: &str = "mail";
 pub const ATTR_MAIL_DESTINATION: &str = "mail_destination";
+pub const ATTR_MAXIMUM_VALUES: &str = "maximum_values";
 pub const ATTR_MAY: &str = "may";
 pub const ATTR_MEMBER: &str = "member";
 pub const ATTR_MEMBER_CREATE_ONCE: &str = "member_create_once";
@@ -209,6 +215,10 @@ pub const ATTR_RECYCLED: &str = "recycled";
 pub const ATTR_RECYCLEDDIRECTMEMBEROF: &str = "recycled_directmemberof";
 pub const ATTR_REFERS: &str = "refers";
 pub const ATTR_REPLICATED: &str = "replicated";
+pub const ATTR_RETAIN_CURRENT_SESSION_ON_PASSWORD_CHANGE: &str =
+    "retain_current_session_on_password_change";
+pub const ATTR_RETAIN_SESSIONS_ON_PASSWORD_CHANGE: &str = "retain_sessions_on_password_change";
+pub const ATTR_REVOKE_SESSIONS_ON_PASSWORD_CHANGE: &str = "revoke_sessions_on_password_change";
 pub const ATTR_RS256_PRIVATE_KEY_DER: &str = "rs256_private_key_der";
 pub const ATTR_SCIM_SCHEMAS: &str = "schemas";
 pub const ATTR_SEND_AFTER: &str = "send_after";
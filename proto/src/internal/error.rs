@@ -21,6 +21,7 @@ pub enum SchemaError {
     EmptyFilter,
     Corrupted,
     PhantomAttribute(String),
+    TooManyValues(String),
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, ToSchema)]
@@ -59,6 +60,10 @@ pub enum ConsistencyError {
     KeyProviderUuidMissing { key_object: Uuid },
     KeyProviderNoKeys { key_object: Uuid },
     KeyProviderNotFound { key_object: Uuid, provider: Uuid },
+    SessionMaximumExceeded(Uuid, String, usize),
+    SessionRevokedPastTrim(Uuid, String, usize),
+    OAuth2SessionParentMissing(Uuid, Uuid, Uuid),
+    ApiTokenExpiresBeforeIssue(Uuid, Uuid),
 }
 
 #[derive(Serialize, Deserialize, Debug, ToSchema)]
@@ -69,6 +74,7 @@ pub enum OperationError {
     SessionExpired,
     DuplicateKey,
     DuplicateLabel,
+    AmbiguousLabel(Vec<Uuid>),
     EmptyRequest,
     Backend,
     NoMatchingEntries,
@@ -88,9 +94,14 @@ pub enum OperationError {
     InvalidDbState,
     InvalidCacheState,
     InvalidValueState,
+    ValueSetTypeMismatch {
+        expected: String,
+        found: String,
+    },
     InvalidEntryId,
     InvalidRequestState,
     InvalidSyncState,
+    SyncTokenAlreadyActive,
     InvalidState,
     InvalidEntryState,
     InvalidUuid,
@@ -393,13 +404,16 @@ impl OperationError {
             Self::InvalidAttribute(_) => None,
             Self::InvalidLabel => Some("The submitted label for this item is invalid.".into()),
             Self::DuplicateLabel => Some("The submitted label for this item is already in use.".into()),
+            Self::AmbiguousLabel(candidates) => Some(format!("The submitted label matches more than one item, select one by its id: {}", candidates.iter().map(|u| u.to_string()).collect::<Vec<_>>().join(", "))),
             Self::DuplicateKey => Some("The submitted key already exists.".into()),
             Self::InvalidDbState => None,
             Self::InvalidCacheState => None,
             Self::InvalidValueState => None,
+            Self::ValueSetTypeMismatch { expected, found } => Some(format!("Unable to merge a valueset of type {found} into a valueset of type {expected}.")),
             Self::InvalidEntryId => None,
             Self::InvalidRequestState => None,
             Self::InvalidSyncState => None,
+            Self::SyncTokenAlreadyActive => Some("This sync account already has an active sync token. It must be destroyed before a new token can be generated.".into()),
             Self::InvalidState => None,
             Self::InvalidEntryState => None,
             Self::InvalidUuid => None,
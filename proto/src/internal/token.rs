@@ -48,6 +48,9 @@ pub struct UserAuthToken {
 
     pub limit_search_max_results: Option<u64>,
     pub limit_search_max_filter_test: Option<u64>,
+    /// If set, the session is revoked once it has not been used for this
+    /// many seconds.
+    pub idle_timeout: Option<u32>,
 }
 
 impl fmt::Display for UserAuthToken {
@@ -109,7 +112,9 @@ impl UserAuthToken {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, ToSchema,
+)]
 #[serde(rename_all = "lowercase")]
 pub enum ApiTokenPurpose {
     #[default]
@@ -118,6 +123,16 @@ pub enum ApiTokenPurpose {
     Synchronise,
 }
 
+impl fmt::Display for ApiTokenPurpose {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ApiTokenPurpose::ReadOnly => "read only",
+            ApiTokenPurpose::ReadWrite => "read write",
+            ApiTokenPurpose::Synchronise => "synchronise",
+        })
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub struct ApiToken {
@@ -132,6 +147,9 @@ pub struct ApiToken {
     // Defaults to ReadOnly if not present
     #[serde(default)]
     pub purpose: ApiTokenPurpose,
+    // The rotation generation of the token. Defaults to 0 if not present
+    #[serde(default)]
+    pub generation: u32,
 }
 
 impl fmt::Display for ApiToken {
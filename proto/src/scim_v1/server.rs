@@ -6,7 +6,7 @@ use crate::internal::UiHint;
 use crate::v1::OutboundMessage;
 use crypto_glue::s256::Sha256Output;
 use scim_proto::{ScimEntry, ScimEntryHeader};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_with::{base64, formats, hex::Hex, serde_as, skip_serializing_none};
 use std::collections::{BTreeMap, BTreeSet};
 use std::num::NonZeroU64;
@@ -185,53 +185,121 @@ pub struct ScimKeyInternal {
     pub valid_from: OffsetDateTime,
 }
 
+/// An auth session as read from an entry. This carries every field of the session that
+/// is not secret, so that sessions can be restored from it during a migration.
 #[serde_as]
 #[skip_serializing_none]
-#[derive(Serialize, Debug, Clone, ToSchema)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ScimAuthSession {
     pub id: Uuid,
+    pub label: String,
     #[serde_as(as = "Option<Rfc3339>")]
     pub expires: Option<OffsetDateTime>,
     #[serde_as(as = "Option<Rfc3339>")]
     pub revoked: Option<OffsetDateTime>,
+    /// The server that revoked the session, if it is revoked.
+    pub revoked_by_server: Option<Uuid>,
     #[serde_as(as = "Rfc3339")]
     pub issued_at: OffsetDateTime,
     pub issued_by: Uuid,
+    /// The kind of identity that issued this, absent from values written before it
+    /// was recorded.
+    #[serde(default)]
+    pub issued_by_type: String,
     pub credential_id: Uuid,
     pub auth_type: String,
     pub session_scope: String,
+    pub bound_to: Option<String>,
+    #[serde_as(as = "Option<Rfc3339>")]
+    pub last_active: Option<OffsetDateTime>,
+    /// When the session was disabled, if it is currently disabled.
+    #[serde_as(as = "Option<Rfc3339>")]
+    pub disabled: Option<OffsetDateTime>,
+    #[serde(default)]
+    pub pinned: bool,
+    /// If the session was issued to a service account.
+    #[serde(default)]
+    pub service_account: bool,
+}
+
+/// A summary of the auth sessions of an entry, as a lighter alternative to listing
+/// every session.
+#[derive(Serialize, Debug, Clone, Default, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimSessionSummary {
+    pub total: usize,
+    pub active: usize,
+    pub revoked: usize,
+    pub expired: usize,
+    pub disabled: usize,
+    /// The number of sessions, in any state, by the auth type that created them.
+    pub by_auth_type: BTreeMap<String, usize>,
 }
 
 #[serde_as]
 #[skip_serializing_none]
-#[derive(Serialize, Debug, Clone, ToSchema)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ScimOAuth2Session {
     pub id: Uuid,
     pub parent_id: Option<Uuid>,
     pub client_id: Uuid,
+    pub client_display_name: Option<String>,
     #[serde_as(as = "Rfc3339")]
     pub issued_at: OffsetDateTime,
     #[serde_as(as = "Option<Rfc3339>")]
     pub expires: Option<OffsetDateTime>,
     #[serde_as(as = "Option<Rfc3339>")]
     pub revoked: Option<OffsetDateTime>,
+    /// The server that revoked the session, if it is revoked.
+    pub revoked_by_server: Option<Uuid>,
+    /// The scopes granted to the session, absent from sessions issued before they were
+    /// recorded.
+    #[serde(default)]
+    pub scopes: BTreeSet<String>,
+    pub has_refresh: bool,
+    #[serde_as(as = "Option<Rfc3339>")]
+    pub last_refreshed: Option<OffsetDateTime>,
+    pub origin: Option<String>,
 }
 
 #[serde_as]
 #[skip_serializing_none]
-#[derive(Serialize, Debug, Clone, ToSchema)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ScimApiToken {
     pub id: Uuid,
     pub label: String,
     #[serde_as(as = "Option<Rfc3339>")]
     pub expires: Option<OffsetDateTime>,
+    #[serde_as(as = "Option<Rfc3339>")]
+    pub revoked: Option<OffsetDateTime>,
     #[serde_as(as = "Rfc3339")]
     pub issued_at: OffsetDateTime,
     pub issued_by: Uuid,
+    /// The kind of identity that issued this, absent from values written before it
+    /// was recorded.
+    #[serde(default)]
+    pub issued_by_type: String,
     pub scope: String,
+    pub parent_session: Option<Uuid>,
+    /// The categories of changes this token is limited to, if any.
+    pub constraints: Option<BTreeSet<String>>,
+    #[serde(default)]
+    pub generation: u32,
+    // The expiry depends on the current time, so it is resolved by the server.
+    #[serde(default)]
+    pub expired: bool,
+}
+
+/// A single session or api token, read from an attribute of an entry.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+#[serde(untagged)]
+pub enum ScimSessionValue {
+    AuthSession(ScimAuthSession),
+    OAuth2Session(ScimOAuth2Session),
+    ApiToken(ScimApiToken),
 }
 
 #[serde_as]
@@ -296,6 +364,7 @@ pub enum ScimValueKanidm {
     AuditString(Vec<ScimAuditString>),
     SshPublicKey(Vec<ScimSshPublicKey>),
     AuthSession(Vec<ScimAuthSession>),
+    AuthSessionSummary(ScimSessionSummary),
     OAuth2Session(Vec<ScimOAuth2Session>),
     ApiToken(Vec<ScimApiToken>),
     IntentToken(Vec<ScimIntentToken>),
@@ -530,6 +599,12 @@ impl From<Vec<ScimAuthSession>> for ScimValueKanidm {
     }
 }
 
+impl From<ScimSessionSummary> for ScimValueKanidm {
+    fn from(summary: ScimSessionSummary) -> Self {
+        Self::AuthSessionSummary(summary)
+    }
+}
+
 impl From<Vec<ScimOAuth2Session>> for ScimValueKanidm {
     fn from(set: Vec<ScimOAuth2Session>) -> Self {
         Self::OAuth2Session(set)
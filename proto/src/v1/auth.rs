@@ -32,6 +32,10 @@ pub enum AuthStep {
         #[serde(default)]
         /// If true, the session will have r/w access.
         privileged: bool,
+        #[serde(default)]
+        /// If true, the session will have r/w access but may never re-authenticate
+        /// to gain privileges. This is intended for automation.
+        api_only: bool,
     },
     /// Request the named authentication mechanism to proceed
     Begin(AuthMech),
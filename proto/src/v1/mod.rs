@@ -10,6 +10,8 @@ use std::collections::BTreeMap;
 use std::fmt;
 use std::fmt::Display;
 use utoipa::ToSchema;
+
+use crate::internal::ApiTokenPurpose;
 use uuid::Uuid;
 
 mod auth;
@@ -52,8 +54,18 @@ pub enum UatPurposeStatus {
     PrivilegeCapable,
 }
 
+impl fmt::Display for UatPurposeStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UatPurposeStatus::ReadOnly => write!(f, "read only"),
+            UatPurposeStatus::ReadWrite => write!(f, "read write"),
+            UatPurposeStatus::PrivilegeCapable => write!(f, "privilege capable"),
+        }
+    }
+}
+
 /// The expiry of the User Auth Token.
-#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum UatStatusState {
     #[serde(with = "time::serde::timestamp")]
@@ -82,6 +94,12 @@ pub struct UatStatus {
     #[serde(with = "time::serde::timestamp")]
     pub issued_at: time::OffsetDateTime,
     pub purpose: UatPurposeStatus,
+    /// The label of the session, typically describing the device that created it.
+    #[serde(default)]
+    pub label: String,
+    /// The type of authentication that created the session.
+    #[serde(default)]
+    pub auth_type: String,
 }
 
 impl fmt::Display for UatStatus {
@@ -90,15 +108,123 @@ impl fmt::Display for UatStatus {
         writeln!(f, "session_id: {}", self.session_id)?;
         writeln!(f, "state: {}", self.state)?;
         writeln!(f, "issued_at: {}", self.issued_at)?;
-        match &self.purpose {
-            UatPurposeStatus::ReadOnly => writeln!(f, "purpose: read only")?,
-            UatPurposeStatus::ReadWrite => writeln!(f, "purpose: read write")?,
-            UatPurposeStatus::PrivilegeCapable => writeln!(f, "purpose: privilege capable")?,
+        writeln!(f, "label: {}", self.label)?;
+        writeln!(f, "auth_type: {}", self.auth_type)?;
+        writeln!(f, "purpose: {}", self.purpose)
+    }
+}
+
+/// Options for listing the User Auth Tokens of an account. Tokens are listed from the
+/// most to the least recently issued, which is stable between pages.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub struct UatStatusQuery {
+    /// Omit tokens that have been revoked.
+    #[serde(default)]
+    pub exclude_revoked: bool,
+    /// The number of tokens to skip.
+    #[serde(default)]
+    pub offset: usize,
+    /// The maximum number of tokens to return.
+    pub limit: Option<usize>,
+    /// Only list tokens from sessions that were created by this type of authentication,
+    /// such as `password` or `passkey`.
+    pub auth_type: Option<String>,
+}
+
+/// The number of sessions or tokens of an account in each state.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub struct SessionStateCount {
+    pub active: usize,
+    pub expired: usize,
+    pub revoked: usize,
+}
+
+impl fmt::Display for SessionStateCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} active, {} expired, {} revoked",
+            self.active, self.expired, self.revoked
+        )
+    }
+}
+
+/// A summary of the sessions, oauth2 sessions and api tokens of an account. This only
+/// contains counts, not the details of each session.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub struct AccountSessionSummary {
+    pub account_id: Uuid,
+    pub user_auth_tokens: SessionStateCount,
+    pub oauth2_sessions: SessionStateCount,
+    /// The number of active oauth2 sessions for each client.
+    pub oauth2_sessions_by_client: BTreeMap<Uuid, usize>,
+    pub api_tokens: SessionStateCount,
+    /// The number of active api tokens for each purpose.
+    pub api_tokens_by_purpose: BTreeMap<ApiTokenPurpose, usize>,
+}
+
+impl fmt::Display for AccountSessionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "account_id: {}", self.account_id)?;
+        writeln!(f, "sessions: {}", self.user_auth_tokens)?;
+        writeln!(f, "oauth2 sessions: {}", self.oauth2_sessions)?;
+        for (client, count) in &self.oauth2_sessions_by_client {
+            writeln!(f, "  {client}: {count} active")?;
+        }
+        writeln!(f, "api tokens: {}", self.api_tokens)?;
+        for (purpose, count) in &self.api_tokens_by_purpose {
+            writeln!(f, "  {purpose}: {count} active")?;
         }
         Ok(())
     }
 }
 
+/// The oauth2 sessions that an account holds with a single client.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub struct Oauth2ClientSessions {
+    pub account_id: Uuid,
+    pub client_id: Uuid,
+    /// The name of the client. This is absent if the client has been deleted.
+    pub client_name: Option<String>,
+    pub client_displayname: Option<String>,
+    /// The number of sessions held with the client.
+    pub sessions: usize,
+    /// When the most recent session with the client was issued.
+    #[serde(with = "time::serde::timestamp")]
+    pub newest_issued_at: time::OffsetDateTime,
+    /// The state of the longest lived session with the client.
+    pub state: UatStatusState,
+}
+
+impl fmt::Display for Oauth2ClientSessions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "account_id: {}", self.account_id)?;
+        writeln!(f, "client_id: {}", self.client_id)?;
+        if let Some(client_name) = &self.client_name {
+            writeln!(f, "client_name: {client_name}")?;
+        }
+        if let Some(client_displayname) = &self.client_displayname {
+            writeln!(f, "client_displayname: {client_displayname}")?;
+        }
+        writeln!(f, "sessions: {}", self.sessions)?;
+        writeln!(f, "newest_issued_at: {}", self.newest_issued_at)?;
+        writeln!(f, "state: {}", self.state)
+    }
+}
+
+/// Options for listing the oauth2 sessions of an account by client.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub struct Oauth2ClientSessionsQuery {
+    /// Include sessions that have been revoked.
+    #[serde(default)]
+    pub include_revoked: bool,
+}
+
 /// A request to generate a new API token for a service account
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 #[serde(rename_all = "lowercase")]
@@ -10,7 +10,9 @@ use kanidm_proto::internal::{
 use kanidm_proto::scim_v1::client::ScimAssertGeneric;
 use kanidmd_lib::prelude::*;
 use kanidmd_lib::{
-    event::{PurgeDeleteAfterEvent, PurgeRecycledEvent, PurgeTombstoneEvent},
+    event::{
+        PurgeDeleteAfterEvent, PurgeExpiredSessionsEvent, PurgeRecycledEvent, PurgeTombstoneEvent,
+    },
     idm::delayed::DelayedAction,
     server::scim::ScimAssertEvent,
 };
@@ -123,6 +125,49 @@ impl QueryServerWriteV1 {
             .inspect_err(|err| error!(?err, "Unable to purge delete after entries"));
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?msg.eventid)
+    )]
+    pub async fn handle_purge_expired_sessions_event(&self, msg: PurgeExpiredSessionsEvent) {
+        // Each batch is written in its own transaction so that other writes are not
+        // blocked while a large number of entries are purged.
+        loop {
+            let ct = duration_from_epoch_now();
+            let Ok(mut idms_prox_write) = self.idms.proxy_write(ct).await else {
+                warn!("Unable to start purge expired sessions event, will retry later");
+                return;
+            };
+
+            let res = idms_prox_write
+                .qs_write
+                .purge_expired_sessions(EXPIRED_SESSION_PURGE_BATCH_SIZE)
+                .and_then(|touched| {
+                    // don't need to commit a txn with no changes
+                    if touched > 0 {
+                        idms_prox_write.commit().map(|()| touched)
+                    } else {
+                        Ok(touched)
+                    }
+                });
+
+            match res {
+                Ok(touched) if touched < EXPIRED_SESSION_PURGE_BATCH_SIZE => {
+                    debug!("Purge expired sessions success");
+                    return;
+                }
+                Ok(_) => {
+                    debug!("Purge expired sessions batch success, continuing");
+                }
+                Err(err) => {
+                    error!(?err, "Unable to purge expired sessions");
+                    return;
+                }
+            }
+        }
+    }
+
     pub(crate) async fn handle_delayedaction(&self, da_batch: &mut Vec<DelayedAction>) {
         let eventid = Uuid::new_v4();
         let span = span!(Level::INFO, "process_delayed_action", uuid = ?eventid);
@@ -7,7 +7,9 @@ use kanidm_proto::internal::{
 };
 use kanidm_proto::oauth2::OidcWebfingerResponse;
 use kanidm_proto::v1::{
-    AuthIssueSession, Entry as ProtoEntry, UatStatus, UnixGroupToken, UnixUserToken, WhoamiResponse,
+    AccountSessionSummary, AuthIssueSession, Entry as ProtoEntry, Oauth2ClientSessions,
+    Oauth2ClientSessionsQuery, UatStatus, UatStatusQuery, UnixGroupToken, UnixUserToken,
+    WhoamiResponse,
 };
 use kanidmd_lib::be::BackendTransaction;
 use kanidmd_lib::idm::identityverification::{
@@ -17,7 +19,9 @@ use kanidmd_lib::prelude::*;
 use kanidmd_lib::{
     event::{OnlineBackupEvent, SearchEvent, SearchResult, WhoamiResult},
     filter::{Filter, FilterInvalid},
-    idm::account::ListUserAuthTokenEvent,
+    idm::account::{
+        AccountSessionSummaryEvent, ListOauth2ClientSessionsEvent, ListUserAuthTokenEvent,
+    },
     idm::authentication::{AuthStep, ReauthRequest},
     idm::credupdatesession::CredentialUpdateSessionToken,
     idm::event::{
@@ -32,6 +36,7 @@ use kanidmd_lib::{
     },
     idm::server::{DomainInfoRead, IdmServerTransaction},
     idm::serviceaccount::ListApiTokenEvent,
+    server::expiry::SessionExpiryNotifier,
 };
 use ldap3_proto::simple::*;
 use regex::Regex;
@@ -186,6 +191,68 @@ impl QueryServerReadV1 {
         res
     }
 
+    #[instrument(level = "debug", name = "session_metrics", skip_all)]
+    pub async fn handle_session_metrics(&self) {
+        let ct = duration_from_epoch_now();
+
+        let metrics = match self.idms.proxy_read().await {
+            Ok(mut idms_prox_read) => idms_prox_read.qs_read.session_metrics(ct),
+            Err(err) => Err(err),
+        };
+
+        let metrics = match metrics {
+            Ok(metrics) => metrics,
+            Err(err) => {
+                error!(?err, "Unable to sample session metrics");
+                return;
+            }
+        };
+
+        info!(
+            live_sessions = metrics.live_sessions,
+            revoked_sessions = metrics.revoked_sessions,
+            oauth2_sessions_other = metrics.oauth2_sessions_other,
+            api_tokens_expiring = metrics.api_tokens_expiring,
+            "Session metrics"
+        );
+
+        for (client, sessions) in metrics.oauth2_sessions_by_client {
+            info!(%client, sessions, "Oauth2 session metrics");
+        }
+    }
+
+    #[instrument(level = "debug", name = "session_expiry", skip_all)]
+    pub async fn handle_session_expiry(&self, notifier: &mut SessionExpiryNotifier) {
+        let ct = duration_from_epoch_now();
+
+        let expiring = match self.idms.proxy_read().await {
+            Ok(mut idms_prox_read) => notifier.scan(
+                &mut idms_prox_read.qs_read,
+                ct,
+                SESSION_EXPIRY_NOTIFY_HORIZON,
+            ),
+            Err(err) => Err(err),
+        };
+
+        let expiring = match expiring {
+            Ok(expiring) => expiring,
+            Err(err) => {
+                error!(?err, "Unable to scan for expiring sessions");
+                return;
+            }
+        };
+
+        for session in expiring {
+            info!(
+                entry = %session.entry,
+                attr = %session.attr,
+                session_id = %session.id,
+                expiry = %session.expiry,
+                "Session expiring"
+            );
+        }
+    }
+
     #[instrument(
         level = "info",
         name = "online_backup",
@@ -965,6 +1032,7 @@ impl QueryServerReadV1 {
         &self,
         client_auth_info: ClientAuthInfo,
         uuid_or_name: String,
+        query: UatStatusQuery,
         eventid: Uuid,
     ) -> Result<Vec<UatStatus>, OperationError> {
         let ct = duration_from_epoch_now();
@@ -982,11 +1050,82 @@ impl QueryServerReadV1 {
                 error!(?err, "Error resolving id to target");
             })?;
 
-        let lte = ListUserAuthTokenEvent { ident, target };
+        let lte = ListUserAuthTokenEvent {
+            ident,
+            target,
+            query,
+        };
 
         idms_prox_read.account_list_user_auth_tokens(&lte)
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_account_oauth2_client_sessions_get(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        query: Oauth2ClientSessionsQuery,
+        eventid: Uuid,
+    ) -> Result<Vec<Oauth2ClientSessions>, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!("Invalid identity: {:?}", e);
+                e
+            })?;
+        let target = idms_prox_read
+            .qs_read
+            .name_to_uuid(uuid_or_name.as_str())
+            .inspect_err(|err| {
+                error!(?err, "Error resolving id to target");
+            })?;
+
+        let lse = ListOauth2ClientSessionsEvent {
+            ident,
+            target,
+            query,
+        };
+
+        idms_prox_read.account_list_oauth2_client_sessions(&lse)
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_account_session_summary_get(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        eventid: Uuid,
+    ) -> Result<AccountSessionSummary, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!("Invalid identity: {:?}", e);
+                e
+            })?;
+        let target = idms_prox_read
+            .qs_read
+            .name_to_uuid(uuid_or_name.as_str())
+            .inspect_err(|err| {
+                error!(?err, "Error resolving id to target");
+            })?;
+
+        let sse = AccountSessionSummaryEvent { ident, target };
+
+        idms_prox_read.account_session_summary(&sse, ct)
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -1,7 +1,7 @@
 use super::{QueryServerReadV1, QueryServerWriteV1};
 use kanidm_proto::scim_v1::{
     client::{ScimEntryPostGeneric, ScimEntryPutGeneric},
-    server::{ScimEntryKanidm, ScimListResponse},
+    server::{ScimEntryKanidm, ScimListResponse, ScimSessionValue},
     ScimApplicationPassword, ScimApplicationPasswordCreate, ScimEntryGetQuery, ScimFilter,
     ScimSyncRequest, ScimSyncState,
 };
@@ -452,6 +452,44 @@ impl QueryServerReadV1 {
             .scim_entry_id_get_ext(target_uuid, class, query, ident)
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn scim_entry_id_attr_value_get(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        eventid: Uuid,
+        uuid_or_name: String,
+        class: EntryClass,
+        attr: Attribute,
+        value_id: Uuid,
+    ) -> Result<ScimSessionValue, OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_read = self.idms.proxy_read().await?;
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .inspect_err(|err| {
+                error!(?err, "Invalid identity");
+            })?;
+
+        let target_uuid = idms_prox_read
+            .qs_read
+            .name_to_uuid(uuid_or_name.as_str())
+            .inspect_err(|err| {
+                error!(?err, "Error resolving id to target");
+            })?;
+
+        idms_prox_read.qs_read.scim_entry_id_attr_value_get_ext(
+            target_uuid,
+            class,
+            &attr,
+            value_id,
+            ident,
+        )
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -16,7 +16,7 @@ use uuid::Uuid;
 use kanidmd_lib::{
     event::{CreateEvent, DeleteEvent, ModifyEvent, ReviveRecycledEvent},
     filter::{Filter, FilterInvalid},
-    idm::account::DestroySessionTokenEvent,
+    idm::account::{DestroySessionTokenEvent, RevokeOauth2ClientSessionsEvent},
     idm::credupdatesession::{
         CredentialUpdateIntentTokenExchange, CredentialUpdateSessionToken,
         InitCredentialUpdateEvent, InitCredentialUpdateIntentEvent,
@@ -28,7 +28,7 @@ use kanidmd_lib::{
         TokenRevokeRequest,
     },
     idm::server::IdmServerTransaction,
-    idm::serviceaccount::{DestroyApiTokenEvent, GenerateApiTokenEvent},
+    idm::serviceaccount::{DestroyApiTokenEvent, DestroyApiTokenLabelEvent, GenerateApiTokenEvent},
     modify::{Modify, ModifyInvalid, ModifyList},
     value::{OauthClaimMapJoin, PartialValue, Value},
 };
@@ -444,6 +444,7 @@ impl QueryServerWriteV1 {
             label,
             expiry,
             read_write,
+            constraints: None,
             compact,
         };
 
@@ -493,6 +494,47 @@ impl QueryServerWriteV1 {
             .and_then(|r| idms_prox_write.commit().map(|_| r))
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_service_account_api_token_destroy_by_label(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        label: String,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let target = idms_prox_write
+            .qs_write
+            .name_to_uuid(uuid_or_name.as_str())
+            .map_err(|e| {
+                error!(err = ?e, "Error resolving id to target");
+                e
+            })?;
+
+        let dte = DestroyApiTokenLabelEvent {
+            ident,
+            target,
+            label,
+        };
+
+        let token_id = idms_prox_write.service_account_destroy_api_token_by_label(&dte)?;
+        info!(?token_id, "Destroyed api token by label");
+
+        idms_prox_write.commit()
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -533,6 +575,55 @@ impl QueryServerWriteV1 {
             .and_then(|r| idms_prox_write.commit().map(|_| r))
     }
 
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub async fn handle_account_oauth2_client_sessions_revoke(
+        &self,
+        client_auth_info: ClientAuthInfo,
+        uuid_or_name: String,
+        client_id: String,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await?;
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(client_auth_info, ct)
+            .map_err(|e| {
+                error!(err = ?e, "Invalid identity");
+                e
+            })?;
+
+        let target = idms_prox_write
+            .qs_write
+            .name_to_uuid(uuid_or_name.as_str())
+            .map_err(|e| {
+                error!(err = ?e, "Error resolving id to target");
+                e
+            })?;
+
+        // A deleted client can only be named by its uuid.
+        let client_id = idms_prox_write
+            .qs_write
+            .name_to_uuid(client_id.as_str())
+            .map_err(|e| {
+                error!(err = ?e, "Error resolving id to oauth2 client");
+                e
+            })?;
+
+        let rce = RevokeOauth2ClientSessionsEvent {
+            ident,
+            target,
+            client_id,
+        };
+
+        idms_prox_write
+            .account_revoke_oauth2_client_sessions(&rce)
+            .and_then(|r| idms_prox_write.commit().map(|_| r))
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -328,6 +328,10 @@ pub struct ServerConfig {
     /// Trust the X-Forwarded-For header for client IP address. Defaults to false if unset.
     trust_x_forward_for: Option<bool>,
 
+    /// Periodically revoke sessions and api tokens that have long expired, so that they are
+    /// removed from accounts. Defaults to true if unset.
+    purge_expired_sessions: Option<bool>,
+
     /// The path to the "admin" socket, used for local communication when performing certain server control tasks. Default is set on build, based on the system target.
     adminbindpath: Option<String>,
 
@@ -421,6 +425,7 @@ pub struct ServerConfigV2 {
     role: Option<ServerRole>,
     log_level: Option<LogLevel>,
     online_backup: Option<OnlineBackup>,
+    purge_expired_sessions: Option<bool>,
 
     http_client_address_info: Option<HttpAddressInfo>,
     ldap_client_address_info: Option<LdapAddressInfo>,
@@ -474,6 +479,8 @@ pub struct Configuration {
     pub tls_config: Option<TlsConfiguration>,
     pub integration_test_config: Option<Box<IntegrationTestConfig>>,
     pub online_backup: Option<OnlineBackup>,
+    /// Revoke long expired sessions and api tokens on the purge interval.
+    pub purge_expired_sessions: bool,
     pub domain: String,
     pub origin: Url,
     pub role: ServerRole,
@@ -509,6 +516,7 @@ impl Configuration {
             tls_chain: None,
             tls_client_ca: None,
             online_backup: None,
+            purge_expired_sessions: None,
             domain: None,
             origin: None,
             log_level: None,
@@ -535,6 +543,7 @@ impl Configuration {
             tls_config: None,
             integration_test_config: None,
             online_backup: None,
+            purge_expired_sessions: true,
             domain: "idm.example.com".to_string(),
             origin: Url::from_str("https://idm.example.com")
                 .expect("Failed to parse built-in string as URL"),
@@ -603,6 +612,11 @@ impl fmt::Display for Configuration {
             ),
             None => write!(f, "online_backup: disabled, "),
         }?;
+        write!(
+            f,
+            "purge expired sessions: {}, ",
+            self.purge_expired_sessions
+        )?;
         write!(
             f,
             "integration mode: {}, ",
@@ -648,6 +662,7 @@ pub struct ConfigurationBuilder {
     tls_chain: Option<PathBuf>,
     tls_client_ca: Option<PathBuf>,
     online_backup: Option<OnlineBackup>,
+    purge_expired_sessions: Option<bool>,
     domain: Option<String>,
     origin: Option<Url>,
     role: Option<ServerRole>,
@@ -884,6 +899,10 @@ impl ConfigurationBuilder {
             self.online_backup = config.online_backup;
         }
 
+        if config.purge_expired_sessions.is_some() {
+            self.purge_expired_sessions = config.purge_expired_sessions;
+        }
+
         if config.repl_config.is_some() {
             self.repl_config = config.repl_config;
         }
@@ -972,6 +991,10 @@ impl ConfigurationBuilder {
             self.online_backup = config.online_backup;
         }
 
+        if config.purge_expired_sessions.is_some() {
+            self.purge_expired_sessions = config.purge_expired_sessions;
+        }
+
         if config.repl_config.is_some() {
             self.repl_config = config.repl_config;
         }
@@ -1008,6 +1031,7 @@ impl ConfigurationBuilder {
             tls_chain,
             tls_client_ca,
             mut online_backup,
+            purge_expired_sessions,
             domain,
             origin,
             role,
@@ -1058,6 +1082,7 @@ impl ConfigurationBuilder {
         let address = bindaddress.unwrap_or(vec![DEFAULT_SERVER_ADDRESS.to_string()]);
         let role = role.unwrap_or(ServerRole::WriteReplica);
         let log_level = log_level.unwrap_or_default();
+        let purge_expired_sessions = purge_expired_sessions.unwrap_or(true);
 
         Some(Configuration {
             address,
@@ -1073,6 +1098,7 @@ impl ConfigurationBuilder {
             ldap_client_address_info,
             tls_config,
             online_backup,
+            purge_expired_sessions,
             domain,
             origin,
             role,
@@ -79,6 +79,7 @@ impl Modify for SecurityAddon {
         super::v1_scim::scim_entry_post,
         super::v1_scim::scim_entry_put,
         super::v1_scim::scim_entry_id_get,
+        super::v1_scim::scim_entry_id_attr_value_get,
         super::v1_scim::scim_entry_id_delete,
         super::v1_scim::scim_person_id_get,
         super::v1_scim::scim_person_id_application_create_password,
@@ -107,6 +108,7 @@ impl Modify for SecurityAddon {
         super::v1::person_post,
         super::v1::service_account_credential_generate,
         super::v1::service_account_api_token_delete,
+        super::v1::service_account_api_token_label_delete,
         super::v1::service_account_api_token_get,
         super::v1::service_account_api_token_post,
         super::v1::person_search_id,
@@ -171,6 +173,9 @@ impl Modify for SecurityAddon {
         super::v1::account_id_ssh_pubkeys_tag_get,
         super::v1::account_id_user_auth_token_get,
         super::v1::account_user_auth_token_delete,
+        super::v1::account_id_session_summary_get,
+        super::v1::account_id_oauth2_session_get,
+        super::v1::account_id_oauth2_session_delete,
         super::v1::credential_update_exchange_intent,
         super::v1::credential_update_status,
         super::v1::credential_update_update,
@@ -286,6 +291,9 @@ impl Modify for SecurityAddon {
             v1::KeyTypeKindSchema,
             v1::KeyTypeSchema,
             internal::UiHint,
+            v1::AccountSessionSummary,
+            v1::Oauth2ClientSessions,
+            v1::SessionStateCount,
             v1::UatPurposeStatus,
             v1::UatStatus,
             v1::UatStatusState,
@@ -107,6 +107,7 @@ impl IntoResponse for WebError {
                     }
 
                     OperationError::NoMatchingEntries => (StatusCode::NOT_FOUND, None),
+                    OperationError::AmbiguousLabel(_) => (StatusCode::CONFLICT, None),
                     OperationError::PasswordQuality(_)
                     | OperationError::EmptyRequest
                     | OperationError::InvalidAttribute(_)
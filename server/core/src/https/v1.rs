@@ -6,7 +6,7 @@ use super::middleware::KOpId;
 use super::ServerState;
 use crate::https::apidocs::response_schema::{ApiResponseWithout200, DefaultApiResponse};
 use crate::https::extractors::{ClientConnInfo, VerifiedClientInformation};
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
 use axum::http::{HeaderMap, HeaderValue};
 use axum::middleware::from_fn;
 use axum::response::{IntoResponse, Response};
@@ -22,9 +22,10 @@ use kanidm_proto::internal::{
     COOKIE_AUTH_SESSION_ID, COOKIE_BEARER_TOKEN,
 };
 use kanidm_proto::v1::{
-    AccountUnixExtend, ApiTokenGenerate, AuthIssueSession, AuthRequest, AuthResponse,
-    AuthState as ProtoAuthState, Entry as ProtoEntry, GroupUnixExtend, SingleStringRequest,
-    UatStatus, UnixGroupToken, UnixUserToken, WhoamiResponse,
+    AccountSessionSummary, AccountUnixExtend, ApiTokenGenerate, AuthIssueSession, AuthRequest,
+    AuthResponse, AuthState as ProtoAuthState, Entry as ProtoEntry, GroupUnixExtend,
+    Oauth2ClientSessions, Oauth2ClientSessionsQuery, SingleStringRequest, UatStatus,
+    UatStatusQuery, UnixGroupToken, UnixUserToken, WhoamiResponse,
 };
 use kanidmd_lib::idm::authentication::{AuthState, AuthStep, ReauthRequest};
 use kanidmd_lib::idm::event::AuthResult;
@@ -995,6 +996,37 @@ pub async fn service_account_api_token_delete(
         .map_err(WebError::from)
 }
 
+#[utoipa::path(
+    delete,
+    path = "/v1/service_account/{id}/_api_token/_label/{label}",
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "service_account",
+    operation_id = "service_account_api_token_label_delete",
+)]
+/// Destroy the api token with this label. The request fails if the label is
+/// held by more than one token.
+pub async fn service_account_api_token_label_delete(
+    State(state): State<ServerState>,
+    Path((id, label)): Path<(String, String)>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<()>, WebError> {
+    state
+        .qe_w_ref
+        .handle_service_account_api_token_destroy_by_label(
+            client_auth_info,
+            id,
+            label,
+            kopid.eventid,
+        )
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
 #[utoipa::path(
     get,
     path = "/v1/person/{id}/_attr/{attr}",
@@ -1324,10 +1356,91 @@ pub async fn account_id_user_auth_token_get(
     Path(id): Path<String>,
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Query(query): Query<UatStatusQuery>,
 ) -> Result<Json<Vec<UatStatus>>, WebError> {
     state
         .qe_r_ref
-        .handle_account_user_auth_token_get(client_auth_info, id, kopid.eventid)
+        .handle_account_user_auth_token_get(client_auth_info, id, query, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/account/{id}/_oauth2_session",
+    responses(
+        (status = 200, body=Vec<Oauth2ClientSessions>, content_type=APPLICATION_JSON),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "account",
+)]
+/// List the oauth2 sessions of an account grouped by the client they were issued to.
+pub async fn account_id_oauth2_session_get(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+    Query(query): Query<Oauth2ClientSessionsQuery>,
+) -> Result<Json<Vec<Oauth2ClientSessions>>, WebError> {
+    state
+        .qe_r_ref
+        .handle_account_oauth2_client_sessions_get(client_auth_info, id, query, kopid.eventid)
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/account/{id}/_oauth2_session/{client_id}",
+    responses(
+        DefaultApiResponse,
+    ),
+    security(("token_jwt" = [])),
+    tag = "account",
+)]
+/// Revoke all of the oauth2 sessions that an account holds with a client.
+pub async fn account_id_oauth2_session_delete(
+    State(state): State<ServerState>,
+    Path((id, client_id)): Path<(String, String)>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<()>, WebError> {
+    state
+        .qe_w_ref
+        .handle_account_oauth2_client_sessions_revoke(
+            client_auth_info,
+            id,
+            client_id,
+            kopid.eventid,
+        )
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/account/{id}/_session_summary",
+    responses(
+        (status = 200, body=AccountSessionSummary, content_type=APPLICATION_JSON),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "account",
+)]
+/// Count the sessions, oauth2 sessions and api tokens of an account by their state.
+pub async fn account_id_session_summary_get(
+    State(state): State<ServerState>,
+    Path(id): Path<String>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<AccountSessionSummary>, WebError> {
+    state
+        .qe_r_ref
+        .handle_account_session_summary_get(client_auth_info, id, kopid.eventid)
         .await
         .map(Json::from)
         .map_err(WebError::from)
@@ -3321,6 +3434,10 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
             "/v1/service_account/{id}/_api_token/{token_id}",
             delete(service_account_api_token_delete),
         )
+        .route(
+            "/v1/service_account/{id}/_api_token/_label/{label}",
+            delete(service_account_api_token_label_delete),
+        )
         // .route(
         //     "/v1/service_account/{id}/_credential",
         //     get(|| async { "TODO" }),
@@ -3376,6 +3493,18 @@ pub(crate) fn route_setup(state: ServerState) -> Router<ServerState> {
             "/v1/account/{id}/_user_auth_token/{token_id}",
             delete(account_user_auth_token_delete),
         )
+        .route(
+            "/v1/account/{id}/_session_summary",
+            get(account_id_session_summary_get),
+        )
+        .route(
+            "/v1/account/{id}/_oauth2_session",
+            get(account_id_oauth2_session_get),
+        )
+        .route(
+            "/v1/account/{id}/_oauth2_session/{client_id}",
+            delete(account_id_oauth2_session_delete),
+        )
         .route(
             "/v1/credential/_exchange_intent",
             post(credential_update_exchange_intent),
@@ -14,7 +14,7 @@ use axum::{Extension, Json, Router};
 use kanidm_proto::scim_v1::ScimEntry;
 use kanidm_proto::scim_v1::{
     client::{ScimEntryPostGeneric, ScimEntryPutGeneric},
-    server::{ScimEntryKanidm, ScimListResponse},
+    server::{ScimEntryKanidm, ScimListResponse, ScimSessionValue},
     ScimApplicationPassword, ScimApplicationPasswordCreate, ScimEntryGetQuery, ScimSyncRequest,
     ScimSyncState,
 };
@@ -460,6 +460,40 @@ async fn scim_entry_id_get(
         .map_err(WebError::from)
 }
 
+#[utoipa::path(
+    get,
+    path = "/scim/v1/Entry/{id}/_attr/{attr}/{value_id}",
+    responses(
+        (status = 200, content_type=APPLICATION_JSON, body=ScimSessionValue),
+        ApiResponseWithout200,
+    ),
+    security(("token_jwt" = [])),
+    tag = "scim",
+    operation_id = "scim_entry_id_attr_value_get"
+)]
+/// Retrieve a single session or api token of an entry, without reading the
+/// other values of the attribute.
+async fn scim_entry_id_attr_value_get(
+    State(state): State<ServerState>,
+    Path((id, attr, value_id)): Path<(String, String, Uuid)>,
+    Extension(kopid): Extension<KOpId>,
+    VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
+) -> Result<Json<ScimSessionValue>, WebError> {
+    state
+        .qe_r_ref
+        .scim_entry_id_attr_value_get(
+            client_auth_info,
+            kopid.eventid,
+            id,
+            EntryClass::Object,
+            Attribute::from(attr.as_str()),
+            value_id,
+        )
+        .await
+        .map(Json::from)
+        .map_err(WebError::from)
+}
+
 #[utoipa::path(
     get,
     path = "/scim/v1/Person/{id}",
@@ -962,6 +996,13 @@ pub fn route_setup() -> Router<ServerState> {
             "/scim/v1/Entry/{id}",
             get(scim_entry_id_get).delete(scim_entry_id_delete),
         )
+        //  Entry    /Entry/{id}/_attr/{attr}/{value_id}
+        //                            GET                    Retrieve a single session or api
+        //                                                   token of an entry.
+        .route(
+            "/scim/v1/Entry/{id}/_attr/{attr}/{value_id}",
+            get(scim_entry_id_attr_value_get),
+        )
         //  Person   /Person/{id}     GET                    Retrieve a a person from the
         //                                                   database.
         //                                                   {id} is any unique id.
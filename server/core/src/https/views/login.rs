@@ -484,6 +484,7 @@ pub async fn view_login_begin_post(
                 username: username.clone(),
                 issue: AuthIssueSession::Cookie,
                 privileged: false,
+                api_only: false,
             },
             kopid.eventid,
             client_auth_info.clone(),
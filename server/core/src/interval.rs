@@ -17,14 +17,17 @@ use crate::CoreAction;
 use crate::actors::{QueryServerReadV1, QueryServerWriteV1};
 use kanidmd_lib::constants::PURGE_FREQUENCY;
 use kanidmd_lib::event::{
-    OnlineBackupEvent, PurgeDeleteAfterEvent, PurgeRecycledEvent, PurgeTombstoneEvent,
+    OnlineBackupEvent, PurgeDeleteAfterEvent, PurgeExpiredSessionsEvent, PurgeRecycledEvent,
+    PurgeTombstoneEvent,
 };
+use kanidmd_lib::server::expiry::SessionExpiryNotifier;
 
 pub(crate) struct IntervalActor;
 
 impl IntervalActor {
     pub fn start(
         server: &'static QueryServerWriteV1,
+        purge_expired_sessions: bool,
         mut rx: broadcast::Receiver<CoreAction>,
     ) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
@@ -41,6 +44,11 @@ impl IntervalActor {
                 server
                     .handle_purge_delete_after_event(PurgeDeleteAfterEvent::new())
                     .await;
+                if purge_expired_sessions {
+                    server
+                        .handle_purge_expired_sessions_event(PurgeExpiredSessionsEvent::new())
+                        .await;
+                }
 
                 tokio::select! {
                     Ok(action) = rx.recv() => {
@@ -60,6 +68,63 @@ impl IntervalActor {
         })
     }
 
+    /// Periodically sample the session metrics of the server.
+    pub fn start_session_metrics(
+        server: &'static QueryServerReadV1,
+        mut rx: broadcast::Receiver<CoreAction>,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut inter = interval(Duration::from_secs(PURGE_FREQUENCY));
+            inter.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            loop {
+                tokio::select! {
+                    Ok(action) = rx.recv() => {
+                        match action {
+                            CoreAction::Shutdown => break,
+                            CoreAction::Reload => continue,
+                        }
+                    }
+                    _ = inter.tick() => {
+                        server.handle_session_metrics().await;
+                    }
+                }
+            }
+
+            info!("Stopped {}", super::TaskName::SessionMetricsActor);
+        })
+    }
+
+    /// Periodically notify the sessions and api tokens that are about to expire. Each
+    /// is notified once per run of the server.
+    pub fn start_session_expiry(
+        server: &'static QueryServerReadV1,
+        mut rx: broadcast::Receiver<CoreAction>,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut inter = interval(Duration::from_secs(PURGE_FREQUENCY));
+            inter.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            let mut notifier = SessionExpiryNotifier::new();
+
+            loop {
+                tokio::select! {
+                    Ok(action) = rx.recv() => {
+                        match action {
+                            CoreAction::Shutdown => break,
+                            CoreAction::Reload => continue,
+                        }
+                    }
+                    _ = inter.tick() => {
+                        server.handle_session_expiry(&mut notifier).await;
+                    }
+                }
+            }
+
+            info!("Stopped {}", super::TaskName::SessionExpiryActor);
+        })
+    }
+
     // Allow this because result is the only way to map and ? to bubble up, but we aren't
     // returning an op-error here because this is in early start up.
     #[allow(clippy::result_unit_err)]
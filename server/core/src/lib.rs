@@ -919,6 +919,8 @@ pub(crate) enum TaskName {
     IntervalActor,
     LdapActor,
     Replication,
+    SessionMetricsActor,
+    SessionExpiryActor,
     TlsAcceptorReload,
     MigrationReload,
 }
@@ -937,6 +939,8 @@ impl Display for TaskName {
                 TaskName::IntervalActor => "Interval Actor",
                 TaskName::LdapActor => "LDAP Acceptor Actor",
                 TaskName::Replication => "Replication",
+                TaskName::SessionMetricsActor => "Session Metrics Actor",
+                TaskName::SessionExpiryActor => "Session Expiry Actor",
                 TaskName::TlsAcceptorReload => "TlsAcceptor Reload Monitor",
                 TaskName::MigrationReload => "Migration Reload Monitor",
             }
@@ -1287,8 +1291,16 @@ pub async fn create_server_core(
     });
 
     // Setup timed events associated to the write thread
-    let interval_handle = IntervalActor::start(server_write_ref, broadcast_tx.subscribe());
+    let interval_handle = IntervalActor::start(
+        server_write_ref,
+        config.purge_expired_sessions,
+        broadcast_tx.subscribe(),
+    );
     // Setup timed events associated to the read thread
+    let session_metrics_handle =
+        IntervalActor::start_session_metrics(server_read_ref, broadcast_tx.subscribe());
+    let session_expiry_handle =
+        IntervalActor::start_session_expiry(server_read_ref, broadcast_tx.subscribe());
     let maybe_backup_handle = match &config.online_backup {
         Some(online_backup_config) => {
             if online_backup_config.enabled {
@@ -1403,6 +1415,8 @@ pub async fn create_server_core(
 
     let mut handles: Vec<(TaskName, task::JoinHandle<()>)> = vec![
         (TaskName::IntervalActor, interval_handle),
+        (TaskName::SessionMetricsActor, session_metrics_handle),
+        (TaskName::SessionExpiryActor, session_expiry_handle),
         (TaskName::DelayedActionActor, delayed_handle),
         (TaskName::AuditdActor, auditd_handle),
         (TaskName::TlsAcceptorReload, tls_acceptor_reload_handle),
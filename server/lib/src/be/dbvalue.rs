@@ -525,6 +525,8 @@ pub enum DbValueAccessScopeV1 {
     PrivilegeCapable,
     #[serde(rename = "s")]
     Synchronise,
+    #[serde(rename = "a")]
+    ApiReadWrite,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
@@ -550,6 +552,36 @@ pub enum DbValueSessionStateV1 {
     RevokedAt(DbCidV1),
 }
 
+/// A timestamp stored as unix nanoseconds, which is smaller and faster to load than a
+/// string. Timestamps outside the range of an i64 (approximately the years 1677 to 2262)
+/// are stored as RFC3339 strings instead.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub enum DbValueTimestampV1 {
+    #[serde(rename = "n")]
+    UnixNanos(i64),
+    #[serde(rename = "s")]
+    Rfc3339(String),
+}
+
+/// As per [DbValueSessionStateV1], but with the expiry stored as a [DbValueTimestampV1].
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub enum DbValueSessionStateV2 {
+    #[serde(rename = "ea")]
+    ExpiresAt(DbValueTimestampV1),
+    #[serde(rename = "nv")]
+    Never,
+    #[serde(rename = "ra")]
+    RevokedAt(DbCidV1),
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub enum DbValueSessionDisabledV1 {
+    #[serde(rename = "da")]
+    DisabledAt(DbCidV1),
+    #[serde(rename = "ea")]
+    EnabledAt(DbCidV1),
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub enum DbValueAuthTypeV1 {
     #[serde(rename = "an")]
@@ -570,6 +602,12 @@ pub enum DbValueAuthTypeV1 {
     AttestedPasskey,
     #[serde(rename = "ot")]
     OAuth2Trust,
+    #[serde(rename = "sa")]
+    ServiceAccount,
+    #[serde(rename = "lp")]
+    LdapPassword,
+    #[serde(rename = "tx")]
+    TokenExchange,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
@@ -651,6 +689,39 @@ pub enum DbValueSession {
         #[serde(rename = "x", default)]
         ext_metadata: DbValueSessionExtMetadataV1,
     },
+    /// As per V4, but timestamps are stored as [DbValueTimestampV1], and the session
+    /// records its binding, last activity, if it was issued to a service account, and if it
+    /// is pinned or disabled.
+    V5 {
+        #[serde(rename = "u")]
+        refer: Uuid,
+        #[serde(rename = "l")]
+        label: String,
+        #[serde(rename = "e")]
+        state: DbValueSessionStateV2,
+        #[serde(rename = "i")]
+        issued_at: DbValueTimestampV1,
+        #[serde(rename = "b")]
+        issued_by: DbValueIdentityId,
+        #[serde(rename = "c")]
+        cred_id: Uuid,
+        #[serde(rename = "s", default)]
+        scope: DbValueAccessScopeV1,
+        #[serde(rename = "t")]
+        type_: DbValueAuthTypeV1,
+        #[serde(rename = "x", default)]
+        ext_metadata: DbValueSessionExtMetadataV1,
+        #[serde(rename = "bt", default)]
+        bound_to: Option<String>,
+        #[serde(rename = "la", default)]
+        last_active: Option<DbValueTimestampV1>,
+        #[serde(rename = "p", default)]
+        pinned: bool,
+        #[serde(rename = "sa", default)]
+        service_account: bool,
+        #[serde(rename = "d", default)]
+        disabled: Option<DbValueSessionDisabledV1>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
@@ -664,6 +735,18 @@ pub enum DbValueApiTokenScopeV1 {
     Synchronise,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DbValueApiTokenConstraintV1 {
+    #[serde(rename = "g")]
+    GroupMembership,
+    #[serde(rename = "c")]
+    Credentials,
+    #[serde(rename = "s")]
+    SshPublicKeys,
+    #[serde(rename = "p")]
+    Profile,
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum DbValueApiToken {
     V1 {
@@ -680,6 +763,26 @@ pub enum DbValueApiToken {
         #[serde(rename = "s", default)]
         scope: DbValueApiTokenScopeV1,
     },
+    V2 {
+        #[serde(rename = "u")]
+        refer: Uuid,
+        #[serde(rename = "l")]
+        label: String,
+        #[serde(rename = "t")]
+        state: DbValueSessionStateV1,
+        #[serde(rename = "i")]
+        issued_at: String,
+        #[serde(rename = "b")]
+        issued_by: DbValueIdentityId,
+        #[serde(rename = "s", default)]
+        scope: DbValueApiTokenScopeV1,
+        #[serde(rename = "p", default)]
+        parent_session: Option<Uuid>,
+        #[serde(rename = "g", default)]
+        generation: u32,
+        #[serde(rename = "c", default)]
+        constraints: Option<BTreeSet<DbValueApiTokenConstraintV1>>,
+    },
 }
 
 #[skip_serializing_none]
@@ -721,6 +824,66 @@ pub enum DbValueOauth2Session {
         #[serde(rename = "r")]
         rs_uuid: Uuid,
     },
+    V4 {
+        #[serde(rename = "u")]
+        refer: Uuid,
+        #[serde(rename = "p")]
+        parent: Option<Uuid>,
+        #[serde(rename = "e")]
+        state: DbValueSessionStateV1,
+        #[serde(rename = "i")]
+        issued_at: String,
+        #[serde(rename = "r")]
+        rs_uuid: Uuid,
+        #[serde(rename = "g", default)]
+        granted_scopes: BTreeSet<String>,
+    },
+    V5 {
+        #[serde(rename = "u")]
+        refer: Uuid,
+        #[serde(rename = "p")]
+        parent: Option<Uuid>,
+        #[serde(rename = "e")]
+        state: DbValueSessionStateV1,
+        #[serde(rename = "i")]
+        issued_at: String,
+        #[serde(rename = "r")]
+        rs_uuid: Uuid,
+        #[serde(rename = "g", default)]
+        granted_scopes: BTreeSet<String>,
+        #[serde(rename = "h", default)]
+        has_refresh: bool,
+        #[serde(rename = "l")]
+        last_refreshed: Option<String>,
+    },
+    V6 {
+        #[serde(rename = "u")]
+        refer: Uuid,
+        #[serde(rename = "p")]
+        parent: Option<Uuid>,
+        #[serde(rename = "e")]
+        state: DbValueSessionStateV1,
+        #[serde(rename = "i")]
+        issued_at: String,
+        #[serde(rename = "r")]
+        rs_uuid: Uuid,
+        #[serde(rename = "g", default)]
+        granted_scopes: BTreeSet<String>,
+        #[serde(rename = "h", default)]
+        has_refresh: bool,
+        #[serde(rename = "l")]
+        last_refreshed: Option<String>,
+        #[serde(rename = "o", default)]
+        origin: Option<String>,
+    },
+}
+
+impl DbValueOauth2Session {
+    /// If this session is stored as an older version. Sessions are always written as the
+    /// newest version, so these are upgraded the next time their valueset is written.
+    pub fn needs_migration(&self) -> bool {
+        !matches!(self, DbValueOauth2Session::V6 { .. })
+    }
 }
 
 // Internal representation of an image
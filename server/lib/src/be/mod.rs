@@ -489,9 +489,17 @@ pub trait BackendTransaction {
                             */
                             IdList::Indexed(r)
                         }
-                        (IdList::Indexed(ia), IdList::Partial(ib))
-                        | (IdList::Partial(ia), IdList::Indexed(ib))
-                        | (IdList::Partial(ia), IdList::Partial(ib)) => {
+                        // A partial set may hold entries that don't match the inner filter,
+                        // so it can't be excluded. The filter test removes the matches instead.
+                        (IdList::Indexed(ia), IdList::Partial(_))
+                        | (IdList::Partial(ia), IdList::Partial(_)) => IdList::Partial(ia),
+                        (IdList::Indexed(ia), IdList::PartialThreshold(_))
+                        | (IdList::PartialThreshold(ia), IdList::PartialThreshold(_))
+                        | (IdList::PartialThreshold(ia), IdList::Partial(_))
+                        | (IdList::Partial(ia), IdList::PartialThreshold(_)) => {
+                            IdList::PartialThreshold(ia)
+                        }
+                        (IdList::Partial(ia), IdList::Indexed(ib)) => {
                             let r = ia.andnot(ib);
                             // DO trigger threshold on partials, because we have to apply the filter
                             // test anyway, so we may as well shortcut at this point.
@@ -502,11 +510,7 @@ pub trait BackendTransaction {
                                 IdList::Partial(r)
                             }
                         }
-                        (IdList::Indexed(ia), IdList::PartialThreshold(ib))
-                        | (IdList::PartialThreshold(ia), IdList::Indexed(ib))
-                        | (IdList::PartialThreshold(ia), IdList::PartialThreshold(ib))
-                        | (IdList::PartialThreshold(ia), IdList::Partial(ib))
-                        | (IdList::Partial(ia), IdList::PartialThreshold(ib)) => {
+                        (IdList::PartialThreshold(ia), IdList::Indexed(ib)) => {
                             let r = ia.andnot(ib);
                             // DO trigger threshold on partials, because we have to apply the filter
                             // test anyway, so we may as well shortcut at this point.
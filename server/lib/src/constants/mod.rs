@@ -15,7 +15,7 @@ use std::time::Duration;
 // index is performed on first-run.
 //
 // It's also useful if we need to force a reindex due to a bug though :)
-pub const SYSTEM_INDEX_VERSION: i64 = 32;
+pub const SYSTEM_INDEX_VERSION: i64 = 33;
 
 /*
  * domain functional levels
@@ -149,6 +149,26 @@ pub const DEFAULT_MESSAGE_RETENTION: Duration = Duration::from_secs(86400 * 7);
 /// some latency while dequeuing and writing those operations.
 pub const DELAYED_ACTION_BATCH_SIZE: usize = 256;
 
+/// The time that a session or api token must have been expired for before it is
+/// revoked by the expired session purge, allowing it to later be trimmed.
+pub const EXPIRED_SESSION_PURGE_GRACE: Duration = Duration::from_secs(86400);
+
+/// The number of entries holding expired sessions to modify per write transaction.
+pub const EXPIRED_SESSION_PURGE_BATCH_SIZE: usize = 64;
+
+/// Api tokens that will expire within this window are reported as nearing expiry in
+/// the session metrics.
+pub const SESSION_METRICS_API_TOKEN_EXPIRY_WINDOW: Duration = Duration::from_secs(86400 * 7);
+
+/// The maximum number of oauth2 clients that are individually reported in the session
+/// metrics. Sessions of the remaining clients are reported together, bounding the number
+/// of labels that are emitted.
+pub const SESSION_METRICS_OAUTH2_CLIENT_LIMIT: usize = 16;
+
+/// Sessions and api tokens that will expire within this horizon are notified as expiring,
+/// so that their holders can be warned ahead of time.
+pub const SESSION_EXPIRY_NOTIFY_HORIZON: Duration = Duration::from_secs(86400 * 7);
+
 /// The amount of time to wait to acquire a database ticket before timing out.
 /// Higher values allow greater operation queuing but can cause feedback
 /// loops where operations will stall for long periods.
@@ -178,12 +198,21 @@ pub const PW_MIN_LENGTH: u32 = 10;
 pub const MAXIMUM_AUTH_SESSION_EXPIRY: u32 = u32::MAX;
 // Default - sessions last for 1 day
 pub const DEFAULT_AUTH_SESSION_EXPIRY: u32 = 86400;
+// Maximum - sessions established with a single factor last for at most 1 day.
+pub const MAXIMUM_AUTH_SESSION_EXPIRY_SINGLE_FACTOR: u32 = 86400;
 // Maximum - privileges last for 1 hour.
 pub const MAXIMUM_AUTH_PRIVILEGE_EXPIRY: u32 = 3600;
 // Default - privileges last for 10 minutes.
 pub const DEFAULT_AUTH_PRIVILEGE_EXPIRY: u32 = 600;
 // Default - directly privileged sessions only last 1 hour.
 pub const DEFAULT_AUTH_SESSION_LIMITED_EXPIRY: u32 = 3600;
+// Default - at most 32 sessions may be issued to an account within 5 minutes. This must be
+// below the session maximum, else trimming would hide the sessions from the count.
+pub const DEFAULT_AUTH_SESSION_ISSUE_LIMIT: u32 = 32;
+pub const DEFAULT_AUTH_SESSION_ISSUE_WINDOW: u32 = 300;
+// The activity of a session with an idle timeout is only recorded once this fraction
+// of the timeout has passed, so that validating a session rarely requires a write.
+pub const AUTH_SESSION_ACTIVITY_DIVISOR: u32 = 4;
 // Default - oauth refresh tokens last for 16 hours.
 pub const OAUTH_REFRESH_TOKEN_EXPIRY: u32 = 3600 * 16;
 
@@ -218,9 +247,14 @@ pub const DEFAULT_LIMIT_FILTER_MAX_ELEMENTS: u64 = 32;
 /// The maximum amount of recursion allowed in a filter.
 pub const DEFAULT_LIMIT_FILTER_DEPTH_MAX: u64 = 12;
 
-/// The maximum number of sessions allowed on a single entry.
+/// The default maximum number of sessions allowed on a single entry, where the schema
+/// `maximum_values` of the attribute is not set.
 pub(crate) const SESSION_MAXIMUM: usize = 48;
 
+/// The default maximum number of unrevoked api tokens allowed on a single entry, where
+/// the schema `maximum_values` of the attribute is not set.
+pub(crate) const API_TOKEN_MAXIMUM: usize = 32;
+
 #[cfg(test)]
 // Test only certificate. This is a self-signed server cert.
 pub(crate) const TEST_X509_CERT_DATA: &str = r#"-----BEGIN CERTIFICATE-----
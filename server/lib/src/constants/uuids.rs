@@ -394,6 +394,23 @@ pub const UUID_SCHEMA_ATTR_DOMAIN_ALLOW_ACCOUNT_RECOVERY: Uuid =
     uuid!("00000000-0000-0000-0000-ffff00000224");
 pub const UUID_SCHEMA_ATTR_OAUTH2_REFRESH_TOKEN_EXPIRY: Uuid =
     uuid!("00000000-0000-0000-0000-ffff00000225");
+pub const UUID_SCHEMA_ATTR_MAXIMUM_VALUES: Uuid = uuid!("00000000-0000-0000-0000-ffff00000226");
+pub const UUID_SCHEMA_ATTR_RETAIN_SESSIONS_ON_PASSWORD_CHANGE: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000227");
+pub const UUID_SCHEMA_ATTR_AUTH_SESSION_IDLE_TIMEOUT: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000228");
+pub const UUID_SCHEMA_ATTR_AUTH_SESSION_EXPIRY_SINGLE_FACTOR: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000229");
+pub const UUID_SCHEMA_ATTR_AUTH_SESSION_EXPIRY_MULTI_FACTOR: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000230");
+pub const UUID_SCHEMA_ATTR_REVOKE_SESSIONS_ON_PASSWORD_CHANGE: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000231");
+pub const UUID_SCHEMA_ATTR_RETAIN_CURRENT_SESSION_ON_PASSWORD_CHANGE: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000232");
+pub const UUID_SCHEMA_ATTR_AUTH_SESSION_ISSUE_LIMIT: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000233");
+pub const UUID_SCHEMA_ATTR_AUTH_SESSION_ISSUE_WINDOW: Uuid =
+    uuid!("00000000-0000-0000-0000-ffff00000234");
 
 // =====
 // Incorrectly name spaced.
@@ -531,6 +548,7 @@ pub const UUID_INTERNAL_MIGRATION: Uuid = uuid!("00000000-0000-0000-0000-ffffff0
 pub const UUID_INTERNAL_SESSION_ID: Uuid = uuid!("00000000-0000-0000-0000-ffffff000083");
 pub const UUID_INTERNAL_ACCOUNT_REQUEST: Uuid = uuid!("00000000-0000-0000-0000-ffffff000084");
 pub const UUID_INTERNAL_MESSAGE_QUEUE: Uuid = uuid!("00000000-0000-0000-0000-ffffff000085");
+pub const UUID_IDM_ACP_PEOPLE_SESSION_REVOKE: Uuid = uuid!("00000000-0000-0000-0000-ffffff000086");
 
 // End of system ranges
 pub const UUID_DOES_NOT_EXIST: Uuid = uuid!("00000000-0000-0000-0000-fffffffffffe");
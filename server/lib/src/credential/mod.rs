@@ -637,6 +637,16 @@ impl Credential {
         }
     }
 
+    /// Determine if `other` holds a different password to this credential. Unlike the
+    /// credential id, this is unchanged when only the other factors of the credential change.
+    pub(crate) fn password_differs(&self, other: &Credential) -> bool {
+        match (self.password_ref(), other.password_ref()) {
+            (Ok(pw), Ok(other_pw)) => pw != other_pw,
+            (Err(_), Err(_)) => false,
+            _ => true,
+        }
+    }
+
     pub fn is_mfa(&self) -> bool {
         match &self.type_ {
             CredentialType::Password(_) | CredentialType::GeneratedPassword(_) => false,
@@ -37,12 +37,12 @@ use crate::repl::cid::Cid;
 use crate::repl::entry::EntryChangeState;
 use crate::repl::proto::{ReplEntryV1, ReplIncrementalEntryV1};
 use crate::schema::{SchemaAttribute, SchemaClass, SchemaTransaction};
-use crate::server::access::AccessEffectivePermission;
+use crate::server::access::{Access, AccessEffectivePermission};
 use crate::value::{
     ApiToken, CredentialType, IndexType, IntentTokenState, Oauth2Session, PartialValue, Session,
     SyntaxType, Value,
 };
-use crate::valueset::{self, ScimResolveStatus, ValueSet, ValueSetSpn};
+use crate::valueset::{self, ScimResolveStatus, ValueSet, ValueSetSpn, ValueSetUint32};
 use compact_jwt::JwsEs256Signer;
 use crypto_glue::s256::Sha256Output;
 use hashbrown::{HashMap, HashSet};
@@ -528,6 +528,12 @@ impl From<&SchemaAttribute> for EntryInitNew {
         attrs.insert(Attribute::Unique, vs_bool![s.unique]);
         attrs.insert(Attribute::Indexed, vs_bool![s.indexed]);
         attrs.insert(Attribute::Syntax, vs_syntax![s.syntax]);
+        if let Some(maximum_values) = s.maximum_values {
+            attrs.insert(
+                Attribute::MaximumValues,
+                ValueSetUint32::new(maximum_values),
+            );
+        }
         attrs.insert(
             Attribute::Class,
             vs_iutf8![
@@ -656,6 +662,76 @@ impl<STATE> Entry<EntryIncremental, STATE> {
     }
 }
 
+/// Attributes of these syntaxes carry a replication state per value, so concurrent changes
+/// to them must always be merged rather than resolved in favour of the latest writer. An
+/// absent attribute is equivalent to an empty set, since values are only removed once
+/// trimmed.
+fn repl_always_merge(vs: &ValueSet) -> bool {
+    matches!(
+        vs.syntax(),
+        SyntaxType::Session | SyntaxType::Oauth2Session | SyntaxType::ApiToken
+    )
+}
+
+/// Values of these syntaxes may be refused as they are added to an entry, such as a session
+/// with a scope that its auth type may not hold, or a second active sync token. A
+/// modification adding such a value fails rather than dropping it. Values of other syntaxes
+/// are added as per `add_ava`, and are left for schema validation to reject.
+fn insert_is_checked(value: &Value) -> bool {
+    matches!(
+        value,
+        Value::Session(..) | Value::Oauth2Session(..) | Value::ApiToken(..)
+    )
+}
+
+/// Merge an attribute that is only present on the older side of a change. Only
+/// attributes that `repl_always_merge` are retained, trimmed as they would be on merge.
+fn repl_merge_absent(vs: &ValueSet, trim_cid: &Cid) -> Option<ValueSet> {
+    if !repl_always_merge(vs) {
+        return None;
+    }
+    let mut vs = vs.clone();
+    vs.trim(trim_cid);
+    (!vs.is_empty()).then_some(vs)
+}
+
+/// Resolve values of a merged attribute that were written concurrently on different
+/// replicas, and that together would be rejected by schema.
+fn repl_resolve_merged(vs: &mut ValueSet, cid: &Cid) {
+    if vs.syntax() == SyntaxType::ApiToken {
+        if let Some(tokens) = vs.as_apitoken_set_mut() {
+            tokens.revoke_duplicate_labels(cid);
+        }
+    }
+}
+
+/// Determine the change cid of a merged attribute. When the older side contributed content
+/// that the newer side lacks, the newer cid is already known to the origin of the newer
+/// side, so the merge must be recorded as a change at the consumer's `cid` for the merged
+/// content to be replicated back.
+fn repl_merge_change_cid(
+    newer: Option<&ValueSet>,
+    merged: &ValueSet,
+    newer_cid: &Cid,
+    cid: &Cid,
+    trim_cid: &Cid,
+) -> Cid {
+    let contributed = match newer {
+        Some(newer) => {
+            let mut newer = newer.clone();
+            newer.trim(trim_cid);
+            !newer.equal(merged)
+        }
+        None => true,
+    };
+
+    if contributed {
+        cid.clone()
+    } else {
+        newer_cid.clone()
+    }
+}
+
 impl Entry<EntryIncremental, EntryNew> {
     fn stub_ecstate(&self) -> EntryChangeState {
         self.valid.ecstate.stub()
@@ -843,6 +919,7 @@ impl Entry<EntryIncremental, EntryNew> {
         &self,
         db_ent: &EntrySealedCommitted,
         schema: &dyn SchemaTransaction,
+        cid: &Cid,
         trim_cid: &Cid,
     ) -> EntryIncrementalCommitted {
         use crate::repl::entry::State;
@@ -895,28 +972,46 @@ impl Entry<EntryIncremental, EntryNew> {
 
                             match (self.attrs.get(attr_name), db_ent.attrs.get(attr_name)) {
                                 (Some(vs_left), Some(vs_right)) if take_left => {
-                                    changes.insert(attr_name.clone(), cid_left.clone());
                                     #[allow(clippy::todo)]
-                                    if let Some(merged_attr_state) =
+                                    if let Some(mut merged_attr_state) =
                                         vs_left.repl_merge_valueset(vs_right, trim_cid)
                                     {
                                         // NOTE: This is for special attr types that need to merge
                                         // rather than choose content.
+                                        repl_resolve_merged(&mut merged_attr_state, cid);
+                                        let change_cid = repl_merge_change_cid(
+                                            Some(vs_left),
+                                            &merged_attr_state,
+                                            cid_left,
+                                            cid,
+                                            trim_cid,
+                                        );
+                                        changes.insert(attr_name.clone(), change_cid);
                                         eattrs.insert(attr_name.clone(), merged_attr_state);
                                     } else {
+                                        changes.insert(attr_name.clone(), cid_left.clone());
                                         eattrs.insert(attr_name.clone(), vs_left.clone());
                                     }
                                 }
                                 (Some(vs_left), Some(vs_right)) => {
-                                    changes.insert(attr_name.clone(), cid_right.clone());
                                     #[allow(clippy::todo)]
-                                    if let Some(merged_attr_state) =
+                                    if let Some(mut merged_attr_state) =
                                         vs_right.repl_merge_valueset(vs_left, trim_cid)
                                     {
                                         // NOTE: This is for special attr types that need to merge
                                         // rather than choose content.
+                                        repl_resolve_merged(&mut merged_attr_state, cid);
+                                        let change_cid = repl_merge_change_cid(
+                                            Some(vs_right),
+                                            &merged_attr_state,
+                                            cid_right,
+                                            cid,
+                                            trim_cid,
+                                        );
+                                        changes.insert(attr_name.clone(), change_cid);
                                         eattrs.insert(attr_name.clone(), merged_attr_state);
                                     } else {
+                                        changes.insert(attr_name.clone(), cid_right.clone());
                                         eattrs.insert(attr_name.clone(), vs_right.clone());
                                     }
                                 }
@@ -924,13 +1019,43 @@ impl Entry<EntryIncremental, EntryNew> {
                                     changes.insert(attr_name.clone(), cid_left.clone());
                                     eattrs.insert(attr_name.clone(), vs_left.clone());
                                 }
-                                (Some(_vs_left), None) => {
-                                    changes.insert(attr_name.clone(), cid_right.clone());
-                                    // Taking right, nothing to do due to no attr.
+                                (Some(vs_left), None) => {
+                                    // Taking right, nothing to do due to no attr, unless the
+                                    // values need to be merged into the absent set.
+                                    if let Some(merged_attr_state) =
+                                        repl_merge_absent(vs_left, trim_cid)
+                                    {
+                                        let change_cid = repl_merge_change_cid(
+                                            None,
+                                            &merged_attr_state,
+                                            cid_right,
+                                            cid,
+                                            trim_cid,
+                                        );
+                                        changes.insert(attr_name.clone(), change_cid);
+                                        eattrs.insert(attr_name.clone(), merged_attr_state);
+                                    } else {
+                                        changes.insert(attr_name.clone(), cid_right.clone());
+                                    }
                                 }
-                                (None, Some(_vs_right)) if take_left => {
-                                    changes.insert(attr_name.clone(), cid_left.clone());
-                                    // Taking left, nothing to do due to no attr.
+                                (None, Some(vs_right)) if take_left => {
+                                    // Taking left, nothing to do due to no attr, unless the
+                                    // values need to be merged into the absent set.
+                                    if let Some(merged_attr_state) =
+                                        repl_merge_absent(vs_right, trim_cid)
+                                    {
+                                        let change_cid = repl_merge_change_cid(
+                                            None,
+                                            &merged_attr_state,
+                                            cid_left,
+                                            cid,
+                                            trim_cid,
+                                        );
+                                        changes.insert(attr_name.clone(), change_cid);
+                                        eattrs.insert(attr_name.clone(), merged_attr_state);
+                                    } else {
+                                        changes.insert(attr_name.clone(), cid_left.clone());
+                                    }
                                 }
                                 (None, Some(vs_right)) => {
                                     changes.insert(attr_name.clone(), cid_right.clone());
@@ -1189,6 +1314,12 @@ impl Entry<EntryInvalid, EntryCommitted> {
         // This will put the modify ahead of the recycle transition.
         self.add_ava(Attribute::Class, EntryClass::Recycled.into());
 
+        // Revoke any api tokens, so that reviving this entry doesn't also revive them. The
+        // revoked tokens are retained as a record of what existed.
+        if self.attrs.contains_key(&Attribute::ApiTokenSession) {
+            self.purge_ava(Attribute::ApiTokenSession);
+        }
+
         // Change state repl doesn't need this flag
         // self.valid.ecstate.recycled(&self.valid.cid);
 
@@ -2477,6 +2608,44 @@ impl Entry<EntryReduced, EntryCommitted> {
         })
     }
 
+    /// If the identity that read this entry may search the attribute `attr`. This is
+    /// only known if the entry was read with an effective access check.
+    pub(crate) fn may_search_attr(&self, attr: &Attribute) -> bool {
+        self.valid
+            .effective_access
+            .as_ref()
+            .is_some_and(|eff_acc| match &eff_acc.search {
+                Access::Grant => true,
+                Access::Deny => false,
+                Access::Allow(attrs) => attrs.contains(attr),
+            })
+    }
+
+    /// Transform the single value of `attr` identified by `pv` into a SCIM form that can
+    /// be sent to clients.
+    pub fn to_scim_value_single<'a, TXN>(
+        &self,
+        attr: &Attribute,
+        pv: &PartialValue,
+        read_txn: &mut TXN,
+    ) -> Result<Option<ScimValueKanidm>, OperationError>
+    where
+        TXN: QueryServerTransaction<'a>,
+    {
+        let Some(value) = self.attrs.get(attr).and_then(|vs| vs.get_value(pv)) else {
+            return Ok(None);
+        };
+
+        let vs = valueset::from_value_iter(std::iter::once(value))?;
+        match vs.to_scim_value() {
+            None => Ok(None),
+            Some(ScimResolveStatus::Resolved(scim_value_kani)) => Ok(Some(scim_value_kani)),
+            Some(ScimResolveStatus::NeedsResolution(scim_value_interim)) => {
+                read_txn.resolve_scim_interim(scim_value_interim)
+            }
+        }
+    }
+
     /// Transform this reduced entry into an LDAP form that can be sent to clients.
     pub fn to_ldap(
         &self,
@@ -2673,6 +2842,11 @@ impl<VALID, STATE> Entry<VALID, STATE> {
         self.attrs.get(attr.as_ref())
     }
 
+    /// Return the single value of this attribute that is identified by the partial value.
+    pub fn get_ava_value<A: AsRef<Attribute>>(&self, attr: A, pv: &PartialValue) -> Option<Value> {
+        self.get_ava_set(attr).and_then(|vs| vs.get_value(pv))
+    }
+
     pub fn get_ava_refer<A: AsRef<Attribute>>(&self, attr: A) -> Option<&BTreeSet<Uuid>> {
         self.get_ava_set(attr).and_then(|vs| vs.as_refer_set())
     }
@@ -3219,6 +3393,30 @@ where
         self.add_ava_int(attr, value);
     }
 
+    /// As per `add_ava`, but if the value is refused by the valueset of the attribute the
+    /// error is returned rather than being silently dropped. Values are checked against
+    /// `curtime`, the time of the write. This is only used for values that
+    /// `insert_is_checked`.
+    fn add_ava_checked(
+        &mut self,
+        attr: &Attribute,
+        value: Value,
+        curtime: OffsetDateTime,
+    ) -> Result<(), OperationError> {
+        self.valid.ecstate.change_ava(&self.valid.cid, attr);
+        if let Some(vs) = self.attrs.get_mut(attr) {
+            vs.insert_checked_at(value, curtime).map(|_| ())
+        } else {
+            // Start from an empty set of the same syntax, so that the first value of an
+            // attribute is checked as any other.
+            let mut vs = valueset::from_value_iter(std::iter::once(value.clone()))?;
+            vs.clear();
+            vs.insert_checked_at(value, curtime)?;
+            self.attrs.insert(attr.clone(), vs);
+            Ok(())
+        }
+    }
+
     pub fn add_ava_if_not_exist<A: AsRef<Attribute>>(&mut self, attr: A, value: Value) {
         let attr_ref = attr.as_ref();
         // This returns true if the value WAS changed! See add_ava_int.
@@ -3363,12 +3561,22 @@ where
     }
 
     /// Apply the content of this modlist to this entry, enforcing the expressed state.
+    /// `ct` is the current time of the transaction performing the modification. Values
+    /// that `insert_is_checked` fail the modification if they are refused.
     pub fn apply_modlist(
         &mut self,
         modlist: &ModifyList<ModifyValid>,
+        ct: Duration,
     ) -> Result<(), OperationError> {
+        let curtime = OffsetDateTime::UNIX_EPOCH + ct;
         for modify in modlist {
             match modify {
+                Modify::Present(attr, value) if insert_is_checked(value) => {
+                    self.add_ava_checked(attr, value.clone(), curtime)
+                        .inspect_err(|err| {
+                            error!(?err, "Value was refused by attribute {}", attr);
+                        })?;
+                }
                 Modify::Present(attr, value) => {
                     self.add_ava(attr.clone(), value.clone());
                 }
@@ -3539,7 +3747,9 @@ mod tests {
             Value::new_iutf8("value"),
         )]);
 
-        assert!(e.apply_modlist(&present_single_mods).is_ok());
+        assert!(e
+            .apply_modlist(&present_single_mods, Duration::ZERO)
+            .is_ok());
 
         // Assert the changes are there
         assert!(e.attribute_equality(Attribute::UserId, &PartialValue::new_utf8s("william")));
@@ -3551,7 +3761,9 @@ mod tests {
             Modify::Present(Attribute::Class, Value::new_iutf8("multi_test")),
         ]);
 
-        assert!(e.apply_modlist(&present_multivalue_mods).is_ok());
+        assert!(e
+            .apply_modlist(&present_multivalue_mods, Duration::ZERO)
+            .is_ok());
 
         assert!(e.attribute_equality(Attribute::Class, &PartialValue::new_iutf8("test")));
         assert!(e.attribute_equality(Attribute::Class, &PartialValue::new_iutf8("multi_test")));
@@ -3559,19 +3771,19 @@ mod tests {
         // Assert purge on single/multi/empty value
         let purge_single_mods = ModifyList::new_valid_list(vec![Modify::Purged(Attribute::Attr)]);
 
-        assert!(e.apply_modlist(&purge_single_mods).is_ok());
+        assert!(e.apply_modlist(&purge_single_mods, Duration::ZERO).is_ok());
 
         assert!(!e.attribute_pres(Attribute::Attr));
 
         let purge_multi_mods = ModifyList::new_valid_list(vec![Modify::Purged(Attribute::Class)]);
 
-        assert!(e.apply_modlist(&purge_multi_mods).is_ok());
+        assert!(e.apply_modlist(&purge_multi_mods, Duration::ZERO).is_ok());
 
         assert!(!e.attribute_pres(Attribute::Class));
 
         let purge_empty_mods = purge_single_mods;
 
-        assert!(e.apply_modlist(&purge_empty_mods).is_ok());
+        assert!(e.apply_modlist(&purge_empty_mods, Duration::ZERO).is_ok());
 
         // Assert removed on value that exists and doesn't exist
         let remove_mods = ModifyList::new_valid_list(vec![Modify::Removed(
@@ -3579,18 +3791,54 @@ mod tests {
             PartialValue::new_iutf8("value"),
         )]);
 
-        assert!(e.apply_modlist(&present_single_mods).is_ok());
+        assert!(e
+            .apply_modlist(&present_single_mods, Duration::ZERO)
+            .is_ok());
         assert!(e.attribute_equality(Attribute::Attr, &PartialValue::new_iutf8("value")));
-        assert!(e.apply_modlist(&remove_mods).is_ok());
+        assert!(e.apply_modlist(&remove_mods, Duration::ZERO).is_ok());
         assert!(!e.attrs.contains_key(&Attribute::Attr));
 
         let remove_empty_mods = remove_mods;
 
-        assert!(e.apply_modlist(&remove_empty_mods).is_ok());
+        assert!(e.apply_modlist(&remove_empty_mods, Duration::ZERO).is_ok());
 
         assert!(!e.attrs.contains_key(&Attribute::Attr));
     }
 
+    #[test]
+    fn test_entry_apply_modlist_checked() {
+        use crate::value::{ApiToken, ApiTokenScope};
+
+        let mut e: Entry<EntryInvalid, EntryNew> = Entry::new().into_invalid_new();
+
+        let sync_token = || {
+            Value::ApiToken(
+                Uuid::new_v4(),
+                ApiToken::builder(time::OffsetDateTime::UNIX_EPOCH)
+                    .label(Uuid::new_v4().to_string())
+                    .scope(ApiTokenScope::Synchronise)
+                    .build(),
+            )
+        };
+
+        let present_token_mods = || {
+            ModifyList::new_valid_list(vec![Modify::Present(
+                Attribute::ApiTokenSession,
+                sync_token(),
+            )])
+        };
+
+        assert!(e
+            .apply_modlist(&present_token_mods(), Duration::ZERO)
+            .is_ok());
+
+        // Token values that are refused fail the modification.
+        assert_eq!(
+            e.apply_modlist(&present_token_mods(), Duration::ZERO),
+            Err(OperationError::SyncTokenAlreadyActive)
+        );
+    }
+
     #[test]
     fn test_entry_idx_diff() {
         let mut e1: Entry<EntryInit, EntryNew> = Entry::new();
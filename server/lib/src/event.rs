@@ -777,6 +777,27 @@ impl PurgeDeleteAfterEvent {
     }
 }
 
+#[derive(Debug)]
+pub struct PurgeExpiredSessionsEvent {
+    pub ident: Identity,
+    pub eventid: Uuid,
+}
+
+impl Default for PurgeExpiredSessionsEvent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PurgeExpiredSessionsEvent {
+    pub fn new() -> Self {
+        PurgeExpiredSessionsEvent {
+            ident: Identity::from_internal(),
+            eventid: Uuid::new_v4(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct OnlineBackupEvent {
     pub ident: Identity,
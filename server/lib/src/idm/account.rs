@@ -11,11 +11,17 @@ use crate::idm::server::{IdmServerProxyReadTransaction, IdmServerProxyWriteTrans
 use crate::modify::{ModifyInvalid, ModifyList};
 use crate::prelude::*;
 use crate::schema::SchemaTransaction;
-use crate::value::{IntentTokenState, PartialValue, SessionState, Value};
+use crate::value::{AuthType, IntentTokenState, PartialValue, SessionState, Value};
 use kanidm_lib_crypto::CryptoPolicy;
-use kanidm_proto::internal::{CredentialStatus, UatPurpose, UiHint, UserAuthToken};
-use kanidm_proto::v1::{UatStatus, UatStatusState, UnixGroupToken, UnixUserToken};
+use kanidm_proto::internal::{
+    ApiTokenPurpose, CredentialStatus, UatPurpose, UiHint, UserAuthToken,
+};
+use kanidm_proto::v1::{
+    AccountSessionSummary, Oauth2ClientSessions, Oauth2ClientSessionsQuery, SessionStateCount,
+    UatStatus, UatStatusQuery, UatStatusState, UnixGroupToken, UnixUserToken,
+};
 use sshkey_attest::proto::PublicKey as SshPublicKey;
+use std::collections::btree_map::Entry as BTreeEntry;
 use std::collections::{BTreeMap, BTreeSet};
 use std::time::Duration;
 use time::OffsetDateTime;
@@ -45,6 +51,17 @@ pub struct OAuth2AccountCredential {
     pub(crate) user_id: String,
 }
 
+/// The result of checking a session against its idle timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SessionIdleStatus {
+    /// The session has no idle timeout, or its recorded activity is recent.
+    Active,
+    /// The session is valid, but its activity should be recorded again.
+    Stale,
+    /// The session has been idle for longer than the timeout and must be revoked.
+    Idle,
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct Account {
     // To make this self-referential, we'll need to likely make Entry Pin<Arc<_>>
@@ -71,6 +88,7 @@ pub struct Account {
     pub apps_pwds: BTreeMap<Uuid, Vec<ApplicationPassword>>,
     pub(crate) oauth2_client_provider: Option<OAuth2AccountCredential>,
     pub updated_at: Option<Cid>,
+    pub(crate) service_account: bool,
 }
 
 #[cfg(test)]
@@ -171,6 +189,11 @@ macro_rules! try_from_entry {
             ui_hints.insert(UiHint::SynchronisedAccount);
         }
 
+        let service_account = $value.attribute_equality(
+            Attribute::Class,
+            &EntryClass::ServiceAccount.to_partialvalue(),
+        );
+
         let sshkeys = $value
             .get_ava_set(Attribute::SshPublicKey)
             .and_then(|vs| vs.as_sshkey_map())
@@ -261,6 +284,7 @@ macro_rules! try_from_entry {
             apps_pwds,
             oauth2_client_provider,
             updated_at,
+            service_account,
         })
     }};
 }
@@ -353,6 +377,7 @@ impl Account {
         &self,
         session_id: Uuid,
         scope: SessionScope,
+        auth_type: AuthType,
         ct: Duration,
         account_policy: &ResolvedAccountPolicy,
     ) -> Option<UserAuthToken> {
@@ -364,12 +389,14 @@ impl Account {
 
         let limit_search_max_results = account_policy.limit_search_max_results();
         let limit_search_max_filter_test = account_policy.limit_search_max_filter_test();
+        let idle_timeout = account_policy.authsession_idle_timeout();
 
         // Note that currently the auth_session time comes from policy, but the already-privileged
         // session bound is hardcoded. This mostly affects admin/idm_admin breakglass accounts.
+        // Policy may further limit the session time based on the strength of the auth type.
         let expiry = OffsetDateTime::UNIX_EPOCH
             + ct
-            + Duration::from_secs(account_policy.authsession_expiry() as u64);
+            + Duration::from_secs(account_policy.authsession_expiry_for(auth_type) as u64);
         let limited_expiry = OffsetDateTime::UNIX_EPOCH
             + ct
             + Duration::from_secs(DEFAULT_AUTH_SESSION_LIMITED_EXPIRY as u64);
@@ -383,7 +410,7 @@ impl Account {
                 return None;
             }
             SessionScope::ReadOnly => (UatPurpose::ReadOnly, expiry),
-            SessionScope::ReadWrite => {
+            SessionScope::ReadWrite | SessionScope::ApiReadWrite => {
                 // These sessions are always rw, and so have limited life.
                 // Ensure that we take the lower of the two bounds.
                 let capped = std::cmp::min(expiry, limited_expiry);
@@ -412,6 +439,7 @@ impl Account {
             // groups: self.groups.iter().map(|g| g.to_proto()).collect(),
             limit_search_max_results,
             limit_search_max_filter_test,
+            idle_timeout,
         })
     }
 
@@ -431,9 +459,13 @@ impl Account {
 
         let limit_search_max_results = account_policy.limit_search_max_results();
         let limit_search_max_filter_test = account_policy.limit_search_max_filter_test();
+        let idle_timeout = account_policy.authsession_idle_timeout();
 
         let (purpose, expiry) = match scope {
-            SessionScope::Synchronise | SessionScope::ReadOnly | SessionScope::ReadWrite => {
+            SessionScope::Synchronise
+            | SessionScope::ReadOnly
+            | SessionScope::ReadWrite
+            | SessionScope::ApiReadWrite => {
                 warn!(
                     "Impossible state, should not be re-issuing for session scope {:?}",
                     scope
@@ -472,6 +504,7 @@ impl Account {
             // groups: self.groups.iter().map(|g| g.to_proto()).collect(),
             limit_search_max_results,
             limit_search_max_filter_test,
+            idle_timeout,
         })
     }
 
@@ -509,6 +542,8 @@ impl Account {
             // groups: self.groups.iter().map(|g| g.to_proto()).collect(),
             limit_search_max_results,
             limit_search_max_filter_test,
+            // Certificate sessions have no session record to track activity against.
+            idle_timeout: None,
         })
     }
 
@@ -581,6 +616,10 @@ impl Account {
         self.uuid == UUID_ANONYMOUS
     }
 
+    pub(crate) fn is_service_account(&self) -> bool {
+        self.service_account
+    }
+
     #[cfg(test)]
     pub(crate) fn gen_password_mod(
         &self,
@@ -787,6 +826,11 @@ impl Account {
             // already checked in token_to_token. Here we just need to check it's consistent
             // to our internal session knowledge.
             if let Some(session) = session_present {
+                if session.is_disabled() {
+                    security_info!("Session has been disabled");
+                    return false;
+                }
+
                 match (&session.state, &uat.expiry) {
                     (SessionState::ExpiresAt(s_exp), Some(u_exp)) if s_exp == u_exp => {
                         security_info!("A valid limited session value exists for this token");
@@ -825,6 +869,41 @@ impl Account {
         }
     }
 
+    /// Check the session of this token against the idle timeout it was issued with. This
+    /// only inspects the session held in the entry, so that it is cheap enough to perform
+    /// on every validation.
+    pub(crate) fn check_user_auth_token_idle(
+        ct: Duration,
+        uat: &UserAuthToken,
+        entry: &Entry<EntrySealed, EntryCommitted>,
+    ) -> SessionIdleStatus {
+        let Some(idle_timeout) = uat.idle_timeout else {
+            return SessionIdleStatus::Active;
+        };
+        let idle_timeout = Duration::from_secs(idle_timeout.into());
+
+        // If the session isn't present we are within the grace window, which
+        // check_user_auth_token_valid has already enforced.
+        let Some(session) = entry
+            .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+            .and_then(|session_map| session_map.get(&uat.session_id))
+        else {
+            return SessionIdleStatus::Active;
+        };
+
+        let current = OffsetDateTime::UNIX_EPOCH + ct;
+        let last_active = session.last_active.unwrap_or(session.issued_at);
+
+        if session.is_idle_at(current, idle_timeout) {
+            security_info!(%last_active, "Session has been idle for longer than the idle timeout");
+            SessionIdleStatus::Idle
+        } else if last_active + (idle_timeout / AUTH_SESSION_ACTIVITY_DIVISOR) <= current {
+            SessionIdleStatus::Stale
+        } else {
+            SessionIdleStatus::Active
+        }
+    }
+
     pub(crate) fn verify_application_password(
         &self,
         application: &Application,
@@ -929,6 +1008,20 @@ impl DestroySessionTokenEvent {
     }
 }
 
+pub struct RevokeOauth2ClientSessionsEvent {
+    // Who initiated this?
+    pub ident: Identity,
+    // Who is it targeting?
+    pub target: Uuid,
+    // Which client's sessions.
+    pub client_id: Uuid,
+}
+
+pub struct RevokeOtherSessionsEvent {
+    // Who initiated this? Their current session is the one that is retained.
+    pub ident: Identity,
+}
+
 impl IdmServerProxyWriteTransaction<'_> {
     pub fn account_destroy_session_token(
         &mut self,
@@ -970,6 +1063,134 @@ impl IdmServerProxyWriteTransaction<'_> {
             })
     }
 
+    /// Revoke all of the oauth2 sessions that an account holds with a single client.
+    pub fn account_revoke_oauth2_client_sessions(
+        &mut self,
+        rce: &RevokeOauth2ClientSessionsEvent,
+    ) -> Result<(), OperationError> {
+        // Removing the uuid of the client revokes all of its sessions.
+        let modlist = ModifyList::new_list(vec![Modify::Removed(
+            Attribute::OAuth2Session,
+            PartialValue::Refer(rce.client_id),
+        )]);
+
+        self.qs_write
+            .impersonate_modify(
+                // Filter as executed
+                &filter!(f_and!([
+                    f_eq(Attribute::Uuid, PartialValue::Uuid(rce.target)),
+                    f_eq(Attribute::OAuth2Session, PartialValue::Refer(rce.client_id))
+                ])),
+                // Filter as intended (acp)
+                &filter_all!(f_and!([
+                    f_eq(Attribute::Uuid, PartialValue::Uuid(rce.target)),
+                    f_eq(Attribute::OAuth2Session, PartialValue::Refer(rce.client_id))
+                ])),
+                &modlist,
+                // As with destroying a session, revoking access must never require a re-auth.
+                &rce.ident.project_with_scope(AccessScope::ReadWrite),
+            )
+            .map_err(|e| {
+                admin_error!("Failed to revoke oauth2 client sessions {:?}", e);
+                e
+            })
+    }
+
+    /// Revoke every user auth token session and oauth2 session on the calling account
+    /// except the session performing the request, returning the number revoked. API
+    /// tokens are not affected.
+    pub fn account_revoke_other_sessions(
+        &mut self,
+        rse: &RevokeOtherSessionsEvent,
+    ) -> Result<usize, OperationError> {
+        // Only a user has sessions to retain and revoke.
+        let target = rse
+            .ident
+            .get_user_entry()
+            .map(|entry| entry.get_uuid())
+            .ok_or_else(|| {
+                admin_error!("Invalid identity - no user entry present");
+                OperationError::InvalidState
+            })?;
+        let current_session_id = rse.ident.get_session_id();
+
+        let account_entry = self.qs_write.internal_search_uuid(target)?;
+
+        let (uat_revoke, oauth2_revoke) =
+            sessions_to_revoke(account_entry.as_ref(), Some(current_session_id));
+
+        if uat_revoke.is_empty() && oauth2_revoke.is_empty() {
+            return Ok(0);
+        }
+
+        // Check with the QS that the caller holds self write over their uat sessions. This
+        // is projected as readwrite as with destroying a single session, since logging out
+        // elsewhere must never require a re-auth. The oauth2 sessions are cascaded from the
+        // permitted revocation.
+        let check_modlist = if uat_revoke.is_empty() {
+            // The check is never applied, so it's safe to name our own session here.
+            ModifyList::new_list(vec![Modify::Removed(
+                Attribute::UserAuthTokenSession,
+                PartialValue::Refer(current_session_id),
+            )])
+        } else {
+            ModifyList::new_list(
+                uat_revoke
+                    .iter()
+                    .map(|id| {
+                        Modify::Removed(Attribute::UserAuthTokenSession, PartialValue::Refer(*id))
+                    })
+                    .collect(),
+            )
+        };
+
+        let me = self
+            .qs_write
+            .impersonate_modify_gen_event(
+                // Filter as executed
+                &filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(target))),
+                // Filter as intended (acp)
+                &filter_all!(f_eq(Attribute::Uuid, PartialValue::Uuid(target))),
+                &check_modlist,
+                &rse.ident.project_with_scope(AccessScope::ReadWrite),
+            )
+            .map_err(|e| {
+                request_error!(error = ?e);
+                e
+            })?;
+
+        self.qs_write
+            .modify_pre_apply(&me)
+            .and_then(|opt_mp| opt_mp.ok_or(OperationError::NoMatchingEntries))
+            .map_err(|e| {
+                admin_error!("Not permitted to revoke sessions {:?}", e);
+                e
+            })?;
+
+        // Access control passed, now revoke everything in a single modify.
+        let modlist =
+            ModifyList::new_list(
+                uat_revoke
+                    .iter()
+                    .map(|id| {
+                        Modify::Removed(Attribute::UserAuthTokenSession, PartialValue::Refer(*id))
+                    })
+                    .chain(oauth2_revoke.iter().map(|id| {
+                        Modify::Removed(Attribute::OAuth2Session, PartialValue::Refer(*id))
+                    }))
+                    .collect(),
+            );
+
+        self.qs_write
+            .internal_modify_uuid(target, &modlist)
+            .map_err(|e| {
+                admin_error!("Failed to revoke other sessions {:?}", e);
+                e
+            })?;
+
+        Ok(uat_revoke.len() + oauth2_revoke.len())
+    }
+
     pub fn service_account_into_person(
         &mut self,
         ident: &Identity,
@@ -1054,6 +1275,8 @@ pub struct ListUserAuthTokenEvent {
     pub ident: Identity,
     // Who is it targeting?
     pub target: Uuid,
+    // Which tokens, and how many?
+    pub query: UatStatusQuery,
 }
 
 impl IdmServerProxyReadTransaction<'_> {
@@ -1061,6 +1284,18 @@ impl IdmServerProxyReadTransaction<'_> {
         &mut self,
         lte: &ListUserAuthTokenEvent,
     ) -> Result<Vec<UatStatus>, OperationError> {
+        let auth_type = lte
+            .query
+            .auth_type
+            .as_deref()
+            .map(|auth_type| {
+                AuthType::try_from(auth_type).map_err(|_| {
+                    admin_error!(?auth_type, "Invalid auth type to list user auth tokens by");
+                    OperationError::InvalidRequestState
+                })
+            })
+            .transpose()?;
+
         // Make an event from the request
         let srch = match SearchEvent::from_target_uuid_request(
             lte.ident.clone(),
@@ -1082,11 +1317,19 @@ impl IdmServerProxyReadTransaction<'_> {
                     .and_then(|e| {
                         let account_id = e.get_uuid();
                         // From the entry, turn it into the value
-                        e.get_ava_as_session_map(Attribute::UserAuthTokenSession)
-                            .map(|smap| {
-                                smap.iter()
+                        e.get_ava_set(Attribute::UserAuthTokenSession)
+                            .and_then(|vs| vs.as_session_set())
+                            .map(|sessions| {
+                                sessions
+                                    .sessions_by_recency_page(
+                                        lte.query.exclude_revoked,
+                                        auth_type,
+                                        lte.query.offset,
+                                        lte.query.limit,
+                                    )
+                                    .into_iter()
                                     .map(|(u, s)| {
-                                        let state = match s.state {
+                                        let state = match s.effective_state() {
                                             SessionState::ExpiresAt(odt) => {
                                                 UatStatusState::ExpiresAt(odt)
                                             }
@@ -1100,10 +1343,12 @@ impl IdmServerProxyReadTransaction<'_> {
                                             .try_into()
                                             .map(|purpose| UatStatus {
                                                 account_id,
-                                                session_id: *u,
+                                                session_id: u,
                                                 state,
                                                 issued_at: s.issued_at,
                                                 purpose,
+                                                label: s.label.clone(),
+                                                auth_type: s.type_.to_string(),
                                             })
                                             .inspect_err(|_e| {
                                                 admin_error!("Invalid user auth token {}", u);
@@ -1120,13 +1365,270 @@ impl IdmServerProxyReadTransaction<'_> {
             Err(e) => Err(e),
         }
     }
+
+    /// List the oauth2 sessions of an account grouped by the client they were issued to,
+    /// showing which clients have access to the account.
+    pub fn account_list_oauth2_client_sessions(
+        &mut self,
+        lse: &ListOauth2ClientSessionsEvent,
+    ) -> Result<Vec<Oauth2ClientSessions>, OperationError> {
+        let mut srch =
+            SearchEvent::from_target_uuid_request(lse.ident.clone(), lse.target, &self.qs_read)
+                .inspect_err(|err| {
+                    admin_error!(?err, "Failed to begin account oauth2 client session list");
+                })?;
+        srch.attrs = Some(BTreeSet::from([Attribute::OAuth2Session]));
+
+        let Some(entry) = self.qs_read.search_ext(&srch)?.pop() else {
+            // No matching entry? Return none.
+            return Ok(Vec::with_capacity(0));
+        };
+        let account_id = entry.get_uuid();
+
+        let mut clients: BTreeMap<Uuid, (usize, OffsetDateTime, UatStatusState)> = BTreeMap::new();
+
+        entry
+            .get_ava_as_oauth2session_map(Attribute::OAuth2Session)
+            .into_iter()
+            .flatten()
+            .filter(|(_, session)| {
+                lse.query.include_revoked || !matches!(session.state, SessionState::RevokedAt(_))
+            })
+            .for_each(|(_, session)| {
+                let state = match session.state {
+                    SessionState::ExpiresAt(odt) => UatStatusState::ExpiresAt(odt),
+                    SessionState::NeverExpires => UatStatusState::NeverExpires,
+                    SessionState::RevokedAt(_) => UatStatusState::Revoked,
+                };
+
+                match clients.entry(session.rs_uuid) {
+                    BTreeEntry::Occupied(mut client) => {
+                        let (sessions, newest_issued_at, longest_state) = client.get_mut();
+                        *sessions += 1;
+                        *newest_issued_at = (*newest_issued_at).max(session.issued_at);
+                        *longest_state = longest_lived_state(longest_state.clone(), state);
+                    }
+                    BTreeEntry::Vacant(client) => {
+                        client.insert((1, session.issued_at, state));
+                    }
+                }
+            });
+
+        clients
+            .into_iter()
+            .map(|(client_id, (sessions, newest_issued_at, state))| {
+                // The names are resolved internally since the account was shown them when
+                // consenting to the client. Once the client is deleted only its uuid remains.
+                let client = match self.qs_read.internal_search_uuid(client_id) {
+                    Ok(client) => Some(client),
+                    Err(OperationError::NoMatchingEntries) => None,
+                    Err(err) => {
+                        admin_error!(?err, ?client_id, "Failed to resolve oauth2 client");
+                        return Err(err);
+                    }
+                };
+
+                Ok(Oauth2ClientSessions {
+                    account_id,
+                    client_id,
+                    client_name: client
+                        .as_ref()
+                        .and_then(|e| e.get_ava_single_iname(Attribute::Name))
+                        .map(str::to_string),
+                    client_displayname: client
+                        .as_ref()
+                        .and_then(|e| e.get_ava_single_utf8(Attribute::DisplayName))
+                        .map(str::to_string),
+                    sessions,
+                    newest_issued_at,
+                    state,
+                })
+            })
+            .collect()
+    }
+
+    /// Summarise the sessions, oauth2 sessions and api tokens of an account. Only the
+    /// session attributes that the initiator can read are counted.
+    pub fn account_session_summary(
+        &mut self,
+        sse: &AccountSessionSummaryEvent,
+        ct: Duration,
+    ) -> Result<AccountSessionSummary, OperationError> {
+        let ct = OffsetDateTime::UNIX_EPOCH + ct;
+
+        let mut srch =
+            SearchEvent::from_target_uuid_request(sse.ident.clone(), sse.target, &self.qs_read)
+                .inspect_err(|err| {
+                    admin_error!(?err, "Failed to begin account session summary");
+                })?;
+        srch.attrs = Some(BTreeSet::from([
+            Attribute::UserAuthTokenSession,
+            Attribute::OAuth2Session,
+            Attribute::ApiTokenSession,
+        ]));
+
+        let entry = self
+            .qs_read
+            .search_ext(&srch)?
+            .pop()
+            .ok_or(OperationError::NoMatchingEntries)?;
+
+        let user_auth_tokens = count_session_states(
+            entry
+                .get_ava_set(Attribute::UserAuthTokenSession)
+                .and_then(|vs| vs.as_session_map())
+                .into_iter()
+                .flatten()
+                .map(|(_, session)| session.effective_state()),
+            ct,
+        );
+
+        let oauth2_sessions = entry
+            .get_ava_set(Attribute::OAuth2Session)
+            .and_then(|vs| vs.as_oauth2session_set());
+
+        let oauth2_sessions_by_client = oauth2_sessions
+            .map(|vs| vs.count_by_rs_iter(ct).collect())
+            .unwrap_or_default();
+
+        let oauth2_sessions = count_session_states(
+            oauth2_sessions
+                .and_then(|vs| vs.as_oauth2session_map())
+                .into_iter()
+                .flatten()
+                .map(|(_, session)| session.state.clone()),
+            ct,
+        );
+
+        let api_tokens = entry
+            .get_ava_set(Attribute::ApiTokenSession)
+            .and_then(|vs| vs.as_apitoken_map());
+
+        let api_tokens_by_purpose = api_tokens
+            .into_iter()
+            .flatten()
+            .filter(|(_, token)| token.is_valid_at(ct))
+            .try_fold(BTreeMap::new(), |mut counts, (_, token)| {
+                let purpose: ApiTokenPurpose = token.scope.try_into()?;
+                *counts.entry(purpose).or_insert(0) += 1;
+                Ok::<_, OperationError>(counts)
+            })?;
+
+        let api_tokens = count_session_states(
+            api_tokens
+                .into_iter()
+                .flatten()
+                .map(|(_, token)| token.state.clone()),
+            ct,
+        );
+
+        Ok(AccountSessionSummary {
+            account_id: entry.get_uuid(),
+            user_auth_tokens,
+            oauth2_sessions,
+            oauth2_sessions_by_client,
+            api_tokens,
+            api_tokens_by_purpose,
+        })
+    }
+}
+
+pub struct ListOauth2ClientSessionsEvent {
+    // Who initiated this?
+    pub ident: Identity,
+    // Who is it targeting?
+    pub target: Uuid,
+    // Which sessions?
+    pub query: Oauth2ClientSessionsQuery,
+}
+
+pub struct AccountSessionSummaryEvent {
+    // Who initiated this?
+    pub ident: Identity,
+    // Who is it targeting?
+    pub target: Uuid,
+}
+
+/// The ids of the unrevoked sessions of `entry` other than `retained_session_id`, and of
+/// the unrevoked oauth2 sessions that were not issued by it.
+pub(crate) fn sessions_to_revoke(
+    entry: &EntrySealedCommitted,
+    retained_session_id: Option<Uuid>,
+) -> (Vec<Uuid>, Vec<Uuid>) {
+    let uat_revoke = entry
+        .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+        .into_iter()
+        .flatten()
+        .filter(|(id, session)| {
+            Some(**id) != retained_session_id
+                && !matches!(session.state, SessionState::RevokedAt(_))
+        })
+        .map(|(id, _)| *id)
+        .collect();
+
+    let oauth2_revoke = entry
+        .get_ava_as_oauth2session_map(Attribute::OAuth2Session)
+        .into_iter()
+        .flatten()
+        .filter(|(_, session)| {
+            retained_session_id.is_none_or(|id| session.parent != Some(id))
+                && !matches!(session.state, SessionState::RevokedAt(_))
+        })
+        .map(|(id, _)| *id)
+        .collect();
+
+    (uat_revoke, oauth2_revoke)
+}
+
+/// The state of whichever of two sessions will remain valid the longest.
+fn longest_lived_state(a: UatStatusState, b: UatStatusState) -> UatStatusState {
+    match (a, b) {
+        (UatStatusState::NeverExpires, _) | (_, UatStatusState::NeverExpires) => {
+            UatStatusState::NeverExpires
+        }
+        (UatStatusState::ExpiresAt(a), UatStatusState::ExpiresAt(b)) => {
+            UatStatusState::ExpiresAt(a.max(b))
+        }
+        (UatStatusState::ExpiresAt(exp), UatStatusState::Revoked)
+        | (UatStatusState::Revoked, UatStatusState::ExpiresAt(exp)) => {
+            UatStatusState::ExpiresAt(exp)
+        }
+        (UatStatusState::Revoked, UatStatusState::Revoked) => UatStatusState::Revoked,
+    }
+}
+
+/// Count the session states that are active, expired and revoked at `ct`.
+fn count_session_states(
+    states: impl Iterator<Item = SessionState>,
+    ct: OffsetDateTime,
+) -> SessionStateCount {
+    states.fold(SessionStateCount::default(), |mut count, state| {
+        match state {
+            SessionState::RevokedAt(_) => count.revoked += 1,
+            SessionState::ExpiresAt(exp) if exp <= ct => count.expired += 1,
+            SessionState::ExpiresAt(_) | SessionState::NeverExpires => count.active += 1,
+        }
+        count
+    })
 }
 
 #[cfg(test)]
 mod tests {
+    use super::{
+        AccountSessionSummaryEvent, ListOauth2ClientSessionsEvent, RevokeOauth2ClientSessionsEvent,
+    };
+    use crate::credential::Credential;
     use crate::idm::accountpolicy::ResolvedAccountPolicy;
     use crate::prelude::*;
-    use kanidm_proto::internal::UiHint;
+    use crate::value::{ApiToken, AuthType, Oauth2Session, Session, SessionState};
+    use kanidm_lib_crypto::CryptoPolicy;
+    use kanidm_proto::internal::{ApiTokenPurpose, UiHint};
+    use kanidm_proto::v1::{
+        AccountSessionSummary, Oauth2ClientSessions, Oauth2ClientSessionsQuery, SessionStateCount,
+        UatStatusState,
+    };
+    use std::collections::{BTreeMap, BTreeSet};
+    use time::OffsetDateTime;
 
     #[idm_test]
     async fn test_idm_account_ui_hints(idms: &IdmServer, _idms_delayed: &mut IdmServerDelayed) {
@@ -1158,6 +1660,7 @@ mod tests {
             .to_userauthtoken(
                 session_id,
                 SessionScope::ReadWrite,
+                AuthType::Passkey,
                 ct,
                 &ResolvedAccountPolicy::test_policy(),
             )
@@ -1189,6 +1692,7 @@ mod tests {
             .to_userauthtoken(
                 session_id,
                 SessionScope::ReadWrite,
+                AuthType::Passkey,
                 ct,
                 &ResolvedAccountPolicy::test_policy(),
             )
@@ -1222,6 +1726,7 @@ mod tests {
             .to_userauthtoken(
                 session_id,
                 SessionScope::ReadWrite,
+                AuthType::Passkey,
                 ct,
                 &ResolvedAccountPolicy::test_policy(),
             )
@@ -1234,4 +1739,494 @@ mod tests {
 
         assert!(idms_prox_write.commit().is_ok());
     }
+
+    #[idm_test]
+    async fn test_idm_account_session_summary(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = duration_from_epoch_now();
+        let odt = OffsetDateTime::UNIX_EPOCH + ct;
+        // The summary is taken after the short lived values have expired.
+        let summary_ct = ct + Duration::from_secs(120);
+        let short_lived = SessionState::ExpiresAt(odt + Duration::from_secs(60));
+        let long_lived = SessionState::ExpiresAt(odt + Duration::from_secs(3600));
+
+        let target_uuid = Uuid::new_v4();
+        let (rs_a, rs_b) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let cred = Credential::new_password_only(
+            &CryptoPolicy::minimum(),
+            "test_password",
+            OffsetDateTime::UNIX_EPOCH,
+        )
+        .unwrap();
+
+        let session = |state: SessionState| Session {
+            label: "label".to_string(),
+            state,
+            issued_at: odt,
+            issued_by: IdentityId::User(target_uuid),
+            cred_id: cred.uuid,
+            scope: SessionScope::ReadOnly,
+            type_: AuthType::Password,
+            ext_metadata: Default::default(),
+            bound_to: None,
+            last_active: None,
+            pinned: false,
+            service_account: false,
+            disabled: None,
+        };
+
+        let oauth2_session = |rs_uuid: Uuid, state: SessionState| Oauth2Session {
+            parent: None,
+            state,
+            issued_at: odt,
+            rs_uuid,
+            granted_scopes: BTreeSet::default(),
+            has_refresh: false,
+            last_refreshed: None,
+            origin: None,
+        };
+
+        let token = |scope: ApiTokenScope, state: SessionState| {
+            ApiToken::builder(odt)
+                .label(Uuid::new_v4().to_string())
+                .scope(scope)
+                .state(state)
+                .build()
+        };
+
+        let resource_server = |uuid: Uuid, name: &str| {
+            entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (Attribute::Class, EntryClass::Account.to_value()),
+                (
+                    Attribute::Class,
+                    EntryClass::OAuth2ResourceServer.to_value()
+                ),
+                (
+                    Attribute::Class,
+                    EntryClass::OAuth2ResourceServerPublic.to_value()
+                ),
+                (Attribute::Uuid, Value::Uuid(uuid)),
+                (Attribute::Name, Value::new_iname(name)),
+                (Attribute::DisplayName, Value::new_utf8s(name)),
+                (
+                    Attribute::OAuth2RsOriginLanding,
+                    Value::new_url_s("https://demo.example.com").unwrap()
+                )
+            )
+        };
+
+        let (revoked_session, revoked_token) = (Uuid::new_v4(), Uuid::new_v4());
+
+        // The reader may only search the session attributes of the account.
+        let (reader_uuid, readers_uuid) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let e_reader = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Name, Value::new_iname("test_summary_reader")),
+            (Attribute::Uuid, Value::Uuid(reader_uuid)),
+            (
+                Attribute::DisplayName,
+                Value::new_utf8s("test_summary_reader")
+            )
+        );
+
+        let e_readers = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("test_summary_readers")),
+            (Attribute::Uuid, Value::Uuid(readers_uuid)),
+            (Attribute::Member, Value::Refer(reader_uuid))
+        );
+
+        let e_acp = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (
+                Attribute::Class,
+                EntryClass::AccessControlProfile.to_value()
+            ),
+            (
+                Attribute::Class,
+                EntryClass::AccessControlReceiverGroup.to_value()
+            ),
+            (
+                Attribute::Class,
+                EntryClass::AccessControlTargetScope.to_value()
+            ),
+            (Attribute::Class, EntryClass::AccessControlSearch.to_value()),
+            (
+                Attribute::Name,
+                Value::new_iname("test_acp_session_summary")
+            ),
+            (Attribute::Uuid, Value::Uuid(Uuid::new_v4())),
+            (Attribute::AcpReceiverGroup, Value::Refer(readers_uuid)),
+            (
+                Attribute::AcpTargetScope,
+                Value::new_json_filter_s(&format!("{{\"eq\":[\"uuid\",\"{target_uuid}\"]}}"))
+                    .expect("filter")
+            ),
+            (Attribute::AcpSearchAttr, Value::from(Attribute::Uuid)),
+            (
+                Attribute::AcpSearchAttr,
+                Value::from(Attribute::UserAuthTokenSession)
+            ),
+            (
+                Attribute::AcpSearchAttr,
+                Value::from(Attribute::OAuth2Session)
+            ),
+            (
+                Attribute::AcpSearchAttr,
+                Value::from(Attribute::ApiTokenSession)
+            )
+        );
+
+        let mut e_account = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Name, Value::new_iname("test_account_only")),
+            (Attribute::Uuid, Value::Uuid(target_uuid)),
+            (
+                Attribute::DisplayName,
+                Value::new_utf8s("test_account_only")
+            ),
+            (
+                Attribute::PrimaryCredential,
+                Value::Cred("primary".to_string(), cred.clone())
+            )
+        );
+
+        [
+            (Uuid::new_v4(), SessionState::NeverExpires),
+            (Uuid::new_v4(), short_lived.clone()),
+            (revoked_session, SessionState::NeverExpires),
+        ]
+        .into_iter()
+        .for_each(|(session_id, state)| {
+            e_account.add_ava(
+                Attribute::UserAuthTokenSession,
+                Value::Session(session_id, session(state)),
+            )
+        });
+
+        [
+            (rs_a, long_lived.clone()),
+            (rs_a, long_lived.clone()),
+            (rs_b, long_lived),
+            (rs_b, short_lived.clone()),
+        ]
+        .into_iter()
+        .for_each(|(rs_uuid, state)| {
+            e_account.add_ava(
+                Attribute::OAuth2Session,
+                Value::Oauth2Session(Uuid::new_v4(), oauth2_session(rs_uuid, state)),
+            )
+        });
+
+        [
+            (
+                Uuid::new_v4(),
+                ApiTokenScope::ReadOnly,
+                SessionState::NeverExpires,
+            ),
+            (
+                Uuid::new_v4(),
+                ApiTokenScope::ReadWrite,
+                SessionState::NeverExpires,
+            ),
+            (Uuid::new_v4(), ApiTokenScope::ReadOnly, short_lived),
+            (
+                revoked_token,
+                ApiTokenScope::Synchronise,
+                SessionState::NeverExpires,
+            ),
+        ]
+        .into_iter()
+        .for_each(|(token_id, scope, state)| {
+            e_account.add_ava(
+                Attribute::ApiTokenSession,
+                Value::ApiToken(token_id, token(scope, state)),
+            )
+        });
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        idms_prox_write
+            .qs_write
+            .internal_create(vec![
+                resource_server(rs_a, "test_resource_server_a"),
+                resource_server(rs_b, "test_resource_server_b"),
+                e_account,
+                e_reader,
+                e_readers,
+                e_acp,
+            ])
+            .expect("Failed to create entries");
+
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                target_uuid,
+                &ModifyList::new_list(vec![
+                    Modify::Removed(
+                        Attribute::UserAuthTokenSession,
+                        PartialValue::Refer(revoked_session),
+                    ),
+                    Modify::Removed(
+                        Attribute::ApiTokenSession,
+                        PartialValue::Refer(revoked_token),
+                    ),
+                ]),
+            )
+            .expect("Failed to revoke");
+
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+
+        let reader = idms_prox_read
+            .qs_read
+            .internal_search_uuid(reader_uuid)
+            .expect("Failed to find reader");
+
+        let summary = idms_prox_read
+            .account_session_summary(
+                &AccountSessionSummaryEvent {
+                    ident: Identity::from_impersonate_entry_readonly(reader),
+                    target: target_uuid,
+                },
+                summary_ct,
+            )
+            .expect("Failed to summarise sessions");
+
+        assert_eq!(
+            summary,
+            AccountSessionSummary {
+                account_id: target_uuid,
+                user_auth_tokens: SessionStateCount {
+                    active: 1,
+                    expired: 1,
+                    revoked: 1,
+                },
+                oauth2_sessions: SessionStateCount {
+                    active: 3,
+                    expired: 1,
+                    revoked: 0,
+                },
+                oauth2_sessions_by_client: BTreeMap::from([(rs_a, 2), (rs_b, 1)]),
+                api_tokens: SessionStateCount {
+                    active: 2,
+                    expired: 1,
+                    revoked: 1,
+                },
+                api_tokens_by_purpose: BTreeMap::from([
+                    (ApiTokenPurpose::ReadOnly, 1),
+                    (ApiTokenPurpose::ReadWrite, 1),
+                ]),
+            }
+        );
+    }
+
+    #[idm_test]
+    async fn test_idm_account_oauth2_client_sessions(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = duration_from_epoch_now();
+        let odt = OffsetDateTime::UNIX_EPOCH + ct;
+        let later = odt + Duration::from_secs(10);
+        let expiry = SessionState::ExpiresAt(odt + Duration::from_secs(3600));
+
+        let target_uuid = Uuid::new_v4();
+        let (rs_a, rs_b, rs_deleted) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        let oauth2_session =
+            |rs_uuid: Uuid, issued_at: OffsetDateTime, state: SessionState| Oauth2Session {
+                parent: None,
+                state,
+                issued_at,
+                rs_uuid,
+                granted_scopes: BTreeSet::default(),
+                has_refresh: false,
+                last_refreshed: None,
+                origin: None,
+            };
+
+        let resource_server = |uuid: Uuid, name: &str| {
+            entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (Attribute::Class, EntryClass::Account.to_value()),
+                (
+                    Attribute::Class,
+                    EntryClass::OAuth2ResourceServer.to_value()
+                ),
+                (
+                    Attribute::Class,
+                    EntryClass::OAuth2ResourceServerPublic.to_value()
+                ),
+                (Attribute::Uuid, Value::Uuid(uuid)),
+                (Attribute::Name, Value::new_iname(name)),
+                (Attribute::DisplayName, Value::new_utf8s(name)),
+                (
+                    Attribute::OAuth2RsOriginLanding,
+                    Value::new_url_s("https://demo.example.com").unwrap()
+                )
+            )
+        };
+
+        let mut e_account = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("test_person")),
+            (Attribute::Uuid, Value::Uuid(target_uuid)),
+            (Attribute::DisplayName, Value::new_utf8s("test_person"))
+        );
+
+        [
+            (rs_a, odt, expiry.clone()),
+            (rs_a, later, SessionState::NeverExpires),
+            (rs_b, odt, expiry.clone()),
+            (rs_deleted, later, SessionState::NeverExpires),
+        ]
+        .into_iter()
+        .for_each(|(rs_uuid, issued_at, state)| {
+            e_account.add_ava(
+                Attribute::OAuth2Session,
+                Value::Oauth2Session(Uuid::new_v4(), oauth2_session(rs_uuid, issued_at, state)),
+            )
+        });
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        idms_prox_write
+            .qs_write
+            .internal_create(vec![
+                resource_server(rs_a, "test_client_a"),
+                resource_server(rs_b, "test_client_b"),
+                resource_server(rs_deleted, "test_client_deleted"),
+                e_account,
+            ])
+            .expect("Failed to create entries");
+
+        // Deleting the client revokes its sessions.
+        idms_prox_write
+            .qs_write
+            .internal_delete_uuid(rs_deleted)
+            .expect("Failed to delete client");
+
+        assert!(idms_prox_write.commit().is_ok());
+
+        let client_a = Oauth2ClientSessions {
+            account_id: target_uuid,
+            client_id: rs_a,
+            client_name: Some("test_client_a".to_string()),
+            client_displayname: Some("test_client_a".to_string()),
+            sessions: 2,
+            newest_issued_at: later,
+            state: UatStatusState::NeverExpires,
+        };
+
+        let client_b = Oauth2ClientSessions {
+            account_id: target_uuid,
+            client_id: rs_b,
+            client_name: Some("test_client_b".to_string()),
+            client_displayname: Some("test_client_b".to_string()),
+            sessions: 1,
+            newest_issued_at: odt,
+            state: UatStatusState::ExpiresAt(odt + Duration::from_secs(3600)),
+        };
+
+        let client_deleted = Oauth2ClientSessions {
+            account_id: target_uuid,
+            client_id: rs_deleted,
+            client_name: None,
+            client_displayname: None,
+            sessions: 1,
+            newest_issued_at: later,
+            state: UatStatusState::Revoked,
+        };
+
+        let sorted = |mut clients: Vec<Oauth2ClientSessions>| {
+            clients.sort_unstable_by_key(|client| client.client_id);
+            clients
+        };
+
+        // The account lists its own sessions.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+
+        let account = idms_prox_read
+            .qs_read
+            .internal_search_uuid(target_uuid)
+            .expect("Failed to find account");
+        let ident = Identity::from_impersonate_entry_readonly(account);
+
+        let clients = idms_prox_read
+            .account_list_oauth2_client_sessions(&ListOauth2ClientSessionsEvent {
+                ident: ident.clone(),
+                target: target_uuid,
+                query: Oauth2ClientSessionsQuery::default(),
+            })
+            .expect("Failed to list oauth2 client sessions");
+
+        assert_eq!(clients, sorted(vec![client_a.clone(), client_b.clone()]));
+
+        // Revoked sessions are only listed on request.
+        let clients = idms_prox_read
+            .account_list_oauth2_client_sessions(&ListOauth2ClientSessionsEvent {
+                ident,
+                target: target_uuid,
+                query: Oauth2ClientSessionsQuery {
+                    include_revoked: true,
+                },
+            })
+            .expect("Failed to list oauth2 client sessions");
+
+        assert_eq!(
+            clients,
+            sorted(vec![
+                client_a.clone(),
+                client_b.clone(),
+                client_deleted.clone()
+            ])
+        );
+
+        drop(idms_prox_read);
+
+        // The account revokes the access of a client.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let account = idms_prox_write
+            .qs_write
+            .internal_search_uuid(target_uuid)
+            .expect("Failed to find account");
+        let ident = Identity::from_impersonate_entry_readonly(account);
+
+        idms_prox_write
+            .account_revoke_oauth2_client_sessions(&RevokeOauth2ClientSessionsEvent {
+                ident: ident.clone(),
+                target: target_uuid,
+                client_id: rs_b,
+            })
+            .expect("Failed to revoke oauth2 client sessions");
+
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+
+        let clients = idms_prox_read
+            .account_list_oauth2_client_sessions(&ListOauth2ClientSessionsEvent {
+                ident: ident.clone(),
+                target: target_uuid,
+                query: Oauth2ClientSessionsQuery::default(),
+            })
+            .expect("Failed to list oauth2 client sessions");
+
+        assert_eq!(clients, vec![client_a]);
+    }
 }
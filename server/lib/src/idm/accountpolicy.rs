@@ -1,5 +1,5 @@
 use crate::prelude::*;
-use crate::value::CredentialType;
+use crate::value::{AuthType, CredentialType};
 use webauthn_rs::prelude::AttestationCaList;
 
 #[derive(Clone)]
@@ -13,6 +13,14 @@ pub(crate) struct AccountPolicy {
     limit_search_max_filter_test: Option<u64>,
     limit_search_max_results: Option<u64>,
     allow_primary_cred_fallback: Option<bool>,
+    retain_sessions_on_password_change: Option<bool>,
+    authsession_idle_timeout: Option<u32>,
+    authsession_expiry_single_factor: u32,
+    authsession_expiry_multi_factor: u32,
+    revoke_sessions_on_password_change: Option<bool>,
+    retain_current_session_on_password_change: Option<bool>,
+    authsession_issue_limit: Option<u32>,
+    authsession_issue_window: Option<u32>,
 }
 
 impl From<&EntrySealedCommitted> for Option<AccountPolicy> {
@@ -55,6 +63,29 @@ impl From<&EntrySealedCommitted> for Option<AccountPolicy> {
         let allow_primary_cred_fallback =
             val.get_ava_single_bool(Attribute::AllowPrimaryCredFallback);
 
+        let retain_sessions_on_password_change =
+            val.get_ava_single_bool(Attribute::RetainSessionsOnPasswordChange);
+
+        let authsession_idle_timeout = val.get_ava_single_uint32(Attribute::AuthSessionIdleTimeout);
+
+        let authsession_expiry_single_factor = val
+            .get_ava_single_uint32(Attribute::AuthSessionExpirySingleFactor)
+            .unwrap_or(MAXIMUM_AUTH_SESSION_EXPIRY_SINGLE_FACTOR);
+
+        let authsession_expiry_multi_factor = val
+            .get_ava_single_uint32(Attribute::AuthSessionExpiryMultiFactor)
+            .unwrap_or(MAXIMUM_AUTH_SESSION_EXPIRY);
+
+        let revoke_sessions_on_password_change =
+            val.get_ava_single_bool(Attribute::RevokeSessionsOnPasswordChange);
+
+        let retain_current_session_on_password_change =
+            val.get_ava_single_bool(Attribute::RetainCurrentSessionOnPasswordChange);
+
+        let authsession_issue_limit = val.get_ava_single_uint32(Attribute::AuthSessionIssueLimit);
+
+        let authsession_issue_window = val.get_ava_single_uint32(Attribute::AuthSessionIssueWindow);
+
         Some(AccountPolicy {
             privilege_expiry,
             authsession_expiry,
@@ -64,6 +95,14 @@ impl From<&EntrySealedCommitted> for Option<AccountPolicy> {
             limit_search_max_filter_test,
             limit_search_max_results,
             allow_primary_cred_fallback,
+            retain_sessions_on_password_change,
+            authsession_idle_timeout,
+            authsession_expiry_single_factor,
+            authsession_expiry_multi_factor,
+            revoke_sessions_on_password_change,
+            retain_current_session_on_password_change,
+            authsession_issue_limit,
+            authsession_issue_window,
         })
     }
 }
@@ -79,6 +118,14 @@ pub(crate) struct ResolvedAccountPolicy {
     limit_search_max_filter_test: Option<u64>,
     limit_search_max_results: Option<u64>,
     allow_primary_cred_fallback: Option<bool>,
+    retain_sessions_on_password_change: Option<bool>,
+    authsession_idle_timeout: Option<u32>,
+    authsession_expiry_single_factor: u32,
+    authsession_expiry_multi_factor: u32,
+    revoke_sessions_on_password_change: Option<bool>,
+    retain_current_session_on_password_change: Option<bool>,
+    authsession_issue_limit: Option<u32>,
+    authsession_issue_window: Option<u32>,
 }
 
 impl ResolvedAccountPolicy {
@@ -93,6 +140,14 @@ impl ResolvedAccountPolicy {
             limit_search_max_filter_test: Some(DEFAULT_LIMIT_SEARCH_MAX_FILTER_TEST),
             limit_search_max_results: Some(DEFAULT_LIMIT_SEARCH_MAX_RESULTS),
             allow_primary_cred_fallback: None,
+            retain_sessions_on_password_change: None,
+            authsession_idle_timeout: None,
+            authsession_expiry_single_factor: MAXIMUM_AUTH_SESSION_EXPIRY_SINGLE_FACTOR,
+            authsession_expiry_multi_factor: MAXIMUM_AUTH_SESSION_EXPIRY,
+            revoke_sessions_on_password_change: None,
+            retain_current_session_on_password_change: None,
+            authsession_issue_limit: None,
+            authsession_issue_window: None,
         }
     }
 
@@ -110,6 +165,14 @@ impl ResolvedAccountPolicy {
             limit_search_max_filter_test: None,
             limit_search_max_results: None,
             allow_primary_cred_fallback: None,
+            retain_sessions_on_password_change: None,
+            authsession_idle_timeout: None,
+            authsession_expiry_single_factor: MAXIMUM_AUTH_SESSION_EXPIRY_SINGLE_FACTOR,
+            authsession_expiry_multi_factor: MAXIMUM_AUTH_SESSION_EXPIRY,
+            revoke_sessions_on_password_change: None,
+            retain_current_session_on_password_change: None,
+            authsession_issue_limit: None,
+            authsession_issue_window: None,
         };
 
         iter.for_each(|acc_pol| {
@@ -123,6 +186,19 @@ impl ResolvedAccountPolicy {
                 accumulate.authsession_expiry = acc_pol.authsession_expiry
             }
 
+            // Take the smaller expiry for each class of auth type
+            if acc_pol.authsession_expiry_single_factor
+                < accumulate.authsession_expiry_single_factor
+            {
+                accumulate.authsession_expiry_single_factor =
+                    acc_pol.authsession_expiry_single_factor
+            }
+
+            if acc_pol.authsession_expiry_multi_factor < accumulate.authsession_expiry_multi_factor
+            {
+                accumulate.authsession_expiry_multi_factor = acc_pol.authsession_expiry_multi_factor
+            }
+
             // Take larger pw min len
             if acc_pol.pw_min_length > accumulate.pw_min_length {
                 accumulate.pw_min_length = acc_pol.pw_min_length
@@ -168,6 +244,62 @@ impl ResolvedAccountPolicy {
                         None => Some(allow_primary_cred_fallback),
                     };
             }
+
+            // Sessions are only retained if every policy permits it.
+            if let Some(retain) = acc_pol.retain_sessions_on_password_change {
+                accumulate.retain_sessions_on_password_change =
+                    match accumulate.retain_sessions_on_password_change {
+                        Some(acc_retain) => Some(retain && acc_retain),
+                        None => Some(retain),
+                    };
+            }
+
+            // Sessions are revoked if any policy requires it.
+            if let Some(revoke) = acc_pol.revoke_sessions_on_password_change {
+                accumulate.revoke_sessions_on_password_change =
+                    match accumulate.revoke_sessions_on_password_change {
+                        Some(acc_revoke) => Some(revoke || acc_revoke),
+                        None => Some(revoke),
+                    };
+            }
+
+            // The current session is only retained if every policy permits it.
+            if let Some(retain) = acc_pol.retain_current_session_on_password_change {
+                accumulate.retain_current_session_on_password_change =
+                    match accumulate.retain_current_session_on_password_change {
+                        Some(acc_retain) => Some(retain && acc_retain),
+                        None => Some(retain),
+                    };
+            }
+
+            // Take the smaller issue limit over the longer window
+            if let Some(pol_limit) = acc_pol.authsession_issue_limit {
+                if accumulate
+                    .authsession_issue_limit
+                    .is_none_or(|acc_limit| pol_limit < acc_limit)
+                {
+                    accumulate.authsession_issue_limit = Some(pol_limit);
+                }
+            }
+
+            if let Some(pol_window) = acc_pol.authsession_issue_window {
+                if accumulate
+                    .authsession_issue_window
+                    .is_none_or(|acc_window| pol_window > acc_window)
+                {
+                    accumulate.authsession_issue_window = Some(pol_window);
+                }
+            }
+
+            // Take the smaller idle timeout
+            if let Some(pol_idle) = acc_pol.authsession_idle_timeout {
+                if accumulate
+                    .authsession_idle_timeout
+                    .is_none_or(|acc_idle| pol_idle < acc_idle)
+                {
+                    accumulate.authsession_idle_timeout = Some(pol_idle);
+                }
+            }
         });
 
         accumulate
@@ -177,8 +309,26 @@ impl ResolvedAccountPolicy {
         self.privilege_expiry
     }
 
-    pub(crate) fn authsession_expiry(&self) -> u32 {
-        self.authsession_expiry
+    /// The expiry of a session established with `auth_type`. This is the lesser of the
+    /// session expiry and the expiry of the class of auth type, so that sessions with weaker
+    /// authentication can be made to expire sooner.
+    pub(crate) fn authsession_expiry_for(&self, auth_type: AuthType) -> u32 {
+        let class_expiry = match auth_type {
+            AuthType::Password | AuthType::GeneratedPassword | AuthType::LdapPassword => {
+                self.authsession_expiry_single_factor
+            }
+            AuthType::PasswordTotp
+            | AuthType::PasswordBackupCode
+            | AuthType::PasswordSecurityKey => self.authsession_expiry_multi_factor,
+            AuthType::Anonymous
+            | AuthType::Passkey
+            | AuthType::AttestedPasskey
+            | AuthType::OAuth2Trust
+            | AuthType::ServiceAccount
+            | AuthType::TokenExchange => self.authsession_expiry,
+        };
+
+        std::cmp::min(self.authsession_expiry, class_expiry)
     }
 
     pub(crate) fn pw_min_length(&self) -> u32 {
@@ -204,11 +354,39 @@ impl ResolvedAccountPolicy {
     pub(crate) fn allow_primary_cred_fallback(&self) -> Option<bool> {
         self.allow_primary_cred_fallback
     }
+
+    pub(crate) fn retain_sessions_on_password_change(&self) -> Option<bool> {
+        self.retain_sessions_on_password_change
+    }
+
+    pub(crate) fn authsession_idle_timeout(&self) -> Option<u32> {
+        self.authsession_idle_timeout
+    }
+
+    pub(crate) fn revoke_sessions_on_password_change(&self) -> Option<bool> {
+        self.revoke_sessions_on_password_change
+    }
+
+    pub(crate) fn retain_current_session_on_password_change(&self) -> Option<bool> {
+        self.retain_current_session_on_password_change
+    }
+
+    /// The number of sessions that may be issued to the account within
+    /// [`Self::authsession_issue_window`] before further authentication is refused.
+    pub(crate) fn authsession_issue_limit(&self) -> u32 {
+        self.authsession_issue_limit
+            .unwrap_or(DEFAULT_AUTH_SESSION_ISSUE_LIMIT)
+    }
+
+    pub(crate) fn authsession_issue_window(&self) -> u32 {
+        self.authsession_issue_window
+            .unwrap_or(DEFAULT_AUTH_SESSION_ISSUE_WINDOW)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{AccountPolicy, CredentialType, ResolvedAccountPolicy};
+    use super::{AccountPolicy, AuthType, CredentialType, ResolvedAccountPolicy};
     use crate::prelude::*;
     use webauthn_rs_core::proto::AttestationCaListBuilder;
 
@@ -285,6 +463,14 @@ jAGGiQIwHFj+dJZYUJR786osByBelJYsVZd2GbHQu209b5RCmGQ21gpSAk9QZW4B
             limit_search_max_filter_test: Some(10),
             limit_search_max_results: Some(10),
             allow_primary_cred_fallback: None,
+            retain_sessions_on_password_change: Some(true),
+            authsession_idle_timeout: Some(600),
+            authsession_expiry_single_factor: 30,
+            authsession_expiry_multi_factor: 80,
+            revoke_sessions_on_password_change: Some(false),
+            retain_current_session_on_password_change: Some(true),
+            authsession_issue_limit: Some(10),
+            authsession_issue_window: Some(60),
         };
 
         let mut att_ca_builder = AttestationCaListBuilder::new();
@@ -307,17 +493,40 @@ jAGGiQIwHFj+dJZYUJR786osByBelJYsVZd2GbHQu209b5RCmGQ21gpSAk9QZW4B
             limit_search_max_filter_test: Some(5),
             limit_search_max_results: Some(15),
             allow_primary_cred_fallback: Some(false),
+            retain_sessions_on_password_change: Some(false),
+            authsession_idle_timeout: None,
+            authsession_expiry_single_factor: MAXIMUM_AUTH_SESSION_EXPIRY_SINGLE_FACTOR,
+            authsession_expiry_multi_factor: 40,
+            revoke_sessions_on_password_change: Some(true),
+            retain_current_session_on_password_change: Some(false),
+            authsession_issue_limit: Some(20),
+            authsession_issue_window: Some(120),
         };
 
         let rap = ResolvedAccountPolicy::fold_from([policy_a, policy_b].into_iter());
 
         assert_eq!(rap.privilege_expiry(), 100);
-        assert_eq!(rap.authsession_expiry(), 50);
+        assert_eq!(rap.authsession_expiry, 50);
         assert_eq!(rap.pw_min_length(), 15);
         assert_eq!(rap.credential_policy, CredentialType::Passkey);
         assert_eq!(rap.limit_search_max_results(), Some(15));
         assert_eq!(rap.limit_search_max_filter_test(), Some(10));
         assert_eq!(rap.allow_primary_cred_fallback(), Some(false));
+        assert_eq!(rap.retain_sessions_on_password_change(), Some(false));
+        assert_eq!(rap.authsession_idle_timeout(), Some(600));
+        assert_eq!(rap.revoke_sessions_on_password_change(), Some(true));
+        assert_eq!(rap.retain_current_session_on_password_change(), Some(false));
+        assert_eq!(rap.authsession_issue_limit(), 10);
+        assert_eq!(rap.authsession_issue_window(), 120);
+        assert_eq!(rap.authsession_expiry_for(AuthType::Password), 30);
+        assert_eq!(rap.authsession_expiry_for(AuthType::GeneratedPassword), 30);
+        assert_eq!(rap.authsession_expiry_for(AuthType::PasswordTotp), 40);
+        assert_eq!(
+            rap.authsession_expiry_for(AuthType::PasswordSecurityKey),
+            40
+        );
+        assert_eq!(rap.authsession_expiry_for(AuthType::Passkey), 50);
+        assert_eq!(rap.authsession_expiry_for(AuthType::AttestedPasskey), 50);
 
         let mut att_ca_builder = AttestationCaListBuilder::new();
 
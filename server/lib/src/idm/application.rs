@@ -640,11 +640,13 @@ mod tests {
             .is_ok());
 
         // Within gracewindow?
-        // This is okay, because we are within the gracewindow.
-        let ident = idms_prox_write
-            .validate_client_auth_info_to_ident(api_token.clone().into(), ct)
-            .expect("Unable to verify api token.");
-        assert_eq!(ident.get_uuid(), test_entry_uuid);
+        // The token is revoked rather than removed, so the gracewindow doesn't apply.
+        assert!(
+            idms_prox_write
+                .validate_client_auth_info_to_ident(api_token.clone().into(), ct)
+                .expect_err("Should not succeed")
+                == OperationError::SessionExpired
+        );
 
         // Past gracewindow?
         assert!(
@@ -20,6 +20,30 @@ impl From<Source> for AuditSource {
     }
 }
 
+/// The attribute that holds a session or token which changed state.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AuditSessionKind {
+    UserAuthToken,
+    OAuth2,
+    ApiToken,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AuditSessionTransition {
+    Created,
+    Revoked,
+    /// The session was revoked after it had passed its expiry.
+    Expired,
+    /// The session was removed from the entry, such as when trimmed to the session maximum.
+    Removed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuditSession {
+    pub entry: Uuid,
+    pub id: Uuid,
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum AuditEvent {
     AuthenticationDenied {
@@ -29,4 +53,13 @@ pub enum AuditEvent {
         #[serde(with = "time::serde::timestamp")]
         time: OffsetDateTime,
     },
+    /// Sessions that made the same transition in a single operation. Bulk operations
+    /// are summarised into one event rather than an event per session.
+    SessionTransition {
+        kind: AuditSessionKind,
+        transition: AuditSessionTransition,
+        actor: IdentityId,
+        cid: Cid,
+        sessions: Vec<AuditSession>,
+    },
 }
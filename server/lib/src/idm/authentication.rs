@@ -20,6 +20,7 @@ pub enum AuthStep {
         username: String,
         issue: AuthIssueSession,
         privileged: bool,
+        api_only: bool,
     },
     Begin(AuthMech),
     Cred(AuthCredential),
@@ -33,10 +34,12 @@ impl From<ProtoAuthStep> for AuthStep {
                 username,
                 issue,
                 privileged,
+                api_only,
             } => Self::Init2 {
                 username,
                 issue,
                 privileged,
+                api_only,
             },
             ProtoAuthStep::Begin(mech) => Self::Begin(mech),
             ProtoAuthStep::Cred(proto_cred) => Self::Cred(AuthCredential::from(proto_cred)),
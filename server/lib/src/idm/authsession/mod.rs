@@ -50,11 +50,13 @@ const BAD_CREDENTIALS: &str = "invalid credential message";
 const ACCOUNT_EXPIRED: &str = "account expired";
 const PW_BADLIST_MSG: &str = "password is in badlist";
 const BAD_OAUTH2_CSRF_STATE_MSG: &str = "invalid oauth2 csrf state";
+const AUTH_DENIED_MSG: &str = "authentication denied";
 
 #[derive(Debug, Clone)]
 enum AuthIntent {
     InitialAuth {
         privileged: bool,
+        api_only: bool,
     },
     Reauth {
         read_write: bool,
@@ -1124,6 +1126,9 @@ pub(crate) struct AuthSession {
 
     // The cryptographic provider to encrypt or sign anything in this operation.
     key_object: Arc<KeyObject>,
+
+    // Have too many sessions recently been issued to this account to issue another?
+    issue_limited: bool,
 }
 
 impl AuthSession {
@@ -1131,12 +1136,29 @@ impl AuthSession {
         &self.account
     }
 
+    pub(crate) fn account_policy(&self) -> &ResolvedAccountPolicy {
+        &self.account_policy
+    }
+
+    /// Refuse to issue a new session if the credentials are valid, as too many sessions
+    /// were recently issued to this account. This has no effect on a re-authentication as
+    /// that re-issues the existing session.
+    pub(crate) fn set_issue_limited(&mut self, issue_limited: bool) {
+        self.issue_limited = issue_limited;
+    }
+
+    /// If the session was denied despite valid credentials, due to the issue limit.
+    pub(crate) fn is_denied_by_issue_limit(&self) -> bool {
+        matches!(self.state, AuthSessionState::Denied(AUTH_DENIED_MSG))
+    }
+
     /// Create a new auth session, based on the available credential handlers of the account.
     /// the session is a whole encapsulated unit of what we need to proceed, so that subsequent
     /// or interleved write operations do not cause inconsistency in this process.
     pub fn new(
         asd: AuthSessionData<'_>,
         privileged: bool,
+        api_only: bool,
         key_object: Arc<KeyObject>,
     ) -> (Option<Self>, AuthState) {
         // During this setup, determine the credential handler that we'll be using
@@ -1250,9 +1272,13 @@ impl AuthSession {
                 account_policy: asd.account_policy,
                 state,
                 issue: asd.issue,
-                intent: AuthIntent::InitialAuth { privileged },
+                intent: AuthIntent::InitialAuth {
+                    privileged,
+                    api_only,
+                },
                 source: asd.client_auth_info.source,
                 key_object,
+                issue_limited: false,
             };
             // Get the set of mechanisms that can proceed. This is tied
             // to the session so that it can mutate state and have progression
@@ -1369,7 +1395,11 @@ impl AuthSession {
                         }
                     }
                 }
-                AuthType::Anonymous | AuthType::OAuth2Trust => {}
+                AuthType::Anonymous
+                | AuthType::OAuth2Trust
+                | AuthType::ServiceAccount
+                | AuthType::LdapPassword
+                | AuthType::TokenExchange => {}
             }
 
             // Did anything get set-up?
@@ -1414,6 +1444,7 @@ impl AuthSession {
                     },
                     source: asd.client_auth_info.source,
                     key_object,
+                    issue_limited: false,
                 };
 
                 (Some(auth_session), next_auth_state)
@@ -1538,6 +1569,18 @@ impl AuthSession {
                     webauthn,
                     pw_badlist,
                 ) {
+                    CredState::Success { .. }
+                        if self.issue_limited
+                            && matches!(self.intent, AuthIntent::InitialAuth { .. }) =>
+                    {
+                        // The credentials are valid, but we don't reveal why no session
+                        // is issued beyond a generic denial.
+                        security_info!("Refusing to issue session - session issue limit reached");
+                        (
+                            Some(AuthSessionState::Denied(AUTH_DENIED_MSG)),
+                            Ok(AuthState::Denied(AUTH_DENIED_MSG.to_string())),
+                        )
+                    }
                     CredState::Success {
                         auth_type,
                         cred_id,
@@ -1627,20 +1670,38 @@ impl AuthSession {
     ) -> Result<UserAuthToken, OperationError> {
         security_debug!("Successful cred handling");
         match self.intent {
-            AuthIntent::InitialAuth { privileged } => {
+            AuthIntent::InitialAuth {
+                privileged,
+                api_only,
+            } => {
                 let session_id = Uuid::new_v4();
                 // We need to actually work this out better, and then
                 // pass it to to_userauthtoken
                 let scope = match auth_type {
                     AuthType::Anonymous | AuthType::OAuth2Trust => SessionScope::ReadOnly,
-                    AuthType::GeneratedPassword => SessionScope::ReadWrite,
+                    AuthType::LdapPassword | AuthType::TokenExchange => {
+                        error!(
+                            ?auth_type,
+                            "AuthType is not issued by an auth session. Rejecting"
+                        );
+                        return Err(OperationError::InvalidState);
+                    }
+                    AuthType::GeneratedPassword | AuthType::ServiceAccount => {
+                        if api_only {
+                            SessionScope::ApiReadWrite
+                        } else {
+                            SessionScope::ReadWrite
+                        }
+                    }
                     AuthType::Password
                     | AuthType::PasswordTotp
                     | AuthType::PasswordBackupCode
                     | AuthType::PasswordSecurityKey
                     | AuthType::Passkey
                     | AuthType::AttestedPasskey => {
-                        if privileged {
+                        if api_only {
+                            SessionScope::ApiReadWrite
+                        } else if privileged {
                             SessionScope::ReadWrite
                         } else {
                             SessionScope::PrivilegeCapable
@@ -1659,7 +1720,7 @@ impl AuthSession {
 
                 let uat = self
                     .account
-                    .to_userauthtoken(session_id, scope, time, &self.account_policy)
+                    .to_userauthtoken(session_id, scope, auth_type, time, &self.account_policy)
                     .ok_or(OperationError::AU0004UserAuthTokenInvalid)?;
 
                 // Queue the session info write.
@@ -1671,6 +1732,9 @@ impl AuthSession {
                     AuthType::Anonymous => {
                         // Skip - these sessions are not validated by session id.
                     }
+                    AuthType::LdapPassword | AuthType::TokenExchange => {
+                        // Skip - these were rejected when selecting the scope.
+                    }
                     AuthType::Password
                     | AuthType::GeneratedPassword
                     | AuthType::PasswordTotp
@@ -1678,7 +1742,8 @@ impl AuthSession {
                     | AuthType::PasswordSecurityKey
                     | AuthType::Passkey
                     | AuthType::AttestedPasskey
-                    | AuthType::OAuth2Trust => {
+                    | AuthType::OAuth2Trust
+                    | AuthType::ServiceAccount => {
                         trace!("⚠️   Queued AuthSessionRecord for {}", self.account.uuid);
                         async_tx.send(DelayedAction::AuthSessionRecord(AuthSessionRecord {
                             target_uuid: self.account.uuid,
@@ -1691,6 +1756,9 @@ impl AuthSession {
                             scope,
                             type_: auth_type,
                             ext_metadata,
+                            // Service accounts are machines, whichever credential they
+                            // authenticated with.
+                            service_account: self.account.is_service_account(),
                         }))
                         .map_err(|e| {
                             debug!(?e, "queue failure");
@@ -1710,7 +1778,12 @@ impl AuthSession {
                 // Sanity check - We have already been really strict about what session types
                 // can actually trigger a re-auth, but we recheck here for paranoia!
                 let scope = match auth_type {
-                    AuthType::Anonymous | AuthType::GeneratedPassword | AuthType::OAuth2Trust => {
+                    AuthType::Anonymous
+                    | AuthType::GeneratedPassword
+                    | AuthType::OAuth2Trust
+                    | AuthType::ServiceAccount
+                    | AuthType::LdapPassword
+                    | AuthType::TokenExchange => {
                         error!("AuthType used in Reauth is not valid for session re-issuance. Rejecting");
                         return Err(OperationError::AU0006CredentialMayNotReauthenticate);
                     }
@@ -1772,12 +1845,13 @@ mod tests {
         AuthSession, AuthSessionData, BAD_AUTH_TYPE_MSG, BAD_BACKUPCODE_MSG, BAD_PASSWORD_MSG,
         BAD_TOTP_MSG, BAD_WEBAUTHN_MSG, PW_BADLIST_MSG,
     };
-    use crate::idm::delayed::DelayedAction;
+    use crate::idm::delayed::{AuthSessionRecord, DelayedAction};
     use crate::idm::oauth2_client::OAuth2ClientProvider;
     use crate::migration_data::{BUILTIN_ACCOUNT_ANONYMOUS, BUILTIN_ACCOUNT_TEST_PERSON};
     use crate::prelude::*;
     use crate::server::keys::KeyObjectInternal;
     use crate::utils::readable_password_from_random;
+    use crate::value::AuthType;
     use compact_jwt::{dangernoverify::JwsDangerReleaseWithoutVerify, JwsVerifier};
     use hashbrown::HashSet;
     use kanidm_lib_crypto::CryptoPolicy;
@@ -1825,7 +1899,7 @@ mod tests {
         };
 
         let key_object = KeyObjectInternal::new_test();
-        let (session, state) = AuthSession::new(asd, false, key_object);
+        let (session, state) = AuthSession::new(asd, false, false, key_object);
         if let AuthState::Choose(auth_mechs) = state {
             assert!(auth_mechs.iter().any(|x| matches!(x, AuthMech::Anonymous)));
         } else {
@@ -1863,7 +1937,7 @@ mod tests {
                 oauth2_client_provider: None,
             };
             let key_object = KeyObjectInternal::new_test();
-            let (session, state) = AuthSession::new(asd, $privileged, key_object);
+            let (session, state) = AuthSession::new(asd, $privileged, false, key_object);
             let mut session = session.unwrap();
 
             if let AuthState::Choose(auth_mechs) = state {
@@ -1988,6 +2062,54 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_idm_authsession_service_account_auth_type() {
+        sketching::test_init();
+        let webauthn = create_webauthn();
+        let mut account: Account = BUILTIN_ACCOUNT_TEST_PERSON.clone().into();
+        account.service_account = true;
+        let p = CryptoPolicy::minimum();
+        let cred =
+            Credential::new_password_only(&p, "test_password", OffsetDateTime::UNIX_EPOCH).unwrap();
+        account.primary = Some(cred);
+
+        let (async_tx, mut async_rx) = unbounded();
+        let (audit_tx, mut audit_rx) = unbounded();
+
+        let (mut session, pw_badlist_cache) =
+            start_password_session!(&mut audit, account, &webauthn, false);
+
+        let attempt = AuthCredential::Password("test_password".to_string());
+        match session.validate_creds(
+            &attempt,
+            Duration::from_secs(0),
+            &async_tx,
+            &audit_tx,
+            &webauthn,
+            &pw_badlist_cache,
+        ) {
+            Ok(AuthState::Success(_, AuthIssueSession::Token)) => {}
+            _ => panic!(),
+        };
+
+        // The session is recorded as a machine session, but keeps the credential type and
+        // scope that it authenticated with.
+        match async_rx.blocking_recv() {
+            Some(DelayedAction::AuthSessionRecord(AuthSessionRecord {
+                type_: AuthType::Password,
+                scope: SessionScope::PrivilegeCapable,
+                service_account: true,
+                ..
+            })) => {}
+            _ => panic!("Oh no"),
+        }
+
+        drop(async_tx);
+        assert!(async_rx.blocking_recv().is_none());
+        drop(audit_tx);
+        assert!(audit_rx.blocking_recv().is_none());
+    }
+
     #[test]
     fn test_idm_authsession_simple_password_badlist() {
         sketching::test_init();
@@ -2046,7 +2168,7 @@ mod tests {
             oauth2_client_provider: None,
         };
         let key_object = KeyObjectInternal::new_test();
-        let (session, state) = AuthSession::new(asd, false, key_object);
+        let (session, state) = AuthSession::new(asd, false, false, key_object);
         let mut session = session.expect("Session was unable to be created.");
 
         if let AuthState::Choose(auth_mechs) = state {
@@ -2087,7 +2209,7 @@ mod tests {
             oauth2_client_provider: None,
         };
         let key_object = KeyObjectInternal::new_test();
-        let (session, state) = AuthSession::new(asd, false, key_object);
+        let (session, state) = AuthSession::new(asd, false, false, key_object);
         let mut session = session.expect("Session was unable to be created.");
 
         if let AuthState::Choose(auth_mechs) = state {
@@ -2133,7 +2255,7 @@ mod tests {
             oauth2_client_provider: None,
         };
         let key_object = KeyObjectInternal::new_test();
-        let (session, state) = AuthSession::new(asd, false, key_object);
+        let (session, state) = AuthSession::new(asd, false, false, key_object);
         let mut session = session.expect("Session was unable to be created.");
 
         if let AuthState::Choose(auth_mechs) = state {
@@ -2426,7 +2548,7 @@ mod tests {
                 oauth2_client_provider: None,
             };
             let key_object = KeyObjectInternal::new_test();
-            let (session, state) = AuthSession::new(asd, false, key_object);
+            let (session, state) = AuthSession::new(asd, false, false, key_object);
             let mut session = session.unwrap();
 
             if let AuthState::Choose(auth_mechs) = state {
@@ -3476,7 +3598,7 @@ mod tests {
         };
         let key_object = KeyObjectInternal::new_test();
 
-        let (session, state) = AuthSession::new(asd, privileged, key_object);
+        let (session, state) = AuthSession::new(asd, privileged, false, key_object);
 
         trace!(?state);
 
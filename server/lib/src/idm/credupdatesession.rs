@@ -1,7 +1,7 @@
 use super::accountpolicy::ResolvedAccountPolicy;
 use crate::credential::totp::{Totp, TOTP_DEFAULT_STEP};
 use crate::credential::{BackupCodes, Credential};
-use crate::idm::account::Account;
+use crate::idm::account::{sessions_to_revoke, Account};
 use crate::idm::server::{IdmServerCredUpdateTransaction, IdmServerProxyWriteTransaction};
 use crate::prelude::*;
 use crate::server::access::Access;
@@ -136,6 +136,9 @@ pub(crate) struct CredentialUpdateSession {
     resolved_account_policy: ResolvedAccountPolicy,
     // What intent was used to initiate this session.
     intent_token_id: Option<String>,
+    // The session of the account that initiated this session, if it is updating its
+    // own credentials.
+    initiating_session_id: Option<Uuid>,
 
     // Is there an extertal credential portal?
     ext_cred_portal: CUExtPortal,
@@ -188,6 +191,7 @@ impl fmt::Debug for CredentialUpdateSession {
             .field("account.unix", &self.account.unix_extn().is_some())
             .field("resolved_account_policy", &self.resolved_account_policy)
             .field("intent_token_id", &self.intent_token_id)
+            .field("initiating_session_id", &self.initiating_session_id)
             .field("primary.detail()", &primary)
             .field("primary.state", &self.primary_state)
             .field("passkeys.list()", &passkeys)
@@ -572,6 +576,29 @@ impl InitCredentialUpdateEvent {
     }
 }
 
+/// The modifications that revoke every session of this account along with the oauth2
+/// sessions they issued, other than `retained_session_id` and its oauth2 sessions. Sessions
+/// that are already revoked are unchanged, so they keep the cid of their revocation.
+fn revoke_sessions_modlist(
+    entry: &EntrySealedCommitted,
+    retained_session_id: Option<Uuid>,
+) -> Vec<Modify> {
+    let (uat_revoke, oauth2_revoke) = sessions_to_revoke(entry, retained_session_id);
+
+    uat_revoke
+        .into_iter()
+        .map(|session_id| {
+            Modify::Removed(
+                Attribute::UserAuthTokenSession,
+                PartialValue::Refer(session_id),
+            )
+        })
+        .chain(oauth2_revoke.into_iter().map(|session_id| {
+            Modify::Removed(Attribute::OAuth2Session, PartialValue::Refer(session_id))
+        }))
+        .collect()
+}
+
 impl IdmServerProxyWriteTransaction<'_> {
     fn validate_init_credential_update(
         &mut self,
@@ -792,6 +819,7 @@ impl IdmServerProxyWriteTransaction<'_> {
         &mut self,
         sessionid: Uuid,
         intent_token_id: Option<String>,
+        initiating_session_id: Option<Uuid>,
         account: Account,
         resolved_account_policy: ResolvedAccountPolicy,
         perms: CredUpdateSessionPerms,
@@ -933,6 +961,7 @@ impl IdmServerProxyWriteTransaction<'_> {
             resolved_account_policy,
             issuer,
             intent_token_id,
+            initiating_session_id,
             ext_cred_portal,
             primary,
             primary_state,
@@ -1425,6 +1454,7 @@ impl IdmServerProxyWriteTransaction<'_> {
         self.create_credupdate_session(
             session_id,
             Some(intent_id),
+            None,
             account,
             resolved_account_policy,
             perms,
@@ -1446,10 +1476,15 @@ impl IdmServerProxyWriteTransaction<'_> {
         // than needing to do calculations.
         let sessionid = uuid_from_duration(current_time + MAXIMUM_CRED_UPDATE_TTL, self.sid);
 
+        // Only an account updating its own credentials has a session that may be retained.
+        let initiating_session_id =
+            (event.ident.get_uuid() == event.target).then(|| event.ident.get_session_id());
+
         // Build the cred update session.
         self.create_credupdate_session(
             sessionid,
             None,
+            initiating_session_id,
             account,
             resolved_account_policy,
             perms,
@@ -1646,6 +1681,38 @@ impl IdmServerProxyWriteTransaction<'_> {
 
         cred_changed.get_or_insert(OffsetDateTime::UNIX_EPOCH);
 
+        // If policy requires it, a changed password revokes every session of the account. This
+        // is part of the same modification so that the revocations share the cid of the change.
+        // The credential id rotates on any change to the credential, so the password itself
+        // is compared to ignore changes to only the other factors.
+        let password_changed = matches!(session.primary_state, CredentialState::Modifiable)
+            && session.primary.as_ref().is_some_and(|ncred| {
+                account
+                    .primary
+                    .as_ref()
+                    .is_none_or(|cred| cred.password_differs(ncred))
+            });
+
+        let revoke_sessions = password_changed
+            && session
+                .resolved_account_policy
+                .revoke_sessions_on_password_change()
+                == Some(true);
+
+        let retained_session_id = session.initiating_session_id.filter(|_| {
+            revoke_sessions
+                && session
+                    .resolved_account_policy
+                    .retain_current_session_on_password_change()
+                    == Some(true)
+        });
+
+        if revoke_sessions {
+            for m in revoke_sessions_modlist(entry.as_ref(), retained_session_id) {
+                modlist.push_mod(m);
+            }
+        }
+
         if let Some(timestamp) = cred_changed {
             modlist.push_mod(Modify::Purged(Attribute::PasswordChangedTime));
             modlist.push_mod(Modify::Present(
@@ -1711,19 +1778,26 @@ impl IdmServerProxyWriteTransaction<'_> {
             trace!("no changes to apply");
             Ok(())
         } else {
-            self.qs_write
-                .internal_modify(
-                    // Filter as executed
-                    &filter!(f_eq(
-                        Attribute::Uuid,
-                        PartialValue::Uuid(session.account.uuid)
-                    )),
-                    &modlist,
-                )
-                .map_err(|e| {
-                    request_error!(error = ?e);
-                    e
-                })
+            // The change is made on behalf of a retained session, so that it moves to the new
+            // password rather than being revoked with the password it was issued by.
+            let ident = retained_session_id
+                .map_or_else(Identity::from_internal, Identity::from_internal_for_session);
+
+            ModifyEvent::from_internal_parts(
+                ident,
+                &modlist,
+                // Filter as executed
+                &filter!(f_eq(
+                    Attribute::Uuid,
+                    PartialValue::Uuid(session.account.uuid)
+                )),
+                &self.qs_write,
+            )
+            .and_then(|me| self.qs_write.modify(&me))
+            .map_err(|e| {
+                request_error!(error = ?e);
+                e
+            })
         }
     }
 
@@ -2863,10 +2937,12 @@ mod tests {
     use crate::idm::event::{
         AuthEvent, AuthResult, RegenerateRadiusSecretEvent, UnixUserAuthEvent,
     };
-    use crate::idm::server::{IdmServer, IdmServerCredUpdateTransaction, IdmServerDelayed};
+    use crate::idm::server::{
+        IdmServer, IdmServerCredUpdateTransaction, IdmServerDelayed, IdmServerTransaction,
+    };
     use crate::prelude::*;
     use crate::utils::password_from_random_len;
-    use crate::value::CredentialType;
+    use crate::value::{ApiToken, CredentialType, Oauth2Session, SessionState};
     use crate::valueset::ValueSetEmailAddress;
     use compact_jwt::JwsCompact;
     use kanidm_proto::internal::{CUExtPortal, CredentialDetailType, PasswordFeedback};
@@ -3186,6 +3262,8 @@ mod tests {
                 // Process the auth session
                 let da = idms_delayed.try_recv().expect("invalid");
                 assert!(matches!(da, DelayedAction::AuthSessionRecord(_)));
+                let r = idms.delayed_action(ct, da).await;
+                assert!(r.is_ok());
 
                 Some(*token)
             }
@@ -3413,6 +3491,8 @@ mod tests {
                 // Process the auth session
                 let da = idms_delayed.try_recv().expect("invalid");
                 assert!(matches!(da, DelayedAction::AuthSessionRecord(_)));
+                let r = idms.delayed_action(ct, da).await;
+                assert!(r.is_ok());
 
                 Some(*token)
             }
@@ -5199,7 +5279,7 @@ mod tests {
 
         // This gives an auth denied because the attested passkey still exists but it no longer
         // meets criteria.
-        match idms_audit.audit_rx().try_recv() {
+        match idms_audit.try_recv_skip_sessions() {
             Ok(AuditEvent::AuthenticationDenied { .. }) => {}
             _ => panic!("Oh no"),
         }
@@ -5918,4 +5998,594 @@ mod tests {
             .expect("PasswordChangedTime should be set after switching to passkey");
         assert_eq!(pwd_changed_2, OffsetDateTime::UNIX_EPOCH + ct);
     }
+
+    #[idm_test]
+    async fn credential_update_passkey_remove_revokes_sessions(
+        idms: &IdmServer,
+        idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let test_pw = "fo3EitierohF9AelaNgiem0Ei6vup4equo1Oogeevaetehah8Tobeengae3Ci0ooh0uki";
+
+        let (cust, _) = setup_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        let origin = cutxn.get_origin().clone();
+
+        let mut wa = SoftPasskey::new(true);
+        let c_status = create_new_passkey(ct, &origin, &cutxn, &cust, &mut wa).await;
+        let pk_uuid = c_status.passkeys.first().map(|pkd| pkd.uuid).unwrap();
+
+        let _c_status = cutxn
+            .credential_primary_set_password(&cust, ct, test_pw)
+            .expect("Failed to update the primary cred password");
+
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        // Login with each credential.
+        let pk_token = check_testperson_passkey(idms, idms_delayed, &mut wa, origin.clone(), ct)
+            .await
+            .expect("Failed to auth with passkey");
+        let pw_token = check_testperson_password(idms, idms_delayed, test_pw, ct)
+            .await
+            .expect("Failed to auth with password");
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let pk_session_id = idms_prox_read
+            .validate_client_auth_info_to_ident(pk_token.clone().into(), ct)
+            .expect("Failed to validate")
+            .get_session_id();
+        drop(idms_prox_read);
+
+        // An api token created by the passkey session.
+        let sa_uuid = Uuid::new_v4();
+        let api_token_id = Uuid::new_v4();
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Name, Value::new_iname("test_service_account")),
+            (Attribute::Uuid, Value::Uuid(sa_uuid)),
+            (
+                Attribute::DisplayName,
+                Value::new_utf8s("test_service_account")
+            ),
+            (
+                Attribute::ApiTokenSession,
+                Value::ApiToken(
+                    api_token_id,
+                    ApiToken::builder(OffsetDateTime::UNIX_EPOCH + ct)
+                        .parent_session(Some(pk_session_id))
+                        .build()
+                )
+            )
+        );
+        assert!(idms_prox_write
+            .qs_write
+            .create(&CreateEvent::new_internal(vec![e1]))
+            .is_ok());
+        idms_prox_write.commit().expect("Failed to commit txn");
+
+        // Remove the passkey.
+        let (cust, _) = renew_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        let _c_status = cutxn
+            .credential_passkey_remove(&cust, ct, pk_uuid)
+            .expect("Failed to delete the passkey");
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        // Only the session of the removed passkey is revoked.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        assert!(matches!(
+            idms_prox_read.validate_client_auth_info_to_ident(pk_token.into(), ct),
+            Err(OperationError::SessionExpired)
+        ));
+        idms_prox_read
+            .validate_client_auth_info_to_ident(pw_token.into(), ct)
+            .expect("Failed to validate");
+
+        // As is the api token that it created.
+        let sa = idms_prox_read
+            .qs_read
+            .internal_search_uuid(sa_uuid)
+            .expect("failed");
+        let token = sa
+            .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+            .and_then(|tokens| tokens.get(&api_token_id))
+            .expect("api token missing");
+        assert!(matches!(token.state, SessionState::RevokedAt(_)));
+    }
+
+    #[idm_test]
+    async fn credential_update_password_change_session_policy(
+        idms: &IdmServer,
+        idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let test_pw_a = "fo3EitierohF9AelaNgiem0Ei6vup4equo1Oogeevaetehah8Tobeengae3Ci0ooh0uki";
+        let test_pw_b = "eiPh9aeP4ohleeNgaiw3Sheequ6Aiv9oozaeNg0vaem6iegh6quiekaehu8Eek1r";
+        let test_pw_c = "Aicoo9eiThaeshiel6moh2ohZ0ahghaesiequ8Eiwae7Keiw9ua4Oosh9fooFei4";
+
+        let (cust, _) = setup_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        let _c_status = cutxn
+            .credential_primary_set_password(&cust, ct, test_pw_a)
+            .expect("Failed to update the primary cred password");
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        // By default, changing the password revokes the sessions of the old password.
+        let token_a = check_testperson_password(idms, idms_delayed, test_pw_a, ct)
+            .await
+            .expect("Failed to auth with password");
+
+        let (cust, _) = renew_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        let _c_status = cutxn
+            .credential_primary_set_password(&cust, ct, test_pw_b)
+            .expect("Failed to update the primary cred password");
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        assert!(matches!(
+            idms_prox_read.validate_client_auth_info_to_ident(token_a.into(), ct),
+            Err(OperationError::SessionExpired)
+        ));
+        drop(idms_prox_read);
+
+        // When policy allows, the sessions survive the change.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                UUID_IDM_ALL_PERSONS,
+                &ModifyList::new_purge_and_set(
+                    Attribute::RetainSessionsOnPasswordChange,
+                    Value::new_bool(true),
+                ),
+            )
+            .expect("Unable to set account policy");
+        idms_prox_write.commit().expect("Failed to commit txn");
+
+        let token_b = check_testperson_password(idms, idms_delayed, test_pw_b, ct)
+            .await
+            .expect("Failed to auth with password");
+
+        let (cust, _) = renew_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        let _c_status = cutxn
+            .credential_primary_set_password(&cust, ct, test_pw_c)
+            .expect("Failed to update the primary cred password");
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        idms_prox_read
+            .validate_client_auth_info_to_ident(token_b.into(), ct)
+            .expect("Failed to validate");
+        drop(idms_prox_read);
+
+        // Changing another factor of the credential still revokes the sessions.
+        let token_c = check_testperson_password(idms, idms_delayed, test_pw_c, ct)
+            .await
+            .expect("Failed to auth with password");
+
+        let (cust, _) = renew_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        let c_status = cutxn
+            .credential_primary_init_totp(&cust, ct)
+            .expect("Failed to update the primary cred totp");
+
+        let totp_token: Totp = match c_status.mfaregstate {
+            MfaRegStateStatus::TotpCheck(secret) => Some(secret.try_into().unwrap()),
+            _ => None,
+        }
+        .expect("Unable to retrieve totp token, invalid state.");
+
+        let chal = totp_token
+            .do_totp_duration_from_epoch(&ct)
+            .expect("Failed to perform totp step");
+
+        let _c_status = cutxn
+            .credential_primary_check_totp(&cust, ct, chal, "totp")
+            .expect("Failed to update the primary cred totp");
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        assert!(matches!(
+            idms_prox_read.validate_client_auth_info_to_ident(token_c.into(), ct),
+            Err(OperationError::SessionExpired)
+        ));
+    }
+
+    #[idm_test]
+    async fn credential_update_password_reset_revokes_all_sessions(
+        idms: &IdmServer,
+        idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let test_pw_a = "fo3EitierohF9AelaNgiem0Ei6vup4equo1Oogeevaetehah8Tobeengae3Ci0ooh0uki";
+        let test_pw_b = "eiPh9aeP4ohleeNgaiw3Sheequ6Aiv9oozaeNg0vaem6iegh6quiekaehu8Eek1r";
+
+        let (cust, _) = setup_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        let origin = cutxn.get_origin().clone();
+
+        let mut wa = SoftPasskey::new(true);
+        let _c_status = create_new_passkey(ct, &origin, &cutxn, &cust, &mut wa).await;
+        let _c_status = cutxn
+            .credential_primary_set_password(&cust, ct, test_pw_a)
+            .expect("Failed to update the primary cred password");
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        // Even where the current session may be retained, an admin reset has no current
+        // session of the account.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        for attr in [
+            Attribute::RevokeSessionsOnPasswordChange,
+            Attribute::RetainCurrentSessionOnPasswordChange,
+        ] {
+            idms_prox_write
+                .qs_write
+                .internal_modify_uuid(
+                    UUID_IDM_ALL_PERSONS,
+                    &ModifyList::new_purge_and_set(attr, Value::new_bool(true)),
+                )
+                .expect("Unable to set account policy");
+        }
+        idms_prox_write.commit().expect("Failed to commit txn");
+
+        let pk_token = check_testperson_passkey(idms, idms_delayed, &mut wa, origin.clone(), ct)
+            .await
+            .expect("Failed to auth with passkey");
+        let pw_token = check_testperson_password(idms, idms_delayed, test_pw_a, ct)
+            .await
+            .expect("Failed to auth with password");
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let pk_session_id = idms_prox_read
+            .validate_client_auth_info_to_ident(pk_token.clone().into(), ct)
+            .expect("Failed to validate")
+            .get_session_id();
+        drop(idms_prox_read);
+
+        // An oauth2 session issued by the passkey session.
+        let rs_uuid = Uuid::new_v4();
+        let o2_session_id = Uuid::new_v4();
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let rs = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServer.to_value()
+            ),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServerPublic.to_value()
+            ),
+            (Attribute::Uuid, Value::Uuid(rs_uuid)),
+            (Attribute::Name, Value::new_iname("test_resource_server")),
+            (
+                Attribute::DisplayName,
+                Value::new_utf8s("test_resource_server")
+            ),
+            (
+                Attribute::OAuth2RsOriginLanding,
+                Value::new_url_s("https://demo.example.com").unwrap()
+            )
+        );
+        assert!(idms_prox_write
+            .qs_write
+            .create(&CreateEvent::new_internal(vec![rs]))
+            .is_ok());
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                TESTPERSON_UUID,
+                &ModifyList::new_append(
+                    Attribute::OAuth2Session,
+                    Value::Oauth2Session(
+                        o2_session_id,
+                        Oauth2Session {
+                            parent: Some(pk_session_id),
+                            state: SessionState::NeverExpires,
+                            issued_at: OffsetDateTime::UNIX_EPOCH + ct,
+                            rs_uuid,
+                            granted_scopes: Default::default(),
+                            has_refresh: false,
+                            last_refreshed: None,
+                            origin: None,
+                        },
+                    ),
+                ),
+            )
+            .expect("Failed to add oauth2 session");
+
+        // The admin issues an intent token for the reset.
+        let idm_admin = idms_prox_write
+            .qs_write
+            .internal_search_uuid(UUID_IDM_ADMIN)
+            .expect("failed");
+        let intent_tok = idms_prox_write
+            .init_credential_update_intent(
+                &InitCredentialUpdateIntentEvent::new_impersonate_entry(
+                    idm_admin,
+                    TESTPERSON_UUID,
+                    MINIMUM_INTENT_TTL,
+                ),
+                ct,
+            )
+            .expect("Failed to create intent token!");
+        let (cust, _) = idms_prox_write
+            .exchange_intent_credential_update(intent_tok.into(), ct)
+            .expect("Failed to exchange intent token");
+        idms_prox_write.commit().expect("Failed to commit txn");
+
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        let _c_status = cutxn
+            .credential_primary_set_password(&cust, ct, test_pw_b)
+            .expect("Failed to update the primary cred password");
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        // Every session is revoked, including that of the passkey which remains.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        for token in [pk_token, pw_token] {
+            assert!(matches!(
+                idms_prox_read.validate_client_auth_info_to_ident(token.into(), ct),
+                Err(OperationError::SessionExpired)
+            ));
+        }
+
+        // All in the same change, along with the oauth2 session of the passkey session.
+        let entry = idms_prox_read
+            .qs_read
+            .internal_search_uuid(TESTPERSON_UUID)
+            .expect("failed");
+        let states: Vec<_> = entry
+            .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+            .expect("No sessions")
+            .values()
+            .map(|session| session.state.clone())
+            .chain(
+                entry
+                    .get_ava_as_oauth2session_map(Attribute::OAuth2Session)
+                    .expect("No oauth2 sessions")
+                    .values()
+                    .map(|o2_session| o2_session.state.clone()),
+            )
+            .collect();
+        assert_eq!(states.len(), 3);
+        assert!(matches!(states[0], SessionState::RevokedAt(_)));
+        assert!(states.iter().all(|state| *state == states[0]));
+    }
+
+    #[idm_test]
+    async fn credential_update_password_change_retains_current_session(
+        idms: &IdmServer,
+        idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let test_pw_a = "fo3EitierohF9AelaNgiem0Ei6vup4equo1Oogeevaetehah8Tobeengae3Ci0ooh0uki";
+        let test_pw_b = "eiPh9aeP4ohleeNgaiw3Sheequ6Aiv9oozaeNg0vaem6iegh6quiekaehu8Eek1r";
+
+        let (cust, _) = setup_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        let _c_status = cutxn
+            .credential_primary_set_password(&cust, ct, test_pw_a)
+            .expect("Failed to update the primary cred password");
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        for attr in [
+            Attribute::RevokeSessionsOnPasswordChange,
+            Attribute::RetainCurrentSessionOnPasswordChange,
+            Attribute::RetainSessionsOnPasswordChange,
+        ] {
+            idms_prox_write
+                .qs_write
+                .internal_modify_uuid(
+                    UUID_IDM_ALL_PERSONS,
+                    &ModifyList::new_purge_and_set(attr, Value::new_bool(true)),
+                )
+                .expect("Unable to set account policy");
+        }
+        idms_prox_write.commit().expect("Failed to commit txn");
+
+        let current_token = check_testperson_password(idms, idms_delayed, test_pw_a, ct)
+            .await
+            .expect("Failed to auth with password");
+        let other_token = check_testperson_password(idms, idms_delayed, test_pw_a, ct)
+            .await
+            .expect("Failed to auth with password");
+
+        // The account changes its own password from the current session.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(current_token.clone().into(), ct)
+            .expect("Failed to validate");
+        drop(idms_prox_read);
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let (cust, _) = idms_prox_write
+            .init_credential_update(
+                &InitCredentialUpdateEvent::new(
+                    ident.project_with_scope(AccessScope::ReadWrite),
+                    TESTPERSON_UUID,
+                ),
+                ct,
+            )
+            .expect("Failed to start update");
+        idms_prox_write.commit().expect("Failed to commit txn");
+
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        let _c_status = cutxn
+            .credential_primary_set_password(&cust, ct, test_pw_b)
+            .expect("Failed to update the primary cred password");
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        // Revoking sessions takes precedence over retaining them, so only the current
+        // session remains, moved to the new password.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        idms_prox_read
+            .validate_client_auth_info_to_ident(current_token.into(), ct)
+            .expect("Failed to validate");
+        assert!(matches!(
+            idms_prox_read.validate_client_auth_info_to_ident(other_token.into(), ct),
+            Err(OperationError::SessionExpired)
+        ));
+    }
+
+    #[idm_test]
+    async fn credential_update_totp_change_keeps_sessions(
+        idms: &IdmServer,
+        idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let test_pw = "fo3EitierohF9AelaNgiem0Ei6vup4equo1Oogeevaetehah8Tobeengae3Ci0ooh0uki";
+
+        let (cust, _) = setup_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        let origin = cutxn.get_origin().clone();
+
+        let mut wa = SoftPasskey::new(true);
+        let _c_status = create_new_passkey(ct, &origin, &cutxn, &cust, &mut wa).await;
+        let _c_status = cutxn
+            .credential_primary_set_password(&cust, ct, test_pw)
+            .expect("Failed to update the primary cred password");
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                UUID_IDM_ALL_PERSONS,
+                &ModifyList::new_purge_and_set(
+                    Attribute::RevokeSessionsOnPasswordChange,
+                    Value::new_bool(true),
+                ),
+            )
+            .expect("Unable to set account policy");
+        idms_prox_write.commit().expect("Failed to commit txn");
+
+        let pk_token = check_testperson_passkey(idms, idms_delayed, &mut wa, origin.clone(), ct)
+            .await
+            .expect("Failed to auth with passkey");
+
+        // Adding totp rotates the id of the primary credential, but the password is unchanged.
+        let (cust, _) = renew_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        let c_status = cutxn
+            .credential_primary_init_totp(&cust, ct)
+            .expect("Failed to update the primary cred totp");
+
+        let totp_token: Totp = match c_status.mfaregstate {
+            MfaRegStateStatus::TotpCheck(secret) => Some(secret.try_into().unwrap()),
+            _ => None,
+        }
+        .expect("Unable to retrieve totp token, invalid state.");
+
+        let chal = totp_token
+            .do_totp_duration_from_epoch(&ct)
+            .expect("Failed to perform totp step");
+
+        let c_status = cutxn
+            .credential_primary_check_totp(&cust, ct, chal, "totp")
+            .expect("Failed to update the primary cred totp");
+        assert!(matches!(c_status.mfaregstate, MfaRegStateStatus::None));
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        // The passkey session is not revoked, as the password did not change.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        idms_prox_read
+            .validate_client_auth_info_to_ident(pk_token.into(), ct)
+            .expect("Failed to validate");
+    }
+
+    #[idm_test]
+    async fn credential_update_password_change_retains_only_current_session(
+        idms: &IdmServer,
+        idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let test_pw_a = "fo3EitierohF9AelaNgiem0Ei6vup4equo1Oogeevaetehah8Tobeengae3Ci0ooh0uki";
+        let test_pw_b = "eiPh9aeP4ohleeNgaiw3Sheequ6Aiv9oozaeNg0vaem6iegh6quiekaehu8Eek1r";
+
+        let (cust, _) = setup_test_session(idms, ct).await;
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        let _c_status = cutxn
+            .credential_primary_set_password(&cust, ct, test_pw_a)
+            .expect("Failed to update the primary cred password");
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        for attr in [
+            Attribute::RevokeSessionsOnPasswordChange,
+            Attribute::RetainCurrentSessionOnPasswordChange,
+        ] {
+            idms_prox_write
+                .qs_write
+                .internal_modify_uuid(
+                    UUID_IDM_ALL_PERSONS,
+                    &ModifyList::new_purge_and_set(attr, Value::new_bool(true)),
+                )
+                .expect("Unable to set account policy");
+        }
+        idms_prox_write.commit().expect("Failed to commit txn");
+
+        let current_token = check_testperson_password(idms, idms_delayed, test_pw_a, ct)
+            .await
+            .expect("Failed to auth with password");
+        let other_token = check_testperson_password(idms, idms_delayed, test_pw_a, ct)
+            .await
+            .expect("Failed to auth with password");
+
+        // The account changes its own password from the current session.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(current_token.clone().into(), ct)
+            .expect("Failed to validate");
+        drop(idms_prox_read);
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let (cust, _) = idms_prox_write
+            .init_credential_update(
+                &InitCredentialUpdateEvent::new(
+                    ident.project_with_scope(AccessScope::ReadWrite),
+                    TESTPERSON_UUID,
+                ),
+                ct,
+            )
+            .expect("Failed to start update");
+        idms_prox_write.commit().expect("Failed to commit txn");
+
+        let cutxn = idms.cred_update_transaction().await.unwrap();
+        let _c_status = cutxn
+            .credential_primary_set_password(&cust, ct, test_pw_b)
+            .expect("Failed to update the primary cred password");
+        drop(cutxn);
+        commit_session(idms, ct, cust).await;
+
+        // Without retaining the sessions of the replaced password, the current session
+        // is still moved to the new password.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        idms_prox_read
+            .validate_client_auth_info_to_ident(current_token.into(), ct)
+            .expect("Failed to validate");
+        assert!(matches!(
+            idms_prox_read.validate_client_auth_info_to_ident(other_token.into(), ct),
+            Err(OperationError::SessionExpired)
+        ));
+    }
 }
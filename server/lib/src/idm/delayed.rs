@@ -2,6 +2,7 @@ use crate::prelude::*;
 use crate::value::AuthType;
 use crate::value::SessionExtMetadata;
 use std::fmt;
+use std::time::Duration;
 use time::OffsetDateTime;
 use uuid::Uuid;
 use webauthn_rs::prelude::AuthenticationResult;
@@ -13,6 +14,7 @@ pub enum DelayedAction {
     WebauthnCounterIncrement(WebauthnCounterIncrement),
     BackupCodeRemoval(BackupCodeRemoval),
     AuthSessionRecord(AuthSessionRecord),
+    AuthSessionActivity(AuthSessionActivity),
 }
 
 pub struct PasswordUpgrade {
@@ -65,4 +67,15 @@ pub struct AuthSessionRecord {
     pub scope: SessionScope,
     pub type_: AuthType,
     pub ext_metadata: SessionExtMetadata,
+    pub service_account: bool,
+}
+
+/// A session with an idle timeout was used at `active_at`. If the session had already
+/// been idle for `idle_timeout` it is revoked, otherwise the activity is recorded.
+#[derive(Debug)]
+pub struct AuthSessionActivity {
+    pub target_uuid: Uuid,
+    pub session_id: Uuid,
+    pub active_at: OffsetDateTime,
+    pub idle_timeout: Duration,
 }
@@ -274,6 +274,7 @@ pub struct AuthEventStepInit {
     pub username: String,
     pub issue: AuthIssueSession,
     pub privileged: bool,
+    pub api_only: bool,
 }
 
 #[derive(Debug)]
@@ -306,6 +307,7 @@ impl AuthEventStep {
                         username,
                         issue: AuthIssueSession::Token,
                         privileged: false,
+                        api_only: false,
                     }))
                 }
             }
@@ -313,6 +315,7 @@ impl AuthEventStep {
                 username,
                 issue,
                 privileged,
+                api_only,
             } => {
                 if username.trim().is_empty() {
                     Err(OperationError::EmptyRequest)
@@ -321,6 +324,7 @@ impl AuthEventStep {
                         username,
                         issue,
                         privileged,
+                        api_only,
                     }))
                 }
             }
@@ -346,6 +350,7 @@ impl AuthEventStep {
             username: "anonymous".to_string(),
             issue: AuthIssueSession::Token,
             privileged: false,
+            api_only: false,
         })
     }
 
@@ -355,6 +360,17 @@ impl AuthEventStep {
             username: name.to_string(),
             issue: AuthIssueSession::Token,
             privileged: false,
+            api_only: false,
+        })
+    }
+
+    #[cfg(test)]
+    pub fn named_init_api_only(name: &str) -> Self {
+        AuthEventStep::Init(AuthEventStepInit {
+            username: name.to_string(),
+            issue: AuthIssueSession::Token,
+            privileged: false,
+            api_only: true,
         })
     }
 
@@ -438,6 +454,14 @@ impl AuthEvent {
         }
     }
 
+    #[cfg(test)]
+    pub fn named_init_api_only(name: &str) -> Self {
+        AuthEvent {
+            ident: None,
+            step: AuthEventStep::named_init_api_only(name),
+        }
+    }
+
     #[cfg(test)]
     pub fn begin_mech(sessionid: Uuid, mech: AuthMech) -> Self {
         AuthEvent {
@@ -875,13 +875,15 @@ mod tests {
 
     use super::{LdapServer, LdapSession};
     use crate::idm::application::GenerateApplicationPasswordEvent;
+    use crate::idm::delayed::{AuthSessionRecord, DelayedAction};
     use crate::idm::event::{LdapApplicationAuthEvent, UnixPasswordChangeEvent};
     use crate::idm::serviceaccount::GenerateApiTokenEvent;
+    use crate::value::{AuthType, SessionState};
 
     const TEST_PASSWORD: &str = "ntaoeuntnaoeuhraohuercahu😍";
 
     #[idm_test]
-    async fn test_ldap_simple_bind(idms: &IdmServer, _idms_delayed: &IdmServerDelayed) {
+    async fn test_ldap_simple_bind(idms: &IdmServer, idms_delayed: &mut IdmServerDelayed) {
         let ldaps = LdapServer::new(idms).await.expect("failed to start ldap");
 
         let mut idms_prox_write = idms.proxy_write(duration_from_epoch_now()).await.unwrap();
@@ -908,6 +910,32 @@ mod tests {
             .unwrap()
             .unwrap();
         assert_eq!(admin_t.effective_session, LdapSession::UnixBind(UUID_ADMIN));
+
+        // The bind is recorded as a session of the account.
+        let ct = duration_from_epoch_now();
+        let da = idms_delayed.try_recv().expect("invalid");
+        assert!(matches!(
+            da,
+            DelayedAction::AuthSessionRecord(AuthSessionRecord {
+                type_: AuthType::LdapPassword,
+                scope: SessionScope::ReadOnly,
+                ..
+            })
+        ));
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        assert!(idms_prox_write.process_delayedaction(&da, ct).is_ok());
+        let admin_e = idms_prox_write
+            .qs_write
+            .internal_search_uuid(UUID_ADMIN)
+            .unwrap();
+        let session = admin_e
+            .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+            .and_then(|sessions| sessions.get(&admin_t.session_id))
+            .expect("Session not recorded");
+        assert_eq!(session.type_, AuthType::LdapPassword);
+        assert!(matches!(session.state, SessionState::ExpiresAt(_)));
+        assert!(idms_prox_write.commit().is_ok());
+
         let admin_t = ldaps
             .do_bind(idms, "admin@example.com", TEST_PASSWORD)
             .await
@@ -1073,6 +1101,9 @@ mod tests {
             .is_err());
 
         assert!(ldaps.do_bind(idms, "claire", "test").await.is_err());
+
+        // Drain the session records of the remaining binds.
+        while idms_delayed.try_recv().is_ok() {}
     }
 
     #[idm_test]
@@ -5,6 +5,7 @@
 //! for operations involving OAuth2 authentication processing.
 
 use crate::idm::account::Account;
+use crate::idm::delayed::AuthSessionRecord;
 use crate::idm::server::{
     IdmServerProxyReadTransaction, IdmServerProxyWriteTransaction, IdmServerTransaction, Token,
 };
@@ -13,7 +14,9 @@ use crate::server::keys::{
     KeyId, KeyObject, KeyProvidersTransaction, KeyProvidersWriteTransaction,
 };
 use crate::utils;
-use crate::value::{Oauth2Session, OauthClaimMapJoin, SessionState, OAUTHSCOPE_RE};
+use crate::value::{
+    AuthType, Oauth2Session, OauthClaimMapJoin, SessionExtMetadata, SessionState, OAUTHSCOPE_RE,
+};
 use base64::{engine::general_purpose, Engine as _};
 pub use compact_jwt::{compact::JwkKeySet, OidcToken};
 use compact_jwt::{
@@ -188,6 +191,10 @@ struct OAuth2SessionContext {
     pub(crate) auth_time: Option<OffsetDateTime>,
     pub(crate) nonce: Option<String>,
     pub(crate) account_uuid: Uuid,
+    // Set when this context is for a refresh of an existing session.
+    pub(crate) is_refresh: bool,
+    // The redirect uri that the session was authorised against.
+    pub(crate) origin: Option<Url>,
 }
 
 // == internal state formats that we encrypt and send.
@@ -1582,6 +1589,8 @@ impl IdmServerProxyWriteTransaction<'_> {
             auth_time: code_xchg.auth_time,
             nonce: code_xchg.nonce,
             account_uuid: code_xchg.account_uuid,
+            is_refresh: false,
+            origin: Some(code_xchg.redirect_uri),
         };
 
         self.generate_access_token_response(
@@ -1672,9 +1681,16 @@ impl IdmServerProxyWriteTransaction<'_> {
 
                 // If the refresh token was issued previous to the time listed in our oauth2_session
                 // this indicates session desync / replay. We must nuke the session at this point.
+                // The session issue time is fixed, so the last refresh is the most recent issue.
                 //
                 // Need to think about how to handle this nicely give transactions.
-                if iat < oauth2_session.issued_at.unix_timestamp() {
+                let last_issued_at = oauth2_session
+                    .last_refreshed
+                    .map_or(oauth2_session.issued_at, |lr| {
+                        lr.max(oauth2_session.issued_at)
+                    });
+
+                if iat < last_issued_at.unix_timestamp() {
                     security_info!(
                         ?session_id,
                         "Attempt to reuse a refresh token detected, destroying session"
@@ -1723,6 +1739,8 @@ impl IdmServerProxyWriteTransaction<'_> {
                     auth_time,
                     nonce,
                     account_uuid,
+                    is_refresh: true,
+                    origin: None,
                 };
 
                 self.generate_access_token_response(
@@ -1822,7 +1840,7 @@ impl IdmServerProxyWriteTransaction<'_> {
         };
 
         let ident = self
-            .process_apit_to_identity(&apit, Source::Internal, entry, ct)
+            .process_apit_to_identity(&apit, Source::Internal, entry.clone(), ct)
             .map_err(|err| match err {
                 OperationError::SessionExpired | OperationError::NotAuthenticated => {
                     security_info!(
@@ -1837,13 +1855,48 @@ impl IdmServerProxyWriteTransaction<'_> {
         let (_req_scopes, granted_scopes) =
             process_requested_scopes_for_identity(o2rs, &ident, req_scopes)?;
 
+        let (_, account_policy) =
+            Account::try_from_entry_with_policy(entry.as_ref(), &mut self.qs_write)
+                .map_err(Oauth2Error::ServerError)?;
+
+        // Record the exchange as a session of the service account, derived from the api token.
+        // Revoking the token revokes this session, and with it the oauth2 session below.
+        let issued_at =
+            OffsetDateTime::UNIX_EPOCH + ct - Duration::from_nanos(ct.subsec_nanos() as u64);
+        let policy_expiry = issued_at
+            + Duration::from_secs(
+                account_policy.authsession_expiry_for(AuthType::TokenExchange) as u64,
+            );
+        let exchange_session_id = Uuid::new_v4();
+
+        self.process_authsessionrecord(&AuthSessionRecord {
+            target_uuid: apit.account_id,
+            session_id: exchange_session_id,
+            cred_id: apit.token_id,
+            label: format!("Token Exchange ({})", o2rs.name),
+            expiry: Some(
+                apit.expiry
+                    .map_or(policy_expiry, |expiry| expiry.min(policy_expiry)),
+            ),
+            issued_at,
+            issued_by: IdentityId::User(apit.account_id),
+            scope: SessionScope::ReadOnly,
+            type_: AuthType::TokenExchange,
+            ext_metadata: SessionExtMetadata::None,
+            // Only service accounts hold api tokens.
+            service_account: true,
+        })
+        .map_err(Oauth2Error::ServerError)?;
+
         let session_id = Uuid::new_v4();
-        let parent_session_id = None;
+        let parent_session_id = Some(exchange_session_id);
         let session_ctx = OAuth2SessionContext {
             // Service accounts don't have an auth time
             auth_time: None,
             account_uuid: apit.account_id,
             nonce: None,
+            is_refresh: false,
+            origin: None,
         };
 
         self.generate_access_token_response(
@@ -1935,6 +1988,10 @@ impl IdmServerProxyWriteTransaction<'_> {
                 state: SessionState::ExpiresAt(odt_exp),
                 issued_at: odt_ct,
                 rs_uuid: o2rs.uuid,
+                granted_scopes: scope.clone(),
+                has_refresh: false,
+                last_refreshed: None,
+                origin: None,
             },
         );
 
@@ -2149,6 +2206,10 @@ impl IdmServerProxyWriteTransaction<'_> {
                 state: SessionState::ExpiresAt(odt_refresh_expiry),
                 issued_at: odt_ct,
                 rs_uuid: o2rs.uuid,
+                granted_scopes: scope.clone(),
+                has_refresh: true,
+                last_refreshed: session_ctx.is_refresh.then_some(odt_ct),
+                origin: session_ctx.origin.map(String::from),
             },
         );
 
@@ -3605,7 +3666,7 @@ mod tests {
         host_is_local, parse_basic_authz, AuthoriseResponse, Oauth2Error, OauthRSType,
     };
     use crate::idm::server::{IdmServer, IdmServerTransaction};
-    use crate::idm::serviceaccount::GenerateApiTokenEvent;
+    use crate::idm::serviceaccount::{DestroyApiTokenEvent, GenerateApiTokenEvent};
     use crate::prelude::*;
     use crate::value::{AuthType, OauthClaimMapJoin, SessionState};
     use crate::valueset::{ValueSetOauthScopeMap, ValueSetSshKey, ValueSetUint32};
@@ -3787,6 +3848,7 @@ mod tests {
             .to_userauthtoken(
                 session_id,
                 SessionScope::ReadWrite,
+                AuthType::Passkey,
                 ct,
                 &ResolvedAccountPolicy::test_policy(),
             )
@@ -3814,6 +3876,11 @@ mod tests {
                 scope: SessionScope::ReadWrite,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                bound_to: None,
+                last_active: None,
+                pinned: false,
+                service_account: false,
+                disabled: None,
             },
         );
 
@@ -3925,6 +3992,7 @@ mod tests {
             .to_userauthtoken(
                 session_id,
                 SessionScope::ReadWrite,
+                AuthType::Passkey,
                 ct,
                 &ResolvedAccountPolicy::test_policy(),
             )
@@ -3952,6 +4020,11 @@ mod tests {
                 scope: SessionScope::ReadWrite,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                bound_to: None,
+                last_active: None,
+                pinned: false,
+                service_account: false,
+                disabled: None,
             },
         );
 
@@ -4062,6 +4135,7 @@ mod tests {
             .to_userauthtoken(
                 session_id,
                 SessionScope::ReadWrite,
+                AuthType::Passkey,
                 ct,
                 &ResolvedAccountPolicy::test_policy(),
             )
@@ -4552,6 +4626,7 @@ mod tests {
             .to_userauthtoken(
                 session_id,
                 SessionScope::ReadWrite,
+                AuthType::Passkey,
                 ct,
                 &ResolvedAccountPolicy::test_policy(),
             )
@@ -5315,6 +5390,7 @@ mod tests {
                 .to_userauthtoken(
                     session_id,
                     SessionScope::ReadWrite,
+                    AuthType::Passkey,
                     ct,
                     &ResolvedAccountPolicy::test_policy(),
                 )
@@ -7007,6 +7083,11 @@ mod tests {
             ),
             session.1.state
         );
+        // The redirect uri of the original authorisation is retained over the refresh.
+        assert_eq!(
+            session.1.origin.as_deref(),
+            Some("https://demo.example.com/oauth2/result")
+        );
 
         assert!(idms_prox_write.commit().is_ok());
 
@@ -8042,6 +8123,51 @@ mod tests {
             intr_response.sub.as_deref(),
             Some(service_account_uuid.to_string().as_str())
         );
+        drop(idms_prox_read);
+
+        // The exchange is recorded as a session derived from the api token.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let sa_entry = idms_prox_write
+            .qs_write
+            .internal_search_uuid(service_account_uuid)
+            .expect("Failed to search service account");
+        let token_id = sa_entry
+            .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+            .and_then(|tokens| tokens.keys().next().copied())
+            .expect("Api token not found");
+        let (exchange_session_id, exchange_session) = sa_entry
+            .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+            .and_then(|sessions| sessions.iter().next())
+            .expect("Exchange session not recorded");
+        assert_eq!(exchange_session.type_, AuthType::TokenExchange);
+        assert_eq!(exchange_session.cred_id, token_id);
+        assert!(matches!(exchange_session.state, SessionState::ExpiresAt(_)));
+        assert!(sa_entry
+            .get_ava_as_oauth2session_map(Attribute::OAuth2Session)
+            .and_then(|sessions| sessions.values().next())
+            .is_some_and(|session| session.parent == Some(*exchange_session_id)));
+        let exchange_session_id = *exchange_session_id;
+
+        // Destroying the api token revokes the exchanged session.
+        let dte = DestroyApiTokenEvent::new_internal(service_account_uuid, token_id);
+        assert!(idms_prox_write
+            .service_account_destroy_api_token(&dte)
+            .is_ok());
+        let sa_entry = idms_prox_write
+            .qs_write
+            .internal_search_uuid(service_account_uuid)
+            .expect("Failed to search service account");
+        assert!(sa_entry
+            .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+            .and_then(|sessions| sessions.get(&exchange_session_id))
+            .is_some_and(|session| matches!(session.state, SessionState::RevokedAt(_))));
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let intr_response = idms_prox_read
+            .check_oauth2_token_introspect(&intr_request, ct)
+            .expect("Failed to introspect service account token");
+        assert!(!intr_response.active);
     }
 
     #[idm_test]
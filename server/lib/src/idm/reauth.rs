@@ -57,6 +57,11 @@ impl IdmServerAuthTransaction<'_> {
             SessionScope::PrivilegeCapable => {
                 // Yes! This session can re-auth!
             }
+            SessionScope::ApiReadWrite => {
+                // These were requested with the promise they could never be elevated.
+                error!("Session scope is ApiReadWrite and may never be used in re-auth.");
+                return Err(OperationError::SessionMayNotReauth);
+            }
             SessionScope::ReadOnly | SessionScope::ReadWrite | SessionScope::Synchronise => {
                 // These can not!
                 error!("Session scope is not PrivilegeCapable and can not be used in re-auth.");
@@ -330,12 +335,11 @@ mod tests {
         ct: Duration,
         wa: &mut SoftPasskey,
         idms_delayed: &mut IdmServerDelayed,
+        auth_init: AuthEvent,
     ) -> Option<JwsCompact> {
         let mut idms_auth = idms.auth().await.unwrap();
         let origin = idms_auth.get_origin().clone();
 
-        let auth_init = AuthEvent::named_init("testperson");
-
         let r1 = idms_auth
             .auth(&auth_init, ct, Source::Internal.into())
             .await;
@@ -635,9 +639,15 @@ mod tests {
         let mut passkey = setup_testaccount_passkey(idms, ct).await;
 
         // Do an initial auth.
-        let token = auth_passkey(idms, ct, &mut passkey, idms_delayed)
-            .await
-            .expect("failed to authenticate with passkey");
+        let token = auth_passkey(
+            idms,
+            ct,
+            &mut passkey,
+            idms_delayed,
+            AuthEvent::named_init("testperson"),
+        )
+        .await
+        .expect("failed to authenticate with passkey");
 
         // Token_str to uat
         let ident = token_to_ident(idms, ct, token.clone().into()).await;
@@ -692,6 +702,48 @@ mod tests {
         assert!(matches!(ident.access_scope(), AccessScope::ReadOnly));
     }
 
+    #[idm_test]
+    async fn test_idm_reauth_api_only_rejected(
+        idms: &IdmServer,
+        idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = duration_from_epoch_now();
+
+        setup_testaccount(idms, ct).await;
+        let mut passkey = setup_testaccount_passkey(idms, ct).await;
+
+        // Request a session that can never be elevated.
+        let token = auth_passkey(
+            idms,
+            ct,
+            &mut passkey,
+            idms_delayed,
+            AuthEvent::named_init_api_only("testperson"),
+        )
+        .await
+        .expect("failed to authenticate with passkey");
+
+        let ident = token_to_ident(idms, ct, token.into()).await;
+
+        // The session is read write from the outset.
+        assert!(matches!(ident.access_scope(), AccessScope::ReadWrite));
+        let session = ident.get_session().expect("Unable to access sessions");
+        assert_eq!(session.scope, SessionScope::ApiReadWrite);
+
+        // But it may not be used to re-auth.
+        let mut idms_auth = idms.auth().await.unwrap();
+        let result = idms_auth
+            .reauth_init(
+                ident.clone(),
+                AuthIssueSession::Token,
+                ct,
+                Source::Internal.into(),
+                ReauthRequest::GrantReadWrite,
+            )
+            .await;
+        assert_eq!(result.map(|_| ()), Err(OperationError::SessionMayNotReauth));
+    }
+
     #[idm_test(audit = 1)]
     async fn test_idm_reauth_softlocked_pw(
         idms: &IdmServer,
@@ -737,7 +789,7 @@ mod tests {
         .is_none());
 
         // There should be a queued audit event
-        match idms_audit.audit_rx().try_recv() {
+        match idms_audit.try_recv_skip_sessions() {
             Ok(AuditEvent::AuthenticationDenied { .. }) => {}
             _ => panic!("Oh no"),
         }
@@ -72,8 +72,8 @@ impl SyncAccount {
         // Get the sessions. There are no gracewindows on sync, we are much stricter.
         let session_present = entry
             .get_ava_as_apitoken_map(Attribute::SyncTokenSession)
-            .map(|session_map| session_map.get(&sst.token_id).is_some())
-            .unwrap_or(false);
+            .and_then(|session_map| session_map.get(&sst.token_id))
+            .is_some_and(|token| !token.is_revoked());
 
         debug!(?session_present, valid_purpose);
 
@@ -116,21 +116,14 @@ impl IdmServerProxyWriteTransaction<'_> {
         let scope = ApiTokenScope::Synchronise;
         let purpose = scope.try_into()?;
 
-        let session = Value::ApiToken(
-            session_id,
-            ApiToken {
-                label: gte.label.clone(),
-                expiry: None,
-                // Need the other inner bits?
-                // for the gracewindow.
-                issued_at,
-                // Who actually created this?
-                issued_by: gte.ident.get_event_origin_id(),
-                // What is the access scope of this session? This is
-                // for auditing purposes.
-                scope,
-            },
-        );
+        let session = ApiToken::builder(issued_at)
+            .label(gte.label.clone())
+            // Who actually created this?
+            .issued_by(gte.ident.get_event_origin_id())
+            // What is the access scope of this session? This is
+            // for auditing purposes.
+            .scope(scope)
+            .build_value(session_id)?;
 
         let scim_sync_token = ScimSyncToken {
             token_id: session_id,
@@ -157,7 +150,14 @@ impl IdmServerProxyWriteTransaction<'_> {
                 &gte.ident,
             )
             .map_err(|err| {
-                error!(?err, "Failed to generate sync token");
+                if err == OperationError::SyncTokenAlreadyActive {
+                    error!(
+                        ?err,
+                        "Failed to generate sync token, the existing sync token must be destroyed first"
+                    );
+                } else {
+                    error!(?err, "Failed to generate sync token");
+                }
                 err
             })?;
 
@@ -1723,7 +1723,7 @@ mod tests {
         // Steal these from the legit sesh.
         let (token_id, issued_at) = sync_tokens
             .iter()
-            .next()
+            .find(|(_, v)| !v.is_revoked())
             .map(|(k, v)| (*k, v.issued_at))
             .expect("No sync tokens present");
 
@@ -3455,4 +3455,38 @@ mod tests {
   "retain": "Ignore"
 }
     "#;
+
+    #[idm_test]
+    async fn test_idm_scim_sync_single_active_token(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let (sync_uuid, _sync_token) = create_scim_sync_account(&mut idms_prox_write, ct);
+
+        // A second token is refused while the first is live.
+        let gte = GenerateScimSyncTokenEvent::new_internal(sync_uuid, "Second Connector");
+        assert_eq!(
+            idms_prox_write
+                .scim_sync_generate_token(&gte, ct)
+                .expect_err("Should not succeed"),
+            OperationError::SyncTokenAlreadyActive
+        );
+
+        // Once the first is destroyed, a new token can be generated.
+        idms_prox_write
+            .sync_account_destroy_token(&Identity::from_internal(), sync_uuid, ct)
+            .expect("Failed to destroy sync token");
+
+        let sync_token = idms_prox_write
+            .scim_sync_generate_token(&gte, ct)
+            .expect("failed to generate new scim sync token");
+
+        assert!(idms_prox_write
+            .validate_sync_client_auth_info_to_ident(sync_token.into(), ct)
+            .is_ok());
+
+        assert!(idms_prox_write.commit().is_ok());
+    }
 }
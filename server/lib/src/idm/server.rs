@@ -1,6 +1,7 @@
 use super::ldap::{LdapBoundToken, LdapSession};
 use crate::credential::{softlock::CredSoftLock, Credential};
-use crate::idm::account::Account;
+use crate::idm::account::{Account, SessionIdleStatus};
+use crate::idm::accountpolicy::ResolvedAccountPolicy;
 use crate::idm::application::{
     LdapApplications, LdapApplicationsReadTransaction, LdapApplicationsWriteTransaction,
 };
@@ -9,8 +10,8 @@ use crate::idm::authentication::{AuthState, PreValidatedTokenStatus};
 use crate::idm::authsession::{AuthSession, AuthSessionData};
 use crate::idm::credupdatesession::CredentialUpdateSessionMutex;
 use crate::idm::delayed::{
-    AuthSessionRecord, BackupCodeRemoval, DelayedAction, PasswordUpgrade, UnixPasswordUpgrade,
-    WebauthnCounterIncrement,
+    AuthSessionActivity, AuthSessionRecord, BackupCodeRemoval, DelayedAction, PasswordUpgrade,
+    UnixPasswordUpgrade, WebauthnCounterIncrement,
 };
 use crate::idm::event::{
     AuthEvent, AuthEventStep, AuthResult, CredentialStatusEvent, LdapAuthEvent, LdapTokenAuthEvent,
@@ -30,7 +31,7 @@ use crate::prelude::*;
 use crate::server::keys::KeyProvidersTransaction;
 use crate::server::DomainInfo;
 use crate::utils::{password_from_random, readable_password_from_random, uuid_from_duration, Sid};
-use crate::value::{Session, SessionState};
+use crate::value::{AuthType, Session, SessionExtMetadata, SessionState};
 use compact_jwt::{Jwk, JwsCompact};
 use concread::bptree::{BptreeMap, BptreeMapReadTxn, BptreeMapWriteTxn};
 use concread::cowcell::CowCellReadTxn;
@@ -42,6 +43,7 @@ use kanidm_proto::internal::{
 };
 use kanidm_proto::v1::{UnixGroupToken, UnixUserToken};
 use rand::prelude::*;
+use std::collections::BTreeSet;
 use std::convert::TryFrom;
 use std::sync::Arc;
 use std::time::Duration;
@@ -116,6 +118,8 @@ pub struct IdmServerCredUpdateTransaction<'a> {
 pub struct IdmServerProxyReadTransaction<'a> {
     pub qs_read: QueryServerReadTransaction<'a>,
     pub(crate) oauth2rs: Oauth2ResourceServersReadTransaction,
+    // For flagging eventual actions.
+    pub(crate) async_tx: UnboundedSender<DelayedAction>,
 }
 
 pub struct IdmServerProxyWriteTransaction<'a> {
@@ -132,6 +136,9 @@ pub struct IdmServerProxyWriteTransaction<'a> {
 
     pub(crate) origin: &'a Url,
     pub(crate) oauth2_client_providers: HashMapWriteTxn<'a, Uuid, OAuth2ClientProvider>,
+    // For flagging eventual actions.
+    pub(crate) async_tx: UnboundedSender<DelayedAction>,
+    pub(crate) audit_tx: UnboundedSender<AuditEvent>,
 }
 
 pub struct IdmServerDelayed {
@@ -273,11 +280,20 @@ impl IdmServer {
     /// Read from the database, in a transaction.
     #[instrument(level = "debug", skip_all)]
     pub async fn proxy_read(&self) -> Result<IdmServerProxyReadTransaction<'_>, OperationError> {
-        let qs_read = self.qs.read().await?;
+        self.proxy_read_at(duration_from_epoch_now()).await
+    }
+
+    /// As per `proxy_read`, resolving values that depend on the current time against `ts`.
+    #[instrument(level = "debug", skip_all)]
+    pub async fn proxy_read_at(
+        &self,
+        ts: Duration,
+    ) -> Result<IdmServerProxyReadTransaction<'_>, OperationError> {
+        let qs_read = self.qs.read_at(ts).await?;
         Ok(IdmServerProxyReadTransaction {
             qs_read,
             oauth2rs: self.oauth2rs.read(),
-            // async_tx: self.async_tx.clone(),
+            async_tx: self.async_tx.clone(),
         })
     }
 
@@ -302,6 +318,8 @@ impl IdmServer {
             applications: self.applications.write(),
             origin: &self.origin,
             oauth2_client_providers: self.oauth2_client_providers.write(),
+            async_tx: self.async_tx.clone(),
+            audit_tx: self.audit_tx.clone(),
         })
     }
 
@@ -332,11 +350,25 @@ impl IdmServer {
 }
 
 impl IdmServerAudit {
+    /// Session transitions are reported by most operations, so tests only need to
+    /// consume the other audit events.
+    #[cfg(test)]
+    pub(crate) fn try_recv_skip_sessions(
+        &mut self,
+    ) -> Result<AuditEvent, tokio::sync::mpsc::error::TryRecvError> {
+        loop {
+            match self.audit_rx.try_recv()? {
+                AuditEvent::SessionTransition { .. } => continue,
+                audit_event => return Ok(audit_event),
+            }
+        }
+    }
+
     #[cfg(test)]
     pub(crate) fn check_is_empty_or_panic(&mut self) {
         use tokio::sync::mpsc::error::TryRecvError;
 
-        match self.audit_rx.try_recv() {
+        match self.try_recv_skip_sessions() {
             Err(TryRecvError::Empty) => {}
             Err(TryRecvError::Disconnected) => {
                 panic!("Task queue disconnected");
@@ -401,6 +433,8 @@ pub trait IdmServerTransaction<'a> {
 
     fn get_qs_txn(&mut self) -> &mut Self::QsTransactionType;
 
+    fn get_async_tx(&self) -> &UnboundedSender<DelayedAction>;
+
     /// This is the preferred method to transform and securely verify a token into
     /// an identity that can be used for operations and access enforcement. This
     /// function *is* aware of the various classes of tokens that may exist, and can
@@ -575,8 +609,18 @@ pub trait IdmServerTransaction<'a> {
             return Ok(Token::ApiToken(apit, entry));
         };
 
-        // Is it just directly encoded session UUID?
-        if let Ok(session_id) = Uuid::from_slice(jws_inner.payload()) {
+        // Is it just directly encoded session UUID? Rotated tokens also carry their
+        // generation after the uuid.
+        let compact_session = match jws_inner.payload() {
+            [id @ .., g0, g1, g2, g3] if id.len() == 16 => Uuid::from_slice(id)
+                .ok()
+                .map(|session_id| (session_id, u32::from_be_bytes([*g0, *g1, *g2, *g3]))),
+            payload => Uuid::from_slice(payload)
+                .ok()
+                .map(|session_id| (session_id, 0)),
+        };
+
+        if let Some((session_id, generation)) = compact_session {
             // Now we have to look up the session.
 
             let filter = filter!(f_eq(
@@ -609,6 +653,16 @@ pub trait IdmServerTransaction<'a> {
                 OperationError::NotAuthenticated
             })?;
 
+            if api_token_internal.is_revoked() {
+                security_info!(entry_id = %entry.get_display_id(), "Session revoked");
+                return Err(OperationError::SessionExpired);
+            }
+
+            if api_token_internal.generation != generation {
+                security_info!(entry_id = %entry.get_display_id(), "Session has been rotated");
+                return Err(OperationError::SessionExpired);
+            }
+
             let purpose = api_token_internal.scope.try_into().map_err(|_| {
                 security_info!(entry_id = %entry.get_display_id(), "Account scope is not valid.");
                 OperationError::NotAuthenticated
@@ -618,9 +672,10 @@ pub trait IdmServerTransaction<'a> {
                 account_id: entry.get_uuid(),
                 token_id: session_id,
                 label: api_token_internal.label.clone(),
-                expiry: api_token_internal.expiry,
+                expiry: api_token_internal.expiry(),
                 issued_at: api_token_internal.issued_at,
                 purpose,
+                generation: api_token_internal.generation,
             };
 
             if let Some(expiry) = apit.expiry {
@@ -671,13 +726,14 @@ pub trait IdmServerTransaction<'a> {
 
         let grace_valid = ct < (Duration::from_secs(iat as u64) + AUTH_TOKEN_GRACE_WINDOW);
 
-        let oauth2_session = entry
-            .get_ava_as_oauth2session_map(Attribute::OAuth2Session)
-            .and_then(|sessions| sessions.get(&session_id));
+        let pv_session_id = PartialValue::Refer(session_id);
+        let oauth2_sessions = entry
+            .get_ava_set(Attribute::OAuth2Session)
+            .filter(|vs| vs.contains(&pv_session_id));
 
-        if let Some(oauth2_session) = oauth2_session {
+        if let Some(oauth2_sessions) = oauth2_sessions {
             // We have the oauth2 session, lets check it.
-            let oauth2_session_valid = !matches!(oauth2_session.state, SessionState::RevokedAt(_));
+            let oauth2_session_valid = oauth2_sessions.contains_active(&pv_session_id);
 
             if !oauth2_session_valid {
                 security_info!("The oauth2 session associated to this token is revoked.");
@@ -686,11 +742,12 @@ pub trait IdmServerTransaction<'a> {
 
             // Do we have a parent session? If yes, we need to enforce it's presence.
             if let Some(parent_session_id) = parent_session_id {
-                let uat_session = entry
-                    .get_ava_as_session_map(Attribute::UserAuthTokenSession)
-                    .and_then(|sessions| sessions.get(&parent_session_id));
+                let uat_session = entry.get_ava_value(
+                    Attribute::UserAuthTokenSession,
+                    &PartialValue::Refer(parent_session_id),
+                );
 
-                if let Some(uat_session) = uat_session {
+                if let Some(Value::Session(_, uat_session)) = uat_session {
                     let parent_session_valid =
                         !matches!(uat_session.state, SessionState::RevokedAt(_));
                     if parent_session_valid {
@@ -704,10 +761,19 @@ pub trait IdmServerTransaction<'a> {
                         return Ok(None);
                     }
                 } else {
-                    let api_session = entry
-                        .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
-                        .and_then(|sessions| sessions.get(&parent_session_id));
-                    if api_session.is_some() {
+                    let api_session = match entry.get_ava_value(
+                        Attribute::ApiTokenSession,
+                        &PartialValue::Refer(parent_session_id),
+                    ) {
+                        Some(Value::ApiToken(_, token)) => Some(token),
+                        _ => None,
+                    };
+                    if api_session.as_ref().is_some_and(|token| token.is_revoked()) {
+                        security_info!(
+                            "The parent api token session associated to this token is revoked."
+                        );
+                        return Ok(None);
+                    } else if api_session.is_some() {
                         security_info!("A valid api token session value exists for this token");
                     } else if grace_valid {
                         security_info!(
@@ -772,6 +838,33 @@ pub trait IdmServerTransaction<'a> {
             return Err(OperationError::SessionExpired);
         }
 
+        let idle_status = Account::check_user_auth_token_idle(ct, uat, &entry);
+
+        if let (SessionIdleStatus::Stale | SessionIdleStatus::Idle, Some(idle_timeout)) =
+            (idle_status, uat.idle_timeout)
+        {
+            // The session is revoked or its activity recorded in a later write, so
+            // that validation never has to write itself.
+            let activity = AuthSessionActivity {
+                target_uuid: uat.uuid,
+                session_id: uat.session_id,
+                active_at: time::OffsetDateTime::UNIX_EPOCH + ct,
+                idle_timeout: Duration::from_secs(idle_timeout.into()),
+            };
+
+            if self
+                .get_async_tx()
+                .send(DelayedAction::AuthSessionActivity(activity))
+                .is_err()
+            {
+                admin_warn!("failed to queue delayed action - auth session activity");
+            }
+        }
+
+        if idle_status == SessionIdleStatus::Idle {
+            return Err(OperationError::SessionExpired);
+        }
+
         // ✅  Session is valid! Start to setup for it to be used.
 
         let scope = match uat.purpose {
@@ -850,14 +943,31 @@ pub trait IdmServerTransaction<'a> {
 
         let limits = Limits::api_token();
 
-        Ok(Identity::new(
+        // A constrained token may only modify the attributes of its categories.
+        let write_constraint: Option<BTreeSet<Attribute>> = entry
+            .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+            .and_then(|tokens| tokens.get(&apit.token_id))
+            .and_then(|token| token.constraints.as_ref())
+            .map(|constraints| {
+                constraints
+                    .iter()
+                    .flat_map(|c| c.attributes().iter().cloned())
+                    .collect()
+            });
+
+        let ident = Identity::new(
             IdentType::User(IdentUser { entry }),
             source,
             apit.token_id,
             scope,
             limits,
             last_verified_at,
-        ))
+        );
+
+        Ok(match write_constraint {
+            Some(attrs) => ident.with_write_constraint(attrs),
+            None => ident,
+        })
     }
 
     fn client_cert_info_entry(
@@ -914,8 +1024,10 @@ pub trait IdmServerTransaction<'a> {
         // Now get the related entry.
         let entry = self.get_qs_txn().internal_search_uuid(refers_uuid)?;
 
-        let (account, account_policy) =
-            Account::try_from_entry_with_policy(entry.as_ref(), self.get_qs_txn())?;
+        let (account, account_policy) = crate::idm::account::Account::try_from_entry_with_policy(
+            entry.as_ref(),
+            self.get_qs_txn(),
+        )?;
 
         // Is the account in it's valid window?
         if !account.is_within_valid_time(ct) {
@@ -978,8 +1090,10 @@ pub trait IdmServerTransaction<'a> {
         // Now get the related entry.
         let entry = self.get_qs_txn().internal_search_uuid(refers_uuid)?;
 
-        let (account, account_policy) =
-            Account::try_from_entry_with_policy(entry.as_ref(), self.get_qs_txn())?;
+        let (account, account_policy) = crate::idm::account::Account::try_from_entry_with_policy(
+            entry.as_ref(),
+            self.get_qs_txn(),
+        )?;
 
         // Is the account in it's valid window?
         if !account.is_within_valid_time(ct) {
@@ -1009,8 +1123,10 @@ pub trait IdmServerTransaction<'a> {
                 err
             })?;
 
-        let (account, account_policy) =
-            Account::try_from_entry_with_policy(entry.as_ref(), self.get_qs_txn())?;
+        let (account, account_policy) = crate::idm::account::Account::try_from_entry_with_policy(
+            entry.as_ref(),
+            self.get_qs_txn(),
+        )?;
 
         if !account.is_within_valid_time(ct) {
             info!("Account is expired or not yet valid.");
@@ -1159,6 +1275,10 @@ impl<'a> IdmServerTransaction<'a> for IdmServerAuthTransaction<'a> {
     fn get_qs_txn(&mut self) -> &mut Self::QsTransactionType {
         &mut self.qs_read
     }
+
+    fn get_async_tx(&self) -> &UnboundedSender<DelayedAction> {
+        &self.async_tx
+    }
 }
 
 impl IdmServerAuthTransaction<'_> {
@@ -1223,6 +1343,7 @@ impl IdmServerAuthTransaction<'_> {
                     username = %init.username,
                     issue = ?init.issue,
                     privileged = ?init.privileged,
+                    api_only = ?init.api_only,
                     uuid = %euuid,
                     "Initiating Authentication Session",
                 );
@@ -1288,7 +1409,8 @@ impl IdmServerAuthTransaction<'_> {
 
                 let domain_keys = self.qs_read.get_domain_key_object_handle()?;
 
-                let (auth_session, state) = AuthSession::new(asd, init.privileged, domain_keys);
+                let (auth_session, state) =
+                    AuthSession::new(asd, init.privileged, init.api_only, domain_keys);
 
                 match auth_session {
                     Some(auth_session) => {
@@ -1411,6 +1533,26 @@ impl IdmServerAuthTransaction<'_> {
                 };
 
                 if is_valid {
+                    // A script repeatedly authenticating would otherwise mint sessions until
+                    // the session maximum evicts the legitimate ones, so no session is issued
+                    // once too many were issued to this account within the window. This is
+                    // checked here rather than at init so that only a caller with valid
+                    // credentials can learn of it.
+                    let account_policy = auth_session.account_policy();
+                    let issue_window =
+                        Duration::from_secs(account_policy.authsession_issue_window() as u64);
+                    let issued_since = OffsetDateTime::UNIX_EPOCH + ct.saturating_sub(issue_window);
+                    let recently_issued = self
+                        .qs_read
+                        .internal_search_uuid(auth_session.account().uuid)?
+                        .get_ava_set(Attribute::UserAuthTokenSession)
+                        .and_then(|vs| vs.as_session_set())
+                        .map(|vs| vs.recent_issue_count(issued_since))
+                        .unwrap_or_default();
+                    let issue_limited =
+                        recently_issued >= account_policy.authsession_issue_limit() as usize;
+                    auth_session.set_issue_limited(issue_limited);
+
                     // Process the credentials here as required.
                     // Basically throw them at the auth_session and see what
                     // falls out.
@@ -1425,8 +1567,12 @@ impl IdmServerAuthTransaction<'_> {
                         )
                         .inspect(|aus| {
                             // Inspect the result:
-                            // if it was a failure, we need to inc the softlock.
+                            // if it was a failure, we need to inc the softlock. Valid
+                            // credentials refused by the issue limit are not a failure.
                             if let AuthState::Denied(_) = aus {
+                                if auth_session.is_denied_by_issue_limit() {
+                                    return;
+                                }
                                 // Update it.
                                 if let Some(ref mut slock) = maybe_slock {
                                     slock.record_failure(ct);
@@ -1451,7 +1597,7 @@ impl IdmServerAuthTransaction<'_> {
         id: Uuid,
         cleartext: &str,
         ct: Duration,
-    ) -> Result<Option<Account>, OperationError> {
+    ) -> Result<Option<(Account, Uuid, ResolvedAccountPolicy)>, OperationError> {
         let entry = match self.qs_read.internal_search_uuid(id) {
             Ok(entry) => entry,
             Err(e) => {
@@ -1550,7 +1696,7 @@ impl IdmServerAuthTransaction<'_> {
                 })?;
         }
 
-        Ok(Some(account))
+        Ok(Some((account, cred_id, acp)))
     }
 
     pub async fn auth_unix(
@@ -1561,7 +1707,7 @@ impl IdmServerAuthTransaction<'_> {
         Ok(self
             .auth_with_unix_pass(uae.target, &uae.cleartext, ct)
             .await?
-            .and_then(|acc| acc.to_unixusertoken(ct).ok()))
+            .and_then(|(acc, _, _)| acc.to_unixusertoken(ct).ok()))
     }
 
     pub async fn auth_ldap(
@@ -1608,7 +1754,7 @@ impl IdmServerAuthTransaction<'_> {
                 .await?;
 
             match auth {
-                Some(account) => {
+                Some((account, cred_id, acp)) => {
                     let session_id = Uuid::new_v4();
                     security_info!(
                         "Starting session {} for {} {}",
@@ -1617,6 +1763,33 @@ impl IdmServerAuthTransaction<'_> {
                         account.uuid
                     );
 
+                    // Record the bind so that it is visible and subject to policy like any
+                    // other session. As with a uat, the nanoseconds are dropped.
+                    let ct = ct - Duration::from_nanos(ct.subsec_nanos() as u64);
+                    let expiry = ct
+                        + Duration::from_secs(
+                            acp.authsession_expiry_for(AuthType::LdapPassword) as u64
+                        );
+
+                    self.async_tx
+                        .send(DelayedAction::AuthSessionRecord(AuthSessionRecord {
+                            target_uuid: account.uuid,
+                            session_id,
+                            cred_id,
+                            label: "LDAP Bind".to_string(),
+                            expiry: Some(OffsetDateTime::UNIX_EPOCH + expiry),
+                            issued_at: OffsetDateTime::UNIX_EPOCH + ct,
+                            issued_by: IdentityId::User(account.uuid),
+                            scope: SessionScope::ReadOnly,
+                            type_: AuthType::LdapPassword,
+                            ext_metadata: SessionExtMetadata::None,
+                            service_account: account.is_service_account(),
+                        }))
+                        .map_err(|_| {
+                            admin_error!("failed to queue delayed action - ldap session record");
+                            OperationError::AU0005DelayedProcessFailure
+                        })?;
+
                     Ok(Some(LdapBoundToken {
                         spn: account.spn().into(),
                         session_id,
@@ -1667,6 +1840,10 @@ impl<'a> IdmServerTransaction<'a> for IdmServerProxyReadTransaction<'a> {
     fn get_qs_txn(&mut self) -> &mut Self::QsTransactionType {
         &mut self.qs_read
     }
+
+    fn get_async_tx(&self) -> &UnboundedSender<DelayedAction> {
+        &self.async_tx
+    }
 }
 
 fn gen_password_mod(
@@ -1786,6 +1963,10 @@ impl<'a> IdmServerTransaction<'a> for IdmServerProxyWriteTransaction<'a> {
     fn get_qs_txn(&mut self) -> &mut Self::QsTransactionType {
         &mut self.qs_write
     }
+
+    fn get_async_tx(&self) -> &UnboundedSender<DelayedAction> {
+        &self.async_tx
+    }
 }
 
 impl IdmServerProxyWriteTransaction<'_> {
@@ -2259,31 +2440,18 @@ impl IdmServerProxyWriteTransaction<'_> {
         &mut self,
         asr: &AuthSessionRecord,
     ) -> Result<(), OperationError> {
-        // We have to get the entry so we can work out if we need to expire any of it's sessions.
-        let state = match asr.expiry {
-            Some(e) => SessionState::ExpiresAt(e),
-            None => SessionState::NeverExpires,
-        };
-
-        let session = Value::Session(
-            asr.session_id,
-            Session {
-                label: asr.label.clone(),
-                state,
-                // Need the other inner bits?
-                // for the gracewindow.
-                issued_at: asr.issued_at,
-                // Who actually created this?
-                issued_by: asr.issued_by.clone(),
-                // Which credential was used?
-                cred_id: asr.cred_id,
-                // What is the access scope of this session? This is
-                // for auditing purposes.
-                scope: asr.scope,
-                type_: asr.type_,
-                ext_metadata: Default::default(),
-            },
-        );
+        let session = Session::builder(asr.issued_at, asr.type_)
+            .label(asr.label.clone())
+            .expiry(asr.expiry)
+            // Who actually created this?
+            .issued_by(asr.issued_by.clone())
+            // Which credential was used?
+            .cred_id(asr.cred_id)
+            // What is the access scope of this session? This is
+            // for auditing purposes.
+            .scope(asr.scope)
+            .service_account(asr.service_account)
+            .build_value(asr.session_id)?;
 
         info!(session_id = %asr.session_id, "Persisting auth session");
 
@@ -2302,6 +2470,50 @@ impl IdmServerProxyWriteTransaction<'_> {
         // Done!
     }
 
+    #[instrument(level = "debug", skip_all)]
+    pub(crate) fn process_authsessionactivity(
+        &mut self,
+        asa: &AuthSessionActivity,
+    ) -> Result<(), OperationError> {
+        let entry = self.qs_write.internal_search_uuid(asa.target_uuid)?;
+
+        let Some(session) = entry
+            .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+            .and_then(|session_map| session_map.get(&asa.session_id))
+        else {
+            debug!(session_id = %asa.session_id, "Auth session not found, ignoring activity");
+            return Ok(());
+        };
+
+        if matches!(session.state, SessionState::RevokedAt(_)) {
+            return Ok(());
+        }
+
+        // This is checked again since the session may have been used on another
+        // replica since the activity was queued.
+        let modlist = if session.is_idle_at(asa.active_at, asa.idle_timeout) {
+            info!(session_id = %asa.session_id, "Revoking idle auth session");
+            ModifyList::new_remove(
+                Attribute::UserAuthTokenSession,
+                PartialValue::Refer(asa.session_id),
+            )
+        } else {
+            let mut session = session.clone();
+            session.last_active = Some(asa.active_at);
+            ModifyList::new_append(
+                Attribute::UserAuthTokenSession,
+                Value::Session(asa.session_id, session),
+            )
+        };
+
+        self.qs_write
+            .internal_modify_uuid(asa.target_uuid, &modlist)
+            .map_err(|e| {
+                admin_error!("Failed to record auth session activity {:?}", e);
+                e
+            })
+    }
+
     #[instrument(level = "debug", skip_all)]
     pub fn process_delayedaction(
         &mut self,
@@ -2314,6 +2526,7 @@ impl IdmServerProxyWriteTransaction<'_> {
             DelayedAction::WebauthnCounterIncrement(wci) => self.process_webauthncounterinc(wci),
             DelayedAction::BackupCodeRemoval(bcr) => self.process_backupcoderemoval(bcr),
             DelayedAction::AuthSessionRecord(asr) => self.process_authsessionrecord(asr),
+            DelayedAction::AuthSessionActivity(asa) => self.process_authsessionactivity(asa),
         }
     }
 
@@ -2361,7 +2574,15 @@ impl IdmServerProxyWriteTransaction<'_> {
         self.oauth2_client_providers.commit();
 
         trace!("cred_update_session.commit");
-        self.qs_write.commit()
+        let audit_events = self.qs_write.take_audit_events();
+        self.qs_write.commit()?;
+
+        for audit_event in audit_events {
+            if self.audit_tx.send(audit_event).is_err() {
+                error!("Unable to submit audit event to queue");
+            }
+        }
+        Ok(())
     }
 }
 
@@ -2373,9 +2594,9 @@ mod tests {
     use std::time::Duration;
 
     use crate::credential::{Credential, Password};
-    use crate::idm::account::DestroySessionTokenEvent;
+    use crate::idm::account::{DestroySessionTokenEvent, RevokeOtherSessionsEvent};
     use crate::idm::accountpolicy::ResolvedAccountPolicy;
-    use crate::idm::audit::AuditEvent;
+    use crate::idm::audit::{AuditEvent, AuditSession, AuditSessionKind, AuditSessionTransition};
     use crate::idm::authentication::AuthState;
     use crate::idm::delayed::{AuthSessionRecord, DelayedAction};
     use crate::idm::event::{AuthEvent, AuthResult};
@@ -2783,7 +3004,7 @@ mod tests {
         };
 
         // There should be a queued audit event
-        match idms_audit.audit_rx().try_recv() {
+        match idms_audit.try_recv_skip_sessions() {
             Ok(AuditEvent::AuthenticationDenied { .. }) => {}
             _ => panic!("Oh no"),
         }
@@ -3409,7 +3630,7 @@ mod tests {
         };
 
         // There should be a queued audit event
-        match idms_audit.audit_rx().try_recv() {
+        match idms_audit.try_recv_skip_sessions() {
             Ok(AuditEvent::AuthenticationDenied { .. }) => {}
             _ => panic!("Oh no"),
         }
@@ -3575,7 +3796,7 @@ mod tests {
             }
         };
 
-        match idms_audit.audit_rx().try_recv() {
+        match idms_audit.try_recv_skip_sessions() {
             Ok(AuditEvent::AuthenticationDenied { .. }) => {}
             _ => panic!("Oh no"),
         }
@@ -3745,6 +3966,7 @@ mod tests {
             scope: SessionScope::ReadOnly,
             type_: AuthType::Passkey,
             ext_metadata: Default::default(),
+            service_account: false,
         });
         // Persist it.
         let r = idms.delayed_action(ct, da).await;
@@ -3778,6 +4000,7 @@ mod tests {
             scope: SessionScope::ReadOnly,
             type_: AuthType::Passkey,
             ext_metadata: Default::default(),
+            service_account: false,
         });
         // Persist it.
         let r = idms.delayed_action(expiry_a, da).await;
@@ -3915,6 +4138,331 @@ mod tests {
         }
     }
 
+    #[idm_test(audit = 1)]
+    async fn test_idm_account_session_audit_events(
+        idms: &IdmServer,
+        idms_delayed: &mut IdmServerDelayed,
+        idms_audit: &mut IdmServerAudit,
+    ) {
+        let ct = duration_from_epoch_now();
+
+        init_testperson_w_password(idms, TEST_PASSWORD)
+            .await
+            .expect("Failed to setup admin account");
+
+        let mut session_ids = Vec::with_capacity(2);
+        let mut tokens = Vec::with_capacity(2);
+        for _ in 0..2 {
+            tokens.push(check_testperson_password(idms, TEST_PASSWORD, ct).await);
+            let da = idms_delayed.try_recv().expect("invalid");
+            let DelayedAction::AuthSessionRecord(asr) = &da else {
+                panic!("Unexpected delayed action");
+            };
+            let session_id = asr.session_id;
+            session_ids.push(session_id);
+
+            // The creation is only reported once the session is committed.
+            assert!(idms_audit.audit_rx().try_recv().is_err());
+            let r = idms.delayed_action(ct, da).await;
+            assert_eq!(Ok(true), r);
+
+            match idms_audit.audit_rx().try_recv() {
+                Ok(AuditEvent::SessionTransition {
+                    kind: AuditSessionKind::UserAuthToken,
+                    transition: AuditSessionTransition::Created,
+                    sessions,
+                    ..
+                }) => assert_eq!(
+                    sessions,
+                    vec![AuditSession {
+                        entry: UUID_TESTPERSON_1,
+                        id: session_id
+                    }]
+                ),
+                _ => panic!("Oh no"),
+            }
+        }
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(tokens.remove(0).into(), ct)
+            .expect("Failed to validate");
+        drop(idms_prox_read);
+
+        let actor = ident.get_event_origin_id();
+
+        // A revocation that is rolled back is never reported.
+        let dte = DestroySessionTokenEvent {
+            ident: ident.clone(),
+            target: UUID_TESTPERSON_1,
+            token_id: session_ids[1],
+        };
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        assert!(idms_prox_write.account_destroy_session_token(&dte).is_ok());
+        drop(idms_prox_write);
+        assert!(idms_audit.audit_rx().try_recv().is_err());
+
+        // Once committed, the revocation is attributed to the user that requested it.
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let cid = idms_prox_write.qs_write.get_cid().clone();
+        assert!(idms_prox_write.account_destroy_session_token(&dte).is_ok());
+        assert!(idms_prox_write.commit().is_ok());
+
+        assert_eq!(
+            idms_audit.audit_rx().try_recv(),
+            Ok(AuditEvent::SessionTransition {
+                kind: AuditSessionKind::UserAuthToken,
+                transition: AuditSessionTransition::Revoked,
+                actor,
+                cid,
+                sessions: vec![AuditSession {
+                    entry: UUID_TESTPERSON_1,
+                    id: session_ids[1],
+                }],
+            })
+        );
+        idms_audit.check_is_empty_or_panic();
+    }
+
+    #[idm_test]
+    async fn test_idm_account_revoke_other_sessions(
+        idms: &IdmServer,
+        idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = duration_from_epoch_now();
+
+        init_testperson_w_password(idms, TEST_PASSWORD)
+            .await
+            .expect("Failed to setup admin account");
+
+        // Login from three places, persisting each session.
+        let mut tokens = Vec::with_capacity(3);
+        for _ in 0..3 {
+            tokens.push(check_testperson_password(idms, TEST_PASSWORD, ct).await);
+            let da = idms_delayed.try_recv().expect("invalid");
+            assert!(matches!(da, DelayedAction::AuthSessionRecord(_)));
+            let r = idms.delayed_action(ct, da).await;
+            assert_eq!(Ok(true), r);
+        }
+        idms_delayed.check_is_empty_or_panic();
+
+        let current = tokens.remove(0);
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let ident = idms_prox_read
+            .validate_client_auth_info_to_ident(current.clone().into(), ct)
+            .expect("Failed to validate");
+        drop(idms_prox_read);
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        let rse = RevokeOtherSessionsEvent { ident };
+        assert_eq!(idms_prox_write.account_revoke_other_sessions(&rse), Ok(2));
+        // Nothing remains to revoke.
+        assert_eq!(idms_prox_write.account_revoke_other_sessions(&rse), Ok(0));
+        assert!(idms_prox_write.commit().is_ok());
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        idms_prox_read
+            .validate_client_auth_info_to_ident(current.into(), ct)
+            .expect("Failed to validate");
+
+        for token in tokens {
+            match idms_prox_read.validate_client_auth_info_to_ident(token.into(), ct) {
+                Err(OperationError::SessionExpired) => {}
+                _ => panic!("Oh no"),
+            }
+        }
+    }
+
+    #[idm_test]
+    async fn test_idm_account_session_issue_limit(
+        idms: &IdmServer,
+        idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let issue_window = Duration::from_secs(300);
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                UUID_IDM_ALL_ACCOUNTS,
+                &ModifyList::new_list(vec![
+                    Modify::Purged(Attribute::AuthSessionIssueLimit),
+                    Modify::Present(Attribute::AuthSessionIssueLimit, Value::Uint32(3)),
+                    Modify::Purged(Attribute::AuthSessionIssueWindow),
+                    Modify::Present(
+                        Attribute::AuthSessionIssueWindow,
+                        Value::Uint32(issue_window.as_secs() as u32),
+                    ),
+                ]),
+            )
+            .expect("Unable to set the session issue limit");
+        assert!(idms_prox_write.commit().is_ok());
+
+        init_testperson_w_password(idms, TEST_PASSWORD)
+            .await
+            .expect("Failed to setup admin account");
+
+        // A burst of logins up to the limit is permitted.
+        for _ in 0..3 {
+            check_testperson_password(idms, TEST_PASSWORD, ct).await;
+            let da = idms_delayed.try_recv().expect("invalid");
+            assert!(matches!(da, DelayedAction::AuthSessionRecord(_)));
+            let r = idms.delayed_action(ct, da).await;
+            assert_eq!(Ok(true), r);
+        }
+        idms_delayed.check_is_empty_or_panic();
+
+        let auth_password = |at: Duration| async move {
+            let sid = init_authsession_sid(idms, at, "testperson1").await;
+            let mut idms_auth = idms.auth().await.unwrap();
+            let r = idms_auth
+                .auth(
+                    &AuthEvent::cred_step_password(sid, TEST_PASSWORD),
+                    at,
+                    Source::Internal.into(),
+                )
+                .await
+                .map(|ar| ar.state);
+            idms_auth.commit().expect("Must not fail");
+            r
+        };
+
+        // Past the limit, valid credentials are denied without a session being issued
+        // until the sessions leave the window. As the credentials were valid, this does
+        // not softlock the account, so the reason is the same on each attempt.
+        let mut reasons = Vec::new();
+        for at in [ct, ct, ct + issue_window] {
+            match auth_password(at).await {
+                Ok(AuthState::Denied(reason)) => reasons.push(reason),
+                r => panic!("Unexpected auth result {r:?}"),
+            }
+            idms_delayed.check_is_empty_or_panic();
+        }
+        reasons.dedup();
+        assert_eq!(reasons.len(), 1);
+
+        // Once the sessions leave the window, a session is issued again.
+        match auth_password(ct + issue_window + Duration::from_secs(1)).await {
+            Ok(AuthState::Success(_, AuthIssueSession::Token)) => {}
+            r => panic!("Unexpected auth result {r:?}"),
+        }
+        let da = idms_delayed.try_recv().expect("invalid");
+        assert!(matches!(da, DelayedAction::AuthSessionRecord(_)));
+    }
+
+    #[idm_test]
+    async fn test_idm_account_session_idle_timeout(
+        idms: &IdmServer,
+        idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = duration_from_epoch_now();
+        let idle_timeout = Duration::from_secs(300);
+
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                UUID_IDM_ALL_ACCOUNTS,
+                &ModifyList::new_purge_and_set(
+                    Attribute::AuthSessionIdleTimeout,
+                    Value::Uint32(idle_timeout.as_secs() as u32),
+                ),
+            )
+            .expect("Unable to set the idle timeout");
+        assert!(idms_prox_write.commit().is_ok());
+
+        init_testperson_w_password(idms, TEST_PASSWORD)
+            .await
+            .expect("Failed to setup admin account");
+
+        let mut tokens = Vec::with_capacity(2);
+        for _ in 0..2 {
+            tokens.push(check_testperson_password(idms, TEST_PASSWORD, ct).await);
+            let da = idms_delayed.try_recv().expect("invalid");
+            assert!(matches!(da, DelayedAction::AuthSessionRecord(_)));
+            let r = idms.delayed_action(ct, da).await;
+            assert_eq!(Ok(true), r);
+        }
+        let idle_token = tokens.pop().expect("missing token");
+        let active_token = tokens.pop().expect("missing token");
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let idle_session_id = idms_prox_read
+            .validate_client_auth_info_to_ident(idle_token.clone().into(), ct)
+            .expect("Failed to validate")
+            .get_session_id();
+        drop(idms_prox_read);
+        idms_delayed.check_is_empty_or_panic();
+
+        // The active session is used regularly, and its activity recorded each time.
+        let mut active_at = ct;
+        for _ in 0..4 {
+            active_at += Duration::from_secs(250);
+
+            let mut idms_prox_read = idms.proxy_read().await.unwrap();
+            idms_prox_read
+                .validate_client_auth_info_to_ident(active_token.clone().into(), active_at)
+                .expect("Failed to validate");
+            drop(idms_prox_read);
+
+            let da = idms_delayed.try_recv().expect("invalid");
+            assert!(matches!(da, DelayedAction::AuthSessionActivity(_)));
+            let r = idms.delayed_action(active_at, da).await;
+            assert_eq!(Ok(true), r);
+        }
+
+        // Use within a fraction of the timeout of the last activity isn't recorded.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        idms_prox_read
+            .validate_client_auth_info_to_ident(
+                active_token.clone().into(),
+                active_at + Duration::from_secs(1),
+            )
+            .expect("Failed to validate");
+        drop(idms_prox_read);
+        idms_delayed.check_is_empty_or_panic();
+
+        // The unused session is now idle, and is revoked.
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        match idms_prox_read.validate_client_auth_info_to_ident(idle_token.into(), active_at) {
+            Err(OperationError::SessionExpired) => {}
+            _ => panic!("Oh no"),
+        }
+        drop(idms_prox_read);
+
+        let da = idms_delayed.try_recv().expect("invalid");
+        assert!(matches!(da, DelayedAction::AuthSessionActivity(_)));
+        let r = idms.delayed_action(active_at, da).await;
+        assert_eq!(Ok(true), r);
+        idms_delayed.check_is_empty_or_panic();
+
+        let mut idms_prox_read = idms.proxy_read().await.unwrap();
+        let entry = idms_prox_read
+            .qs_read
+            .internal_search_uuid(UUID_TESTPERSON_1)
+            .expect("Failed to access testperson entry");
+        let sessions = entry
+            .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+            .expect("No sessions present");
+        assert!(matches!(
+            sessions.get(&idle_session_id).map(|session| &session.state),
+            Some(SessionState::RevokedAt(_))
+        ));
+        assert_eq!(
+            sessions
+                .values()
+                .filter(|session| !matches!(session.state, SessionState::RevokedAt(_)))
+                .count(),
+            1
+        );
+
+        idms_prox_read
+            .validate_client_auth_info_to_ident(active_token.into(), active_at)
+            .expect("Failed to validate");
+    }
+
     #[idm_test]
     async fn test_idm_account_session_expiry(
         idms: &IdmServer,
@@ -4083,6 +4631,7 @@ mod tests {
             .to_userauthtoken(
                 session_id,
                 SessionScope::ReadWrite,
+                AuthType::Passkey,
                 ct,
                 &ResolvedAccountPolicy::test_policy(),
             )
@@ -4102,6 +4651,7 @@ mod tests {
             .to_userauthtoken(
                 session_id,
                 SessionScope::ReadWrite,
+                AuthType::Passkey,
                 ct,
                 &ResolvedAccountPolicy::test_policy(),
             )
@@ -4121,6 +4671,7 @@ mod tests {
             .to_userauthtoken(
                 session_id,
                 SessionScope::ReadWrite,
+                AuthType::Passkey,
                 ct,
                 &ResolvedAccountPolicy::test_policy(),
             )
@@ -4140,6 +4691,7 @@ mod tests {
             .to_userauthtoken(
                 session_id,
                 SessionScope::ReadWrite,
+                AuthType::Passkey,
                 ct,
                 &ResolvedAccountPolicy::test_policy(),
             )
@@ -4159,6 +4711,7 @@ mod tests {
             .to_userauthtoken(
                 session_id,
                 SessionScope::ReadWrite,
+                AuthType::Passkey,
                 ct,
                 &ResolvedAccountPolicy::test_policy(),
             )
@@ -4178,6 +4731,7 @@ mod tests {
             .to_userauthtoken(
                 session_id,
                 SessionScope::ReadWrite,
+                AuthType::Passkey,
                 ct,
                 &ResolvedAccountPolicy::test_policy(),
             )
@@ -4218,6 +4772,7 @@ mod tests {
             .to_userauthtoken(
                 session_id,
                 SessionScope::ReadWrite,
+                AuthType::Passkey,
                 ct,
                 &ResolvedAccountPolicy::test_policy(),
             )
@@ -4237,6 +4792,71 @@ mod tests {
         );
     }
 
+    #[idm_test]
+    async fn test_idm_uat_expiry_by_auth_type_account_policy(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        idms_prox_write
+            .qs_write
+            .internal_create(vec![E_TESTPERSON_1.clone()])
+            .expect("Failed to create test person");
+
+        for (attr, expiry) in [
+            (Attribute::AuthSessionExpiry, 1000),
+            (Attribute::AuthSessionExpirySingleFactor, 300),
+            (Attribute::AuthSessionExpiryMultiFactor, 600),
+        ] {
+            idms_prox_write
+                .qs_write
+                .internal_modify_uuid(
+                    UUID_IDM_ALL_ACCOUNTS,
+                    &ModifyList::new_purge_and_set(attr, Value::Uint32(expiry)),
+                )
+                .expect("Unable to change session expiry policy");
+        }
+
+        let entry = idms_prox_write
+            .qs_write
+            .internal_search_uuid(UUID_TESTPERSON_1)
+            .expect("account must exist");
+        let (account, account_policy) = crate::idm::account::Account::try_from_entry_with_policy(
+            entry.as_ref(),
+            &mut idms_prox_write.qs_write,
+        )
+        .expect("account must be valid");
+
+        // Weaker auth types are limited by their class, stronger ones by the session expiry.
+        for (auth_type, expiry) in [
+            (AuthType::Password, 300),
+            (AuthType::GeneratedPassword, 300),
+            (AuthType::PasswordTotp, 600),
+            (AuthType::PasswordBackupCode, 600),
+            (AuthType::PasswordSecurityKey, 600),
+            (AuthType::Passkey, 1000),
+            (AuthType::AttestedPasskey, 1000),
+        ] {
+            let uat = account
+                .to_userauthtoken(
+                    Uuid::new_v4(),
+                    SessionScope::ReadOnly,
+                    auth_type,
+                    ct,
+                    &account_policy,
+                )
+                .expect("Unable to create uat");
+
+            assert_eq!(
+                uat.expiry,
+                Some(OffsetDateTime::UNIX_EPOCH + ct + Duration::from_secs(expiry)),
+                "unexpected expiry for {auth_type}"
+            );
+        }
+    }
+
     #[idm_test]
     async fn test_idm_jwt_uat_token_key_reload(
         idms: &IdmServer,
@@ -4334,7 +4954,7 @@ mod tests {
 
     async fn idm_fallback_auth_fixture(
         idms: &IdmServer,
-        _idms_delayed: &mut IdmServerDelayed,
+        idms_delayed: &mut IdmServerDelayed,
         has_posix_password: bool,
         allow_primary_cred_fallback: Option<bool>,
         expected: Option<()>,
@@ -4414,6 +5034,14 @@ mod tests {
         assert!(result.is_ok());
         if expected.is_some() {
             assert!(result.unwrap().is_some());
+            let da = idms_delayed.try_recv().expect("invalid");
+            assert!(matches!(
+                da,
+                DelayedAction::AuthSessionRecord(AuthSessionRecord {
+                    type_: AuthType::LdapPassword,
+                    ..
+                })
+            ));
         } else {
             assert!(result.unwrap().is_none());
         }
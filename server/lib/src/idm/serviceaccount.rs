@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::time::Duration;
 
 use compact_jwt::{jws::JwsBuilder, Jws, JwsCompact};
@@ -12,7 +12,8 @@ use crate::idm::event::GeneratePasswordEvent;
 use crate::idm::server::{IdmServerProxyReadTransaction, IdmServerProxyWriteTransaction};
 use crate::prelude::*;
 use crate::utils::password_from_random;
-use crate::value::ApiToken;
+use crate::value::{ApiToken, ApiTokenConstraint};
+use crate::valueset::ValueSetApiToken;
 
 macro_rules! try_from_entry {
     ($value:expr) => {{
@@ -85,12 +86,23 @@ impl ServiceAccount {
         // Get the sessions.
         let session_present = entry
             .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
-            .map(|session_map| session_map.get(&apit.token_id).is_some())
-            .unwrap_or(false);
+            .and_then(|session_map| session_map.get(&apit.token_id));
 
-        if session_present {
-            security_info!("A valid session value exists for this token");
-            true
+        if let Some(token) = session_present {
+            if token.is_revoked() {
+                security_info!("The api token session for this token is revoked");
+                false
+            } else if token.generation != apit.generation {
+                security_info!(
+                    token_generation = %apit.generation,
+                    session_generation = %token.generation,
+                    "The api token has been rotated, this token is no longer valid"
+                );
+                false
+            } else {
+                security_info!("A valid session value exists for this token");
+                true
+            }
         } else {
             let grace = apit.issued_at + AUTH_TOKEN_GRACE_WINDOW;
             let current = time::OffsetDateTime::UNIX_EPOCH + ct;
@@ -127,6 +139,7 @@ pub struct GenerateApiTokenEvent {
     // Is it read_write capable?
     pub read_write: bool,
     // Limits?
+    pub constraints: Option<BTreeSet<ApiTokenConstraint>>,
 
     // Should it be compact?
     pub compact: bool,
@@ -141,6 +154,7 @@ impl GenerateApiTokenEvent {
             label: label.to_string(),
             expiry: expiry.map(|ct| time::OffsetDateTime::UNIX_EPOCH + ct),
             read_write: false,
+            constraints: None,
             compact: false,
         }
     }
@@ -166,6 +180,88 @@ impl DestroyApiTokenEvent {
     }
 }
 
+pub struct DestroyApiTokenLabelEvent {
+    // Who initiated this?
+    pub ident: Identity,
+    // Who is it targeting?
+    pub target: Uuid,
+    // The label of the token.
+    pub label: String,
+}
+
+impl DestroyApiTokenLabelEvent {
+    #[cfg(test)]
+    pub fn new_internal(target: Uuid, label: &str) -> Self {
+        DestroyApiTokenLabelEvent {
+            ident: Identity::from_internal(),
+            target,
+            label: label.to_string(),
+        }
+    }
+}
+
+pub struct RotateApiTokenEvent {
+    // Who initiated this?
+    pub ident: Identity,
+    // Who is it targeting?
+    pub target: Uuid,
+    // Which token id.
+    pub token_id: Uuid,
+    // Should the expiry be replaced?
+    pub expiry: Option<time::OffsetDateTime>,
+    // Should it be compact?
+    pub compact: bool,
+}
+
+impl RotateApiTokenEvent {
+    #[cfg(test)]
+    pub fn new_internal(target: Uuid, token_id: Uuid, expiry: Option<Duration>) -> Self {
+        RotateApiTokenEvent {
+            ident: Identity::from_internal(),
+            target,
+            token_id,
+            expiry: expiry.map(|ct| time::OffsetDateTime::UNIX_EPOCH + ct),
+            compact: false,
+        }
+    }
+}
+
+/// Build the unsigned token that is issued for `api_token`.
+fn api_token_to_jws(
+    account_id: Uuid,
+    token_id: Uuid,
+    api_token: &ApiToken,
+    compact: bool,
+) -> Result<Jws, OperationError> {
+    if compact {
+        // We only issue the session uuid now. This makes the token as compact as possible, fitting
+        // within 128 characters. Once rotated the generation is appended so that secrets
+        // from earlier generations no longer validate.
+        let mut payload = token_id.as_bytes().to_vec();
+        if api_token.generation > 0 {
+            payload.extend_from_slice(&api_token.generation.to_be_bytes());
+        }
+        Ok(JwsBuilder::from(payload).build())
+    } else {
+        let purpose = api_token.scope.try_into()?;
+        // create the session token (not yet signed)
+        let proto_api_token = ProtoApiToken {
+            account_id,
+            token_id,
+            label: api_token.label.clone(),
+            expiry: api_token.expiry(),
+            issued_at: api_token.issued_at,
+            purpose,
+            generation: api_token.generation,
+        };
+
+        Jws::into_json(&proto_api_token).map_err(|err| {
+            error!(?err, "Unable to serialise JWS");
+            OperationError::SerdeJsonError
+        })
+    }
+}
+
 impl IdmServerProxyWriteTransaction<'_> {
     pub fn service_account_generate_api_token(
         &mut self,
@@ -184,56 +280,45 @@ impl IdmServerProxyWriteTransaction<'_> {
         let session_id = Uuid::new_v4();
         let issued_at = time::OffsetDateTime::UNIX_EPOCH + ct;
 
-        // Normalise to UTC in case it was provided as something else.
-        let expiry = gte.expiry.map(|odt| odt.to_offset(time::UtcOffset::UTC));
-
         let scope = if gte.read_write {
             ApiTokenScope::ReadWrite
         } else {
             ApiTokenScope::ReadOnly
         };
 
-        // create a new session
-        let session = Value::ApiToken(
-            session_id,
-            ApiToken {
-                label: gte.label.clone(),
-                expiry,
-                // Need the other inner bits?
-                // for the gracewindow.
-                issued_at,
-                // Who actually created this?
-                issued_by: gte.ident.get_event_origin_id(),
-                // What is the access scope of this session? This is
-                // for auditing purposes.
-                scope,
-            },
-        );
+        // Record the interactive session that minted this token, so that a leaked
+        // token can be traced back to its origin. Impersonated identities carry the
+        // internal session id, which is not a real session.
+        let parent_session = match &gte.ident.origin {
+            IdentType::User(_) if gte.ident.get_session_id() != UUID_INTERNAL_SESSION_ID => {
+                Some(gte.ident.get_session_id())
+            }
+            IdentType::User(_) | IdentType::Internal(_) | IdentType::Synch(_) => None,
+        };
 
-        let token = if gte.compact {
-            // We only issue the session uuid now. This makes the token as compact as possible, fitting
-            // within 128 characters.
-            let payload = session_id.as_bytes().to_vec();
-            JwsBuilder::from(payload).build()
-        } else {
-            let purpose = scope.try_into()?;
-            // create the session token (not yet signed)
-            let proto_api_token = ProtoApiToken {
-                account_id: service_account.uuid,
-                token_id: session_id,
-                label: gte.label.clone(),
-                expiry: gte.expiry,
-                issued_at,
-                purpose,
-            };
-
-            let token = Jws::into_json(&proto_api_token).map_err(|err| {
-                error!(?err, "Unable to serialise JWS");
-                OperationError::SerdeJsonError
-            })?;
+        // create a new session
+        let api_token = ApiToken::builder(issued_at)
+            .label(gte.label.clone())
+            // Normalised to UTC in case it was provided as something else.
+            .expiry(gte.expiry)
+            // Who actually created this?
+            .issued_by(gte.ident.get_event_origin_id())
+            // What is the access scope of this session? This is
+            // for auditing purposes.
+            .scope(scope)
+            // Which session minted this token, if any?
+            .parent_session(parent_session)
+            // What changes is this token limited to?
+            .constraints(gte.constraints.clone())
+            .try_build()?;
+
+        if api_token.is_expired_at_issue() {
+            error!(expiry = ?gte.expiry, %issued_at, "Refusing to issue an api token that is already expired");
+            return Err(OperationError::InvalidValueState);
+        }
 
-            token
-        };
+        let token = api_token_to_jws(service_account.uuid, session_id, &api_token, gte.compact)?;
+        let session = Value::ApiToken(session_id, api_token);
 
         // modify the account to put the session onto it.
         let modlist =
@@ -297,6 +382,150 @@ impl IdmServerProxyWriteTransaction<'_> {
             })
     }
 
+    /// Destroy the api token holding this label, returning the id of the token that
+    /// was destroyed. Labels are unique, but as entries may hold legacy duplicates
+    /// this fails if the label can't be resolved to a single token.
+    pub fn service_account_destroy_api_token_by_label(
+        &mut self,
+        dte: &DestroyApiTokenLabelEvent,
+    ) -> Result<Uuid, OperationError> {
+        // Resolve the label with the access of the caller. As the filter is on the
+        // label, they must be able to search the tokens to select one.
+        let entries = self
+            .qs_write
+            .impersonate_search(
+                // Filter as executed
+                filter!(f_and!([
+                    f_eq(Attribute::Uuid, PartialValue::Uuid(dte.target)),
+                    f_eq(
+                        Attribute::ApiTokenSession,
+                        PartialValue::new_iutf8(&dte.label)
+                    )
+                ])),
+                // Filter as intended (acp)
+                filter_all!(f_and!([
+                    f_eq(Attribute::Uuid, PartialValue::Uuid(dte.target)),
+                    f_eq(
+                        Attribute::ApiTokenSession,
+                        PartialValue::new_iutf8(&dte.label)
+                    )
+                ])),
+                &dte.ident,
+            )
+            .map_err(|e| {
+                admin_error!("Failed to search api tokens by label {:?}", e);
+                e
+            })?;
+
+        let label = dte.label.to_lowercase();
+        let now = self.qs_write.get_curtime_odt();
+
+        let candidates: Vec<(Uuid, bool)> = entries
+            .iter()
+            .filter_map(|e| e.get_ava_as_apitoken_map(Attribute::ApiTokenSession))
+            .flat_map(|smap| smap.iter())
+            .filter(|(_, at)| !at.is_revoked() && at.label.to_lowercase() == label)
+            .map(|(u, at)| (*u, at.is_valid_at(now)))
+            .collect();
+
+        let token_id = match candidates.as_slice() {
+            [] => {
+                error!(label = %dte.label, "No api token holds this label");
+                return Err(OperationError::NoMatchingEntries);
+            }
+            [(token_id, _)] => *token_id,
+            _ => {
+                // An expired token may share its label with the token that replaced
+                // it, in which case the live token is the one intended.
+                let mut live = candidates.iter().filter(|(_, is_live)| *is_live);
+                match (live.next(), live.next()) {
+                    (Some((token_id, _)), None) => *token_id,
+                    _ => {
+                        let candidates: Vec<Uuid> =
+                            candidates.into_iter().map(|(u, _)| u).collect();
+                        error!(label = %dte.label, ?candidates, "Api token label is ambiguous");
+                        return Err(OperationError::AmbiguousLabel(candidates));
+                    }
+                }
+            }
+        };
+
+        self.service_account_destroy_api_token(&DestroyApiTokenEvent {
+            ident: dte.ident.clone(),
+            target: dte.target,
+            token_id,
+        })
+        .map(|()| token_id)
+    }
+
+    pub fn service_account_rotate_api_token(
+        &mut self,
+        rte: &RotateApiTokenEvent,
+        ct: Duration,
+    ) -> Result<JwsCompact, OperationError> {
+        let service_account = self
+            .qs_write
+            .internal_search_uuid(rte.target)
+            .and_then(|account_entry| ServiceAccount::try_from_entry_rw(&account_entry))
+            .map_err(|e| {
+                admin_error!(?e, "Failed to search service account");
+                e
+            })?;
+
+        let issued_at = time::OffsetDateTime::UNIX_EPOCH + ct;
+
+        // The token keeps its uuid, so that references to it remain valid, but moves
+        // to a new generation which invalidates the previously issued secret.
+        let mut api_tokens =
+            ValueSetApiToken::from_iter(service_account.api_tokens).ok_or_else(|| {
+                error!(token_id = ?rte.token_id, "Unable to rotate api token, it does not exist");
+                OperationError::NoMatchingEntries
+            })?;
+
+        let api_token = api_tokens
+            .rotate(rte.token_id, issued_at, rte.expiry)?
+            .clone();
+
+        let token = api_token_to_jws(service_account.uuid, rte.token_id, &api_token, rte.compact)?;
+
+        // Presenting a later generation of a token replaces the current one.
+        let modlist = ModifyList::new_list(vec![Modify::Present(
+            Attribute::ApiTokenSession,
+            Value::ApiToken(rte.token_id, api_token),
+        )]);
+
+        self.qs_write
+            .impersonate_modify(
+                // Filter as executed
+                &filter!(f_and!([
+                    f_eq(Attribute::Uuid, PartialValue::Uuid(rte.target)),
+                    f_eq(
+                        Attribute::ApiTokenSession,
+                        PartialValue::Refer(rte.token_id)
+                    )
+                ])),
+                // Filter as intended (acp)
+                &filter_all!(f_and!([
+                    f_eq(Attribute::Uuid, PartialValue::Uuid(rte.target)),
+                    f_eq(
+                        Attribute::ApiTokenSession,
+                        PartialValue::Refer(rte.token_id)
+                    )
+                ])),
+                &modlist,
+                // Provide the event to impersonate
+                &rte.ident,
+            )
+            .map_err(|err| {
+                error!(?err, "Failed to rotate api token");
+                err
+            })?;
+
+        self.qs_write
+            .get_domain_key_object_handle()?
+            .jws_hs256_sign(&token, ct)
+    }
+
     pub fn generate_service_account_password(
         &mut self,
         gpe: &GeneratePasswordEvent,
@@ -372,6 +601,8 @@ impl IdmServerProxyReadTransaction<'_> {
                         e.get_ava_as_apitoken_map(Attribute::ApiTokenSession)
                             .map(|smap| {
                                 smap.iter()
+                                    // Revoked tokens are retained only for replication.
+                                    .filter(|(_, s)| !s.is_revoked())
                                     .map(|(u, s)| {
                                         s.scope
                                             .try_into()
@@ -379,9 +610,10 @@ impl IdmServerProxyReadTransaction<'_> {
                                                 account_id,
                                                 token_id: *u,
                                                 label: s.label.clone(),
-                                                expiry: s.expiry,
+                                                expiry: s.expiry(),
                                                 issued_at: s.issued_at,
                                                 purpose,
+                                                generation: s.generation,
                                             })
                                             .inspect_err(|err| {
                                                 admin_error!(?err, "Invalid api_token {}", u);
@@ -407,9 +639,13 @@ mod tests {
     use compact_jwt::{dangernoverify::JwsDangerReleaseWithoutVerify, JwsVerifier};
     use kanidm_proto::internal::ApiToken;
 
-    use super::{DestroyApiTokenEvent, GenerateApiTokenEvent};
-    use crate::idm::server::IdmServerTransaction;
+    use super::{
+        DestroyApiTokenEvent, DestroyApiTokenLabelEvent, GenerateApiTokenEvent, RotateApiTokenEvent,
+    };
+    use crate::idm::server::{IdmServerProxyWriteTransaction, IdmServerTransaction};
     use crate::prelude::*;
+    use crate::value::{ApiTokenConstraint, ApiTokenScope, SessionState};
+    use crate::valueset::ValueSetApiToken;
 
     const TEST_CURRENT_TIME: u64 = 6000;
 
@@ -482,11 +718,13 @@ mod tests {
             .is_ok());
 
         // Within gracewindow?
-        // This is okay, because we are within the gracewindow.
-        let ident = idms_prox_write
-            .validate_client_auth_info_to_ident(api_token.clone().into(), ct)
-            .expect("Unable to verify api token.");
-        assert_eq!(ident.get_uuid(), testaccount_uuid);
+        // The token is revoked rather than removed, so the gracewindow doesn't apply.
+        assert!(
+            idms_prox_write
+                .validate_client_auth_info_to_ident(api_token.clone().into(), ct)
+                .expect_err("Should not succeed")
+                == OperationError::SessionExpired
+        );
 
         // Past gracewindow?
         assert!(
@@ -571,4 +809,472 @@ mod tests {
 
         assert!(idms_prox_write.commit().is_ok());
     }
+
+    #[idm_test]
+    async fn test_idm_service_account_api_token_expired(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let testaccount_uuid = Uuid::new_v4();
+
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Name, Value::new_iname("test_account_only")),
+            (Attribute::Uuid, Value::Uuid(testaccount_uuid)),
+            (Attribute::Description, Value::new_utf8s("testaccount")),
+            (Attribute::DisplayName, Value::new_utf8s("testaccount"))
+        );
+
+        idms_prox_write
+            .qs_write
+            .internal_create(vec![e1])
+            .expect("Failed to create service account");
+
+        // A token that expires at the time it is issued is already expired.
+        for exp in [Duration::from_secs(TEST_CURRENT_TIME - 1), ct] {
+            let gte = GenerateApiTokenEvent::new_internal(testaccount_uuid, "TestToken", Some(exp));
+            assert_eq!(
+                idms_prox_write
+                    .service_account_generate_api_token(&gte, ct)
+                    .expect_err("Should not succeed"),
+                OperationError::InvalidValueState
+            );
+        }
+
+        let gte = GenerateApiTokenEvent::new_internal(
+            testaccount_uuid,
+            "TestToken",
+            Some(Duration::from_secs(TEST_CURRENT_TIME + 1)),
+        );
+        assert!(idms_prox_write
+            .service_account_generate_api_token(&gte, ct)
+            .is_ok());
+
+        assert!(idms_prox_write.commit().is_ok());
+    }
+
+    #[idm_test]
+    async fn test_idm_service_account_api_token_by_label(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let testaccount_uuid = Uuid::new_v4();
+
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Name, Value::new_iname("test_account_only")),
+            (Attribute::Uuid, Value::Uuid(testaccount_uuid)),
+            (Attribute::Description, Value::new_utf8s("testaccount")),
+            (Attribute::DisplayName, Value::new_utf8s("testaccount"))
+        );
+
+        idms_prox_write
+            .qs_write
+            .internal_create(vec![e1])
+            .expect("Failed to create service account");
+
+        let gte = GenerateApiTokenEvent::new_internal(testaccount_uuid, "TestToken", None);
+        let api_token = idms_prox_write
+            .service_account_generate_api_token(&gte, ct)
+            .expect("failed to generate new api token");
+
+        // The token can be found by label.
+        let label_filter = filter!(f_eq(
+            Attribute::ApiTokenSession,
+            PartialValue::new_iutf8("testtoken")
+        ));
+        let entries = idms_prox_write
+            .qs_write
+            .internal_search(label_filter.clone())
+            .expect("Failed to search");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].get_uuid(), testaccount_uuid);
+
+        // And revoked by label.
+        idms_prox_write
+            .qs_write
+            .internal_modify(
+                &label_filter,
+                &ModifyList::new_remove(
+                    Attribute::ApiTokenSession,
+                    PartialValue::new_iutf8("testtoken"),
+                ),
+            )
+            .expect("Failed to revoke token");
+
+        assert!(idms_prox_write
+            .validate_client_auth_info_to_ident(api_token.into(), ct)
+            .is_err());
+
+        assert!(idms_prox_write.commit().is_ok());
+    }
+
+    #[idm_test]
+    async fn test_idm_service_account_api_token_rotate(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let rotate_ct = Duration::from_secs(TEST_CURRENT_TIME + 60);
+        let exp = Duration::from_secs(TEST_CURRENT_TIME + 6000);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let testaccount_uuid = Uuid::new_v4();
+
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Name, Value::new_iname("test_account_only")),
+            (Attribute::Uuid, Value::Uuid(testaccount_uuid)),
+            (Attribute::Description, Value::new_utf8s("testaccount")),
+            (Attribute::DisplayName, Value::new_utf8s("testaccount"))
+        );
+
+        idms_prox_write
+            .qs_write
+            .internal_create(vec![e1])
+            .expect("Failed to create service account");
+
+        let gte = GenerateApiTokenEvent::new_internal(testaccount_uuid, "TestToken", None);
+        let api_token = idms_prox_write
+            .service_account_generate_api_token(&gte, ct)
+            .expect("failed to generate new api token");
+
+        let jws_verifier = JwsDangerReleaseWithoutVerify::default();
+        let apitoken_inner = jws_verifier
+            .verify(&api_token)
+            .unwrap()
+            .from_json::<ApiToken>()
+            .unwrap();
+        assert_eq!(apitoken_inner.generation, 0);
+
+        // Rotate the token, setting a new expiry.
+        let rte =
+            RotateApiTokenEvent::new_internal(testaccount_uuid, apitoken_inner.token_id, Some(exp));
+        let rotated_token = idms_prox_write
+            .service_account_rotate_api_token(&rte, rotate_ct)
+            .expect("failed to rotate api token");
+
+        let rotated_inner = jws_verifier
+            .verify(&rotated_token)
+            .unwrap()
+            .from_json::<ApiToken>()
+            .unwrap();
+        assert_eq!(rotated_inner.token_id, apitoken_inner.token_id);
+        assert_eq!(rotated_inner.label, apitoken_inner.label);
+        assert_eq!(rotated_inner.generation, 1);
+        assert_eq!(
+            rotated_inner.expiry,
+            Some(time::OffsetDateTime::UNIX_EPOCH + exp)
+        );
+
+        // The old token no longer validates, the new one does.
+        assert!(idms_prox_write
+            .validate_client_auth_info_to_ident(api_token.into(), rotate_ct)
+            .is_err());
+
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(rotated_token.into(), rotate_ct)
+            .expect("Unable to verify rotated api token.");
+        assert_eq!(ident.get_uuid(), testaccount_uuid);
+
+        // Compact tokens carry the generation once rotated.
+        let mut rte =
+            RotateApiTokenEvent::new_internal(testaccount_uuid, apitoken_inner.token_id, None);
+        rte.compact = true;
+        let compact_token = idms_prox_write
+            .service_account_rotate_api_token(&rte, rotate_ct)
+            .expect("failed to rotate api token");
+
+        assert!(
+            idms_prox_write
+                .validate_client_auth_info_to_ident(compact_token.clone().into(), rotate_ct)
+                .expect("Unable to verify rotated api token.")
+                .get_uuid()
+                == testaccount_uuid
+        );
+
+        let rte =
+            RotateApiTokenEvent::new_internal(testaccount_uuid, apitoken_inner.token_id, None);
+        idms_prox_write
+            .service_account_rotate_api_token(&rte, rotate_ct)
+            .expect("failed to rotate api token");
+
+        assert_eq!(
+            idms_prox_write
+                .validate_client_auth_info_to_ident(compact_token.into(), rotate_ct)
+                .expect_err("Should not succeed"),
+            OperationError::SessionExpired
+        );
+
+        assert!(idms_prox_write.commit().is_ok());
+    }
+
+    #[idm_test]
+    async fn test_idm_service_account_api_token_destroy_by_label(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let token = |label: &str, state: SessionState| crate::value::ApiToken {
+            label: label.to_string(),
+            state,
+            issued_at: time::OffsetDateTime::UNIX_EPOCH,
+            issued_by: IdentityId::Internal(UUID_SYSTEM),
+            scope: ApiTokenScope::ReadOnly,
+            parent_session: None,
+            generation: 0,
+            constraints: None,
+        };
+
+        let testaccount_uuid = Uuid::new_v4();
+        let deploy = Uuid::new_v4();
+        let backup_a = Uuid::new_v4();
+        let backup_b = Uuid::new_v4();
+        let rotated_expired = Uuid::new_v4();
+        let rotated_live = Uuid::new_v4();
+
+        let rotated_at = time::OffsetDateTime::UNIX_EPOCH + Duration::from_secs(1);
+
+        // Legacy data may hold duplicate labels, which can't be inserted through
+        // the normal write path.
+        let api_tokens = ValueSetApiToken::from_iter([
+            (deploy, token("deploy", SessionState::NeverExpires)),
+            (backup_a, token("Backup", SessionState::NeverExpires)),
+            (backup_b, token("backup", SessionState::NeverExpires)),
+            (
+                rotated_expired,
+                token("rotated", SessionState::ExpiresAt(rotated_at)),
+            ),
+            (
+                rotated_live,
+                crate::value::ApiToken {
+                    issued_at: rotated_at,
+                    ..token("rotated", SessionState::NeverExpires)
+                },
+            ),
+        ])
+        .expect("Unable to build valueset api token");
+
+        let mut e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Name, Value::new_iname("test_account_only")),
+            (Attribute::Uuid, Value::Uuid(testaccount_uuid)),
+            (Attribute::Description, Value::new_utf8s("testaccount")),
+            (Attribute::DisplayName, Value::new_utf8s("testaccount"))
+        );
+        e1.set_ava_set(&Attribute::ApiTokenSession, api_tokens);
+
+        idms_prox_write
+            .qs_write
+            .internal_create(vec![e1])
+            .expect("Failed to create service account");
+
+        fn is_revoked(
+            idms_prox_write: &mut IdmServerProxyWriteTransaction<'_>,
+            target: Uuid,
+            token_id: Uuid,
+        ) -> bool {
+            idms_prox_write
+                .qs_write
+                .internal_search_uuid(target)
+                .expect("Failed to search")
+                .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+                .and_then(|smap| smap.get(&token_id))
+                .map(|at| at.is_revoked())
+                .expect("Token is missing")
+        }
+
+        // A unique label resolves to its token, case insensitively.
+        let dte = DestroyApiTokenLabelEvent::new_internal(testaccount_uuid, "DEPLOY");
+        assert_eq!(
+            idms_prox_write.service_account_destroy_api_token_by_label(&dte),
+            Ok(deploy)
+        );
+        assert!(is_revoked(&mut idms_prox_write, testaccount_uuid, deploy));
+
+        // Once revoked the label no longer matches.
+        assert_eq!(
+            idms_prox_write.service_account_destroy_api_token_by_label(&dte),
+            Err(OperationError::NoMatchingEntries)
+        );
+
+        // Duplicates are reported with the candidates, and neither is revoked.
+        let dte = DestroyApiTokenLabelEvent::new_internal(testaccount_uuid, "backup");
+        match idms_prox_write.service_account_destroy_api_token_by_label(&dte) {
+            Err(OperationError::AmbiguousLabel(candidates)) => {
+                let mut expect = vec![backup_a, backup_b];
+                expect.sort_unstable();
+                assert_eq!(candidates, expect);
+            }
+            res => panic!("Unexpected result {res:?}"),
+        }
+        assert!(!is_revoked(
+            &mut idms_prox_write,
+            testaccount_uuid,
+            backup_a
+        ));
+        assert!(!is_revoked(
+            &mut idms_prox_write,
+            testaccount_uuid,
+            backup_b
+        ));
+
+        // A label reused after expiry resolves to the live token.
+        let dte = DestroyApiTokenLabelEvent::new_internal(testaccount_uuid, "rotated");
+        assert_eq!(
+            idms_prox_write.service_account_destroy_api_token_by_label(&dte),
+            Ok(rotated_live)
+        );
+        assert!(is_revoked(
+            &mut idms_prox_write,
+            testaccount_uuid,
+            rotated_live
+        ));
+        assert!(!is_revoked(
+            &mut idms_prox_write,
+            testaccount_uuid,
+            rotated_expired
+        ));
+
+        assert!(idms_prox_write.commit().is_ok());
+    }
+
+    #[idm_test]
+    async fn test_idm_service_account_api_token_constrained(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let mut idms_prox_write = idms.proxy_write(ct).await.unwrap();
+
+        let testaccount_uuid = Uuid::new_v4();
+        let testgroup_uuid = Uuid::new_v4();
+
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Name, Value::new_iname("test_account_only")),
+            (Attribute::Uuid, Value::Uuid(testaccount_uuid)),
+            (Attribute::Description, Value::new_utf8s("testaccount")),
+            (Attribute::DisplayName, Value::new_utf8s("testaccount"))
+        );
+
+        let e2 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("test_group")),
+            (Attribute::Uuid, Value::Uuid(testgroup_uuid))
+        );
+
+        idms_prox_write
+            .qs_write
+            .internal_create(vec![e1, e2])
+            .expect("Failed to create entries");
+
+        // The account is a group admin, so its access controls allow it to manage
+        // the members and the description of groups.
+        idms_prox_write
+            .qs_write
+            .internal_modify_uuid(
+                UUID_IDM_GROUP_ADMINS,
+                &ModifyList::new_append(Attribute::Member, Value::Refer(testaccount_uuid)),
+            )
+            .expect("Failed to add account to group admins");
+
+        let mut gte = GenerateApiTokenEvent::new_internal(testaccount_uuid, "TestToken", None);
+        gte.read_write = true;
+
+        let unconstrained_token = idms_prox_write
+            .service_account_generate_api_token(&gte, ct)
+            .expect("failed to generate new api token");
+
+        gte.label = "Constrained".to_string();
+        gte.constraints = Some(btreeset![ApiTokenConstraint::GroupMembership]);
+
+        let constrained_token = idms_prox_write
+            .service_account_generate_api_token(&gte, ct)
+            .expect("failed to generate new api token");
+
+        let set_description = |ident: Identity| {
+            ModifyEvent::new_impersonate_identity(
+                ident,
+                filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(testgroup_uuid))),
+                ModifyList::new_purge_and_set(
+                    Attribute::Description,
+                    Value::new_utf8s("description"),
+                ),
+            )
+        };
+
+        // Without constraints, the token can do anything the account can.
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(unconstrained_token.into(), ct)
+            .expect("Unable to verify api token.");
+
+        assert!(idms_prox_write
+            .qs_write
+            .modify(&set_description(ident))
+            .is_ok());
+
+        // The constrained token can manage group membership.
+        let ident = idms_prox_write
+            .validate_client_auth_info_to_ident(constrained_token.into(), ct)
+            .expect("Unable to verify api token.");
+
+        let me = ModifyEvent::new_impersonate_identity(
+            ident.clone(),
+            filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(testgroup_uuid))),
+            ModifyList::new_append(Attribute::Member, Value::Refer(testaccount_uuid)),
+        );
+        assert!(idms_prox_write.qs_write.modify(&me).is_ok());
+
+        // But nothing else, even though the access controls would allow it.
+        assert_eq!(
+            idms_prox_write
+                .qs_write
+                .modify(&set_description(ident.clone())),
+            Err(OperationError::AccessDenied)
+        );
+
+        let de = DeleteEvent::new_impersonate_identity(
+            ident,
+            filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(testgroup_uuid))),
+        );
+        assert_eq!(
+            idms_prox_write.qs_write.delete(&de),
+            Err(OperationError::AccessDenied)
+        );
+
+        // The constraint is visible on the stored token.
+        let constraints = idms_prox_write
+            .qs_write
+            .internal_search_uuid(testaccount_uuid)
+            .expect("Failed to search")
+            .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+            .and_then(|tokens| tokens.values().find(|t| t.label == "Constrained").cloned())
+            .and_then(|t| t.constraints);
+        assert_eq!(
+            constraints,
+            Some(btreeset![ApiTokenConstraint::GroupMembership])
+        );
+
+        assert!(idms_prox_write.commit().is_ok());
+    }
 }
@@ -517,6 +517,14 @@ pub static IDM_ACP_GROUP_ACCOUNT_POLICY_MANAGE_DL8: LazyLock<BuiltinAcp> =
             Attribute::LimitSearchMaxResults,
             Attribute::LimitSearchMaxFilterTest,
             Attribute::AllowPrimaryCredFallback,
+            Attribute::RetainSessionsOnPasswordChange,
+            Attribute::AuthSessionIdleTimeout,
+            Attribute::AuthSessionExpirySingleFactor,
+            Attribute::AuthSessionExpiryMultiFactor,
+            Attribute::RevokeSessionsOnPasswordChange,
+            Attribute::RetainCurrentSessionOnPasswordChange,
+            Attribute::AuthSessionIssueLimit,
+            Attribute::AuthSessionIssueWindow,
         ],
         modify_removed_attrs: vec![
             Attribute::Class,
@@ -528,6 +536,14 @@ pub static IDM_ACP_GROUP_ACCOUNT_POLICY_MANAGE_DL8: LazyLock<BuiltinAcp> =
             Attribute::LimitSearchMaxResults,
             Attribute::LimitSearchMaxFilterTest,
             Attribute::AllowPrimaryCredFallback,
+            Attribute::RetainSessionsOnPasswordChange,
+            Attribute::AuthSessionIdleTimeout,
+            Attribute::AuthSessionExpirySingleFactor,
+            Attribute::AuthSessionExpiryMultiFactor,
+            Attribute::RevokeSessionsOnPasswordChange,
+            Attribute::RetainCurrentSessionOnPasswordChange,
+            Attribute::AuthSessionIssueLimit,
+            Attribute::AuthSessionIssueWindow,
         ],
         modify_present_attrs: vec![
             Attribute::Class,
@@ -539,6 +555,14 @@ pub static IDM_ACP_GROUP_ACCOUNT_POLICY_MANAGE_DL8: LazyLock<BuiltinAcp> =
             Attribute::LimitSearchMaxResults,
             Attribute::LimitSearchMaxFilterTest,
             Attribute::AllowPrimaryCredFallback,
+            Attribute::RetainSessionsOnPasswordChange,
+            Attribute::AuthSessionIdleTimeout,
+            Attribute::AuthSessionExpirySingleFactor,
+            Attribute::AuthSessionExpiryMultiFactor,
+            Attribute::RevokeSessionsOnPasswordChange,
+            Attribute::RetainCurrentSessionOnPasswordChange,
+            Attribute::AuthSessionIssueLimit,
+            Attribute::AuthSessionIssueWindow,
         ],
         modify_classes: vec![EntryClass::AccountPolicy],
         ..Default::default()
@@ -954,6 +978,7 @@ pub static IDM_ACP_SELF_READ_DL8: LazyLock<BuiltinAcp> = LazyLock::new(|| Builti
         Attribute::AccountValidFrom,
         Attribute::PrimaryCredential,
         Attribute::UserAuthTokenSession,
+        Attribute::OAuth2Session,
         Attribute::PassKeys,
         Attribute::AttestedPasskeys,
         Attribute::ApplicationPassword,
@@ -985,6 +1010,7 @@ pub static IDM_ACP_SELF_WRITE_DL8: LazyLock<BuiltinAcp> = LazyLock::new(|| {
             Attribute::PassKeys,
             Attribute::AttestedPasskeys,
             Attribute::UserAuthTokenSession,
+            Attribute::OAuth2Session,
             Attribute::ApplicationPassword,
         ],
         modify_present_attrs: vec![
@@ -2119,3 +2145,26 @@ pub static IDM_ACP_OAUTH2_ACCOUNT_ENROL: LazyLock<BuiltinAcp> = LazyLock::new(||
     modify_classes: vec![EntryClass::OAuth2Account],
     ..Default::default()
 });
+
+// Person Session Revoke
+
+pub static IDM_ACP_PEOPLE_SESSION_REVOKE: LazyLock<BuiltinAcp> = LazyLock::new(|| BuiltinAcp {
+    classes: vec![
+        EntryClass::Object,
+        EntryClass::AccessControlProfile,
+        EntryClass::AccessControlModify,
+    ],
+    name: "idm_acp_people_session_revoke",
+    uuid: UUID_IDM_ACP_PEOPLE_SESSION_REVOKE,
+    description: "Builtin IDM Control for revoking the sessions of people.",
+    receiver: BuiltinAcpReceiver::Group(vec![UUID_IDM_SERVICE_DESK]),
+    target: BuiltinAcpTarget::Filter(ProtoFilter::And(vec![
+        match_class_filter!(EntryClass::Person),
+        match_class_filter!(EntryClass::Account),
+        FILTER_ANDNOT_HP_OR_RECYCLED_OR_TOMBSTONE.clone(),
+    ])),
+    // Removing a session value revokes it. No search or present access is granted, so the
+    // details of a session can't be read, and no session can be issued.
+    modify_removed_attrs: vec![Attribute::UserAuthTokenSession, Attribute::OAuth2Session],
+    ..Default::default()
+});
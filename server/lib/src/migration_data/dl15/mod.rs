@@ -129,6 +129,15 @@ pub fn phase_1_schema_attrs() -> Vec<SchemaAttribute> {
         SCHEMA_ATTR_PASSWORD_CHANGED_TIME.clone(),
         SCHEMA_ATTR_OAUTH2_REFRESH_TOKEN_EXPIRY.clone(),
         SCHEMA_ATTR_DOMAIN_ALLOW_ACCOUNT_RECOVERY.clone(),
+        // DL15
+        SCHEMA_ATTR_RETAIN_SESSIONS_ON_PASSWORD_CHANGE.clone(),
+        SCHEMA_ATTR_AUTH_SESSION_IDLE_TIMEOUT.clone(),
+        SCHEMA_ATTR_AUTH_SESSION_EXPIRY_SINGLE_FACTOR.clone(),
+        SCHEMA_ATTR_AUTH_SESSION_EXPIRY_MULTI_FACTOR.clone(),
+        SCHEMA_ATTR_REVOKE_SESSIONS_ON_PASSWORD_CHANGE.clone(),
+        SCHEMA_ATTR_RETAIN_CURRENT_SESSION_ON_PASSWORD_CHANGE.clone(),
+        SCHEMA_ATTR_AUTH_SESSION_ISSUE_LIMIT.clone(),
+        SCHEMA_ATTR_AUTH_SESSION_ISSUE_WINDOW.clone(),
     ]
 }
 
@@ -318,6 +327,8 @@ pub fn phase_7_builtin_access_control_profiles() -> Vec<EntryInitNew> {
         IDM_ACP_OAUTH2_ACCOUNT_ENROL.clone().into(),
         // DL13
         IDM_ACP_OAUTH2_MANAGE_BASIC.clone().into(),
+        // DL15
+        IDM_ACP_PEOPLE_SESSION_REVOKE.clone().into(),
     ]
 }
 
@@ -579,6 +579,7 @@ pub static SCHEMA_ATTR_API_TOKEN_SESSION: LazyLock<SchemaAttribute> =
         unique: true,
         multivalue: true,
         syntax: SyntaxType::ApiToken,
+        maximum_values: Some(crate::constants::API_TOKEN_MAXIMUM as u32),
         ..Default::default()
     });
 
@@ -591,6 +592,7 @@ pub static SCHEMA_ATTR_USER_AUTH_TOKEN_SESSION: LazyLock<SchemaAttribute> =
         unique: true,
         multivalue: true,
         syntax: SyntaxType::Session,
+        maximum_values: Some(crate::constants::SESSION_MAXIMUM as u32),
         ..Default::default()
     });
 
@@ -604,6 +606,7 @@ pub static SCHEMA_ATTR_OAUTH2_SESSION: LazyLock<SchemaAttribute> =
         indexed: true,
         multivalue: true,
         syntax: SyntaxType::Oauth2Session,
+        maximum_values: Some(crate::constants::SESSION_MAXIMUM as u32),
         ..Default::default()
     });
 
@@ -614,6 +617,9 @@ pub static SCHEMA_ATTR_SYNC_TOKEN_SESSION: LazyLock<SchemaAttribute> =
         description: "A session entry related to an issued sync token".to_string(),
         indexed: true,
         unique: true,
+        // Revoked tokens are retained until they are trimmed, so there may be more
+        // than one value present.
+        multivalue: true,
         syntax: SyntaxType::ApiToken,
         ..Default::default()
     });
@@ -844,6 +850,101 @@ pub static SCHEMA_ATTR_ALLOW_PRIMARY_CRED_FALLBACK_DL8: LazyLock<SchemaAttribute
         ..Default::default()
     });
 
+pub static SCHEMA_ATTR_RETAIN_SESSIONS_ON_PASSWORD_CHANGE: LazyLock<SchemaAttribute> =
+    LazyLock::new(|| SchemaAttribute {
+        uuid: UUID_SCHEMA_ATTR_RETAIN_SESSIONS_ON_PASSWORD_CHANGE,
+        name: Attribute::RetainSessionsOnPasswordChange,
+        description: "Allow existing sessions to remain valid when the primary password is changed"
+            .to_string(),
+        multivalue: false,
+        syntax: SyntaxType::Boolean,
+        ..Default::default()
+    });
+
+pub static SCHEMA_ATTR_AUTH_SESSION_IDLE_TIMEOUT: LazyLock<SchemaAttribute> =
+    LazyLock::new(|| SchemaAttribute {
+        uuid: UUID_SCHEMA_ATTR_AUTH_SESSION_IDLE_TIMEOUT,
+        name: Attribute::AuthSessionIdleTimeout,
+        description: "The time in seconds after which an unused authentication session is revoked"
+            .to_string(),
+        multivalue: false,
+        syntax: SyntaxType::Uint32,
+        ..Default::default()
+    });
+
+pub static SCHEMA_ATTR_AUTH_SESSION_EXPIRY_SINGLE_FACTOR: LazyLock<SchemaAttribute> =
+    LazyLock::new(|| SchemaAttribute {
+        uuid: UUID_SCHEMA_ATTR_AUTH_SESSION_EXPIRY_SINGLE_FACTOR,
+        name: Attribute::AuthSessionExpirySingleFactor,
+        description:
+            "An expiration time for an authentication session established with a single factor"
+                .to_string(),
+        multivalue: false,
+        syntax: SyntaxType::Uint32,
+        ..Default::default()
+    });
+
+pub static SCHEMA_ATTR_AUTH_SESSION_EXPIRY_MULTI_FACTOR: LazyLock<SchemaAttribute> = LazyLock::new(
+    || {
+        SchemaAttribute {
+        uuid: UUID_SCHEMA_ATTR_AUTH_SESSION_EXPIRY_MULTI_FACTOR,
+        name: Attribute::AuthSessionExpiryMultiFactor,
+        description: "An expiration time for an authentication session established with a password and second factor"
+            .to_string(),
+        multivalue: false,
+        syntax: SyntaxType::Uint32,
+        ..Default::default()
+    }
+    },
+);
+
+pub static SCHEMA_ATTR_REVOKE_SESSIONS_ON_PASSWORD_CHANGE: LazyLock<SchemaAttribute> =
+    LazyLock::new(|| SchemaAttribute {
+        uuid: UUID_SCHEMA_ATTR_REVOKE_SESSIONS_ON_PASSWORD_CHANGE,
+        name: Attribute::RevokeSessionsOnPasswordChange,
+        description: "Revoke all existing sessions when the primary password is changed"
+            .to_string(),
+        multivalue: false,
+        syntax: SyntaxType::Boolean,
+        ..Default::default()
+    });
+
+pub static SCHEMA_ATTR_RETAIN_CURRENT_SESSION_ON_PASSWORD_CHANGE: LazyLock<SchemaAttribute> =
+    LazyLock::new(|| SchemaAttribute {
+        uuid: UUID_SCHEMA_ATTR_RETAIN_CURRENT_SESSION_ON_PASSWORD_CHANGE,
+        name: Attribute::RetainCurrentSessionOnPasswordChange,
+        description:
+            "Allow the session that changed the password to remain valid when sessions are revoked"
+                .to_string(),
+        multivalue: false,
+        syntax: SyntaxType::Boolean,
+        ..Default::default()
+    });
+
+pub static SCHEMA_ATTR_AUTH_SESSION_ISSUE_LIMIT: LazyLock<SchemaAttribute> = LazyLock::new(|| {
+    SchemaAttribute {
+        uuid: UUID_SCHEMA_ATTR_AUTH_SESSION_ISSUE_LIMIT,
+        name: Attribute::AuthSessionIssueLimit,
+        description: "The maximum number of authentication sessions that may be issued to an account within the issue window"
+            .to_string(),
+        multivalue: false,
+        syntax: SyntaxType::Uint32,
+        ..Default::default()
+    }
+});
+
+pub static SCHEMA_ATTR_AUTH_SESSION_ISSUE_WINDOW: LazyLock<SchemaAttribute> = LazyLock::new(|| {
+    SchemaAttribute {
+        uuid: UUID_SCHEMA_ATTR_AUTH_SESSION_ISSUE_WINDOW,
+        name: Attribute::AuthSessionIssueWindow,
+        description: "The time in seconds over which issued authentication sessions are counted against the issue limit"
+            .to_string(),
+        multivalue: false,
+        syntax: SyntaxType::Uint32,
+        ..Default::default()
+    }
+});
+
 pub static SCHEMA_ATTR_CERTIFICATE_DL7: LazyLock<SchemaAttribute> =
     LazyLock::new(|| SchemaAttribute {
         uuid: UUID_SCHEMA_ATTR_CERTIFICATE,
@@ -1157,6 +1258,14 @@ pub static SCHEMA_CLASS_ACCOUNT_POLICY_DL8: LazyLock<SchemaClass> = LazyLock::ne
         Attribute::LimitSearchMaxResults,
         Attribute::LimitSearchMaxFilterTest,
         Attribute::AllowPrimaryCredFallback,
+        Attribute::RetainSessionsOnPasswordChange,
+        Attribute::AuthSessionIdleTimeout,
+        Attribute::AuthSessionExpirySingleFactor,
+        Attribute::AuthSessionExpiryMultiFactor,
+        Attribute::RevokeSessionsOnPasswordChange,
+        Attribute::RetainCurrentSessionOnPasswordChange,
+        Attribute::AuthSessionIssueLimit,
+        Attribute::AuthSessionIssueWindow,
     ],
     systemsupplements: vec![EntryClass::Group.into()],
     ..Default::default()
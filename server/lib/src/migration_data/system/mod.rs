@@ -18,6 +18,7 @@ pub fn attributes() -> Vec<SchemaAttribute> {
         SCHEMA_ATTR_MULTI_VALUE.clone(),
         SCHEMA_ATTR_PHANTOM.clone(),
         SCHEMA_ATTR_SYNC_ALLOWED.clone(),
+        SCHEMA_ATTR_MAXIMUM_VALUES.clone(),
         SCHEMA_ATTR_REPLICATED.clone(),
         SCHEMA_ATTR_UNIQUE.clone(),
         SCHEMA_ATTR_INDEX.clone(),
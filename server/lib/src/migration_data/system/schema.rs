@@ -12,6 +12,7 @@ pub static SCHEMA_ATTR_CLASS: LazyLock<SchemaAttribute> = LazyLock::new(|| Schem
     replicated: Replicated::True,
     indexed: true,
     syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_UUID: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::Uuid,
@@ -26,6 +27,7 @@ pub static SCHEMA_ATTR_UUID: LazyLock<SchemaAttribute> = LazyLock::new(|| Schema
     replicated: Replicated::True,
     indexed: true,
     syntax: SyntaxType::Uuid,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_SOURCE_UUID: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::SourceUuid,
@@ -42,6 +44,7 @@ pub static SCHEMA_ATTR_SOURCE_UUID: LazyLock<SchemaAttribute> = LazyLock::new(||
     replicated: Replicated::True,
     indexed: true,
     syntax: SyntaxType::Uuid,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_CREATED_AT_CID: LazyLock<SchemaAttribute> =
     LazyLock::new(|| SchemaAttribute {
@@ -57,6 +60,7 @@ pub static SCHEMA_ATTR_CREATED_AT_CID: LazyLock<SchemaAttribute> =
         replicated: Replicated::False,
         indexed: false,
         syntax: SyntaxType::Cid,
+        ..Default::default()
     });
 pub static SCHEMA_ATTR_LAST_MODIFIED_CID: LazyLock<SchemaAttribute> =
     LazyLock::new(|| SchemaAttribute {
@@ -72,6 +76,7 @@ pub static SCHEMA_ATTR_LAST_MODIFIED_CID: LazyLock<SchemaAttribute> =
         replicated: Replicated::False,
         indexed: false,
         syntax: SyntaxType::Cid,
+        ..Default::default()
     });
 pub static SCHEMA_ATTR_NAME: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::Name,
@@ -84,6 +89,7 @@ pub static SCHEMA_ATTR_NAME: LazyLock<SchemaAttribute> = LazyLock::new(|| Schema
     replicated: Replicated::True,
     indexed: true,
     syntax: SyntaxType::Utf8StringIname,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_SPN: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::Spn,
@@ -98,6 +104,7 @@ pub static SCHEMA_ATTR_SPN: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaA
     replicated: Replicated::True,
     indexed: true,
     syntax: SyntaxType::SecurityPrincipalName,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_ATTRIBUTE_NAME: LazyLock<SchemaAttribute> =
     LazyLock::new(|| SchemaAttribute {
@@ -111,6 +118,7 @@ pub static SCHEMA_ATTR_ATTRIBUTE_NAME: LazyLock<SchemaAttribute> =
         replicated: Replicated::True,
         indexed: true,
         syntax: SyntaxType::Utf8StringInsensitive,
+        ..Default::default()
     });
 pub static SCHEMA_ATTR_CLASS_NAME: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::ClassName,
@@ -123,6 +131,7 @@ pub static SCHEMA_ATTR_CLASS_NAME: LazyLock<SchemaAttribute> = LazyLock::new(||
     replicated: Replicated::True,
     indexed: true,
     syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_DESCRIPTION: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::Description,
@@ -135,6 +144,7 @@ pub static SCHEMA_ATTR_DESCRIPTION: LazyLock<SchemaAttribute> = LazyLock::new(||
     replicated: Replicated::True,
     indexed: false,
     syntax: SyntaxType::Utf8String,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_MULTI_VALUE: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::MultiValue,
@@ -149,6 +159,7 @@ pub static SCHEMA_ATTR_MULTI_VALUE: LazyLock<SchemaAttribute> = LazyLock::new(||
     replicated: Replicated::True,
     indexed: false,
     syntax: SyntaxType::Boolean,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_PHANTOM: LazyLock<SchemaAttribute> = LazyLock::new(|| {
     SchemaAttribute {
@@ -162,6 +173,7 @@ pub static SCHEMA_ATTR_PHANTOM: LazyLock<SchemaAttribute> = LazyLock::new(|| {
                 replicated: Replicated::True,
                 indexed: false,
                 syntax: SyntaxType::Boolean,
+                ..Default::default()
             }
 });
 pub static SCHEMA_ATTR_SYNC_ALLOWED: LazyLock<SchemaAttribute> =
@@ -178,6 +190,23 @@ pub static SCHEMA_ATTR_SYNC_ALLOWED: LazyLock<SchemaAttribute> =
         replicated: Replicated::True,
         indexed: false,
         syntax: SyntaxType::Boolean,
+        ..Default::default()
+    });
+pub static SCHEMA_ATTR_MAXIMUM_VALUES: LazyLock<SchemaAttribute> =
+    LazyLock::new(|| SchemaAttribute {
+        name: Attribute::MaximumValues,
+        uuid: UUID_SCHEMA_ATTR_MAXIMUM_VALUES,
+        description: String::from(
+            "The maximum number of values this attribute may hold on a single entry",
+        ),
+        multivalue: false,
+        unique: false,
+        phantom: false,
+        sync_allowed: false,
+        replicated: Replicated::True,
+        indexed: false,
+        syntax: SyntaxType::Uint32,
+        ..Default::default()
     });
 pub static SCHEMA_ATTR_REPLICATED: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::Replicated,
@@ -192,6 +221,7 @@ pub static SCHEMA_ATTR_REPLICATED: LazyLock<SchemaAttribute> = LazyLock::new(||
     replicated: Replicated::True,
     indexed: false,
     syntax: SyntaxType::Boolean,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_UNIQUE: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::Unique,
@@ -206,6 +236,7 @@ pub static SCHEMA_ATTR_UNIQUE: LazyLock<SchemaAttribute> = LazyLock::new(|| Sche
     replicated: Replicated::True,
     indexed: false,
     syntax: SyntaxType::Boolean,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_INDEX: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::Index,
@@ -218,6 +249,7 @@ pub static SCHEMA_ATTR_INDEX: LazyLock<SchemaAttribute> = LazyLock::new(|| Schem
     replicated: Replicated::True,
     indexed: false,
     syntax: SyntaxType::IndexId,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_INDEXED: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::Indexed,
@@ -232,6 +264,7 @@ pub static SCHEMA_ATTR_INDEXED: LazyLock<SchemaAttribute> = LazyLock::new(|| Sch
     replicated: Replicated::True,
     indexed: false,
     syntax: SyntaxType::Boolean,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_SYNTAX: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::Syntax,
@@ -246,6 +279,7 @@ pub static SCHEMA_ATTR_SYNTAX: LazyLock<SchemaAttribute> = LazyLock::new(|| Sche
     replicated: Replicated::True,
     indexed: false,
     syntax: SyntaxType::SyntaxId,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_SYSTEM_MAY: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::SystemMay,
@@ -260,6 +294,7 @@ pub static SCHEMA_ATTR_SYSTEM_MAY: LazyLock<SchemaAttribute> = LazyLock::new(||
     replicated: Replicated::True,
     indexed: false,
     syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_MAY: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::May,
@@ -274,6 +309,7 @@ pub static SCHEMA_ATTR_MAY: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaA
     replicated: Replicated::True,
     indexed: false,
     syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_SYSTEM_MUST: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::SystemMust,
@@ -288,6 +324,7 @@ pub static SCHEMA_ATTR_SYSTEM_MUST: LazyLock<SchemaAttribute> = LazyLock::new(||
     replicated: Replicated::True,
     indexed: false,
     syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_MUST: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::Must,
@@ -302,6 +339,7 @@ pub static SCHEMA_ATTR_MUST: LazyLock<SchemaAttribute> = LazyLock::new(|| Schema
     replicated: Replicated::True,
     indexed: false,
     syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_SYSTEM_SUPPLEMENTS: LazyLock<SchemaAttribute> = LazyLock::new(|| {
     SchemaAttribute {
@@ -317,6 +355,7 @@ pub static SCHEMA_ATTR_SYSTEM_SUPPLEMENTS: LazyLock<SchemaAttribute> = LazyLock:
                 replicated: Replicated::True,
                 indexed: false,
                 syntax: SyntaxType::Utf8StringInsensitive,
+                ..Default::default()
             }
 });
 pub static SCHEMA_ATTR_SUPPLEMENTS: LazyLock<SchemaAttribute> = LazyLock::new(|| {
@@ -333,6 +372,7 @@ pub static SCHEMA_ATTR_SUPPLEMENTS: LazyLock<SchemaAttribute> = LazyLock::new(||
                 replicated: Replicated::True,
                 indexed: false,
                 syntax: SyntaxType::Utf8StringInsensitive,
+                ..Default::default()
             }
 });
 pub static SCHEMA_ATTR_SYSTEM_EXCLUDES: LazyLock<SchemaAttribute> =
@@ -349,6 +389,7 @@ pub static SCHEMA_ATTR_SYSTEM_EXCLUDES: LazyLock<SchemaAttribute> =
         replicated: Replicated::True,
         indexed: false,
         syntax: SyntaxType::Utf8StringInsensitive,
+        ..Default::default()
     });
 pub static SCHEMA_ATTR_EXCLUDES: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::Excludes,
@@ -363,6 +404,7 @@ pub static SCHEMA_ATTR_EXCLUDES: LazyLock<SchemaAttribute> = LazyLock::new(|| Sc
     replicated: Replicated::True,
     indexed: false,
     syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
 });
 
 // SYSINFO attrs
@@ -379,6 +421,7 @@ pub static SCHEMA_ATTR_ACP_ENABLE: LazyLock<SchemaAttribute> = LazyLock::new(||
                 replicated: Replicated::True,
                 indexed: true,
                 syntax: SyntaxType::Boolean,
+                ..Default::default()
             }
 });
 
@@ -394,6 +437,7 @@ pub static SCHEMA_ATTR_ACP_RECEIVER: LazyLock<SchemaAttribute> =
         replicated: Replicated::True,
         indexed: true,
         syntax: SyntaxType::JsonFilter,
+        ..Default::default()
     });
 pub static SCHEMA_ATTR_ACP_RECEIVER_GROUP: LazyLock<SchemaAttribute> =
     LazyLock::new(|| SchemaAttribute {
@@ -407,6 +451,7 @@ pub static SCHEMA_ATTR_ACP_RECEIVER_GROUP: LazyLock<SchemaAttribute> =
         replicated: Replicated::True,
         indexed: true,
         syntax: SyntaxType::ReferenceUuid,
+        ..Default::default()
     });
 
 pub static SCHEMA_ATTR_ACP_TARGET_SCOPE: LazyLock<SchemaAttribute> =
@@ -423,6 +468,7 @@ pub static SCHEMA_ATTR_ACP_TARGET_SCOPE: LazyLock<SchemaAttribute> =
         replicated: Replicated::True,
         indexed: true,
         syntax: SyntaxType::JsonFilter,
+        ..Default::default()
     });
 pub static SCHEMA_ATTR_ACP_SEARCH_ATTR: LazyLock<SchemaAttribute> =
     LazyLock::new(|| SchemaAttribute {
@@ -438,6 +484,7 @@ pub static SCHEMA_ATTR_ACP_SEARCH_ATTR: LazyLock<SchemaAttribute> =
         replicated: Replicated::True,
         indexed: true,
         syntax: SyntaxType::Utf8StringInsensitive,
+        ..Default::default()
     });
 pub static SCHEMA_ATTR_ACP_CREATE_CLASS: LazyLock<SchemaAttribute> =
     LazyLock::new(|| SchemaAttribute {
@@ -451,6 +498,7 @@ pub static SCHEMA_ATTR_ACP_CREATE_CLASS: LazyLock<SchemaAttribute> =
         replicated: Replicated::True,
         indexed: true,
         syntax: SyntaxType::Utf8StringInsensitive,
+        ..Default::default()
     });
 pub static SCHEMA_ATTR_ACP_CREATE_ATTR: LazyLock<SchemaAttribute> =
     LazyLock::new(|| SchemaAttribute {
@@ -464,6 +512,7 @@ pub static SCHEMA_ATTR_ACP_CREATE_ATTR: LazyLock<SchemaAttribute> =
         replicated: Replicated::True,
         indexed: true,
         syntax: SyntaxType::Utf8StringInsensitive,
+        ..Default::default()
     });
 
 pub static SCHEMA_ATTR_ACP_MODIFY_REMOVED_ATTR: LazyLock<SchemaAttribute> =
@@ -480,6 +529,7 @@ pub static SCHEMA_ATTR_ACP_MODIFY_REMOVED_ATTR: LazyLock<SchemaAttribute> =
         replicated: Replicated::True,
         indexed: true,
         syntax: SyntaxType::Utf8StringInsensitive,
+        ..Default::default()
     });
 pub static SCHEMA_ATTR_ACP_MODIFY_PRESENT_ATTR: LazyLock<SchemaAttribute> =
     LazyLock::new(|| SchemaAttribute {
@@ -495,6 +545,7 @@ pub static SCHEMA_ATTR_ACP_MODIFY_PRESENT_ATTR: LazyLock<SchemaAttribute> =
         replicated: Replicated::True,
         indexed: true,
         syntax: SyntaxType::Utf8StringInsensitive,
+        ..Default::default()
     });
 pub static SCHEMA_ATTR_ACP_MODIFY_CLASS: LazyLock<SchemaAttribute> = LazyLock::new(|| {
     SchemaAttribute {
@@ -508,6 +559,7 @@ pub static SCHEMA_ATTR_ACP_MODIFY_CLASS: LazyLock<SchemaAttribute> = LazyLock::n
                 replicated: Replicated::True,
                 indexed: true,
                 syntax: SyntaxType::Utf8StringInsensitive,
+                ..Default::default()
             }
 });
 pub static SCHEMA_ATTR_ACP_MODIFY_PRESENT_CLASS: LazyLock<SchemaAttribute> = LazyLock::new(|| {
@@ -522,6 +574,7 @@ pub static SCHEMA_ATTR_ACP_MODIFY_PRESENT_CLASS: LazyLock<SchemaAttribute> = Laz
                     replicated: Replicated::True,
                     indexed: false,
                     syntax: SyntaxType::Utf8StringInsensitive,
+                    ..Default::default()
                 }
 });
 pub static SCHEMA_ATTR_ACP_MODIFY_REMOVE_CLASS: LazyLock<SchemaAttribute> = LazyLock::new(|| {
@@ -536,6 +589,7 @@ pub static SCHEMA_ATTR_ACP_MODIFY_REMOVE_CLASS: LazyLock<SchemaAttribute> = Lazy
                     replicated: Replicated::True,
                     indexed: false,
                     syntax: SyntaxType::Utf8StringInsensitive,
+                    ..Default::default()
                 }
 });
 pub static SCHEMA_ATTR_ENTRY_MANAGED_BY: LazyLock<SchemaAttribute> =
@@ -552,6 +606,7 @@ pub static SCHEMA_ATTR_ENTRY_MANAGED_BY: LazyLock<SchemaAttribute> =
         replicated: Replicated::True,
         indexed: true,
         syntax: SyntaxType::ReferenceUuid,
+        ..Default::default()
     });
 // MO/Member
 pub static SCHEMA_ATTR_MEMBER_OF: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
@@ -565,6 +620,7 @@ pub static SCHEMA_ATTR_MEMBER_OF: LazyLock<SchemaAttribute> = LazyLock::new(|| S
     replicated: Replicated::False,
     indexed: true,
     syntax: SyntaxType::ReferenceUuid,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_DIRECT_MEMBER_OF: LazyLock<SchemaAttribute> =
     LazyLock::new(|| SchemaAttribute {
@@ -578,6 +634,7 @@ pub static SCHEMA_ATTR_DIRECT_MEMBER_OF: LazyLock<SchemaAttribute> =
         replicated: Replicated::False,
         indexed: true,
         syntax: SyntaxType::ReferenceUuid,
+        ..Default::default()
     });
 pub static SCHEMA_ATTR_RECYCLED_DIRECT_MEMBER_OF: LazyLock<SchemaAttribute> = LazyLock::new(|| {
     SchemaAttribute {
@@ -595,6 +652,7 @@ pub static SCHEMA_ATTR_RECYCLED_DIRECT_MEMBER_OF: LazyLock<SchemaAttribute> = La
                 replicated: Replicated::True,
                 indexed: true,
                 syntax: SyntaxType::ReferenceUuid,
+                ..Default::default()
             }
 });
 pub static SCHEMA_ATTR_MEMBER: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
@@ -608,6 +666,7 @@ pub static SCHEMA_ATTR_MEMBER: LazyLock<SchemaAttribute> = LazyLock::new(|| Sche
     replicated: Replicated::True,
     indexed: true,
     syntax: SyntaxType::ReferenceUuid,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_DYN_MEMBER: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::DynMember,
@@ -620,6 +679,7 @@ pub static SCHEMA_ATTR_DYN_MEMBER: LazyLock<SchemaAttribute> = LazyLock::new(||
     replicated: Replicated::False,
     indexed: true,
     syntax: SyntaxType::ReferenceUuid,
+    ..Default::default()
 });
 
 pub static SCHEMA_ATTR_REFERS: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
@@ -633,6 +693,7 @@ pub static SCHEMA_ATTR_REFERS: LazyLock<SchemaAttribute> = LazyLock::new(|| Sche
     replicated: Replicated::True,
     indexed: true,
     syntax: SyntaxType::ReferenceUuid,
+    ..Default::default()
 });
 
 pub static SCHEMA_ATTR_CASCADE_DELETED: LazyLock<SchemaAttribute> = LazyLock::new(|| {
@@ -649,6 +710,7 @@ pub static SCHEMA_ATTR_CASCADE_DELETED: LazyLock<SchemaAttribute> = LazyLock::ne
                 // NOTE: This has to be Uuid so that referential integrity doesn't consider
                 // this value in its operation.
                 syntax: SyntaxType::Uuid,
+                ..Default::default()
             }
 });
 
@@ -664,6 +726,7 @@ pub static SCHEMA_ATTR_VERSION: LazyLock<SchemaAttribute> = LazyLock::new(|| Sch
     replicated: Replicated::True,
     indexed: false,
     syntax: SyntaxType::Uint32,
+    ..Default::default()
 });
 // Domain for sysinfo
 pub static SCHEMA_ATTR_DOMAIN: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
@@ -677,6 +740,7 @@ pub static SCHEMA_ATTR_DOMAIN: LazyLock<SchemaAttribute> = LazyLock::new(|| Sche
     replicated: Replicated::True,
     indexed: true,
     syntax: SyntaxType::Utf8StringIname,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_CLAIM: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::Claim,
@@ -689,6 +753,7 @@ pub static SCHEMA_ATTR_CLAIM: LazyLock<SchemaAttribute> = LazyLock::new(|| Schem
     replicated: Replicated::True,
     indexed: false,
     syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_SCOPE: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::Scope,
@@ -701,6 +766,7 @@ pub static SCHEMA_ATTR_SCOPE: LazyLock<SchemaAttribute> = LazyLock::new(|| Schem
     replicated: Replicated::True,
     indexed: false,
     syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
 });
 
 // External Scim Sync
@@ -718,6 +784,7 @@ pub static SCHEMA_ATTR_SYNC_EXTERNAL_ID: LazyLock<SchemaAttribute> =
         replicated: Replicated::True,
         indexed: true,
         syntax: SyntaxType::Utf8StringInsensitive,
+        ..Default::default()
     });
 pub static SCHEMA_ATTR_SYNC_PARENT_UUID: LazyLock<SchemaAttribute> =
     LazyLock::new(|| SchemaAttribute {
@@ -731,6 +798,7 @@ pub static SCHEMA_ATTR_SYNC_PARENT_UUID: LazyLock<SchemaAttribute> =
         replicated: Replicated::True,
         indexed: true,
         syntax: SyntaxType::ReferenceUuid,
+        ..Default::default()
     });
 pub static SCHEMA_ATTR_SYNC_CLASS: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::SyncClass,
@@ -743,6 +811,7 @@ pub static SCHEMA_ATTR_SYNC_CLASS: LazyLock<SchemaAttribute> = LazyLock::new(||
     replicated: Replicated::True,
     indexed: false,
     syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
 });
 
 pub static SCHEMA_ATTR_PASSWORD_IMPORT: LazyLock<SchemaAttribute> =
@@ -757,6 +826,7 @@ pub static SCHEMA_ATTR_PASSWORD_IMPORT: LazyLock<SchemaAttribute> =
         replicated: Replicated::False,
         indexed: false,
         syntax: SyntaxType::Utf8String,
+        ..Default::default()
     });
 
 pub static SCHEMA_ATTR_UNIX_PASSWORD_IMPORT: LazyLock<SchemaAttribute> =
@@ -771,6 +841,7 @@ pub static SCHEMA_ATTR_UNIX_PASSWORD_IMPORT: LazyLock<SchemaAttribute> =
         replicated: Replicated::False,
         indexed: false,
         syntax: SyntaxType::Utf8String,
+        ..Default::default()
     });
 
 pub static SCHEMA_ATTR_TOTP_IMPORT: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
@@ -784,6 +855,7 @@ pub static SCHEMA_ATTR_TOTP_IMPORT: LazyLock<SchemaAttribute> = LazyLock::new(||
     replicated: Replicated::False,
     indexed: false,
     syntax: SyntaxType::TotpSecret,
+    ..Default::default()
 });
 
 // LDAP Masking Phantoms
@@ -798,6 +870,7 @@ pub static SCHEMA_ATTR_DN: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAt
     replicated: Replicated::False,
     indexed: false,
     syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_ENTRY_DN: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::EntryDn,
@@ -810,6 +883,7 @@ pub static SCHEMA_ATTR_ENTRY_DN: LazyLock<SchemaAttribute> = LazyLock::new(|| Sc
     replicated: Replicated::False,
     indexed: false,
     syntax: SyntaxType::Utf8StringInsensitive,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_ENTRY_UUID: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::EntryUuid,
@@ -822,6 +896,7 @@ pub static SCHEMA_ATTR_ENTRY_UUID: LazyLock<SchemaAttribute> = LazyLock::new(||
     replicated: Replicated::False,
     indexed: false,
     syntax: SyntaxType::Uuid,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_OBJECT_CLASS: LazyLock<SchemaAttribute> =
     LazyLock::new(|| SchemaAttribute {
@@ -835,6 +910,7 @@ pub static SCHEMA_ATTR_OBJECT_CLASS: LazyLock<SchemaAttribute> =
         replicated: Replicated::False,
         indexed: false,
         syntax: SyntaxType::Utf8StringInsensitive,
+        ..Default::default()
     });
 pub static SCHEMA_ATTR_CN: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::Cn,
@@ -847,6 +923,7 @@ pub static SCHEMA_ATTR_CN: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAt
     replicated: Replicated::False,
     indexed: false,
     syntax: SyntaxType::Utf8StringIname,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_LDAP_KEYS: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::LdapKeys, // keys
@@ -859,6 +936,7 @@ pub static SCHEMA_ATTR_LDAP_KEYS: LazyLock<SchemaAttribute> = LazyLock::new(|| S
     replicated: Replicated::False,
     indexed: false,
     syntax: SyntaxType::SshKey,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_LDAP_SSH_PUBLIC_KEYS: LazyLock<SchemaAttribute> =
     LazyLock::new(|| SchemaAttribute {
@@ -872,6 +950,7 @@ pub static SCHEMA_ATTR_LDAP_SSH_PUBLIC_KEYS: LazyLock<SchemaAttribute> =
         replicated: Replicated::False,
         indexed: false,
         syntax: SyntaxType::SshKey,
+        ..Default::default()
     });
 pub static SCHEMA_ATTR_EMAIL: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::Email,
@@ -884,6 +963,7 @@ pub static SCHEMA_ATTR_EMAIL: LazyLock<SchemaAttribute> = LazyLock::new(|| Schem
     replicated: Replicated::False,
     indexed: false,
     syntax: SyntaxType::EmailAddress,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_EMAIL_PRIMARY: LazyLock<SchemaAttribute> =
     LazyLock::new(|| SchemaAttribute {
@@ -897,6 +977,7 @@ pub static SCHEMA_ATTR_EMAIL_PRIMARY: LazyLock<SchemaAttribute> =
         replicated: Replicated::False,
         indexed: false,
         syntax: SyntaxType::EmailAddress,
+        ..Default::default()
     });
 pub static SCHEMA_ATTR_EMAIL_ALTERNATIVE: LazyLock<SchemaAttribute> =
     LazyLock::new(|| SchemaAttribute {
@@ -910,6 +991,7 @@ pub static SCHEMA_ATTR_EMAIL_ALTERNATIVE: LazyLock<SchemaAttribute> =
         replicated: Replicated::False,
         indexed: false,
         syntax: SyntaxType::EmailAddress,
+        ..Default::default()
     });
 pub static SCHEMA_ATTR_LDAP_EMAIL_ADDRESS: LazyLock<SchemaAttribute> =
     LazyLock::new(|| SchemaAttribute {
@@ -923,6 +1005,7 @@ pub static SCHEMA_ATTR_LDAP_EMAIL_ADDRESS: LazyLock<SchemaAttribute> =
         replicated: Replicated::False,
         indexed: false,
         syntax: SyntaxType::EmailAddress,
+        ..Default::default()
     });
 pub static SCHEMA_ATTR_GECOS: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::Gecos,
@@ -935,6 +1018,7 @@ pub static SCHEMA_ATTR_GECOS: LazyLock<SchemaAttribute> = LazyLock::new(|| Schem
     replicated: Replicated::False,
     indexed: false,
     syntax: SyntaxType::Utf8String,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_UID: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::Uid,
@@ -947,6 +1031,7 @@ pub static SCHEMA_ATTR_UID: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaA
     replicated: Replicated::False,
     indexed: false,
     syntax: SyntaxType::Utf8String,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_UID_NUMBER: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::UidNumber,
@@ -959,6 +1044,7 @@ pub static SCHEMA_ATTR_UID_NUMBER: LazyLock<SchemaAttribute> = LazyLock::new(||
     replicated: Replicated::False,
     indexed: false,
     syntax: SyntaxType::Uint32,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_SUDO_HOST: LazyLock<SchemaAttribute> = LazyLock::new(|| SchemaAttribute {
     name: Attribute::SudoHost,
@@ -971,6 +1057,7 @@ pub static SCHEMA_ATTR_SUDO_HOST: LazyLock<SchemaAttribute> = LazyLock::new(|| S
     replicated: Replicated::False,
     indexed: false,
     syntax: SyntaxType::Utf8String,
+    ..Default::default()
 });
 pub static SCHEMA_ATTR_HOME_DIRECTORY: LazyLock<SchemaAttribute> =
     LazyLock::new(|| SchemaAttribute {
@@ -984,6 +1071,7 @@ pub static SCHEMA_ATTR_HOME_DIRECTORY: LazyLock<SchemaAttribute> =
         replicated: Replicated::False,
         indexed: false,
         syntax: SyntaxType::Utf8String,
+        ..Default::default()
     });
 // end LDAP masking phantoms
 
@@ -1001,6 +1089,7 @@ pub static SCHEMA_CLASS_ATTRIBUTE_TYPE: LazyLock<SchemaClass> = LazyLock::new(||
         Attribute::SyncAllowed,
         Attribute::Index,
         Attribute::Indexed,
+        Attribute::MaximumValues,
     ],
     systemmust: vec![
         Attribute::Class,
@@ -333,6 +333,7 @@ impl Plugins {
     ) -> Result<(), OperationError> {
         refint::ReferentialIntegrity::post_modify(qs, pre_cand, cand, me)?;
         spn::Spn::post_modify(qs, pre_cand, cand, me)?;
+        session::SessionConsistency::post_modify(qs, pre_cand, cand, me)?;
         memberof::MemberOf::post_modify(qs, pre_cand, cand, me)
     }
 
@@ -371,6 +372,7 @@ impl Plugins {
     ) -> Result<(), OperationError> {
         refint::ReferentialIntegrity::post_batch_modify(qs, pre_cand, cand, me)?;
         spn::Spn::post_batch_modify(qs, pre_cand, cand, me)?;
+        session::SessionConsistency::post_batch_modify(qs, pre_cand, cand, me)?;
         memberof::MemberOf::post_batch_modify(qs, pre_cand, cand, me)
     }
 
@@ -477,5 +479,6 @@ impl Plugins {
         run_verify_plugin!(qs, results, dyngroup::DynGroup);
         run_verify_plugin!(qs, results, memberof::MemberOf);
         run_verify_plugin!(qs, results, spn::Spn);
+        run_verify_plugin!(qs, results, session::SessionConsistency);
     }
 }
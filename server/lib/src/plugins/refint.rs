@@ -431,6 +431,7 @@ impl Plugin for ReferentialIntegrity {
         let mut res = Vec::with_capacity(0);
         // For all cands
         for c in &all_cand {
+            let intra_refs = intra_entry_references(c);
             // For all reference in each cand.
             for rtype in ref_types.values() {
                 // If the attribute is present
@@ -438,7 +439,7 @@ impl Plugin for ReferentialIntegrity {
                     // For each value in the set.
                     match vs.as_ref_uuid_iter() {
                         Some(uuid_iter) => {
-                            for vu in uuid_iter {
+                            for vu in uuid_iter.filter(|u| !intra_refs.contains(u)) {
                                 if acu_map.get(&vu).is_none() {
                                     res.push(Err(ConsistencyError::RefintNotUpheld(c.get_id())))
                                 }
@@ -456,6 +457,16 @@ impl Plugin for ReferentialIntegrity {
     }
 }
 
+/// Some references point to values within the same entry rather than to other entries,
+/// such as the parent session of an oauth2 session. These can not be checked for existence
+/// as entries. When the referenced value is removed, the session plugin revokes the
+/// dependent values instead.
+fn intra_entry_references(cand: &EntrySealedCommitted) -> BTreeSet<Uuid> {
+    cand.get_ava_as_oauth2session_map(Attribute::OAuth2Session)
+        .map(|sessions| sessions.values().filter_map(|s| s.parent).collect())
+        .unwrap_or_default()
+}
+
 fn update_reference_set<'a, I>(
     ref_types: &HashMap<Attribute, SchemaAttribute>,
     entry_iter: I,
@@ -483,9 +494,11 @@ where
             }
         });
 
+        let intra_refs = intra_entry_references(cand);
+
         for vs in cand_ref_valuesets {
             if let Some(uuid_iter) = vs.as_ref_uuid_iter() {
-                reference_set.extend(uuid_iter);
+                reference_set.extend(uuid_iter.filter(|u| !intra_refs.contains(u)));
                 Ok(())
             } else {
                 error!(?vs, "reference value could not convert to reference uuid.");
@@ -1233,6 +1246,10 @@ mod tests {
                         state: SessionState::NeverExpires,
                         issued_at,
                         rs_uuid,
+                        granted_scopes: Default::default(),
+                        has_refresh: false,
+                        last_refreshed: None,
+                        origin: None,
                     },
                 )
             ),
@@ -1255,6 +1272,11 @@ mod tests {
                         scope,
                         type_: AuthType::Passkey,
                         ext_metadata: Default::default(),
+                        bound_to: None,
+                        last_active: None,
+                        pinned: false,
+                        service_account: false,
+                        disabled: None,
                     },
                 )
             ),
@@ -1296,6 +1318,155 @@ mod tests {
         assert!(server_txn.commit().is_ok());
     }
 
+    #[qs_test]
+    async fn test_delete_oauth2_rs_revokes_sessions_with_parents(server: &QueryServer) {
+        let curtime = duration_from_epoch_now();
+        let curtime_odt = OffsetDateTime::UNIX_EPOCH + curtime;
+
+        let p = CryptoPolicy::minimum();
+        let cred =
+            Credential::new_password_only(&p, "test_password", OffsetDateTime::UNIX_EPOCH).unwrap();
+        let cred_id = cred.uuid;
+
+        let mut server_txn = server.write(curtime).await.unwrap();
+
+        let tuuid = Uuid::new_v4();
+        let rs_a_uuid = Uuid::new_v4();
+        let rs_b_uuid = Uuid::new_v4();
+
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Name, Value::new_iname("testperson1")),
+            (Attribute::Uuid, Value::Uuid(tuuid)),
+            (Attribute::Description, Value::new_utf8s("testperson1")),
+            (Attribute::DisplayName, Value::new_utf8s("testperson1")),
+            (
+                Attribute::PrimaryCredential,
+                Value::Cred("primary".to_string(), cred.clone())
+            )
+        );
+
+        let rs_entry = |rs_uuid: Uuid, name: &str| {
+            entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (Attribute::Class, EntryClass::Account.to_value()),
+                (
+                    Attribute::Class,
+                    EntryClass::OAuth2ResourceServer.to_value()
+                ),
+                (Attribute::Uuid, Value::Uuid(rs_uuid)),
+                (Attribute::Name, Value::new_iname(name)),
+                (Attribute::DisplayName, Value::new_utf8s(name)),
+                (
+                    Attribute::OAuth2RsOriginLanding,
+                    Value::new_url_s("https://demo.example.com").unwrap()
+                )
+            )
+        };
+
+        let ce = CreateEvent::new_internal(vec![
+            e1,
+            rs_entry(rs_a_uuid, "test_resource_server_a"),
+            rs_entry(rs_b_uuid, "test_resource_server_b"),
+        ]);
+        assert!(server_txn.create(&ce).is_ok());
+
+        // The parent session is not an entry, but refint must accept the reference.
+        let parent_id = Uuid::new_v4();
+        let session_a_child = Uuid::new_v4();
+        let session_a_orphan = Uuid::new_v4();
+        let session_b_child = Uuid::new_v4();
+
+        let oauth2_session = |session_id: Uuid, parent: Option<Uuid>, rs_uuid: Uuid| {
+            Modify::Present(
+                Attribute::OAuth2Session,
+                Value::Oauth2Session(
+                    session_id,
+                    Oauth2Session {
+                        parent,
+                        state: SessionState::NeverExpires,
+                        issued_at: curtime_odt,
+                        rs_uuid,
+                        granted_scopes: Default::default(),
+                        has_refresh: false,
+                        last_refreshed: None,
+                        origin: None,
+                    },
+                ),
+            )
+        };
+
+        let modlist = ModifyList::new_list(vec![
+            oauth2_session(session_a_child, Some(parent_id), rs_a_uuid),
+            oauth2_session(session_a_orphan, None, rs_a_uuid),
+            oauth2_session(session_b_child, Some(parent_id), rs_b_uuid),
+            Modify::Present(
+                Attribute::UserAuthTokenSession,
+                Value::Session(
+                    parent_id,
+                    Session {
+                        label: "label".to_string(),
+                        state: SessionState::NeverExpires,
+                        issued_at: curtime_odt,
+                        issued_by: IdentityId::User(tuuid),
+                        cred_id,
+                        scope: SessionScope::ReadOnly,
+                        type_: AuthType::Passkey,
+                        ext_metadata: Default::default(),
+                        bound_to: None,
+                        last_active: None,
+                        pinned: false,
+                        service_account: false,
+                        disabled: None,
+                    },
+                ),
+            ),
+        ]);
+
+        server_txn
+            .internal_modify(
+                &filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(tuuid))),
+                &modlist,
+            )
+            .expect("Failed to modify user");
+
+        // Delete the first resource server.
+        assert!(server_txn.internal_delete_uuid(rs_a_uuid).is_ok());
+
+        let entry = server_txn.internal_search_uuid(tuuid).expect("failed");
+
+        let sessions = entry
+            .get_ava_as_oauth2session_map(Attribute::OAuth2Session)
+            .expect("No session map found");
+
+        // The sessions of the deleted client are revoked, not removed.
+        assert_eq!(sessions.len(), 3);
+        assert!(matches!(
+            sessions.get(&session_a_child).map(|s| &s.state),
+            Some(SessionState::RevokedAt(_))
+        ));
+        assert!(matches!(
+            sessions.get(&session_a_orphan).map(|s| &s.state),
+            Some(SessionState::RevokedAt(_))
+        ));
+        // Other clients are unaffected.
+        assert!(matches!(
+            sessions.get(&session_b_child).map(|s| &s.state),
+            Some(SessionState::NeverExpires)
+        ));
+
+        // As is the shared parent session.
+        let session = entry
+            .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+            .and_then(|sessions| sessions.get(&parent_id))
+            .expect("No session map found");
+        assert!(matches!(session.state, SessionState::NeverExpires));
+
+        assert!(server_txn.commit().is_ok());
+    }
+
     #[qs_test]
     async fn test_ignore_references_for_regen(server: &QueryServer) {
         // Test that we ignore certain reference types that are specifically
@@ -5,14 +5,31 @@
 //! oauth2 session should also be terminated.
 //!
 //! This plugin is also responsible for invaliding old sessions that are past
-//! their expiry.
+//! their expiry, and for trimming sessions that exceed the schema maximum.
+//!
+//! When a credential is removed or replaced, the sessions it issued are revoked
+//! along with any api tokens those sessions created. If account policy allows,
+//! sessions issued by a replaced password are instead moved to the new password.
+//!
+//! When a modification locks an account, such as by setting its expiry to the past,
+//! its sessions are disabled rather than revoked. If a later modification unlocks the
+//! account the sessions are enabled again, so that users need not log in again.
+//!
+//! Once a modification is applied, the sessions and api tokens that were created,
+//! revoked or removed by it are reported as audit events.
 
 use crate::event::ModifyEvent;
+use crate::idm::account::Account;
+use crate::idm::audit::{AuditEvent, AuditSession, AuditSessionKind, AuditSessionTransition};
+use crate::idm::group::load_account_policy;
 use crate::plugins::Plugin;
 use crate::prelude::*;
-use crate::value::SessionState;
-use std::collections::BTreeSet;
+use crate::schema::SchemaTransaction;
+use crate::value::{ApiToken, SessionState};
+use crate::valueset::revoked_at_timestamp;
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
+use std::time::Duration;
 use time::OffsetDateTime;
 
 pub struct SessionConsistency {}
@@ -25,25 +42,664 @@ impl Plugin for SessionConsistency {
     #[instrument(level = "debug", name = "session_consistency", skip_all)]
     fn pre_modify(
         qs: &mut QueryServerWriteTransaction,
-        _pre_cand: &[Arc<EntrySealedCommitted>],
+        pre_cand: &[Arc<EntrySealedCommitted>],
         cand: &mut Vec<Entry<EntryInvalid, EntryCommitted>>,
-        _me: &ModifyEvent,
+        me: &ModifyEvent,
     ) -> Result<(), OperationError> {
-        Self::modify_inner(qs, cand)
+        Self::credential_removed(qs, pre_cand, cand, &me.ident)?;
+        Self::account_lock_changed(qs, pre_cand, cand);
+        Self::modify_inner(qs, cand)?;
+        Self::api_token_maximum(qs, pre_cand, cand)
     }
 
     #[instrument(level = "debug", name = "session_consistency", skip_all)]
     fn pre_batch_modify(
         qs: &mut QueryServerWriteTransaction,
-        _pre_cand: &[Arc<EntrySealedCommitted>],
+        pre_cand: &[Arc<EntrySealedCommitted>],
         cand: &mut Vec<Entry<EntryInvalid, EntryCommitted>>,
-        _me: &BatchModifyEvent,
+        me: &BatchModifyEvent,
+    ) -> Result<(), OperationError> {
+        Self::credential_removed(qs, pre_cand, cand, &me.ident)?;
+        Self::account_lock_changed(qs, pre_cand, cand);
+        Self::modify_inner(qs, cand)?;
+        Self::api_token_maximum(qs, pre_cand, cand)
+    }
+
+    #[instrument(level = "debug", name = "session_consistency", skip_all)]
+    fn post_modify(
+        qs: &mut QueryServerWriteTransaction,
+        pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &[EntrySealedCommitted],
+        me: &ModifyEvent,
     ) -> Result<(), OperationError> {
-        Self::modify_inner(qs, cand)
+        Self::audit_transitions(qs, pre_cand, cand, &me.ident);
+        Self::revoke_orphaned_api_tokens(qs, pre_cand, cand)
+    }
+
+    #[instrument(level = "debug", name = "session_consistency", skip_all)]
+    fn post_batch_modify(
+        qs: &mut QueryServerWriteTransaction,
+        pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &[EntrySealedCommitted],
+        me: &BatchModifyEvent,
+    ) -> Result<(), OperationError> {
+        Self::audit_transitions(qs, pre_cand, cand, &me.ident);
+        Self::revoke_orphaned_api_tokens(qs, pre_cand, cand)
+    }
+
+    #[instrument(level = "debug", name = "session_consistency::verify", skip_all)]
+    fn verify(qs: &mut QueryServerReadTransaction) -> Vec<Result<(), ConsistencyError>> {
+        let curtime_odt = qs.get_txn_curtime_odt();
+
+        let schema = qs.get_schema();
+        let session_maximum =
+            schema_maximum_values(schema, &Attribute::UserAuthTokenSession, SESSION_MAXIMUM);
+        let oauth2_maximum =
+            schema_maximum_values(schema, &Attribute::OAuth2Session, SESSION_MAXIMUM);
+        let api_token_maximum =
+            schema_maximum_values(schema, &Attribute::ApiTokenSession, API_TOKEN_MAXIMUM);
+
+        let filt_in = filter!(f_or(vec![
+            f_pres(Attribute::UserAuthTokenSession),
+            f_pres(Attribute::OAuth2Session),
+            f_pres(Attribute::ApiTokenSession),
+        ]));
+
+        let all_cand = match qs
+            .internal_search(filt_in)
+            .map_err(|_| Err(ConsistencyError::QueryServerSearchFailure))
+        {
+            Ok(all_cand) => all_cand,
+            Err(e) => return vec![e],
+        };
+
+        let mut r = Vec::with_capacity(0);
+
+        for e in all_cand {
+            let entry = e.get_uuid();
+
+            // Values revoked before the trim cid of the last change to this entry should
+            // have been trimmed by that change.
+            let trim_cid = e
+                .get_changestate()
+                .get_max_cid()
+                .sub_secs(CHANGELOG_MAX_AGE)
+                .ok();
+            let is_past_trim = |state: &SessionState| matches!((state, trim_cid.as_ref()), (SessionState::RevokedAt(cid), Some(trim_cid)) if cid < trim_cid);
+
+            let sessions = e.get_ava_as_session_map(Attribute::UserAuthTokenSession);
+            if let Some(sessions) = sessions {
+                let count = sessions.len();
+                if count > session_maximum {
+                    error!(%entry, count, "Entry exceeds the session maximum");
+                    r.push(ConsistencyError::SessionMaximumExceeded(
+                        entry,
+                        Attribute::UserAuthTokenSession.to_string(),
+                        count,
+                    ));
+                } else if count > session_maximum * 3 / 4 {
+                    warn!(%entry, count, "Entry is nearing the session maximum");
+                }
+
+                let count = sessions
+                    .values()
+                    .filter(|session| is_past_trim(&session.state))
+                    .count();
+                if count > 0 {
+                    error!(%entry, count, "Entry holds revoked sessions that should have been trimmed");
+                    r.push(ConsistencyError::SessionRevokedPastTrim(
+                        entry,
+                        Attribute::UserAuthTokenSession.to_string(),
+                        count,
+                    ));
+                }
+            }
+
+            if let Some(oauth2_sessions) = e.get_ava_as_oauth2session_map(Attribute::OAuth2Session)
+            {
+                // Revoked sessions are retained for replication, so only the remaining
+                // sessions are held to the limit.
+                let count = oauth2_sessions
+                    .values()
+                    .filter(|session| !matches!(session.state, SessionState::RevokedAt(_)))
+                    .count();
+                if count > oauth2_maximum {
+                    error!(%entry, count, "Entry exceeds the oauth2 session maximum");
+                    r.push(ConsistencyError::SessionMaximumExceeded(
+                        entry,
+                        Attribute::OAuth2Session.to_string(),
+                        count,
+                    ));
+                }
+
+                let count = oauth2_sessions
+                    .values()
+                    .filter(|session| is_past_trim(&session.state))
+                    .count();
+                if count > 0 {
+                    error!(%entry, count, "Entry holds revoked oauth2 sessions that should have been trimmed");
+                    r.push(ConsistencyError::SessionRevokedPastTrim(
+                        entry,
+                        Attribute::OAuth2Session.to_string(),
+                        count,
+                    ));
+                }
+
+                // Sessions within the grace window may not have their parent yet, and
+                // revoked sessions may outlive their parent.
+                for (session_id, session) in oauth2_sessions.iter() {
+                    let Some(parent) = session.parent else {
+                        continue;
+                    };
+
+                    if matches!(session.state, SessionState::RevokedAt(_))
+                        || curtime_odt < session.issued_at + AUTH_TOKEN_GRACE_WINDOW
+                        || sessions.is_some_and(|sessions| sessions.contains_key(&parent))
+                    {
+                        continue;
+                    }
+
+                    error!(%entry, %session_id, %parent, "Oauth2 session parent is missing");
+                    r.push(ConsistencyError::OAuth2SessionParentMissing(
+                        entry,
+                        *session_id,
+                        parent,
+                    ));
+                }
+            }
+
+            if let Some(tokens) = e.get_ava_as_apitoken_map(Attribute::ApiTokenSession) {
+                let count = tokens
+                    .values()
+                    .filter(|token| !matches!(token.state, SessionState::RevokedAt(_)))
+                    .count();
+                if count > api_token_maximum {
+                    error!(%entry, count, "Entry exceeds the api token maximum");
+                    r.push(ConsistencyError::SessionMaximumExceeded(
+                        entry,
+                        Attribute::ApiTokenSession.to_string(),
+                        count,
+                    ));
+                } else if count > api_token_maximum * 3 / 4 {
+                    warn!(%entry, count, "Entry is nearing the api token maximum");
+                }
+
+                let count = tokens
+                    .values()
+                    .filter(|token| is_past_trim(&token.state))
+                    .count();
+                if count > 0 {
+                    error!(%entry, count, "Entry holds revoked api tokens that should have been trimmed");
+                    r.push(ConsistencyError::SessionRevokedPastTrim(
+                        entry,
+                        Attribute::ApiTokenSession.to_string(),
+                        count,
+                    ));
+                }
+
+                for (token_id, token) in tokens.iter() {
+                    if token.expiry().is_some_and(|exp| exp < token.issued_at) {
+                        error!(%entry, %token_id, "Api token expires before it was issued");
+                        r.push(ConsistencyError::ApiTokenExpiresBeforeIssue(
+                            entry, *token_id,
+                        ));
+                    }
+                }
+            }
+        }
+
+        r.into_iter().map(Err).collect()
+    }
+}
+
+/// The state of each session or api token of `kind` on this entry.
+fn session_states(
+    entry: &EntrySealedCommitted,
+    kind: AuditSessionKind,
+) -> BTreeMap<&Uuid, &SessionState> {
+    match kind {
+        AuditSessionKind::UserAuthToken => entry
+            .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+            .into_iter()
+            .flatten()
+            .map(|(session_id, session)| (session_id, &session.state))
+            .collect(),
+        AuditSessionKind::OAuth2 => entry
+            .get_ava_as_oauth2session_map(Attribute::OAuth2Session)
+            .into_iter()
+            .flatten()
+            .map(|(session_id, session)| (session_id, &session.state))
+            .collect(),
+        AuditSessionKind::ApiToken => entry
+            .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+            .into_iter()
+            .flatten()
+            .map(|(token_id, token)| (token_id, &token.state))
+            .collect(),
     }
 }
 
+/// Determine how each session changed between the states before and after a modification.
+fn session_transitions<'a>(
+    pre: BTreeMap<&'a Uuid, &'a SessionState>,
+    post: BTreeMap<&'a Uuid, &'a SessionState>,
+) -> impl Iterator<Item = (AuditSessionTransition, Uuid)> + 'a {
+    let removed = pre
+        .keys()
+        .filter(|session_id| !post.contains_key(*session_id))
+        .map(|session_id| (AuditSessionTransition::Removed, **session_id))
+        .collect::<Vec<_>>();
+
+    post.into_iter()
+        .filter_map(move |(session_id, state)| {
+            let transition = match (pre.get(session_id), state) {
+                (None, _) => AuditSessionTransition::Created,
+                (Some(SessionState::RevokedAt(_)), _) | (Some(_), SessionState::NeverExpires) => {
+                    return None
+                }
+                (Some(SessionState::ExpiresAt(exp)), SessionState::RevokedAt(cid))
+                    if *exp <= revoked_at_timestamp(cid) =>
+                {
+                    AuditSessionTransition::Expired
+                }
+                (Some(_), SessionState::RevokedAt(_)) => AuditSessionTransition::Revoked,
+                // A change of expiry, such as a renewal, isn't a transition.
+                (Some(_), SessionState::ExpiresAt(_)) => return None,
+            };
+            Some((transition, *session_id))
+        })
+        .chain(removed)
+}
+
+/// If this entry is within its validity window at `ct`, and so is not locked.
+/// The maximum number of values that schema allows `attr` to hold on a single entry, or
+/// `default` where the schema does not set one.
+fn schema_maximum_values(
+    schema: &dyn SchemaTransaction,
+    attr: &Attribute,
+    default: usize,
+) -> usize {
+    schema
+        .get_attributes()
+        .get(attr)
+        .and_then(|schema_a| schema_a.maximum_values)
+        .map_or(default, |maximum| maximum as usize)
+}
+
+fn within_valid_time<VALID, STATE>(entry: &Entry<VALID, STATE>, ct: Duration) -> bool {
+    Account::check_within_valid_time(
+        ct,
+        entry
+            .get_ava_single_datetime(Attribute::AccountValidFrom)
+            .as_ref(),
+        entry
+            .get_ava_single_datetime(Attribute::AccountExpire)
+            .as_ref(),
+    )
+}
+
+/// The ids of every credential present on this entry that may issue a session.
+fn credential_ids<VALID, STATE>(entry: &Entry<VALID, STATE>) -> BTreeSet<Uuid> {
+    entry
+        .get_ava_single_credential(Attribute::PrimaryCredential)
+        .iter()
+        .chain(
+            entry
+                .get_ava_single_credential(Attribute::UnixPassword)
+                .iter(),
+        )
+        .map(|c| c.uuid)
+        .chain(
+            entry
+                .get_ava_passkeys(Attribute::PassKeys)
+                .iter()
+                .flat_map(|pks| pks.keys().copied()),
+        )
+        .chain(
+            entry
+                .get_ava_attestedpasskeys(Attribute::AttestedPasskeys)
+                .iter()
+                .flat_map(|pks| pks.keys().copied()),
+        )
+        .chain(entry.get_ava_single_uuid(Attribute::OAuth2AccountCredentialUuid))
+        // Sessions from a token exchange are derived from the api token that was exchanged.
+        .chain(
+            entry
+                .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+                .into_iter()
+                .flatten()
+                .filter(|(_, token)| !token.is_revoked())
+                .map(|(token_id, _)| *token_id),
+        )
+        .collect()
+}
+
 impl SessionConsistency {
+    /// Revoke the sessions of any credential that this modification removes. Where the
+    /// primary password is replaced and account policy allows it, the sessions are moved
+    /// to the new password instead. A session that the change is made on behalf of is always
+    /// moved to the new password.
+    fn credential_removed(
+        qs: &mut QueryServerWriteTransaction,
+        pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &mut [Entry<EntryInvalid, EntryCommitted>],
+        ident: &Identity,
+    ) -> Result<(), OperationError> {
+        let pre_cand: BTreeMap<Uuid, &Arc<EntrySealedCommitted>> =
+            pre_cand.iter().map(|e| (e.get_uuid(), e)).collect();
+
+        // A change made on behalf of a session, such as a credential update that the session
+        // initiated, moves that session to the new password rather than revoking it.
+        let retained_session_id = match ident.origin {
+            IdentType::Internal(_) if ident.get_session_id() != UUID_INTERNAL_SESSION_ID => {
+                Some(ident.get_session_id())
+            }
+            _ => None,
+        };
+
+        for entry in cand.iter_mut() {
+            let Some(pre) = entry.get_uuid().and_then(|u| pre_cand.get(&u)) else {
+                continue;
+            };
+
+            if entry.get_ava_set(Attribute::UserAuthTokenSession).is_none() {
+                continue;
+            }
+
+            let removed: BTreeSet<Uuid> = credential_ids(pre.as_ref())
+                .difference(&credential_ids(entry))
+                .copied()
+                .collect();
+
+            if removed.is_empty() {
+                continue;
+            }
+
+            let rotated = match (
+                pre.get_ava_single_credential(Attribute::PrimaryCredential),
+                entry.get_ava_single_credential(Attribute::PrimaryCredential),
+            ) {
+                // The credential id rotates on any change to invalidate sessions, so only a
+                // change of the password itself moves sessions to the new credential.
+                (Some(pre_primary), Some(primary))
+                    if pre_primary.uuid != primary.uuid
+                        && pre_primary.password_differs(primary) =>
+                {
+                    Some((pre_primary.uuid, primary.uuid))
+                }
+                _ => None,
+            };
+
+            let retain = rotated.is_some()
+                && load_account_policy(pre.as_ref(), qs)?.retain_sessions_on_password_change()
+                    == Some(true);
+
+            let cid = qs.get_cid().clone();
+
+            if let Some(sessions) = entry
+                .get_ava_mut(Attribute::UserAuthTokenSession)
+                .and_then(|vs| vs.as_session_set_mut())
+            {
+                for cred_id in removed {
+                    match rotated {
+                        Some((from, to)) if retain && from == cred_id => {
+                            let moved = sessions.rebind_cred_id(from, to);
+                            info!(%cred_id, moved, "Retaining auth sessions of replaced password");
+                        }
+                        _ => {
+                            if let (Some((from, to)), Some(session_id)) =
+                                (rotated, retained_session_id)
+                            {
+                                if from == cred_id
+                                    && sessions.rebind_session_cred_id(&session_id, from, to)
+                                {
+                                    info!(%session_id, "Retaining the auth session that replaced the password");
+                                }
+                            }
+
+                            let revoked = sessions.revoke_by_cred_id(cred_id, &cid);
+                            info!(%cred_id, revoked, "Revoking auth sessions of removed credential");
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Disable the sessions of any account that this modification locks, and enable the
+    /// sessions of any account that it unlocks. An account that locks due to the passage
+    /// of time is not changed, as its sessions are rejected by the validity check anyway.
+    fn account_lock_changed(
+        qs: &mut QueryServerWriteTransaction,
+        pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &mut [Entry<EntryInvalid, EntryCommitted>],
+    ) {
+        let pre_cand: BTreeMap<Uuid, &Arc<EntrySealedCommitted>> =
+            pre_cand.iter().map(|e| (e.get_uuid(), e)).collect();
+
+        let ct = qs.get_curtime();
+        let cid = qs.get_cid().clone();
+
+        for entry in cand.iter_mut() {
+            let Some(pre) = entry.get_uuid().and_then(|u| pre_cand.get(&u)) else {
+                continue;
+            };
+
+            let was_valid = within_valid_time(pre.as_ref(), ct);
+            let is_valid = within_valid_time(entry, ct);
+
+            if was_valid == is_valid {
+                continue;
+            }
+
+            let Some(sessions) = entry
+                .get_ava_mut(Attribute::UserAuthTokenSession)
+                .and_then(|vs| vs.as_session_set_mut())
+            else {
+                continue;
+            };
+
+            if is_valid {
+                let enabled = sessions.enable_all(&cid);
+                info!(enabled, "Enabling auth sessions of unlocked account");
+            } else {
+                let disabled = sessions.disable_all(&cid);
+                info!(disabled, "Disabling auth sessions of locked account");
+            }
+        }
+    }
+
+    /// Queue an audit event for each kind of transition that sessions and api tokens made in
+    /// this modification. Each event lists every session that made the transition, so that
+    /// bulk operations such as a purge or trim are reported once.
+    fn audit_transitions(
+        qs: &mut QueryServerWriteTransaction,
+        pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &[EntrySealedCommitted],
+        ident: &Identity,
+    ) {
+        let pre_cand: BTreeMap<Uuid, &Arc<EntrySealedCommitted>> =
+            pre_cand.iter().map(|e| (e.get_uuid(), e)).collect();
+
+        let mut transitions: BTreeMap<_, Vec<AuditSession>> = BTreeMap::new();
+
+        for entry in cand {
+            let entry_uuid = entry.get_uuid();
+            let pre = pre_cand.get(&entry_uuid);
+
+            for kind in [
+                AuditSessionKind::UserAuthToken,
+                AuditSessionKind::OAuth2,
+                AuditSessionKind::ApiToken,
+            ] {
+                let pre_states = pre.map(|pre| session_states(pre, kind)).unwrap_or_default();
+
+                for (transition, id) in session_transitions(pre_states, session_states(entry, kind))
+                {
+                    transitions
+                        .entry((kind, transition))
+                        .or_default()
+                        .push(AuditSession {
+                            entry: entry_uuid,
+                            id,
+                        });
+                }
+            }
+        }
+
+        let actor = ident.get_event_origin_id();
+        let cid = qs.get_cid().clone();
+
+        for ((kind, transition), sessions) in transitions {
+            qs.queue_audit_event(AuditEvent::SessionTransition {
+                kind,
+                transition,
+                actor: actor.clone(),
+                cid: cid.clone(),
+                sessions,
+            });
+        }
+    }
+
+    /// Revoke the api tokens that were created by a session which this modification
+    /// revoked because its credential was removed.
+    fn revoke_orphaned_api_tokens(
+        qs: &mut QueryServerWriteTransaction,
+        pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &[EntrySealedCommitted],
+    ) -> Result<(), OperationError> {
+        let pre_cand: BTreeMap<Uuid, &Arc<EntrySealedCommitted>> =
+            pre_cand.iter().map(|e| (e.get_uuid(), e)).collect();
+
+        let mut revoked_sessions = BTreeSet::new();
+
+        for entry in cand {
+            let Some(pre) = pre_cand.get(&entry.get_uuid()) else {
+                continue;
+            };
+
+            let (Some(pre_sessions), Some(sessions)) = (
+                pre.get_ava_as_session_map(Attribute::UserAuthTokenSession),
+                entry.get_ava_as_session_map(Attribute::UserAuthTokenSession),
+            ) else {
+                continue;
+            };
+
+            let cred_ids = credential_ids(entry);
+
+            revoked_sessions.extend(
+                sessions
+                    .iter()
+                    .filter(|(session_id, session)| {
+                        matches!(session.state, SessionState::RevokedAt(_))
+                            && !cred_ids.contains(&session.cred_id)
+                            && pre_sessions.get(session_id).is_some_and(|pre_session| {
+                                !matches!(pre_session.state, SessionState::RevokedAt(_))
+                            })
+                    })
+                    .map(|(session_id, _)| *session_id),
+            );
+        }
+
+        if revoked_sessions.is_empty() {
+            return Ok(());
+        }
+
+        // Tokens are indexed by the session that issued them, so only their holders are loaded.
+        let token_holders = qs.internal_search(filter!(f_or(
+            revoked_sessions
+                .iter()
+                .map(|session_id| f_eq(
+                    Attribute::ApiTokenSession,
+                    PartialValue::Refer(*session_id)
+                ))
+                .collect()
+        )))?;
+
+        for holder in token_holders {
+            let revoke: Vec<_> = holder
+                .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+                .map(|tokens| {
+                    tokens
+                        .iter()
+                        .filter(|(_, token)| {
+                            !matches!(token.state, SessionState::RevokedAt(_))
+                                && token
+                                    .parent_session
+                                    .is_some_and(|parent| revoked_sessions.contains(&parent))
+                        })
+                        .map(|(token_id, _)| {
+                            info!(%token_id, "Revoking api token whose issuing session was revoked");
+                            Modify::Removed(Attribute::ApiTokenSession, PartialValue::Refer(*token_id))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            if !revoke.is_empty() {
+                qs.internal_modify_uuid(holder.get_uuid(), &ModifyList::new_list(revoke))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Refuse a modification that adds unrevoked api tokens to an entry beyond the schema
+    /// maximum. Unlike sessions, tokens are long lived secrets so we refuse to exceed the
+    /// limit rather than evicting. An entry that already exceeds the limit, such as after
+    /// the schema is changed, may still revoke its tokens.
+    fn api_token_maximum(
+        qs: &QueryServerWriteTransaction,
+        pre_cand: &[Arc<EntrySealedCommitted>],
+        cand: &[Entry<EntryInvalid, EntryCommitted>],
+    ) -> Result<(), OperationError> {
+        let maximum = schema_maximum_values(
+            qs.get_schema(),
+            &Attribute::ApiTokenSession,
+            API_TOKEN_MAXIMUM,
+        );
+
+        let pre_cand: BTreeMap<Uuid, &Arc<EntrySealedCommitted>> =
+            pre_cand.iter().map(|e| (e.get_uuid(), e)).collect();
+
+        let live_tokens = |tokens: Option<&BTreeMap<Uuid, ApiToken>>| {
+            tokens.map_or(0, |tokens| {
+                tokens
+                    .values()
+                    .filter(|token| !matches!(token.state, SessionState::RevokedAt(_)))
+                    .count()
+            })
+        };
+
+        for entry in cand.iter() {
+            let count = live_tokens(entry.get_ava_as_apitoken_map(Attribute::ApiTokenSession));
+            if count <= maximum {
+                continue;
+            }
+
+            let pre_count = entry
+                .get_uuid()
+                .and_then(|u| pre_cand.get(&u))
+                .map_or(0, |pre| {
+                    live_tokens(pre.get_ava_as_apitoken_map(Attribute::ApiTokenSession))
+                });
+
+            if count > pre_count {
+                error!(
+                    entry = ?entry.get_uuid(),
+                    count,
+                    maximum,
+                    "Refusing to add api tokens, entry has reached the api token maximum"
+                );
+                return Err(OperationError::ResourceLimit);
+            }
+        }
+
+        Ok(())
+    }
+
     fn modify_inner<T: Clone + std::fmt::Debug>(
         qs: &mut QueryServerWriteTransaction,
         cand: &mut [Entry<EntryInvalid, T>],
@@ -52,29 +708,24 @@ impl SessionConsistency {
         let curtime_odt = OffsetDateTime::UNIX_EPOCH + curtime;
         trace!(%curtime_odt);
 
+        let cid = qs.get_cid().clone();
+        let trim_cid = qs.trim_cid().clone();
+
+        let maximum_values: Vec<(Attribute, usize)> = {
+            let schema = qs.get_schema();
+            [Attribute::UserAuthTokenSession, Attribute::OAuth2Session]
+                .into_iter()
+                .map(|attr| {
+                    let maximum = schema_maximum_values(schema, &attr, SESSION_MAXIMUM);
+                    (attr, maximum)
+                })
+                .collect()
+        };
+
         // We need to assert a number of properties. We must do these *in order*.
         cand.iter_mut().try_for_each(|entry| {
             // * If the session's credential is no longer on the account, we remove the session.
-            let cred_ids: BTreeSet<Uuid> =
-                entry
-                    .get_ava_single_credential(Attribute::PrimaryCredential)
-                        .iter()
-                        .map(|c| c.uuid)
-
-                .chain(
-                    entry.get_ava_passkeys(Attribute::PassKeys)
-                        .iter()
-                        .flat_map(|pks| pks.keys().copied())
-                )
-                .chain(
-                    entry.get_ava_attestedpasskeys(Attribute::AttestedPasskeys)
-                        .iter()
-                        .flat_map(|pks| pks.keys().copied())
-                )
-                .chain(
-                    entry.get_ava_single_uuid(Attribute::OAuth2AccountCredentialUuid)
-                )
-                .collect();
+            let cred_ids = credential_ids(entry);
 
             let invalidate: Option<BTreeSet<_>> = entry.get_ava_as_session_map(Attribute::UserAuthTokenSession)
                 .map(|sessions| {
@@ -122,65 +773,72 @@ impl SessionConsistency {
             }
 
             // * If an oauth2 session is past it's expiry, remove it.
-            // * If an oauth2 session is past the grace window, and no parent session exists, remove it.
-            let oauth2_remove: Option<BTreeSet<_>> = entry.get_ava_as_oauth2session_map(Attribute::OAuth2Session).map(|oauth2_sessions| {
-                // If we have oauth2 sessions, we need to be able to lookup if sessions exist in the uat.
-                let sessions = entry.get_ava_as_session_map(Attribute::UserAuthTokenSession);
-
-                oauth2_sessions.iter().filter_map(|(o2_session_id, session)| {
-                    trace!(?o2_session_id, ?session);
-                    match &session.state {
-                        SessionState::ExpiresAt(exp) if exp <= &curtime_odt => {
-                            info!(%o2_session_id, "Removing expired oauth2 session");
-                            Some(PartialValue::Refer(*o2_session_id))
-                        }
-                        SessionState::RevokedAt(_) => {
-                            // no-op, it's already revoked.
-                            trace!("Skip already revoked session");
-                            None
-                        }
-                        _ => {
-                            // Okay, now check the issued / grace time for parent enforcement.
-                                if sessions.map(|session_map| {
-                                    if let Some(parent_session_id) = session.parent.as_ref() {
-                                        // A parent session id exists - validate it exists in the account.
-                                        if let Some(parent_session) = session_map.get(parent_session_id) {
-                                            // Only match non-revoked sessions
-                                            !matches!(parent_session.state, SessionState::RevokedAt(_))
-                                        } else {
-                                            // not found
-                                            false
-                                        }
-                                    } else {
-                                        // The session specifically has no parent session and so is
-                                        // not bounded by it's presence.
-                                        true
-                                    }
-                                }).unwrap_or(false) {
-                                    // The parent exists and is still valid, go ahead
-                                    debug!("Parent session remains valid.");
-                                    None
-                                } else {
-                                    // Can't find the parent. Are we within grace window
-                                    if session.issued_at + AUTH_TOKEN_GRACE_WINDOW <= curtime_odt {
-                                        info!(%o2_session_id, parent_id = ?session.parent, "Removing orphaned oauth2 session");
-                                        Some(PartialValue::Refer(*o2_session_id))
-                                    } else {
-                                        // Grace window is still in effect
-                                        debug!("Not enforcing parent session consistency on session within grace window");
-                                        None
-                                    }
-
+            let oauth2_expired: Option<BTreeSet<_>> = entry
+                .get_ava_as_oauth2session_map(Attribute::OAuth2Session)
+                .map(|oauth2_sessions| {
+                    oauth2_sessions
+                        .iter()
+                        .filter_map(|(o2_session_id, session)| {
+                            trace!(?o2_session_id, ?session);
+                            match &session.state {
+                                SessionState::ExpiresAt(exp) if exp <= &curtime_odt => {
+                                    info!(%o2_session_id, "Removing expired oauth2 session");
+                                    Some(PartialValue::Refer(*o2_session_id))
                                 }
-                        }
-                    }
+                                _ => None,
+                            }
+                        })
+                        .collect()
+                });
 
+            if let Some(oauth2_expired) = oauth2_expired.as_ref() {
+                entry.remove_avas(Attribute::OAuth2Session, oauth2_expired);
+            }
+
+            // * If an oauth2 session is past the grace window, and no parent session exists, remove it.
+            let live_parents: BTreeSet<Uuid> = entry
+                .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+                .map(|sessions| {
+                    sessions
+                        .iter()
+                        .filter(|(_, session)| {
+                            !matches!(session.state, SessionState::RevokedAt(_))
+                        })
+                        .map(|(session_id, _)| *session_id)
+                        .collect()
                 })
-                .collect()
-            });
+                .unwrap_or_default();
 
-            if let Some(oauth2_remove) = oauth2_remove.as_ref() {
-                entry.remove_avas(Attribute::OAuth2Session, oauth2_remove);
+            let orphaned = entry
+                .get_ava_set(Attribute::OAuth2Session)
+                .and_then(|vs| vs.as_oauth2session_set())
+                .is_some_and(|vs| {
+                    vs.has_orphaned(&live_parents, curtime_odt, AUTH_TOKEN_GRACE_WINDOW)
+                });
+
+            if orphaned {
+                if let Some(vs) = entry
+                    .get_ava_mut(Attribute::OAuth2Session)
+                    .and_then(|vs| vs.as_oauth2session_set_mut())
+                {
+                    vs.prune_orphaned(&live_parents, curtime_odt, AUTH_TOKEN_GRACE_WINDOW, &cid);
+                }
+            }
+
+            // * If there are more sessions than the schema allows, trim the oldest.
+            for (attr, maximum) in maximum_values.iter() {
+                let exceeded = entry
+                    .get_ava_set(attr)
+                    .is_some_and(|vs| vs.len() > *maximum);
+
+                if exceeded {
+                    if let Some(vs) = entry.get_ava_mut(attr) {
+                        let trimmed = vs.trim_to_maximum(*maximum, &cid, &trim_cid);
+                        if trimmed > 0 {
+                            info!(%attr, trimmed, "Trimmed sessions exceeding the schema maximum");
+                        }
+                    }
+                }
             }
 
             Ok(())
@@ -193,8 +851,10 @@ mod tests {
     use crate::prelude::*;
 
     use crate::event::CreateEvent;
-    use crate::value::{AuthType, Oauth2Session, Session, SessionState};
+    use crate::idm::audit::{AuditEvent, AuditSession, AuditSessionKind, AuditSessionTransition};
+    use crate::value::{ApiToken, AuthType, Oauth2Session, Session, SessionScope, SessionState};
     use kanidm_proto::constants::OAUTH2_SCOPE_OPENID;
+    use kanidm_proto::internal::ConsistencyError;
     use std::time::Duration;
     use time::OffsetDateTime;
     use uuid::uuid;
@@ -262,6 +922,11 @@ mod tests {
                 scope,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                bound_to: None,
+                last_active: None,
+                pinned: false,
+                service_account: false,
+                disabled: None,
             },
         );
 
@@ -405,6 +1070,10 @@ mod tests {
                         state,
                         issued_at,
                         rs_uuid,
+                        granted_scopes: Default::default(),
+                        has_refresh: false,
+                        last_refreshed: None,
+                        origin: None,
                     },
                 )
             ),
@@ -427,6 +1096,11 @@ mod tests {
                         scope,
                         type_: AuthType::Passkey,
                         ext_metadata: Default::default(),
+                        bound_to: None,
+                        last_active: None,
+                        pinned: false,
+                        service_account: false,
+                        disabled: None,
                     },
                 )
             ),
@@ -580,6 +1254,10 @@ mod tests {
                         state: SessionState::NeverExpires,
                         issued_at,
                         rs_uuid,
+                        granted_scopes: Default::default(),
+                        has_refresh: false,
+                        last_refreshed: None,
+                        origin: None,
                     },
                 )
             ),
@@ -602,6 +1280,11 @@ mod tests {
                         scope,
                         type_: AuthType::Passkey,
                         ext_metadata: Default::default(),
+                        bound_to: None,
+                        last_active: None,
+                        pinned: false,
+                        service_account: false,
+                        disabled: None,
                     },
                 )
             ),
@@ -742,6 +1425,10 @@ mod tests {
                 state: SessionState::NeverExpires,
                 issued_at,
                 rs_uuid,
+                granted_scopes: Default::default(),
+                has_refresh: false,
+                last_refreshed: None,
+                origin: None,
             },
         );
 
@@ -852,6 +1539,11 @@ mod tests {
                 scope,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                bound_to: None,
+                last_active: None,
+                pinned: false,
+                service_account: false,
+                disabled: None,
             },
         );
 
@@ -902,4 +1594,671 @@ mod tests {
 
         assert!(server_txn.commit().is_ok());
     }
+
+    #[qs_test]
+    async fn test_session_consistency_disabled_when_account_locked(server: &QueryServer) {
+        let curtime = duration_from_epoch_now();
+        let curtime_odt = OffsetDateTime::UNIX_EPOCH + curtime;
+
+        let p = CryptoPolicy::minimum();
+        let cred =
+            Credential::new_password_only(&p, "test_password", OffsetDateTime::UNIX_EPOCH).unwrap();
+
+        let mut server_txn = server.write(curtime).await.unwrap();
+
+        let tuuid = Uuid::new_v4();
+        let session_id = Uuid::new_v4();
+
+        let session = Session::builder(curtime_odt, AuthType::Password)
+            .issued_by(IdentityId::User(tuuid))
+            .cred_id(cred.uuid)
+            .build_value(session_id)
+            .expect("Failed to build session");
+
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Name, Value::new_iname("testperson1")),
+            (Attribute::Uuid, Value::Uuid(tuuid)),
+            (Attribute::Description, Value::new_utf8s("testperson1")),
+            (Attribute::DisplayName, Value::new_utf8s("testperson1")),
+            (
+                Attribute::PrimaryCredential,
+                Value::Cred("primary".to_string(), cred.clone())
+            )
+        );
+
+        let ce = CreateEvent::new_internal(vec![e1]);
+        assert!(server_txn.create(&ce).is_ok());
+
+        server_txn
+            .internal_modify_uuid(
+                tuuid,
+                &ModifyList::new_append(Attribute::UserAuthTokenSession, session),
+            )
+            .expect("Failed to modify user");
+
+        let get_session = |server_txn: &mut QueryServerWriteTransaction| {
+            server_txn
+                .internal_search_uuid(tuuid)
+                .expect("failed")
+                .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+                .and_then(|sessions| sessions.get(&session_id))
+                .cloned()
+                .expect("No session found")
+        };
+
+        let initial = get_session(&mut server_txn);
+        assert!(!initial.is_disabled());
+
+        // Lock the account by expiring it.
+        server_txn
+            .internal_modify_uuid(
+                tuuid,
+                &ModifyList::new_purge_and_set(
+                    Attribute::AccountExpire,
+                    Value::new_datetime_epoch(curtime - Duration::from_secs(1)),
+                ),
+            )
+            .expect("Failed to modify user");
+
+        let session = get_session(&mut server_txn);
+        assert!(session.is_disabled());
+        assert_eq!(session.state, initial.state);
+
+        // Unrelated changes to a locked account leave the session disabled.
+        server_txn
+            .internal_modify_uuid(
+                tuuid,
+                &ModifyList::new_purge_and_set(Attribute::Description, Value::new_utf8s("locked")),
+            )
+            .expect("Failed to modify user");
+
+        assert!(get_session(&mut server_txn).is_disabled());
+
+        // Unlocking the account enables the session again.
+        server_txn
+            .internal_modify_uuid(tuuid, &ModifyList::new_purge(Attribute::AccountExpire))
+            .expect("Failed to modify user");
+
+        let session = get_session(&mut server_txn);
+        assert!(!session.is_disabled());
+        assert!(matches!(
+            session.disabled,
+            Some(crate::value::SessionDisabled::EnabledAt(_))
+        ));
+        assert_eq!(session.state, initial.state);
+
+        assert!(server_txn.commit().is_ok());
+    }
+
+    #[qs_test]
+    async fn test_session_consistency_verify(server: &QueryServer) {
+        let curtime = duration_from_epoch_now();
+        let curtime_odt = OffsetDateTime::UNIX_EPOCH + curtime;
+
+        let p = CryptoPolicy::minimum();
+        let cred =
+            Credential::new_password_only(&p, "test_password", OffsetDateTime::UNIX_EPOCH).unwrap();
+
+        let session = |state: SessionState| Session {
+            label: "label".to_string(),
+            state,
+            issued_at: curtime_odt,
+            issued_by: IdentityId::Internal(UUID_SYSTEM),
+            cred_id: cred.uuid,
+            scope: SessionScope::ReadOnly,
+            type_: AuthType::Passkey,
+            ext_metadata: Default::default(),
+            bound_to: None,
+            last_active: None,
+            pinned: false,
+            service_account: false,
+            disabled: None,
+        };
+
+        let account = |name: &str, uuid: Uuid| {
+            entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (Attribute::Class, EntryClass::Account.to_value()),
+                (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+                (Attribute::Name, Value::new_iname(name)),
+                (Attribute::Uuid, Value::Uuid(uuid)),
+                (Attribute::DisplayName, Value::new_utf8s(name)),
+                (
+                    Attribute::PrimaryCredential,
+                    Value::Cred("primary".to_string(), cred.clone())
+                )
+            )
+        };
+
+        let rs_uuid = Uuid::new_v4();
+        let e_rs = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServer.to_value()
+            ),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServerBasic.to_value()
+            ),
+            (Attribute::Uuid, Value::Uuid(rs_uuid)),
+            (Attribute::Name, Value::new_iname("test_resource_server")),
+            (
+                Attribute::DisplayName,
+                Value::new_utf8s("test_resource_server")
+            ),
+            (
+                Attribute::OAuth2RsOriginLanding,
+                Value::new_url_s("https://demo.example.com").unwrap()
+            )
+        );
+
+        // The anomalies are trimmed or rejected on write, so they are written to the
+        // backend directly as if they had been stored by an older or faulty server.
+        let tamper = |server_txn: &mut QueryServerWriteTransaction,
+                      uuid: Uuid,
+                      attr: Attribute,
+                      values: Vec<Value>| {
+            let cid = server_txn.get_cid().clone();
+            let pre = server_txn
+                .internal_search_uuid(uuid)
+                .expect("Failed to find entry");
+            let mut post = pre
+                .as_ref()
+                .clone()
+                .invalidate(cid.clone(), &Cid::new_zero());
+            for value in values {
+                post.add_ava(attr.clone(), value);
+            }
+            server_txn
+                .be_txn
+                .modify(&cid, &[pre], &[post.into_sealed_committed()])
+                .expect("Failed to write entry");
+        };
+
+        // More unpinned sessions than the maximum.
+        let over_limit = Uuid::new_v4();
+        let over_limit_sessions = (0..=SESSION_MAXIMUM)
+            .map(|_| Value::Session(Uuid::new_v4(), session(SessionState::NeverExpires)))
+            .collect();
+
+        // A revocation far older than the trim window of the entries last change.
+        let past_trim = Uuid::new_v4();
+        let past_trim_sessions = vec![Value::Session(
+            Uuid::new_v4(),
+            session(SessionState::RevokedAt(Cid::new_count(1))),
+        )];
+
+        // An oauth2 session past the grace window whose parent does not exist.
+        let orphaned = Uuid::new_v4();
+        let (o2_session_id, parent_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let orphaned_sessions = vec![Value::Oauth2Session(
+            o2_session_id,
+            Oauth2Session {
+                parent: Some(parent_id),
+                state: SessionState::NeverExpires,
+                issued_at: curtime_odt - AUTH_TOKEN_GRACE_WINDOW - Duration::from_secs(1),
+                rs_uuid,
+                granted_scopes: Default::default(),
+                has_refresh: false,
+                last_refreshed: None,
+                origin: None,
+            },
+        )];
+
+        // An api token that expires before it was issued.
+        let bad_token = Uuid::new_v4();
+        let token_id = Uuid::new_v4();
+        let bad_tokens = vec![Value::ApiToken(
+            token_id,
+            ApiToken::builder(curtime_odt)
+                .label("token".to_string())
+                .state(SessionState::ExpiresAt(
+                    curtime_odt - Duration::from_secs(60),
+                ))
+                .build(),
+        )];
+
+        let fixtures = [
+            (
+                over_limit,
+                Attribute::UserAuthTokenSession,
+                over_limit_sessions,
+            ),
+            (
+                past_trim,
+                Attribute::UserAuthTokenSession,
+                past_trim_sessions,
+            ),
+            (orphaned, Attribute::OAuth2Session, orphaned_sessions),
+            (bad_token, Attribute::ApiTokenSession, bad_tokens),
+        ];
+
+        let mut server_txn = server.write(curtime).await.unwrap();
+
+        let entries = fixtures
+            .iter()
+            .enumerate()
+            .map(|(i, (uuid, _, _))| account(&format!("testaccount{i}"), *uuid))
+            .chain(std::iter::once(e_rs))
+            .collect();
+        server_txn
+            .internal_create(entries)
+            .expect("Failed to create entries");
+
+        for (uuid, attr, values) in fixtures {
+            tamper(&mut server_txn, uuid, attr, values);
+        }
+        server_txn.commit().unwrap();
+
+        let mut server_txn = server.read().await.unwrap();
+        let mut results: Vec<_> = server_txn
+            .verify()
+            .into_iter()
+            .filter_map(Result::err)
+            .collect();
+        drop(server_txn);
+
+        results.sort_by_key(|err| format!("{err:?}"));
+
+        let mut expected = vec![
+            ConsistencyError::SessionMaximumExceeded(
+                over_limit,
+                Attribute::UserAuthTokenSession.to_string(),
+                SESSION_MAXIMUM + 1,
+            ),
+            ConsistencyError::SessionRevokedPastTrim(
+                past_trim,
+                Attribute::UserAuthTokenSession.to_string(),
+                1,
+            ),
+            ConsistencyError::OAuth2SessionParentMissing(orphaned, o2_session_id, parent_id),
+            ConsistencyError::ApiTokenExpiresBeforeIssue(bad_token, token_id),
+        ];
+        expected.sort_by_key(|err| format!("{err:?}"));
+
+        assert_eq!(results, expected);
+
+        // Remove the anomalies so that the verification at the end of the test passes.
+        let mut server_txn = server.write(curtime).await.unwrap();
+        server_txn
+            .internal_modify_uuid(
+                bad_token,
+                &ModifyList::new_purge(Attribute::ApiTokenSession),
+            )
+            .expect("Failed to modify entry");
+        for uuid in [over_limit, past_trim, orphaned, bad_token] {
+            server_txn
+                .internal_delete_uuid(uuid)
+                .expect("Failed to delete entry");
+        }
+        server_txn.commit().unwrap();
+    }
+
+    #[qs_test]
+    async fn test_session_consistency_oauth2_maximum_values(server: &QueryServer) {
+        let curtime = duration_from_epoch_now();
+        let curtime_odt = OffsetDateTime::UNIX_EPOCH + curtime;
+
+        let mut server_txn = server.write(curtime).await.unwrap();
+
+        let tuuid = uuid!("cc8e95b4-c24f-4d68-ba54-8bed76f63930");
+        let rs_uuid = Uuid::new_v4();
+
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Name, Value::new_iname("testperson1")),
+            (Attribute::Uuid, Value::Uuid(tuuid)),
+            (Attribute::Description, Value::new_utf8s("testperson1")),
+            (Attribute::DisplayName, Value::new_utf8s("testperson1"))
+        );
+
+        let e2 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServer.to_value()
+            ),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServerBasic.to_value()
+            ),
+            (Attribute::Uuid, Value::Uuid(rs_uuid)),
+            (Attribute::Name, Value::new_iname("test_resource_server")),
+            (
+                Attribute::DisplayName,
+                Value::new_utf8s("test_resource_server")
+            ),
+            (
+                Attribute::OAuth2RsOriginLanding,
+                Value::new_url_s("https://demo.example.com").unwrap()
+            ),
+            (
+                Attribute::OAuth2RsScopeMap,
+                Value::new_oauthscopemap(
+                    UUID_IDM_ALL_ACCOUNTS,
+                    btreeset![OAUTH2_SCOPE_OPENID.to_string()]
+                )
+                .expect("invalid oauthscope")
+            )
+        );
+
+        let ce = CreateEvent::new_internal(vec![e1, e2]);
+        assert!(server_txn.create(&ce).is_ok());
+
+        // Add more sessions than the schema allows, each issued one second apart.
+        let session_ids: Vec<Uuid> = (0..SESSION_MAXIMUM + 2).map(|_| Uuid::new_v4()).collect();
+
+        let modlist = ModifyList::new_list(
+            session_ids
+                .iter()
+                .enumerate()
+                .map(|(i, session_id)| {
+                    Modify::Present(
+                        Attribute::OAuth2Session,
+                        Value::Oauth2Session(
+                            *session_id,
+                            Oauth2Session {
+                                parent: None,
+                                state: SessionState::NeverExpires,
+                                issued_at: curtime_odt + Duration::from_secs(i as u64),
+                                rs_uuid,
+                                granted_scopes: Default::default(),
+                                has_refresh: false,
+                                last_refreshed: None,
+                                origin: None,
+                            },
+                        ),
+                    )
+                })
+                .collect(),
+        );
+
+        server_txn
+            .internal_modify(
+                &filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(tuuid))),
+                &modlist,
+            )
+            .expect("Failed to modify user");
+
+        let entry = server_txn.internal_search_uuid(tuuid).expect("failed");
+        let sessions = entry
+            .get_ava_as_oauth2session_map(Attribute::OAuth2Session)
+            .expect("No oauth2 sessions");
+
+        // The two oldest sessions were revoked, so that the revocation replicates.
+        let is_revoked = |session_id: &Uuid| {
+            sessions
+                .get(session_id)
+                .is_some_and(|session| matches!(session.state, SessionState::RevokedAt(_)))
+        };
+        assert_eq!(sessions.len(), SESSION_MAXIMUM + 2);
+        assert!(is_revoked(&session_ids[0]));
+        assert!(is_revoked(&session_ids[1]));
+        assert!(!is_revoked(&session_ids[2]));
+
+        assert!(server_txn.commit().is_ok());
+    }
+
+    #[qs_test]
+    async fn test_session_consistency_api_token_maximum_values(server: &QueryServer) {
+        let curtime = duration_from_epoch_now();
+        let curtime_odt = OffsetDateTime::UNIX_EPOCH + curtime;
+
+        let mut server_txn = server.write(curtime).await.unwrap();
+
+        let tuuid = Uuid::new_v4();
+        let token_ids: Vec<Uuid> = (0..=API_TOKEN_MAXIMUM).map(|_| Uuid::new_v4()).collect();
+
+        let token = |label: &str| ApiToken::builder(curtime_odt).label(label).build();
+
+        let mut e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Name, Value::new_iname("test_service_account")),
+            (Attribute::Uuid, Value::Uuid(tuuid)),
+            (
+                Attribute::DisplayName,
+                Value::new_utf8s("test_service_account")
+            )
+        );
+        // The entry holds more tokens than the schema allows, such as from before the
+        // maximum was lowered.
+        for (i, token_id) in token_ids.iter().enumerate() {
+            e1.add_ava(
+                Attribute::ApiTokenSession,
+                Value::ApiToken(*token_id, token(&format!("token_{i}"))),
+            );
+        }
+
+        let ce = CreateEvent::new_internal(vec![e1]);
+        assert!(server_txn.create(&ce).is_ok());
+
+        let add_token = |token_id: Uuid| {
+            ModifyList::new_append(
+                Attribute::ApiTokenSession,
+                Value::ApiToken(token_id, token("token_new")),
+            )
+        };
+
+        let new_token_id = Uuid::new_v4();
+
+        // Tokens beyond the schema maximum are refused rather than evicting others.
+        assert_eq!(
+            server_txn.internal_modify_uuid(tuuid, &add_token(new_token_id)),
+            Err(OperationError::ResourceLimit)
+        );
+
+        // The entry remains writable, so that it may revoke its tokens.
+        server_txn
+            .internal_modify_uuid(
+                tuuid,
+                &ModifyList::new_remove(
+                    Attribute::ApiTokenSession,
+                    PartialValue::Refer(token_ids[0]),
+                ),
+            )
+            .expect("Failed to revoke token");
+
+        // Revoked tokens don't count toward the limit, but the entry is still at it.
+        assert_eq!(
+            server_txn.internal_modify_uuid(tuuid, &add_token(new_token_id)),
+            Err(OperationError::ResourceLimit)
+        );
+
+        server_txn
+            .internal_modify_uuid(
+                tuuid,
+                &ModifyList::new_remove(
+                    Attribute::ApiTokenSession,
+                    PartialValue::Refer(token_ids[1]),
+                ),
+            )
+            .expect("Failed to revoke token");
+
+        server_txn
+            .internal_modify_uuid(tuuid, &add_token(new_token_id))
+            .expect("Failed to add token");
+
+        assert!(server_txn.commit().is_ok());
+    }
+
+    #[qs_test]
+    async fn test_session_consistency_audit_transitions(server: &QueryServer) {
+        let curtime = duration_from_epoch_now();
+        let curtime_odt = OffsetDateTime::UNIX_EPOCH + curtime;
+
+        let mut server_txn = server.write(curtime).await.unwrap();
+
+        let tuuid = uuid!("cc8e95b4-c24f-4d68-ba54-8bed76f63930");
+        let rs_uuid = Uuid::new_v4();
+
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Name, Value::new_iname("testperson1")),
+            (Attribute::Uuid, Value::Uuid(tuuid)),
+            (Attribute::Description, Value::new_utf8s("testperson1")),
+            (Attribute::DisplayName, Value::new_utf8s("testperson1"))
+        );
+
+        let e2 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServer.to_value()
+            ),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServerBasic.to_value()
+            ),
+            (Attribute::Uuid, Value::Uuid(rs_uuid)),
+            (Attribute::Name, Value::new_iname("test_resource_server")),
+            (
+                Attribute::DisplayName,
+                Value::new_utf8s("test_resource_server")
+            ),
+            (
+                Attribute::OAuth2RsOriginLanding,
+                Value::new_url_s("https://demo.example.com").unwrap()
+            ),
+            (
+                Attribute::OAuth2RsScopeMap,
+                Value::new_oauthscopemap(
+                    UUID_IDM_ALL_ACCOUNTS,
+                    btreeset![OAUTH2_SCOPE_OPENID.to_string()]
+                )
+                .expect("invalid oauthscope")
+            )
+        );
+
+        let ce = CreateEvent::new_internal(vec![e1, e2]);
+        assert!(server_txn.create(&ce).is_ok());
+
+        let session_ids: Vec<Uuid> = (0..SESSION_MAXIMUM + 2).map(|_| Uuid::new_v4()).collect();
+
+        let add_sessions = |session_ids: &[Uuid], offset: usize| {
+            ModifyList::new_list(
+                session_ids
+                    .iter()
+                    .enumerate()
+                    .map(|(i, session_id)| {
+                        Modify::Present(
+                            Attribute::OAuth2Session,
+                            Value::Oauth2Session(
+                                *session_id,
+                                Oauth2Session {
+                                    parent: None,
+                                    state: SessionState::NeverExpires,
+                                    issued_at: curtime_odt
+                                        + Duration::from_secs((offset + i) as u64),
+                                    rs_uuid,
+                                    granted_scopes: Default::default(),
+                                    has_refresh: false,
+                                    last_refreshed: None,
+                                    origin: None,
+                                },
+                            ),
+                        )
+                    })
+                    .collect(),
+            )
+        };
+
+        // Events list the sessions of an entry in the order of their ids.
+        let audit_sessions = |ids: &[Uuid]| {
+            let mut sessions: Vec<_> = ids
+                .iter()
+                .map(|id| AuditSession {
+                    entry: tuuid,
+                    id: *id,
+                })
+                .collect();
+            sessions.sort_by_key(|session| session.id);
+            sessions
+        };
+
+        let actor = Identity::from_internal().get_event_origin_id();
+        let cid = server_txn.get_cid().clone();
+
+        let transition_event = |transition, ids: &[Uuid]| AuditEvent::SessionTransition {
+            kind: AuditSessionKind::OAuth2,
+            transition,
+            actor: actor.clone(),
+            cid: cid.clone(),
+            sessions: audit_sessions(ids),
+        };
+
+        // A bulk creation is reported as one event.
+        server_txn
+            .internal_modify_uuid(tuuid, &add_sessions(&session_ids[..SESSION_MAXIMUM], 0))
+            .expect("Failed to modify user");
+        assert_eq!(
+            server_txn.take_audit_events(),
+            vec![transition_event(
+                AuditSessionTransition::Created,
+                &session_ids[..SESSION_MAXIMUM]
+            )]
+        );
+
+        server_txn
+            .internal_modify_uuid(
+                tuuid,
+                &ModifyList::new_remove(
+                    Attribute::OAuth2Session,
+                    PartialValue::Refer(session_ids[5]),
+                ),
+            )
+            .expect("Failed to modify user");
+        assert_eq!(
+            server_txn.take_audit_events(),
+            vec![transition_event(
+                AuditSessionTransition::Revoked,
+                &session_ids[5..6]
+            )]
+        );
+
+        // Exceeding the maximum revokes the oldest sessions. The session that is already
+        // revoked does not count toward the maximum.
+        server_txn
+            .internal_modify_uuid(
+                tuuid,
+                &add_sessions(&session_ids[SESSION_MAXIMUM..], SESSION_MAXIMUM),
+            )
+            .expect("Failed to modify user");
+        assert_eq!(
+            server_txn.take_audit_events(),
+            vec![
+                transition_event(
+                    AuditSessionTransition::Created,
+                    &session_ids[SESSION_MAXIMUM..]
+                ),
+                transition_event(AuditSessionTransition::Revoked, &session_ids[..1]),
+            ]
+        );
+
+        // Modifications that don't change a session report nothing.
+        server_txn
+            .internal_modify_uuid(
+                tuuid,
+                &ModifyList::new_purge_and_set(
+                    Attribute::Description,
+                    Value::new_utf8s("test person 1 change"),
+                ),
+            )
+            .expect("Failed to modify user");
+        assert!(server_txn.take_audit_events().is_empty());
+
+        assert!(server_txn.commit().is_ok());
+    }
 }
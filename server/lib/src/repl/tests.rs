@@ -6,7 +6,7 @@ use crate::repl::proto::ConsumerState;
 use crate::repl::proto::ReplIncrementalContext;
 use crate::repl::ruv::ReplicationUpdateVectorTransaction;
 use crate::repl::ruv::{RangeDiffStatus, ReplicationUpdateVector};
-use crate::value::{AuthType, Session, SessionState};
+use crate::value::{ApiToken, ApiTokenScope, AuthType, Session, SessionState};
 use crypto_glue::{traits::DecodePem, x509::Certificate};
 use kanidm_lib_crypto::CryptoPolicy;
 use std::collections::BTreeMap;
@@ -3303,6 +3303,11 @@ async fn test_repl_increment_session_new(server_a: &QueryServer, server_b: &Quer
             scope,
             type_,
             ext_metadata: Default::default(),
+            bound_to: None,
+            last_active: None,
+            pinned: false,
+            service_account: false,
+            disabled: None,
         },
     );
 
@@ -3345,6 +3350,11 @@ async fn test_repl_increment_session_new(server_a: &QueryServer, server_b: &Quer
             scope,
             type_,
             ext_metadata: Default::default(),
+            bound_to: None,
+            last_active: None,
+            pinned: false,
+            service_account: false,
+            disabled: None,
         },
     );
 
@@ -3421,6 +3431,659 @@ async fn test_repl_increment_session_new(server_a: &QueryServer, server_b: &Quer
     drop(server_b_txn);
 }
 
+fn repl_create_person(server_txn: &mut QueryServerWriteTransaction<'_>, t_uuid: Uuid) -> Uuid {
+    let p = CryptoPolicy::minimum();
+    let cred = Credential::new_password_only(&p, "test_password", OffsetDateTime::UNIX_EPOCH)
+        .expect("Failed to create credential");
+    let cred_id = cred.uuid;
+
+    let e1 = entry_init!(
+        (Attribute::Class, EntryClass::Object.to_value()),
+        (Attribute::Class, EntryClass::Person.to_value()),
+        (Attribute::Class, EntryClass::Account.to_value()),
+        (Attribute::Name, Value::new_iname("testperson1")),
+        (Attribute::Uuid, Value::Uuid(t_uuid)),
+        (Attribute::Description, Value::new_utf8s("testperson1")),
+        (Attribute::DisplayName, Value::new_utf8s("testperson1")),
+        (
+            Attribute::PrimaryCredential,
+            Value::Cred("primary".to_string(), cred.clone())
+        )
+    );
+
+    let ce = CreateEvent::new_internal(vec![e1]);
+    assert!(server_txn.create(&ce).is_ok());
+    cred_id
+}
+
+fn repl_session(t_uuid: Uuid, cred_id: Uuid, ct: Duration) -> Session {
+    Session {
+        label: "label".to_string(),
+        state: SessionState::ExpiresAt(OffsetDateTime::UNIX_EPOCH + ct + Duration::from_secs(60)),
+        issued_at: OffsetDateTime::UNIX_EPOCH + ct,
+        issued_by: IdentityId::User(t_uuid),
+        cred_id,
+        scope: SessionScope::ReadOnly,
+        type_: AuthType::Passkey,
+        ext_metadata: Default::default(),
+        bound_to: None,
+        last_active: None,
+        pinned: false,
+        service_account: false,
+        disabled: None,
+    }
+}
+
+fn repl_issue_session(
+    server_txn: &mut QueryServerWriteTransaction<'_>,
+    t_uuid: Uuid,
+    session_id: Uuid,
+    session: Session,
+) {
+    server_txn
+        .internal_modify(
+            &filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(t_uuid))),
+            &ModifyList::new_append(
+                Attribute::UserAuthTokenSession,
+                Value::Session(session_id, session),
+            ),
+        )
+        .expect("Failed to modify user");
+}
+
+// Assert that both nodes have converged, and that each of the sessions is present
+// and still valid on both of them.
+fn repl_assert_sessions_converged(
+    server_a_txn: &mut QueryServerWriteTransaction<'_>,
+    server_b_txn: &mut QueryServerReadTransaction<'_>,
+    t_uuid: Uuid,
+    session_ids: &[Uuid],
+) {
+    let e1 = server_a_txn
+        .internal_search_all_uuid(t_uuid)
+        .expect("Unable to access entry.");
+    let e2 = server_b_txn
+        .internal_search_all_uuid(t_uuid)
+        .expect("Unable to access entry.");
+
+    for entry in [&e1, &e2] {
+        let sessions = entry
+            .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+            .expect("No sessions present");
+        assert_eq!(sessions.len(), session_ids.len());
+        for session_id in session_ids {
+            let session = sessions.get(session_id).expect("Session was lost");
+            assert!(matches!(session.state, SessionState::ExpiresAt(_)));
+        }
+    }
+
+    assert_eq!(e1.get_changestate(), e2.get_changestate());
+    assert_eq!(e1, e2);
+}
+
+// Sessions that are issued concurrently to the same entry on two nodes are both
+// retained once the nodes converge.
+#[qs_pair_test]
+async fn test_repl_increment_session_concurrent_issue(
+    server_a: &QueryServer,
+    server_b: &QueryServer,
+) {
+    let ct = duration_from_epoch_now();
+
+    let t_uuid = Uuid::new_v4();
+    let mut server_b_txn = server_b.write(ct).await.unwrap();
+    let cred_id = repl_create_person(&mut server_b_txn, t_uuid);
+    server_b_txn.commit().expect("Failed to commit");
+
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    let mut server_b_txn = server_b.read().await.unwrap();
+    assert!(repl_initialise(&mut server_b_txn, &mut server_a_txn)
+        .and_then(|_| server_a_txn.commit())
+        .is_ok());
+    drop(server_b_txn);
+
+    // Issue a session on each node, with neither having seen the other.
+    let ct_a = ct + Duration::from_secs(1);
+    let session_id_a = Uuid::new_v4();
+    let mut server_a_txn = server_a.write(ct_a).await.unwrap();
+    repl_issue_session(
+        &mut server_a_txn,
+        t_uuid,
+        session_id_a,
+        repl_session(t_uuid, cred_id, ct_a),
+    );
+    server_a_txn.commit().expect("Failed to commit");
+
+    let ct_b = ct + Duration::from_secs(2);
+    let session_id_b = Uuid::new_v4();
+    let mut server_b_txn = server_b.write(ct_b).await.unwrap();
+    repl_issue_session(
+        &mut server_b_txn,
+        t_uuid,
+        session_id_b,
+        repl_session(t_uuid, cred_id, ct_b),
+    );
+    server_b_txn.commit().expect("Failed to commit");
+
+    // Now incremental in both directions.
+    let ct = ct + Duration::from_secs(3);
+    let mut server_b_txn = server_b.read().await.unwrap();
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    repl_incremental(&mut server_b_txn, &mut server_a_txn);
+    server_a_txn.commit().expect("Failed to commit");
+    drop(server_b_txn);
+
+    let mut server_a_txn = server_a.read().await.unwrap();
+    let mut server_b_txn = server_b.write(ct).await.unwrap();
+    repl_incremental(&mut server_a_txn, &mut server_b_txn);
+    server_b_txn.commit().expect("Failed to commit");
+    drop(server_a_txn);
+
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    let mut server_b_txn = server_b.read().await.unwrap();
+    repl_assert_sessions_converged(
+        &mut server_a_txn,
+        &mut server_b_txn,
+        t_uuid,
+        &[session_id_a, session_id_b],
+    );
+    server_a_txn.commit().expect("Failed to commit");
+    drop(server_b_txn);
+}
+
+// A session issued on one node is retained when the session attribute was concurrently
+// trimmed away on another, since an absent session set is equivalent to an empty one.
+#[qs_pair_test]
+async fn test_repl_increment_session_issue_concurrent_trim(
+    server_a: &QueryServer,
+    server_b: &QueryServer,
+) {
+    let ct = duration_from_epoch_now();
+
+    let t_uuid = Uuid::new_v4();
+    let mut server_b_txn = server_b.write(ct).await.unwrap();
+    let cred_id = repl_create_person(&mut server_b_txn, t_uuid);
+    server_b_txn.commit().expect("Failed to commit");
+
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    let mut server_b_txn = server_b.read().await.unwrap();
+    assert!(repl_initialise(&mut server_b_txn, &mut server_a_txn)
+        .and_then(|_| server_a_txn.commit())
+        .is_ok());
+    drop(server_b_txn);
+
+    // Issue a session on B.
+    let ct_b = ct + Duration::from_secs(1);
+    let session_id_b = Uuid::new_v4();
+    let mut server_b_txn = server_b.write(ct_b).await.unwrap();
+    repl_issue_session(
+        &mut server_b_txn,
+        t_uuid,
+        session_id_b,
+        repl_session(t_uuid, cred_id, ct_b),
+    );
+    server_b_txn.commit().expect("Failed to commit");
+
+    // And later trim the session attribute away on A, as occurs once the last
+    // revoked sessions pass the trim window.
+    let ct_a = ct + Duration::from_secs(2);
+    let mut server_a_txn = server_a.write(ct_a).await.unwrap();
+    let filt = filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(t_uuid)));
+    let mut work_set = server_a_txn
+        .internal_search_writeable(&filt)
+        .expect("Failed to perform internal search writeable");
+    for (_, entry) in work_set.iter_mut() {
+        let _ = entry.force_trim_ava(Attribute::UserAuthTokenSession);
+    }
+    assert!(server_a_txn.internal_apply_writable(work_set).is_ok());
+    server_a_txn.commit().expect("Failed to commit");
+
+    // Now incremental in both directions. The newer change on A must not discard
+    // the session from B.
+    let ct = ct + Duration::from_secs(3);
+    let mut server_a_txn = server_a.read().await.unwrap();
+    let mut server_b_txn = server_b.write(ct).await.unwrap();
+    repl_incremental(&mut server_a_txn, &mut server_b_txn);
+    server_b_txn.commit().expect("Failed to commit");
+    drop(server_a_txn);
+
+    let mut server_b_txn = server_b.read().await.unwrap();
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    repl_incremental(&mut server_b_txn, &mut server_a_txn);
+
+    repl_assert_sessions_converged(
+        &mut server_a_txn,
+        &mut server_b_txn,
+        t_uuid,
+        &[session_id_b],
+    );
+    server_a_txn.commit().expect("Failed to commit");
+    drop(server_b_txn);
+}
+
+fn repl_api_token(t_uuid: Uuid, issued_at: OffsetDateTime) -> ApiToken {
+    ApiToken {
+        label: "label".to_string(),
+        state: SessionState::NeverExpires,
+        issued_at,
+        issued_by: IdentityId::User(t_uuid),
+        scope: ApiTokenScope::ReadOnly,
+        parent_session: None,
+        generation: 0,
+        constraints: None,
+    }
+}
+
+fn repl_create_service_account(server_txn: &mut QueryServerWriteTransaction<'_>, t_uuid: Uuid) {
+    let e1 = entry_init!(
+        (Attribute::Class, EntryClass::Object.to_value()),
+        (Attribute::Class, EntryClass::Account.to_value()),
+        (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+        (Attribute::Name, Value::new_iname("testaccount1")),
+        (Attribute::Uuid, Value::Uuid(t_uuid)),
+        (Attribute::Description, Value::new_utf8s("testaccount1")),
+        (Attribute::DisplayName, Value::new_utf8s("testaccount1"))
+    );
+
+    let ce = CreateEvent::new_internal(vec![e1]);
+    assert!(server_txn.create(&ce).is_ok());
+}
+
+fn repl_modify_api_tokens(
+    server_txn: &mut QueryServerWriteTransaction<'_>,
+    t_uuid: Uuid,
+    modlist: &ModifyList<ModifyInvalid>,
+) {
+    server_txn
+        .internal_modify(
+            &filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(t_uuid))),
+            modlist,
+        )
+        .expect("Failed to modify service account");
+}
+
+// Concurrently created api tokens on two nodes are both retained.
+#[qs_pair_test]
+async fn test_repl_increment_api_token_create_create(
+    server_a: &QueryServer,
+    server_b: &QueryServer,
+) {
+    let ct = duration_from_epoch_now();
+
+    let mut server_b_txn = server_b.write(ct).await.unwrap();
+    let t_uuid = Uuid::new_v4();
+    repl_create_service_account(&mut server_b_txn, t_uuid);
+    server_b_txn.commit().expect("Failed to commit");
+
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    let mut server_b_txn = server_b.read().await.unwrap();
+    assert!(repl_initialise(&mut server_b_txn, &mut server_a_txn)
+        .and_then(|_| server_a_txn.commit())
+        .is_ok());
+    drop(server_b_txn);
+
+    // Create a token on A.
+    let token_id_a = Uuid::new_v4();
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    repl_modify_api_tokens(
+        &mut server_a_txn,
+        t_uuid,
+        &ModifyList::new_append(
+            Attribute::ApiTokenSession,
+            Value::ApiToken(
+                token_id_a,
+                repl_api_token(t_uuid, OffsetDateTime::UNIX_EPOCH + ct),
+            ),
+        ),
+    );
+    server_a_txn.commit().expect("Failed to commit");
+
+    // And a token on B.
+    let ct = duration_from_epoch_now();
+    let token_id_b = Uuid::new_v4();
+    let mut server_b_txn = server_b.write(ct).await.unwrap();
+    repl_modify_api_tokens(
+        &mut server_b_txn,
+        t_uuid,
+        &ModifyList::new_append(
+            Attribute::ApiTokenSession,
+            Value::ApiToken(
+                token_id_b,
+                ApiToken {
+                    label: "label_b".to_string(),
+                    ..repl_api_token(t_uuid, OffsetDateTime::UNIX_EPOCH + ct)
+                },
+            ),
+        ),
+    );
+    server_b_txn.commit().expect("Failed to commit");
+
+    // Now incremental in both directions.
+    let ct = duration_from_epoch_now();
+    let mut server_a_txn = server_a.read().await.unwrap();
+    let mut server_b_txn = server_b.write(ct).await.unwrap();
+    repl_incremental(&mut server_a_txn, &mut server_b_txn);
+    server_b_txn.commit().expect("Failed to commit");
+    drop(server_a_txn);
+
+    let ct = duration_from_epoch_now();
+    let mut server_b_txn = server_b.read().await.unwrap();
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    repl_incremental(&mut server_b_txn, &mut server_a_txn);
+
+    let e1 = server_a_txn
+        .internal_search_all_uuid(t_uuid)
+        .expect("Unable to access entry.");
+    let e2 = server_b_txn
+        .internal_search_all_uuid(t_uuid)
+        .expect("Unable to access entry.");
+
+    let tokens = e1
+        .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+        .expect("No api tokens present");
+    assert_eq!(tokens.len(), 2);
+    assert!(tokens.contains_key(&token_id_a));
+    assert!(tokens.contains_key(&token_id_b));
+
+    assert_eq!(e1.get_changestate(), e2.get_changestate());
+    assert_eq!(e1, e2);
+
+    server_a_txn.commit().expect("Failed to commit");
+    drop(server_b_txn);
+}
+
+// Api tokens concurrently created on two nodes with the same label converge with the
+// later token revoked, rather than moving the account to a conflict state.
+#[qs_pair_test]
+async fn test_repl_increment_api_token_create_create_same_label(
+    server_a: &QueryServer,
+    server_b: &QueryServer,
+) {
+    let ct = duration_from_epoch_now();
+
+    let mut server_b_txn = server_b.write(ct).await.unwrap();
+    let t_uuid = Uuid::new_v4();
+    repl_create_service_account(&mut server_b_txn, t_uuid);
+    server_b_txn.commit().expect("Failed to commit");
+
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    let mut server_b_txn = server_b.read().await.unwrap();
+    assert!(repl_initialise(&mut server_b_txn, &mut server_a_txn)
+        .and_then(|_| server_a_txn.commit())
+        .is_ok());
+    drop(server_b_txn);
+
+    let labelled = |issued_at: OffsetDateTime| ApiToken {
+        label: "backup".to_string(),
+        ..repl_api_token(t_uuid, issued_at)
+    };
+
+    // Create a token on A.
+    let token_id_a = Uuid::new_v4();
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    repl_modify_api_tokens(
+        &mut server_a_txn,
+        t_uuid,
+        &ModifyList::new_append(
+            Attribute::ApiTokenSession,
+            Value::ApiToken(token_id_a, labelled(OffsetDateTime::UNIX_EPOCH + ct)),
+        ),
+    );
+    server_a_txn.commit().expect("Failed to commit");
+
+    // And a token with the same label on B.
+    let ct = duration_from_epoch_now();
+    let token_id_b = Uuid::new_v4();
+    let mut server_b_txn = server_b.write(ct).await.unwrap();
+    repl_modify_api_tokens(
+        &mut server_b_txn,
+        t_uuid,
+        &ModifyList::new_append(
+            Attribute::ApiTokenSession,
+            Value::ApiToken(token_id_b, labelled(OffsetDateTime::UNIX_EPOCH + ct)),
+        ),
+    );
+    server_b_txn.commit().expect("Failed to commit");
+
+    // Now incremental in both directions.
+    let ct = duration_from_epoch_now();
+    let mut server_a_txn = server_a.read().await.unwrap();
+    let mut server_b_txn = server_b.write(ct).await.unwrap();
+    repl_incremental(&mut server_a_txn, &mut server_b_txn);
+    server_b_txn.commit().expect("Failed to commit");
+    drop(server_a_txn);
+
+    let ct = duration_from_epoch_now();
+    let mut server_b_txn = server_b.read().await.unwrap();
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    repl_incremental(&mut server_b_txn, &mut server_a_txn);
+
+    let e1 = server_a_txn
+        .internal_search_all_uuid(t_uuid)
+        .expect("Unable to access entry.");
+    let e2 = server_b_txn
+        .internal_search_all_uuid(t_uuid)
+        .expect("Unable to access entry.");
+
+    assert!(!e1.attribute_equality(Attribute::Class, &EntryClass::Conflict.into()));
+
+    // The earlier token keeps the label.
+    let tokens = e1
+        .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+        .expect("No api tokens present");
+    assert_eq!(tokens.len(), 2);
+    assert!(tokens.get(&token_id_a).is_some_and(|at| !at.is_revoked()));
+    assert!(tokens.get(&token_id_b).is_some_and(|at| at.is_revoked()));
+
+    assert_eq!(e1.get_changestate(), e2.get_changestate());
+    assert_eq!(e1, e2);
+
+    server_a_txn.commit().expect("Failed to commit");
+    drop(server_b_txn);
+}
+
+// A token that is deleted on one node while the attribute is concurrently changed on
+// another converges to revoked, rather than being resurrected.
+#[qs_pair_test]
+async fn test_repl_increment_api_token_create_delete(
+    server_a: &QueryServer,
+    server_b: &QueryServer,
+) {
+    let ct = duration_from_epoch_now();
+
+    let token_id_a = Uuid::new_v4();
+    let t_uuid = Uuid::new_v4();
+
+    let mut server_b_txn = server_b.write(ct).await.unwrap();
+    repl_create_service_account(&mut server_b_txn, t_uuid);
+    repl_modify_api_tokens(
+        &mut server_b_txn,
+        t_uuid,
+        &ModifyList::new_append(
+            Attribute::ApiTokenSession,
+            Value::ApiToken(
+                token_id_a,
+                repl_api_token(t_uuid, OffsetDateTime::UNIX_EPOCH + ct),
+            ),
+        ),
+    );
+    server_b_txn.commit().expect("Failed to commit");
+
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    let mut server_b_txn = server_b.read().await.unwrap();
+    assert!(repl_initialise(&mut server_b_txn, &mut server_a_txn)
+        .and_then(|_| server_a_txn.commit())
+        .is_ok());
+    drop(server_b_txn);
+
+    // Delete the token on A.
+    let ct = duration_from_epoch_now();
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    repl_modify_api_tokens(
+        &mut server_a_txn,
+        t_uuid,
+        &ModifyList::new_remove(Attribute::ApiTokenSession, PartialValue::Refer(token_id_a)),
+    );
+    server_a_txn.commit().expect("Failed to commit");
+
+    // Concurrently, create a new token on B.
+    let ct = duration_from_epoch_now();
+    let token_id_b = Uuid::new_v4();
+    let mut server_b_txn = server_b.write(ct).await.unwrap();
+    repl_modify_api_tokens(
+        &mut server_b_txn,
+        t_uuid,
+        &ModifyList::new_append(
+            Attribute::ApiTokenSession,
+            Value::ApiToken(
+                token_id_b,
+                ApiToken {
+                    label: "label_b".to_string(),
+                    ..repl_api_token(t_uuid, OffsetDateTime::UNIX_EPOCH + ct)
+                },
+            ),
+        ),
+    );
+    server_b_txn.commit().expect("Failed to commit");
+
+    // Now incremental in both directions.
+    let ct = duration_from_epoch_now();
+    let mut server_a_txn = server_a.read().await.unwrap();
+    let mut server_b_txn = server_b.write(ct).await.unwrap();
+    repl_incremental(&mut server_a_txn, &mut server_b_txn);
+    server_b_txn.commit().expect("Failed to commit");
+    drop(server_a_txn);
+
+    let ct = duration_from_epoch_now();
+    let mut server_b_txn = server_b.read().await.unwrap();
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    repl_incremental(&mut server_b_txn, &mut server_a_txn);
+
+    let e1 = server_a_txn
+        .internal_search_all_uuid(t_uuid)
+        .expect("Unable to access entry.");
+    let e2 = server_b_txn
+        .internal_search_all_uuid(t_uuid)
+        .expect("Unable to access entry.");
+
+    let tokens = e1
+        .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+        .expect("No api tokens present");
+    assert!(tokens
+        .get(&token_id_a)
+        .is_some_and(|token| token.is_revoked()));
+    assert!(tokens
+        .get(&token_id_b)
+        .is_some_and(|token| !token.is_revoked()));
+
+    assert_eq!(e1.get_changestate(), e2.get_changestate());
+    assert_eq!(e1, e2);
+
+    server_a_txn.commit().expect("Failed to commit");
+    drop(server_b_txn);
+}
+
+// A token that is concurrently rotated on two nodes with conflicting content converges
+// to the same value on both nodes.
+#[qs_pair_test]
+async fn test_repl_increment_api_token_rotate_rotate(
+    server_a: &QueryServer,
+    server_b: &QueryServer,
+) {
+    let ct = duration_from_epoch_now();
+
+    let token_id = Uuid::new_v4();
+    let t_uuid = Uuid::new_v4();
+
+    let mut server_b_txn = server_b.write(ct).await.unwrap();
+    repl_create_service_account(&mut server_b_txn, t_uuid);
+    repl_modify_api_tokens(
+        &mut server_b_txn,
+        t_uuid,
+        &ModifyList::new_append(
+            Attribute::ApiTokenSession,
+            Value::ApiToken(
+                token_id,
+                repl_api_token(t_uuid, OffsetDateTime::UNIX_EPOCH + ct),
+            ),
+        ),
+    );
+    server_b_txn.commit().expect("Failed to commit");
+
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    let mut server_b_txn = server_b.read().await.unwrap();
+    assert!(repl_initialise(&mut server_b_txn, &mut server_a_txn)
+        .and_then(|_| server_a_txn.commit())
+        .is_ok());
+    drop(server_b_txn);
+
+    // Both nodes rotate the token at the same instant, but disagree on its content.
+    let rotated_at = OffsetDateTime::UNIX_EPOCH + duration_from_epoch_now();
+    let rotated = |label: &str| ApiToken {
+        label: label.to_string(),
+        issued_at: rotated_at,
+        generation: 1,
+        ..repl_api_token(t_uuid, rotated_at)
+    };
+
+    let ct = duration_from_epoch_now();
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    repl_modify_api_tokens(
+        &mut server_a_txn,
+        t_uuid,
+        &ModifyList::new_append(
+            Attribute::ApiTokenSession,
+            Value::ApiToken(token_id, rotated("rotated_a")),
+        ),
+    );
+    server_a_txn.commit().expect("Failed to commit");
+
+    let ct = duration_from_epoch_now();
+    let mut server_b_txn = server_b.write(ct).await.unwrap();
+    repl_modify_api_tokens(
+        &mut server_b_txn,
+        t_uuid,
+        &ModifyList::new_append(
+            Attribute::ApiTokenSession,
+            Value::ApiToken(token_id, rotated("rotated_b")),
+        ),
+    );
+    server_b_txn.commit().expect("Failed to commit");
+
+    // Now incremental in both directions.
+    let ct = duration_from_epoch_now();
+    let mut server_a_txn = server_a.read().await.unwrap();
+    let mut server_b_txn = server_b.write(ct).await.unwrap();
+    repl_incremental(&mut server_a_txn, &mut server_b_txn);
+    server_b_txn.commit().expect("Failed to commit");
+    drop(server_a_txn);
+
+    let ct = duration_from_epoch_now();
+    let mut server_b_txn = server_b.read().await.unwrap();
+    let mut server_a_txn = server_a.write(ct).await.unwrap();
+    repl_incremental(&mut server_b_txn, &mut server_a_txn);
+
+    let e1 = server_a_txn
+        .internal_search_all_uuid(t_uuid)
+        .expect("Unable to access entry.");
+    let e2 = server_b_txn
+        .internal_search_all_uuid(t_uuid)
+        .expect("Unable to access entry.");
+
+    // Everything else being equal, the content is compared lexicographically.
+    let token = e1
+        .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+        .and_then(|tokens| tokens.get(&token_id))
+        .expect("No api token present");
+    assert_eq!(token, &rotated("rotated_b"));
+
+    assert_eq!(e1.get_changestate(), e2.get_changestate());
+    assert_eq!(e1, e2);
+
+    server_a_txn.commit().expect("Failed to commit");
+    drop(server_b_txn);
+}
+
 /// Test the process of refreshing a consumer once it has entered a lag state.
 ///
 /// It was noticed in a production instance that it was possible for a consumer
@@ -119,6 +119,10 @@ pub struct SchemaAttribute {
     pub indexed: bool,
     /// THe type of data that this attribute may hold.
     pub syntax: SyntaxType,
+    /// The maximum number of values this attribute may hold on a single entry. Session
+    /// types trim their oldest values to this limit when modified, where other types
+    /// are rejected from being written.
+    pub maximum_values: Option<u32>,
 }
 
 impl SchemaAttribute {
@@ -200,6 +204,8 @@ impl SchemaAttribute {
                 OperationError::InvalidSchemaState(format!("missing {}", Attribute::Syntax))
             })?;
 
+        let maximum_values = value.get_ava_single_uint32(Attribute::MaximumValues);
+
         trace!(?name, ?indexed);
 
         Ok(SchemaAttribute {
@@ -213,6 +219,7 @@ impl SchemaAttribute {
             replicated,
             indexed,
             syntax,
+            maximum_values,
         })
     }
 
@@ -259,9 +266,16 @@ impl SchemaAttribute {
             SyntaxType::Passkey => matches!(v, PartialValue::Passkey(_)),
             SyntaxType::AttestedPasskey => matches!(v, PartialValue::AttestedPasskey(_)),
             // Allow refer types.
-            SyntaxType::Session => matches!(v, PartialValue::Refer(_)),
-            SyntaxType::ApiToken => matches!(v, PartialValue::Refer(_)),
-            SyntaxType::Oauth2Session => matches!(v, PartialValue::Refer(_)),
+            // Sessions may also be compared by when they end.
+            SyntaxType::Session | SyntaxType::Oauth2Session => {
+                matches!(v, PartialValue::Refer(_)) || matches!(v, PartialValue::DateTime(_))
+            }
+            // Api tokens may also be found by their label.
+            SyntaxType::ApiToken => {
+                matches!(v, PartialValue::Refer(_))
+                    || matches!(v, PartialValue::Iutf8(_))
+                    || matches!(v, PartialValue::DateTime(_))
+            }
             // These are just insensitive string lookups on the hex-ified kid.
             SyntaxType::JwsKeyEs256 => matches!(v, PartialValue::Iutf8(_)),
             SyntaxType::JwsKeyRs256 => matches!(v, PartialValue::Iutf8(_)),
@@ -378,6 +392,14 @@ impl SchemaAttribute {
         };
         // If syntax, check the type is correct
         let valid = self.syntax == ava.syntax();
+        if !ava.validate_maximum_values(self) {
+            error!(
+                ?a,
+                maximum_values = ?self.maximum_values,
+                "validate_ava - too many values"
+            );
+            return Err(SchemaError::TooManyValues(a.to_string()));
+        }
         if valid && ava.validate(self) {
             Ok(())
         } else {
@@ -1328,6 +1350,108 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_schema_attribute_maximum_values() {
+        let limited = SchemaAttribute {
+            name: Attribute::from("mv_limited"),
+            uuid: Uuid::new_v4(),
+            description: String::from(""),
+            multivalue: true,
+            syntax: SyntaxType::Utf8String,
+            maximum_values: Some(2),
+            ..Default::default()
+        };
+
+        let rvs = vs_utf8!["a".to_string(), "b".to_string()] as _;
+        assert_eq!(
+            limited.validate_ava(&Attribute::from("mv_limited"), &rvs),
+            Ok(())
+        );
+
+        let rvs = vs_utf8!["a".to_string(), "b".to_string(), "c".to_string()] as _;
+        assert_eq!(
+            limited.validate_ava(&Attribute::from("mv_limited"), &rvs),
+            Err(SchemaError::TooManyValues("mv_limited".to_string()))
+        );
+
+        // Sessions trim themselves rather than being rejected.
+        let limited_session = SchemaAttribute {
+            name: Attribute::from("mv_session"),
+            uuid: Uuid::new_v4(),
+            description: String::from(""),
+            multivalue: true,
+            syntax: SyntaxType::Session,
+            maximum_values: Some(1),
+            ..Default::default()
+        };
+
+        let session = |secs| crate::value::Session {
+            label: "label".to_string(),
+            state: crate::value::SessionState::NeverExpires,
+            issued_at: time::OffsetDateTime::UNIX_EPOCH + std::time::Duration::from_secs(secs),
+            issued_by: IdentityId::Internal(UUID_SYSTEM),
+            cred_id: Uuid::new_v4(),
+            scope: crate::value::SessionScope::ReadOnly,
+            type_: crate::value::AuthType::Passkey,
+            ext_metadata: Default::default(),
+            bound_to: None,
+            last_active: None,
+            pinned: false,
+            service_account: false,
+            disabled: None,
+        };
+
+        let newest = Uuid::new_v4();
+        let mut rvs: ValueSet = crate::valueset::ValueSetSession::new(Uuid::new_v4(), session(1));
+        assert_eq!(
+            rvs.insert_checked(Value::Session(newest, session(2))),
+            Ok(true)
+        );
+        assert_eq!(
+            limited_session.validate_ava(&Attribute::from("mv_session"), &rvs),
+            Ok(())
+        );
+
+        assert_eq!(
+            rvs.trim_to_maximum(1, &Cid::new_count(2), &Cid::new_count(1)),
+            1
+        );
+        assert_eq!(rvs.len(), 1);
+        assert!(rvs.contains(&PartialValue::Refer(newest)));
+    }
+
+    #[test]
+    fn test_schema_attribute_maximum_values_from_entry() {
+        let e = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::AttributeType.to_value()),
+            (
+                Attribute::AttributeName,
+                Value::new_iutf8("schema_attr_test")
+            ),
+            (
+                Attribute::Uuid,
+                Value::Uuid(uuid::uuid!("66c68b2f-d02c-4243-8013-7946e40fe321"))
+            ),
+            (
+                Attribute::Description,
+                Value::Utf8("Test attr parsing".to_string())
+            ),
+            (Attribute::MultiValue, Value::Bool(true)),
+            (Attribute::Unique, Value::Bool(false)),
+            (Attribute::Syntax, Value::Syntax(SyntaxType::Utf8String)),
+            (Attribute::MaximumValues, Value::Uint32(4))
+        )
+        .into_sealed_committed();
+
+        let sa = SchemaAttribute::try_from(&e).expect("Failed to parse schema attribute");
+        assert_eq!(sa.maximum_values, Some(4));
+
+        // And it is retained when converted back to an entry.
+        let e = EntryInitNew::from(&sa);
+        assert_eq!(e.get_ava_single_uint32(Attribute::MaximumValues), Some(4));
+    }
+
     #[test]
     fn test_schema_simple() {
         let schema = Schema::new().expect("failed to create schema");
@@ -158,6 +158,11 @@ fn create_filter_entry<'a>(
         }
     };
 
+    if ident.write_constraint().is_some() {
+        security_access!("denied ❌ - identity is constrained and not permitted to create");
+        return IResult::Deny;
+    }
+
     // Build the set of requested classes and attrs here.
     let create_attrs: BTreeSet<&str> = entry.get_ava_names().collect();
     // If this is empty, we make an empty set, which is fine because
@@ -101,6 +101,11 @@ fn delete_filter_entry<'a>(
         }
     };
 
+    if ident.write_constraint().is_some() {
+        security_access!("denied ❌ - identity is constrained and not permitted to delete");
+        return IResult::Deny;
+    }
+
     let ident_memberof = ident.get_memberof();
     let ident_uuid = ident.get_uuid();
 
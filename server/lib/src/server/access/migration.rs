@@ -107,6 +107,14 @@ pub fn migration_entry_attrs(
             Attribute::LimitSearchMaxResults,
             Attribute::LimitSearchMaxFilterTest,
             Attribute::AllowPrimaryCredFallback,
+            Attribute::RetainSessionsOnPasswordChange,
+            Attribute::AuthSessionIdleTimeout,
+            Attribute::AuthSessionExpirySingleFactor,
+            Attribute::AuthSessionExpiryMultiFactor,
+            Attribute::RevokeSessionsOnPasswordChange,
+            Attribute::RetainCurrentSessionOnPasswordChange,
+            Attribute::AuthSessionIssueLimit,
+            Attribute::AuthSessionIssueWindow,
         ]);
     }
 
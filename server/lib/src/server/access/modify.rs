@@ -198,14 +198,14 @@ pub(super) fn apply_modify_access<'a>(
     } else if grant {
         ModifyResult::Grant
     } else {
-        let allowed_pres = if !constrain_pres.is_empty() {
+        let mut allowed_pres = if !constrain_pres.is_empty() {
             // bit_and
             &constrain_pres & &allow_pres
         } else {
             allow_pres
         };
 
-        let allowed_rem = if !constrain_rem.is_empty() {
+        let mut allowed_rem = if !constrain_rem.is_empty() {
             // bit_and
             &constrain_rem & &allow_rem
         } else {
@@ -235,6 +235,17 @@ pub(super) fn apply_modify_access<'a>(
             allowed_rem_cls.remove(protected_cls.as_str());
         }
 
+        // A constrained identity (such as a scoped api token) can only ever modify the
+        // attributes it was limited to, on top of whatever else remains allowed.
+        if let Some(write_constraint) = ident.write_constraint() {
+            allowed_pres = &allowed_pres & write_constraint;
+            allowed_rem = &allowed_rem & write_constraint;
+            if !write_constraint.contains(&Attribute::Class) {
+                allowed_pres_cls.clear();
+                allowed_rem_cls.clear();
+            }
+        }
+
         ModifyResult::Allow {
             pres: allowed_pres,
             rem: allowed_rem,
@@ -467,6 +478,14 @@ fn modify_protected_entry_attrs<'a>(classes: &BTreeSet<String>) -> AccessModResu
             Attribute::LimitSearchMaxResults,
             Attribute::LimitSearchMaxFilterTest,
             Attribute::AllowPrimaryCredFallback,
+            Attribute::RetainSessionsOnPasswordChange,
+            Attribute::AuthSessionIdleTimeout,
+            Attribute::AuthSessionExpirySingleFactor,
+            Attribute::AuthSessionExpiryMultiFactor,
+            Attribute::RevokeSessionsOnPasswordChange,
+            Attribute::RetainCurrentSessionOnPasswordChange,
+            Attribute::AuthSessionIssueLimit,
+            Attribute::AuthSessionIssueWindow,
         ]);
     }
 
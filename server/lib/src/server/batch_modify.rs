@@ -120,7 +120,7 @@ impl QueryServerWriteTransaction<'_> {
                     })
                     .and_then(|modlist| {
                         ent_mut
-                            .apply_modlist(modlist)
+                            .apply_modlist(modlist, self.curtime)
                             // Return if success
                             .map(|()| ent_mut)
                             // Error log otherwise.
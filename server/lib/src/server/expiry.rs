@@ -0,0 +1,354 @@
+//! Discovery of the sessions and api tokens that are about to expire, so that their holders
+//! can be warned before they stop working.
+
+use crate::prelude::*;
+use crate::value::SessionState;
+use std::collections::BTreeMap;
+use time::OffsetDateTime;
+
+/// A session or api token that will expire within the requested horizon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpiringSession {
+    /// The entry that holds the session.
+    pub entry: Uuid,
+    /// The attribute the session is held in.
+    pub attr: Attribute,
+    /// The id of the session or api token.
+    pub id: Uuid,
+    pub expiry: OffsetDateTime,
+}
+
+impl QueryServerReadTransaction<'_> {
+    /// List the user auth sessions and api tokens that are still valid at `ct`, but that
+    /// will expire within `horizon` of it. These are ordered by expiry.
+    pub fn expiring_sessions(
+        &mut self,
+        ct: Duration,
+        horizon: Duration,
+    ) -> Result<Vec<ExpiringSession>, OperationError> {
+        let now = OffsetDateTime::UNIX_EPOCH + ct;
+        let until = now + horizon;
+
+        let attrs = [Attribute::UserAuthTokenSession, Attribute::ApiTokenSession];
+
+        // The less than match is satisfied by any session ending before the horizon, so
+        // the session states are checked below.
+        let filter = filter!(f_or(
+            attrs
+                .iter()
+                .map(|attr| f_lt(attr.clone(), PartialValue::DateTime(until)))
+                .collect()
+        ));
+
+        let entries = self.internal_search(filter)?;
+
+        let in_range = |state: &SessionState| match state {
+            SessionState::ExpiresAt(exp) if now < *exp && *exp < until => Some(*exp),
+            _ => None,
+        };
+
+        let mut expiring = Vec::new();
+
+        for entry in entries.iter() {
+            let entry_uuid = entry.get_uuid();
+
+            if let Some(sessions) = entry.get_ava_as_session_map(Attribute::UserAuthTokenSession) {
+                expiring.extend(sessions.iter().filter_map(|(id, session)| {
+                    in_range(&session.state).map(|expiry| ExpiringSession {
+                        entry: entry_uuid,
+                        attr: Attribute::UserAuthTokenSession,
+                        id: *id,
+                        expiry,
+                    })
+                }));
+            }
+
+            if let Some(tokens) = entry.get_ava_as_apitoken_map(Attribute::ApiTokenSession) {
+                expiring.extend(tokens.iter().filter_map(|(id, token)| {
+                    in_range(&token.state).map(|expiry| ExpiringSession {
+                        entry: entry_uuid,
+                        attr: Attribute::ApiTokenSession,
+                        id: *id,
+                        expiry,
+                    })
+                }));
+            }
+        }
+
+        expiring.sort_by(|a, b| a.expiry.cmp(&b.expiry).then_with(|| a.id.cmp(&b.id)));
+
+        Ok(expiring)
+    }
+}
+
+/// Reports each expiring session once. The notifier remembers the sessions it has
+/// reported until they expire, so that a session is reported again only if its expiry
+/// changes. As this is held in memory nothing is written to the database, so notifying
+/// does not cause replication traffic. Sessions may be notified again after the server
+/// restarts.
+#[derive(Debug, Default)]
+pub struct SessionExpiryNotifier {
+    notified: BTreeMap<(Uuid, Uuid), OffsetDateTime>,
+}
+
+impl SessionExpiryNotifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Find the sessions that will expire within `horizon` of `ct` that have not been
+    /// reported by an earlier scan.
+    pub fn scan(
+        &mut self,
+        qs: &mut QueryServerReadTransaction,
+        ct: Duration,
+        horizon: Duration,
+    ) -> Result<Vec<ExpiringSession>, OperationError> {
+        let now = OffsetDateTime::UNIX_EPOCH + ct;
+
+        // Sessions that have expired can't be reported again.
+        self.notified.retain(|_, expiry| now < *expiry);
+
+        let mut expiring = qs.expiring_sessions(ct, horizon)?;
+        expiring.retain(|session| {
+            self.notified
+                .insert((session.entry, session.id), session.expiry)
+                != Some(session.expiry)
+        });
+
+        Ok(expiring)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExpiringSession, SessionExpiryNotifier};
+    use crate::credential::Credential;
+    use crate::prelude::*;
+    use crate::value::{ApiToken, AuthType, Session};
+    use kanidm_lib_crypto::CryptoPolicy;
+    use time::OffsetDateTime;
+
+    #[qs_test]
+    async fn test_session_expiry_notify_once(server: &QueryServer) {
+        let ct = duration_from_epoch_now();
+        let now = OffsetDateTime::UNIX_EPOCH + ct;
+        let hour = Duration::from_secs(3600);
+        let day = Duration::from_secs(86400);
+
+        let cred = Credential::new_password_only(
+            &CryptoPolicy::minimum(),
+            "test_password",
+            OffsetDateTime::UNIX_EPOCH,
+        )
+        .unwrap();
+
+        let tuuid = Uuid::new_v4();
+        let (session_soon, session_never) = (Uuid::new_v4(), Uuid::new_v4());
+        let (token_soon, token_later, token_revoked) =
+            (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        let session = |expiry: Option<OffsetDateTime>, session_id: Uuid| {
+            Session::builder(now, AuthType::Passkey)
+                .label("session")
+                .cred_id(cred.uuid)
+                .expiry(expiry)
+                .build_value(session_id)
+                .unwrap()
+        };
+
+        let token = |label: &str, expiry: Option<OffsetDateTime>, token_id: Uuid| {
+            ApiToken::builder(now)
+                .label(label)
+                .expiry(expiry)
+                .build_value(token_id)
+                .unwrap()
+        };
+
+        let mut server_txn = server.write(ct).await.unwrap();
+
+        let e = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Name, Value::new_iname("testaccount")),
+            (Attribute::Uuid, Value::Uuid(tuuid)),
+            (Attribute::DisplayName, Value::new_utf8s("testaccount")),
+            (
+                Attribute::PrimaryCredential,
+                Value::Cred("primary".to_string(), cred.clone())
+            ),
+            (
+                Attribute::UserAuthTokenSession,
+                session(Some(now + hour), session_soon)
+            ),
+            (
+                Attribute::UserAuthTokenSession,
+                session(None, session_never)
+            ),
+            (
+                Attribute::ApiTokenSession,
+                token("soon", Some(now + hour * 2), token_soon)
+            ),
+            (
+                Attribute::ApiTokenSession,
+                token("later", Some(now + day * 3), token_later)
+            ),
+            (
+                Attribute::ApiTokenSession,
+                token("revoked", Some(now + hour), token_revoked)
+            )
+        );
+        server_txn.internal_create(vec![e]).unwrap();
+
+        // Revoked tokens are never notified.
+        server_txn
+            .internal_modify_uuid(
+                tuuid,
+                &ModifyList::new_remove(
+                    Attribute::ApiTokenSession,
+                    PartialValue::Refer(token_revoked),
+                ),
+            )
+            .unwrap();
+        server_txn.commit().unwrap();
+
+        let soon = vec![
+            ExpiringSession {
+                entry: tuuid,
+                attr: Attribute::UserAuthTokenSession,
+                id: session_soon,
+                expiry: now + hour,
+            },
+            ExpiringSession {
+                entry: tuuid,
+                attr: Attribute::ApiTokenSession,
+                id: token_soon,
+                expiry: now + hour * 2,
+            },
+        ];
+
+        let mut server_txn = server.read().await.unwrap();
+        assert_eq!(server_txn.expiring_sessions(ct, day).unwrap(), soon);
+
+        let mut notifier = SessionExpiryNotifier::new();
+        assert_eq!(notifier.scan(&mut server_txn, ct, day).unwrap(), soon);
+
+        // Scanning again, or with a shorter horizon, reports nothing new.
+        assert!(notifier.scan(&mut server_txn, ct, day).unwrap().is_empty());
+        assert!(notifier
+            .scan(&mut server_txn, ct + hour, hour)
+            .unwrap()
+            .is_empty());
+
+        // As time passes, only the sessions that newly enter the horizon are reported.
+        assert!(notifier
+            .scan(&mut server_txn, ct + hour, day)
+            .unwrap()
+            .is_empty());
+
+        let later = notifier
+            .scan(&mut server_txn, ct + day * 2 + hour, day)
+            .unwrap();
+        assert_eq!(
+            later,
+            vec![ExpiringSession {
+                entry: tuuid,
+                attr: Attribute::ApiTokenSession,
+                id: token_later,
+                expiry: now + day * 3,
+            }]
+        );
+
+        assert!(notifier
+            .scan(&mut server_txn, ct + day * 2 + hour, day)
+            .unwrap()
+            .is_empty());
+
+        // A new notifier, such as after a restart, reports them again.
+        let mut notifier = SessionExpiryNotifier::new();
+        assert_eq!(
+            notifier
+                .scan(&mut server_txn, ct + day * 2 + hour, day)
+                .unwrap(),
+            later
+        );
+    }
+
+    #[qs_test]
+    async fn test_session_expiry_notify_issued_after_scan(server: &QueryServer) {
+        let ct = duration_from_epoch_now();
+        let now = OffsetDateTime::UNIX_EPOCH + ct;
+        let minute = Duration::from_secs(60);
+        let hour = Duration::from_secs(3600);
+        let day = Duration::from_secs(86400);
+
+        let cred = Credential::new_password_only(
+            &CryptoPolicy::minimum(),
+            "test_password",
+            OffsetDateTime::UNIX_EPOCH,
+        )
+        .unwrap();
+
+        let tuuid = Uuid::new_v4();
+        let session_id = Uuid::new_v4();
+
+        let mut server_txn = server.write(ct).await.unwrap();
+        let e = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Name, Value::new_iname("testaccount")),
+            (Attribute::Uuid, Value::Uuid(tuuid)),
+            (Attribute::DisplayName, Value::new_utf8s("testaccount")),
+            (
+                Attribute::PrimaryCredential,
+                Value::Cred("primary".to_string(), cred.clone())
+            )
+        );
+        server_txn.internal_create(vec![e]).unwrap();
+        server_txn.commit().unwrap();
+
+        let mut notifier = SessionExpiryNotifier::new();
+        let mut server_txn = server.read().await.unwrap();
+        assert!(notifier.scan(&mut server_txn, ct, day).unwrap().is_empty());
+        drop(server_txn);
+
+        // A short lived session is issued after the scan, expiring within its horizon.
+        let mut server_txn = server.write(ct + minute).await.unwrap();
+        server_txn
+            .internal_modify_uuid(
+                tuuid,
+                &ModifyList::new_append(
+                    Attribute::UserAuthTokenSession,
+                    Session::builder(now + minute, AuthType::Passkey)
+                        .label("session")
+                        .cred_id(cred.uuid)
+                        .expiry(Some(now + hour))
+                        .build_value(session_id)
+                        .unwrap(),
+                ),
+            )
+            .unwrap();
+        server_txn.commit().unwrap();
+
+        let expiring = vec![ExpiringSession {
+            entry: tuuid,
+            attr: Attribute::UserAuthTokenSession,
+            id: session_id,
+            expiry: now + hour,
+        }];
+
+        let mut server_txn = server.read().await.unwrap();
+        assert_eq!(
+            notifier
+                .scan(&mut server_txn, ct + minute * 2, day)
+                .unwrap(),
+            expiring
+        );
+        assert!(notifier
+            .scan(&mut server_txn, ct + minute * 3, day)
+            .unwrap()
+            .is_empty());
+    }
+}
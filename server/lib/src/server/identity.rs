@@ -122,6 +122,27 @@ pub enum IdentityId {
     Internal(Uuid),
 }
 
+impl IdentityId {
+    /// The kind of identity, as displayed to clients.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            IdentityId::User(_) => "user",
+            IdentityId::Synch(_) => "synch",
+            IdentityId::Internal(_) => "internal",
+        }
+    }
+
+    /// The inverse of [`IdentityId::kind`], rebuilding the identity id of `uuid`.
+    pub fn from_kind(kind: &str, uuid: Uuid) -> Option<Self> {
+        match kind {
+            "user" => Some(IdentityId::User(uuid)),
+            "synch" => Some(IdentityId::Synch(uuid)),
+            "internal" => Some(IdentityId::Internal(uuid)),
+            _ => None,
+        }
+    }
+}
+
 impl From<&IdentityId> for Uuid {
     fn from(ident: &IdentityId) -> Uuid {
         match ident {
@@ -151,6 +172,9 @@ pub struct Identity {
     pub(crate) scope: AccessScope,
     limits: Limits,
     last_verified_at: Option<OffsetDateTime>,
+    /// If present, the only attributes this identity may modify, regardless of what
+    /// access controls would otherwise allow. Such identities may not create or delete.
+    write_constraint: Option<BTreeSet<Attribute>>,
 }
 
 impl std::fmt::Display for Identity {
@@ -189,6 +213,7 @@ impl Identity {
             scope,
             limits,
             last_verified_at,
+            write_constraint: None,
         }
     }
 
@@ -220,6 +245,7 @@ impl Identity {
             scope: AccessScope::ReadWrite,
             limits: Limits::unlimited(),
             last_verified_at: None,
+            write_constraint: None,
         }
     }
 
@@ -231,6 +257,7 @@ impl Identity {
             scope: AccessScope::ReadOnly,
             limits: Limits::unlimited(),
             last_verified_at: None,
+            write_constraint: None,
         }
     }
 
@@ -242,6 +269,7 @@ impl Identity {
             scope: AccessScope::ReadWrite,
             limits: Limits::unlimited(),
             last_verified_at: None,
+            write_constraint: None,
         }
     }
 
@@ -253,6 +281,16 @@ impl Identity {
             scope: AccessScope::ReadWrite,
             limits: Limits::unlimited(),
             last_verified_at: None,
+            write_constraint: None,
+        }
+    }
+
+    /// An internal identity that acts on behalf of the session `session_id`, such as when
+    /// committing a change that the session initiated.
+    pub(crate) fn from_internal_for_session(session_id: Uuid) -> Self {
+        Identity {
+            session_id,
+            ..Self::from_internal()
         }
     }
 
@@ -267,6 +305,7 @@ impl Identity {
             scope: AccessScope::ReadOnly,
             limits: Limits::unlimited(),
             last_verified_at: None,
+            write_constraint: None,
         }
     }
 
@@ -280,6 +319,7 @@ impl Identity {
             scope: AccessScope::ReadWrite,
             limits: Limits::unlimited(),
             last_verified_at: None,
+            write_constraint: None,
         }
     }
 
@@ -287,6 +327,15 @@ impl Identity {
         self.scope
     }
 
+    pub(crate) fn write_constraint(&self) -> Option<&BTreeSet<Attribute>> {
+        self.write_constraint.as_ref()
+    }
+
+    pub(crate) fn with_write_constraint(mut self, attrs: BTreeSet<Attribute>) -> Self {
+        self.write_constraint = Some(attrs);
+        self
+    }
+
     pub fn project_with_scope(&self, scope: AccessScope) -> Self {
         let mut new = self.clone();
         new.scope = scope;
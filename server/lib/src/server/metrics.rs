@@ -0,0 +1,267 @@
+//! Sampling of session and api token statistics, so that they can be reported as server
+//! metrics.
+
+use crate::prelude::*;
+use std::collections::BTreeMap;
+use time::OffsetDateTime;
+
+/// A point in time sample of the sessions and api tokens held by all entries.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SessionMetrics {
+    /// User auth sessions that are neither expired, revoked nor disabled.
+    pub live_sessions: usize,
+    /// User auth sessions that have been revoked, and are awaiting trimming.
+    pub revoked_sessions: usize,
+    /// Live oauth2 sessions of the clients with the most sessions, in descending order of
+    /// session count. At most [SESSION_METRICS_OAUTH2_CLIENT_LIMIT] clients are listed.
+    pub oauth2_sessions_by_client: Vec<(Uuid, usize)>,
+    /// Live oauth2 sessions of clients not listed in `oauth2_sessions_by_client`.
+    pub oauth2_sessions_other: usize,
+    /// Api tokens that will expire within [SESSION_METRICS_API_TOKEN_EXPIRY_WINDOW].
+    pub api_tokens_expiring: usize,
+}
+
+impl QueryServerReadTransaction<'_> {
+    #[instrument(level = "debug", skip_all)]
+    /// Sample the session metrics of all entries at `ct`.
+    pub fn session_metrics(&mut self, ct: Duration) -> Result<SessionMetrics, OperationError> {
+        let now = OffsetDateTime::UNIX_EPOCH + ct;
+
+        let filter = filter!(f_or(vec![
+            f_pres(Attribute::UserAuthTokenSession),
+            f_pres(Attribute::OAuth2Session),
+            f_pres(Attribute::ApiTokenSession),
+        ]));
+
+        let entries = self.internal_search(filter)?;
+
+        let mut metrics = SessionMetrics::default();
+        let mut oauth2_counts: BTreeMap<Uuid, usize> = BTreeMap::new();
+
+        for entry in entries.iter() {
+            if let Some(vs) = entry
+                .get_ava_set(Attribute::UserAuthTokenSession)
+                .and_then(|vs| vs.as_session_set())
+            {
+                let (live, revoked) = vs.count_by_state(now);
+                metrics.live_sessions += live;
+                metrics.revoked_sessions += revoked;
+            }
+
+            if let Some(vs) = entry
+                .get_ava_set(Attribute::OAuth2Session)
+                .and_then(|vs| vs.as_oauth2session_set())
+            {
+                for (rs_uuid, count) in vs.count_by_rs_iter(now) {
+                    *oauth2_counts.entry(rs_uuid).or_insert(0) += count;
+                }
+            }
+
+            if let Some(vs) = entry
+                .get_ava_set(Attribute::ApiTokenSession)
+                .and_then(|vs| vs.as_apitoken_set())
+            {
+                metrics.api_tokens_expiring +=
+                    vs.count_expiring(now, SESSION_METRICS_API_TOKEN_EXPIRY_WINDOW);
+            }
+        }
+
+        (
+            metrics.oauth2_sessions_by_client,
+            metrics.oauth2_sessions_other,
+        ) = top_clients(oauth2_counts, SESSION_METRICS_OAUTH2_CLIENT_LIMIT);
+
+        Ok(metrics)
+    }
+}
+
+/// Select the `limit` clients with the most sessions, returning them along with the sum of
+/// the sessions of all other clients. Ties are broken by client uuid so that the selected
+/// clients are stable between samples.
+fn top_clients(counts: BTreeMap<Uuid, usize>, limit: usize) -> (Vec<(Uuid, usize)>, usize) {
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|(a_uuid, a), (b_uuid, b)| b.cmp(a).then_with(|| a_uuid.cmp(b_uuid)));
+
+    let other = if counts.len() > limit {
+        counts.split_off(limit).into_iter().map(|(_, c)| c).sum()
+    } else {
+        0
+    };
+
+    (counts, other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{top_clients, SessionMetrics};
+    use crate::credential::Credential;
+    use crate::prelude::*;
+    use crate::value::{ApiToken, AuthType, Oauth2Session, Session, SessionScope, SessionState};
+    use kanidm_lib_crypto::CryptoPolicy;
+    use std::collections::BTreeMap;
+    use time::OffsetDateTime;
+
+    #[test]
+    fn test_session_metrics_top_clients() {
+        let a = Uuid::new_v4();
+        let b = Uuid::new_v4();
+        let c = Uuid::new_v4();
+
+        let counts = BTreeMap::from([(a, 1), (b, 5), (c, 3)]);
+
+        assert_eq!(top_clients(counts.clone(), 2), (vec![(b, 5), (c, 3)], 1));
+        assert_eq!(
+            top_clients(counts.clone(), 3),
+            (vec![(b, 5), (c, 3), (a, 1)], 0)
+        );
+        assert_eq!(top_clients(counts, 0), (vec![], 9));
+    }
+
+    #[qs_test]
+    async fn test_session_metrics(server: &QueryServer) {
+        let ct = duration_from_epoch_now();
+        let now = OffsetDateTime::UNIX_EPOCH + ct;
+
+        let cred = Credential::new_password_only(
+            &CryptoPolicy::minimum(),
+            "test_password",
+            OffsetDateTime::UNIX_EPOCH,
+        )
+        .unwrap();
+
+        let session = |state: SessionState| Session {
+            label: "label".to_string(),
+            state,
+            issued_at: now,
+            issued_by: IdentityId::Internal(UUID_SYSTEM),
+            cred_id: cred.uuid,
+            scope: SessionScope::ReadOnly,
+            type_: AuthType::Passkey,
+            ext_metadata: Default::default(),
+            bound_to: None,
+            last_active: None,
+            pinned: false,
+            service_account: false,
+            disabled: None,
+        };
+
+        let tuuid = Uuid::new_v4();
+        let rs_uuid = Uuid::new_v4();
+        let (session_a, session_b) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let mut server_txn = server.write(ct).await.unwrap();
+
+        let e = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Name, Value::new_iname("testaccount")),
+            (Attribute::Uuid, Value::Uuid(tuuid)),
+            (Attribute::DisplayName, Value::new_utf8s("testaccount")),
+            (
+                Attribute::PrimaryCredential,
+                Value::Cred("primary".to_string(), cred.clone())
+            )
+        );
+        let e_rs = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServer.to_value()
+            ),
+            (
+                Attribute::Class,
+                EntryClass::OAuth2ResourceServerBasic.to_value()
+            ),
+            (Attribute::Uuid, Value::Uuid(rs_uuid)),
+            (Attribute::Name, Value::new_iname("test_resource_server")),
+            (
+                Attribute::DisplayName,
+                Value::new_utf8s("test_resource_server")
+            ),
+            (
+                Attribute::OAuth2RsOriginLanding,
+                Value::new_url_s("https://demo.example.com").unwrap()
+            )
+        );
+        server_txn.internal_create(vec![e, e_rs]).unwrap();
+        server_txn.commit().unwrap();
+
+        let sample = || async {
+            let mut server_txn = server.read().await.unwrap();
+            server_txn.session_metrics(ct).unwrap()
+        };
+
+        assert_eq!(sample().await, SessionMetrics::default());
+
+        // Create two sessions, and a token that is nearing expiry.
+        let mut server_txn = server.write(ct).await.unwrap();
+        server_txn
+            .internal_modify_uuid(
+                tuuid,
+                &ModifyList::new_list(vec![
+                    Modify::Present(
+                        Attribute::UserAuthTokenSession,
+                        Value::Session(session_a, session(SessionState::NeverExpires)),
+                    ),
+                    Modify::Present(
+                        Attribute::UserAuthTokenSession,
+                        Value::Session(session_b, session(SessionState::NeverExpires)),
+                    ),
+                    Modify::Present(
+                        Attribute::ApiTokenSession,
+                        Value::ApiToken(
+                            Uuid::new_v4(),
+                            ApiToken::builder(now)
+                                .label("token".to_string())
+                                .state(SessionState::ExpiresAt(now + Duration::from_secs(3600)))
+                                .build(),
+                        ),
+                    ),
+                    Modify::Present(
+                        Attribute::OAuth2Session,
+                        Value::Oauth2Session(
+                            Uuid::new_v4(),
+                            Oauth2Session {
+                                parent: Some(session_a),
+                                state: SessionState::NeverExpires,
+                                issued_at: now,
+                                rs_uuid,
+                                granted_scopes: Default::default(),
+                                has_refresh: false,
+                                last_refreshed: None,
+                                origin: None,
+                            },
+                        ),
+                    ),
+                ]),
+            )
+            .unwrap();
+        server_txn.commit().unwrap();
+
+        let metrics = sample().await;
+        assert_eq!(metrics.live_sessions, 2);
+        assert_eq!(metrics.revoked_sessions, 0);
+        assert_eq!(metrics.oauth2_sessions_by_client, vec![(rs_uuid, 1)]);
+        assert_eq!(metrics.oauth2_sessions_other, 0);
+        assert_eq!(metrics.api_tokens_expiring, 1);
+
+        // Revoking a session moves it to the revoked count.
+        let mut server_txn = server.write(ct).await.unwrap();
+        server_txn
+            .internal_modify_uuid(
+                tuuid,
+                &ModifyList::new_remove(
+                    Attribute::UserAuthTokenSession,
+                    PartialValue::Refer(session_b),
+                ),
+            )
+            .unwrap();
+        server_txn.commit().unwrap();
+
+        let metrics = sample().await;
+        assert_eq!(metrics.live_sessions, 1);
+        assert_eq!(metrics.revoked_sessions, 1);
+    }
+}
@@ -17,6 +17,7 @@ use crate::filter::{
     Filter, FilterInvalid, FilterValid, FilterValidResolved, ResolveFilterCache,
     ResolveFilterCacheReadTxn,
 };
+use crate::idm::audit::AuditEvent;
 use crate::plugins::{
     self,
     dyngroup::{DynGroup, DynGroupCache},
@@ -30,7 +31,7 @@ use crate::schema::{
     Schema, SchemaAttribute, SchemaClass, SchemaReadTransaction, SchemaTransaction,
     SchemaWriteTransaction,
 };
-use crate::value::{CredentialType, EXTRACT_VAL_DN};
+use crate::value::{CredentialType, API_TOKEN_LABEL_PREFIX, EXTRACT_VAL_DN};
 use crate::valueset::*;
 use concread::arcache::{ARCacheBuilder, ARCacheReadTxn, ARCacheWriteTxn};
 use concread::cowcell::*;
@@ -38,7 +39,9 @@ use crypto_glue::{hmac_s256::HmacSha256Key, s256::Sha256Output};
 use hashbrown::{HashMap, HashSet};
 use kanidm_proto::internal::{DomainInfo as ProtoDomainInfo, ImageValue, UiHint};
 use kanidm_proto::scim_v1::{
-    server::{ScimListResponse, ScimOAuth2ClaimMap, ScimOAuth2ScopeMap, ScimReference},
+    server::{
+        ScimListResponse, ScimOAuth2ClaimMap, ScimOAuth2ScopeMap, ScimReference, ScimSessionValue,
+    },
     JsonValue, ScimEntryGetQuery, ScimFilter,
 };
 use std::collections::{BTreeMap, BTreeSet};
@@ -54,8 +57,10 @@ pub mod assert;
 pub mod batch_modify;
 pub mod create;
 pub mod delete;
+pub mod expiry;
 pub mod identity;
 pub(crate) mod keys;
+pub mod metrics;
 pub(crate) mod migrations;
 pub mod modify;
 pub(crate) mod recycle;
@@ -187,6 +192,8 @@ pub struct QueryServerReadTransaction<'a> {
     // Future we may need this.
     // cid_max: CowCellReadTxn<Cid>,
     trim_cid: Cid,
+    // As per the write transaction, the time values are resolved against.
+    curtime: Duration,
     txn_name_to_uuid: BTreeMap<String, Uuid>,
 }
 
@@ -247,6 +254,9 @@ pub struct QueryServerWriteTransaction<'a> {
     >,
     dyngroup_cache: CowCellWriteTxn<'a, DynGroupCache>,
     txn_name_to_uuid: BTreeMap<String, Uuid>,
+    // Audit events are held until the transaction commits, so that changes which are
+    // rolled back are never reported.
+    audit_events: Vec<AuditEvent>,
 }
 
 impl QueryServerWriteTransaction<'_> {
@@ -299,6 +309,10 @@ pub trait QueryServerTransaction<'a> {
 
     fn get_resolve_filter_cache(&mut self) -> Option<&mut ResolveFilterCacheReadTxn<'a>>;
 
+    /// The time this transaction considers to be current, used to resolve values that
+    /// depend on the current time.
+    fn get_txn_curtime_odt(&self) -> OffsetDateTime;
+
     fn get_feature_hmac_name_history_config(&self) -> &HmacNameHistoryConfig;
 
     fn txn_name_to_uuid(&mut self) -> &mut BTreeMap<String, Uuid>;
@@ -855,16 +869,30 @@ pub trait QueryServerTransaction<'a> {
                     SyntaxType::ReferenceUuid
                     | SyntaxType::OauthScopeMap
                     | SyntaxType::Session
-                    | SyntaxType::ApiToken
                     | SyntaxType::Oauth2Session
                     | SyntaxType::ApplicationPassword => {
                         let un = self.name_to_uuid(value).unwrap_or(UUID_DOES_NOT_EXIST);
                         Ok(PartialValue::Refer(un))
                     }
-                    SyntaxType::OauthClaimMap => self
-                        .name_to_uuid(value)
-                        .map(PartialValue::Refer)
-                        .or_else(|_| Ok(PartialValue::new_iutf8(value))),
+                    // A token is found by label when the label is explicitly prefixed. Otherwise
+                    // if this isn't a reference, it's a token label.
+                    SyntaxType::ApiToken => match value.strip_prefix(API_TOKEN_LABEL_PREFIX) {
+                        Some(label) => Ok(PartialValue::new_iutf8(label)),
+                        None => match self.name_to_uuid(value) {
+                            Ok(un) => Ok(PartialValue::Refer(un)),
+                            Err(OperationError::NoMatchingEntries) => {
+                                Ok(PartialValue::new_iutf8(value))
+                            }
+                            Err(err) => Err(err),
+                        },
+                    },
+                    SyntaxType::OauthClaimMap => match self.name_to_uuid(value) {
+                        Ok(un) => Ok(PartialValue::Refer(un)),
+                        Err(OperationError::NoMatchingEntries) => {
+                            Ok(PartialValue::new_iutf8(value))
+                        }
+                        Err(err) => Err(err),
+                    },
 
                     SyntaxType::JsonFilter => {
                         PartialValue::new_json_filter_s(value).ok_or_else(|| {
@@ -1031,6 +1059,44 @@ pub trait QueryServerTransaction<'a> {
 
                 Ok(Some(ScimValueKanidm::OAuth2ScopeMap(scim_claim_maps)))
             }
+
+            ScimValueIntermediate::Oauth2Session(mut sessions) => {
+                // Many sessions are likely to share a client, so only look up each once.
+                let mut display_names: BTreeMap<Uuid, Option<String>> = BTreeMap::new();
+
+                for session in sessions.iter_mut() {
+                    let display_name = match display_names.get(&session.client_id) {
+                        Some(display_name) => display_name.clone(),
+                        None => {
+                            let display_name = match self.internal_search_uuid(session.client_id) {
+                                Ok(entry) => entry
+                                    .get_ava_single_utf8(Attribute::DisplayName)
+                                    .or_else(|| entry.get_ava_single_iname(Attribute::Name))
+                                    .map(str::to_string),
+                                // The client was removed, so there is nothing to display.
+                                Err(OperationError::NoMatchingEntries) => None,
+                                Err(err) => return Err(err),
+                            };
+                            display_names.insert(session.client_id, display_name.clone());
+                            display_name
+                        }
+                    };
+
+                    session.client_display_name = display_name;
+                }
+
+                Ok(Some(ScimValueKanidm::OAuth2Session(sessions)))
+            }
+
+            ScimValueIntermediate::ApiToken(mut tokens) => {
+                let ct = self.get_txn_curtime_odt();
+
+                for token in tokens.iter_mut() {
+                    token.expired = token.expires.is_some_and(|exp| exp <= ct);
+                }
+
+                Ok(Some(ScimValueKanidm::ApiToken(tokens)))
+            }
         }
     }
 
@@ -1429,6 +1495,10 @@ impl<'a> QueryServerTransaction<'a> for QueryServerReadTransaction<'a> {
         Some(&mut self.resolve_filter_cache)
     }
 
+    fn get_txn_curtime_odt(&self) -> OffsetDateTime {
+        OffsetDateTime::UNIX_EPOCH + self.curtime
+    }
+
     fn get_feature_hmac_name_history_config(&self) -> &HmacNameHistoryConfig {
         &self.feature_config.hmac_name_history
     }
@@ -1610,6 +1680,83 @@ impl QueryServerReadTransaction<'_> {
         }
     }
 
+    /// Retrieve the single session or api token `value_id` from the attribute `attr` of
+    /// the entry `uuid`. Access controls apply as though the whole attribute was read.
+    pub fn scim_entry_id_attr_value_get_ext(
+        &mut self,
+        uuid: Uuid,
+        class: EntryClass,
+        attr: &Attribute,
+        value_id: Uuid,
+        ident: Identity,
+    ) -> Result<ScimSessionValue, OperationError> {
+        let Some(schema_a) = self.get_schema().get_attributes().get(attr) else {
+            return Err(OperationError::InvalidAttributeName(attr.to_string()));
+        };
+
+        if !matches!(
+            schema_a.syntax,
+            SyntaxType::Session | SyntaxType::Oauth2Session | SyntaxType::ApiToken
+        ) {
+            return Err(OperationError::InvalidAttribute(format!(
+                "{attr} does not contain sessions"
+            )));
+        }
+
+        let filter_intent = filter!(f_and!([
+            f_eq(Attribute::Uuid, PartialValue::Uuid(uuid)),
+            f_eq(Attribute::Class, class.into())
+        ]));
+
+        let f_intent_valid = filter_intent
+            .validate(self.get_schema())
+            .map_err(OperationError::SchemaViolation)?;
+
+        let f_valid = f_intent_valid.clone().into_ignore_hidden();
+
+        let se = SearchEvent {
+            ident,
+            filter: f_valid,
+            filter_orig: f_intent_valid,
+            attrs: Some(BTreeSet::from([attr.clone()])),
+            // Required to tell an unreadable attribute from one without the value.
+            effective_access_check: true,
+        };
+
+        let mut vs = self.search_ext(&se)?;
+        let entry = match vs.pop() {
+            Some(entry) if vs.is_empty() => entry,
+            _ => {
+                return if vs.is_empty() {
+                    Err(OperationError::NoMatchingEntries)
+                } else {
+                    // Multiple entries matched, should not be possible!
+                    Err(OperationError::UniqueConstraintViolation)
+                };
+            }
+        };
+
+        if !entry.may_search_attr(attr) {
+            return Err(OperationError::AccessDenied);
+        }
+
+        let scim_value = entry
+            .to_scim_value_single(attr, &PartialValue::Refer(value_id), self)?
+            .ok_or(OperationError::NoMatchingEntries)?;
+
+        match scim_value {
+            ScimValueKanidm::AuthSession(mut sessions) => {
+                sessions.pop().map(ScimSessionValue::AuthSession)
+            }
+            ScimValueKanidm::OAuth2Session(mut sessions) => {
+                sessions.pop().map(ScimSessionValue::OAuth2Session)
+            }
+            ScimValueKanidm::ApiToken(mut tokens) => tokens.pop().map(ScimSessionValue::ApiToken),
+            _ => None,
+        }
+        .ok_or(OperationError::InvalidState)
+    }
+
     #[instrument(level = "debug", skip_all)]
     pub fn scim_search_ext(
         &mut self,
@@ -1778,6 +1925,10 @@ impl<'a> QueryServerTransaction<'a> for QueryServerWriteTransaction<'a> {
         }
     }
 
+    fn get_txn_curtime_odt(&self) -> OffsetDateTime {
+        self.get_curtime_odt()
+    }
+
     fn get_feature_hmac_name_history_config(&self) -> &HmacNameHistoryConfig {
         &self.feature_config.hmac_name_history
     }
@@ -1990,7 +2141,17 @@ impl QueryServer {
         Some((read_ticket, db_ticket))
     }
 
+    /// Begin a read transaction at the current time. See `read_at`.
     pub async fn read(&self) -> Result<QueryServerReadTransaction<'_>, OperationError> {
+        self.read_at(duration_from_epoch_now()).await
+    }
+
+    /// Begin a read transaction, where values that depend on the current time such as
+    /// token expiry are resolved against `curtime`.
+    pub async fn read_at(
+        &self,
+        curtime: Duration,
+    ) -> Result<QueryServerReadTransaction<'_>, OperationError> {
         let (read_ticket, db_ticket) = self
             .read_acquire_ticket()
             .await
@@ -2017,6 +2178,7 @@ impl QueryServer {
             _read_ticket: read_ticket,
             resolve_filter_cache: self.resolve_filter_cache.read(),
             trim_cid,
+            curtime,
             txn_name_to_uuid: Default::default(),
         })
     }
@@ -2128,6 +2290,7 @@ impl QueryServer {
             dyngroup_cache: self.dyngroup_cache.write(),
             key_providers: self.key_providers.write(),
             txn_name_to_uuid: Default::default(),
+            audit_events: Vec::new(),
         })
     }
 
@@ -2195,6 +2358,15 @@ impl<'a> QueryServerWriteTransaction<'a> {
         &self.cid
     }
 
+    pub(crate) fn queue_audit_event(&mut self, audit_event: AuditEvent) {
+        self.audit_events.push(audit_event);
+    }
+
+    /// Take the audit events queued in this transaction, to be sent once it commits.
+    pub(crate) fn take_audit_events(&mut self) -> Vec<AuditEvent> {
+        std::mem::take(&mut self.audit_events)
+    }
+
     pub(crate) fn get_key_providers_mut(&mut self) -> &mut KeyProvidersWriteTransaction<'a> {
         &mut self.key_providers
     }
@@ -2991,6 +3163,7 @@ impl<'a> QueryServerWriteTransaction<'a> {
             resolve_filter_cache_clear,
             mut resolve_filter_cache_write,
             txn_name_to_uuid: _,
+            audit_events: _,
         } = self;
         debug_assert!(!committed);
 
@@ -3032,12 +3205,16 @@ impl<'a> QueryServerWriteTransaction<'a> {
 
 #[cfg(test)]
 mod tests {
+    use crate::credential::Credential;
     use crate::prelude::*;
+    use crate::value::{ApiToken, AuthType, Session};
+    use kanidm_lib_crypto::CryptoPolicy;
     use kanidm_proto::scim_v1::{
-        server::{ScimListResponse, ScimReference},
+        server::{ScimListResponse, ScimReference, ScimSessionValue, ScimValueKanidm},
         JsonValue, ScimEntryGetQuery, ScimFilter,
     };
     use std::num::NonZeroU64;
+    use time::OffsetDateTime;
 
     #[qs_test]
     async fn test_name_to_uuid(server: &QueryServer) {
@@ -3228,6 +3405,22 @@ mod tests {
             r4,
             Ok(Value::Refer(uuid!("cc8e95b4-c24f-4d68-ba54-8bed76f63930")))
         );
+
+        // Api tokens are found by reference, by label when nothing else matches, or by
+        // label when explicitly prefixed.
+        let r5 = server_txn.clone_partialvalue(&Attribute::ApiTokenSession, "testperson1");
+        assert_eq!(
+            r5,
+            Ok(PartialValue::Refer(uuid!(
+                "cc8e95b4-c24f-4d68-ba54-8bed76f63930"
+            )))
+        );
+
+        let r6 = server_txn.clone_partialvalue(&Attribute::ApiTokenSession, "Deploy");
+        assert_eq!(r6, Ok(PartialValue::new_iutf8("deploy")));
+
+        let r7 = server_txn.clone_partialvalue(&Attribute::ApiTokenSession, "label:TestPerson1");
+        assert_eq!(r7, Ok(PartialValue::new_iutf8("testperson1")));
     }
 
     #[qs_test(domain_level=DOMAIN_LEVEL_13)]
@@ -3443,6 +3636,53 @@ mod tests {
         }
     }
 
+    #[qs_test]
+    async fn test_scim_api_token_expired_at_read_time(server: &QueryServer) {
+        let ct = duration_from_epoch_now();
+        let mut server_txn = server.write(ct).await.unwrap();
+
+        let sa_uuid = Uuid::new_v4();
+        let issued_at = server_txn.get_txn_curtime_odt();
+        let token = ApiToken::builder(issued_at)
+            .label("expiring")
+            .expiry(Some(issued_at + time::Duration::minutes(1)))
+            .build_value(Uuid::new_v4())
+            .unwrap();
+
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Name, Value::new_iname("test_sa")),
+            (Attribute::Uuid, Value::Uuid(sa_uuid)),
+            (Attribute::DisplayName, Value::new_utf8s("test_sa")),
+            (Attribute::ApiTokenSession, token.clone())
+        );
+
+        assert!(server_txn.internal_create(vec![e1]).is_ok());
+        assert!(server_txn.commit().is_ok());
+
+        // Expiry is resolved against the time the read transaction was given.
+        for (read_ct, expect_expired) in [(ct, false), (ct + Duration::from_secs(120), true)] {
+            let mut read_txn = server.read_at(read_ct).await.unwrap();
+            let entry = read_txn.internal_search_uuid(sa_uuid).unwrap();
+            let scim_entry = entry
+                .as_ref()
+                .clone()
+                .into_reduced()
+                .to_scim_kanidm(&mut read_txn)
+                .unwrap();
+
+            match scim_entry.attrs.get(&Attribute::ApiTokenSession) {
+                Some(ScimValueKanidm::ApiToken(tokens)) => {
+                    assert_eq!(tokens.len(), 1);
+                    assert_eq!(tokens[0].expired, expect_expired);
+                }
+                other => panic!("expected ApiToken, actual {other:?}"),
+            }
+        }
+    }
+
     #[qs_test]
     async fn test_scim_effective_access_query(server: &QueryServer) {
         let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
@@ -3663,4 +3903,106 @@ mod tests {
         assert_eq!(testgroup_name_0, "testgroup2");
         assert_eq!(testgroup_name_1, "testgroup3");
     }
+
+    #[qs_test]
+    async fn test_scim_entry_id_attr_value_get(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let p = CryptoPolicy::minimum();
+        let cred =
+            Credential::new_password_only(&p, "test_password", OffsetDateTime::UNIX_EPOCH).unwrap();
+
+        let person_uuid = Uuid::new_v4();
+        let session_id = Uuid::new_v4();
+        let other_session_id = Uuid::new_v4();
+
+        let session = |session_id: Uuid| {
+            Session::builder(server_txn.get_txn_curtime_odt(), AuthType::Passkey)
+                .cred_id(cred.uuid)
+                .build_value(session_id)
+                .unwrap()
+        };
+
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("testperson1")),
+            (Attribute::Uuid, Value::Uuid(person_uuid)),
+            (Attribute::DisplayName, Value::new_utf8s("testperson1")),
+            (
+                Attribute::PrimaryCredential,
+                Value::Cred("primary".to_string(), cred.clone())
+            ),
+            (Attribute::UserAuthTokenSession, session(session_id)),
+            (Attribute::UserAuthTokenSession, session(other_session_id))
+        );
+
+        assert!(server_txn.internal_create(vec![e1]).is_ok());
+        assert!(server_txn.commit().is_ok());
+
+        let mut server_txn = server.read().await.unwrap();
+
+        let person_entry = server_txn.internal_search_uuid(person_uuid).unwrap();
+        let person_ident = Identity::from_impersonate_entry_readwrite(person_entry);
+
+        // A person can read their own session.
+        let scim_session = server_txn
+            .scim_entry_id_attr_value_get_ext(
+                person_uuid,
+                EntryClass::Object,
+                &Attribute::UserAuthTokenSession,
+                session_id,
+                person_ident.clone(),
+            )
+            .expect("Failed to get session");
+
+        assert!(matches!(
+            scim_session,
+            ScimSessionValue::AuthSession(session) if session.id == session_id
+        ));
+
+        // Sessions that don't exist are not found.
+        assert_eq!(
+            server_txn
+                .scim_entry_id_attr_value_get_ext(
+                    person_uuid,
+                    EntryClass::Object,
+                    &Attribute::UserAuthTokenSession,
+                    Uuid::new_v4(),
+                    person_ident.clone(),
+                )
+                .unwrap_err(),
+            OperationError::NoMatchingEntries
+        );
+
+        // Only session attributes can be read this way.
+        assert!(matches!(
+            server_txn.scim_entry_id_attr_value_get_ext(
+                person_uuid,
+                EntryClass::Object,
+                &Attribute::Name,
+                session_id,
+                person_ident,
+            ),
+            Err(OperationError::InvalidAttribute(_))
+        ));
+
+        // idm_admin can read the person, but not their sessions.
+        let idm_admin_entry = server_txn.internal_search_uuid(UUID_IDM_ADMIN).unwrap();
+        let idm_admin_ident = Identity::from_impersonate_entry_readwrite(idm_admin_entry);
+
+        assert_eq!(
+            server_txn
+                .scim_entry_id_attr_value_get_ext(
+                    person_uuid,
+                    EntryClass::Object,
+                    &Attribute::UserAuthTokenSession,
+                    session_id,
+                    idm_admin_ident,
+                )
+                .unwrap_err(),
+            OperationError::AccessDenied
+        );
+    }
 }
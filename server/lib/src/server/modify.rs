@@ -106,9 +106,10 @@ impl QueryServerWriteTransaction<'_> {
             .collect();
 
         candidates.iter_mut().try_for_each(|er| {
-            er.apply_modlist(&me.modlist).inspect_err(|_e| {
-                error!("Modification failed for {:?}", er.get_uuid());
-            })
+            er.apply_modlist(&me.modlist, self.curtime)
+                .inspect_err(|_e| {
+                    error!("Modification failed for {:?}", er.get_uuid());
+                })
         })?;
 
         trace!("modify: candidates -> {:?}", candidates);
@@ -609,6 +610,7 @@ impl QueryServerWriteTransaction<'_> {
 mod tests {
     use crate::credential::Credential;
     use crate::prelude::*;
+    use crate::value::{ApiToken, ApiTokenScope, AuthType, Session, SessionScope, SessionState};
     use kanidm_lib_crypto::CryptoPolicy;
     use time::OffsetDateTime;
 
@@ -996,4 +998,197 @@ mod tests {
             Err(OperationError::AccessDenied)
         );
     }
+
+    fn test_session(issued_by: Uuid, cred_id: Uuid) -> Session {
+        Session {
+            label: "label".to_string(),
+            state: SessionState::NeverExpires,
+            issued_at: OffsetDateTime::UNIX_EPOCH,
+            issued_by: IdentityId::User(issued_by),
+            cred_id,
+            scope: SessionScope::ReadWrite,
+            type_: AuthType::Passkey,
+            ext_metadata: Default::default(),
+            bound_to: None,
+            last_active: None,
+            pinned: false,
+            service_account: false,
+            disabled: None,
+        }
+    }
+
+    fn create_person_with_session(
+        server_txn: &mut QueryServerWriteTransaction,
+        name: &str,
+        user_uuid: Uuid,
+        session_id: Uuid,
+    ) {
+        let p = CryptoPolicy::minimum();
+        let cred =
+            Credential::new_password_only(&p, "test_password", OffsetDateTime::UNIX_EPOCH).unwrap();
+        let cred_id = cred.uuid;
+
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Name, Value::new_iname(name)),
+            (Attribute::Uuid, Value::Uuid(user_uuid)),
+            (Attribute::DisplayName, Value::new_utf8s(name)),
+            (
+                Attribute::PrimaryCredential,
+                Value::Cred("primary".to_string(), cred.clone())
+            ),
+            (
+                Attribute::UserAuthTokenSession,
+                Value::Session(session_id, test_session(user_uuid, cred_id))
+            )
+        );
+
+        assert!(server_txn.internal_create(vec![e1]).is_ok());
+    }
+
+    fn session_is_revoked(
+        server_txn: &mut QueryServerWriteTransaction,
+        user_uuid: Uuid,
+        session_id: Uuid,
+    ) -> bool {
+        server_txn
+            .internal_search_uuid(user_uuid)
+            .expect("failed")
+            .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+            .and_then(|sessions| sessions.get(&session_id))
+            .map(|session| matches!(session.state, SessionState::RevokedAt(_)))
+            .expect("No session found")
+    }
+
+    #[qs_test]
+    async fn test_modify_session_self_revoke(server: &QueryServer) {
+        let user_uuid = uuid!("cc8e95b4-c24f-4d68-ba54-8bed76f63930");
+        let session_id = Uuid::new_v4();
+
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+        create_person_with_session(&mut server_txn, "testperson1", user_uuid, session_id);
+
+        let testperson_entry = server_txn.internal_search_uuid(user_uuid).unwrap();
+        let user_ident = Identity::from_impersonate_entry_readwrite(testperson_entry);
+
+        // A person can read their own sessions.
+        let se = SearchEvent::new_impersonate_identity(
+            user_ident.clone(),
+            filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(user_uuid))),
+        );
+        let entry = server_txn
+            .search_ext(&se)
+            .expect("Failed to search")
+            .pop()
+            .expect("No entry found");
+        assert!(entry.attribute_pres(Attribute::UserAuthTokenSession));
+
+        // But they can't add a forged session to themself.
+        let forged_session_id = Uuid::new_v4();
+        let me_forge = ModifyEvent::new_impersonate_identity(
+            user_ident.clone(),
+            filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(user_uuid))),
+            ModifyList::new_list(vec![Modify::Present(
+                Attribute::UserAuthTokenSession,
+                Value::Session(forged_session_id, test_session(user_uuid, Uuid::new_v4())),
+            )]),
+        );
+        assert_eq!(
+            server_txn.modify(&me_forge),
+            Err(OperationError::AccessDenied)
+        );
+
+        // Removing their own session revokes it.
+        let me_revoke = ModifyEvent::new_impersonate_identity(
+            user_ident,
+            filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(user_uuid))),
+            ModifyList::new_list(vec![Modify::Removed(
+                Attribute::UserAuthTokenSession,
+                PartialValue::Refer(session_id),
+            )]),
+        );
+        assert!(server_txn.modify(&me_revoke).is_ok());
+        assert!(session_is_revoked(&mut server_txn, user_uuid, session_id));
+    }
+
+    #[qs_test]
+    async fn test_modify_session_service_desk_revoke(server: &QueryServer) {
+        let user_uuid = uuid!("cc8e95b4-c24f-4d68-ba54-8bed76f63930");
+        let desk_uuid = Uuid::new_v4();
+        let session_id = Uuid::new_v4();
+
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+        create_person_with_session(&mut server_txn, "testperson1", user_uuid, session_id);
+        create_person_with_session(&mut server_txn, "servicedesk1", desk_uuid, Uuid::new_v4());
+
+        assert!(server_txn
+            .internal_modify_uuid(
+                UUID_IDM_SERVICE_DESK,
+                &ModifyList::new_append(Attribute::Member, Value::Refer(desk_uuid)),
+            )
+            .is_ok());
+
+        let desk_entry = server_txn.internal_search_uuid(desk_uuid).unwrap();
+        let desk_ident = Identity::from_impersonate_entry_readwrite(desk_entry);
+
+        // The service desk can find the person, but can't read their sessions.
+        let se = SearchEvent::new_impersonate_identity(
+            desk_ident.clone(),
+            filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(user_uuid))),
+        );
+        let entry = server_txn
+            .search_ext(&se)
+            .expect("Failed to search")
+            .pop()
+            .expect("No entry found");
+        assert!(!entry.attribute_pres(Attribute::UserAuthTokenSession));
+
+        // Nor can they issue a session or api token to the person.
+        let me_forge = ModifyEvent::new_impersonate_identity(
+            desk_ident.clone(),
+            filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(user_uuid))),
+            ModifyList::new_list(vec![Modify::Present(
+                Attribute::UserAuthTokenSession,
+                Value::Session(Uuid::new_v4(), test_session(desk_uuid, Uuid::new_v4())),
+            )]),
+        );
+        assert_eq!(
+            server_txn.modify(&me_forge),
+            Err(OperationError::AccessDenied)
+        );
+
+        let me_forge = ModifyEvent::new_impersonate_identity(
+            desk_ident.clone(),
+            filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(user_uuid))),
+            ModifyList::new_list(vec![Modify::Present(
+                Attribute::ApiTokenSession,
+                Value::ApiToken(
+                    Uuid::new_v4(),
+                    ApiToken::builder(OffsetDateTime::UNIX_EPOCH)
+                        .label("forged")
+                        .issued_by(IdentityId::User(desk_uuid))
+                        .scope(ApiTokenScope::ReadWrite)
+                        .build(),
+                ),
+            )]),
+        );
+        assert_eq!(
+            server_txn.modify(&me_forge),
+            Err(OperationError::AccessDenied)
+        );
+
+        // But they can revoke the persons session.
+        let me_revoke = ModifyEvent::new_impersonate_identity(
+            desk_ident,
+            filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(user_uuid))),
+            ModifyList::new_list(vec![Modify::Removed(
+                Attribute::UserAuthTokenSession,
+                PartialValue::Refer(session_id),
+            )]),
+        );
+        assert!(server_txn.modify(&me_revoke).is_ok());
+        assert!(session_is_revoked(&mut server_txn, user_uuid, session_id));
+    }
 }
@@ -2,6 +2,7 @@ use super::modify::ModifyPartial;
 use crate::event::ReviveRecycledEvent;
 use crate::prelude::*;
 use crate::server::Plugins;
+use crate::value::SessionState;
 use std::collections::BTreeMap;
 
 impl QueryServerWriteTransaction<'_> {
@@ -96,6 +97,70 @@ impl QueryServerWriteTransaction<'_> {
         Ok(entries.len())
     }
 
+    #[instrument(level = "debug", skip_all)]
+    /// Revoke sessions and api tokens that expired more than [EXPIRED_SESSION_PURGE_GRACE]
+    /// ago, so that they are later trimmed. At most `max_entries` entries are modified,
+    /// returning the number that were.
+    pub fn purge_expired_sessions(&mut self, max_entries: usize) -> Result<usize, OperationError> {
+        let cutoff = self.get_curtime_odt() - EXPIRED_SESSION_PURGE_GRACE;
+
+        let filter = filter!(f_or(vec![
+            f_lt(
+                Attribute::UserAuthTokenSession,
+                PartialValue::DateTime(cutoff)
+            ),
+            f_lt(Attribute::ApiTokenSession, PartialValue::DateTime(cutoff)),
+        ]));
+
+        // The ordering index narrows this to entries holding sessions or tokens, and the
+        // match skips revoked values. As the cutoff is checked again below, values that
+        // expired within the grace window are left alone.
+        let candidates = self.internal_search(filter)?;
+
+        let modset: Vec<_> = candidates
+            .iter()
+            .filter_map(|entry| {
+                let expired_sessions = entry
+                    .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+                    .into_iter()
+                    .flatten()
+                    .filter(|(_, session)| {
+                        matches!(session.state, SessionState::ExpiresAt(exp) if exp < cutoff)
+                    })
+                    .map(|(session_id, _)| {
+                        Modify::Removed(
+                            Attribute::UserAuthTokenSession,
+                            PartialValue::Refer(*session_id),
+                        )
+                    });
+
+                let expired_tokens = entry
+                    .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+                    .into_iter()
+                    .flatten()
+                    .filter(|(_, token)| token.expiry().is_some_and(|exp| exp < cutoff))
+                    .map(|(token_id, _)| {
+                        Modify::Removed(Attribute::ApiTokenSession, PartialValue::Refer(*token_id))
+                    });
+
+                let modlist: Vec<_> = expired_sessions.chain(expired_tokens).collect();
+
+                (!modlist.is_empty()).then(|| (entry.get_uuid(), ModifyList::new_list(modlist)))
+            })
+            .take(max_entries)
+            .collect();
+
+        // it's enough to say "yeah we tried to touch this many" because
+        // we're using this to decide if we're going to commit the txn
+        let touched = modset.len();
+
+        if touched > 0 {
+            self.internal_batch_modify(modset.into_iter())?;
+        }
+
+        Ok(touched)
+    }
+
     #[instrument(level = "debug", skip_all)]
     pub fn revive_recycled(&mut self, re: &ReviveRecycledEvent) -> Result<(), OperationError> {
         // Revive an entry to live. This is a specialised function, and draws a lot of
@@ -359,11 +424,14 @@ impl QueryServerWriteTransaction<'_> {
 #[cfg(test)]
 mod tests {
     use super::ReviveRecycledEvent;
+    use crate::credential::Credential;
     use crate::event::{CreateEvent, DeleteEvent};
     use crate::prelude::*;
     use crate::server::ModifyEvent;
     use crate::server::SearchEvent;
     use crate::server::ValueSetMessage;
+    use crate::value::{ApiToken, AuthType, Session, SessionState};
+    use kanidm_lib_crypto::CryptoPolicy;
     use kanidm_proto::v1::OutboundMessage;
     use time::OffsetDateTime;
 
@@ -975,4 +1043,234 @@ mod tests {
 
         server_txn.commit().unwrap();
     }
+
+    #[qs_test]
+    async fn test_recycle_revive_api_tokens(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let t_uuid = Uuid::new_v4();
+        let token_ids = [Uuid::new_v4(), Uuid::new_v4()];
+        let odt = OffsetDateTime::UNIX_EPOCH + duration_from_epoch_now();
+
+        let mut e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+            (Attribute::Name, Value::new_iname("testaccount1")),
+            (Attribute::Uuid, Value::Uuid(t_uuid)),
+            (Attribute::DisplayName, Value::new_utf8s("testaccount1"))
+        );
+        e1.add_ava(
+            Attribute::ApiTokenSession,
+            Value::ApiToken(token_ids[0], ApiToken::builder(odt).label("never").build()),
+        );
+        e1.add_ava(
+            Attribute::ApiTokenSession,
+            Value::ApiToken(
+                token_ids[1],
+                ApiToken::builder(odt)
+                    .label("expires")
+                    .state(SessionState::ExpiresAt(odt + Duration::from_secs(3600)))
+                    .build(),
+            ),
+        );
+
+        let ce = CreateEvent::new_internal(vec![e1]);
+        assert!(server_txn.create(&ce).is_ok());
+
+        assert!(server_txn.internal_delete_uuid(t_uuid).is_ok());
+        assert!(server_txn.internal_revive_uuid(t_uuid).is_ok());
+
+        // Every token is retained, but none of them are valid once revived.
+        let entry = server_txn.internal_search_uuid(t_uuid).unwrap();
+        let tokens = entry
+            .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+            .expect("Api tokens were lost");
+        assert_eq!(tokens.len(), token_ids.len());
+        for token_id in token_ids {
+            let token = tokens.get(&token_id).expect("Api token was lost");
+            assert!(token.is_revoked());
+            assert!(!token.is_valid_at(odt));
+        }
+
+        assert!(server_txn.commit().is_ok());
+    }
+
+    #[qs_test]
+    async fn test_purge_expired_sessions(server: &QueryServer) {
+        let time_p1 = duration_from_epoch_now();
+        let time_p2 = time_p1 + EXPIRED_SESSION_PURGE_GRACE + Duration::from_secs(120);
+
+        let odt_p1 = OffsetDateTime::UNIX_EPOCH + time_p1;
+        // At p2 this has been expired for longer than the grace.
+        let expired = SessionState::ExpiresAt(odt_p1 + Duration::from_secs(60));
+        // At p2 this is expired, but still within the grace.
+        let recently_expired = SessionState::ExpiresAt(odt_p1 + Duration::from_secs(600));
+
+        // Sessions are only retained while the credential that issued them exists.
+        let cred = Credential::new_password_only(
+            &CryptoPolicy::minimum(),
+            "test_password",
+            OffsetDateTime::UNIX_EPOCH,
+        )
+        .unwrap();
+
+        let session = |state: &SessionState| Session {
+            label: "label".to_string(),
+            state: state.clone(),
+            issued_at: odt_p1,
+            issued_by: IdentityId::Internal(UUID_SYSTEM),
+            cred_id: cred.uuid,
+            scope: SessionScope::ReadOnly,
+            type_: AuthType::Passkey,
+            ext_metadata: Default::default(),
+            bound_to: None,
+            last_active: None,
+            pinned: false,
+            service_account: false,
+            disabled: None,
+        };
+
+        let token = |state: &SessionState| {
+            ApiToken::builder(odt_p1)
+                .label(Uuid::new_v4().to_string())
+                .state(state.clone())
+                .build()
+        };
+
+        // Three accounts have values to purge, one only has values within the grace. The
+        // accounts are (uuid, expired value id, live value id).
+        let expired_accounts: Vec<(Uuid, Uuid, Uuid)> = (0..3)
+            .map(|_| (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()))
+            .collect();
+        let (recent_account, recent_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let account = |i: usize, uuid: Uuid| {
+            entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (Attribute::Class, EntryClass::Account.to_value()),
+                (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+                (
+                    Attribute::Name,
+                    Value::new_iname(&format!("testaccount{i}"))
+                ),
+                (Attribute::Uuid, Value::Uuid(uuid)),
+                (Attribute::DisplayName, Value::new_utf8s("testaccount")),
+                (
+                    Attribute::PrimaryCredential,
+                    Value::Cred("primary".to_string(), cred.clone())
+                )
+            )
+        };
+
+        let mut entries: Vec<_> = expired_accounts
+            .iter()
+            .enumerate()
+            .map(|(i, (uuid, expired_id, live_id))| {
+                let (expired_id, live_id) = (*expired_id, *live_id);
+                let mut e = account(i, *uuid);
+                e.add_ava(
+                    Attribute::UserAuthTokenSession,
+                    Value::Session(expired_id, session(&expired)),
+                );
+                e.add_ava(
+                    Attribute::UserAuthTokenSession,
+                    Value::Session(live_id, session(&SessionState::NeverExpires)),
+                );
+                e.add_ava(
+                    Attribute::ApiTokenSession,
+                    Value::ApiToken(expired_id, token(&expired)),
+                );
+                e.add_ava(
+                    Attribute::ApiTokenSession,
+                    Value::ApiToken(live_id, token(&SessionState::NeverExpires)),
+                );
+                e
+            })
+            .collect();
+
+        let mut e_recent = account(3, recent_account);
+        e_recent.add_ava(
+            Attribute::UserAuthTokenSession,
+            Value::Session(recent_id, session(&recently_expired)),
+        );
+        e_recent.add_ava(
+            Attribute::ApiTokenSession,
+            Value::ApiToken(recent_id, token(&recently_expired)),
+        );
+        entries.push(e_recent);
+
+        let mut server_txn = server.write(time_p1).await.unwrap();
+        server_txn.internal_create(entries).unwrap();
+
+        // Nothing has expired yet.
+        assert_eq!(server_txn.purge_expired_sessions(2), Ok(0));
+        server_txn.commit().unwrap();
+
+        // Purge in batches of two until nothing remains.
+        let mut batches = 0;
+        loop {
+            let mut server_txn = server.write(time_p2).await.unwrap();
+            let touched = server_txn.purge_expired_sessions(2).unwrap();
+            server_txn.commit().unwrap();
+
+            if touched == 0 {
+                break;
+            }
+            assert!(touched <= 2);
+            batches += 1;
+        }
+        assert_eq!(batches, 2);
+
+        let mut server_txn = server.read().await.unwrap();
+
+        let states = |entry: &EntrySealedCommitted, id: &Uuid| {
+            let session = entry
+                .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+                .and_then(|sessions| sessions.get(id))
+                .map(|session| session.state.clone());
+            let token = entry
+                .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+                .and_then(|tokens| tokens.get(id))
+                .map(|token| token.state.clone());
+            (session, token)
+        };
+
+        for (uuid, expired_id, live_id) in expired_accounts {
+            let entry = server_txn.internal_search_uuid(uuid).unwrap();
+
+            let (session, token) = states(&entry, &expired_id);
+            assert!(matches!(session, Some(SessionState::RevokedAt(_))));
+            assert!(matches!(token, Some(SessionState::RevokedAt(_))));
+
+            // Live values are untouched.
+            assert_eq!(
+                states(&entry, &live_id),
+                (
+                    Some(SessionState::NeverExpires),
+                    Some(SessionState::NeverExpires)
+                )
+            );
+        }
+
+        // Values within the grace are untouched.
+        let entry = server_txn.internal_search_uuid(recent_account).unwrap();
+        assert_eq!(
+            states(&entry, &recent_id),
+            (Some(recently_expired.clone()), Some(recently_expired))
+        );
+
+        // Revoked values no longer match by expiry, so a later purge loads nothing.
+        let cutoff = OffsetDateTime::UNIX_EPOCH + time_p2 - EXPIRED_SESSION_PURGE_GRACE;
+        assert!(server_txn
+            .internal_search(filter!(f_or(vec![
+                f_lt(
+                    Attribute::UserAuthTokenSession,
+                    PartialValue::DateTime(cutoff)
+                ),
+                f_lt(Attribute::ApiTokenSession, PartialValue::DateTime(cutoff)),
+            ])))
+            .unwrap()
+            .is_empty());
+    }
 }
@@ -3,16 +3,19 @@ use crate::schema::{SchemaAttribute, SchemaTransaction};
 use crate::server::assert::{AssertEvent, AssertOnce, EntryAssertion};
 use crate::server::batch_modify::{BatchModifyEvent, ModSetValid};
 use crate::server::ValueSetResolveStatus;
+use crate::value::{
+    ApiToken, ApiTokenConstraint, ApiTokenScope, AuthType, Session, SessionDisabled, SessionScope,
+    SessionState,
+};
 use crate::valueset::*;
 use crypto_glue::s256::Sha256Output;
 use kanidm_proto::scim_v1::client::{
     ScimEntryAssertion, ScimEntryPostGeneric, ScimEntryPutGeneric,
 };
+use kanidm_proto::scim_v1::server::{ScimApiToken, ScimAuthSession};
 use kanidm_proto::scim_v1::JsonValue;
-use std::collections::{
-    // BTreeSet,
-    BTreeMap,
-};
+use std::collections::{BTreeMap, BTreeSet};
+use time::OffsetDateTime;
 
 #[derive(Debug)]
 pub struct ScimEntryPutEvent {
@@ -25,6 +28,8 @@ pub struct ScimEntryPutEvent {
     /// Update an attribute to contain the following value state.
     /// If the attribute is None, it is removed.
     pub(crate) attrs: BTreeMap<Attribute, Option<ValueSet>>,
+    /// Sessions and tokens that were omitted from the put, and so are revoked.
+    pub(crate) revoke: BTreeMap<Attribute, BTreeSet<Uuid>>,
 
     /// If an effective access check should be carried out post modification
     /// of the entries
@@ -39,8 +44,21 @@ impl ScimEntryPutEvent {
     ) -> Result<Self, OperationError> {
         let target = entry.id;
 
-        let attrs = entry
-            .attrs
+        // Sessions can't be set, only revoked, so they are resolved separately.
+        let (session_attrs, attrs): (BTreeMap<_, _>, BTreeMap<_, _>) =
+            entry.attrs.into_iter().partition(|(attr, _)| {
+                qs.get_schema()
+                    .get_attributes()
+                    .get(attr)
+                    .is_some_and(|schema_a| {
+                        matches!(
+                            schema_a.syntax,
+                            SyntaxType::Session | SyntaxType::Oauth2Session | SyntaxType::ApiToken
+                        )
+                    })
+            });
+
+        let attrs = attrs
             .into_iter()
             .map(|(attr, json_value)| {
                 qs.resolve_scim_json_put(&attr, json_value)
@@ -48,12 +66,21 @@ impl ScimEntryPutEvent {
             })
             .collect::<Result<_, _>>()?;
 
+        let revoke = session_attrs
+            .into_iter()
+            .map(|(attr, json_value)| {
+                qs.resolve_scim_session_put(target, &attr, json_value)
+                    .map(|revoke| (attr, revoke))
+            })
+            .collect::<Result<_, _>>()?;
+
         let query = entry.query;
 
         Ok(ScimEntryPutEvent {
             ident,
             target,
             attrs,
+            revoke,
             effective_access_check: query.ext_access_check,
         })
     }
@@ -145,11 +172,45 @@ impl ScimAssertEvent {
     }
 }
 
+#[derive(Debug)]
+pub struct ScimSessionRestoreEvent {
+    /// The identity performing the restore. This must be the internal or admin identity.
+    pub(crate) ident: Identity,
+
+    /// The entry that the sessions and api tokens are restored to.
+    pub(crate) target: Uuid,
+
+    /// Auth sessions, as they were read from the entry they are migrated from.
+    pub(crate) sessions: Vec<ScimAuthSession>,
+
+    /// Api tokens, as they were read from the entry they are migrated from.
+    pub(crate) api_tokens: Vec<ScimApiToken>,
+}
+
+impl ScimSessionRestoreEvent {
+    pub fn new(
+        ident: Identity,
+        target: Uuid,
+        sessions: Vec<ScimAuthSession>,
+        api_tokens: Vec<ScimApiToken>,
+    ) -> Self {
+        ScimSessionRestoreEvent {
+            ident,
+            target,
+            sessions,
+            api_tokens,
+        }
+    }
+}
+
 impl QueryServerWriteTransaction<'_> {
     /// SCIM PUT is the handler where a single entry is updated. In a SCIM PUT request
     /// the request defines the state of an attribute in entirety for the update. This
     /// means if the caller wants to add one email address, they must PUT all existing
     /// addresses in addition to the new one.
+    ///
+    /// Sessions and api tokens are the exception, as they can only be revoked. Omitting
+    /// a session from the put revokes it, rather than removing it from the entry.
     pub fn scim_put(
         &mut self,
         scim_entry_put: ScimEntryPutEvent,
@@ -158,11 +219,21 @@ impl QueryServerWriteTransaction<'_> {
             ident,
             target,
             attrs,
+            revoke,
             effective_access_check,
         } = scim_entry_put;
 
         // This function transforms the put event into a modify event.
-        let mods_invalid: ModifyList<ModifyInvalid> = attrs.into();
+        let mut mods_invalid: ModifyList<ModifyInvalid> = attrs.into();
+
+        for (attr, session_ids) in revoke {
+            for session_id in session_ids {
+                mods_invalid.push_mod(Modify::Removed(
+                    attr.clone(),
+                    PartialValue::Refer(session_id),
+                ));
+            }
+        }
 
         let mods_valid = mods_invalid
             .validate(self.get_schema())
@@ -362,6 +433,218 @@ impl QueryServerWriteTransaction<'_> {
         self.assert(assert_event)
     }
 
+    /// Restore auth sessions and api tokens to an entry from their SCIM representations,
+    /// such as when migrating from another server. Unlike the issuance paths this keeps
+    /// the ids, issue times and states of the sessions as they were read, so it is only
+    /// available to the internal and admin identities.
+    ///
+    /// Sessions whose credential is not present on the entry are revoked as usual, so
+    /// credentials must be restored before their sessions.
+    pub fn scim_restore_sessions(
+        &mut self,
+        scim_restore: ScimSessionRestoreEvent,
+    ) -> Result<(), OperationError> {
+        let ScimSessionRestoreEvent {
+            ident,
+            target,
+            sessions,
+            api_tokens,
+        } = scim_restore;
+
+        if !ident.is_internal() && ident.get_uuid() != UUID_ADMIN {
+            security_access!(name = %ident, %target, "session restore denied");
+            return Err(OperationError::AccessDenied);
+        }
+
+        security_info!(
+            name = %ident,
+            %target,
+            sessions = sessions.len(),
+            api_tokens = api_tokens.len(),
+            "session restore initiator"
+        );
+
+        let mut modlist = ModifyList::new();
+
+        for session in sessions {
+            modlist.push_mod(Modify::Present(
+                Attribute::UserAuthTokenSession,
+                self.restore_scim_session(session)?,
+            ));
+        }
+
+        for token in api_tokens {
+            modlist.push_mod(Modify::Present(
+                Attribute::ApiTokenSession,
+                self.restore_scim_api_token(token)?,
+            ));
+        }
+
+        if modlist.is_empty() {
+            return Ok(());
+        }
+
+        self.internal_modify_uuid(target, &modlist)
+    }
+
+    fn restore_scim_session(&self, scim: ScimAuthSession) -> Result<Value, OperationError> {
+        let attr = Attribute::UserAuthTokenSession;
+
+        let issued_at = self.restore_issued_at(&attr, scim.issued_at)?;
+        let state = self.restore_session_state(&attr, issued_at, scim.expires, scim.revoked)?;
+
+        let last_active = scim
+            .last_active
+            .map(|odt| self.restore_timestamp(&attr, issued_at, odt))
+            .transpose()?;
+
+        let disabled = scim
+            .disabled
+            .map(|odt| {
+                self.restore_timestamp(&attr, issued_at, odt)
+                    .and_then(|odt| self.restore_cid(&attr, odt))
+                    .map(SessionDisabled::DisabledAt)
+            })
+            .transpose()?;
+
+        let issued_by =
+            IdentityId::from_kind(&scim.issued_by_type, scim.issued_by).ok_or_else(|| {
+                OperationError::InvalidAttribute(format!("{attr} session issuer type is invalid"))
+            })?;
+
+        let scope = SessionScope::try_from(scim.session_scope.as_str()).map_err(|_| {
+            OperationError::InvalidAttribute(format!("{attr} session scope is invalid"))
+        })?;
+
+        let type_ = AuthType::try_from(scim.auth_type.as_str()).map_err(|_| {
+            OperationError::InvalidAttribute(format!("{attr} session auth type is invalid"))
+        })?;
+
+        Session::builder(issued_at, type_)
+            .label(scim.label)
+            .state(state)
+            .issued_by(issued_by)
+            .cred_id(scim.credential_id)
+            .scope(scope)
+            .bound_to(scim.bound_to)
+            .last_active(last_active)
+            .pinned(scim.pinned)
+            .disabled(disabled)
+            .build_value(scim.id)
+    }
+
+    fn restore_scim_api_token(&self, scim: ScimApiToken) -> Result<Value, OperationError> {
+        let attr = Attribute::ApiTokenSession;
+
+        let issued_at = self.restore_issued_at(&attr, scim.issued_at)?;
+        let state = self.restore_session_state(&attr, issued_at, scim.expires, scim.revoked)?;
+
+        let issued_by =
+            IdentityId::from_kind(&scim.issued_by_type, scim.issued_by).ok_or_else(|| {
+                OperationError::InvalidAttribute(format!("{attr} token issuer type is invalid"))
+            })?;
+
+        let scope = ApiTokenScope::try_from(scim.scope.as_str()).map_err(|_| {
+            OperationError::InvalidAttribute(format!("{attr} token scope is invalid"))
+        })?;
+
+        let constraints = scim
+            .constraints
+            .map(|constraints| {
+                constraints
+                    .iter()
+                    .map(|c| {
+                        ApiTokenConstraint::try_from(c.as_str()).map_err(|_| {
+                            OperationError::InvalidAttribute(format!(
+                                "{attr} token constraint {c} is invalid"
+                            ))
+                        })
+                    })
+                    .collect::<Result<BTreeSet<_>, _>>()
+            })
+            .transpose()?;
+
+        ApiToken::builder(issued_at)
+            .label(scim.label)
+            .state(state)
+            .issued_by(issued_by)
+            .scope(scope)
+            .parent_session(scim.parent_session)
+            .generation(scim.generation)
+            .constraints(constraints)
+            .build_value(scim.id)
+    }
+
+    /// A restored session must have been issued before now.
+    fn restore_issued_at(
+        &self,
+        attr: &Attribute,
+        issued_at: OffsetDateTime,
+    ) -> Result<OffsetDateTime, OperationError> {
+        let issued_at = issued_at.to_offset(time::UtcOffset::UTC);
+        if issued_at > self.get_curtime_odt() {
+            return Err(OperationError::InvalidAttribute(format!(
+                "{attr} session was issued in the future"
+            )));
+        }
+        Ok(issued_at)
+    }
+
+    /// A restored event of a session, such as its revocation, must lie between the
+    /// issue of the session and now.
+    fn restore_timestamp(
+        &self,
+        attr: &Attribute,
+        issued_at: OffsetDateTime,
+        odt: OffsetDateTime,
+    ) -> Result<OffsetDateTime, OperationError> {
+        let odt = odt.to_offset(time::UtcOffset::UTC);
+        if odt < issued_at || odt > self.get_curtime_odt() {
+            return Err(OperationError::InvalidAttribute(format!(
+                "{attr} session timestamp {odt} is not between its issue and now"
+            )));
+        }
+        Ok(odt)
+    }
+
+    fn restore_session_state(
+        &self,
+        attr: &Attribute,
+        issued_at: OffsetDateTime,
+        expires: Option<OffsetDateTime>,
+        revoked: Option<OffsetDateTime>,
+    ) -> Result<SessionState, OperationError> {
+        match (expires, revoked) {
+            (_, Some(revoked)) => self
+                .restore_timestamp(attr, issued_at, revoked)
+                .and_then(|revoked| self.restore_cid(attr, revoked))
+                .map(SessionState::RevokedAt),
+            (Some(expires), None) => {
+                let expires = expires.to_offset(time::UtcOffset::UTC);
+                if expires < issued_at {
+                    return Err(OperationError::InvalidAttribute(format!(
+                        "{attr} session expires before it was issued"
+                    )));
+                }
+                Ok(SessionState::ExpiresAt(expires))
+            }
+            (None, None) => Ok(SessionState::NeverExpires),
+        }
+    }
+
+    /// Changes restored from another server are attributed to this server, at the time
+    /// that they were originally made.
+    fn restore_cid(&self, attr: &Attribute, odt: OffsetDateTime) -> Result<Cid, OperationError> {
+        (odt - OffsetDateTime::UNIX_EPOCH)
+            .try_into()
+            .map(|ts| Cid::new(self.get_server_uuid(), ts))
+            .map_err(|_| {
+                OperationError::InvalidAttribute(format!(
+                    "{attr} session timestamp {odt} is before the unix epoch"
+                ))
+            })
+    }
+
     pub(crate) fn resolve_scim_json_put(
         &mut self,
         attr: &Attribute,
@@ -384,6 +667,135 @@ impl QueryServerWriteTransaction<'_> {
         self.resolve_scim_json(schema_a, value).map(Some)
     }
 
+    /// Resolve a put to a session or api token attribute into the set of sessions that
+    /// must be revoked. The put may only name sessions that already exist on the entry,
+    /// either by id or in the form they were read, and may not alter them. Any session
+    /// that is not named is revoked.
+    fn resolve_scim_session_put(
+        &mut self,
+        target: Uuid,
+        attr: &Attribute,
+        value: Option<JsonValue>,
+    ) -> Result<BTreeSet<Uuid>, OperationError> {
+        let entry = self.internal_search_uuid(target)?;
+
+        // The state and, for api tokens, the label of the current sessions.
+        let current: BTreeMap<Uuid, (&SessionState, Option<&str>)> = match entry.get_ava_set(attr) {
+            None => BTreeMap::new(),
+            Some(vs) => {
+                if let Some(map) = vs.as_session_map() {
+                    map.iter()
+                        .map(|(id, session)| (*id, (&session.state, None)))
+                        .collect()
+                } else if let Some(map) = vs.as_oauth2session_map() {
+                    map.iter()
+                        .map(|(id, session)| (*id, (&session.state, None)))
+                        .collect()
+                } else if let Some(map) = vs.as_apitoken_map() {
+                    map.iter()
+                        .map(|(id, token)| (*id, (&token.state, Some(token.label.as_str()))))
+                        .collect()
+                } else {
+                    return Err(OperationError::InvalidValueState);
+                }
+            }
+        };
+
+        let items = match value {
+            // Revoke everything.
+            None => Vec::new(),
+            Some(JsonValue::Array(items)) => items,
+            Some(_) => {
+                return Err(OperationError::InvalidAttribute(format!(
+                    "{attr} must be an array of session ids"
+                )))
+            }
+        };
+
+        let mut retained = BTreeSet::new();
+        for item in items {
+            let (session_id, fields) = match item {
+                JsonValue::String(id) => (id, None),
+                JsonValue::Object(mut fields) => match fields.remove("id") {
+                    Some(JsonValue::String(id)) => (id, Some(fields)),
+                    _ => {
+                        return Err(OperationError::InvalidAttribute(format!(
+                            "{attr} sessions must have an id"
+                        )))
+                    }
+                },
+                _ => {
+                    return Err(OperationError::InvalidAttribute(format!(
+                        "{attr} must be an array of session ids"
+                    )))
+                }
+            };
+
+            let session_id = Uuid::parse_str(&session_id).map_err(|_| {
+                OperationError::InvalidAttribute(format!("{attr} session id is not a valid uuid"))
+            })?;
+
+            let Some((state, label)) = current.get(&session_id) else {
+                return Err(OperationError::InvalidAttribute(format!(
+                    "{attr} sessions can not be added, only revoked by omitting them"
+                )));
+            };
+
+            if let Some(fields) = fields {
+                let expires = match fields.get("expires") {
+                    None | Some(JsonValue::Null) => None,
+                    Some(JsonValue::String(odt)) => Some(
+                        time::OffsetDateTime::parse(
+                            odt,
+                            &time::format_description::well_known::Rfc3339,
+                        )
+                        .map_err(|_| {
+                            OperationError::InvalidAttribute(format!(
+                                "{attr} session expiry is not a valid datetime"
+                            ))
+                        })?,
+                    ),
+                    Some(_) => {
+                        return Err(OperationError::InvalidAttribute(format!(
+                            "{attr} session expiry is not a valid datetime"
+                        )))
+                    }
+                };
+                let revoked = fields
+                    .get("revoked")
+                    .is_some_and(|revoked| !revoked.is_null());
+                let label_changed = match (fields.get("label"), label) {
+                    (Some(JsonValue::String(new_label)), Some(label)) => new_label != label,
+                    (Some(_), _) => true,
+                    (None, _) => false,
+                };
+
+                let unchanged = match state {
+                    SessionState::ExpiresAt(exp) => expires == Some(*exp) && !revoked,
+                    SessionState::NeverExpires => expires.is_none() && !revoked,
+                    SessionState::RevokedAt(_) => revoked,
+                } && !label_changed;
+
+                if !unchanged {
+                    return Err(OperationError::InvalidAttribute(format!(
+                        "{attr} sessions can not be modified, only revoked by omitting them"
+                    )));
+                }
+            }
+
+            retained.insert(session_id);
+        }
+
+        // Sessions that are already revoked retain their original revocation.
+        Ok(current
+            .into_iter()
+            .filter(|(session_id, (state, _))| {
+                !retained.contains(session_id) && !matches!(state, SessionState::RevokedAt(_))
+            })
+            .map(|(session_id, _)| session_id)
+            .collect())
+    }
+
     pub(crate) fn resolve_scim_json_post(
         &mut self,
         attr: &Attribute,
@@ -530,14 +942,19 @@ impl QueryServerWriteTransaction<'_> {
 
 #[cfg(test)]
 mod tests {
-    use super::{ScimAssertEvent, ScimEntryPutEvent};
+    use super::{ScimAssertEvent, ScimEntryPutEvent, ScimSessionRestoreEvent};
+    use crate::credential::Credential;
     use crate::prelude::*;
+    use crate::value::{ApiToken, ApiTokenConstraint, AuthType, Session, SessionState};
+    use kanidm_lib_crypto::CryptoPolicy;
     use kanidm_proto::scim_v1::client::{
-        ScimEntryAssertion, ScimEntryPutKanidm, ScimReference as ScimClientReference,
+        ScimEntryAssertion, ScimEntryPutGeneric, ScimEntryPutKanidm,
+        ScimReference as ScimClientReference,
     };
     use kanidm_proto::scim_v1::server::ScimReference;
     use kanidm_proto::scim_v1::ScimMail;
     use std::collections::BTreeMap;
+    use time::OffsetDateTime;
 
     #[qs_test]
     async fn scim_put_basic(server: &QueryServer) {
@@ -840,4 +1257,347 @@ mod tests {
 
         server_txn.scim_assert(scim_assert).expect("Must not fail!");
     }
+
+    #[qs_test]
+    async fn scim_put_session_revocation(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let person_uuid = Uuid::new_v4();
+        let keep_uuid = Uuid::new_v4();
+        let revoke_uuid = Uuid::new_v4();
+
+        let p = CryptoPolicy::minimum();
+        let cred =
+            Credential::new_password_only(&p, "test_password", OffsetDateTime::UNIX_EPOCH).unwrap();
+        let cred_id = cred.uuid;
+
+        let session = |label: &str, session_id: Uuid| {
+            Session::builder(server_txn.get_txn_curtime_odt(), AuthType::Passkey)
+                .label(label)
+                .cred_id(cred_id)
+                .build_value(session_id)
+                .unwrap()
+        };
+
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Name, Value::new_iname("testperson")),
+            (Attribute::DisplayName, Value::new_utf8s("Test Person")),
+            (Attribute::Uuid, Value::Uuid(person_uuid)),
+            (
+                Attribute::PrimaryCredential,
+                Value::Cred("primary".to_string(), cred.clone())
+            ),
+            (Attribute::UserAuthTokenSession, session("keep", keep_uuid)),
+            (
+                Attribute::UserAuthTokenSession,
+                session("revoke", revoke_uuid)
+            )
+        );
+
+        assert!(server_txn.internal_create(vec![e1]).is_ok());
+
+        // Users are able to revoke their own sessions.
+        let person_entry = server_txn.internal_search_uuid(person_uuid).unwrap();
+        let person_ident = Identity::from_impersonate_entry_readwrite(person_entry);
+
+        let put_sessions = |sessions: Option<JsonValue>| ScimEntryPutGeneric {
+            id: person_uuid,
+            attrs: [(Attribute::UserAuthTokenSession, sessions)].into(),
+            ..Default::default()
+        };
+
+        // Sessions can't be added.
+        let put_event = ScimEntryPutEvent::try_from(
+            person_ident.clone(),
+            put_sessions(Some(serde_json::json!([Uuid::new_v4()]))),
+            &mut server_txn,
+        );
+        assert!(matches!(
+            put_event,
+            Err(OperationError::InvalidAttribute(_))
+        ));
+
+        // Nor can they be modified.
+        let put_event = ScimEntryPutEvent::try_from(
+            person_ident.clone(),
+            put_sessions(Some(serde_json::json!([
+                { "id": keep_uuid, "expires": "2020-01-01T00:00:00Z" },
+                revoke_uuid
+            ]))),
+            &mut server_txn,
+        );
+        assert!(matches!(
+            put_event,
+            Err(OperationError::InvalidAttribute(_))
+        ));
+
+        // Omitting a session revokes it, and it's retained on the entry.
+        let put_event = ScimEntryPutEvent::try_from(
+            person_ident.clone(),
+            put_sessions(Some(serde_json::json!([{ "id": keep_uuid }]))),
+            &mut server_txn,
+        )
+        .expect("Failed to resolve sessions");
+
+        let updated_entry = server_txn.scim_put(put_event).expect("Failed to put");
+        let Some(ScimValueKanidm::AuthSession(sessions)) =
+            updated_entry.attrs.get(&Attribute::UserAuthTokenSession)
+        else {
+            unreachable!("Expected sessions");
+        };
+
+        assert_eq!(sessions.len(), 2);
+        assert!(sessions
+            .iter()
+            .any(|s| s.id == keep_uuid && s.revoked.is_none()));
+        assert!(sessions
+            .iter()
+            .any(|s| s.id == revoke_uuid && s.revoked.is_some()));
+
+        // Removing the attribute revokes everything rather than purging it.
+        let put_event =
+            ScimEntryPutEvent::try_from(person_ident.clone(), put_sessions(None), &mut server_txn)
+                .expect("Failed to resolve sessions");
+
+        let updated_entry = server_txn.scim_put(put_event).expect("Failed to put");
+        let Some(ScimValueKanidm::AuthSession(sessions)) =
+            updated_entry.attrs.get(&Attribute::UserAuthTokenSession)
+        else {
+            unreachable!("Expected sessions");
+        };
+
+        assert_eq!(sessions.len(), 2);
+        assert!(sessions.iter().all(|s| s.revoked.is_some()));
+    }
+
+    #[qs_test]
+    async fn scim_restore_sessions(server: &QueryServer) {
+        let ct = duration_from_epoch_now();
+        let now = OffsetDateTime::UNIX_EPOCH + ct;
+
+        let source_uuid = Uuid::new_v4();
+        let target_uuid = Uuid::new_v4();
+        let (live_uuid, expiring_uuid, revoked_uuid) =
+            (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let (token_uuid, revoked_token_uuid) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let p = CryptoPolicy::minimum();
+        let cred =
+            Credential::new_password_only(&p, "test_password", OffsetDateTime::UNIX_EPOCH).unwrap();
+        let cred_id = cred.uuid;
+
+        let account = |name: &str, uuid: Uuid| {
+            entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (Attribute::Class, EntryClass::Account.to_value()),
+                (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+                (Attribute::Name, Value::new_iname(name)),
+                (Attribute::DisplayName, Value::new_utf8s(name)),
+                (Attribute::Uuid, Value::Uuid(uuid)),
+                (
+                    Attribute::PrimaryCredential,
+                    Value::Cred("primary".to_string(), cred.clone())
+                )
+            )
+        };
+
+        let session = |label: &str| {
+            Session::builder(now, AuthType::Passkey)
+                .label(label)
+                .cred_id(cred_id)
+                .issued_by(IdentityId::User(source_uuid))
+        };
+
+        let mut server_txn = server.write(ct).await.unwrap();
+        server_txn
+            .internal_create(vec![
+                account("restore_source", source_uuid),
+                account("restore_target", target_uuid),
+            ])
+            .unwrap();
+
+        server_txn
+            .internal_modify_uuid(
+                source_uuid,
+                &ModifyList::new_list(vec![
+                    Modify::Present(
+                        Attribute::UserAuthTokenSession,
+                        session("live")
+                            .bound_to(Some("device".to_string()))
+                            .last_active(Some(now))
+                            .pinned(true)
+                            .build_value(live_uuid)
+                            .unwrap(),
+                    ),
+                    Modify::Present(
+                        Attribute::UserAuthTokenSession,
+                        session("expiring")
+                            .expiry(Some(now + Duration::from_secs(3600)))
+                            .build_value(expiring_uuid)
+                            .unwrap(),
+                    ),
+                    Modify::Present(
+                        Attribute::UserAuthTokenSession,
+                        session("revoked").build_value(revoked_uuid).unwrap(),
+                    ),
+                    Modify::Present(
+                        Attribute::ApiTokenSession,
+                        ApiToken::builder(now)
+                            .label("token")
+                            .expiry(Some(now + Duration::from_secs(86400)))
+                            .generation(3)
+                            .constraints(Some(
+                                [ApiTokenConstraint::GroupMembership].into_iter().collect(),
+                            ))
+                            .build_value(token_uuid)
+                            .unwrap(),
+                    ),
+                    Modify::Present(
+                        Attribute::ApiTokenSession,
+                        ApiToken::builder(now)
+                            .label("revoked token")
+                            .build_value(revoked_token_uuid)
+                            .unwrap(),
+                    ),
+                ]),
+            )
+            .unwrap();
+
+        server_txn
+            .internal_modify_uuid(
+                source_uuid,
+                &ModifyList::new_list(vec![
+                    Modify::Removed(
+                        Attribute::UserAuthTokenSession,
+                        PartialValue::Refer(revoked_uuid),
+                    ),
+                    Modify::Removed(
+                        Attribute::ApiTokenSession,
+                        PartialValue::Refer(revoked_token_uuid),
+                    ),
+                ]),
+            )
+            .unwrap();
+        server_txn.commit().unwrap();
+
+        let mut server_txn = server.write(ct + Duration::from_secs(1)).await.unwrap();
+
+        // Export the sessions as a client would read them.
+        let source = server_txn.internal_search_uuid(source_uuid).unwrap();
+        let scim_source = source
+            .as_ref()
+            .clone()
+            .into_reduced()
+            .to_scim_kanidm(&mut server_txn)
+            .unwrap();
+
+        let Some(ScimValueKanidm::AuthSession(sessions)) =
+            scim_source.attrs.get(&Attribute::UserAuthTokenSession)
+        else {
+            unreachable!("Expected sessions");
+        };
+        let Some(ScimValueKanidm::ApiToken(api_tokens)) =
+            scim_source.attrs.get(&Attribute::ApiTokenSession)
+        else {
+            unreachable!("Expected api tokens");
+        };
+
+        let sessions = serde_json::from_value(serde_json::to_value(sessions).unwrap()).unwrap();
+        let api_tokens = serde_json::from_value(serde_json::to_value(api_tokens).unwrap()).unwrap();
+
+        // Only the internal and admin identities may restore sessions.
+        let idm_admin_entry = server_txn.internal_search_uuid(UUID_IDM_ADMIN).unwrap();
+        let idm_admin_ident = Identity::from_impersonate_entry_readwrite(idm_admin_entry);
+
+        assert_eq!(
+            server_txn.scim_restore_sessions(ScimSessionRestoreEvent::new(
+                idm_admin_ident,
+                target_uuid,
+                Vec::clone(&sessions),
+                Vec::clone(&api_tokens),
+            )),
+            Err(OperationError::AccessDenied)
+        );
+
+        // Session ids are unique across entries, so the source must be removed before
+        // its sessions can be restored elsewhere.
+        server_txn.internal_delete_uuid(source_uuid).unwrap();
+
+        let admin_entry = server_txn.internal_search_uuid(UUID_ADMIN).unwrap();
+        let admin_ident = Identity::from_impersonate_entry_readwrite(admin_entry);
+
+        server_txn
+            .scim_restore_sessions(ScimSessionRestoreEvent::new(
+                admin_ident,
+                target_uuid,
+                sessions,
+                api_tokens,
+            ))
+            .expect("Failed to restore sessions");
+
+        let target = server_txn.internal_search_uuid(target_uuid).unwrap();
+
+        let source_sessions = source
+            .get_ava_as_session_map(Attribute::UserAuthTokenSession)
+            .unwrap();
+        assert_eq!(source_sessions.len(), 3);
+        assert!(matches!(
+            source_sessions.get(&revoked_uuid).map(|s| &s.state),
+            Some(SessionState::RevokedAt(_))
+        ));
+        assert_eq!(
+            target.get_ava_as_session_map(Attribute::UserAuthTokenSession),
+            Some(source_sessions)
+        );
+
+        let source_tokens = source
+            .get_ava_as_apitoken_map(Attribute::ApiTokenSession)
+            .unwrap();
+        assert_eq!(source_tokens.len(), 2);
+        assert_eq!(
+            target.get_ava_as_apitoken_map(Attribute::ApiTokenSession),
+            Some(source_tokens)
+        );
+
+        // Sessions can't be restored with events before their issue.
+        let mut scim_session = scim_source
+            .attrs
+            .get(&Attribute::UserAuthTokenSession)
+            .and_then(|value| match value {
+                ScimValueKanidm::AuthSession(sessions) => sessions.first().cloned(),
+                _ => None,
+            })
+            .unwrap();
+        scim_session.id = Uuid::new_v4();
+        scim_session.revoked = Some(now - Duration::from_secs(60));
+
+        assert!(matches!(
+            server_txn.scim_restore_sessions(ScimSessionRestoreEvent::new(
+                Identity::from_internal(),
+                target_uuid,
+                vec![scim_session.clone()],
+                Vec::new(),
+            )),
+            Err(OperationError::InvalidAttribute(_))
+        ));
+
+        // Nor issued in the future.
+        scim_session.revoked = None;
+        scim_session.issued_at = now + Duration::from_secs(3600);
+
+        assert!(matches!(
+            server_txn.scim_restore_sessions(ScimSessionRestoreEvent::new(
+                Identity::from_internal(),
+                target_uuid,
+                vec![scim_session],
+                Vec::new(),
+            )),
+            Err(OperationError::InvalidAttribute(_))
+        ));
+
+        server_txn.commit().unwrap();
+    }
 }
@@ -77,6 +77,10 @@ pub static HEXSTR_RE: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new("^[a-f0-9]+$").expect("Invalid hexstring regex found")
 });
 
+/// Api tokens given as a string are referenced by their id, unless the string has this
+/// prefix in which case the remainder is the label of the token.
+pub const API_TOKEN_LABEL_PREFIX: &str = "label:";
+
 pub static EXTRACT_VAL_DN: LazyLock<Regex> = LazyLock::new(|| {
     #[allow(clippy::expect_used)]
     Regex::new("^(([^=,]+)=)?(?P<val>[^=,]+)").expect("extract val from dn regex")
@@ -444,9 +448,12 @@ impl SyntaxType {
             SyntaxType::IntentToken => &[IndexType::Equality],
             SyntaxType::Passkey => &[IndexType::Equality],
             SyntaxType::AttestedPasskey => &[IndexType::Equality],
-            SyntaxType::Session => &[IndexType::Equality],
-            SyntaxType::Oauth2Session => &[IndexType::Equality],
-            SyntaxType::ApiToken => &[IndexType::Equality],
+            // Ordering allows sessions and tokens to be found by their expiry.
+            SyntaxType::Session | SyntaxType::Oauth2Session | SyntaxType::ApiToken => &[
+                IndexType::Equality,
+                IndexType::Presence,
+                IndexType::Ordering,
+            ],
             SyntaxType::OauthClaimMap => &[IndexType::Equality],
             SyntaxType::ApplicationPassword => &[IndexType::Equality],
             SyntaxType::SecretUtf8String => &[],
@@ -546,6 +553,12 @@ impl From<CredentialType> for PartialValue {
     }
 }
 
+impl From<ApiTokenScope> for PartialValue {
+    fn from(scope: ApiTokenScope) -> PartialValue {
+        PartialValue::ApiTokenScope(scope)
+    }
+}
+
 impl From<Attribute> for Value {
     fn from(attr: Attribute) -> Value {
         let s: &str = attr.as_str();
@@ -607,6 +620,9 @@ pub enum PartialValue {
     /// We compare on the value hash
     Image(String),
     CredentialType(CredentialType),
+    /// Matches api tokens by their scope. Scopes are not indexed, so this can not be
+    /// used in a filter and is only able to be tested on a loaded entry.
+    ApiTokenScope(ApiTokenScope),
 
     OauthClaim(String, Uuid),
     OauthClaimValue(String, Uuid, String),
@@ -1011,6 +1027,7 @@ impl PartialValue {
             PartialValue::UiHint(u) => (*u as u16).to_string(),
             PartialValue::Image(imagehash) => imagehash.to_owned(),
             PartialValue::CredentialType(ct) => ct.to_string(),
+            PartialValue::ApiTokenScope(scope) => scope.to_string(),
             // This will never work, we don't allow equality searching on Cid's
             PartialValue::Cid(_) => "_".to_string(),
             // We don't allow searching on claim/uuid pairs.
@@ -1041,7 +1058,7 @@ impl PartialValue {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum ApiTokenScope {
     ReadOnly,
     ReadWrite,
@@ -1058,6 +1075,19 @@ impl fmt::Display for ApiTokenScope {
     }
 }
 
+impl TryFrom<&str> for ApiTokenScope {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<ApiTokenScope, Self::Error> {
+        match value {
+            "read_only" => Ok(ApiTokenScope::ReadOnly),
+            "read_write" => Ok(ApiTokenScope::ReadWrite),
+            "synchronise" => Ok(ApiTokenScope::Synchronise),
+            _ => Err(()),
+        }
+    }
+}
+
 impl TryInto<ApiTokenPurpose> for ApiTokenScope {
     type Error = OperationError;
 
@@ -1070,22 +1100,218 @@ impl TryInto<ApiTokenPurpose> for ApiTokenScope {
     }
 }
 
+/// A category of changes that an api token may be constrained to. A token with
+/// constraints may only modify the attributes of its categories, and may never create
+/// or delete entries.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ApiTokenConstraint {
+    GroupMembership,
+    Credentials,
+    SshPublicKeys,
+    Profile,
+}
+
+impl ApiTokenConstraint {
+    /// The attributes that a token with this constraint may modify.
+    pub fn attributes(&self) -> &'static [Attribute] {
+        match self {
+            ApiTokenConstraint::GroupMembership => &[Attribute::Member],
+            ApiTokenConstraint::Credentials => &[
+                Attribute::PrimaryCredential,
+                Attribute::PassKeys,
+                Attribute::AttestedPasskeys,
+                Attribute::UnixPassword,
+            ],
+            ApiTokenConstraint::SshPublicKeys => &[Attribute::SshPublicKey],
+            ApiTokenConstraint::Profile => &[
+                Attribute::DisplayName,
+                Attribute::LegalName,
+                Attribute::Mail,
+            ],
+        }
+    }
+}
+
+impl fmt::Display for ApiTokenConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiTokenConstraint::GroupMembership => write!(f, "group_membership"),
+            ApiTokenConstraint::Credentials => write!(f, "credentials"),
+            ApiTokenConstraint::SshPublicKeys => write!(f, "ssh_public_keys"),
+            ApiTokenConstraint::Profile => write!(f, "profile"),
+        }
+    }
+}
+
+impl TryFrom<&str> for ApiTokenConstraint {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<ApiTokenConstraint, Self::Error> {
+        match value {
+            "group_membership" => Ok(ApiTokenConstraint::GroupMembership),
+            "credentials" => Ok(ApiTokenConstraint::Credentials),
+            "ssh_public_keys" => Ok(ApiTokenConstraint::SshPublicKeys),
+            "profile" => Ok(ApiTokenConstraint::Profile),
+            _ => Err(()),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ApiToken {
     pub label: String,
-    pub expiry: Option<OffsetDateTime>,
+    pub state: SessionState,
     pub issued_at: OffsetDateTime,
     pub issued_by: IdentityId,
     pub scope: ApiTokenScope,
+    /// The session that created this token. Tokens created internally or by
+    /// synchronisation have no parent.
+    pub parent_session: Option<Uuid>,
+    /// Incremented each time the token is rotated. Tokens issued for an earlier
+    /// generation are no longer valid.
+    pub generation: u32,
+    /// The categories of changes this token is limited to. Tokens without
+    /// constraints are limited only by their scope.
+    pub constraints: Option<BTreeSet<ApiTokenConstraint>>,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+impl ApiToken {
+    pub fn builder(issued_at: OffsetDateTime) -> ApiTokenBuilder {
+        ApiTokenBuilder::new(issued_at)
+    }
+
+    /// Convert the timestamps of this token to UTC, preserving the instants they refer to.
+    pub(crate) fn into_utc(mut self) -> Self {
+        self.state = session_state_to_utc(self.state);
+        self.issued_at = self.issued_at.to_offset(time::UtcOffset::UTC);
+        self
+    }
+
+    /// The time this token expires at. Revoked tokens and tokens that never
+    /// expire have no expiry.
+    pub fn expiry(&self) -> Option<OffsetDateTime> {
+        match self.state {
+            SessionState::ExpiresAt(odt) => Some(odt),
+            SessionState::NeverExpires | SessionState::RevokedAt(_) => None,
+        }
+    }
+
+    pub fn is_revoked(&self) -> bool {
+        matches!(self.state, SessionState::RevokedAt(_))
+    }
+
+    /// If this token is neither revoked nor expired at `ct`.
+    pub fn is_valid_at(&self, ct: OffsetDateTime) -> bool {
+        match self.state {
+            SessionState::ExpiresAt(exp) => ct < exp,
+            SessionState::NeverExpires => true,
+            SessionState::RevokedAt(_) => false,
+        }
+    }
+
+    /// If this token is already expired at the time it was issued. A token that expires
+    /// at the same instant it was issued is expired.
+    pub fn is_expired_at_issue(&self) -> bool {
+        matches!(self.state, SessionState::ExpiresAt(exp) if exp <= self.issued_at)
+    }
+}
+
+/// Constructs an [`ApiToken`]. The time of issue must always be given. Unless otherwise
+/// set the token never expires, is issued by the system, is read only, and has no parent
+/// session. Timestamps are normalised to UTC.
+#[derive(Debug, Clone)]
+pub struct ApiTokenBuilder {
+    token: ApiToken,
+}
+
+impl ApiTokenBuilder {
+    pub fn new(issued_at: OffsetDateTime) -> Self {
+        ApiTokenBuilder {
+            token: ApiToken {
+                label: String::new(),
+                state: SessionState::NeverExpires,
+                issued_at: issued_at.to_offset(time::UtcOffset::UTC),
+                issued_by: IdentityId::Internal(UUID_SYSTEM),
+                scope: ApiTokenScope::ReadOnly,
+                parent_session: None,
+                generation: 0,
+                constraints: None,
+            },
+        }
+    }
+
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.token.label = label.into();
+        self
+    }
+
+    pub fn state(mut self, state: SessionState) -> Self {
+        self.token.state = session_state_to_utc(state);
+        self
+    }
+
+    /// Set the token to expire at `expiry`, or to never expire if it is `None`.
+    pub fn expiry(self, expiry: Option<OffsetDateTime>) -> Self {
+        self.state(
+            expiry
+                .map(SessionState::ExpiresAt)
+                .unwrap_or(SessionState::NeverExpires),
+        )
+    }
+
+    pub fn issued_by(mut self, issued_by: IdentityId) -> Self {
+        self.token.issued_by = issued_by;
+        self
+    }
+
+    pub fn scope(mut self, scope: ApiTokenScope) -> Self {
+        self.token.scope = scope;
+        self
+    }
+
+    pub fn parent_session(mut self, parent_session: Option<Uuid>) -> Self {
+        self.token.parent_session = parent_session;
+        self
+    }
+
+    pub fn generation(mut self, generation: u32) -> Self {
+        self.token.generation = generation;
+        self
+    }
+
+    pub fn constraints(mut self, constraints: Option<BTreeSet<ApiTokenConstraint>>) -> Self {
+        self.token.constraints = constraints;
+        self
+    }
+
+    pub fn build(self) -> ApiToken {
+        self.token
+    }
+
+    /// Build the token, rejecting labels that are not a single line or contain
+    /// control characters.
+    pub fn try_build(self) -> Result<ApiToken, OperationError> {
+        validate_session_label(&self.token.label)?;
+        Ok(self.token)
+    }
+
+    /// Build the token as a [`Value::ApiToken`] with the id `token_id`.
+    pub fn build_value(self, token_id: Uuid) -> Result<Value, OperationError> {
+        self.try_build()
+            .map(|token| Value::ApiToken(token_id, token))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum SessionScope {
     ReadOnly,
     ReadWrite,
     PrivilegeCapable,
     // For migration! To be removed in future!
     Synchronise,
+    /// A read write session for automation, which may never be re-authenticated to
+    /// become privilege capable.
+    ApiReadWrite,
 }
 
 impl fmt::Display for SessionScope {
@@ -1095,6 +1321,22 @@ impl fmt::Display for SessionScope {
             SessionScope::ReadWrite => write!(f, "read_write"),
             SessionScope::PrivilegeCapable => write!(f, "privilege_capable"),
             SessionScope::Synchronise => write!(f, "synchronise"),
+            SessionScope::ApiReadWrite => write!(f, "api_read_write"),
+        }
+    }
+}
+
+impl TryFrom<&str> for SessionScope {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<SessionScope, Self::Error> {
+        match value {
+            "read_only" => Ok(SessionScope::ReadOnly),
+            "read_write" => Ok(SessionScope::ReadWrite),
+            "privilege_capable" => Ok(SessionScope::PrivilegeCapable),
+            "synchronise" => Ok(SessionScope::Synchronise),
+            "api_read_write" => Ok(SessionScope::ApiReadWrite),
+            _ => Err(()),
         }
     }
 }
@@ -1105,7 +1347,7 @@ impl TryInto<UatPurposeStatus> for SessionScope {
     fn try_into(self: SessionScope) -> Result<UatPurposeStatus, OperationError> {
         match self {
             SessionScope::ReadOnly => Ok(UatPurposeStatus::ReadOnly),
-            SessionScope::ReadWrite => Ok(UatPurposeStatus::ReadWrite),
+            SessionScope::ReadWrite | SessionScope::ApiReadWrite => Ok(UatPurposeStatus::ReadWrite),
             SessionScope::PrivilegeCapable => Ok(UatPurposeStatus::PrivilegeCapable),
             SessionScope::Synchronise => Err(OperationError::InvalidEntryState),
         }
@@ -1155,6 +1397,47 @@ impl Ord for SessionState {
     }
 }
 
+/// A change to whether a session is disabled, such as while its account is locked.
+/// Unlike revocation this can be reversed, so enabling a session is also recorded with
+/// the cid it was made at. This is independent of the session state, so a revoked
+/// session is never usable, and otherwise a disabled session beats a live one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SessionDisabled {
+    DisabledAt(Cid),
+    EnabledAt(Cid),
+}
+
+impl SessionDisabled {
+    pub fn cid(&self) -> &Cid {
+        match self {
+            SessionDisabled::DisabledAt(cid) | SessionDisabled::EnabledAt(cid) => cid,
+        }
+    }
+}
+
+impl PartialOrd for SessionDisabled {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SessionDisabled {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // The latest change takes priority, so that replicas converge on it. Cids are
+        // unique so the two kinds of change should never share one, but if they do the
+        // session remains disabled.
+        self.cid()
+            .cmp(other.cid())
+            .then_with(|| match (self, other) {
+                (SessionDisabled::DisabledAt(_), SessionDisabled::EnabledAt(_)) => {
+                    Ordering::Greater
+                }
+                (SessionDisabled::EnabledAt(_), SessionDisabled::DisabledAt(_)) => Ordering::Less,
+                _ => Ordering::Equal,
+            })
+    }
+}
+
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub enum AuthType {
     Anonymous,
@@ -1166,6 +1449,11 @@ pub enum AuthType {
     Passkey,
     AttestedPasskey,
     OAuth2Trust,
+    ServiceAccount,
+    /// A bind with the account's unix password through the LDAP interface.
+    LdapPassword,
+    /// A session derived from another credential through an OAuth2 token exchange.
+    TokenExchange,
 }
 
 impl fmt::Display for AuthType {
@@ -1180,6 +1468,31 @@ impl fmt::Display for AuthType {
             AuthType::Passkey => write!(f, "passkey"),
             AuthType::AttestedPasskey => write!(f, "attested_passkey"),
             AuthType::OAuth2Trust => write!(f, "oauth2_trust"),
+            AuthType::ServiceAccount => write!(f, "service_account"),
+            AuthType::LdapPassword => write!(f, "ldap_password"),
+            AuthType::TokenExchange => write!(f, "token_exchange"),
+        }
+    }
+}
+
+impl TryFrom<&str> for AuthType {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<AuthType, Self::Error> {
+        match value {
+            "anonymous" => Ok(AuthType::Anonymous),
+            "password" => Ok(AuthType::Password),
+            "generatedpassword" => Ok(AuthType::GeneratedPassword),
+            "passwordtotp" => Ok(AuthType::PasswordTotp),
+            "passwordbackupcode" => Ok(AuthType::PasswordBackupCode),
+            "passwordsecuritykey" => Ok(AuthType::PasswordSecurityKey),
+            "passkey" => Ok(AuthType::Passkey),
+            "attested_passkey" => Ok(AuthType::AttestedPasskey),
+            "oauth2_trust" => Ok(AuthType::OAuth2Trust),
+            "service_account" => Ok(AuthType::ServiceAccount),
+            "ldap_password" => Ok(AuthType::LdapPassword),
+            "token_exchange" => Ok(AuthType::TokenExchange),
+            _ => Err(()),
         }
     }
 }
@@ -1220,6 +1533,224 @@ pub struct Session {
     pub scope: SessionScope,
     pub type_: AuthType,
     pub ext_metadata: SessionExtMetadata,
+    /// A coarse client identifier (such as a hashed device fingerprint) that this
+    /// session is bound to.
+    pub bound_to: Option<String>,
+    /// The last time this session was recorded as being used. If none, the session
+    /// has not been used since it was issued.
+    pub last_active: Option<OffsetDateTime>,
+    /// A pinned session is only force trimmed once every unpinned session has been, when
+    /// the session maximum is exceeded. It can still expire or be revoked.
+    pub pinned: bool,
+    /// If this session was issued to a service account. Service accounts are machines,
+    /// whichever credential they authenticated with.
+    pub service_account: bool,
+    /// The latest change to whether this session is disabled, if it has ever been
+    /// disabled. A disabled session can not be used until it is enabled again.
+    pub disabled: Option<SessionDisabled>,
+}
+
+impl Session {
+    pub fn builder(issued_at: OffsetDateTime, type_: AuthType) -> SessionBuilder {
+        SessionBuilder::new(issued_at, type_)
+    }
+
+    /// Convert the timestamps of this session to UTC, preserving the instants they refer to.
+    pub(crate) fn into_utc(mut self) -> Self {
+        self.state = session_state_to_utc(self.state);
+        self.issued_at = self.issued_at.to_offset(time::UtcOffset::UTC);
+        self.last_active = self
+            .last_active
+            .map(|odt| odt.to_offset(time::UtcOffset::UTC));
+        self
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        matches!(self.disabled, Some(SessionDisabled::DisabledAt(_)))
+    }
+
+    /// Order sessions from the most to the least recently issued. Sessions issued at
+    /// the same time are ordered by their credential id.
+    pub fn cmp_by_recency(a: &Session, b: &Session) -> Ordering {
+        b.issued_at
+            .cmp(&a.issued_at)
+            .then_with(|| a.cred_id.cmp(&b.cred_id))
+    }
+
+    /// Determine if this session has been idle for `idle_timeout` or longer at `now`. A
+    /// session that has not been used is idle from the time it was issued.
+    pub fn is_idle_at(&self, now: OffsetDateTime, idle_timeout: Duration) -> bool {
+        self.last_active.unwrap_or(self.issued_at) + idle_timeout <= now
+    }
+
+    /// The latest time this session may expire at, if its auth type limits how long
+    /// the session may exist for.
+    pub fn lifetime_ceiling(&self) -> Option<OffsetDateTime> {
+        let maximum = match self.type_ {
+            AuthType::Password | AuthType::GeneratedPassword | AuthType::LdapPassword => {
+                MAXIMUM_AUTH_SESSION_EXPIRY_SINGLE_FACTOR
+            }
+            AuthType::Anonymous
+            | AuthType::PasswordTotp
+            | AuthType::PasswordBackupCode
+            | AuthType::PasswordSecurityKey
+            | AuthType::Passkey
+            | AuthType::AttestedPasskey
+            | AuthType::OAuth2Trust
+            | AuthType::ServiceAccount
+            | AuthType::TokenExchange => return None,
+        };
+
+        Some(self.issued_at + Duration::from_secs(u64::from(maximum)))
+    }
+
+    /// The state of this session once the lifetime ceiling of its auth type is applied.
+    /// This may be shorter than the stored state for sessions created before the ceiling
+    /// applied to them.
+    pub fn effective_state(&self) -> SessionState {
+        match (&self.state, self.lifetime_ceiling()) {
+            (SessionState::NeverExpires, Some(ceiling)) => SessionState::ExpiresAt(ceiling),
+            (SessionState::ExpiresAt(odt), Some(ceiling)) if ceiling < *odt => {
+                SessionState::ExpiresAt(ceiling)
+            }
+            (state, _) => state.clone(),
+        }
+    }
+}
+
+/// Normalise the timestamp within a session state to UTC. Session and token
+/// timestamps are always stored in UTC.
+fn session_state_to_utc(state: SessionState) -> SessionState {
+    match state {
+        SessionState::ExpiresAt(odt) => {
+            SessionState::ExpiresAt(odt.to_offset(time::UtcOffset::UTC))
+        }
+        state => state,
+    }
+}
+
+/// Session and token labels are displayed to users, so they must be a single line
+/// without control characters.
+fn validate_session_label(label: &str) -> Result<(), OperationError> {
+    if Value::validate_singleline(label) && Value::validate_str_escapes(label) {
+        Ok(())
+    } else {
+        Err(OperationError::InvalidAttribute(
+            "session label must be a single line without control characters".to_string(),
+        ))
+    }
+}
+
+/// Constructs a [`Session`], insulating callers from the addition of new fields. The
+/// time of issue and the kind of authentication must always be given. Unless otherwise
+/// set the session never expires, is issued by the system, and is read only. Timestamps
+/// are normalised to UTC.
+#[derive(Debug, Clone)]
+pub struct SessionBuilder {
+    session: Session,
+}
+
+impl SessionBuilder {
+    pub fn new(issued_at: OffsetDateTime, type_: AuthType) -> Self {
+        SessionBuilder {
+            session: Session {
+                label: String::new(),
+                state: SessionState::NeverExpires,
+                issued_at: issued_at.to_offset(time::UtcOffset::UTC),
+                issued_by: IdentityId::Internal(UUID_SYSTEM),
+                cred_id: Uuid::nil(),
+                scope: SessionScope::ReadOnly,
+                type_,
+                ext_metadata: SessionExtMetadata::None,
+                bound_to: None,
+                last_active: None,
+                pinned: false,
+                service_account: false,
+                disabled: None,
+            },
+        }
+    }
+
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.session.label = label.into();
+        self
+    }
+
+    pub fn state(mut self, state: SessionState) -> Self {
+        self.session.state = session_state_to_utc(state);
+        self
+    }
+
+    /// Set the session to expire at `expiry`, or to never expire if it is `None`.
+    pub fn expiry(self, expiry: Option<OffsetDateTime>) -> Self {
+        self.state(
+            expiry
+                .map(SessionState::ExpiresAt)
+                .unwrap_or(SessionState::NeverExpires),
+        )
+    }
+
+    pub fn issued_by(mut self, issued_by: IdentityId) -> Self {
+        self.session.issued_by = issued_by;
+        self
+    }
+
+    pub fn cred_id(mut self, cred_id: Uuid) -> Self {
+        self.session.cred_id = cred_id;
+        self
+    }
+
+    pub fn scope(mut self, scope: SessionScope) -> Self {
+        self.session.scope = scope;
+        self
+    }
+
+    pub fn ext_metadata(mut self, ext_metadata: SessionExtMetadata) -> Self {
+        self.session.ext_metadata = ext_metadata;
+        self
+    }
+
+    pub fn bound_to(mut self, bound_to: Option<String>) -> Self {
+        self.session.bound_to = bound_to;
+        self
+    }
+
+    pub fn last_active(mut self, last_active: Option<OffsetDateTime>) -> Self {
+        self.session.last_active = last_active.map(|odt| odt.to_offset(time::UtcOffset::UTC));
+        self
+    }
+
+    pub fn pinned(mut self, pinned: bool) -> Self {
+        self.session.pinned = pinned;
+        self
+    }
+
+    pub fn service_account(mut self, service_account: bool) -> Self {
+        self.session.service_account = service_account;
+        self
+    }
+
+    pub fn disabled(mut self, disabled: Option<SessionDisabled>) -> Self {
+        self.session.disabled = disabled;
+        self
+    }
+
+    pub fn build(self) -> Session {
+        self.session
+    }
+
+    /// Build the session, rejecting labels that are not a single line or contain
+    /// control characters.
+    pub fn try_build(self) -> Result<Session, OperationError> {
+        validate_session_label(&self.session.label)?;
+        Ok(self.session)
+    }
+
+    /// Build the session as a [`Value::Session`] with the id `session_id`.
+    pub fn build_value(self, session_id: Uuid) -> Result<Value, OperationError> {
+        self.try_build()
+            .map(|session| Value::Session(session_id, session))
+    }
 }
 
 impl fmt::Debug for Session {
@@ -1323,6 +1854,23 @@ pub struct Oauth2Session {
     pub state: SessionState,
     pub issued_at: OffsetDateTime,
     pub rs_uuid: Uuid,
+    pub granted_scopes: BTreeSet<String>,
+    pub has_refresh: bool,
+    pub last_refreshed: Option<OffsetDateTime>,
+    // The redirect uri this session was authorised against, if any.
+    pub origin: Option<String>,
+}
+
+impl Oauth2Session {
+    /// Convert the timestamps of this session to UTC, preserving the instants they refer to.
+    pub(crate) fn into_utc(mut self) -> Self {
+        self.state = session_state_to_utc(self.state);
+        self.issued_at = self.issued_at.to_offset(time::UtcOffset::UTC);
+        self.last_refreshed = self
+            .last_refreshed
+            .map(|odt| odt.to_offset(time::UtcOffset::UTC));
+        self
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -2265,6 +2813,9 @@ impl Value {
             Value::ApiToken(_, at) => {
                 Value::validate_str_escapes(&at.label) && Value::validate_singleline(&at.label)
             }
+            Value::Oauth2Session(session_id, session) => {
+                !session_id.is_nil() && !session.rs_uuid.is_nil()
+            }
             Value::AuditLogString(_, s) => {
                 Value::validate_str_escapes(s) && Value::validate_singleline(s)
             }
@@ -2317,7 +2868,6 @@ impl Value {
             | Value::RestrictedString(_)
             | Value::JwsKeyEs256(_)
             | Value::Session(_, _)
-            | Value::Oauth2Session(_, _)
             | Value::JwsKeyRs256(_)
             | Value::UiHint(_)
             | Value::CredentialType(_)
@@ -2678,4 +3228,93 @@ mod tests {
         assert_eq!(do_extract("cn=william,o=blackhats"), "william");
         assert_eq!(do_extract("cn=william@example.com"), "william@example.com");
     }
+
+    #[test]
+    fn test_session_builder() {
+        let session = Session::builder(OffsetDateTime::UNIX_EPOCH, AuthType::Password).build();
+
+        assert_eq!(session.label, "");
+        assert_eq!(session.state, SessionState::NeverExpires);
+        assert_eq!(session.issued_at, OffsetDateTime::UNIX_EPOCH);
+        assert_eq!(session.issued_by, IdentityId::Internal(UUID_SYSTEM));
+        assert_eq!(session.cred_id, Uuid::nil());
+        assert_eq!(session.scope, SessionScope::ReadOnly);
+        assert_eq!(session.type_, AuthType::Password);
+        assert_eq!(session.ext_metadata, SessionExtMetadata::None);
+        assert_eq!(session.bound_to, None);
+
+        let cred_id = Uuid::new_v4();
+        let session = SessionBuilder::new(OffsetDateTime::UNIX_EPOCH, AuthType::Passkey)
+            .label("hacks")
+            .state(SessionState::ExpiresAt(OffsetDateTime::UNIX_EPOCH))
+            .cred_id(cred_id)
+            .scope(SessionScope::ReadWrite)
+            .build();
+
+        assert_eq!(session.label, "hacks");
+        assert_eq!(
+            session.state,
+            SessionState::ExpiresAt(OffsetDateTime::UNIX_EPOCH)
+        );
+        assert_eq!(session.issued_at, OffsetDateTime::UNIX_EPOCH);
+        assert_eq!(session.cred_id, cred_id);
+        assert_eq!(session.scope, SessionScope::ReadWrite);
+        assert_eq!(session.type_, AuthType::Passkey);
+    }
+
+    #[test]
+    fn test_session_builder_normalises_utc() {
+        let offset = time::UtcOffset::from_hms(10, 0, 0).unwrap();
+        let issued_at = OffsetDateTime::UNIX_EPOCH.to_offset(offset);
+        let expiry = (OffsetDateTime::UNIX_EPOCH + Duration::from_secs(60)).to_offset(offset);
+
+        let session = Session::builder(issued_at, AuthType::Password)
+            .expiry(Some(expiry))
+            .build();
+
+        assert_eq!(session.issued_at.offset(), time::UtcOffset::UTC);
+        assert_eq!(session.issued_at, OffsetDateTime::UNIX_EPOCH);
+        assert!(matches!(
+            session.state,
+            SessionState::ExpiresAt(odt) if odt.offset() == time::UtcOffset::UTC && odt == expiry
+        ));
+
+        let token = ApiToken::builder(issued_at)
+            .state(SessionState::ExpiresAt(expiry))
+            .build();
+
+        assert_eq!(token.issued_at.offset(), time::UtcOffset::UTC);
+        assert!(matches!(
+            token.state,
+            SessionState::ExpiresAt(odt) if odt.offset() == time::UtcOffset::UTC
+        ));
+        assert!(!token.is_expired_at_issue());
+    }
+
+    #[test]
+    fn test_session_builder_label_validation() {
+        let session_id = Uuid::new_v4();
+        assert!(matches!(
+            Session::builder(OffsetDateTime::UNIX_EPOCH, AuthType::Password).label("laptop").build_value(session_id),
+            Ok(Value::Session(u, s)) if u == session_id && s.label == "laptop"
+        ));
+        assert!(
+            Session::builder(OffsetDateTime::UNIX_EPOCH, AuthType::Password)
+                .label("two\nlines")
+                .build_value(session_id)
+                .is_err()
+        );
+
+        let token = ApiToken::builder(OffsetDateTime::UNIX_EPOCH)
+            .label("ci token")
+            .try_build()
+            .unwrap();
+        assert_eq!(token.state, SessionState::NeverExpires);
+        assert_eq!(token.scope, ApiTokenScope::ReadOnly);
+        assert_eq!(token.parent_session, None);
+        assert!(ApiToken::builder(OffsetDateTime::UNIX_EPOCH)
+            .label("bad\u{7}label")
+            .build_value(session_id)
+            .is_err());
+    }
 }
@@ -13,6 +13,7 @@ use dyn_clone::DynClone;
 use hashbrown::HashSet;
 use kanidm_proto::internal::ImageValue;
 use kanidm_proto::internal::{Filter as ProtoFilter, UiHint};
+use kanidm_proto::scim_v1::server::{ScimApiToken, ScimOAuth2Session};
 use kanidm_proto::scim_v1::JsonValue;
 use kanidm_proto::scim_v1::ScimOauth2ClaimMapJoinChar;
 use kanidm_proto::v1::OutboundMessage;
@@ -54,7 +55,11 @@ pub use self::oauth::{
 pub use self::restricted::ValueSetRestricted;
 pub use self::s256::ValueSetSha256;
 pub use self::secret::ValueSetSecret;
-pub use self::session::{ValueSetApiToken, ValueSetOauth2Session, ValueSetSession};
+pub(crate) use self::session::revoked_at_timestamp;
+pub use self::session::{
+    cascade_revoke, SessionDiff, TrimPolicy, ValueSetApiToken, ValueSetOauth2Session,
+    ValueSetSession,
+};
 pub use self::spn::ValueSetSpn;
 pub use self::ssh::ValueSetSshKey;
 pub use self::syntax::ValueSetSyntax;
@@ -113,6 +118,16 @@ pub trait ValueSetT: std::fmt::Debug + DynClone {
     ///
     fn insert_checked(&mut self, value: Value) -> Result<bool, OperationError>;
 
+    /// As per `insert_checked`, for a value written by a transaction at `curtime`. Values
+    /// that are only invalid relative to the time of the write are refused here.
+    fn insert_checked_at(
+        &mut self,
+        value: Value,
+        _curtime: OffsetDateTime,
+    ) -> Result<bool, OperationError> {
+        self.insert_checked(value)
+    }
+
     fn clear(&mut self);
 
     fn remove(&mut self, pv: &PartialValue, cid: &Cid) -> bool;
@@ -128,6 +143,19 @@ pub trait ValueSetT: std::fmt::Debug + DynClone {
 
     fn contains(&self, pv: &PartialValue) -> bool;
 
+    /// Fetch the single value identified by this partial value, if present. Unlike `contains`
+    /// this only matches the identity of a value, not any secondary keys it may be indexed by.
+    /// Types that do not support this return `None`.
+    fn get_value(&self, _pv: &PartialValue) -> Option<Value> {
+        None
+    }
+
+    /// As per `contains`, but values that have been revoked are not considered
+    /// present. Types without a revocation state are identical to `contains`.
+    fn contains_active(&self, pv: &PartialValue) -> bool {
+        self.contains(pv)
+    }
+
     fn substring(&self, _pv: &crate::value::PartialValue) -> bool {
         false
     }
@@ -162,6 +190,24 @@ pub trait ValueSetT: std::fmt::Debug + DynClone {
 
     fn validate(&self, schema_attr: &SchemaAttribute) -> bool;
 
+    /// Assert that this set holds no more values than the schema allows. Types that
+    /// are able to trim themselves to the maximum (see `trim_to_maximum`) only warn,
+    /// as they may exceed the limit after a replication merge or a change in schema
+    /// until they are next modified.
+    fn validate_maximum_values(&self, schema_attr: &SchemaAttribute) -> bool {
+        schema_attr
+            .maximum_values
+            .is_none_or(|maximum| self.len() <= maximum as usize)
+    }
+
+    /// Reduce this set until it holds no more than `maximum` values, returning the number
+    /// changed. Types whose removals must replicate may instead revoke values at `cid`, and
+    /// only discard them once past `trim_cid`. Most types can not decide which values are
+    /// safe to discard, so this is a no-op.
+    fn trim_to_maximum(&mut self, _maximum: usize, _cid: &Cid, _trim_cid: &Cid) -> usize {
+        0
+    }
+
     fn to_proto_string_clone_iter(&self) -> Box<dyn Iterator<Item = String> + '_>;
 
     fn to_scim_value(&self) -> Option<ScimResolveStatus>;
@@ -608,16 +654,53 @@ pub trait ValueSetT: std::fmt::Debug + DynClone {
         None
     }
 
+    fn as_session_set(&self) -> Option<&ValueSetSession> {
+        debug_assert!(false);
+        None
+    }
+
+    fn as_session_set_mut(&mut self) -> Option<&mut ValueSetSession> {
+        debug_assert!(false);
+        None
+    }
+
     fn as_apitoken_map(&self) -> Option<&BTreeMap<Uuid, ApiToken>> {
         debug_assert!(false);
         None
     }
 
+    fn as_apitoken_set(&self) -> Option<&ValueSetApiToken> {
+        debug_assert!(false);
+        None
+    }
+
+    fn as_apitoken_set_mut(&mut self) -> Option<&mut ValueSetApiToken> {
+        debug_assert!(false);
+        None
+    }
+
+    fn as_apitoken_issued_by_iter(
+        &self,
+    ) -> Option<Box<dyn Iterator<Item = (Uuid, IdentityId)> + '_>> {
+        debug_assert!(false);
+        None
+    }
+
     fn as_oauth2session_map(&self) -> Option<&BTreeMap<Uuid, Oauth2Session>> {
         debug_assert!(false);
         None
     }
 
+    fn as_oauth2session_set(&self) -> Option<&ValueSetOauth2Session> {
+        debug_assert!(false);
+        None
+    }
+
+    fn as_oauth2session_set_mut(&mut self) -> Option<&mut ValueSetOauth2Session> {
+        debug_assert!(false);
+        None
+    }
+
     fn to_jws_key_es256_single(&self) -> Option<&JwsEs256Signer> {
         debug_assert!(false);
         None
@@ -748,6 +831,10 @@ pub enum ScimValueIntermediate {
     References(Vec<Uuid>),
     Oauth2ClaimMap(Vec<UnresolvedScimValueOauth2ClaimMap>),
     Oauth2ScopeMap(Vec<UnresolvedScimValueOauth2ScopeMap>),
+    // The client display names of these sessions are resolved.
+    Oauth2Session(Vec<ScimOAuth2Session>),
+    // Whether these tokens have expired is resolved against the current time.
+    ApiToken(Vec<ScimApiToken>),
 }
 
 pub enum ScimResolveStatus {
@@ -850,6 +937,20 @@ pub fn uuid_to_proto_string(u: Uuid) -> String {
     u.as_hyphenated().to_string()
 }
 
+/// Answer a lessthan query for valuesets whose values each carry an optional point in
+/// time, such as an expiry. This is true if *any* value is strictly before the requested
+/// time. A value of `None` has no end, so it is before no time.
+pub(crate) fn datetime_lessthan_any<I>(iter: I, pv: &PartialValue) -> bool
+where
+    I: IntoIterator<Item = Option<OffsetDateTime>>,
+{
+    let PartialValue::DateTime(at) = pv else {
+        return false;
+    };
+
+    iter.into_iter().flatten().any(|value| value < *at)
+}
+
 pub fn from_result_value_iter(
     mut iter: impl Iterator<Item = Result<Value, OperationError>>,
 ) -> Result<ValueSet, OperationError> {
@@ -1,229 +1,782 @@
 use crate::be::dbvalue::{
-    DbCidV1, DbValueAccessScopeV1, DbValueApiToken, DbValueApiTokenScopeV1, DbValueAuthTypeV1,
-    DbValueIdentityId, DbValueOauth2Session, DbValueSession, DbValueSessionExtMetadataV1,
-    DbValueSessionStateV1,
+    DbCidV1, DbValueAccessScopeV1, DbValueApiToken, DbValueApiTokenConstraintV1,
+    DbValueApiTokenScopeV1, DbValueAuthTypeV1, DbValueIdentityId, DbValueOauth2Session,
+    DbValueSession, DbValueSessionDisabledV1, DbValueSessionExtMetadataV1, DbValueSessionStateV1,
+    DbValueSessionStateV2, DbValueTimestampV1,
 };
 use crate::prelude::*;
 use crate::repl::cid::Cid;
 use crate::schema::SchemaAttribute;
 use crate::value::{
-    ApiToken, ApiTokenScope, AuthType, Oauth2Session, Session, SessionExtMetadata, SessionScope,
-    SessionState,
+    ApiToken, ApiTokenConstraint, ApiTokenScope, AuthType, Oauth2Session, Session, SessionDisabled,
+    SessionExtMetadata, SessionScope, SessionState,
 };
-use crate::valueset::{uuid_to_proto_string, DbValueSetV2, ScimResolveStatus, ValueSet};
+use crate::valueset::{
+    datetime_lessthan_any, uuid_to_proto_string, DbValueSetV2, ScimResolveStatus,
+    ScimValueIntermediate, ValueSet,
+};
+use crypto_glue::{s256::Sha256, traits::Digest};
 use kanidm_proto::scim_v1::server::ScimApiToken;
 use kanidm_proto::scim_v1::server::ScimAuthSession;
 use kanidm_proto::scim_v1::server::ScimOAuth2Session;
+use kanidm_proto::scim_v1::server::ScimSessionSummary;
+use std::cmp::Ordering;
 use std::collections::btree_map::Entry as BTreeEntry;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use time::OffsetDateTime;
 
+/// The time at which a session was revoked, derived from the [Cid] of the revocation. A
+/// cid with a zero or unrepresentable timestamp can only come from a corrupted or badly
+/// migrated entry, so this is logged and the result is clamped to the UNIX epoch, which
+/// keeps the session treated as revoked.
+pub(crate) fn revoked_at_timestamp(cid: &Cid) -> OffsetDateTime {
+    if cid.ts.is_zero() {
+        warn!(
+            ?cid,
+            "Session revocation cid has a zero timestamp, this may indicate corruption"
+        );
+        return OffsetDateTime::UNIX_EPOCH;
+    }
+
+    OffsetDateTime::UNIX_EPOCH
+        .checked_add(cid.ts.try_into().unwrap_or(time::Duration::MAX))
+        .unwrap_or_else(|| {
+            error!(
+                ?cid,
+                "Session revocation cid timestamp is out of range, this may indicate corruption"
+            );
+            OffsetDateTime::UNIX_EPOCH
+        })
+}
+
+/// The expiry of a session in this state for expiry matching, `Some(None)` if it never
+/// expires. A revoked session is only awaiting trimming and no longer expires, so is `None`.
+fn session_state_expiry(state: &SessionState) -> Option<Option<OffsetDateTime>> {
+    match state {
+        SessionState::ExpiresAt(odt) => Some(Some(*odt)),
+        SessionState::NeverExpires => Some(None),
+        SessionState::RevokedAt(_) => None,
+    }
+}
+
+/// Pairings of auth type and session scope that must never be inserted, regardless of what
+/// the auth flow requested. None of these auth types prove enough about the holder to be
+/// allowed to become privileged.
+const SESSION_SCOPE_DENIED: &[(AuthType, SessionScope)] = &[
+    (AuthType::Anonymous, SessionScope::PrivilegeCapable),
+    (AuthType::GeneratedPassword, SessionScope::PrivilegeCapable),
+    (AuthType::OAuth2Trust, SessionScope::PrivilegeCapable),
+    (AuthType::ServiceAccount, SessionScope::PrivilegeCapable),
+    (AuthType::LdapPassword, SessionScope::PrivilegeCapable),
+    (AuthType::TokenExchange, SessionScope::PrivilegeCapable),
+];
+
+/// Check a session's auth type may be issued with its scope.
+fn session_scope_permitted(session: &Session) -> bool {
+    !SESSION_SCOPE_DENIED
+        .iter()
+        .any(|(type_, scope)| *type_ == session.type_ && *scope == session.scope)
+}
+
+/// Limit a session to the lifetime ceiling of its auth type, so that a session can not be
+/// inserted that outlives what its authentication permits.
+fn clamp_session_lifetime(session_id: Uuid, session: &mut Session) {
+    let effective_state = session.effective_state();
+    if effective_state != session.state {
+        warn!(%session_id, type_ = %session.type_, "Clamping session expiry to the lifetime ceiling of its auth type");
+        session.state = effective_state;
+    }
+}
+
+/// Session types trim themselves to the schema maximum when modified rather than being
+/// rejected, since a set may exceed the maximum after a replication merge or a change in
+/// schema. Until then, this is only worth a warning.
+fn warn_exceeds_maximum_values(len: usize, schema_attr: &SchemaAttribute) {
+    if let Some(maximum) = schema_attr.maximum_values {
+        if len > maximum as usize {
+            warn!(
+                attr = %schema_attr.name,
+                len,
+                maximum,
+                "session set exceeds the schema maximum_values, it will be trimmed on next modification"
+            );
+        }
+    }
+}
+
+/// The valueset types of an attribute should never differ, so a mismatch during a merge
+/// indicates the types have been confused. The error identifies both types.
+fn check_merge_syntax(expected: SyntaxType, other: &ValueSet) -> Result<(), OperationError> {
+    let found = other.syntax();
+    if found == expected {
+        Ok(())
+    } else {
+        error!(%expected, %found, "Unable to merge valuesets of differing types");
+        Err(OperationError::ValueSetTypeMismatch {
+            expected: expected.to_string(),
+            found: found.to_string(),
+        })
+    }
+}
+
+/// In replication the newer valueset is kept when the types differ, but as with
+/// `check_merge_syntax` this should never happen.
+fn check_repl_merge_syntax(expected: SyntaxType, older: &ValueSet) -> bool {
+    let found = older.syntax();
+    if found != expected {
+        error!(
+            %expected,
+            %found,
+            "Unable to merge replicated valuesets of differing types, keeping the newer value"
+        );
+    }
+    found == expected
+}
+
+/// Convert a timestamp to unix nanoseconds for storage, if it is within the range of
+/// an i64 (approximately the years 1677 to 2262).
+fn odt_to_unix_nanos(odt: &OffsetDateTime) -> Option<i64> {
+    i64::try_from(odt.unix_timestamp_nanos()).ok()
+}
+
+fn odt_to_rfc3339(odt: &OffsetDateTime) -> String {
+    #[allow(clippy::expect_used)]
+    odt.format(&Rfc3339)
+        .expect("Failed to format timestamp into RFC3339!")
+}
+
+/// The bytes each serialized session requires regardless of its content - the field names,
+/// uuids, enumerations and punctuation. This deliberately overestimates.
+const SESSION_DB_FIXED_OVERHEAD: usize = 384;
+
+/// The bytes a single serialized timestamp may require, as either unix nanoseconds or an
+/// RFC3339 string for timestamps out of that range.
+const SESSION_DB_TIMESTAMP_SIZE: usize = 48;
+
+/// The length of a string once it is quoted and escaped for storage.
+fn escaped_str_len(value: &str) -> usize {
+    2 + value
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => 2,
+            c if c.is_control() => 6,
+            c => c.len_utf8(),
+        })
+        .sum::<usize>()
+}
+
+/// Convert a single session into its current database representation.
+pub(crate) fn session_to_dbv(refer: Uuid, m: &Session) -> DbValueSession {
+    let label = m.label.clone();
+    let issued_by = match m.issued_by {
+        IdentityId::Internal(u) => DbValueIdentityId::V2Internal(u),
+        IdentityId::User(u) => DbValueIdentityId::V1Uuid(u),
+        IdentityId::Synch(u) => DbValueIdentityId::V1Sync(u),
+    };
+    let cred_id = m.cred_id;
+    let scope = match m.scope {
+        SessionScope::ReadOnly => DbValueAccessScopeV1::ReadOnly,
+        SessionScope::ReadWrite => DbValueAccessScopeV1::ReadWrite,
+        SessionScope::PrivilegeCapable => DbValueAccessScopeV1::PrivilegeCapable,
+        SessionScope::Synchronise => DbValueAccessScopeV1::Synchronise,
+        SessionScope::ApiReadWrite => DbValueAccessScopeV1::ApiReadWrite,
+    };
+    let type_ = match m.type_ {
+        AuthType::Anonymous => DbValueAuthTypeV1::Anonymous,
+        AuthType::Password => DbValueAuthTypeV1::Password,
+        AuthType::GeneratedPassword => DbValueAuthTypeV1::GeneratedPassword,
+        AuthType::PasswordTotp => DbValueAuthTypeV1::PasswordTotp,
+        AuthType::PasswordBackupCode => DbValueAuthTypeV1::PasswordBackupCode,
+        AuthType::PasswordSecurityKey => DbValueAuthTypeV1::PasswordSecurityKey,
+        AuthType::Passkey => DbValueAuthTypeV1::Passkey,
+        AuthType::AttestedPasskey => DbValueAuthTypeV1::AttestedPasskey,
+        AuthType::OAuth2Trust => DbValueAuthTypeV1::OAuth2Trust,
+        AuthType::ServiceAccount => DbValueAuthTypeV1::ServiceAccount,
+        AuthType::LdapPassword => DbValueAuthTypeV1::LdapPassword,
+        AuthType::TokenExchange => DbValueAuthTypeV1::TokenExchange,
+    };
+    let ext_metadata = match &m.ext_metadata {
+        SessionExtMetadata::None => DbValueSessionExtMetadataV1::None,
+        SessionExtMetadata::OAuth2 {
+            access_expires_at,
+            access_token,
+            refresh_token,
+        } => DbValueSessionExtMetadataV1::OAuth2 {
+            access_expires_at: *access_expires_at,
+            access_token: access_token.clone(),
+            refresh_token: refresh_token.clone(),
+        },
+    };
+    let bound_to = m.bound_to.clone();
+    let disabled = m.disabled.as_ref().map(|d| match d {
+        SessionDisabled::DisabledAt(c) => DbValueSessionDisabledV1::DisabledAt(DbCidV1 {
+            server_id: c.s_uuid,
+            timestamp: c.ts,
+        }),
+        SessionDisabled::EnabledAt(c) => DbValueSessionDisabledV1::EnabledAt(DbCidV1 {
+            server_id: c.s_uuid,
+            timestamp: c.ts,
+        }),
+    });
+
+    let state = match &m.state {
+        SessionState::ExpiresAt(odt) => DbValueSessionStateV2::ExpiresAt(odt_to_dbv(odt)),
+        SessionState::NeverExpires => DbValueSessionStateV2::Never,
+        SessionState::RevokedAt(c) => DbValueSessionStateV2::RevokedAt(DbCidV1 {
+            server_id: c.s_uuid,
+            timestamp: c.ts,
+        }),
+    };
+
+    DbValueSession::V5 {
+        refer,
+        label,
+        state,
+        issued_at: odt_to_dbv(&m.issued_at),
+        issued_by,
+        cred_id,
+        scope,
+        type_,
+        ext_metadata,
+        bound_to,
+        last_active: m.last_active.as_ref().map(odt_to_dbv),
+        pinned: m.pinned,
+        service_account: m.service_account,
+        disabled,
+    }
+}
+
+/// Store a timestamp as unix nanoseconds, falling back to a string when it is out of
+/// range for the integer form.
+fn odt_to_dbv(odt: &OffsetDateTime) -> DbValueTimestampV1 {
+    odt_to_unix_nanos(odt)
+        .map(DbValueTimestampV1::UnixNanos)
+        .unwrap_or_else(|| DbValueTimestampV1::Rfc3339(odt_to_rfc3339(odt)))
+}
+
+fn dbv_rfc3339_to_odt(refer: &Uuid, value: &str, field: &str) -> Option<OffsetDateTime> {
+    OffsetDateTime::parse(value, &Rfc3339)
+        .map(|odt| odt.to_offset(time::UtcOffset::UTC))
+        .map_err(|e| {
+            admin_error!(
+                ?e,
+                "Invalidating session {} due to invalid {} timestamp",
+                refer,
+                field
+            )
+        })
+        .ok()
+}
+
+fn dbv_unix_nanos_to_odt(refer: &Uuid, value: i64, field: &str) -> Option<OffsetDateTime> {
+    OffsetDateTime::from_unix_timestamp_nanos(value.into())
+        .map_err(|e| {
+            admin_error!(
+                ?e,
+                "Invalidating session {} due to invalid {} timestamp",
+                refer,
+                field
+            )
+        })
+        .ok()
+}
+
+fn dbv_state_v1_to_state(refer: &Uuid, state: &DbValueSessionStateV1) -> Option<SessionState> {
+    match state {
+        DbValueSessionStateV1::ExpiresAt(e_inner) => {
+            dbv_rfc3339_to_odt(refer, e_inner, "expiry").map(SessionState::ExpiresAt)
+        }
+        DbValueSessionStateV1::Never => Some(SessionState::NeverExpires),
+        DbValueSessionStateV1::RevokedAt(dc) => Some(SessionState::RevokedAt(Cid {
+            s_uuid: dc.server_id,
+            ts: dc.timestamp,
+        })),
+    }
+}
+
+fn dbv_timestamp_to_odt(
+    refer: &Uuid,
+    value: &DbValueTimestampV1,
+    field: &str,
+) -> Option<OffsetDateTime> {
+    match value {
+        DbValueTimestampV1::UnixNanos(nanos) => dbv_unix_nanos_to_odt(refer, *nanos, field),
+        DbValueTimestampV1::Rfc3339(value) => dbv_rfc3339_to_odt(refer, value, field),
+    }
+}
+
+fn dbv_state_v2_to_state(refer: &Uuid, state: &DbValueSessionStateV2) -> Option<SessionState> {
+    match state {
+        DbValueSessionStateV2::ExpiresAt(e_inner) => {
+            dbv_timestamp_to_odt(refer, e_inner, "expiry").map(SessionState::ExpiresAt)
+        }
+        DbValueSessionStateV2::Never => Some(SessionState::NeverExpires),
+        DbValueSessionStateV2::RevokedAt(dc) => Some(SessionState::RevokedAt(Cid {
+            s_uuid: dc.server_id,
+            ts: dc.timestamp,
+        })),
+    }
+}
+
+fn dbv_disabled_to_disabled(disabled: &DbValueSessionDisabledV1) -> SessionDisabled {
+    match disabled {
+        DbValueSessionDisabledV1::DisabledAt(dc) => SessionDisabled::DisabledAt(Cid {
+            s_uuid: dc.server_id,
+            ts: dc.timestamp,
+        }),
+        DbValueSessionDisabledV1::EnabledAt(dc) => SessionDisabled::EnabledAt(Cid {
+            s_uuid: dc.server_id,
+            ts: dc.timestamp,
+        }),
+    }
+}
+
+/// Parse a single database session record. Records that can not be used (such as
+/// those that predate the auth type being stored) yield `None`.
+pub(crate) fn dbv_to_session(dbv: &DbValueSession) -> Option<(Uuid, Session)> {
+    let (
+        refer,
+        label,
+        state,
+        issued_at,
+        issued_by,
+        cred_id,
+        scope,
+        type_,
+        ext_metadata,
+        bound_to,
+        last_active,
+        pinned,
+        service_account,
+        disabled,
+    ) = match dbv {
+        // We need to ignore all older session records as they lack the AuthType
+        // record which prevents re-auth working.
+        DbValueSession::V1 { .. } | DbValueSession::V2 { .. } | DbValueSession::V3 { .. } => {
+            return None
+        }
+        DbValueSession::V4 {
+            refer,
+            label,
+            state,
+            issued_at,
+            issued_by,
+            cred_id,
+            scope,
+            type_,
+            ext_metadata,
+        } => (
+            refer,
+            label,
+            dbv_state_v1_to_state(refer, state)?,
+            dbv_rfc3339_to_odt(refer, issued_at, "issued_at")?,
+            issued_by,
+            cred_id,
+            scope,
+            type_,
+            ext_metadata,
+            None,
+            None,
+            false,
+            false,
+            None,
+        ),
+        DbValueSession::V5 {
+            refer,
+            label,
+            state,
+            issued_at,
+            issued_by,
+            cred_id,
+            scope,
+            type_,
+            ext_metadata,
+            bound_to,
+            last_active,
+            pinned,
+            service_account,
+            disabled,
+        } => (
+            refer,
+            label,
+            dbv_state_v2_to_state(refer, state)?,
+            dbv_timestamp_to_odt(refer, issued_at, "issued_at")?,
+            issued_by,
+            cred_id,
+            scope,
+            type_,
+            ext_metadata,
+            bound_to.clone(),
+            match last_active {
+                Some(la) => Some(dbv_timestamp_to_odt(refer, la, "last_active")?),
+                None => None,
+            },
+            *pinned,
+            *service_account,
+            disabled.as_ref().map(dbv_disabled_to_disabled),
+        ),
+    };
+
+    let issued_by = match issued_by {
+        DbValueIdentityId::V1Internal => IdentityId::Internal(UUID_SYSTEM),
+        DbValueIdentityId::V2Internal(u) => IdentityId::Internal(*u),
+        DbValueIdentityId::V1Uuid(u) => IdentityId::User(*u),
+        DbValueIdentityId::V1Sync(u) => IdentityId::Synch(*u),
+    };
+
+    let scope = match scope {
+        DbValueAccessScopeV1::IdentityOnly | DbValueAccessScopeV1::ReadOnly => {
+            SessionScope::ReadOnly
+        }
+        DbValueAccessScopeV1::ReadWrite => SessionScope::ReadWrite,
+        DbValueAccessScopeV1::PrivilegeCapable => SessionScope::PrivilegeCapable,
+        DbValueAccessScopeV1::Synchronise => SessionScope::Synchronise,
+        DbValueAccessScopeV1::ApiReadWrite => SessionScope::ApiReadWrite,
+    };
+
+    let type_ = match type_ {
+        DbValueAuthTypeV1::Anonymous => AuthType::Anonymous,
+        DbValueAuthTypeV1::Password => AuthType::Password,
+        DbValueAuthTypeV1::GeneratedPassword => AuthType::GeneratedPassword,
+        DbValueAuthTypeV1::PasswordTotp => AuthType::PasswordTotp,
+        DbValueAuthTypeV1::PasswordBackupCode => AuthType::PasswordBackupCode,
+        DbValueAuthTypeV1::PasswordSecurityKey => AuthType::PasswordSecurityKey,
+        DbValueAuthTypeV1::Passkey => AuthType::Passkey,
+        DbValueAuthTypeV1::AttestedPasskey => AuthType::AttestedPasskey,
+        DbValueAuthTypeV1::OAuth2Trust => AuthType::OAuth2Trust,
+        DbValueAuthTypeV1::ServiceAccount => AuthType::ServiceAccount,
+        DbValueAuthTypeV1::LdapPassword => AuthType::LdapPassword,
+        DbValueAuthTypeV1::TokenExchange => AuthType::TokenExchange,
+    };
+
+    let ext_metadata = match ext_metadata {
+        DbValueSessionExtMetadataV1::None => SessionExtMetadata::None,
+        DbValueSessionExtMetadataV1::OAuth2 {
+            access_expires_at,
+            access_token,
+            refresh_token,
+        } => SessionExtMetadata::OAuth2 {
+            access_expires_at: *access_expires_at,
+            access_token: access_token.clone(),
+            refresh_token: refresh_token.clone(),
+        },
+    };
+
+    Some((
+        *refer,
+        Session {
+            label: label.clone(),
+            state,
+            issued_at,
+            issued_by,
+            cred_id: *cred_id,
+            scope,
+            type_,
+            ext_metadata,
+            bound_to,
+            last_active,
+            pinned,
+            service_account,
+            disabled,
+        },
+    ))
+}
+
+/// Parse a single database oauth2 session record. Older versions are read into the
+/// fields of the newest, and records with invalid timestamps yield `None` so that the
+/// session is invalidated.
+fn dbv_to_oauth2_session(dbv: DbValueOauth2Session) -> Option<(Uuid, Oauth2Session)> {
+    let (
+        refer,
+        parent,
+        state,
+        issued_at,
+        rs_uuid,
+        granted_scopes,
+        has_refresh,
+        last_refreshed,
+        origin,
+    ) = match dbv {
+        DbValueOauth2Session::V1 {
+            refer,
+            parent,
+            expiry,
+            issued_at,
+            rs_uuid,
+        } => (
+            refer,
+            Some(parent),
+            match expiry {
+                Some(e_inner) => {
+                    SessionState::ExpiresAt(dbv_rfc3339_to_odt(&refer, &e_inner, "expiry")?)
+                }
+                None => SessionState::NeverExpires,
+            },
+            issued_at,
+            rs_uuid,
+            BTreeSet::new(),
+            false,
+            None,
+            None,
+        ),
+        DbValueOauth2Session::V2 {
+            refer,
+            parent,
+            state,
+            issued_at,
+            rs_uuid,
+        } => (
+            refer,
+            Some(parent),
+            dbv_state_v1_to_state(&refer, &state)?,
+            issued_at,
+            rs_uuid,
+            BTreeSet::new(),
+            false,
+            None,
+            None,
+        ),
+        DbValueOauth2Session::V3 {
+            refer,
+            parent,
+            state,
+            issued_at,
+            rs_uuid,
+        } => (
+            refer,
+            parent,
+            dbv_state_v1_to_state(&refer, &state)?,
+            issued_at,
+            rs_uuid,
+            BTreeSet::new(),
+            false,
+            None,
+            None,
+        ),
+        DbValueOauth2Session::V4 {
+            refer,
+            parent,
+            state,
+            issued_at,
+            rs_uuid,
+            granted_scopes,
+        } => (
+            refer,
+            parent,
+            dbv_state_v1_to_state(&refer, &state)?,
+            issued_at,
+            rs_uuid,
+            granted_scopes,
+            false,
+            None,
+            None,
+        ),
+        DbValueOauth2Session::V5 {
+            refer,
+            parent,
+            state,
+            issued_at,
+            rs_uuid,
+            granted_scopes,
+            has_refresh,
+            last_refreshed,
+        } => (
+            refer,
+            parent,
+            dbv_state_v1_to_state(&refer, &state)?,
+            issued_at,
+            rs_uuid,
+            granted_scopes,
+            has_refresh,
+            last_refreshed,
+            None,
+        ),
+        DbValueOauth2Session::V6 {
+            refer,
+            parent,
+            state,
+            issued_at,
+            rs_uuid,
+            granted_scopes,
+            has_refresh,
+            last_refreshed,
+            origin,
+        } => (
+            refer,
+            parent,
+            dbv_state_v1_to_state(&refer, &state)?,
+            issued_at,
+            rs_uuid,
+            granted_scopes,
+            has_refresh,
+            last_refreshed,
+            origin,
+        ),
+    };
+
+    let issued_at = dbv_rfc3339_to_odt(&refer, &issued_at, "issued_at")?;
+
+    // The last refresh time is informational, so if it can't be parsed
+    // we discard it rather than invalidating the session.
+    let last_refreshed = last_refreshed.and_then(|lr_inner| {
+        OffsetDateTime::parse(&lr_inner, &Rfc3339)
+            .map(|odt| odt.to_offset(time::UtcOffset::UTC))
+            .map_err(|e| {
+                admin_warn!(
+                    ?e,
+                    "Discarding invalid last_refreshed timestamp of session {}",
+                    refer
+                )
+            })
+            .ok()
+    });
+
+    Some((
+        refer,
+        Oauth2Session {
+            parent,
+            state,
+            issued_at,
+            rs_uuid,
+            granted_scopes,
+            has_refresh,
+            last_refreshed,
+            origin,
+        },
+    ))
+}
+
+/// Controls which sessions are removed when a session valueset is trimmed. This is only
+/// available to library callers of `trim_with_policy`; the server has no configuration
+/// for it, and `trim` always applies the default policy.
+#[derive(Debug, Clone, Default)]
+pub struct TrimPolicy {
+    /// Revoked sessions that were revoked after this time are retained even once they
+    /// are past the replication trim window, so that they remain available for
+    /// forensics. Retaining revocations is always safe for replication, but these
+    /// sessions continue to consume storage until the floor is moved forward. They are
+    /// the first to be removed if a maximum is exceeded.
+    pub retain_revoked_until: Option<OffsetDateTime>,
+    /// The maximum number of sessions of each scope. Each scope is force trimmed
+    /// independently of the others, oldest first. The `maximum` still applies to the set
+    /// as a whole.
+    pub scope_maximums: BTreeMap<SessionScope, usize>,
+    /// The maximum number of sessions in the set, usually the schema `maximum_values` of
+    /// the attribute. Without this, only the scope maximums are applied.
+    pub maximum: Option<usize>,
+}
+
+/// The differences between two session valuesets, from an older to a newer snapshot.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionDiff {
+    /// Sessions only present in the newer snapshot.
+    pub added: Vec<Uuid>,
+    /// Sessions only present in the older snapshot.
+    pub removed: Vec<Uuid>,
+    /// Sessions present in both snapshots with a different state, with the older
+    /// state and then the newer state.
+    pub changed: Vec<(Uuid, SessionState, SessionState)>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ValueSetSession {
     map: BTreeMap<Uuid, Session>,
+    /// The number of sessions this set is expected to hold, used to pre-size the vecs
+    /// derived from it.
+    capacity_hint: usize,
 }
 
 impl ValueSetSession {
     pub fn new(u: Uuid, m: Session) -> Box<Self> {
+        let mut m = m.into_utc();
+        clamp_session_lifetime(u, &mut m);
         let mut map = BTreeMap::new();
         map.insert(u, m);
-        Box::new(ValueSetSession { map })
+        Box::new(ValueSetSession {
+            map,
+            capacity_hint: 0,
+        })
     }
 
+    /// Insert `m` as session `u`, replacing any existing session with that id. A revoked
+    /// session is never replaced by one that is not revoked, as that would un-revoke it.
+    /// Returns true if the session was newly inserted.
     pub fn push(&mut self, u: Uuid, m: Session) -> bool {
-        self.map.insert(u, m).is_none()
+        let m = m.into_utc();
+        match self.map.entry(u) {
+            BTreeEntry::Vacant(e) => {
+                e.insert(m);
+                true
+            }
+            BTreeEntry::Occupied(mut e) => {
+                if matches!(e.get().state, SessionState::RevokedAt(_))
+                    && !matches!(m.state, SessionState::RevokedAt(_))
+                {
+                    warn!(session_id = %u, "Refusing to replace a revoked session with an active one");
+                } else {
+                    e.insert(m);
+                }
+                false
+            }
+        }
     }
 
-    fn to_vec_dbvs(&self) -> Vec<DbValueSession> {
-        self.map
-            .iter()
-            .map(|(u, m)| DbValueSession::V4 {
-                refer: *u,
-                label: m.label.clone(),
+    /// Create an empty set that is expected to hold `hint` sessions, such as when an entry
+    /// with many sessions is restored. The map can't be pre-sized, but the index keys and
+    /// db values derived from the set are allocated to fit `hint`.
+    pub fn with_capacity_hint(hint: usize) -> Box<Self> {
+        Box::new(ValueSetSession {
+            map: BTreeMap::new(),
+            capacity_hint: hint,
+        })
+    }
 
-                state: match &m.state {
-                    SessionState::ExpiresAt(odt) => {
-                        debug_assert_eq!(odt.offset(), time::UtcOffset::UTC);
-                        #[allow(clippy::expect_used)]
-                        odt.format(&Rfc3339)
-                            .map(DbValueSessionStateV1::ExpiresAt)
-                            .expect("Failed to format timestamp into RFC3339!")
-                    }
-                    SessionState::NeverExpires => DbValueSessionStateV1::Never,
-                    SessionState::RevokedAt(c) => DbValueSessionStateV1::RevokedAt(DbCidV1 {
-                        server_id: c.s_uuid,
-                        timestamp: c.ts,
-                    }),
-                },
+    /// The number of sessions to allocate space for in vecs derived from this set.
+    fn capacity(&self) -> usize {
+        self.capacity_hint.max(self.map.len())
+    }
 
-                issued_at: {
-                    debug_assert_eq!(m.issued_at.offset(), time::UtcOffset::UTC);
-                    #[allow(clippy::expect_used)]
-                    m.issued_at
-                        .format(&Rfc3339)
-                        .expect("Failed to format timestamp into RFC3339!")
-                },
-                issued_by: match m.issued_by {
-                    IdentityId::Internal(u) => DbValueIdentityId::V2Internal(u),
-                    IdentityId::User(u) => DbValueIdentityId::V1Uuid(u),
-                    IdentityId::Synch(u) => DbValueIdentityId::V1Sync(u),
-                },
-                cred_id: m.cred_id,
-                scope: match m.scope {
-                    SessionScope::ReadOnly => DbValueAccessScopeV1::ReadOnly,
-                    SessionScope::ReadWrite => DbValueAccessScopeV1::ReadWrite,
-                    SessionScope::PrivilegeCapable => DbValueAccessScopeV1::PrivilegeCapable,
-                    SessionScope::Synchronise => DbValueAccessScopeV1::Synchronise,
-                },
-                type_: match m.type_ {
-                    AuthType::Anonymous => DbValueAuthTypeV1::Anonymous,
-                    AuthType::Password => DbValueAuthTypeV1::Password,
-                    AuthType::GeneratedPassword => DbValueAuthTypeV1::GeneratedPassword,
-                    AuthType::PasswordTotp => DbValueAuthTypeV1::PasswordTotp,
-                    AuthType::PasswordBackupCode => DbValueAuthTypeV1::PasswordBackupCode,
-                    AuthType::PasswordSecurityKey => DbValueAuthTypeV1::PasswordSecurityKey,
-                    AuthType::Passkey => DbValueAuthTypeV1::Passkey,
-                    AuthType::AttestedPasskey => DbValueAuthTypeV1::AttestedPasskey,
-                    AuthType::OAuth2Trust => DbValueAuthTypeV1::OAuth2Trust,
-                },
-                ext_metadata: match &m.ext_metadata {
-                    SessionExtMetadata::None => DbValueSessionExtMetadataV1::None,
-                    SessionExtMetadata::OAuth2 {
-                        access_expires_at,
-                        access_token,
-                        refresh_token,
-                    } => DbValueSessionExtMetadataV1::OAuth2 {
-                        access_expires_at: *access_expires_at,
-                        access_token: access_token.clone(),
-                        refresh_token: refresh_token.clone(),
-                    },
-                },
-            })
-            .collect()
+    fn to_vec_dbvs(&self) -> Vec<DbValueSession> {
+        let mut dbvs = Vec::with_capacity(self.capacity());
+        dbvs.extend(self.map.iter().map(|(u, m)| session_to_dbv(*u, m)));
+        dbvs
     }
 
     fn from_dbv_iter<'a>(
         iter: impl Iterator<Item = &'a DbValueSession>,
     ) -> Result<ValueSet, OperationError> {
-        let map = iter
-            .filter_map(|dbv| {
-                match dbv {
-                    // We need to ignore all older session records as they lack the AuthType
-                    // record which prevents re-auth working.
-                    DbValueSession::V1 { .. }
-                    | DbValueSession::V2 { .. }
-                    | DbValueSession::V3 { .. } => None,
-                    DbValueSession::V4 {
-                        refer,
-                        label,
-                        state,
-                        issued_at,
-                        issued_by,
-                        cred_id,
-                        scope,
-                        type_,
-                        ext_metadata,
-                    } => {
-                        // Convert things.
-                        let issued_at = OffsetDateTime::parse(issued_at, &Rfc3339)
-                            .map(|odt| odt.to_offset(time::UtcOffset::UTC))
-                            .map_err(|e| {
-                                admin_error!(
-                                    ?e,
-                                    "Invalidating session {} due to invalid issued_at timestamp",
-                                    refer
-                                )
-                            })
-                            .ok()?;
-
-                        let state = match state {
-                            DbValueSessionStateV1::ExpiresAt(e_inner) => {
-                                OffsetDateTime::parse(e_inner, &Rfc3339)
-                                    .map(|odt| odt.to_offset(time::UtcOffset::UTC))
-                                    .map(SessionState::ExpiresAt)
-                                    .map_err(|e| {
-                                        admin_error!(
-                                        ?e,
-                                        "Invalidating session {} due to invalid expiry timestamp",
-                                        refer
-                                    )
-                                    })
-                                    .ok()?
-                            }
-                            DbValueSessionStateV1::Never => SessionState::NeverExpires,
-                            DbValueSessionStateV1::RevokedAt(dc) => SessionState::RevokedAt(Cid {
-                                s_uuid: dc.server_id,
-                                ts: dc.timestamp,
-                            }),
-                        };
-
-                        let issued_by = match issued_by {
-                            DbValueIdentityId::V1Internal => IdentityId::Internal(UUID_SYSTEM),
-                            DbValueIdentityId::V2Internal(u) => IdentityId::Internal(*u),
-                            DbValueIdentityId::V1Uuid(u) => IdentityId::User(*u),
-                            DbValueIdentityId::V1Sync(u) => IdentityId::Synch(*u),
-                        };
-
-                        let scope = match scope {
-                            DbValueAccessScopeV1::IdentityOnly | DbValueAccessScopeV1::ReadOnly => {
-                                SessionScope::ReadOnly
-                            }
-                            DbValueAccessScopeV1::ReadWrite => SessionScope::ReadWrite,
-                            DbValueAccessScopeV1::PrivilegeCapable => {
-                                SessionScope::PrivilegeCapable
-                            }
-                            DbValueAccessScopeV1::Synchronise => SessionScope::Synchronise,
-                        };
-
-                        let type_ = match type_ {
-                            DbValueAuthTypeV1::Anonymous => AuthType::Anonymous,
-                            DbValueAuthTypeV1::Password => AuthType::Password,
-                            DbValueAuthTypeV1::GeneratedPassword => AuthType::GeneratedPassword,
-                            DbValueAuthTypeV1::PasswordTotp => AuthType::PasswordTotp,
-                            DbValueAuthTypeV1::PasswordBackupCode => AuthType::PasswordBackupCode,
-                            DbValueAuthTypeV1::PasswordSecurityKey => AuthType::PasswordSecurityKey,
-                            DbValueAuthTypeV1::Passkey => AuthType::Passkey,
-                            DbValueAuthTypeV1::AttestedPasskey => AuthType::AttestedPasskey,
-                            DbValueAuthTypeV1::OAuth2Trust => AuthType::OAuth2Trust,
-                        };
-
-                        let ext_metadata = match ext_metadata {
-                            DbValueSessionExtMetadataV1::None => SessionExtMetadata::None,
-                            DbValueSessionExtMetadataV1::OAuth2 {
-                                access_expires_at,
-                                access_token,
-                                refresh_token,
-                            } => SessionExtMetadata::OAuth2 {
-                                access_expires_at: *access_expires_at,
-                                access_token: access_token.clone(),
-                                refresh_token: refresh_token.clone(),
-                            },
-                        };
-
-                        Some((
-                            *refer,
-                            Session {
-                                label: label.clone(),
-                                state,
-                                issued_at,
-                                issued_by,
-                                cred_id: *cred_id,
-                                scope,
-                                type_,
-                                ext_metadata,
-                            },
-                        ))
-                    }
-                }
-            })
-            .collect();
-        Ok(Box::new(ValueSetSession { map }))
+        let map = iter.filter_map(dbv_to_session).collect();
+        Ok(Box::new(ValueSetSession {
+            map,
+            capacity_hint: 0,
+        }))
     }
 
     pub fn from_dbvs2(data: &[DbValueSession]) -> Result<ValueSet, OperationError> {
         Self::from_dbv_iter(data.iter())
     }
 
+    /// As per `from_dbvs2`, but also returns the number of records that could not be
+    /// loaded, such as those with corrupt timestamps. All other records are always
+    /// loaded, so if any were dropped the entry should be flagged for repair.
+    pub fn from_dbvs2_lenient(data: &[DbValueSession]) -> (ValueSet, usize) {
+        let map: BTreeMap<_, _> = data.iter().filter_map(dbv_to_session).collect();
+        let dropped = data.len().saturating_sub(map.len());
+        if dropped > 0 {
+            warn!(
+                ?dropped,
+                "session records could not be loaded and were dropped"
+            );
+        }
+        (
+            Box::new(ValueSetSession {
+                map,
+                capacity_hint: 0,
+            }),
+            dropped,
+        )
+    }
+
     // We need to allow this, because rust doesn't allow us to impl FromIterator on foreign
     // types, and tuples are always foreign.
     #[allow(clippy::should_implement_trait)]
@@ -231,78 +784,239 @@ impl ValueSetSession {
     where
         T: IntoIterator<Item = (Uuid, Session)>,
     {
-        let map = iter.into_iter().collect();
-        Some(Box::new(ValueSetSession { map }))
+        let map = iter.into_iter().map(|(u, m)| (u, m.into_utc())).collect();
+        Some(Box::new(ValueSetSession {
+            map,
+            capacity_hint: 0,
+        }))
     }
-}
 
-impl ValueSetT for ValueSetSession {
-    fn insert_checked(&mut self, value: Value) -> Result<bool, OperationError> {
-        match value {
-            Value::Session(u, m) => {
-                if let BTreeEntry::Vacant(e) = self.map.entry(u) {
-                    e.insert(m);
-                    Ok(true)
-                } else {
-                    Ok(false)
+    /// As per `merge`, but `observer` is called for each session that is present in both
+    /// sets with differing states, with the state of this set and then the other set.
+    pub fn merge_with_observer(
+        &mut self,
+        other: &ValueSet,
+        observer: Option<&dyn Fn(Uuid, &SessionState, &SessionState)>,
+    ) -> Result<(), OperationError> {
+        check_merge_syntax(SyntaxType::Session, other)?;
+        if let Some(b) = other.as_session_map() {
+            Self::merge_map(&mut self.map, b, observer);
+            Ok(())
+        } else {
+            debug_assert!(false);
+            Err(OperationError::InvalidValueState)
+        }
+    }
+
+    /// As per `repl_merge_valueset`, but `observer` is called for each session that is
+    /// present in both sets with differing states, with the state of this set and then
+    /// the older set.
+    pub fn repl_merge_valueset_with_observer(
+        &self,
+        older: &ValueSet,
+        trim_cid: &Cid,
+        observer: Option<&dyn Fn(Uuid, &SessionState, &SessionState)>,
+    ) -> Option<ValueSet> {
+        // If the older value has a different type - return nothing, we
+        // just take the newer value.
+        if !check_repl_merge_syntax(SyntaxType::Session, older) {
+            return None;
+        }
+        let b = older.as_session_map()?;
+        let map = Self::merge_map_incremental(&self.map, b, observer);
+
+        let mut vs = Box::new(ValueSetSession {
+            map,
+            capacity_hint: self.capacity_hint,
+        });
+
+        vs.trim(trim_cid);
+
+        Some(vs)
+    }
+
+    /// As per `merge_map`, but into a copy of `map`. Only the sessions of `other` that are
+    /// absent from `map` or not dominated by it are cloned, since in replication the two
+    /// sets are usually mostly identical.
+    fn merge_map_incremental(
+        map: &BTreeMap<Uuid, Session>,
+        other: &BTreeMap<Uuid, Session>,
+        observer: Option<&dyn Fn(Uuid, &SessionState, &SessionState)>,
+    ) -> BTreeMap<Uuid, Session> {
+        let changed: Vec<(&Uuid, &Session)> = other
+            .iter()
+            .filter(|(k_other, v_other)| match map.get(k_other) {
+                Some(v_self) => {
+                    if v_other.state != v_self.state {
+                        if let Some(observer) = observer {
+                            observer(**k_other, &v_self.state, &v_other.state);
+                        }
+                    }
+                    v_other.state > v_self.state
+                        || v_other.last_active > v_self.last_active
+                        || v_other.disabled > v_self.disabled
+                }
+                None => true,
+            })
+            .collect();
+
+        let mut map = map.clone();
+
+        for (k_other, v_other) in changed {
+            match map.get_mut(k_other) {
+                Some(v_self) => {
+                    // As in merge_map, the dominant state brings the rest of the session
+                    // with it, but the latest activity and disabled change are always kept.
+                    let last_active = v_self.last_active.max(v_other.last_active);
+                    let disabled = v_self.disabled.clone().max(v_other.disabled.clone());
+                    if v_other.state > v_self.state {
+                        v_self.clone_from(v_other);
+                    }
+                    v_self.last_active = last_active;
+                    v_self.disabled = disabled;
+                }
+                None => {
+                    map.insert(*k_other, v_other.clone());
                 }
             }
-            _ => Err(OperationError::InvalidValueState),
         }
-    }
 
-    fn clear(&mut self) {
-        self.map.clear();
+        map
     }
 
-    fn remove(&mut self, pv: &PartialValue, cid: &Cid) -> bool {
-        match pv {
-            PartialValue::Refer(u) => {
-                if let Some(session) = self.map.get_mut(u) {
-                    if !matches!(session.state, SessionState::RevokedAt(_)) {
-                        session.state = SessionState::RevokedAt(cid.clone());
-                        true
-                    } else {
-                        false
+    fn merge_map(
+        map: &mut BTreeMap<Uuid, Session>,
+        other: &BTreeMap<Uuid, Session>,
+        observer: Option<&dyn Fn(Uuid, &SessionState, &SessionState)>,
+    ) {
+        // We can't just do merge maps here, we have to be aware of the
+        // session.state value and what it currently is set to.
+        for (k_other, v_other) in other.iter() {
+            if let Some(v_self) = map.get_mut(k_other) {
+                if v_other.state != v_self.state {
+                    if let Some(observer) = observer {
+                        observer(*k_other, &v_self.state, &v_other.state);
                     }
-                } else {
-                    false
                 }
+                // We only update if greater. This is where RevokedAt
+                // always proceeds other states, and lower revoked
+                // cids will always take effect. The binding is taken from
+                // the dominant state along with the rest of the session.
+                let last_active = v_self.last_active.max(v_other.last_active);
+                let disabled = v_self.disabled.clone().max(v_other.disabled.clone());
+                if v_other.state > v_self.state {
+                    *v_self = v_other.clone();
+                }
+                // Activity and disabling are independent of the state, so the latest of
+                // each is always kept.
+                v_self.last_active = last_active;
+                v_self.disabled = disabled;
+            } else {
+                // Not present, just insert.
+                map.insert(*k_other, v_other.clone());
             }
-            _ => false,
         }
     }
 
-    fn purge(&mut self, cid: &Cid) -> bool {
-        for (_uuid, session) in self.map.iter_mut() {
-            // Send them all to the shadow realm
-            if !matches!(session.state, SessionState::RevokedAt(_)) {
-                session.state = SessionState::RevokedAt(cid.clone())
+    /// A hash of the id, kind of state, issue time and credential of every session in
+    /// this set. This is cheaper to compare than `equal` when deciding if a cached copy
+    /// of the set is stale, and is the same across replicas and process restarts given
+    /// the same sessions.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = Sha256::new();
+        // The map is ordered by session id, so the order of insertion is irrelevant.
+        for (session_id, session) in self.map.iter() {
+            let state: u8 = match session.state {
+                SessionState::RevokedAt(_) => 0,
+                SessionState::ExpiresAt(_) => 1,
+                SessionState::NeverExpires => 2,
+            };
+            hasher.update(session_id.as_bytes());
+            hasher.update([state]);
+            hasher.update(session.issued_at.unix_timestamp_nanos().to_be_bytes());
+            hasher.update(session.cred_id.as_bytes());
+        }
+        hasher
+            .finalize()
+            .iter()
+            .take(8)
+            .fold(0, |acc, byte| (acc << 8) | u64::from(*byte))
+    }
+
+    /// Determine the sessions that were added, removed or changed state between this
+    /// set and the newer set `other`.
+    pub fn diff(&self, other: &ValueSetSession) -> SessionDiff {
+        let mut diff = SessionDiff::default();
+
+        for (session_id, session) in self.map.iter() {
+            match other.map.get(session_id) {
+                Some(other_session) if other_session.state != session.state => {
+                    diff.changed.push((
+                        *session_id,
+                        session.state.clone(),
+                        other_session.state.clone(),
+                    ));
+                }
+                Some(_) => {}
+                None => diff.removed.push(*session_id),
             }
         }
-        // Can't be purged since we need the cid's of revoked to persist.
-        false
+
+        diff.added = other
+            .map
+            .keys()
+            .filter(|session_id| !self.map.contains_key(session_id))
+            .copied()
+            .collect();
+
+        diff
     }
 
-    fn trim(&mut self, trim_cid: &Cid) {
+    /// Check if `presented` matches the client identifier that the session `session_id`
+    /// is bound to. Returns `None` if the session is not present or is not bound.
+    pub fn is_bound_match(&self, session_id: &Uuid, presented: &str) -> Option<bool> {
+        self.map
+            .get(session_id)
+            .and_then(|session| session.bound_to.as_deref())
+            .map(|bound_to| bound_to == presented)
+    }
+
+    /// As per `trim`, but revoked sessions may be retained past the replication trim
+    /// window according to `policy`.
+    pub fn trim_with_policy(&mut self, trim_cid: &Cid, policy: &TrimPolicy) {
         // There might be a neater way to do this with less iterations. The problem
         // is we can't just check on what was in b/older, because then we miss
         // trimmable content from the local map. So once the merge is complete we
         // do a pass for trim.
-        self.map.retain(|_, session| {
+        let mut retained_revoked = BTreeSet::new();
+        self.map.retain(|session_id, session| {
             match &session.state {
                 SessionState::RevokedAt(cid) if cid < trim_cid => {
                     // This value is past the replication trim window and can now safely
-                    // be removed
-                    false
+                    // be removed, unless it was revoked after the retention floor.
+                    let retain = policy
+                        .retain_revoked_until
+                        .is_some_and(|floor| revoked_at_timestamp(cid) > floor);
+                    if retain {
+                        retained_revoked.insert(*session_id);
+                    }
+
+                    retain
                 }
                 // Retain all else
                 _ => true,
             }
         });
 
+        for (scope, maximum) in policy.scope_maximums.iter() {
+            let trimmed = self.force_trim_ordered(Some(*scope), *maximum, &retained_revoked);
+            if trimmed > 0 {
+                warn!(%scope, ?maximum, ?trimmed, "entry has exceeded the scope session limit, force trimmed");
+            }
+        }
+
         // Now, assert that there are fewer or equal sessions to the limit.
-        if self.map.len() > SESSION_MAXIMUM {
+        if let Some(maximum) = policy.maximum.filter(|maximum| self.map.len() > *maximum) {
             // At this point we will force a number of sessions to be removed. This
             // is replication safe since other replicas will also be performing
             // the same operation on merge, since we trim by session issuance order.
@@ -314,31 +1028,536 @@ impl ValueSetT for ValueSetSession {
 
             warn!(
                 "entry has exceeded session_maximum limit ({:?}), force trimming will occur",
-                SESSION_MAXIMUM
+                maximum
             );
 
-            let time_idx: BTreeMap<OffsetDateTime, Uuid> = self
-                .map
-                .iter()
-                .map(|(session_id, session)| (session.issued_at, *session_id))
-                .collect();
-
-            let to_take = self.map.len() - SESSION_MAXIMUM;
-
-            time_idx.values().take(to_take).for_each(|session_id| {
-                warn!(?session_id, "force trimmed");
-                self.map.remove(session_id);
-            });
+            self.force_trim_ordered(None, maximum, &retained_revoked);
         }
         // And we're done.
     }
 
-    fn contains(&self, pv: &PartialValue) -> bool {
-        match pv {
-            PartialValue::Refer(u) => self.map.contains_key(u),
-            _ => false,
-        }
-    }
+    /// The order in which sessions are force trimmed, first to last. Within each tier the
+    /// least recently issued sessions are trimmed first. Revoked sessions that are only
+    /// held for forensics (`retained_revoked`) are trimmed before any other session.
+    /// Synchronise sessions are only trimmed once every other session has been, so that a
+    /// connector is not evicted by a flood of interactive sessions, and pinned and disabled
+    /// sessions are trimmed last. This ordering is deterministic, so every replica trims
+    /// the same sessions.
+    fn trim_order(
+        &self,
+        scope: Option<SessionScope>,
+        retained_revoked: &BTreeSet<Uuid>,
+    ) -> Vec<(Uuid, bool)> {
+        let mut candidates: Vec<_> = self
+            .sessions_by_recency()
+            .into_iter()
+            .rev()
+            .filter(|(_, session)| scope.is_none_or(|scope| session.scope == scope))
+            .map(|(session_id, session)| {
+                let retained = session.pinned || session.is_disabled();
+                let tier = if retained_revoked.contains(&session_id) {
+                    0
+                } else if retained {
+                    3
+                } else if session.scope == SessionScope::Synchronise {
+                    2
+                } else {
+                    1
+                };
+                (tier, session_id, retained)
+            })
+            .collect();
+        // A stable sort keeps the recency order within each tier.
+        candidates.sort_by_key(|(tier, _, _)| *tier);
+        candidates
+            .into_iter()
+            .map(|(_, session_id, retained)| (session_id, retained))
+            .collect()
+    }
+
+    /// Remove sessions in the order of `trim_order` until at most `maximum` of those
+    /// matching `scope` remain, returning the number removed.
+    fn force_trim_ordered(
+        &mut self,
+        scope: Option<SessionScope>,
+        maximum: usize,
+        retained_revoked: &BTreeSet<Uuid>,
+    ) -> usize {
+        let candidates = self.trim_order(scope, retained_revoked);
+        let to_take = candidates.len().saturating_sub(maximum);
+
+        if to_take == 0 {
+            return 0;
+        }
+
+        let trimmed: Vec<Uuid> = candidates
+            .into_iter()
+            .take(to_take)
+            .map(|(session_id, retained)| {
+                if retained {
+                    warn!(
+                        ?session_id,
+                        ?maximum,
+                        "pinned or disabled sessions exceed the session maximum, trimming"
+                    );
+                }
+                session_id
+            })
+            .collect();
+
+        self.remove_trimmed(&trimmed);
+
+        trimmed.len()
+    }
+
+    /// Remove sessions until at most `maximum` remain, returning the number removed.
+    fn force_trim(&mut self, maximum: usize) -> usize {
+        self.force_trim_ordered(None, maximum, &BTreeSet::new())
+    }
+
+    fn remove_trimmed(&mut self, trimmed: &[Uuid]) {
+        trimmed.iter().for_each(|session_id| {
+            warn!(?session_id, "force trimmed");
+            self.map.remove(session_id);
+        });
+    }
+
+    /// Determine if the session `session_id` can be used at `now`. A session that was
+    /// revoked less than `grace` before `now` is still treated as active, so that requests
+    /// which are in flight at logout are not broken. This does not alter the stored state.
+    /// Disabled sessions are never active.
+    pub fn is_active_at(&self, session_id: &Uuid, now: OffsetDateTime, grace: Duration) -> bool {
+        match self
+            .map
+            .get(session_id)
+            .filter(|session| !session.is_disabled())
+            .map(|session| &session.state)
+        {
+            Some(SessionState::ExpiresAt(exp)) => now < *exp,
+            Some(SessionState::NeverExpires) => true,
+            // A grace window that runs past the end of time never closes.
+            Some(SessionState::RevokedAt(cid)) => time::Duration::try_from(grace)
+                .ok()
+                .and_then(|grace| revoked_at_timestamp(cid).checked_add(grace))
+                .is_none_or(|end| now < end),
+            None => false,
+        }
+    }
+
+    /// Determine if the session `session_id` exists and is neither revoked nor expired
+    /// at `now`. Unlike `contains`, this accounts for the state of the session, including
+    /// any limit its auth type places on its lifetime. Disabled sessions are never live.
+    pub fn is_live(&self, session_id: &Uuid, now: OffsetDateTime) -> bool {
+        match self
+            .map
+            .get(session_id)
+            .filter(|session| !session.is_disabled())
+            .map(Session::effective_state)
+        {
+            Some(SessionState::ExpiresAt(exp)) => now < exp,
+            Some(SessionState::NeverExpires) => true,
+            Some(SessionState::RevokedAt(_)) | None => false,
+        }
+    }
+
+    /// The sessions in this set, from the most to the least recently issued. Sessions
+    /// that can't otherwise be ordered are ordered by their session id.
+    pub fn sessions_by_recency(&self) -> Vec<(Uuid, &Session)> {
+        let mut sessions = Vec::with_capacity(self.capacity());
+        sessions.extend(self.map.iter().map(|(u, s)| (*u, s)));
+        // This is a stable sort, so ties retain the session id order of the map.
+        sessions.sort_by(|(_, a), (_, b)| Session::cmp_by_recency(a, b));
+        sessions
+    }
+
+    /// A page of the sessions in this set, ordered as per `sessions_by_recency`. Revoked
+    /// sessions are skipped if `exclude_revoked` is set, and sessions created by another
+    /// type of authentication are skipped if `auth_type` is set, prior to `offset` being
+    /// applied.
+    pub fn sessions_by_recency_page(
+        &self,
+        exclude_revoked: bool,
+        auth_type: Option<AuthType>,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Vec<(Uuid, &Session)> {
+        let mut sessions: Vec<_> = self
+            .map
+            .iter()
+            .filter(|(_, session)| {
+                !(exclude_revoked && matches!(session.state, SessionState::RevokedAt(_)))
+                    && auth_type.is_none_or(|auth_type| session.type_ == auth_type)
+            })
+            .map(|(u, s)| (*u, s))
+            .collect();
+        sessions.sort_by(|(_, a), (_, b)| Session::cmp_by_recency(a, b));
+        sessions
+            .into_iter()
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    /// An upper bound on the size in bytes of this set once serialized for the database,
+    /// which is computed without serializing it. This allows an oversized entry to be
+    /// rejected before paying for `to_db_valueset_v2`.
+    pub fn estimated_db_size(&self) -> usize {
+        self.map
+            .values()
+            .map(|session| {
+                let ext_metadata = match &session.ext_metadata {
+                    SessionExtMetadata::None => 0,
+                    SessionExtMetadata::OAuth2 {
+                        access_token,
+                        refresh_token,
+                        ..
+                    } => {
+                        SESSION_DB_FIXED_OVERHEAD
+                            + escaped_str_len(access_token)
+                            + refresh_token.as_deref().map(escaped_str_len).unwrap_or(0)
+                    }
+                };
+
+                SESSION_DB_FIXED_OVERHEAD
+                    + (3 * SESSION_DB_TIMESTAMP_SIZE)
+                    + escaped_str_len(&session.label)
+                    + session.bound_to.as_deref().map(escaped_str_len).unwrap_or(0)
+                    + ext_metadata
+            })
+            .sum::<usize>()
+            // The enclosing set.
+            + SESSION_DB_FIXED_OVERHEAD
+    }
+
+    /// Count the sessions in this set as `(live, revoked)`, where live is as per `is_live`
+    /// at `now`. Revoked sessions are those awaiting trimming.
+    pub fn count_by_state(&self, now: OffsetDateTime) -> (usize, usize) {
+        self.map.values().fold((0, 0), |(live, revoked), session| {
+            match session.effective_state() {
+                SessionState::RevokedAt(_) => (live, revoked + 1),
+                SessionState::ExpiresAt(exp) if exp <= now => (live, revoked),
+                _ if session.is_disabled() => (live, revoked),
+                _ => (live + 1, revoked),
+            }
+        })
+    }
+
+    /// The ids of sessions that were issued more than `tolerance` after `now`, in session
+    /// id order. These indicate a server with a skewed clock, and as they sort as the most
+    /// recent sessions they would never be trimmed. This does not alter the sessions.
+    pub fn future_dated_sessions(&self, now: OffsetDateTime, tolerance: Duration) -> Vec<Uuid> {
+        // A tolerance too large to represent can't be exceeded.
+        let Some(limit) = time::Duration::try_from(tolerance)
+            .ok()
+            .and_then(|tolerance| now.checked_add(tolerance))
+        else {
+            return Vec::new();
+        };
+
+        self.map
+            .iter()
+            .filter(|(_, session)| session.issued_at > limit)
+            .map(|(session_id, _)| *session_id)
+            .collect()
+    }
+
+    /// Split the sessions in this set into those that are active at `now`, and those
+    /// that are not because they have been revoked or have expired. Both are in session
+    /// id order.
+    pub fn partition_by_state(
+        &self,
+        now: OffsetDateTime,
+    ) -> (Vec<(Uuid, &Session)>, Vec<(Uuid, &Session)>) {
+        self.map
+            .iter()
+            .map(|(u, s)| (*u, s))
+            .partition(|(_, session)| match &session.state {
+                SessionState::ExpiresAt(exp) => now < *exp,
+                SessionState::NeverExpires => true,
+                SessionState::RevokedAt(_) => false,
+            })
+    }
+
+    /// As per `purge`, revoking all sessions at `cid`, but returns the number of sessions
+    /// that were revoked. Sessions that were already revoked are not counted.
+    pub fn purge_counted(&mut self, cid: &Cid) -> usize {
+        let mut count = 0;
+        for (_uuid, session) in self.map.iter_mut() {
+            // Send them all to the shadow realm
+            if !matches!(session.state, SessionState::RevokedAt(_)) {
+                session.state = SessionState::RevokedAt(cid.clone());
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Revoke every session that was issued with the credential `cred_id`, returning the
+    /// number of sessions that were revoked. Sessions that are already revoked retain their
+    /// existing revocation.
+    pub fn revoke_by_cred_id(&mut self, cred_id: Uuid, cid: &Cid) -> usize {
+        self.revoke_by_cred_ids(&BTreeSet::from([cred_id]), cid)
+    }
+
+    /// As per `revoke_by_cred_id`, but revoking the sessions of every credential in
+    /// `compromised` at once.
+    pub fn revoke_by_cred_ids(&mut self, compromised: &BTreeSet<Uuid>, cid: &Cid) -> usize {
+        let mut revoked = 0;
+        for session in self.map.values_mut() {
+            if compromised.contains(&session.cred_id)
+                && !matches!(session.state, SessionState::RevokedAt(_))
+            {
+                session.state = SessionState::RevokedAt(cid.clone());
+                revoked += 1;
+            }
+        }
+        revoked
+    }
+
+    /// Move the active sessions issued with the credential `from` to the credential `to`,
+    /// such as when a credential is replaced but its sessions should remain valid. Returns
+    /// the number of sessions that were moved.
+    pub fn rebind_cred_id(&mut self, from: Uuid, to: Uuid) -> usize {
+        let mut moved = 0;
+        for session in self.map.values_mut() {
+            if session.cred_id == from && !matches!(session.state, SessionState::RevokedAt(_)) {
+                session.cred_id = to;
+                moved += 1;
+            }
+        }
+        moved
+    }
+
+    /// As per `rebind_cred_id`, but only for the session `session_id`, such as when the
+    /// session that replaced a credential is retained. Returns true if the session was moved.
+    pub fn rebind_session_cred_id(&mut self, session_id: &Uuid, from: Uuid, to: Uuid) -> bool {
+        match self.map.get_mut(session_id) {
+            Some(session)
+                if session.cred_id == from
+                    && !matches!(session.state, SessionState::RevokedAt(_)) =>
+            {
+                session.cred_id = to;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Disable every session that is neither revoked nor already disabled at `cid`, such as
+    /// when the account is locked. Returns the number of sessions that were disabled.
+    pub fn disable_all(&mut self, cid: &Cid) -> usize {
+        let mut disabled = 0;
+        for session in self.map.values_mut() {
+            if !matches!(session.state, SessionState::RevokedAt(_)) && !session.is_disabled() {
+                session.disabled = Some(SessionDisabled::DisabledAt(cid.clone()));
+                disabled += 1;
+            }
+        }
+        disabled
+    }
+
+    /// Enable every disabled session that is not revoked at `cid`, such as when the account
+    /// is unlocked. Returns the number of sessions that were enabled.
+    pub fn enable_all(&mut self, cid: &Cid) -> usize {
+        let mut enabled = 0;
+        for session in self.map.values_mut() {
+            if !matches!(session.state, SessionState::RevokedAt(_)) && session.is_disabled() {
+                session.disabled = Some(SessionDisabled::EnabledAt(cid.clone()));
+                enabled += 1;
+            }
+        }
+        enabled
+    }
+
+    /// Record that this session was used at `now`. Revoked sessions are never updated, and
+    /// the recorded activity only moves forward. Returns true if the session was changed.
+    pub fn record_activity(&mut self, session_id: &Uuid, now: OffsetDateTime) -> bool {
+        let Some(session) = self.map.get_mut(session_id) else {
+            return false;
+        };
+
+        if matches!(session.state, SessionState::RevokedAt(_)) {
+            return false;
+        }
+
+        let now = Some(now.to_offset(time::UtcOffset::UTC));
+        if session.last_active >= now {
+            return false;
+        }
+
+        session.last_active = now;
+        true
+    }
+
+    /// Extend the expiry of this session to `new_expiry`, but never beyond `max_lifetime`
+    /// from when it was issued. The expiry only moves forward, and revoked or never expiring
+    /// sessions are not renewed. Returns true if the session was changed.
+    pub fn renew(
+        &mut self,
+        session_id: &Uuid,
+        new_expiry: OffsetDateTime,
+        max_lifetime: Duration,
+    ) -> Result<bool, OperationError> {
+        let Some(session) = self.map.get_mut(session_id) else {
+            return Err(OperationError::NoMatchingEntries);
+        };
+
+        let SessionState::ExpiresAt(current_expiry) = session.state else {
+            return Ok(false);
+        };
+
+        // A lifetime too large to represent places no bound on the expiry.
+        let ceiling = time::Duration::try_from(max_lifetime)
+            .ok()
+            .and_then(|max_lifetime| session.issued_at.checked_add(max_lifetime));
+
+        let expiry = [ceiling, session.lifetime_ceiling()]
+            .into_iter()
+            .flatten()
+            .fold(new_expiry.to_offset(time::UtcOffset::UTC), std::cmp::min);
+
+        if expiry <= current_expiry {
+            return Ok(false);
+        }
+
+        session.state = SessionState::ExpiresAt(expiry);
+        Ok(true)
+    }
+
+    /// Count the sessions issued at or after `since`, regardless of their current state.
+    /// This allows callers to rate limit issuance, the set itself enforces no policy.
+    pub fn recent_issue_count(&self, since: OffsetDateTime) -> usize {
+        self.map.values().filter(|s| s.issued_at >= since).count()
+    }
+
+    /// Summarise the sessions of this set at `now` for SCIM, as a lighter alternative to
+    /// the full list from `to_scim_value`. Each session is counted in exactly one of
+    /// active, revoked, expired or disabled.
+    pub fn to_scim_summary(&self, now: OffsetDateTime) -> ScimResolveStatus {
+        let mut summary = ScimSessionSummary {
+            total: self.map.len(),
+            by_auth_type: self
+                .auth_type_histogram(true)
+                .into_iter()
+                .map(|(type_, count)| (type_.to_string(), count))
+                .collect(),
+            ..Default::default()
+        };
+
+        for session in self.map.values() {
+            match session.effective_state() {
+                SessionState::RevokedAt(_) => summary.revoked += 1,
+                SessionState::ExpiresAt(exp) if exp <= now => summary.expired += 1,
+                _ if session.is_disabled() => summary.disabled += 1,
+                _ => summary.active += 1,
+            }
+        }
+
+        ScimResolveStatus::Resolved(ScimValueKanidm::from(summary))
+    }
+
+    /// Count the sessions in this set by the type of authentication that created them.
+    /// Sessions of service accounts are counted as [`AuthType::ServiceAccount`], so that
+    /// machine sessions are distinct from those of people. Revoked sessions are only
+    /// counted if `include_revoked` is set.
+    pub fn auth_type_histogram(&self, include_revoked: bool) -> BTreeMap<AuthType, usize> {
+        let mut histogram = BTreeMap::new();
+        self.map
+            .values()
+            .filter(|session| {
+                include_revoked || !matches!(session.state, SessionState::RevokedAt(_))
+            })
+            .for_each(|session| {
+                let type_ = if session.service_account {
+                    AuthType::ServiceAccount
+                } else {
+                    session.type_
+                };
+                *histogram.entry(type_).or_insert(0) += 1;
+            });
+        histogram
+    }
+
+    /// Group the sessions in this set that have not been revoked by the identity that
+    /// issued them. Each group is in session id order.
+    pub fn sessions_by_issuer(&self) -> BTreeMap<IdentityId, Vec<Uuid>> {
+        let mut by_issuer: BTreeMap<IdentityId, Vec<Uuid>> = BTreeMap::new();
+        self.map
+            .iter()
+            .filter(|(_, session)| !matches!(session.state, SessionState::RevokedAt(_)))
+            .for_each(|(session_id, session)| {
+                by_issuer
+                    .entry(session.issued_by.clone())
+                    .or_default()
+                    .push(*session_id);
+            });
+        by_issuer
+    }
+}
+
+impl ValueSetT for ValueSetSession {
+    fn insert_checked(&mut self, value: Value) -> Result<bool, OperationError> {
+        match value {
+            Value::Session(u, m) => {
+                let mut m = m.into_utc();
+                if !session_scope_permitted(&m) {
+                    error!(session_id = %u, type_ = %m.type_, scope = %m.scope, "Refusing to insert session with a scope its auth type may not hold");
+                    return Err(OperationError::InvalidValueState);
+                }
+
+                if let BTreeEntry::Vacant(e) = self.map.entry(u) {
+                    clamp_session_lifetime(u, &mut m);
+                    e.insert(m);
+                    Ok(true)
+                } else if let Some(last_active) = m.last_active {
+                    // Only the activity of an existing session may be updated.
+                    Ok(self.record_activity(&u, last_active))
+                } else {
+                    Ok(false)
+                }
+            }
+            _ => Err(OperationError::InvalidValueState),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    fn remove(&mut self, pv: &PartialValue, cid: &Cid) -> bool {
+        match pv {
+            PartialValue::Refer(u) => {
+                if let Some(session) = self.map.get_mut(u) {
+                    if !matches!(session.state, SessionState::RevokedAt(_)) {
+                        session.state = SessionState::RevokedAt(cid.clone());
+                        true
+                    } else {
+                        false
+                    }
+                } else {
+                    false
+                }
+            }
+            _ => false,
+        }
+    }
+
+    fn purge(&mut self, cid: &Cid) -> bool {
+        self.purge_counted(cid);
+        // Can't be purged since we need the cid's of revoked to persist.
+        false
+    }
+
+    fn trim(&mut self, trim_cid: &Cid) {
+        self.trim_with_policy(trim_cid, &TrimPolicy::default())
+    }
+
+    fn contains(&self, pv: &PartialValue) -> bool {
+        match pv {
+            PartialValue::Refer(u) => self.map.contains_key(u),
+            _ => false,
+        }
+    }
 
     fn substring(&self, _pv: &PartialValue) -> bool {
         false
@@ -352,8 +1571,13 @@ impl ValueSetT for ValueSetSession {
         false
     }
 
-    fn lessthan(&self, _pv: &PartialValue) -> bool {
-        false
+    fn lessthan(&self, pv: &PartialValue) -> bool {
+        datetime_lessthan_any(
+            self.map
+                .values()
+                .filter_map(|v| session_state_expiry(&v.state)),
+            pv,
+        )
     }
 
     fn len(&self) -> usize {
@@ -361,10 +1585,9 @@ impl ValueSetT for ValueSetSession {
     }
 
     fn generate_idx_eq_keys(&self) -> Vec<String> {
-        self.map
-            .keys()
-            .map(|u| u.as_hyphenated().to_string())
-            .collect()
+        let mut idx_keys = Vec::with_capacity(self.capacity());
+        idx_keys.extend(self.map.keys().map(|u| u.as_hyphenated().to_string()));
+        idx_keys
     }
 
     fn syntax(&self) -> SyntaxType {
@@ -375,6 +1598,15 @@ impl ValueSetT for ValueSetSession {
         true
     }
 
+    fn validate_maximum_values(&self, schema_attr: &SchemaAttribute) -> bool {
+        warn_exceeds_maximum_values(self.map.len(), schema_attr);
+        true
+    }
+
+    fn trim_to_maximum(&mut self, maximum: usize, _cid: &Cid, _trim_cid: &Cid) -> usize {
+        self.force_trim(maximum)
+    }
+
     fn to_proto_string_clone_iter(&self) -> Box<dyn Iterator<Item = String> + '_> {
         Box::new(
             self.map
@@ -385,28 +1617,40 @@ impl ValueSetT for ValueSetSession {
 
     fn to_scim_value(&self) -> Option<ScimResolveStatus> {
         Some(ScimResolveStatus::Resolved(ScimValueKanidm::from(
-            self.map
-                .iter()
+            self.sessions_by_recency()
+                .into_iter()
                 .map(|(session_id, session)| {
-                    let (expires, revoked) = match &session.state {
-                        SessionState::ExpiresAt(odt) => (Some(*odt), None),
-                        SessionState::NeverExpires => (None, None),
+                    let (expires, revoked, revoked_by_server) = match session.effective_state() {
+                        SessionState::ExpiresAt(odt) => (Some(odt), None, None),
+                        SessionState::NeverExpires => (None, None, None),
                         SessionState::RevokedAt(cid) => {
-                            let odt: OffsetDateTime = cid.into();
-                            (None, Some(odt))
+                            (None, Some(revoked_at_timestamp(&cid)), Some(cid.s_uuid))
                         }
                     };
 
                     ScimAuthSession {
-                        id: *session_id,
+                        id: session_id,
+                        label: session.label.clone(),
                         expires,
                         revoked,
+                        revoked_by_server,
 
                         issued_at: session.issued_at,
                         issued_by: Uuid::from(&session.issued_by),
+                        issued_by_type: session.issued_by.kind().to_string(),
                         credential_id: session.cred_id,
                         auth_type: session.type_.to_string(),
                         session_scope: session.scope.to_string(),
+                        bound_to: session.bound_to.clone(),
+                        last_active: session.last_active,
+                        disabled: match &session.disabled {
+                            Some(SessionDisabled::DisabledAt(cid)) => {
+                                Some(revoked_at_timestamp(cid))
+                            }
+                            Some(SessionDisabled::EnabledAt(_)) | None => None,
+                        },
+                        pinned: session.pinned,
+                        service_account: session.service_account,
                     }
                 })
                 .collect::<Vec<_>>(),
@@ -425,6 +1669,13 @@ impl ValueSetT for ValueSetSession {
         Box::new(self.map.iter().map(|(u, m)| Value::Session(*u, m.clone())))
     }
 
+    fn get_value(&self, pv: &PartialValue) -> Option<Value> {
+        match pv {
+            PartialValue::Refer(u) => self.map.get(u).map(|m| Value::Session(*u, m.clone())),
+            _ => None,
+        }
+    }
+
     fn equal(&self, other: &ValueSet) -> bool {
         if let Some(other) = other.as_session_map() {
             &self.map == other
@@ -435,64 +1686,28 @@ impl ValueSetT for ValueSetSession {
     }
 
     fn merge(&mut self, other: &ValueSet) -> Result<(), OperationError> {
-        if let Some(b) = other.as_session_map() {
-            // We can't just do merge maps here, we have to be aware of the
-            // session.state value and what it currently is set to.
-            for (k_other, v_other) in b.iter() {
-                if let Some(v_self) = self.map.get_mut(k_other) {
-                    // We only update if greater. This is where RevokedAt
-                    // always proceeds other states, and lower revoked
-                    // cids will always take effect.
-                    if v_other.state > v_self.state {
-                        *v_self = v_other.clone();
-                    }
-                } else {
-                    // Not present, just insert.
-                    self.map.insert(*k_other, v_other.clone());
-                }
-            }
-            Ok(())
-        } else {
-            debug_assert!(false);
-            Err(OperationError::InvalidValueState)
-        }
+        self.merge_with_observer(other, None)
     }
 
     fn as_session_map(&self) -> Option<&BTreeMap<Uuid, Session>> {
         Some(&self.map)
     }
 
+    fn as_session_set(&self) -> Option<&ValueSetSession> {
+        Some(self)
+    }
+
+    fn as_session_set_mut(&mut self) -> Option<&mut ValueSetSession> {
+        Some(self)
+    }
+
     fn as_ref_uuid_iter(&self) -> Option<Box<dyn Iterator<Item = Uuid> + '_>> {
         // This is what ties us as a type that can be refint checked.
         Some(Box::new(self.map.keys().copied()))
     }
 
     fn repl_merge_valueset(&self, older: &ValueSet, trim_cid: &Cid) -> Option<ValueSet> {
-        // If the older value has a different type - return nothing, we
-        // just take the newer value.
-        let b = older.as_session_map()?;
-        // We can't just do merge maps here, we have to be aware of the
-        // session.state value and what it currently is set to.
-        let mut map = self.map.clone();
-        for (k_other, v_other) in b.iter() {
-            if let Some(v_self) = map.get_mut(k_other) {
-                // We only update if lower. This is where RevokedAt
-                // always proceeds other states, and lower revoked
-                // cids will always take effect.
-                if v_other.state > v_self.state {
-                    *v_self = v_other.clone();
-                }
-            } else {
-                // Not present, just insert.
-                map.insert(*k_other, v_other.clone());
-            }
-        }
-
-        let mut vs = Box::new(ValueSetSession { map });
-
-        vs.trim(trim_cid);
-
-        Some(vs)
+        self.repl_merge_valueset_with_observer(older, trim_cid, None)
     }
 }
 
@@ -511,6 +1726,7 @@ pub struct ValueSetOauth2Session {
 
 impl ValueSetOauth2Session {
     pub fn new(u: Uuid, m: Oauth2Session) -> Box<Self> {
+        let m = m.into_utc();
         let mut map = BTreeMap::new();
         let rs_filter = m.rs_uuid.as_u128();
         map.insert(u, m);
@@ -519,182 +1735,46 @@ impl ValueSetOauth2Session {
 
     pub fn push(&mut self, u: Uuid, m: Oauth2Session) -> bool {
         self.rs_filter |= m.rs_uuid.as_u128();
-        self.map.insert(u, m).is_none()
+        self.map.insert(u, m.into_utc()).is_none()
     }
 
-    pub fn from_dbvs2(data: Vec<DbValueOauth2Session>) -> Result<ValueSet, OperationError> {
+    /// Insert all of `items`, updating the rs_filter once for the batch. Returns the
+    /// number of sessions that were newly inserted, rather than replacing a session
+    /// with the same id.
+    pub fn extend(&mut self, items: impl IntoIterator<Item = (Uuid, Oauth2Session)>) -> usize {
         let mut rs_filter = u128::MIN;
-        let map = data
-            .into_iter()
-            .filter_map(|dbv| {
-                match dbv {
-                    DbValueOauth2Session::V1 {
-                        refer,
-                        parent,
-                        expiry,
-                        issued_at,
-                        rs_uuid,
-                    } => {
-                        // Convert things.
-                        let issued_at = OffsetDateTime::parse(&issued_at, &Rfc3339)
-                            .map(|odt| odt.to_offset(time::UtcOffset::UTC))
-                            .map_err(|e| {
-                                admin_error!(
-                                    ?e,
-                                    "Invalidating session {} due to invalid issued_at timestamp",
-                                    refer
-                                )
-                            })
-                            .ok()?;
-
-                        // This is a bit annoying. In the case we can't parse the optional
-                        // expiry, we need to NOT return the session so that it's immediately
-                        // invalidated. To do this we have to invert some of the options involved
-                        // here.
-                        let expiry = expiry
-                            .map(|e_inner| {
-                                OffsetDateTime::parse(&e_inner, &Rfc3339)
-                                    .map(|odt| odt.to_offset(time::UtcOffset::UTC))
-                                // We now have an
-                                // Option<Result<ODT, _>>
-                            })
-                            .transpose()
-                            // Result<Option<ODT>, _>
-                            .map_err(|e| {
-                                admin_error!(
-                                    ?e,
-                                    "Invalidating session {} due to invalid expiry timestamp",
-                                    refer
-                                )
-                            })
-                            // Option<Option<ODT>>
-                            .ok()?;
+        let mut inserted = 0;
 
-                        let state = expiry
-                            .map(SessionState::ExpiresAt)
-                            .unwrap_or(SessionState::NeverExpires);
-
-                        let parent = Some(parent);
-
-                        // Insert to the rs_filter.
-                        rs_filter |= rs_uuid.as_u128();
-                        Some((
-                            refer,
-                            Oauth2Session {
-                                parent,
-                                state,
-                                issued_at,
-                                rs_uuid,
-                            },
-                        ))
-                    }
-                    DbValueOauth2Session::V2 {
-                        refer,
-                        parent,
-                        state,
-                        issued_at,
-                        rs_uuid,
-                    } => {
-                        // Convert things.
-                        let issued_at = OffsetDateTime::parse(&issued_at, &Rfc3339)
-                            .map(|odt| odt.to_offset(time::UtcOffset::UTC))
-                            .map_err(|e| {
-                                admin_error!(
-                                    ?e,
-                                    "Invalidating session {} due to invalid issued_at timestamp",
-                                    refer
-                                )
-                            })
-                            .ok()?;
-
-                        let state = match state {
-                            DbValueSessionStateV1::ExpiresAt(e_inner) => {
-                                OffsetDateTime::parse(&e_inner, &Rfc3339)
-                                    .map(|odt| odt.to_offset(time::UtcOffset::UTC))
-                                    .map(SessionState::ExpiresAt)
-                                    .map_err(|e| {
-                                        admin_error!(
-                                    ?e,
-                                    "Invalidating session {} due to invalid expiry timestamp",
-                                    refer
-                                )
-                                    })
-                                    .ok()?
-                            }
-                            DbValueSessionStateV1::Never => SessionState::NeverExpires,
-                            DbValueSessionStateV1::RevokedAt(dc) => SessionState::RevokedAt(Cid {
-                                s_uuid: dc.server_id,
-                                ts: dc.timestamp,
-                            }),
-                        };
+        for (u, m) in items {
+            rs_filter |= m.rs_uuid.as_u128();
+            if self.map.insert(u, m.into_utc()).is_none() {
+                inserted += 1;
+            }
+        }
 
-                        rs_filter |= rs_uuid.as_u128();
-
-                        let parent = Some(parent);
-
-                        Some((
-                            refer,
-                            Oauth2Session {
-                                parent,
-                                state,
-                                issued_at,
-                                rs_uuid,
-                            },
-                        ))
-                    } // End V2
-                    DbValueOauth2Session::V3 {
-                        refer,
-                        parent,
-                        state,
-                        issued_at,
-                        rs_uuid,
-                    } => {
-                        // Convert things.
-                        let issued_at = OffsetDateTime::parse(&issued_at, &Rfc3339)
-                            .map(|odt| odt.to_offset(time::UtcOffset::UTC))
-                            .map_err(|e| {
-                                admin_error!(
-                                    ?e,
-                                    "Invalidating session {} due to invalid issued_at timestamp",
-                                    refer
-                                )
-                            })
-                            .ok()?;
-
-                        let state = match state {
-                            DbValueSessionStateV1::ExpiresAt(e_inner) => {
-                                OffsetDateTime::parse(&e_inner, &Rfc3339)
-                                    .map(|odt| odt.to_offset(time::UtcOffset::UTC))
-                                    .map(SessionState::ExpiresAt)
-                                    .map_err(|e| {
-                                        admin_error!(
-                                    ?e,
-                                    "Invalidating session {} due to invalid expiry timestamp",
-                                    refer
-                                )
-                                    })
-                                    .ok()?
-                            }
-                            DbValueSessionStateV1::Never => SessionState::NeverExpires,
-                            DbValueSessionStateV1::RevokedAt(dc) => SessionState::RevokedAt(Cid {
-                                s_uuid: dc.server_id,
-                                ts: dc.timestamp,
-                            }),
-                        };
+        self.rs_filter |= rs_filter;
 
-                        rs_filter |= rs_uuid.as_u128();
-
-                        Some((
-                            refer,
-                            Oauth2Session {
-                                parent,
-                                state,
-                                issued_at,
-                                rs_uuid,
-                            },
-                        ))
-                    } // End V3
-                }
+        debug_assert!(self.map.values().all(|m| {
+            let u_int = m.rs_uuid.as_u128();
+            self.rs_filter & u_int == u_int
+        }));
+
+        inserted
+    }
+
+    pub fn from_dbvs2(data: Vec<DbValueOauth2Session>) -> Result<ValueSet, OperationError> {
+        let migrations = data.iter().filter(|dbv| dbv.needs_migration()).count();
+        if migrations > 0 {
+            debug!(%migrations, "Oauth2 sessions will be upgraded on next write");
+        }
+
+        let mut rs_filter = u128::MIN;
+        let map = data
+            .into_iter()
+            .filter_map(dbv_to_oauth2_session)
+            .map(|(refer, m)| {
+                rs_filter |= m.rs_uuid.as_u128();
+                (refer, m)
             })
             .collect();
         Ok(Box::new(ValueSetOauth2Session { map, rs_filter }))
@@ -712,37 +1792,301 @@ impl ValueSetOauth2Session {
             .into_iter()
             .map(|(u, m)| {
                 rs_filter |= m.rs_uuid.as_u128();
-                (u, m)
+                (u, m.into_utc())
             })
             .collect();
         Some(Box::new(ValueSetOauth2Session { map, rs_filter }))
     }
+
+    /// Report the number of bits set in the resource server filter, and the fraction of
+    /// the filter that is set. A highly saturated filter will often yield false positives,
+    /// causing lookups by resource server to scan the whole set.
+    pub fn rs_filter_stats(&self) -> (u32, f32) {
+        let set_bits = self.rs_filter.count_ones();
+        (set_bits, set_bits as f32 / u128::BITS as f32)
+    }
+
+    /// Recompute the resource server filter from the sessions in this set.
+    pub fn rebuild_rs_filter(&mut self) {
+        self.rs_filter = self
+            .map
+            .values()
+            .fold(u128::MIN, |acc, session| acc | session.rs_uuid.as_u128());
+    }
+
+    /// Count the sessions with the resource server `rs_uuid` that are neither revoked
+    /// nor expired at `current_time`.
+    pub fn count_by_rs(&self, rs_uuid: Uuid, current_time: OffsetDateTime) -> usize {
+        // If the filter doesn't contain this resource server, there are no sessions for it.
+        let u_int = rs_uuid.as_u128();
+        if self.rs_filter & u_int != u_int {
+            return 0;
+        }
+
+        self.map
+            .values()
+            .filter(|session| {
+                session.rs_uuid == rs_uuid && Self::is_active(&session.state, current_time)
+            })
+            .count()
+    }
+
+    /// Count the sessions that are neither revoked nor expired at `current_time`, grouped
+    /// by resource server.
+    pub fn count_by_rs_iter(
+        &self,
+        current_time: OffsetDateTime,
+    ) -> impl Iterator<Item = (Uuid, usize)> {
+        let mut counts = BTreeMap::new();
+        self.map
+            .values()
+            .filter(|session| Self::is_active(&session.state, current_time))
+            .for_each(|session| {
+                *counts.entry(session.rs_uuid).or_insert(0) += 1;
+            });
+        counts.into_iter()
+    }
+
+    fn is_active(state: &SessionState, current_time: OffsetDateTime) -> bool {
+        match state {
+            SessionState::ExpiresAt(exp) => *exp > current_time,
+            SessionState::NeverExpires => true,
+            SessionState::RevokedAt(_) => false,
+        }
+    }
+
+    /// Determine if an unrevoked session has a parent that is not in the set of live
+    /// parents. The parent may not have been recorded yet when the session is issued, so
+    /// sessions are only orphaned once `grace` has passed since their issue at `now`.
+    fn is_orphaned(
+        session: &Oauth2Session,
+        live_parents: &BTreeSet<Uuid>,
+        now: OffsetDateTime,
+        grace: Duration,
+    ) -> bool {
+        !matches!(session.state, SessionState::RevokedAt(_))
+            && session
+                .parent
+                .as_ref()
+                .is_some_and(|parent| !live_parents.contains(parent))
+            // A grace window that runs past the end of time never closes.
+            && time::Duration::try_from(grace)
+                .ok()
+                .and_then(|grace| session.issued_at.checked_add(grace))
+                .is_some_and(|end| end <= now)
+    }
+
+    /// Determine if any session would be revoked by `prune_orphaned`.
+    pub fn has_orphaned(
+        &self,
+        live_parents: &BTreeSet<Uuid>,
+        now: OffsetDateTime,
+        grace: Duration,
+    ) -> bool {
+        self.map
+            .values()
+            .any(|session| Self::is_orphaned(session, live_parents, now, grace))
+    }
+
+    /// Revoke any oauth2 session whose parent session is not in the set of live
+    /// parents, once the grace window since it was issued has passed at `now`. Sessions
+    /// without a parent are not bound to a primary session and are always retained.
+    /// Returns the number of sessions revoked.
+    pub fn prune_orphaned(
+        &mut self,
+        live_parents: &BTreeSet<Uuid>,
+        now: OffsetDateTime,
+        grace: Duration,
+        cid: &Cid,
+    ) -> usize {
+        let mut revoked = 0;
+        for (session_id, session) in self.map.iter_mut() {
+            if Self::is_orphaned(session, live_parents, now, grace) {
+                info!(%session_id, parent_id = ?session.parent, "Revoking orphaned oauth2 session");
+                session.state = SessionState::RevokedAt(cid.clone());
+                revoked += 1;
+            }
+        }
+        revoked
+    }
+
+    /// Determine if the session `session_id` exists and is neither revoked nor expired
+    /// at `now`. Unlike `contains`, this accounts for the state of the session.
+    pub fn is_live(&self, session_id: &Uuid, now: OffsetDateTime) -> bool {
+        self.map
+            .get(session_id)
+            .is_some_and(|session| Self::is_active(&session.state, now))
+    }
+
+    /// Record that the refresh token of this session was exchanged at `now`. Revoked
+    /// sessions are never updated. Returns true if the session was changed.
+    pub fn record_refresh(&mut self, session_id: &Uuid, now: OffsetDateTime) -> bool {
+        let Some(session) = self.map.get_mut(session_id) else {
+            return false;
+        };
+
+        if matches!(session.state, SessionState::RevokedAt(_)) {
+            return false;
+        }
+
+        let now = Some(now.to_offset(time::UtcOffset::UTC));
+        if session.has_refresh && session.last_refreshed >= now {
+            return false;
+        }
+
+        session.has_refresh = true;
+        session.last_refreshed = session.last_refreshed.max(now);
+        true
+    }
+
+    /// Revoke all but the newest issued session of each lineage, where a lineage is
+    /// the set of non-revoked sessions sharing a parent session and resource server.
+    /// Sessions without a parent are not part of a lineage and are always retained.
+    /// Returns the number of sessions revoked.
+    pub fn coalesce_lineage(&mut self, cid: &Cid) -> usize {
+        // The newest session of each lineage. Ties on issue time are broken by the
+        // session id so that every replica retains the same session.
+        let mut newest: BTreeMap<(Uuid, Uuid), (OffsetDateTime, Uuid)> = BTreeMap::new();
+        for (session_id, session) in self.map.iter() {
+            if matches!(session.state, SessionState::RevokedAt(_)) {
+                continue;
+            }
+            let Some(parent) = session.parent else {
+                continue;
+            };
+
+            let candidate = (session.issued_at, *session_id);
+            newest
+                .entry((parent, session.rs_uuid))
+                .and_modify(|current| *current = (*current).max(candidate))
+                .or_insert(candidate);
+        }
+
+        // Revoked sessions are retained, so the rs_filter remains accurate.
+        let mut revoked = 0;
+        for (session_id, session) in self.map.iter_mut() {
+            if matches!(session.state, SessionState::RevokedAt(_)) {
+                continue;
+            }
+            let Some(parent) = session.parent else {
+                continue;
+            };
+
+            if newest
+                .get(&(parent, session.rs_uuid))
+                .is_some_and(|(_, newest_id)| newest_id != session_id)
+            {
+                debug!(?session_id, ?parent, "revoking superseded oauth2 session");
+                session.state = SessionState::RevokedAt(cid.clone());
+                revoked += 1;
+            }
+        }
+        revoked
+    }
+}
+
+/// Revoke the auth session `session_id` along with every oauth2 session it is the parent
+/// of, at `cid`. Returns the number of sessions that were revoked, which excludes those
+/// that were already revoked.
+pub fn cascade_revoke(
+    sessions: &mut ValueSetSession,
+    oauth2: &mut ValueSetOauth2Session,
+    session_id: &Uuid,
+    cid: &Cid,
+) -> usize {
+    let mut revoked = usize::from(sessions.remove(&PartialValue::Refer(*session_id), cid));
+
+    let children: Vec<_> = oauth2
+        .map
+        .iter()
+        .filter(|(_, session)| session.parent.as_ref() == Some(session_id))
+        .map(|(o2_session_id, _)| *o2_session_id)
+        .collect();
+
+    for o2_session_id in children {
+        if oauth2.remove(&PartialValue::Refer(o2_session_id), cid) {
+            debug!(?o2_session_id, parent = ?session_id, "revoking oauth2 session with parent");
+            revoked += 1;
+        }
+    }
+
+    revoked
 }
 
 impl ValueSetT for ValueSetOauth2Session {
     fn insert_checked(&mut self, value: Value) -> Result<bool, OperationError> {
         match value {
             Value::Oauth2Session(u, m) => {
+                let mut m = m.into_utc();
+                // A refresh is recorded separately once the session is in place.
+                let refreshed_at = m.last_refreshed.take();
+
                 // Unlike other types, this allows overwriting as oauth2 sessions
                 // can be *extended* in time length.
-                match self.map.entry(u) {
+                let changed = match self.map.entry(u) {
                     BTreeEntry::Vacant(e) => {
                         self.rs_filter |= m.rs_uuid.as_u128();
                         e.insert(m);
-                        Ok(true)
+                        true
                     }
                     BTreeEntry::Occupied(mut e) => {
                         let e_v = e.get_mut();
-                        if m.state > e_v.state {
-                            // Replace if the state has higher priority.
-                            *e_v = m;
-                            Ok(true)
-                        } else {
-                            // Else take no action.
-                            Ok(false)
+
+                        let replace = match (&e_v.state, &m.state) {
+                            // A revocation always applies, unless an earlier revocation
+                            // is already present.
+                            (_, SessionState::RevokedAt(_)) => m.state > e_v.state,
+                            // A revoked session can never be extended.
+                            (SessionState::RevokedAt(_), _) => false,
+                            // Extension must strictly move the expiry forward, so that a
+                            // replay of an older value can't shorten the session.
+                            (SessionState::ExpiresAt(e_exp), SessionState::ExpiresAt(m_exp)) => {
+                                m_exp > e_exp
+                            }
+                            // Nothing else may change the length of the session.
+                            _ => false,
+                        };
+
+                        let revoking = matches!(m.state, SessionState::RevokedAt(_));
+
+                        // A grant that extends the session may not broaden its scopes, else
+                        // a refresh could be used to escalate privileges. Sessions that
+                        // predate scope tracking have no scopes recorded, and are exempt.
+                        if replace
+                            && !revoking
+                            && !e_v.granted_scopes.is_empty()
+                            && !m.granted_scopes.is_subset(&e_v.granted_scopes)
+                        {
+                            error!(
+                                session_id = %u,
+                                "Refusing to broaden the granted scopes of oauth2 session"
+                            );
+                            return Err(OperationError::InvalidValueState);
+                        }
+
+                        if replace {
+                            // Only the state and scopes are updated. The issue time and
+                            // parent are fixed for the life of the session.
+                            e_v.state = m.state;
+                            // The union of the granted scopes is kept, so that consent
+                            // recorded for the session is never lost.
+                            if !revoking {
+                                e_v.granted_scopes.extend(m.granted_scopes);
+                            }
+                            e_v.has_refresh |= m.has_refresh;
                         }
+
+                        replace
+                    }
+                };
+
+                if changed {
+                    if let Some(refreshed_at) = refreshed_at {
+                        self.record_refresh(&u, refreshed_at);
                     }
                 }
+
+                Ok(changed)
             }
             _ => Err(OperationError::InvalidValueState),
         }
@@ -769,9 +2113,12 @@ impl ValueSetT for ValueSetOauth2Session {
                     if self.rs_filter & u_int == u_int {
                         // It's there, so we need to do a more costly revoke over the values
                         // that are present.
+                        // Sessions that are already revoked retain their original revocation.
                         let mut removed = false;
                         self.map.values_mut().for_each(|session| {
-                            if session.rs_uuid == *u {
+                            if session.rs_uuid == *u
+                                && !matches!(session.state, SessionState::RevokedAt(_))
+                            {
                                 session.state = SessionState::RevokedAt(cid.clone());
                                 removed = true;
                             }
@@ -817,9 +2164,29 @@ impl ValueSetT for ValueSetOauth2Session {
     }
 
     fn contains(&self, pv: &PartialValue) -> bool {
+        // This reflects the presence of the session or resource server, regardless
+        // of the session state. This matches the index keys we generate.
         match pv {
             PartialValue::Refer(u) => {
                 self.map.contains_key(u) || {
+                    let u_int = u.as_u128();
+                    if self.rs_filter & u_int == u_int {
+                        self.map.values().any(|session| session.rs_uuid == *u)
+                    } else {
+                        false
+                    }
+                }
+            }
+            _ => false,
+        }
+    }
+
+    fn contains_active(&self, pv: &PartialValue) -> bool {
+        match pv {
+            PartialValue::Refer(u) => {
+                if let Some(session) = self.map.get(u) {
+                    !matches!(session.state, SessionState::RevokedAt(_))
+                } else {
                     let u_int = u.as_u128();
                     if self.rs_filter & u_int == u_int {
                         self.map.values().any(|session| {
@@ -847,8 +2214,13 @@ impl ValueSetT for ValueSetOauth2Session {
         false
     }
 
-    fn lessthan(&self, _pv: &PartialValue) -> bool {
-        false
+    fn lessthan(&self, pv: &PartialValue) -> bool {
+        datetime_lessthan_any(
+            self.map
+                .values()
+                .filter_map(|v| session_state_expiry(&v.state)),
+            pv,
+        )
     }
 
     fn len(&self) -> usize {
@@ -873,9 +2245,58 @@ impl ValueSetT for ValueSetOauth2Session {
     }
 
     fn validate(&self, _schema_attr: &SchemaAttribute) -> bool {
+        self.map.iter().all(|(session_id, session)| {
+            // A nil resource server can't be referenced, and sets no bits in the rs_filter.
+            !session_id.is_nil()
+                && !session.rs_uuid.is_nil()
+                // A session can't be its own parent.
+                && session.parent != Some(*session_id)
+                && session
+                    .origin
+                    .as_ref()
+                    .map(|origin| Url::parse(origin).is_ok())
+                    .unwrap_or(true)
+        })
+    }
+
+    fn validate_maximum_values(&self, schema_attr: &SchemaAttribute) -> bool {
+        warn_exceeds_maximum_values(self.map.len(), schema_attr);
         true
     }
 
+    fn trim_to_maximum(&mut self, maximum: usize, cid: &Cid, trim_cid: &Cid) -> usize {
+        // Revoked sessions must remain until they are past the replication trim window,
+        // else a replica that has not seen the revocation would restore the session.
+        let before = self.map.len();
+        self.trim(trim_cid);
+        let removed = before - self.map.len();
+
+        // Revoke the oldest sessions first.
+        let mut sessions: Vec<_> = self
+            .map
+            .iter()
+            .filter(|(_, session)| !matches!(session.state, SessionState::RevokedAt(_)))
+            .map(|(session_id, session)| (session.issued_at, *session_id))
+            .collect();
+        sessions.sort_unstable();
+
+        let to_take = sessions.len().saturating_sub(maximum);
+
+        sessions
+            .into_iter()
+            .take(to_take)
+            .for_each(|(_, session_id)| {
+                if let Some(session) = self.map.get_mut(&session_id) {
+                    warn!(?session_id, "force revoked");
+                    session.state = SessionState::RevokedAt(cid.clone());
+                }
+            });
+
+        self.rebuild_rs_filter();
+
+        removed + to_take
+    }
+
     fn to_proto_string_clone_iter(&self) -> Box<dyn Iterator<Item = String> + '_> {
         Box::new(
             self.map
@@ -885,42 +2306,50 @@ impl ValueSetT for ValueSetOauth2Session {
     }
 
     fn to_scim_value(&self) -> Option<ScimResolveStatus> {
-        Some(ScimResolveStatus::Resolved(ScimValueKanidm::from(
-            self.map
-                .iter()
-                .map(|(session_id, session)| {
-                    let (expires, revoked) = match &session.state {
-                        SessionState::ExpiresAt(odt) => (Some(*odt), None),
-                        SessionState::NeverExpires => (None, None),
-                        SessionState::RevokedAt(cid) => {
-                            let odt: OffsetDateTime = cid.into();
-                            (None, Some(odt))
-                        }
-                    };
+        // The client display name is resolved later from the client_id.
+        Some(ScimResolveStatus::NeedsResolution(
+            ScimValueIntermediate::Oauth2Session(
+                self.map
+                    .iter()
+                    .map(|(session_id, session)| {
+                        let (expires, revoked, revoked_by_server) = match &session.state {
+                            SessionState::ExpiresAt(odt) => (Some(*odt), None, None),
+                            SessionState::NeverExpires => (None, None, None),
+                            SessionState::RevokedAt(cid) => {
+                                (None, Some(revoked_at_timestamp(cid)), Some(cid.s_uuid))
+                            }
+                        };
 
-                    ScimOAuth2Session {
-                        id: *session_id,
-                        parent_id: session.parent,
-                        client_id: session.rs_uuid,
-                        issued_at: session.issued_at,
-                        expires,
-                        revoked,
-                    }
-                })
-                .collect::<Vec<_>>(),
-        )))
+                        ScimOAuth2Session {
+                            id: *session_id,
+                            parent_id: session.parent,
+                            client_id: session.rs_uuid,
+                            client_display_name: None,
+                            issued_at: session.issued_at,
+                            expires,
+                            revoked,
+                            revoked_by_server,
+                            scopes: session.granted_scopes.clone(),
+                            has_refresh: session.has_refresh,
+                            last_refreshed: session.last_refreshed,
+                            origin: session.origin.clone(),
+                        }
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+        ))
     }
 
     fn to_db_valueset_v2(&self) -> DbValueSetV2 {
         DbValueSetV2::Oauth2Session(
             self.map
                 .iter()
-                .map(|(u, m)| DbValueOauth2Session::V3 {
+                .map(|(u, m)| DbValueOauth2Session::V6 {
                     refer: *u,
                     parent: m.parent,
                     state: match &m.state {
-                        SessionState::ExpiresAt(odt) => {
-                            debug_assert_eq!(odt.offset(), time::UtcOffset::UTC);
+                        SessionState::ExpiresAt(odt) =>
+                        {
                             #[allow(clippy::expect_used)]
                             odt.format(&Rfc3339)
                                 .map(DbValueSessionStateV1::ExpiresAt)
@@ -933,13 +2362,20 @@ impl ValueSetT for ValueSetOauth2Session {
                         }),
                     },
                     issued_at: {
-                        debug_assert_eq!(m.issued_at.offset(), time::UtcOffset::UTC);
                         #[allow(clippy::expect_used)]
                         m.issued_at
                             .format(&Rfc3339)
                             .expect("Failed to format timestamp as RFC3339")
                     },
                     rs_uuid: m.rs_uuid,
+                    granted_scopes: m.granted_scopes.clone(),
+                    has_refresh: m.has_refresh,
+                    last_refreshed: m.last_refreshed.map(|odt| {
+                        #[allow(clippy::expect_used)]
+                        odt.format(&Rfc3339)
+                            .expect("Failed to format timestamp as RFC3339")
+                    }),
+                    origin: m.origin.clone(),
                 })
                 .collect(),
         )
@@ -957,6 +2393,13 @@ impl ValueSetT for ValueSetOauth2Session {
         )
     }
 
+    fn get_value(&self, pv: &PartialValue) -> Option<Value> {
+        match pv {
+            PartialValue::Refer(u) => self.map.get(u).map(|m| Value::Oauth2Session(*u, m.clone())),
+            _ => None,
+        }
+    }
+
     fn equal(&self, other: &ValueSet) -> bool {
         if let Some(other) = other.as_oauth2session_map() {
             &self.map == other
@@ -967,6 +2410,7 @@ impl ValueSetT for ValueSetOauth2Session {
     }
 
     fn merge(&mut self, other: &ValueSet) -> Result<(), OperationError> {
+        check_merge_syntax(SyntaxType::Oauth2Session, other)?;
         if let Some(b) = other.as_oauth2session_map() {
             // We can't just do merge maps here, we have to be aware of the
             // session.state value and what it currently is set to. We also
@@ -978,6 +2422,11 @@ impl ValueSetT for ValueSetOauth2Session {
                     // cids will always take effect.
                     if v_other.state > v_self.state {
                         *v_self = v_other.clone();
+                    } else if v_other.state == v_self.state {
+                        // Where both sides agree on the state, the most recent
+                        // refresh is kept.
+                        v_self.has_refresh |= v_other.has_refresh;
+                        v_self.last_refreshed = v_self.last_refreshed.max(v_other.last_refreshed);
                     }
                 } else {
                     // Update the rs_filter!
@@ -997,13 +2446,32 @@ impl ValueSetT for ValueSetOauth2Session {
         Some(&self.map)
     }
 
+    fn as_oauth2session_set(&self) -> Option<&ValueSetOauth2Session> {
+        Some(self)
+    }
+
+    fn as_oauth2session_set_mut(&mut self) -> Option<&mut ValueSetOauth2Session> {
+        Some(self)
+    }
+
     fn as_ref_uuid_iter(&self) -> Option<Box<dyn Iterator<Item = Uuid> + '_>> {
         // This is what ties us as a type that can be refint checked. We need to
-        // bind to our resource servers, not our ids!
-        Some(Box::new(self.map.values().map(|m| &m.rs_uuid).copied()))
+        // bind to our resource servers, not our ids! The parent sessions are also
+        // references, but they are within the same entry, so refint excludes them
+        // from the existence checks.
+        Some(Box::new(
+            self.map
+                .values()
+                .flat_map(|m| std::iter::once(m.rs_uuid).chain(m.parent)),
+        ))
     }
 
     fn repl_merge_valueset(&self, older: &ValueSet, trim_cid: &Cid) -> Option<ValueSet> {
+        // If the older value has a different type - return nothing, we
+        // just take the newer value.
+        if !check_repl_merge_syntax(SyntaxType::Oauth2Session, older) {
+            return None;
+        }
         if let Some(b) = older.as_oauth2session_map() {
             // We can't just do merge maps here, we have to be aware of the
             // session.state value and what it currently is set to.
@@ -1016,6 +2484,11 @@ impl ValueSetT for ValueSetOauth2Session {
                     // cids will always take effect.
                     if v_other.state > v_self.state {
                         *v_self = v_other.clone();
+                    } else if v_other.state == v_self.state {
+                        // Where both sides agree on the state, the most recent
+                        // refresh is kept.
+                        v_self.has_refresh |= v_other.has_refresh;
+                        v_self.last_refreshed = v_self.last_refreshed.max(v_other.last_refreshed);
                     }
                 } else {
                     // Not present, just insert.
@@ -1030,8 +2503,6 @@ impl ValueSetT for ValueSetOauth2Session {
 
             Some(vs)
         } else {
-            // The older value has a different type - return nothing, we
-            // just take the newer value.
             None
         }
     }
@@ -1045,144 +2516,544 @@ pub struct ValueSetApiToken {
 impl ValueSetApiToken {
     pub fn new(u: Uuid, m: ApiToken) -> Box<Self> {
         let mut map = BTreeMap::new();
-        map.insert(u, m);
+        map.insert(u, m.into_utc());
         Box::new(ValueSetApiToken { map })
     }
 
     pub fn push(&mut self, u: Uuid, m: ApiToken) -> bool {
-        self.map.insert(u, m).is_none()
+        self.map.insert(u, m.into_utc()).is_none()
     }
 
-    pub fn from_dbvs2(data: Vec<DbValueApiToken>) -> Result<ValueSet, OperationError> {
-        let map = data
-            .into_iter()
-            .filter_map(|dbv| {
-                match dbv {
-                    DbValueApiToken::V1 {
-                        refer,
-                        label,
-                        expiry,
-                        issued_at,
-                        issued_by,
-                        scope,
-                    } => {
-                        // Convert things.
-                        let issued_at = OffsetDateTime::parse(&issued_at, &Rfc3339)
-                            .map(|odt| odt.to_offset(time::UtcOffset::UTC))
-                            .map_err(|e| {
-                                admin_error!(
-                                    ?e,
-                                    "Invalidating api token {} due to invalid issued_at timestamp",
-                                    refer
-                                )
-                            })
-                            .ok()?;
-
-                        // This is a bit annoying. In the case we can't parse the optional
-                        // expiry, we need to NOT return the session so that it's immediately
-                        // invalidated. To do this we have to invert some of the options involved
-                        // here.
-                        let expiry = expiry
-                            .map(|e_inner| {
-                                OffsetDateTime::parse(&e_inner, &Rfc3339)
-                                    .map(|odt| odt.to_offset(time::UtcOffset::UTC))
-                                // We now have an
-                                // Option<Result<ODT, _>>
-                            })
-                            .transpose()
-                            // Result<Option<ODT>, _>
-                            .map_err(|e| {
-                                admin_error!(
-                                    ?e,
-                                    "Invalidating api token {} due to invalid expiry timestamp",
-                                    refer
-                                )
-                            })
-                            // Option<Option<ODT>>
-                            .ok()?;
-
-                        let issued_by = match issued_by {
-                            DbValueIdentityId::V1Internal => IdentityId::Internal(UUID_SYSTEM),
-                            DbValueIdentityId::V2Internal(u) => IdentityId::Internal(u),
-                            DbValueIdentityId::V1Uuid(u) => IdentityId::User(u),
-                            DbValueIdentityId::V1Sync(u) => IdentityId::Synch(u),
-                        };
-
-                        let scope = match scope {
-                            DbValueApiTokenScopeV1::ReadOnly => ApiTokenScope::ReadOnly,
-                            DbValueApiTokenScopeV1::ReadWrite => ApiTokenScope::ReadWrite,
-                            DbValueApiTokenScopeV1::Synchronise => ApiTokenScope::Synchronise,
-                        };
+    /// The tokens that share their label with an earlier issued token that was still valid
+    /// when they were issued. Labels are compared case insensitively, and revoked tokens
+    /// never hold a label.
+    fn duplicate_labels(&self) -> Vec<Uuid> {
+        let mut live: Vec<_> = self
+            .map
+            .iter()
+            .filter(|(_, at)| !at.is_revoked())
+            .map(|(u, at)| (*u, at, at.label.to_lowercase()))
+            .collect();
+        live.sort_unstable_by(|(u_a, a, _), (u_b, b, _)| {
+            a.issued_at.cmp(&b.issued_at).then_with(|| u_a.cmp(u_b))
+        });
 
-                        Some((
-                            refer,
-                            ApiToken {
-                                label,
-                                expiry,
-                                issued_at,
-                                issued_by,
-                                scope,
-                            },
-                        ))
-                    }
-                }
+        live.iter()
+            .enumerate()
+            .filter(|(i, (_, at, label))| {
+                live.iter().take(*i).any(|(_, earlier, earlier_label)| {
+                    earlier_label == label && earlier.is_valid_at(at.issued_at)
+                })
             })
-            .collect();
-        Ok(Box::new(ValueSetApiToken { map }))
+            .map(|(_, (u, _, _))| *u)
+            .collect()
     }
 
-    // We need to allow this, because rust doesn't allow us to impl FromIterator on foreign
-    // types, and tuples are always foreign.
-    #[allow(clippy::should_implement_trait)]
-    pub fn from_iter<T>(iter: T) -> Option<Box<Self>>
-    where
-        T: IntoIterator<Item = (Uuid, ApiToken)>,
-    {
-        let map = iter.into_iter().collect();
-        Some(Box::new(ValueSetApiToken { map }))
+    /// Revoke the tokens that duplicate the label of an earlier live token, retaining the
+    /// earliest issued. Replicas may concurrently issue tokens with the same label, and
+    /// this resolves them the same way on every replica. Returns true if any token was
+    /// revoked.
+    pub fn revoke_duplicate_labels(&mut self, cid: &Cid) -> bool {
+        let duplicates = self.duplicate_labels();
+        for u in duplicates.iter() {
+            if let Some(at) = self.map.get_mut(u) {
+                warn!(token_id = ?u, label = %at.label, "Revoking api token with a duplicate label");
+                at.state = SessionState::RevokedAt(cid.clone());
+            }
+        }
+        !duplicates.is_empty()
     }
-}
 
-impl ValueSetT for ValueSetApiToken {
-    fn insert_checked(&mut self, value: Value) -> Result<bool, OperationError> {
-        match value {
-            Value::ApiToken(u, m) => {
-                if let BTreeEntry::Vacant(e) = self.map.entry(u) {
-                    e.insert(m);
-                    Ok(true)
-                } else {
-                    Ok(false)
+    /// Count the tokens that are still valid at `now`, but which will expire within
+    /// `within` of it.
+    pub fn count_expiring(&self, now: OffsetDateTime, within: Duration) -> usize {
+        let horizon = now + within;
+        self.map
+            .values()
+            .filter(|at| matches!(at.state, SessionState::ExpiresAt(exp) if now < exp && exp <= horizon))
+            .count()
+    }
+
+    /// Remove the tokens that have expired at `now`, returning the number removed. This
+    /// allows space to be reclaimed when the entry has reached its token limit. Revoked
+    /// tokens are retained as they are required for replication.
+    pub fn evict_expired_tokens(&mut self, now: OffsetDateTime) -> usize {
+        let before = self.map.len();
+        self.map
+            .retain(|_, at| !matches!(at.state, SessionState::ExpiresAt(exp) if exp <= now));
+        before - self.map.len()
+    }
+
+    /// The tokens of this set with their remaining lifetime at `now`, ordered from the
+    /// soonest to expire. Expired tokens have a remaining lifetime of zero, and tokens that
+    /// never expire have none and are ordered last. Revoked tokens are excluded since they
+    /// can no longer be rotated.
+    pub fn tokens_by_expiry(&self, now: OffsetDateTime) -> Vec<(Uuid, Option<Duration>)> {
+        let mut tokens: Vec<_> = self
+            .map
+            .iter()
+            .filter_map(|(token_id, at)| match at.state {
+                SessionState::ExpiresAt(exp) => Some((
+                    *token_id,
+                    Some(Duration::try_from(exp - now).unwrap_or(Duration::ZERO)),
+                )),
+                SessionState::NeverExpires => Some((*token_id, None)),
+                SessionState::RevokedAt(_) => None,
+            })
+            .collect();
+
+        tokens.sort_by(|(a_id, a_rem), (b_id, b_rem)| {
+            match (a_rem, b_rem) {
+                (Some(a_rem), Some(b_rem)) => a_rem.cmp(b_rem),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+            .then_with(|| a_id.cmp(b_id))
+        });
+        tokens
+    }
+
+    /// The ids of the tokens in this set with `scope`, in any state. Scopes are not indexed,
+    /// so this scans every token in the set, and finding the tokens of a scope across all
+    /// entries requires that each candidate entry is loaded.
+    pub fn tokens_with_scope(&self, scope: ApiTokenScope) -> Vec<Uuid> {
+        self.map
+            .iter()
+            .filter(|(_, at)| at.scope == scope)
+            .map(|(u, _)| *u)
+            .collect()
+    }
+
+    /// Rotate the token `token_id`, advancing its generation so that any previously
+    /// issued secret for the token no longer validates. The token uuid and label are
+    /// unchanged. If `expiry` is provided it replaces the current expiry of the token.
+    pub fn rotate(
+        &mut self,
+        token_id: Uuid,
+        issued_at: OffsetDateTime,
+        expiry: Option<OffsetDateTime>,
+    ) -> Result<&ApiToken, OperationError> {
+        let token = self.map.get_mut(&token_id).ok_or_else(|| {
+            error!(?token_id, "Unable to rotate api token, it does not exist");
+            OperationError::NoMatchingEntries
+        })?;
+
+        if token.is_revoked() {
+            error!(
+                ?token_id,
+                "Refusing to rotate api token that has been revoked"
+            );
+            return Err(OperationError::InvalidValueState);
+        }
+
+        let state = expiry
+            .map(|odt| SessionState::ExpiresAt(odt.to_offset(time::UtcOffset::UTC)))
+            .unwrap_or_else(|| token.state.clone());
+
+        if matches!(state, SessionState::ExpiresAt(exp) if exp <= issued_at) {
+            error!(
+                ?token_id,
+                "Refusing to rotate api token, it would be expired at issue"
+            );
+            return Err(OperationError::InvalidValueState);
+        }
+
+        token.generation = token.generation.checked_add(1).ok_or_else(|| {
+            error!(
+                ?token_id,
+                "Unable to rotate api token, generation exhausted"
+            );
+            OperationError::ResourceLimit
+        })?;
+        token.state = state;
+        token.issued_at = issued_at;
+
+        Ok(token)
+    }
+
+    /// Determine if `other` should replace `current` when two replicas hold differing
+    /// values for the same token. This is a total order, so that every replica converges
+    /// on the same token regardless of the direction of the merge.
+    ///
+    /// 1. As with sessions, RevokedAt always proceeds other states, and lower revoked
+    ///    cids will always take effect.
+    /// 2. Otherwise the highest generation wins, so that a rotation is never undone,
+    ///    even if the rotation shortened the expiry of the token.
+    /// 3. Then the greatest state, where an expiry proceeds no expiry and the later
+    ///    expiry proceeds an earlier one.
+    /// 4. Then the latest issued_at.
+    /// 5. Finally the remaining content of the token is compared lexicographically.
+    fn supersedes(other: &ApiToken, current: &ApiToken) -> bool {
+        let content = |t: &ApiToken| {
+            (
+                t.generation,
+                t.label.clone(),
+                t.scope.to_string(),
+                t.issued_by.kind(),
+                Uuid::from(&t.issued_by),
+                t.parent_session,
+                t.constraints.clone(),
+            )
+        };
+
+        let ordering = if current.is_revoked() || other.is_revoked() {
+            other.state.cmp(&current.state)
+        } else {
+            other
+                .generation
+                .cmp(&current.generation)
+                .then_with(|| other.state.cmp(&current.state))
+        };
+
+        ordering
+            .then_with(|| other.issued_at.cmp(&current.issued_at))
+            .then_with(|| content(other).cmp(&content(current)))
+            .is_gt()
+    }
+
+    fn merge_map(map: &mut BTreeMap<Uuid, ApiToken>, other: &BTreeMap<Uuid, ApiToken>) {
+        for (k_other, v_other) in other.iter() {
+            if let Some(v_self) = map.get_mut(k_other) {
+                if Self::supersedes(v_other, v_self) {
+                    *v_self = v_other.clone();
+                }
+            } else {
+                // Not present, just insert.
+                map.insert(*k_other, v_other.clone());
+            }
+        }
+    }
+
+    pub fn from_dbvs2(data: Vec<DbValueApiToken>) -> Result<ValueSet, OperationError> {
+        let map = data
+            .into_iter()
+            .filter_map(|dbv| {
+                let (
+                    refer,
+                    label,
+                    state,
+                    issued_at,
+                    issued_by,
+                    scope,
+                    parent_session,
+                    generation,
+                    constraints,
+                ) = match dbv {
+                    DbValueApiToken::V1 {
+                        refer,
+                        label,
+                        expiry,
+                        issued_at,
+                        issued_by,
+                        scope,
+                    } => {
+                        // V1 tokens were removed on revocation, so any token we have is
+                        // either expiring or valid forever.
+                        let state = expiry
+                            .map(DbValueSessionStateV1::ExpiresAt)
+                            .unwrap_or(DbValueSessionStateV1::Never);
+                        (
+                            refer, label, state, issued_at, issued_by, scope, None, 0, None,
+                        )
+                    }
+                    DbValueApiToken::V2 {
+                        refer,
+                        label,
+                        state,
+                        issued_at,
+                        issued_by,
+                        scope,
+                        parent_session,
+                        generation,
+                        constraints,
+                    } => (
+                        refer,
+                        label,
+                        state,
+                        issued_at,
+                        issued_by,
+                        scope,
+                        parent_session,
+                        generation,
+                        constraints,
+                    ),
+                };
+
+                // Convert things.
+                let issued_at = OffsetDateTime::parse(&issued_at, &Rfc3339)
+                    .map(|odt| odt.to_offset(time::UtcOffset::UTC))
+                    .map_err(|e| {
+                        admin_error!(
+                            ?e,
+                            "Invalidating api token {} due to invalid issued_at timestamp",
+                            refer
+                        )
+                    })
+                    .ok()?;
+
+                // In the case we can't parse the expiry, we need to NOT return the
+                // token so that it's immediately invalidated.
+                let state = match state {
+                    DbValueSessionStateV1::ExpiresAt(e_inner) => {
+                        OffsetDateTime::parse(&e_inner, &Rfc3339)
+                            .map(|odt| odt.to_offset(time::UtcOffset::UTC))
+                            .map(SessionState::ExpiresAt)
+                            .map_err(|e| {
+                                admin_error!(
+                                    ?e,
+                                    "Invalidating api token {} due to invalid expiry timestamp",
+                                    refer
+                                )
+                            })
+                            .ok()?
+                    }
+                    DbValueSessionStateV1::Never => SessionState::NeverExpires,
+                    DbValueSessionStateV1::RevokedAt(dc) => SessionState::RevokedAt(Cid {
+                        s_uuid: dc.server_id,
+                        ts: dc.timestamp,
+                    }),
+                };
+
+                let issued_by = match issued_by {
+                    DbValueIdentityId::V1Internal => IdentityId::Internal(UUID_SYSTEM),
+                    DbValueIdentityId::V2Internal(u) => IdentityId::Internal(u),
+                    DbValueIdentityId::V1Uuid(u) => IdentityId::User(u),
+                    DbValueIdentityId::V1Sync(u) => IdentityId::Synch(u),
+                };
+
+                let scope = match scope {
+                    DbValueApiTokenScopeV1::ReadOnly => ApiTokenScope::ReadOnly,
+                    DbValueApiTokenScopeV1::ReadWrite => ApiTokenScope::ReadWrite,
+                    DbValueApiTokenScopeV1::Synchronise => ApiTokenScope::Synchronise,
+                };
+
+                let constraints = constraints.map(|c| {
+                    c.into_iter()
+                        .map(|c| match c {
+                            DbValueApiTokenConstraintV1::GroupMembership => {
+                                ApiTokenConstraint::GroupMembership
+                            }
+                            DbValueApiTokenConstraintV1::Credentials => {
+                                ApiTokenConstraint::Credentials
+                            }
+                            DbValueApiTokenConstraintV1::SshPublicKeys => {
+                                ApiTokenConstraint::SshPublicKeys
+                            }
+                            DbValueApiTokenConstraintV1::Profile => ApiTokenConstraint::Profile,
+                        })
+                        .collect()
+                });
+
+                Some((
+                    refer,
+                    ApiToken {
+                        label,
+                        state,
+                        issued_at,
+                        issued_by,
+                        scope,
+                        parent_session,
+                        generation,
+                        constraints,
+                    },
+                ))
+            })
+            .collect();
+        Ok(Box::new(ValueSetApiToken { map }))
+    }
+
+    // We need to allow this, because rust doesn't allow us to impl FromIterator on foreign
+    // types, and tuples are always foreign.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_iter<T>(iter: T) -> Option<Box<Self>>
+    where
+        T: IntoIterator<Item = (Uuid, ApiToken)>,
+    {
+        let map = iter.into_iter().map(|(u, m)| (u, m.into_utc())).collect();
+        Some(Box::new(ValueSetApiToken { map }))
+    }
+}
+
+impl ValueSetT for ValueSetApiToken {
+    fn insert_checked(&mut self, value: Value) -> Result<bool, OperationError> {
+        match value {
+            Value::ApiToken(u, m) => {
+                let m = m.into_utc();
+                if m.is_expired_at_issue() {
+                    error!(
+                        token_id = ?u,
+                        "Refusing to insert api token that expires before it was issued"
+                    );
+                    return Err(OperationError::InvalidValueState);
+                }
+
+                // Labels must identify a single usable token. A label may be reused once the
+                // token holding it has expired or been revoked, and a revoked token never
+                // holds a label. This is only checked for the token being written, so that
+                // entries with existing duplicates can still be loaded and modified.
+                let label = m.label.to_lowercase();
+                if !m.is_revoked()
+                    && self.map.iter().any(|(k, at)| {
+                        *k != u && at.is_valid_at(m.issued_at) && at.label.to_lowercase() == label
+                    })
+                {
+                    error!(
+                        token_id = ?u,
+                        label = %m.label,
+                        "Refusing to insert api token, the label is in use by another token"
+                    );
+                    return Err(OperationError::DuplicateLabel);
+                }
+
+                // A sync account may only hold a single live sync token, else it is
+                // ambiguous which token the sync provider should be using. As with
+                // labels this is only checked as tokens are written, since replicas may
+                // concurrently issue tokens.
+                if m.scope == ApiTokenScope::Synchronise
+                    && !m.is_revoked()
+                    && self.map.iter().any(|(k, at)| {
+                        *k != u
+                            && at.scope == ApiTokenScope::Synchronise
+                            && at.is_valid_at(m.issued_at)
+                    })
+                {
+                    error!(
+                        token_id = ?u,
+                        "Refusing to insert sync token, another sync token is still active"
+                    );
+                    return Err(OperationError::SyncTokenAlreadyActive);
+                }
+
+                match self.map.entry(u) {
+                    BTreeEntry::Vacant(e) => {
+                        e.insert(m);
+                        Ok(true)
+                    }
+                    BTreeEntry::Occupied(mut e) => {
+                        // Like oauth2 sessions, an existing token may be overwritten, but
+                        // only by a rotation of a token that is still live.
+                        let e_v = e.get_mut();
+                        if !e_v.is_revoked() && !m.is_revoked() && m.generation > e_v.generation {
+                            *e_v = m;
+                            Ok(true)
+                        } else {
+                            Ok(false)
+                        }
+                    }
                 }
             }
             _ => Err(OperationError::InvalidValueState),
         }
     }
 
+    fn insert_checked_at(
+        &mut self,
+        value: Value,
+        curtime: OffsetDateTime,
+    ) -> Result<bool, OperationError> {
+        // Tokens that have naturally expired remain valid, but a token can't be written
+        // once it has expired as it could never be used.
+        if let Value::ApiToken(u, at) = &value {
+            if matches!(at.state, SessionState::ExpiresAt(exp) if exp <= curtime) {
+                error!(
+                    token_id = ?u,
+                    "Refusing to write api token that is already expired"
+                );
+                return Err(OperationError::InvalidValueState);
+            }
+        }
+        self.insert_checked(value)
+    }
+
     fn clear(&mut self) {
         self.map.clear();
     }
 
-    fn remove(&mut self, pv: &PartialValue, _cid: &Cid) -> bool {
+    fn remove(&mut self, pv: &PartialValue, cid: &Cid) -> bool {
         match pv {
-            PartialValue::Refer(u) => self.map.remove(u).is_some(),
+            PartialValue::Refer(u) => {
+                if let Some(token) = self.map.get_mut(u) {
+                    if !token.is_revoked() {
+                        token.state = SessionState::RevokedAt(cid.clone());
+                        true
+                    } else {
+                        false
+                    }
+                } else {
+                    false
+                }
+            }
+            // Labels should be unique, but entries may hold legacy duplicates. In that
+            // case all the unrevoked tokens with the label are revoked.
+            PartialValue::Iutf8(label) => {
+                let mut removed = false;
+                self.map
+                    .values_mut()
+                    .filter(|token| !token.is_revoked() && token.label.to_lowercase() == *label)
+                    .for_each(|token| {
+                        token.state = SessionState::RevokedAt(cid.clone());
+                        removed = true;
+                    });
+                removed
+            }
             _ => false,
         }
     }
 
-    fn purge(&mut self, _cid: &Cid) -> bool {
-        // Could consider making this a TS capable entry.
-        true
+    fn purge(&mut self, cid: &Cid) -> bool {
+        for (_uuid, token) in self.map.iter_mut() {
+            if !token.is_revoked() {
+                token.state = SessionState::RevokedAt(cid.clone())
+            }
+        }
+        // Can't be purged since we need the cid's of revoked to persist.
+        false
+    }
+
+    fn trim(&mut self, trim_cid: &Cid) {
+        self.map.retain(|_, token| {
+            match &token.state {
+                // This value is past the replication trim window and can now safely
+                // be removed
+                SessionState::RevokedAt(cid) if cid < trim_cid => false,
+                // Retain all else
+                _ => true,
+            }
+        })
     }
 
     fn contains(&self, pv: &PartialValue) -> bool {
         match pv {
-            PartialValue::Refer(u) => self.map.contains_key(u),
+            // Tokens can be found by their id, the identity that issued them, or the session
+            // that issued them.
+            PartialValue::Refer(u) => {
+                self.map.contains_key(u)
+                    || self
+                        .map
+                        .values()
+                        .any(|at| Uuid::from(&at.issued_by) == *u || at.parent_session == Some(*u))
+            }
+            // Or by their label, which is case insensitive. As with `remove`, revoked
+            // tokens never hold a label.
+            PartialValue::Iutf8(label) => self
+                .map
+                .values()
+                .any(|at| !at.is_revoked() && at.label.to_lowercase() == *label),
+            // Or by their scope. This is not indexed, so requires a scan of the tokens.
+            PartialValue::ApiTokenScope(scope) => self.map.values().any(|at| at.scope == *scope),
             _ => false,
         }
     }
 
-    fn substring(&self, _pv: &PartialValue) -> bool {
-        false
+    fn substring(&self, pv: &PartialValue) -> bool {
+        match pv {
+            PartialValue::Iutf8(s) => self
+                .map
+                .values()
+                .any(|at| at.label.to_lowercase().contains(s.as_str())),
+            _ => false,
+        }
     }
 
     fn startswith(&self, _pv: &PartialValue) -> bool {
@@ -1193,8 +3064,13 @@ impl ValueSetT for ValueSetApiToken {
         false
     }
 
-    fn lessthan(&self, _pv: &PartialValue) -> bool {
-        false
+    fn lessthan(&self, pv: &PartialValue) -> bool {
+        datetime_lessthan_any(
+            self.map
+                .values()
+                .filter_map(|v| session_state_expiry(&v.state)),
+            pv,
+        )
     }
 
     fn len(&self) -> usize {
@@ -1202,10 +3078,22 @@ impl ValueSetT for ValueSetApiToken {
     }
 
     fn generate_idx_eq_keys(&self) -> Vec<String> {
-        self.map
-            .keys()
-            .map(|u| u.as_hyphenated().to_string())
-            .collect()
+        // Allocate four times as much for worst-case when every token is
+        // from a unique issuer and parent session to prevent re-allocs.
+        let mut idx_keys = Vec::with_capacity(self.map.len() * 4);
+        for (k, v) in self.map.iter() {
+            idx_keys.push(k.as_hyphenated().to_string());
+            idx_keys.push(Uuid::from(&v.issued_by).as_hyphenated().to_string());
+            if !v.is_revoked() {
+                idx_keys.push(v.label.to_lowercase());
+            }
+            if let Some(parent) = v.parent_session {
+                idx_keys.push(parent.as_hyphenated().to_string());
+            }
+        }
+        idx_keys.sort_unstable();
+        idx_keys.dedup();
+        idx_keys
     }
 
     fn syntax(&self) -> SyntaxType {
@@ -1218,6 +3106,12 @@ impl ValueSetT for ValueSetApiToken {
         })
     }
 
+    fn validate_maximum_values(&self, _schema_attr: &SchemaAttribute) -> bool {
+        // Tokens beyond the schema maximum are refused by the session plugin as they are
+        // added, since an entry that already exceeds it must still be able to revoke them.
+        true
+    }
+
     fn to_proto_string_clone_iter(&self) -> Box<dyn Iterator<Item = String> + '_> {
         Box::new(
             self.map
@@ -1227,36 +3121,58 @@ impl ValueSetT for ValueSetApiToken {
     }
 
     fn to_scim_value(&self) -> Option<ScimResolveStatus> {
-        Some(ScimResolveStatus::Resolved(ScimValueKanidm::from(
-            self.map
-                .iter()
-                .map(|(token_id, token)| ScimApiToken {
-                    id: *token_id,
-                    label: token.label.clone(),
-                    issued_by: Uuid::from(&token.issued_by),
-                    issued_at: token.issued_at,
-                    expires: token.expiry,
-                    scope: token.scope.to_string(),
-                })
-                .collect::<Vec<_>>(),
-        )))
+        // Whether each token has expired is resolved later against the current time.
+        Some(ScimResolveStatus::NeedsResolution(
+            ScimValueIntermediate::ApiToken(
+                self.map
+                    .iter()
+                    .map(|(token_id, token)| ScimApiToken {
+                        id: *token_id,
+                        label: token.label.clone(),
+                        issued_by: Uuid::from(&token.issued_by),
+                        issued_by_type: token.issued_by.kind().to_string(),
+                        issued_at: token.issued_at,
+                        expires: token.expiry(),
+                        revoked: match &token.state {
+                            SessionState::RevokedAt(cid) => Some(revoked_at_timestamp(cid)),
+                            SessionState::ExpiresAt(_) | SessionState::NeverExpires => None,
+                        },
+                        scope: token.scope.to_string(),
+                        parent_session: token.parent_session,
+                        constraints: token
+                            .constraints
+                            .as_ref()
+                            .map(|c| c.iter().map(|c| c.to_string()).collect()),
+                        generation: token.generation,
+                        expired: false,
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+        ))
     }
 
     fn to_db_valueset_v2(&self) -> DbValueSetV2 {
         DbValueSetV2::ApiToken(
             self.map
                 .iter()
-                .map(|(u, m)| DbValueApiToken::V1 {
+                .map(|(u, m)| DbValueApiToken::V2 {
                     refer: *u,
                     label: m.label.clone(),
-                    expiry: m.expiry.map(|odt| {
-                        debug_assert_eq!(odt.offset(), time::UtcOffset::UTC);
-                        #[allow(clippy::expect_used)]
-                        odt.format(&Rfc3339)
-                            .expect("Failed to format timestamp into RFC3339")
-                    }),
+                    state: match &m.state {
+                        SessionState::ExpiresAt(odt) =>
+                        {
+                            #[allow(clippy::expect_used)]
+                            odt.format(&Rfc3339)
+                                .map(DbValueSessionStateV1::ExpiresAt)
+                                .expect("Failed to format timestamp into RFC3339")
+                        }
+                        SessionState::NeverExpires => DbValueSessionStateV1::Never,
+                        SessionState::RevokedAt(c) => DbValueSessionStateV1::RevokedAt(DbCidV1 {
+                            server_id: c.s_uuid,
+                            timestamp: c.ts,
+                        }),
+                    },
                     issued_at: {
-                        debug_assert_eq!(m.issued_at.offset(), time::UtcOffset::UTC);
                         #[allow(clippy::expect_used)]
                         m.issued_at
                             .format(&Rfc3339)
@@ -1272,6 +3188,24 @@ impl ValueSetT for ValueSetApiToken {
                         ApiTokenScope::ReadWrite => DbValueApiTokenScopeV1::ReadWrite,
                         ApiTokenScope::Synchronise => DbValueApiTokenScopeV1::Synchronise,
                     },
+                    parent_session: m.parent_session,
+                    generation: m.generation,
+                    constraints: m.constraints.as_ref().map(|c| {
+                        c.iter()
+                            .map(|c| match c {
+                                ApiTokenConstraint::GroupMembership => {
+                                    DbValueApiTokenConstraintV1::GroupMembership
+                                }
+                                ApiTokenConstraint::Credentials => {
+                                    DbValueApiTokenConstraintV1::Credentials
+                                }
+                                ApiTokenConstraint::SshPublicKeys => {
+                                    DbValueApiTokenConstraintV1::SshPublicKeys
+                                }
+                                ApiTokenConstraint::Profile => DbValueApiTokenConstraintV1::Profile,
+                            })
+                            .collect()
+                    }),
                 })
                 .collect(),
         )
@@ -1285,6 +3219,13 @@ impl ValueSetT for ValueSetApiToken {
         Box::new(self.map.iter().map(|(u, m)| Value::ApiToken(*u, m.clone())))
     }
 
+    fn get_value(&self, pv: &PartialValue) -> Option<Value> {
+        match pv {
+            PartialValue::Refer(u) => self.map.get(u).map(|m| Value::ApiToken(*u, m.clone())),
+            _ => None,
+        }
+    }
+
     fn equal(&self, other: &ValueSet) -> bool {
         if let Some(other) = other.as_apitoken_map() {
             &self.map == other
@@ -1295,8 +3236,10 @@ impl ValueSetT for ValueSetApiToken {
     }
 
     fn merge(&mut self, other: &ValueSet) -> Result<(), OperationError> {
+        check_merge_syntax(SyntaxType::ApiToken, other)?;
         if let Some(b) = other.as_apitoken_map() {
-            mergemaps!(self.map, b)
+            Self::merge_map(&mut self.map, b);
+            Ok(())
         } else {
             debug_assert!(false);
             Err(OperationError::InvalidValueState)
@@ -1307,20 +3250,99 @@ impl ValueSetT for ValueSetApiToken {
         Some(&self.map)
     }
 
+    fn as_apitoken_set(&self) -> Option<&ValueSetApiToken> {
+        Some(self)
+    }
+
+    fn as_apitoken_set_mut(&mut self) -> Option<&mut ValueSetApiToken> {
+        Some(self)
+    }
+
+    fn as_apitoken_issued_by_iter(
+        &self,
+    ) -> Option<Box<dyn Iterator<Item = (Uuid, IdentityId)> + '_>> {
+        Some(Box::new(
+            self.map.iter().map(|(u, at)| (*u, at.issued_by.clone())),
+        ))
+    }
+
     fn as_ref_uuid_iter(&self) -> Option<Box<dyn Iterator<Item = Uuid> + '_>> {
         // This is what ties us as a type that can be refint checked.
         Some(Box::new(self.map.keys().copied()))
     }
+
+    fn repl_merge_valueset(&self, older: &ValueSet, trim_cid: &Cid) -> Option<ValueSet> {
+        // If the older value has a different type - return nothing, we
+        // just take the newer value.
+        if !check_repl_merge_syntax(SyntaxType::ApiToken, older) {
+            return None;
+        }
+        let b = older.as_apitoken_map()?;
+        let mut map = self.map.clone();
+        Self::merge_map(&mut map, b);
+
+        let mut vs = Box::new(ValueSetApiToken { map });
+
+        vs.trim(trim_cid);
+
+        Some(vs)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{ValueSetOauth2Session, ValueSetSession, SESSION_MAXIMUM};
-    use crate::prelude::{IdentityId, SessionScope, Uuid, ValueSet, UUID_SYSTEM};
+    use super::{
+        cascade_revoke, dbv_to_session, revoked_at_timestamp, session_to_dbv, SessionDiff,
+        TrimPolicy, ValueSetApiToken, ValueSetOauth2Session, ValueSetSession, SESSION_MAXIMUM,
+    };
+    use crate::be::dbvalue::{
+        DbValueAccessScopeV1, DbValueApiToken, DbValueApiTokenScopeV1, DbValueAuthTypeV1,
+        DbValueIdentityId, DbValueOauth2Session, DbValueSession, DbValueSessionStateV1,
+        DbValueSessionStateV2, DbValueTimestampV1,
+    };
+    use crate::prelude::*;
     use crate::repl::cid::Cid;
-    use crate::value::{AuthType, Oauth2Session, Session, SessionState};
+    use crate::value::{
+        ApiToken, ApiTokenConstraint, ApiTokenScope, AuthType, Oauth2Session, Session,
+        SessionDisabled, SessionExtMetadata, SessionScope, SessionState,
+    };
+    use crate::valueset::{DbValueSetV2, ScimResolveStatus, ValueSetT};
+    use kanidm_proto::scim_v1::server::{ScimOAuth2Session, ScimValueKanidm};
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::time::Duration;
     use time::OffsetDateTime;
 
+    /// A read only passkey session labelled "hacks", issued by the system at the unix
+    /// epoch and never expiring. Tests override only the fields they depend on.
+    fn test_session() -> Session {
+        Session::builder(OffsetDateTime::UNIX_EPOCH, AuthType::Passkey)
+            .label("hacks")
+            .cred_id(Uuid::new_v4())
+            .build()
+    }
+
+    /// As per [`test_session`], a read only api token labelled "hacks".
+    fn test_api_token() -> ApiToken {
+        ApiToken::builder(OffsetDateTime::UNIX_EPOCH)
+            .label("hacks")
+            .build()
+    }
+
+    /// As per [`test_session`], an oauth2 session of a new resource server with no
+    /// parent session.
+    fn test_oauth2_session() -> Oauth2Session {
+        Oauth2Session {
+            state: SessionState::NeverExpires,
+            issued_at: OffsetDateTime::UNIX_EPOCH,
+            parent: None,
+            rs_uuid: Uuid::new_v4(),
+            granted_scopes: BTreeSet::new(),
+            has_refresh: false,
+            last_refreshed: None,
+            origin: None,
+        }
+    }
+
     #[test]
     fn test_valueset_session_purge() {
         let s_uuid = Uuid::new_v4();
@@ -1337,6 +3359,11 @@ mod tests {
                 scope: SessionScope::ReadOnly,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                bound_to: None,
+                last_active: None,
+                pinned: false,
+                service_account: false,
+                disabled: None,
             },
         );
 
@@ -1372,6 +3399,11 @@ mod tests {
                 scope: SessionScope::ReadOnly,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                bound_to: None,
+                last_active: None,
+                pinned: false,
+                service_account: false,
+                disabled: None,
             },
         );
 
@@ -1387,6 +3419,11 @@ mod tests {
                 scope: SessionScope::ReadOnly,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                bound_to: None,
+                last_active: None,
+                pinned: false,
+                service_account: false,
+                disabled: None,
             },
         );
 
@@ -1417,6 +3454,11 @@ mod tests {
                 scope: SessionScope::ReadOnly,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                bound_to: None,
+                last_active: None,
+                pinned: false,
+                service_account: false,
+                disabled: None,
             },
         );
 
@@ -1432,6 +3474,11 @@ mod tests {
                 scope: SessionScope::ReadOnly,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                bound_to: None,
+                last_active: None,
+                pinned: false,
+                service_account: false,
+                disabled: None,
             },
         );
 
@@ -1465,6 +3512,11 @@ mod tests {
                 scope: SessionScope::ReadOnly,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                bound_to: None,
+                last_active: None,
+                pinned: false,
+                service_account: false,
+                disabled: None,
             },
         );
 
@@ -1481,6 +3533,11 @@ mod tests {
                     scope: SessionScope::ReadOnly,
                     type_: AuthType::Passkey,
                     ext_metadata: Default::default(),
+                    bound_to: None,
+                    last_active: None,
+                    pinned: false,
+                    service_account: false,
+                    disabled: None,
                 },
             ),
             (
@@ -1495,6 +3552,11 @@ mod tests {
                     scope: SessionScope::ReadOnly,
                     type_: AuthType::Passkey,
                     ext_metadata: Default::default(),
+                    bound_to: None,
+                    last_active: None,
+                    pinned: false,
+                    service_account: false,
+                    disabled: None,
                 },
             ),
         ])
@@ -1532,6 +3594,11 @@ mod tests {
                 scope: SessionScope::ReadOnly,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                bound_to: None,
+                last_active: None,
+                pinned: false,
+                service_account: false,
+                disabled: None,
             },
         );
 
@@ -1548,6 +3615,11 @@ mod tests {
                     scope: SessionScope::ReadOnly,
                     type_: AuthType::Passkey,
                     ext_metadata: Default::default(),
+                    bound_to: None,
+                    last_active: None,
+                    pinned: false,
+                    service_account: false,
+                    disabled: None,
                 },
             ),
             (
@@ -1562,6 +3634,11 @@ mod tests {
                     scope: SessionScope::ReadOnly,
                     type_: AuthType::Passkey,
                     ext_metadata: Default::default(),
+                    bound_to: None,
+                    last_active: None,
+                    pinned: false,
+                    service_account: false,
+                    disabled: None,
                 },
             ),
         ])
@@ -1603,6 +3680,11 @@ mod tests {
                     scope: SessionScope::ReadOnly,
                     type_: AuthType::Passkey,
                     ext_metadata: Default::default(),
+                    bound_to: None,
+                    last_active: None,
+                    pinned: false,
+                    service_account: false,
+                    disabled: None,
                 },
             ),
             (
@@ -1617,6 +3699,11 @@ mod tests {
                     scope: SessionScope::ReadOnly,
                     type_: AuthType::Passkey,
                     ext_metadata: Default::default(),
+                    bound_to: None,
+                    last_active: None,
+                    pinned: false,
+                    service_account: false,
+                    disabled: None,
                 },
             ),
             (
@@ -1631,6 +3718,11 @@ mod tests {
                     scope: SessionScope::ReadOnly,
                     type_: AuthType::Passkey,
                     ext_metadata: Default::default(),
+                    bound_to: None,
+                    last_active: None,
+                    pinned: false,
+                    service_account: false,
+                    disabled: None,
                 },
             ),
         ])
@@ -1663,6 +3755,11 @@ mod tests {
                 scope: SessionScope::ReadOnly,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                bound_to: None,
+                last_active: None,
+                pinned: false,
+                service_account: false,
+                disabled: None,
             },
         ))
         .chain((0..SESSION_MAXIMUM).map(|_| {
@@ -1678,6 +3775,11 @@ mod tests {
                     scope: SessionScope::ReadOnly,
                     type_: AuthType::Passkey,
                     ext_metadata: Default::default(),
+                    bound_to: None,
+                    last_active: None,
+                    pinned: false,
+                    service_account: false,
+                    disabled: None,
                 },
             )
         }));
@@ -1686,7 +3788,10 @@ mod tests {
 
         assert!(vs_a.len() > SESSION_MAXIMUM);
 
-        vs_a.trim(&zero_cid);
+        assert_eq!(
+            vs_a.trim_to_maximum(SESSION_MAXIMUM, &zero_cid, &zero_cid),
+            1
+        );
 
         assert_eq!(vs_a.len(), SESSION_MAXIMUM);
 
@@ -1706,6 +3811,10 @@ mod tests {
                 issued_at: OffsetDateTime::now_utc(),
                 parent: Some(Uuid::new_v4()),
                 rs_uuid: Uuid::new_v4(),
+                granted_scopes: Default::default(),
+                has_refresh: false,
+                last_refreshed: None,
+                origin: None,
             },
         );
 
@@ -1737,6 +3846,10 @@ mod tests {
                 issued_at: OffsetDateTime::now_utc(),
                 parent: Some(Uuid::new_v4()),
                 rs_uuid: Uuid::new_v4(),
+                granted_scopes: Default::default(),
+                has_refresh: false,
+                last_refreshed: None,
+                origin: None,
             },
         );
 
@@ -1748,6 +3861,10 @@ mod tests {
                 issued_at: OffsetDateTime::now_utc(),
                 parent: Some(Uuid::new_v4()),
                 rs_uuid: Uuid::new_v4(),
+                granted_scopes: Default::default(),
+                has_refresh: false,
+                last_refreshed: None,
+                origin: None,
             },
         );
 
@@ -1774,6 +3891,10 @@ mod tests {
                 issued_at: OffsetDateTime::now_utc(),
                 parent: Some(Uuid::new_v4()),
                 rs_uuid: Uuid::new_v4(),
+                granted_scopes: Default::default(),
+                has_refresh: false,
+                last_refreshed: None,
+                origin: None,
             },
         );
 
@@ -1785,6 +3906,10 @@ mod tests {
                 issued_at: OffsetDateTime::now_utc(),
                 parent: Some(Uuid::new_v4()),
                 rs_uuid: Uuid::new_v4(),
+                granted_scopes: Default::default(),
+                has_refresh: false,
+                last_refreshed: None,
+                origin: None,
             },
         );
 
@@ -1814,6 +3939,10 @@ mod tests {
                 issued_at: OffsetDateTime::now_utc(),
                 parent: Some(Uuid::new_v4()),
                 rs_uuid: Uuid::new_v4(),
+                granted_scopes: Default::default(),
+                has_refresh: false,
+                last_refreshed: None,
+                origin: None,
             },
         );
 
@@ -1826,6 +3955,10 @@ mod tests {
                     issued_at: OffsetDateTime::now_utc(),
                     parent: Some(Uuid::new_v4()),
                     rs_uuid: Uuid::new_v4(),
+                    granted_scopes: Default::default(),
+                    has_refresh: false,
+                    last_refreshed: None,
+                    origin: None,
                 },
             ),
             (
@@ -1836,6 +3969,10 @@ mod tests {
                     issued_at: OffsetDateTime::now_utc(),
                     parent: Some(Uuid::new_v4()),
                     rs_uuid: Uuid::new_v4(),
+                    granted_scopes: Default::default(),
+                    has_refresh: false,
+                    last_refreshed: None,
+                    origin: None,
                 },
             ),
         ])
@@ -1871,6 +4008,10 @@ mod tests {
                 issued_at: OffsetDateTime::now_utc(),
                 parent: Some(Uuid::new_v4()),
                 rs_uuid: Uuid::new_v4(),
+                granted_scopes: Default::default(),
+                has_refresh: false,
+                last_refreshed: None,
+                origin: None,
             },
         );
 
@@ -1883,6 +4024,10 @@ mod tests {
                     issued_at: OffsetDateTime::now_utc(),
                     parent: Some(Uuid::new_v4()),
                     rs_uuid: Uuid::new_v4(),
+                    granted_scopes: Default::default(),
+                    has_refresh: false,
+                    last_refreshed: None,
+                    origin: None,
                 },
             ),
             (
@@ -1893,6 +4038,10 @@ mod tests {
                     issued_at: OffsetDateTime::now_utc(),
                     parent: Some(Uuid::new_v4()),
                     rs_uuid: Uuid::new_v4(),
+                    granted_scopes: Default::default(),
+                    has_refresh: false,
+                    last_refreshed: None,
+                    origin: None,
                 },
             ),
         ])
@@ -1932,6 +4081,10 @@ mod tests {
                     issued_at: OffsetDateTime::now_utc(),
                     parent: Some(Uuid::new_v4()),
                     rs_uuid: Uuid::new_v4(),
+                    granted_scopes: Default::default(),
+                    has_refresh: false,
+                    last_refreshed: None,
+                    origin: None,
                 },
             ),
             (
@@ -1942,6 +4095,10 @@ mod tests {
                     issued_at: OffsetDateTime::now_utc(),
                     parent: Some(Uuid::new_v4()),
                     rs_uuid: Uuid::new_v4(),
+                    granted_scopes: Default::default(),
+                    has_refresh: false,
+                    last_refreshed: None,
+                    origin: None,
                 },
             ),
             (
@@ -1952,6 +4109,10 @@ mod tests {
                     issued_at: OffsetDateTime::now_utc(),
                     parent: Some(Uuid::new_v4()),
                     rs_uuid: Uuid::new_v4(),
+                    granted_scopes: Default::default(),
+                    has_refresh: false,
+                    last_refreshed: None,
+                    origin: None,
                 },
             ),
         ])
@@ -1983,6 +4144,11 @@ mod tests {
                 scope: SessionScope::ReadOnly,
                 type_: AuthType::Passkey,
                 ext_metadata: Default::default(),
+                bound_to: None,
+                last_active: None,
+                pinned: false,
+                service_account: false,
+                disabled: None,
             },
         );
 
@@ -1993,7 +4159,11 @@ mod tests {
     "credentialId": "3a163ca0-4762-4620-a188-06b750c84c86",
     "issuedAt": "1970-01-01T00:00:00Z",
     "issuedBy": "00000000-0000-0000-0000-ffffff000000",
+    "issuedByType": "internal",
     "id": "3a163ca0-4762-4620-a188-06b750c84c86",
+    "label": "hacks",
+    "pinned": false,
+    "serviceAccount": false,
     "sessionScope": "read_only"
   }
 ]
@@ -2001,8 +4171,10 @@ mod tests {
         crate::valueset::scim_json_reflexive(&vs, data);
     }
 
-    #[test]
-    fn test_scim_oauth2_session() {
+    #[qs_test]
+    async fn test_scim_oauth2_session(server: &QueryServer) {
+        let mut write_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
         let s_uuid = uuid::uuid!("3a163ca0-4762-4620-a188-06b750c84c86");
 
         let vs: ValueSet = ValueSetOauth2Session::new(
@@ -2012,20 +4184,5056 @@ mod tests {
                 issued_at: OffsetDateTime::UNIX_EPOCH,
                 parent: Some(s_uuid),
                 rs_uuid: s_uuid,
+                granted_scopes: ["openid".to_string(), "email".to_string()].into(),
+                has_refresh: false,
+                last_refreshed: None,
+                origin: None,
             },
         );
 
+        // The client doesn't exist, so no display name is present.
         let data = r#"
 [
   {
     "clientId": "3a163ca0-4762-4620-a188-06b750c84c86",
     "issuedAt": "1970-01-01T00:00:00Z",
     "parentId": "3a163ca0-4762-4620-a188-06b750c84c86",
-    "id": "3a163ca0-4762-4620-a188-06b750c84c86"
+    "id": "3a163ca0-4762-4620-a188-06b750c84c86",
+    "scopes": ["email", "openid"],
+    "hasRefresh": false
   }
 ]
         "#;
 
-        crate::valueset::scim_json_reflexive(&vs, data);
+        crate::valueset::scim_json_reflexive_unresolved(&mut write_txn, &vs, data);
+
+        // Sessions issued before scopes were recorded have none.
+        let session: ScimOAuth2Session = serde_json::from_str(
+            r#"{
+                "clientId": "3a163ca0-4762-4620-a188-06b750c84c86",
+                "issuedAt": "1970-01-01T00:00:00Z",
+                "id": "3a163ca0-4762-4620-a188-06b750c84c86",
+                "hasRefresh": false
+            }"#,
+        )
+        .expect("Failed to deserialise session");
+        assert!(session.scopes.is_empty());
+
+        assert!(write_txn.commit().is_ok());
+    }
+
+    #[test]
+    fn test_scim_session_revoked_by_server() {
+        let s_uuid = uuid::uuid!("3a163ca0-4762-4620-a188-06b750c84c86");
+        let server_uuid = uuid::uuid!("b6ac4ee6-5d2b-4b9c-9d1c-3f1bf6a9b4a1");
+
+        let vs: ValueSet = ValueSetSession::new(
+            s_uuid,
+            Session::builder(OffsetDateTime::UNIX_EPOCH, AuthType::Passkey)
+                .label("hacks")
+                .state(SessionState::RevokedAt(Cid::new(
+                    server_uuid,
+                    Duration::from_secs(60),
+                )))
+                .cred_id(s_uuid)
+                .scope(SessionScope::ReadOnly)
+                .build(),
+        );
+
+        let data = r#"
+[
+  {
+    "authType": "passkey",
+    "credentialId": "3a163ca0-4762-4620-a188-06b750c84c86",
+    "issuedAt": "1970-01-01T00:00:00Z",
+    "issuedBy": "00000000-0000-0000-0000-ffffff000000",
+    "issuedByType": "internal",
+    "id": "3a163ca0-4762-4620-a188-06b750c84c86",
+    "label": "hacks",
+    "pinned": false,
+    "revoked": "1970-01-01T00:01:00Z",
+    "revokedByServer": "b6ac4ee6-5d2b-4b9c-9d1c-3f1bf6a9b4a1",
+    "serviceAccount": false,
+    "sessionScope": "read_only"
+  }
+]
+        "#;
+        crate::valueset::scim_json_reflexive(&vs, data);
+    }
+
+    #[qs_test]
+    async fn test_scim_oauth2_session_revoked_by_server(server: &QueryServer) {
+        let mut write_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let s_uuid = uuid::uuid!("3a163ca0-4762-4620-a188-06b750c84c86");
+        let server_uuid = uuid::uuid!("b6ac4ee6-5d2b-4b9c-9d1c-3f1bf6a9b4a1");
+
+        let vs: ValueSet = ValueSetOauth2Session::new(
+            s_uuid,
+            Oauth2Session {
+                state: SessionState::RevokedAt(Cid::new(server_uuid, Duration::from_secs(60))),
+                issued_at: OffsetDateTime::UNIX_EPOCH,
+                parent: None,
+                rs_uuid: s_uuid,
+                granted_scopes: ["openid".to_string()].into(),
+                has_refresh: false,
+                last_refreshed: None,
+                origin: None,
+            },
+        );
+
+        let data = r#"
+[
+  {
+    "clientId": "3a163ca0-4762-4620-a188-06b750c84c86",
+    "issuedAt": "1970-01-01T00:00:00Z",
+    "id": "3a163ca0-4762-4620-a188-06b750c84c86",
+    "revoked": "1970-01-01T00:01:00Z",
+    "revokedByServer": "b6ac4ee6-5d2b-4b9c-9d1c-3f1bf6a9b4a1",
+    "scopes": ["openid"],
+    "hasRefresh": false
+  }
+]
+        "#;
+
+        crate::valueset::scim_json_reflexive_unresolved(&mut write_txn, &vs, data);
+
+        assert!(write_txn.commit().is_ok());
+    }
+
+    #[qs_test]
+    async fn test_scim_oauth2_session_client_display_name(server: &QueryServer) {
+        let mut write_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let rs_uuid = uuid::uuid!("4d21d04a-dc0e-42eb-b850-34dd180b107f");
+        assert!(write_txn
+            .internal_create(vec![entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (Attribute::Class, EntryClass::Account.to_value()),
+                (
+                    Attribute::Class,
+                    EntryClass::OAuth2ResourceServer.to_value()
+                ),
+                (Attribute::Uuid, Value::Uuid(rs_uuid)),
+                (Attribute::Name, Value::new_iname("test_resource_server")),
+                (Attribute::DisplayName, Value::new_utf8s("Nextcloud")),
+                (
+                    Attribute::OAuth2RsOriginLanding,
+                    Value::new_url_s("https://demo.example.com").unwrap()
+                )
+            )])
+            .is_ok());
+
+        let s_uuid = uuid::uuid!("3a163ca0-4762-4620-a188-06b750c84c86");
+
+        let vs: ValueSet = ValueSetOauth2Session::new(
+            s_uuid,
+            Oauth2Session {
+                state: SessionState::NeverExpires,
+                issued_at: OffsetDateTime::UNIX_EPOCH,
+                parent: None,
+                rs_uuid,
+                granted_scopes: ["openid".to_string()].into(),
+                has_refresh: false,
+                last_refreshed: None,
+                origin: None,
+            },
+        );
+
+        let data = r#"
+[
+  {
+    "clientId": "4d21d04a-dc0e-42eb-b850-34dd180b107f",
+    "clientDisplayName": "Nextcloud",
+    "issuedAt": "1970-01-01T00:00:00Z",
+    "id": "3a163ca0-4762-4620-a188-06b750c84c86",
+    "scopes": ["openid"],
+    "hasRefresh": false
+  }
+]
+        "#;
+
+        crate::valueset::scim_json_reflexive_unresolved(&mut write_txn, &vs, data);
+
+        assert!(write_txn.commit().is_ok());
+    }
+
+    #[test]
+    fn test_valueset_oauth2_session_scopes_db_roundtrip() {
+        let s_uuid = Uuid::new_v4();
+        let rs_uuid = Uuid::new_v4();
+
+        let vs: ValueSet = ValueSetOauth2Session::new(
+            s_uuid,
+            Oauth2Session {
+                state: SessionState::NeverExpires,
+                issued_at: OffsetDateTime::UNIX_EPOCH,
+                parent: Some(Uuid::new_v4()),
+                rs_uuid,
+                granted_scopes: ["openid".to_string(), "groups".to_string()].into(),
+                has_refresh: false,
+                last_refreshed: None,
+                origin: None,
+            },
+        );
+
+        let vs2 = crate::valueset::from_db_valueset_v2(vs.to_db_valueset_v2())
+            .expect("Failed to construct vs2 from dbvalue");
+
+        assert_eq!(&vs, &vs2);
+
+        // Older records carry no scope information and load with an empty set.
+        let vs3 = ValueSetOauth2Session::from_dbvs2(vec![DbValueOauth2Session::V3 {
+            refer: s_uuid,
+            parent: None,
+            state: DbValueSessionStateV1::Never,
+            issued_at: "1970-01-01T00:00:00Z".to_string(),
+            rs_uuid,
+        }])
+        .expect("Failed to construct vs3 from dbvalue");
+
+        let session = vs3
+            .as_oauth2session_map()
+            .and_then(|map| map.get(&s_uuid))
+            .expect("Unable to locate session");
+
+        assert!(session.granted_scopes.is_empty());
+    }
+
+    #[test]
+    fn test_valueset_oauth2_session_dbv_migrate() {
+        let s_uuid = Uuid::new_v4();
+        let p_uuid = Uuid::new_v4();
+        let rs_uuid = Uuid::new_v4();
+
+        let dbv = DbValueOauth2Session::V1 {
+            refer: s_uuid,
+            parent: p_uuid,
+            expiry: Some("1970-01-02T00:00:00Z".to_string()),
+            issued_at: "1970-01-01T00:00:00Z".to_string(),
+            rs_uuid,
+        };
+        assert!(dbv.needs_migration());
+
+        let vs = ValueSetOauth2Session::from_dbvs2(vec![dbv]).expect("Unable to load sessions");
+
+        let session = vs
+            .as_oauth2session_map()
+            .and_then(|map| map.get(&s_uuid))
+            .expect("Unable to locate session");
+
+        // The mandatory V1 parent is retained.
+        assert_eq!(session.parent, Some(p_uuid));
+        assert_eq!(
+            session.state,
+            SessionState::ExpiresAt(OffsetDateTime::UNIX_EPOCH + Duration::from_secs(86400))
+        );
+
+        // We always write the newest version, so a single write upgrades the set.
+        let DbValueSetV2::Oauth2Session(dbvs) = vs.to_db_valueset_v2() else {
+            panic!("Invalid db valueset type");
+        };
+        assert!(dbvs.iter().all(|dbv| !dbv.needs_migration()));
+        assert!(matches!(
+            dbvs.as_slice(),
+            [DbValueOauth2Session::V6 {
+                refer,
+                parent: Some(parent),
+                state: DbValueSessionStateV1::ExpiresAt(_),
+                ..
+            }] if *refer == s_uuid && *parent == p_uuid
+        ));
+
+        let vs_2 = ValueSetOauth2Session::from_dbvs2(dbvs).expect("Unable to reload sessions");
+        assert!(vs.equal(&vs_2));
+    }
+
+    #[test]
+    fn test_valueset_oauth2_session_extend_scopes() {
+        let s_uuid = Uuid::new_v4();
+        let exp = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(300);
+
+        let session = Oauth2Session {
+            state: SessionState::ExpiresAt(exp),
+            issued_at: OffsetDateTime::UNIX_EPOCH,
+            parent: Some(Uuid::new_v4()),
+            rs_uuid: Uuid::new_v4(),
+            granted_scopes: ["email".to_string(), "openid".to_string()].into(),
+            has_refresh: false,
+            last_refreshed: None,
+            origin: None,
+        };
+
+        let mut vs: ValueSet = ValueSetOauth2Session::new(s_uuid, session.clone());
+
+        // Extending with the same scopes is accepted.
+        let exp = exp + Duration::from_secs(300);
+        assert_eq!(
+            vs.insert_checked(Value::Oauth2Session(
+                s_uuid,
+                Oauth2Session {
+                    state: SessionState::ExpiresAt(exp),
+                    ..session.clone()
+                },
+            )),
+            Ok(true)
+        );
+
+        // Narrowing the scopes is accepted.
+        let exp = exp + Duration::from_secs(300);
+        assert_eq!(
+            vs.insert_checked(Value::Oauth2Session(
+                s_uuid,
+                Oauth2Session {
+                    state: SessionState::ExpiresAt(exp),
+                    granted_scopes: ["openid".to_string()].into(),
+                    ..session.clone()
+                },
+            )),
+            Ok(true)
+        );
+
+        // The union of the scopes is kept, so returning to the original set is accepted.
+        let exp = exp + Duration::from_secs(300);
+        assert_eq!(
+            vs.insert_checked(Value::Oauth2Session(
+                s_uuid,
+                Oauth2Session {
+                    state: SessionState::ExpiresAt(exp),
+                    ..session.clone()
+                },
+            )),
+            Ok(true)
+        );
+
+        let broader = Oauth2Session {
+            granted_scopes: [
+                "email".to_string(),
+                "groups".to_string(),
+                "openid".to_string(),
+            ]
+            .into(),
+            ..session.clone()
+        };
+
+        // Broadening the scopes beyond those granted is rejected.
+        assert_eq!(
+            vs.insert_checked(Value::Oauth2Session(
+                s_uuid,
+                Oauth2Session {
+                    state: SessionState::ExpiresAt(exp + Duration::from_secs(300)),
+                    ..broader.clone()
+                },
+            )),
+            Err(OperationError::InvalidValueState)
+        );
+
+        // An older value that does not replace the session is ignored, whatever its scopes.
+        assert_eq!(
+            vs.insert_checked(Value::Oauth2Session(
+                s_uuid,
+                Oauth2Session {
+                    state: SessionState::ExpiresAt(exp - Duration::from_secs(300)),
+                    ..broader.clone()
+                },
+            )),
+            Ok(false)
+        );
+
+        let current = vs
+            .as_oauth2session_map()
+            .and_then(|map| map.get(&s_uuid))
+            .expect("Unable to locate session");
+
+        assert_eq!(current.state, SessionState::ExpiresAt(exp));
+        assert_eq!(current.granted_scopes, session.granted_scopes);
+
+        // A revocation always applies, and leaves the granted scopes as they were.
+        assert_eq!(
+            vs.insert_checked(Value::Oauth2Session(
+                s_uuid,
+                Oauth2Session {
+                    state: SessionState::RevokedAt(Cid::new_count(1)),
+                    ..broader
+                },
+            )),
+            Ok(true)
+        );
+
+        let current = vs
+            .as_oauth2session_map()
+            .and_then(|map| map.get(&s_uuid))
+            .expect("Unable to locate session");
+
+        assert_eq!(current.state, SessionState::RevokedAt(Cid::new_count(1)));
+        assert_eq!(current.granted_scopes, session.granted_scopes);
+    }
+
+    #[test]
+    fn test_valueset_oauth2_session_prune_orphaned() {
+        let live_uuid = Uuid::new_v4();
+        let orphan_uuid = Uuid::new_v4();
+        let parentless_uuid = Uuid::new_v4();
+        let revoked_uuid = Uuid::new_v4();
+
+        let live_parent = Uuid::new_v4();
+        let dead_parent = Uuid::new_v4();
+        let zero_cid = Cid::new_zero();
+        let one_cid = Cid::new_count(1);
+
+        let mut vs = ValueSetOauth2Session::from_iter([
+            (
+                live_uuid,
+                Oauth2Session {
+                    state: SessionState::NeverExpires,
+                    issued_at: OffsetDateTime::UNIX_EPOCH,
+                    parent: Some(live_parent),
+                    rs_uuid: Uuid::new_v4(),
+                    granted_scopes: Default::default(),
+                    has_refresh: false,
+                    last_refreshed: None,
+                    origin: None,
+                },
+            ),
+            (
+                orphan_uuid,
+                Oauth2Session {
+                    state: SessionState::NeverExpires,
+                    issued_at: OffsetDateTime::UNIX_EPOCH,
+                    parent: Some(dead_parent),
+                    rs_uuid: Uuid::new_v4(),
+                    granted_scopes: Default::default(),
+                    has_refresh: false,
+                    last_refreshed: None,
+                    origin: None,
+                },
+            ),
+            (
+                parentless_uuid,
+                Oauth2Session {
+                    state: SessionState::NeverExpires,
+                    issued_at: OffsetDateTime::UNIX_EPOCH,
+                    parent: None,
+                    rs_uuid: Uuid::new_v4(),
+                    granted_scopes: Default::default(),
+                    has_refresh: false,
+                    last_refreshed: None,
+                    origin: None,
+                },
+            ),
+            (
+                revoked_uuid,
+                Oauth2Session {
+                    state: SessionState::RevokedAt(zero_cid.clone()),
+                    issued_at: OffsetDateTime::UNIX_EPOCH,
+                    parent: Some(dead_parent),
+                    rs_uuid: Uuid::new_v4(),
+                    granted_scopes: Default::default(),
+                    has_refresh: false,
+                    last_refreshed: None,
+                    origin: None,
+                },
+            ),
+        ])
+        .expect("Unable to build valueset oauth2 session");
+
+        let live_parents = BTreeSet::from([live_parent]);
+        let grace = Duration::from_secs(60);
+
+        // Within the grace window nothing is pruned.
+        let now = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(30);
+        assert!(!vs.has_orphaned(&live_parents, now, grace));
+        assert_eq!(vs.prune_orphaned(&live_parents, now, grace, &one_cid), 0);
+        assert_eq!(
+            vs.prune_orphaned(&live_parents, now, Duration::MAX, &one_cid),
+            0
+        );
+
+        let now = OffsetDateTime::UNIX_EPOCH + grace;
+        assert!(vs.has_orphaned(&live_parents, now, grace));
+        assert_eq!(vs.prune_orphaned(&live_parents, now, grace, &one_cid), 1);
+
+        let sessions = vs
+            .as_oauth2session_map()
+            .expect("Unable to locate sessions");
+
+        assert_eq!(
+            sessions.get(&live_uuid).map(|s| &s.state),
+            Some(&SessionState::NeverExpires)
+        );
+        assert_eq!(
+            sessions.get(&orphan_uuid).map(|s| &s.state),
+            Some(&SessionState::RevokedAt(one_cid.clone()))
+        );
+        assert_eq!(
+            sessions.get(&parentless_uuid).map(|s| &s.state),
+            Some(&SessionState::NeverExpires)
+        );
+        // Already revoked sessions retain their original revocation.
+        assert_eq!(
+            sessions.get(&revoked_uuid).map(|s| &s.state),
+            Some(&SessionState::RevokedAt(zero_cid))
+        );
+
+        // Repeated prunes are a no-op.
+        assert!(!vs.has_orphaned(&live_parents, now, grace));
+        assert_eq!(vs.prune_orphaned(&live_parents, now, grace, &one_cid), 0);
+    }
+
+    #[test]
+    fn test_valueset_oauth2_session_trim_to_maximum() {
+        let trim_cid = Cid::new_count(2);
+        let cid = Cid::new_count(3);
+
+        let session = |secs: u64, state: SessionState| Oauth2Session {
+            state,
+            issued_at: OffsetDateTime::UNIX_EPOCH + Duration::from_secs(secs),
+            ..test_oauth2_session()
+        };
+
+        let past_trim_uuid = Uuid::new_v4();
+        let within_trim_uuid = Uuid::new_v4();
+        let oldest_uuid = Uuid::new_v4();
+        let newest_uuid = Uuid::new_v4();
+
+        let mut vs = ValueSetOauth2Session::from_iter([
+            (
+                past_trim_uuid,
+                session(0, SessionState::RevokedAt(Cid::new_count(1))),
+            ),
+            (
+                within_trim_uuid,
+                session(1, SessionState::RevokedAt(trim_cid.clone())),
+            ),
+            (oldest_uuid, session(2, SessionState::NeverExpires)),
+            (newest_uuid, session(3, SessionState::NeverExpires)),
+        ])
+        .expect("Unable to build valueset oauth2 session");
+
+        // The revocation past the trim window is removed, and the oldest remaining session
+        // is revoked rather than removed.
+        assert_eq!(vs.trim_to_maximum(1, &cid, &trim_cid), 2);
+
+        let state = |u: &Uuid| vs.map.get(u).map(|s| s.state.clone());
+        assert_eq!(state(&past_trim_uuid), None);
+        assert_eq!(
+            state(&within_trim_uuid),
+            Some(SessionState::RevokedAt(trim_cid.clone()))
+        );
+        assert_eq!(
+            state(&oldest_uuid),
+            Some(SessionState::RevokedAt(cid.clone()))
+        );
+        assert_eq!(state(&newest_uuid), Some(SessionState::NeverExpires));
+
+        // The filter no longer holds the resource server of the removed session.
+        assert_eq!(
+            vs.rs_filter,
+            vs.map
+                .values()
+                .fold(u128::MIN, |acc, s| acc | s.rs_uuid.as_u128())
+        );
+
+        // Repeated trims are a no-op.
+        assert_eq!(vs.trim_to_maximum(1, &cid, &trim_cid), 0);
+    }
+
+    #[test]
+    fn test_valueset_session_cascade_revoke() {
+        let parent_uuid = Uuid::new_v4();
+        let other_uuid = Uuid::new_v4();
+        let (child_a, child_b, unrelated) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let one_cid = Cid::new_count(1);
+
+        let oauth2_session = |parent: Uuid| Oauth2Session {
+            parent: Some(parent),
+            ..test_oauth2_session()
+        };
+
+        let mut sessions = ValueSetSession::from_iter([
+            (parent_uuid, test_session()),
+            (other_uuid, test_session()),
+        ])
+        .expect("Unable to build valueset session");
+
+        let mut oauth2 = ValueSetOauth2Session::from_iter([
+            (child_a, oauth2_session(parent_uuid)),
+            (child_b, oauth2_session(parent_uuid)),
+            (unrelated, oauth2_session(other_uuid)),
+        ])
+        .expect("Unable to build valueset oauth2 session");
+
+        assert_eq!(
+            cascade_revoke(&mut sessions, &mut oauth2, &parent_uuid, &one_cid),
+            3
+        );
+
+        let revoked = SessionState::RevokedAt(one_cid.clone());
+        assert_eq!(
+            sessions.map.get(&parent_uuid).map(|s| &s.state),
+            Some(&revoked)
+        );
+        assert_eq!(oauth2.map.get(&child_a).map(|s| &s.state), Some(&revoked));
+        assert_eq!(oauth2.map.get(&child_b).map(|s| &s.state), Some(&revoked));
+
+        // Sessions of another parent are untouched.
+        assert_eq!(
+            sessions.map.get(&other_uuid).map(|s| &s.state),
+            Some(&SessionState::NeverExpires)
+        );
+        assert_eq!(
+            oauth2.map.get(&unrelated).map(|s| &s.state),
+            Some(&SessionState::NeverExpires)
+        );
+
+        // Already revoked sessions are not counted again.
+        assert_eq!(
+            cascade_revoke(&mut sessions, &mut oauth2, &parent_uuid, &Cid::new_count(2)),
+            0
+        );
+        assert_eq!(oauth2.map.get(&child_a).map(|s| &s.state), Some(&revoked));
+    }
+
+    #[test]
+    fn test_valueset_oauth2_session_refresh_db_roundtrip() {
+        let s_uuid = Uuid::new_v4();
+
+        let vs: ValueSet = ValueSetOauth2Session::new(
+            s_uuid,
+            Oauth2Session {
+                state: SessionState::NeverExpires,
+                issued_at: OffsetDateTime::UNIX_EPOCH,
+                parent: Some(Uuid::new_v4()),
+                rs_uuid: Uuid::new_v4(),
+                granted_scopes: Default::default(),
+                has_refresh: true,
+                last_refreshed: Some(OffsetDateTime::UNIX_EPOCH + Duration::from_secs(60)),
+                origin: None,
+            },
+        );
+
+        let vs2 = crate::valueset::from_db_valueset_v2(vs.to_db_valueset_v2())
+            .expect("Failed to construct vs2 from dbvalue");
+
+        assert_eq!(&vs, &vs2);
+    }
+
+    #[test]
+    fn test_valueset_oauth2_session_record_refresh() {
+        let s_uuid = Uuid::new_v4();
+        let r_uuid = Uuid::new_v4();
+        let t1 = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(60);
+        let t2 = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(120);
+
+        let mut vs = ValueSetOauth2Session::from_iter([
+            (
+                s_uuid,
+                Oauth2Session {
+                    state: SessionState::NeverExpires,
+                    issued_at: OffsetDateTime::UNIX_EPOCH,
+                    parent: None,
+                    rs_uuid: Uuid::new_v4(),
+                    granted_scopes: Default::default(),
+                    has_refresh: false,
+                    last_refreshed: None,
+                    origin: None,
+                },
+            ),
+            (
+                r_uuid,
+                Oauth2Session {
+                    state: SessionState::RevokedAt(Cid::new_zero()),
+                    issued_at: OffsetDateTime::UNIX_EPOCH,
+                    parent: None,
+                    rs_uuid: Uuid::new_v4(),
+                    granted_scopes: Default::default(),
+                    has_refresh: false,
+                    last_refreshed: None,
+                    origin: None,
+                },
+            ),
+        ])
+        .expect("Unable to build valueset oauth2 session");
+
+        assert!(vs.record_refresh(&s_uuid, t2));
+        // An older refresh never moves the timestamp backwards.
+        assert!(!vs.record_refresh(&s_uuid, t1));
+        // Revoked and unknown sessions are not updated.
+        assert!(!vs.record_refresh(&r_uuid, t2));
+        assert!(!vs.record_refresh(&Uuid::new_v4(), t2));
+
+        let sessions = vs
+            .as_oauth2session_map()
+            .expect("Unable to locate sessions");
+
+        let session = sessions.get(&s_uuid).expect("Unable to locate session");
+        assert!(session.has_refresh);
+        assert_eq!(session.last_refreshed, Some(t2));
+
+        let session = sessions.get(&r_uuid).expect("Unable to locate session");
+        assert!(!session.has_refresh);
+        assert_eq!(session.last_refreshed, None);
+    }
+
+    #[test]
+    fn test_valueset_oauth2_session_refresh_races_revoke() {
+        let s_uuid = Uuid::new_v4();
+        let rs_uuid = Uuid::new_v4();
+        let t1 = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(60);
+        let t2 = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(120);
+
+        let session = Oauth2Session {
+            state: SessionState::NeverExpires,
+            issued_at: OffsetDateTime::UNIX_EPOCH,
+            parent: None,
+            rs_uuid,
+            granted_scopes: Default::default(),
+            has_refresh: true,
+            last_refreshed: Some(t1),
+            origin: None,
+        };
+
+        // One replica refreshes the session while the other revokes it.
+        let mut refreshed = ValueSetOauth2Session::new(s_uuid, session.clone());
+        assert!(refreshed.record_refresh(&s_uuid, t2));
+        let refreshed: ValueSet = refreshed;
+
+        let revoked: ValueSet = ValueSetOauth2Session::new(
+            s_uuid,
+            Oauth2Session {
+                state: SessionState::RevokedAt(Cid::new_count(1)),
+                ..session
+            },
+        );
+
+        let zero_cid = Cid::new_zero();
+
+        // The revocation must win regardless of the merge direction.
+        for (a, b) in [(&refreshed, &revoked), (&revoked, &refreshed)] {
+            let merged = a
+                .repl_merge_valueset(b, &zero_cid)
+                .expect("Unable to merge valueset");
+
+            let state = merged
+                .as_oauth2session_map()
+                .and_then(|map| map.get(&s_uuid))
+                .map(|s| &s.state);
+
+            assert_eq!(state, Some(&SessionState::RevokedAt(Cid::new_count(1))));
+
+            let mut merged = a.clone();
+            merged.merge(b).expect("Unable to merge valueset");
+
+            let state = merged
+                .as_oauth2session_map()
+                .and_then(|map| map.get(&s_uuid))
+                .map(|s| &s.state);
+
+            assert_eq!(state, Some(&SessionState::RevokedAt(Cid::new_count(1))));
+        }
+    }
+
+    #[test]
+    fn test_valueset_oauth2_session_merge_keeps_latest_refresh() {
+        let s_uuid = Uuid::new_v4();
+        let t1 = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(60);
+        let t2 = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(120);
+
+        let session = Oauth2Session {
+            state: SessionState::NeverExpires,
+            issued_at: OffsetDateTime::UNIX_EPOCH,
+            parent: None,
+            rs_uuid: Uuid::new_v4(),
+            granted_scopes: Default::default(),
+            has_refresh: true,
+            last_refreshed: Some(t2),
+            origin: None,
+        };
+
+        let newer: ValueSet = ValueSetOauth2Session::new(s_uuid, session.clone());
+        let older: ValueSet = ValueSetOauth2Session::new(
+            s_uuid,
+            Oauth2Session {
+                last_refreshed: Some(t1),
+                ..session
+            },
+        );
+
+        let merged = older
+            .repl_merge_valueset(&newer, &Cid::new_zero())
+            .expect("Unable to merge valueset");
+
+        let last_refreshed = merged
+            .as_oauth2session_map()
+            .and_then(|map| map.get(&s_uuid))
+            .and_then(|s| s.last_refreshed);
+
+        assert_eq!(last_refreshed, Some(t2));
+
+        let mut merged = older.clone();
+        merged.merge(&newer).expect("Unable to merge valueset");
+
+        let last_refreshed = merged
+            .as_oauth2session_map()
+            .and_then(|map| map.get(&s_uuid))
+            .and_then(|s| s.last_refreshed);
+
+        assert_eq!(last_refreshed, Some(t2));
+    }
+
+    #[test]
+    fn test_session_dbv_roundtrip() {
+        let s_uuid = Uuid::new_v4();
+        let session = Session {
+            label: "hacks".to_string(),
+            state: SessionState::ExpiresAt(OffsetDateTime::UNIX_EPOCH + Duration::from_secs(300)),
+            issued_at: OffsetDateTime::UNIX_EPOCH,
+            issued_by: IdentityId::User(Uuid::new_v4()),
+            cred_id: Uuid::new_v4(),
+            scope: SessionScope::ReadWrite,
+            type_: AuthType::PasswordTotp,
+            ext_metadata: Default::default(),
+            bound_to: Some("fingerprint".to_string()),
+            last_active: None,
+            pinned: false,
+            service_account: false,
+            disabled: None,
+        };
+
+        let dbv = session_to_dbv(s_uuid, &session);
+        assert!(matches!(dbv, DbValueSession::V5 { refer, .. } if refer == s_uuid));
+
+        let (refer, session2) = dbv_to_session(&dbv).expect("Failed to parse session");
+        assert_eq!(refer, s_uuid);
+        assert_eq!(session, session2);
+
+        // Revoked sessions retain their cid.
+        let session = Session {
+            state: SessionState::RevokedAt(Cid::new_count(1)),
+            ..session
+        };
+
+        let dbv = session_to_dbv(s_uuid, &session);
+        let (_, session2) = dbv_to_session(&dbv).expect("Failed to parse session");
+        assert_eq!(session, session2);
+    }
+
+    #[test]
+    fn test_session_dbv_legacy_dropped() {
+        let refer = Uuid::new_v4();
+        let cred_id = Uuid::new_v4();
+
+        let legacy = [
+            DbValueSession::V1 {
+                refer,
+                label: "hacks".to_string(),
+                expiry: None,
+                issued_at: "1970-01-01T00:00:00Z".to_string(),
+                issued_by: DbValueIdentityId::V1Internal,
+                scope: DbValueAccessScopeV1::ReadOnly,
+            },
+            DbValueSession::V2 {
+                refer,
+                label: "hacks".to_string(),
+                expiry: None,
+                issued_at: "1970-01-01T00:00:00Z".to_string(),
+                issued_by: DbValueIdentityId::V1Internal,
+                cred_id,
+                scope: DbValueAccessScopeV1::ReadOnly,
+            },
+            DbValueSession::V3 {
+                refer,
+                label: "hacks".to_string(),
+                state: DbValueSessionStateV1::Never,
+                issued_at: "1970-01-01T00:00:00Z".to_string(),
+                issued_by: DbValueIdentityId::V1Internal,
+                cred_id,
+                scope: DbValueAccessScopeV1::ReadOnly,
+            },
+        ];
+
+        for dbv in legacy.iter() {
+            assert!(dbv_to_session(dbv).is_none());
+        }
+
+        // The valueset drops them in the same way.
+        let vs = ValueSetSession::from_dbvs2(&legacy).expect("Failed to construct valueset");
+        assert!(vs.is_empty());
+    }
+
+    #[test]
+    fn test_valueset_oauth2_session_extend_monotonic() {
+        let s_uuid = Uuid::new_v4();
+        let parent = Some(Uuid::new_v4());
+        let issued_at = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(60);
+        let exp = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(300);
+
+        let session = Oauth2Session {
+            state: SessionState::ExpiresAt(exp),
+            issued_at,
+            parent,
+            rs_uuid: Uuid::new_v4(),
+            granted_scopes: Default::default(),
+            has_refresh: false,
+            last_refreshed: None,
+            origin: None,
+        };
+
+        let mut vs: ValueSet = ValueSetOauth2Session::new(s_uuid, session.clone());
+
+        // Extension is accepted, but the issue time and parent are preserved.
+        let extended_exp = exp + Duration::from_secs(300);
+        assert_eq!(
+            vs.insert_checked(Value::Oauth2Session(
+                s_uuid,
+                Oauth2Session {
+                    state: SessionState::ExpiresAt(extended_exp),
+                    issued_at: OffsetDateTime::UNIX_EPOCH + Duration::from_secs(600),
+                    parent: Some(Uuid::new_v4()),
+                    ..session.clone()
+                },
+            )),
+            Ok(true)
+        );
+
+        let current = vs
+            .as_oauth2session_map()
+            .and_then(|map| map.get(&s_uuid))
+            .expect("Unable to locate session");
+
+        assert_eq!(current.state, SessionState::ExpiresAt(extended_exp));
+        assert_eq!(current.issued_at, issued_at);
+        assert_eq!(current.parent, parent);
+
+        // A replay of the original, shorter expiry is ignored.
+        assert_eq!(
+            vs.insert_checked(Value::Oauth2Session(s_uuid, session.clone())),
+            Ok(false)
+        );
+
+        // As is an attempt to make the session unbounded.
+        assert_eq!(
+            vs.insert_checked(Value::Oauth2Session(
+                s_uuid,
+                Oauth2Session {
+                    state: SessionState::NeverExpires,
+                    ..session.clone()
+                },
+            )),
+            Ok(false)
+        );
+
+        let current = vs
+            .as_oauth2session_map()
+            .and_then(|map| map.get(&s_uuid))
+            .expect("Unable to locate session");
+
+        assert_eq!(current.state, SessionState::ExpiresAt(extended_exp));
+
+        // Revocation applies, again keeping the issue time and parent.
+        let one_cid = Cid::new_count(1);
+        assert_eq!(
+            vs.insert_checked(Value::Oauth2Session(
+                s_uuid,
+                Oauth2Session {
+                    state: SessionState::RevokedAt(one_cid.clone()),
+                    parent: None,
+                    ..session.clone()
+                },
+            )),
+            Ok(true)
+        );
+
+        // Extension after revocation is rejected.
+        assert_eq!(
+            vs.insert_checked(Value::Oauth2Session(
+                s_uuid,
+                Oauth2Session {
+                    state: SessionState::ExpiresAt(extended_exp + Duration::from_secs(300)),
+                    ..session.clone()
+                },
+            )),
+            Ok(false)
+        );
+
+        let current = vs
+            .as_oauth2session_map()
+            .and_then(|map| map.get(&s_uuid))
+            .expect("Unable to locate session");
+
+        assert_eq!(current.state, SessionState::RevokedAt(one_cid));
+        assert_eq!(current.issued_at, issued_at);
+        assert_eq!(current.parent, parent);
+    }
+
+    #[test]
+    fn test_valueset_oauth2_session_ref_uuids() {
+        let rs_uuid = Uuid::new_v4();
+        let parent = Uuid::new_v4();
+
+        let session = Oauth2Session {
+            state: SessionState::NeverExpires,
+            issued_at: OffsetDateTime::UNIX_EPOCH,
+            parent: Some(parent),
+            rs_uuid,
+            granted_scopes: Default::default(),
+            has_refresh: false,
+            last_refreshed: None,
+            origin: None,
+        };
+
+        let vs = ValueSetOauth2Session::from_iter([
+            (Uuid::new_v4(), session.clone()),
+            (
+                Uuid::new_v4(),
+                Oauth2Session {
+                    parent: None,
+                    ..session
+                },
+            ),
+        ])
+        .expect("Unable to build valueset oauth2 session");
+
+        let refs: Vec<_> = vs
+            .as_ref_uuid_iter()
+            .expect("Unable to get reference uuids")
+            .collect();
+
+        assert_eq!(refs.len(), 3);
+        assert_eq!(refs.iter().filter(|u| **u == rs_uuid).count(), 2);
+        assert_eq!(refs.iter().filter(|u| **u == parent).count(), 1);
+    }
+
+    #[test]
+    fn test_valueset_session_auth_type_histogram() {
+        let session = |type_: AuthType, state: SessionState| {
+            (
+                Uuid::new_v4(),
+                Session {
+                    state,
+                    type_,
+                    ..test_session()
+                },
+            )
+        };
+
+        let revoked = SessionState::RevokedAt(Cid::new_zero());
+
+        let vs = ValueSetSession::from_iter([
+            session(AuthType::Passkey, SessionState::NeverExpires),
+            session(AuthType::Passkey, SessionState::NeverExpires),
+            session(AuthType::Passkey, revoked.clone()),
+            session(AuthType::Password, SessionState::NeverExpires),
+            session(AuthType::Anonymous, SessionState::NeverExpires),
+            session(AuthType::PasswordTotp, revoked),
+        ])
+        .expect("Unable to build valueset session");
+
+        assert_eq!(
+            vs.auth_type_histogram(false),
+            BTreeMap::from([
+                (AuthType::Anonymous, 1),
+                (AuthType::Password, 1),
+                (AuthType::Passkey, 2),
+            ])
+        );
+
+        assert_eq!(
+            vs.auth_type_histogram(true),
+            BTreeMap::from([
+                (AuthType::Anonymous, 1),
+                (AuthType::Password, 1),
+                (AuthType::PasswordTotp, 1),
+                (AuthType::Passkey, 3),
+            ])
+        );
+
+        // Service account sessions are counted apart from the credential they used.
+        let (sa_uuid, sa_session) = session(AuthType::Password, SessionState::NeverExpires);
+        let mut vs = vs;
+        vs.insert_checked(Value::Session(
+            sa_uuid,
+            Session {
+                service_account: true,
+                ..sa_session
+            },
+        ))
+        .expect("Unable to insert session");
+        assert_eq!(
+            vs.auth_type_histogram(false),
+            BTreeMap::from([
+                (AuthType::Anonymous, 1),
+                (AuthType::Password, 1),
+                (AuthType::Passkey, 2),
+                (AuthType::ServiceAccount, 1),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_valueset_session_scim_summary() {
+        let now = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(60);
+
+        let session = |type_: AuthType, state: SessionState, disabled| {
+            (
+                Uuid::new_v4(),
+                Session {
+                    state,
+                    type_,
+                    disabled,
+                    ..test_session()
+                },
+            )
+        };
+
+        let vs = ValueSetSession::from_iter([
+            session(AuthType::Passkey, SessionState::NeverExpires, None),
+            // A session that was enabled again after being disabled is active.
+            session(
+                AuthType::Passkey,
+                SessionState::ExpiresAt(now + Duration::from_secs(600)),
+                Some(SessionDisabled::EnabledAt(Cid::new_count(20))),
+            ),
+            session(
+                AuthType::Password,
+                SessionState::ExpiresAt(now - Duration::from_secs(30)),
+                None,
+            ),
+            session(
+                AuthType::PasswordTotp,
+                SessionState::RevokedAt(Cid::new_count(10)),
+                None,
+            ),
+            session(
+                AuthType::Passkey,
+                SessionState::NeverExpires,
+                Some(SessionDisabled::DisabledAt(Cid::new_count(20))),
+            ),
+        ])
+        .expect("Unable to build valueset session");
+
+        let ScimResolveStatus::Resolved(ScimValueKanidm::AuthSessionSummary(summary)) =
+            vs.to_scim_summary(now)
+        else {
+            unreachable!("Invalid scim value");
+        };
+
+        assert_eq!(summary.total, 5);
+        assert_eq!(summary.active, 2);
+        assert_eq!(summary.expired, 1);
+        assert_eq!(summary.revoked, 1);
+        assert_eq!(summary.disabled, 1);
+        assert_eq!(
+            summary.by_auth_type,
+            BTreeMap::from([
+                ("passkey".to_string(), 3),
+                ("password".to_string(), 1),
+                ("passwordtotp".to_string(), 1),
+            ])
+        );
+
+        // The full listing is unchanged.
+        assert!(matches!(
+            vs.to_scim_value(),
+            Some(ScimResolveStatus::Resolved(ScimValueKanidm::AuthSession(sessions)))
+                if sessions.len() == 5
+        ));
+    }
+
+    #[test]
+    fn test_valueset_session_future_dated() {
+        let now = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(3600);
+        let tolerance = Duration::from_secs(300);
+
+        let session = |issued_at: OffsetDateTime| {
+            (
+                Uuid::new_v4(),
+                Session {
+                    issued_at,
+                    ..test_session()
+                },
+            )
+        };
+
+        let past = session(now - Duration::from_secs(600));
+        let within = session(now + Duration::from_secs(60));
+        let at_limit = session(now + tolerance);
+        let beyond = session(now + Duration::from_secs(86400));
+
+        let beyond_id = beyond.0;
+
+        let vs = ValueSetSession::from_iter([past, within, at_limit, beyond])
+            .expect("Unable to build valueset session");
+
+        assert_eq!(vs.future_dated_sessions(now, tolerance), vec![beyond_id]);
+
+        // With no tolerance, any session issued after now is future dated.
+        assert_eq!(vs.future_dated_sessions(now, Duration::ZERO).len(), 3);
+
+        // Nothing can exceed a tolerance too large to represent.
+        assert!(vs.future_dated_sessions(now, Duration::MAX).is_empty());
+
+        // The sessions are not altered.
+        assert_eq!(vs.len(), 4);
+    }
+
+    #[test]
+    fn test_valueset_session_capacity_hint() {
+        let sessions: Vec<_> = (0..8)
+            .map(|i| {
+                (
+                    Uuid::new_v4(),
+                    Session {
+                        label: format!("session {i}"),
+                        state: SessionState::NeverExpires,
+                        issued_at: OffsetDateTime::UNIX_EPOCH + Duration::from_secs(i),
+                        issued_by: IdentityId::Internal(UUID_SYSTEM),
+                        cred_id: Uuid::new_v4(),
+                        scope: SessionScope::ReadOnly,
+                        type_: AuthType::Passkey,
+                        ext_metadata: Default::default(),
+                        bound_to: None,
+                        last_active: None,
+                        pinned: false,
+                        service_account: false,
+                        disabled: None,
+                    },
+                )
+            })
+            .collect();
+
+        let vs = ValueSetSession::from_iter(sessions.clone()).expect("Unable to build valueset");
+
+        let mut vs_hinted = ValueSetSession::with_capacity_hint(64);
+        for (session_id, session) in sessions {
+            assert!(vs_hinted.push(session_id, session));
+        }
+
+        // The hint only changes how much is allocated, never the output.
+        assert_eq!(vs.to_vec_dbvs(), vs_hinted.to_vec_dbvs());
+        assert_eq!(vs.generate_idx_eq_keys(), vs_hinted.generate_idx_eq_keys());
+
+        let scim_json = |vs: &ValueSetSession| match vs.to_scim_value() {
+            Some(ScimResolveStatus::Resolved(value)) => serde_json::to_value(value).unwrap(),
+            _ => unreachable!("Invalid scim value"),
+        };
+        assert_eq!(scim_json(&vs), scim_json(&vs_hinted));
+
+        assert!(vs_hinted.generate_idx_eq_keys().capacity() >= 64);
+        assert!(vs_hinted.to_vec_dbvs().capacity() >= 64);
+
+        // A hint smaller than the set is ignored.
+        let vs_small = ValueSetSession::with_capacity_hint(2);
+        assert_eq!(vs_small.capacity(), 2);
+        assert_eq!(vs.capacity(), 8);
+    }
+
+    #[test]
+    fn test_valueset_session_utc_normalised() {
+        let offset = time::UtcOffset::from_hms(13, 45, 0).unwrap();
+        let issued_at = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(86400);
+        let expires = issued_at + Duration::from_secs(3600);
+        let last_active = issued_at + Duration::from_secs(60);
+
+        let session = Session {
+            label: "hacks".to_string(),
+            state: SessionState::ExpiresAt(expires.to_offset(offset)),
+            issued_at: issued_at.to_offset(offset),
+            issued_by: IdentityId::Internal(UUID_SYSTEM),
+            cred_id: Uuid::new_v4(),
+            scope: SessionScope::ReadOnly,
+            type_: AuthType::Passkey,
+            ext_metadata: Default::default(),
+            bound_to: None,
+            last_active: Some(last_active.to_offset(offset)),
+            pinned: false,
+            service_account: false,
+            disabled: None,
+        };
+
+        let assert_utc = |session: &Session| {
+            assert_eq!(session.issued_at.offset(), time::UtcOffset::UTC);
+            assert_eq!(session.issued_at, issued_at);
+            assert!(matches!(
+                session.state,
+                SessionState::ExpiresAt(odt) if odt.offset() == time::UtcOffset::UTC && odt == expires
+            ));
+            assert!(matches!(
+                session.last_active,
+                Some(odt) if odt.offset() == time::UtcOffset::UTC && odt == last_active
+            ));
+        };
+
+        // Both construction and insertion normalise the timestamps.
+        let (session_a, session_b) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut vs: ValueSet = ValueSetSession::new(session_a, session.clone());
+        assert_eq!(
+            vs.insert_checked(Value::Session(session_b, session.clone())),
+            Ok(true)
+        );
+
+        let sessions = vs.as_session_map().unwrap();
+        sessions.values().for_each(assert_utc);
+
+        // The instants are preserved through serialisation and reload.
+        let DbValueSetV2::Session(data) = vs.to_db_valueset_v2() else {
+            unreachable!("Invalid db valueset");
+        };
+        let vs2 = ValueSetSession::from_dbvs2(&data).unwrap();
+        assert_eq!(vs2.as_session_map(), Some(sessions));
+        vs2.as_session_map().unwrap().values().for_each(assert_utc);
+
+        // And in scim.
+        let Some(ScimResolveStatus::Resolved(ScimValueKanidm::AuthSession(scim_sessions))) =
+            vs.to_scim_value()
+        else {
+            unreachable!("Invalid scim value");
+        };
+        let scim_json = serde_json::to_value(&scim_sessions).unwrap();
+        assert_eq!(scim_json[0]["issuedAt"], "1970-01-02T00:00:00Z");
+        assert_eq!(scim_json[0]["expires"], "1970-01-02T01:00:00Z");
+        assert_eq!(scim_json[0]["lastActive"], "1970-01-02T00:01:00Z");
+
+        // Api tokens are also normalised.
+        let token = ApiToken::builder(issued_at).label("token").build();
+        let token = ApiToken {
+            state: SessionState::ExpiresAt(expires.to_offset(offset)),
+            issued_at: issued_at.to_offset(offset),
+            ..token
+        };
+
+        let vs: ValueSet = ValueSetApiToken::new(Uuid::new_v4(), token);
+        let DbValueSetV2::ApiToken(data) = vs.to_db_valueset_v2() else {
+            unreachable!("Invalid db valueset");
+        };
+        let vs2 = ValueSetApiToken::from_dbvs2(data).unwrap();
+        assert_eq!(vs2.as_apitoken_map(), vs.as_apitoken_map());
+
+        for token in vs2.as_apitoken_map().unwrap().values() {
+            assert_eq!(token.issued_at.offset(), time::UtcOffset::UTC);
+            assert!(matches!(
+                token.state,
+                SessionState::ExpiresAt(odt) if odt.offset() == time::UtcOffset::UTC && odt == expires
+            ));
+        }
+    }
+
+    #[test]
+    fn test_valueset_oauth2_session_count_by_rs() {
+        let rs_a = Uuid::new_v4();
+        let rs_b = Uuid::new_v4();
+        let current_time = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(300);
+
+        let session = |rs_uuid: Uuid, state: SessionState| {
+            (
+                Uuid::new_v4(),
+                Oauth2Session {
+                    state,
+                    rs_uuid,
+                    ..test_oauth2_session()
+                },
+            )
+        };
+
+        let live = SessionState::ExpiresAt(current_time + Duration::from_secs(1));
+        let expired = SessionState::ExpiresAt(current_time);
+        let revoked = SessionState::RevokedAt(Cid::new_zero());
+
+        let vs = ValueSetOauth2Session::from_iter([
+            session(rs_a, live.clone()),
+            session(rs_a, SessionState::NeverExpires),
+            session(rs_a, expired.clone()),
+            session(rs_a, revoked.clone()),
+            session(rs_b, live),
+            session(rs_b, expired),
+            session(rs_b, revoked),
+        ])
+        .expect("Unable to build valueset oauth2 session");
+
+        assert_eq!(vs.count_by_rs(rs_a, current_time), 2);
+        assert_eq!(vs.count_by_rs(rs_b, current_time), 1);
+        assert_eq!(vs.count_by_rs(Uuid::new_v4(), current_time), 0);
+
+        let counts: BTreeMap<_, _> = vs.count_by_rs_iter(current_time).collect();
+        assert_eq!(counts, BTreeMap::from([(rs_a, 2), (rs_b, 1)]));
+
+        // Later, only sessions that never expire are counted.
+        let later = current_time + Duration::from_secs(300);
+        assert_eq!(vs.count_by_rs(rs_a, later), 1);
+        assert_eq!(vs.count_by_rs(rs_b, later), 0);
+        assert_eq!(vs.count_by_rs_iter(later).count(), 1);
+    }
+
+    #[test]
+    fn test_valueset_session_is_active_at_grace() {
+        let s_uuid = Uuid::new_v4();
+        let revoked_cid = Cid::new_count(300);
+        let revoked_at: OffsetDateTime = (&revoked_cid).into();
+        let grace = Duration::from_secs(30);
+
+        let vs = ValueSetSession::new(
+            s_uuid,
+            Session {
+                label: "hacks".to_string(),
+                state: SessionState::RevokedAt(revoked_cid),
+                issued_at: OffsetDateTime::UNIX_EPOCH,
+                issued_by: IdentityId::Internal(UUID_SYSTEM),
+                cred_id: Uuid::new_v4(),
+                scope: SessionScope::ReadOnly,
+                type_: AuthType::Passkey,
+                ext_metadata: Default::default(),
+                bound_to: None,
+                last_active: None,
+                pinned: false,
+                service_account: false,
+                disabled: None,
+            },
+        );
+
+        // Just inside the grace window the session still works.
+        let inside = revoked_at + grace - Duration::from_secs(1);
+        assert!(vs.is_active_at(&s_uuid, inside, grace));
+
+        // At and beyond the end of the window it does not.
+        assert!(!vs.is_active_at(&s_uuid, revoked_at + grace, grace));
+        let outside = revoked_at + grace + Duration::from_secs(1);
+        assert!(!vs.is_active_at(&s_uuid, outside, grace));
+
+        // Without a grace window, revocation is immediate.
+        assert!(!vs.is_active_at(&s_uuid, revoked_at, Duration::ZERO));
+
+        // A grace window too large to represent does not panic.
+        assert!(vs.is_active_at(&s_uuid, outside, Duration::MAX));
+
+        // The stored state is unchanged.
+        let session = vs
+            .as_session_map()
+            .and_then(|map| map.get(&s_uuid))
+            .expect("Unable to locate session");
+        assert!(matches!(session.state, SessionState::RevokedAt(_)));
+
+        // Unknown sessions are never active.
+        assert!(!vs.is_active_at(&Uuid::new_v4(), inside, grace));
+    }
+
+    #[test]
+    fn test_valueset_session_is_live() {
+        let exp = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(600);
+        let before = exp - Duration::from_secs(1);
+        let after = exp + Duration::from_secs(1);
+
+        let never_id = Uuid::new_v4();
+        let expires_id = Uuid::new_v4();
+        let revoked_id = Uuid::new_v4();
+
+        let session = |state| Session {
+            state,
+            ..test_session()
+        };
+
+        let mut vs = ValueSetSession::new(never_id, session(SessionState::NeverExpires));
+        vs.push(expires_id, session(SessionState::ExpiresAt(exp)));
+        vs.push(
+            revoked_id,
+            session(SessionState::RevokedAt(Cid::new_count(300))),
+        );
+
+        assert!(vs.is_live(&never_id, before));
+        assert!(vs.is_live(&never_id, after));
+
+        assert!(vs.is_live(&expires_id, before));
+        assert!(!vs.is_live(&expires_id, exp));
+        assert!(!vs.is_live(&expires_id, after));
+
+        // A revoked session is present, but never live.
+        assert!(vs.contains(&PartialValue::Refer(revoked_id)));
+        assert!(!vs.is_live(&revoked_id, before));
+        assert!(!vs.is_live(&revoked_id, after));
+
+        assert!(!vs.is_live(&Uuid::new_v4(), before));
+    }
+
+    #[test]
+    fn test_valueset_session_push_revoked() {
+        let s_uuid = Uuid::new_v4();
+
+        let session = |state| Session {
+            state,
+            ..test_session()
+        };
+
+        let mut vs = ValueSetSession::new(s_uuid, session(SessionState::NeverExpires));
+
+        // An active session may be replaced, including by a revoked one.
+        assert!(!vs.push(
+            s_uuid,
+            session(SessionState::RevokedAt(Cid::new_count(300)))
+        ));
+
+        // But pushing an active session must not un-revoke it.
+        assert!(!vs.push(s_uuid, session(SessionState::NeverExpires)));
+        assert!(!vs.push(
+            s_uuid,
+            session(SessionState::ExpiresAt(
+                OffsetDateTime::UNIX_EPOCH + Duration::from_secs(600)
+            ))
+        ));
+
+        let stored = vs.as_session_map().and_then(|m| m.get(&s_uuid));
+        assert!(matches!(
+            stored.map(|s| &s.state),
+            Some(SessionState::RevokedAt(cid)) if *cid == Cid::new_count(300)
+        ));
+    }
+
+    #[test]
+    fn test_valueset_oauth2_session_extend() {
+        let rs_a = Uuid::new_v4();
+        let rs_b = Uuid::new_v4();
+        let rs_c = Uuid::new_v4();
+
+        let session = |rs_uuid| Oauth2Session {
+            rs_uuid,
+            ..test_oauth2_session()
+        };
+
+        let s_a = Uuid::new_v4();
+        let s_b = Uuid::new_v4();
+        let s_c = Uuid::new_v4();
+
+        let mut vs = ValueSetOauth2Session::new(s_a, session(rs_a));
+
+        // s_a is already present, and s_b is repeated within the batch.
+        let inserted = vs.extend([
+            (s_a, session(rs_b)),
+            (s_b, session(rs_b)),
+            (s_b, session(rs_c)),
+            (s_c, session(rs_c)),
+        ]);
+        assert_eq!(inserted, 2);
+        assert_eq!(vs.len(), 3);
+
+        // Overwritten sessions take the latest value.
+        let map = vs.as_oauth2session_map().expect("Unable to access map");
+        assert_eq!(map.get(&s_a).map(|s| s.rs_uuid), Some(rs_b));
+        assert_eq!(map.get(&s_b).map(|s| s.rs_uuid), Some(rs_c));
+
+        // The filter covers every resource server that was inserted.
+        let expect = rs_a.as_u128() | rs_b.as_u128() | rs_c.as_u128();
+        assert_eq!(vs.rs_filter, expect);
+
+        let ct = OffsetDateTime::UNIX_EPOCH;
+        assert_eq!(vs.count_by_rs(rs_b, ct), 1);
+        assert_eq!(vs.count_by_rs(rs_c, ct), 2);
+
+        // An empty batch changes nothing.
+        assert_eq!(vs.extend([]), 0);
+        assert_eq!(vs.rs_filter, expect);
+    }
+
+    #[test]
+    fn test_valueset_oauth2_session_is_live() {
+        let exp = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(600);
+        let before = exp - Duration::from_secs(1);
+        let after = exp + Duration::from_secs(1);
+
+        let never_id = Uuid::new_v4();
+        let expires_id = Uuid::new_v4();
+        let revoked_id = Uuid::new_v4();
+
+        let session = |state| Oauth2Session {
+            state,
+            ..test_oauth2_session()
+        };
+
+        let mut vs = ValueSetOauth2Session::new(never_id, session(SessionState::NeverExpires));
+        vs.push(expires_id, session(SessionState::ExpiresAt(exp)));
+        vs.push(
+            revoked_id,
+            session(SessionState::RevokedAt(Cid::new_count(300))),
+        );
+
+        assert!(vs.is_live(&never_id, before));
+        assert!(vs.is_live(&never_id, after));
+
+        assert!(vs.is_live(&expires_id, before));
+        assert!(!vs.is_live(&expires_id, exp));
+        assert!(!vs.is_live(&expires_id, after));
+
+        // A revoked session is present, but never live.
+        assert!(vs.contains(&PartialValue::Refer(revoked_id)));
+        assert!(!vs.is_live(&revoked_id, before));
+        assert!(!vs.is_live(&revoked_id, after));
+
+        assert!(!vs.is_live(&Uuid::new_v4(), before));
+    }
+
+    #[test]
+    fn test_valueset_api_token_issued_by() {
+        let user_uuid = Uuid::new_v4();
+        let sync_uuid = Uuid::new_v4();
+
+        let token = |issued_by: IdentityId| {
+            (
+                Uuid::new_v4(),
+                ApiToken {
+                    issued_by,
+                    ..test_api_token()
+                },
+            )
+        };
+
+        let tokens = [
+            token(IdentityId::User(user_uuid)),
+            token(IdentityId::User(user_uuid)),
+            token(IdentityId::Synch(sync_uuid)),
+            token(IdentityId::Internal(UUID_SYSTEM)),
+        ];
+
+        let vs: ValueSet =
+            ValueSetApiToken::from_iter(tokens.clone()).expect("Unable to build valueset");
+
+        // Each token id, each distinct issuer and each distinct label is indexed once.
+        let idx_keys = vs.generate_idx_eq_keys();
+        assert_eq!(idx_keys.len(), 8);
+        for key in tokens
+            .iter()
+            .map(|(u, _)| *u)
+            .chain([user_uuid, sync_uuid, UUID_SYSTEM])
+        {
+            assert!(idx_keys.contains(&key.as_hyphenated().to_string()));
+        }
+
+        // Which is consistent with equality.
+        assert!(vs.contains(&PartialValue::Refer(user_uuid)));
+        assert!(vs.contains(&PartialValue::Refer(sync_uuid)));
+        assert!(vs.contains(&PartialValue::Refer(UUID_SYSTEM)));
+        assert!(!vs.contains(&PartialValue::Refer(Uuid::new_v4())));
+
+        let issued_by: BTreeMap<_, _> = vs
+            .as_apitoken_issued_by_iter()
+            .expect("Unable to get issuers")
+            .collect();
+
+        let expect: BTreeMap<_, _> = tokens
+            .into_iter()
+            .map(|(u, at)| (u, at.issued_by))
+            .collect();
+
+        assert_eq!(issued_by, expect);
+    }
+
+    #[test]
+    fn test_valueset_oauth2_session_contains_revoked() {
+        let s_uuid = Uuid::new_v4();
+        let rs_uuid = Uuid::new_v4();
+        let zero_cid = Cid::new_zero();
+
+        let mut vs: ValueSet = ValueSetOauth2Session::new(
+            s_uuid,
+            Oauth2Session {
+                state: SessionState::NeverExpires,
+                issued_at: OffsetDateTime::UNIX_EPOCH,
+                parent: None,
+                rs_uuid,
+                granted_scopes: Default::default(),
+                has_refresh: false,
+                last_refreshed: None,
+                origin: None,
+            },
+        );
+
+        let pv_session = PartialValue::Refer(s_uuid);
+        let pv_rs = PartialValue::Refer(rs_uuid);
+
+        assert!(vs.contains(&pv_session));
+        assert!(vs.contains(&pv_rs));
+        assert!(vs.contains_active(&pv_session));
+        assert!(vs.contains_active(&pv_rs));
+
+        // Revoke the session via the resource server.
+        assert!(vs.remove(&pv_rs, &zero_cid));
+
+        // Presence is the same for both uuids, as is activity.
+        assert!(vs.contains(&pv_session));
+        assert!(vs.contains(&pv_rs));
+        assert!(!vs.contains_active(&pv_session));
+        assert!(!vs.contains_active(&pv_rs));
+
+        // A later revocation does not replace the original.
+        assert!(!vs.remove(&pv_rs, &Cid::new_count(1)));
+        assert!(!vs.remove(&pv_session, &Cid::new_count(1)));
+
+        let session = vs
+            .as_oauth2session_map()
+            .and_then(|map| map.get(&s_uuid))
+            .expect("Unable to locate session");
+        assert_eq!(session.state, SessionState::RevokedAt(zero_cid));
+    }
+
+    #[test]
+    fn test_valueset_session_merge_observer() {
+        let s_uuid = Uuid::new_v4();
+        let new_uuid = Uuid::new_v4();
+
+        let session = Session {
+            label: "hacks".to_string(),
+            state: SessionState::NeverExpires,
+            issued_at: OffsetDateTime::UNIX_EPOCH,
+            issued_by: IdentityId::Internal(UUID_SYSTEM),
+            cred_id: Uuid::new_v4(),
+            scope: SessionScope::ReadOnly,
+            type_: AuthType::Passkey,
+            ext_metadata: Default::default(),
+            bound_to: None,
+            last_active: None,
+            pinned: false,
+            service_account: false,
+            disabled: None,
+        };
+
+        let revoked = SessionState::RevokedAt(Cid::new_zero());
+
+        // The same session, but revoked on the other replica, plus a new session.
+        let other: ValueSet = ValueSetSession::from_iter([
+            (
+                s_uuid,
+                Session {
+                    state: revoked.clone(),
+                    ..session.clone()
+                },
+            ),
+            (new_uuid, session.clone()),
+        ])
+        .expect("Unable to build valueset session");
+
+        let conflicts = std::cell::RefCell::new(Vec::new());
+        let observer = |u: Uuid, s_self: &SessionState, s_other: &SessionState| {
+            conflicts
+                .borrow_mut()
+                .push((u, s_self.clone(), s_other.clone()));
+        };
+
+        let mut vs = ValueSetSession::new(s_uuid, session.clone());
+        vs.merge_with_observer(&other, Some(&observer))
+            .expect("Unable to merge valueset");
+
+        assert_eq!(
+            conflicts.take(),
+            vec![(s_uuid, SessionState::NeverExpires, revoked.clone())]
+        );
+        assert_eq!(vs.len(), 2);
+
+        let vs = ValueSetSession::new(s_uuid, session.clone());
+        let merged = vs
+            .repl_merge_valueset_with_observer(&other, &Cid::new_zero(), Some(&observer))
+            .expect("Unable to merge valueset");
+
+        assert_eq!(
+            conflicts.take(),
+            vec![(s_uuid, SessionState::NeverExpires, revoked)]
+        );
+        assert_eq!(merged.len(), 2);
+
+        // A clean insert of a new session is not a conflict.
+        let insert: ValueSet = ValueSetSession::new(new_uuid, session.clone());
+        let mut vs = ValueSetSession::new(s_uuid, session);
+        vs.merge_with_observer(&insert, Some(&observer))
+            .expect("Unable to merge valueset");
+
+        assert!(conflicts.take().is_empty());
+        assert_eq!(vs.len(), 2);
+    }
+
+    #[test]
+    fn test_valueset_oauth2_session_origin() {
+        let s_uuid = Uuid::new_v4();
+        let rs_uuid = Uuid::new_v4();
+
+        let session = Oauth2Session {
+            state: SessionState::NeverExpires,
+            issued_at: OffsetDateTime::UNIX_EPOCH,
+            parent: None,
+            rs_uuid,
+            granted_scopes: Default::default(),
+            has_refresh: false,
+            last_refreshed: None,
+            origin: Some("https://demo.example.com/oauth2/result".to_string()),
+        };
+
+        let vs: ValueSet = ValueSetOauth2Session::new(s_uuid, session.clone());
+
+        let schema = SchemaAttribute::default();
+        assert!(vs.validate(&schema));
+
+        let vs2 = crate::valueset::from_db_valueset_v2(vs.to_db_valueset_v2())
+            .expect("Failed to construct vs2 from dbvalue");
+        assert_eq!(&vs, &vs2);
+
+        // Older records load without an origin.
+        let vs3 = ValueSetOauth2Session::from_dbvs2(vec![DbValueOauth2Session::V5 {
+            refer: s_uuid,
+            parent: None,
+            state: DbValueSessionStateV1::Never,
+            issued_at: "1970-01-01T00:00:00Z".to_string(),
+            rs_uuid,
+            granted_scopes: Default::default(),
+            has_refresh: false,
+            last_refreshed: None,
+        }])
+        .expect("Failed to construct vs3 from dbvalue");
+
+        let origin = vs3
+            .as_oauth2session_map()
+            .and_then(|map| map.get(&s_uuid))
+            .map(|s| s.origin.clone())
+            .expect("Unable to locate session");
+        assert!(origin.is_none());
+
+        // An origin that is not a url is invalid.
+        let vs4: ValueSet = ValueSetOauth2Session::new(
+            s_uuid,
+            Oauth2Session {
+                origin: Some("not a url".to_string()),
+                ..session
+            },
+        );
+        assert!(!vs4.validate(&schema));
+    }
+
+    #[test]
+    fn test_valueset_oauth2_session_parent_not_self() {
+        let s_uuid = Uuid::new_v4();
+
+        let session = Oauth2Session {
+            state: SessionState::NeverExpires,
+            issued_at: OffsetDateTime::UNIX_EPOCH,
+            parent: Some(Uuid::new_v4()),
+            rs_uuid: Uuid::new_v4(),
+            granted_scopes: Default::default(),
+            has_refresh: false,
+            last_refreshed: None,
+            origin: None,
+        };
+
+        let schema = SchemaAttribute::default();
+
+        let vs: ValueSet = ValueSetOauth2Session::new(s_uuid, session.clone());
+        assert!(vs.validate(&schema));
+
+        let vs2: ValueSet = ValueSetOauth2Session::new(
+            s_uuid,
+            Oauth2Session {
+                parent: Some(s_uuid),
+                ..session
+            },
+        );
+        assert!(!vs2.validate(&schema));
+    }
+
+    #[test]
+    fn test_valueset_oauth2_session_rs_filter_stats() {
+        let rs_uuids = [Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()];
+
+        let mut vs = ValueSetOauth2Session::from_iter(rs_uuids.iter().map(|rs_uuid| {
+            (
+                Uuid::new_v4(),
+                Oauth2Session {
+                    state: SessionState::NeverExpires,
+                    issued_at: OffsetDateTime::UNIX_EPOCH,
+                    parent: None,
+                    rs_uuid: *rs_uuid,
+                    granted_scopes: Default::default(),
+                    has_refresh: false,
+                    last_refreshed: None,
+                    origin: None,
+                },
+            )
+        }))
+        .expect("Unable to build valueset oauth2 session");
+
+        let (set_bits, saturation) = vs.rs_filter_stats();
+        let rs_filter = vs.rs_filter;
+
+        // Rebuilding from the same content yields the same filter.
+        vs.rs_filter = u128::MIN;
+        vs.rebuild_rs_filter();
+        assert_eq!(vs.rs_filter, rs_filter);
+
+        // Every stored rs_uuid matches the filter.
+        for rs_uuid in rs_uuids {
+            let u_int = rs_uuid.as_u128();
+            assert_eq!(vs.rs_filter & u_int, u_int);
+        }
+
+        // At least as many bits as the largest uuid are set, and no more than the whole filter.
+        let min_bits = rs_uuids
+            .iter()
+            .map(|u| u.as_u128().count_ones())
+            .max()
+            .unwrap_or_default();
+        assert!(set_bits >= min_bits);
+        assert!(set_bits <= u128::BITS);
+        assert_eq!(saturation, set_bits as f32 / 128.0);
+        assert!(saturation > 0.0 && saturation <= 1.0);
+
+        // An empty set has an empty filter.
+        vs.clear();
+        assert_eq!(vs.rs_filter_stats(), (0, 0.0));
+    }
+
+    #[test]
+    fn test_valueset_api_token_parent_session() {
+        let parent_session = Uuid::new_v4();
+
+        let vs: ValueSet = ValueSetApiToken::new(
+            Uuid::new_v4(),
+            ApiToken {
+                label: "hacks".to_string(),
+                state: SessionState::NeverExpires,
+                issued_at: OffsetDateTime::UNIX_EPOCH,
+                issued_by: IdentityId::User(Uuid::new_v4()),
+                scope: ApiTokenScope::ReadOnly,
+                parent_session: Some(parent_session),
+                generation: 0,
+                constraints: None,
+            },
+        );
+
+        // The session that issued the token is indexed, which is consistent with equality.
+        assert!(vs
+            .generate_idx_eq_keys()
+            .contains(&parent_session.as_hyphenated().to_string()));
+        assert!(vs.contains(&PartialValue::Refer(parent_session)));
+    }
+
+    #[test]
+    fn test_valueset_api_token_purge() {
+        let t_uuid = Uuid::new_v4();
+        let one_cid = Cid::new_count(1);
+
+        let mut vs: ValueSet = ValueSetApiToken::new(
+            t_uuid,
+            ApiToken {
+                label: "hacks".to_string(),
+                state: SessionState::NeverExpires,
+                issued_at: OffsetDateTime::UNIX_EPOCH,
+                issued_by: IdentityId::Internal(UUID_SYSTEM),
+                scope: ApiTokenScope::ReadOnly,
+                parent_session: None,
+                generation: 0,
+                constraints: None,
+            },
+        );
+
+        // Purging revokes the token, it must remain for replication.
+        assert!(!vs.purge(&one_cid));
+
+        let token = vs
+            .as_apitoken_map()
+            .and_then(|map| map.get(&t_uuid))
+            .expect("Unable to locate token");
+
+        assert_eq!(token.state, SessionState::RevokedAt(one_cid));
+
+        // Removing an already revoked token doesn't alter the revocation.
+        assert!(!vs.remove(&PartialValue::Refer(t_uuid), &Cid::new_count(2)));
+    }
+
+    #[test]
+    fn test_valueset_api_token_tokens_by_expiry() {
+        let now = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(86400);
+
+        let expired = Uuid::new_v4();
+        let soon = Uuid::new_v4();
+        let far = Uuid::new_v4();
+        let never = Uuid::new_v4();
+        let revoked = Uuid::new_v4();
+
+        let token = |state| {
+            ApiToken::builder(OffsetDateTime::UNIX_EPOCH)
+                .label(Uuid::new_v4().to_string())
+                .state(state)
+                .build()
+        };
+
+        let mut vs = ValueSetApiToken::new(never, token(SessionState::NeverExpires));
+        vs.push(
+            far,
+            token(SessionState::ExpiresAt(now + Duration::from_secs(3600))),
+        );
+        vs.push(
+            expired,
+            token(SessionState::ExpiresAt(now - Duration::from_secs(60))),
+        );
+        vs.push(revoked, token(SessionState::RevokedAt(Cid::new_count(1))));
+        vs.push(
+            soon,
+            token(SessionState::ExpiresAt(now + Duration::from_secs(60))),
+        );
+
+        assert_eq!(
+            vs.tokens_by_expiry(now),
+            vec![
+                (expired, Some(Duration::ZERO)),
+                (soon, Some(Duration::from_secs(60))),
+                (far, Some(Duration::from_secs(3600))),
+                (never, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_valueset_api_token_merge_left() {
+        let t_uuid = Uuid::new_v4();
+        let zero_cid = Cid::new_zero();
+
+        let token = |state: SessionState| ApiToken {
+            state,
+            ..test_api_token()
+        };
+
+        let mut vs_a: ValueSet = ValueSetApiToken::new(t_uuid, token(SessionState::NeverExpires));
+        let vs_b: ValueSet =
+            ValueSetApiToken::new(t_uuid, token(SessionState::RevokedAt(zero_cid.clone())));
+
+        vs_a.merge(&vs_b).expect("failed to merge");
+
+        let token = vs_a
+            .as_apitoken_map()
+            .and_then(|map| map.get(&t_uuid))
+            .expect("Unable to locate token");
+
+        assert_eq!(token.state, SessionState::RevokedAt(zero_cid));
+    }
+
+    #[test]
+    fn test_valueset_api_token_merge_type_mismatch() {
+        let t_uuid = Uuid::new_v4();
+        let s_uuid = Uuid::new_v4();
+        let zero_cid = Cid::new_zero();
+
+        let mut vs_a: ValueSet = ValueSetApiToken::new(
+            t_uuid,
+            ApiToken {
+                label: "hacks".to_string(),
+                state: SessionState::NeverExpires,
+                issued_at: OffsetDateTime::UNIX_EPOCH,
+                issued_by: IdentityId::Internal(UUID_SYSTEM),
+                scope: ApiTokenScope::ReadOnly,
+                parent_session: None,
+                generation: 0,
+                constraints: None,
+            },
+        );
+
+        let vs_b: ValueSet = ValueSetSession::new(
+            s_uuid,
+            Session {
+                label: "hacks".to_string(),
+                state: SessionState::NeverExpires,
+                issued_at: OffsetDateTime::UNIX_EPOCH,
+                issued_by: IdentityId::Internal(UUID_SYSTEM),
+                cred_id: s_uuid,
+                scope: SessionScope::ReadOnly,
+                type_: AuthType::Passkey,
+                ext_metadata: Default::default(),
+                bound_to: None,
+                last_active: None,
+                pinned: false,
+                service_account: false,
+                disabled: None,
+            },
+        );
+
+        // The error identifies both types, and the set is unchanged.
+        match vs_a.merge(&vs_b) {
+            Err(OperationError::ValueSetTypeMismatch { expected, found }) => {
+                assert_eq!(expected, SyntaxType::ApiToken.to_string());
+                assert_eq!(found, SyntaxType::Session.to_string());
+            }
+            res => panic!("Unexpected merge result {res:?}"),
+        }
+        assert_eq!(vs_a.len(), 1);
+        assert!(vs_a.contains(&PartialValue::Refer(t_uuid)));
+
+        // Replication keeps the newer value.
+        assert!(vs_a.repl_merge_valueset(&vs_b, &zero_cid).is_none());
+    }
+
+    #[test]
+    fn test_valueset_api_token_repl_merge() {
+        let t_uuid = Uuid::new_v4();
+        let r_uuid = Uuid::new_v4();
+        let zero_cid = Cid::new_zero();
+        let one_cid = Cid::new_count(1);
+
+        let token = |state: SessionState| ApiToken {
+            state,
+            ..test_api_token()
+        };
+
+        let vs_a: ValueSet = ValueSetApiToken::new(t_uuid, token(SessionState::NeverExpires));
+
+        let vs_b: ValueSet = ValueSetApiToken::from_iter([
+            (t_uuid, token(SessionState::RevokedAt(one_cid.clone()))),
+            (r_uuid, token(SessionState::RevokedAt(zero_cid))),
+        ])
+        .expect("Unable to build valueset api token");
+
+        // The revocation wins regardless of which side is newer, and can't be
+        // resurrected by a replica that missed it.
+        for r_vs in [
+            vs_a.repl_merge_valueset(&vs_b, &one_cid),
+            vs_b.repl_merge_valueset(&vs_a, &one_cid),
+        ] {
+            let r_vs = r_vs.expect("failed to merge");
+            let tokens = r_vs.as_apitoken_map().expect("Unable to locate tokens");
+
+            let token = tokens.get(&t_uuid).expect("Unable to locate token");
+            assert_eq!(token.state, SessionState::RevokedAt(one_cid.clone()));
+
+            // Revocations before the trim cid are removed.
+            assert!(!tokens.contains_key(&r_uuid));
+        }
+    }
+
+    #[test]
+    fn test_valueset_api_token_repl_trim() {
+        let zero_uuid = Uuid::new_v4();
+        let one_uuid = Uuid::new_v4();
+        let two_uuid = Uuid::new_v4();
+        let live_uuid = Uuid::new_v4();
+        let two_cid = Cid::new_count(2);
+
+        let token = |state: SessionState| ApiToken {
+            state,
+            ..test_api_token()
+        };
+
+        let mut vs_a: ValueSet = ValueSetApiToken::from_iter([
+            (zero_uuid, token(SessionState::RevokedAt(Cid::new_zero()))),
+            (one_uuid, token(SessionState::RevokedAt(Cid::new_count(1)))),
+            (two_uuid, token(SessionState::RevokedAt(two_cid.clone()))),
+            (live_uuid, token(SessionState::NeverExpires)),
+        ])
+        .expect("Unable to build valueset api token");
+
+        vs_a.trim(&two_cid);
+
+        let tokens = vs_a.as_apitoken_map().expect("Unable to locate tokens");
+
+        assert!(!tokens.contains_key(&zero_uuid));
+        assert!(!tokens.contains_key(&one_uuid));
+        assert!(tokens.contains_key(&two_uuid));
+        assert!(tokens.contains_key(&live_uuid));
+    }
+
+    #[test]
+    fn test_valueset_api_token_dbv_migrate() {
+        let expiring_uuid = Uuid::new_v4();
+        let never_uuid = Uuid::new_v4();
+
+        let dbv = |refer, expiry| DbValueApiToken::V1 {
+            refer,
+            label: "hacks".to_string(),
+            expiry,
+            issued_at: "1970-01-01T00:00:00Z".to_string(),
+            issued_by: DbValueIdentityId::V1Uuid(Uuid::new_v4()),
+            scope: DbValueApiTokenScopeV1::ReadWrite,
+        };
+
+        let vs = ValueSetApiToken::from_dbvs2(vec![
+            dbv(expiring_uuid, Some("1970-01-02T00:00:00Z".to_string())),
+            dbv(never_uuid, None),
+        ])
+        .expect("Unable to load api tokens");
+
+        let tokens = vs.as_apitoken_map().expect("Unable to locate tokens");
+
+        assert_eq!(
+            tokens.get(&expiring_uuid).map(|token| &token.state),
+            Some(&SessionState::ExpiresAt(
+                OffsetDateTime::UNIX_EPOCH + Duration::from_secs(86400)
+            ))
+        );
+        assert_eq!(
+            tokens.get(&never_uuid).map(|token| &token.state),
+            Some(&SessionState::NeverExpires)
+        );
+
+        // We always write the newest version, with the state.
+        let DbValueSetV2::ApiToken(dbvs) = vs.to_db_valueset_v2() else {
+            panic!("Invalid db valueset type");
+        };
+        assert!(dbvs
+            .iter()
+            .all(|dbv| matches!(dbv, DbValueApiToken::V2 { .. })));
+
+        let vs_2 = ValueSetApiToken::from_dbvs2(dbvs).expect("Unable to reload api tokens");
+        assert!(vs.equal(&vs_2));
+    }
+
+    #[test]
+    fn test_valueset_api_token_constraints() {
+        let constrained_uuid = Uuid::new_v4();
+        let unconstrained_uuid = Uuid::new_v4();
+
+        let token = |label: &str, constraints| ApiToken {
+            label: label.to_string(),
+            scope: ApiTokenScope::ReadWrite,
+            constraints,
+            ..test_api_token()
+        };
+
+        let vs = ValueSetApiToken::from_iter([
+            (
+                constrained_uuid,
+                token(
+                    "constrained",
+                    Some(btreeset![
+                        ApiTokenConstraint::GroupMembership,
+                        ApiTokenConstraint::SshPublicKeys
+                    ]),
+                ),
+            ),
+            (unconstrained_uuid, token("unconstrained", None)),
+        ])
+        .expect("Unable to build valueset");
+
+        // The constraints survive the db round trip, and tokens without them are unchanged.
+        let DbValueSetV2::ApiToken(dbvs) = vs.to_db_valueset_v2() else {
+            panic!("Invalid db valueset type");
+        };
+        assert!(dbvs.iter().any(|dbv| matches!(
+            dbv,
+            DbValueApiToken::V2 { refer, constraints: None, .. } if *refer == unconstrained_uuid
+        )));
+
+        let vs_2 = ValueSetApiToken::from_dbvs2(dbvs).expect("Unable to reload api tokens");
+        assert!(vs.equal(&vs_2));
+
+        let Some(crate::valueset::ScimResolveStatus::NeedsResolution(
+            crate::valueset::ScimValueIntermediate::ApiToken(scim_tokens),
+        )) = vs.to_scim_value()
+        else {
+            panic!("Invalid scim value");
+        };
+
+        let scim_constraints: BTreeMap<_, _> = scim_tokens
+            .into_iter()
+            .map(|t| (t.id, t.constraints))
+            .collect();
+        assert_eq!(
+            scim_constraints.get(&constrained_uuid),
+            Some(&Some(btreeset![
+                "group_membership".to_string(),
+                "ssh_public_keys".to_string()
+            ]))
+        );
+        assert_eq!(scim_constraints.get(&unconstrained_uuid), Some(&None));
+    }
+
+    #[test]
+    fn test_valueset_session_bound_to() {
+        let bound_uuid = Uuid::new_v4();
+        let unbound_uuid = Uuid::new_v4();
+        let one_cid = Cid::new_count(1);
+
+        let session = |state: SessionState, bound_to: Option<&str>| Session {
+            state,
+            bound_to: bound_to.map(str::to_string),
+            ..test_session()
+        };
+
+        let mut vs_a = ValueSetSession::from_iter([
+            (bound_uuid, session(SessionState::NeverExpires, Some("a"))),
+            (unbound_uuid, session(SessionState::NeverExpires, None)),
+        ])
+        .expect("Unable to build valueset session");
+
+        assert_eq!(vs_a.is_bound_match(&bound_uuid, "a"), Some(true));
+        assert_eq!(vs_a.is_bound_match(&bound_uuid, "b"), Some(false));
+        assert_eq!(vs_a.is_bound_match(&unbound_uuid, "a"), None);
+        assert_eq!(vs_a.is_bound_match(&Uuid::new_v4(), "a"), None);
+
+        // The binding survives the db round trip.
+        let vs_b = ValueSetSession::from_dbvs2(&vs_a.to_vec_dbvs()).expect("Failed to load");
+        assert!(vs_a.equal(&vs_b));
+
+        // Merging takes the binding of the dominant state.
+        let vs_c: ValueSet = ValueSetSession::new(
+            bound_uuid,
+            session(SessionState::RevokedAt(one_cid.clone()), Some("c")),
+        );
+        vs_a.merge(&vs_c).expect("failed to merge");
+        assert_eq!(vs_a.is_bound_match(&bound_uuid, "c"), Some(true));
+
+        // A weaker state doesn't replace the binding.
+        let vs_d: ValueSet =
+            ValueSetSession::new(bound_uuid, session(SessionState::NeverExpires, Some("d")));
+        vs_a.merge(&vs_d).expect("failed to merge");
+        assert_eq!(vs_a.is_bound_match(&bound_uuid, "c"), Some(true));
+    }
+
+    #[test]
+    fn test_session_dbv_legacy_unbound() {
+        let s_uuid = Uuid::new_v4();
+
+        let dbv = DbValueSession::V4 {
+            refer: s_uuid,
+            label: "hacks".to_string(),
+            state: DbValueSessionStateV1::Never,
+            issued_at: "1970-01-01T00:00:00Z".to_string(),
+            issued_by: DbValueIdentityId::V1Uuid(Uuid::new_v4()),
+            cred_id: Uuid::new_v4(),
+            scope: DbValueAccessScopeV1::ReadOnly,
+            type_: DbValueAuthTypeV1::Passkey,
+            ext_metadata: Default::default(),
+        };
+
+        let (refer, session) = dbv_to_session(&dbv).expect("Failed to parse session");
+        assert_eq!(refer, s_uuid);
+        assert_eq!(session.bound_to, None);
+        assert!(!session.pinned);
+    }
+
+    #[test]
+    fn test_valueset_api_token_repl_merge_converges() {
+        let t_uuid = Uuid::new_v4();
+
+        let token = |label: &str, secs: u64| ApiToken {
+            label: label.to_string(),
+            issued_at: OffsetDateTime::UNIX_EPOCH + Duration::from_secs(secs),
+            ..test_api_token()
+        };
+
+        let vs_a: ValueSet = ValueSetApiToken::new(t_uuid, token("older", 1));
+        let vs_b: ValueSet = ValueSetApiToken::new(t_uuid, token("newer", 2));
+
+        // Regardless of the direction of the merge, the later issued token is kept.
+        let r_vs_a = vs_a
+            .repl_merge_valueset(&vs_b, &Cid::new_zero())
+            .expect("failed to merge");
+        let r_vs_b = vs_b
+            .repl_merge_valueset(&vs_a, &Cid::new_zero())
+            .expect("failed to merge");
+
+        assert!(r_vs_a.equal(&r_vs_b));
+        assert_eq!(
+            r_vs_a
+                .as_apitoken_map()
+                .and_then(|map| map.get(&t_uuid))
+                .map(|token| token.label.as_str()),
+            Some("newer")
+        );
+    }
+
+    #[test]
+    fn test_valueset_session_by_recency() {
+        let cred_a = uuid::uuid!("00000000-0000-0000-0000-00000000000a");
+        let cred_b = uuid::uuid!("00000000-0000-0000-0000-00000000000b");
+
+        let session = |secs: u64, cred_id: Uuid| Session {
+            issued_at: OffsetDateTime::UNIX_EPOCH + Duration::from_secs(secs),
+            cred_id,
+            ..test_session()
+        };
+
+        let oldest = Uuid::new_v4();
+        let newest = Uuid::new_v4();
+        let tie_a = Uuid::new_v4();
+        let tie_b = Uuid::new_v4();
+
+        let vs = ValueSetSession::from_iter([
+            (oldest, session(1, cred_a)),
+            (tie_b, session(2, cred_b)),
+            (newest, session(3, cred_a)),
+            (tie_a, session(2, cred_a)),
+        ])
+        .expect("Unable to build valueset session");
+
+        let order: Vec<_> = vs
+            .sessions_by_recency()
+            .into_iter()
+            .map(|(session_id, _)| session_id)
+            .collect();
+
+        // Sessions issued at the same time are ordered by their credential.
+        assert_eq!(order, vec![newest, tie_a, tie_b, oldest]);
+    }
+
+    #[test]
+    fn test_valueset_api_token_expired_at_issue() {
+        let issued_at = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(60);
+        let schema = SchemaAttribute::default();
+
+        let token = |state: SessionState| ApiToken {
+            label: Uuid::new_v4().to_string(),
+            state,
+            issued_at,
+            ..test_api_token()
+        };
+
+        let mut vs: ValueSet =
+            ValueSetApiToken::new(Uuid::new_v4(), token(SessionState::NeverExpires));
+
+        // A token that expires after it is issued is accepted.
+        assert_eq!(
+            vs.insert_checked(Value::ApiToken(
+                Uuid::new_v4(),
+                token(SessionState::ExpiresAt(issued_at + Duration::from_secs(1)))
+            )),
+            Ok(true)
+        );
+        assert!(vs.validate(&schema));
+
+        // A token that expires before, or at the same instant it is issued, is rejected.
+        for expiry in [issued_at - Duration::from_secs(1), issued_at] {
+            assert_eq!(
+                vs.insert_checked(Value::ApiToken(
+                    Uuid::new_v4(),
+                    token(SessionState::ExpiresAt(expiry))
+                )),
+                Err(OperationError::InvalidValueState)
+            );
+        }
+        assert_eq!(vs.len(), 2);
+
+        // A token that has expired by the time it is written is rejected, though it
+        // remains valid once stored.
+        let expiry = issued_at + Duration::from_secs(30);
+        let curtime = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(90);
+        assert_eq!(
+            vs.insert_checked_at(
+                Value::ApiToken(Uuid::new_v4(), token(SessionState::ExpiresAt(expiry))),
+                curtime
+            ),
+            Err(OperationError::InvalidValueState)
+        );
+        assert_eq!(
+            vs.insert_checked_at(
+                Value::ApiToken(
+                    Uuid::new_v4(),
+                    token(SessionState::ExpiresAt(expiry + Duration::from_secs(60)))
+                ),
+                curtime
+            ),
+            Ok(true)
+        );
+        let vs_expired: ValueSet =
+            ValueSetApiToken::new(Uuid::new_v4(), token(SessionState::ExpiresAt(expiry)));
+        assert!(vs_expired.validate(&schema));
+        assert_eq!(vs.len(), 3);
+
+        // Tokens that were stored and have since expired can still be loaded.
+        let vs_loaded = crate::valueset::from_db_valueset_v2(vs.to_db_valueset_v2())
+            .expect("Failed to load api tokens");
+        assert!(vs.equal(&vs_loaded));
+    }
+
+    #[test]
+    fn test_valueset_api_token_evict_expired() {
+        let now = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(120);
+
+        let token = |state: SessionState| ApiToken {
+            label: Uuid::new_v4().to_string(),
+            state,
+            ..test_api_token()
+        };
+
+        let expired = SessionState::ExpiresAt(OffsetDateTime::UNIX_EPOCH + Duration::from_secs(60));
+
+        // Half the tokens have expired, and one is revoked.
+        let mut vs = ValueSetApiToken::from_iter((0..API_TOKEN_MAXIMUM).map(|i| {
+            let state = if i % 2 == 0 {
+                expired.clone()
+            } else {
+                SessionState::NeverExpires
+            };
+            (Uuid::new_v4(), token(state))
+        }))
+        .expect("Unable to build valueset api token");
+
+        assert!(vs.push(
+            Uuid::new_v4(),
+            token(SessionState::RevokedAt(Cid::new_zero()))
+        ));
+        assert_eq!(vs.len(), API_TOKEN_MAXIMUM + 1);
+
+        // Reclaim the expired tokens to make room. The revoked token is retained.
+        assert_eq!(vs.evict_expired_tokens(now), API_TOKEN_MAXIMUM / 2);
+        assert_eq!(vs.evict_expired_tokens(now), 0);
+        assert_eq!(vs.len(), API_TOKEN_MAXIMUM / 2 + 1);
+
+        assert_eq!(
+            vs.insert_checked(Value::ApiToken(
+                Uuid::new_v4(),
+                token(SessionState::NeverExpires)
+            )),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn test_valueset_session_diff() {
+        let session = |state: SessionState| Session {
+            state,
+            ..test_session()
+        };
+
+        let kept_uuid = Uuid::new_v4();
+        let removed_uuid = Uuid::new_v4();
+        let changed_uuid = Uuid::new_v4();
+        let added_uuid = Uuid::new_v4();
+        let one_cid = Cid::new_count(1);
+
+        let kept = session(SessionState::NeverExpires);
+        let changed = session(SessionState::NeverExpires);
+
+        let vs_a = ValueSetSession::from_iter([
+            (kept_uuid, kept.clone()),
+            (removed_uuid, session(SessionState::NeverExpires)),
+            (changed_uuid, changed.clone()),
+        ])
+        .expect("Unable to build valueset session");
+
+        let vs_b = ValueSetSession::from_iter([
+            (kept_uuid, kept),
+            (
+                changed_uuid,
+                Session {
+                    state: SessionState::RevokedAt(one_cid.clone()),
+                    ..changed
+                },
+            ),
+            (added_uuid, session(SessionState::NeverExpires)),
+        ])
+        .expect("Unable to build valueset session");
+
+        assert_eq!(
+            vs_a.diff(&vs_b),
+            SessionDiff {
+                added: vec![added_uuid],
+                removed: vec![removed_uuid],
+                changed: vec![(
+                    changed_uuid,
+                    SessionState::NeverExpires,
+                    SessionState::RevokedAt(one_cid)
+                )],
+            }
+        );
+
+        // No differences to itself.
+        assert_eq!(vs_a.diff(&vs_a), SessionDiff::default());
+    }
+
+    #[test]
+    fn test_valueset_api_token_unique_label() {
+        let issued_at = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(60);
+
+        let token = |label: &str, state: SessionState| ApiToken {
+            label: label.to_string(),
+            state,
+            issued_at,
+            ..test_api_token()
+        };
+
+        let mut vs: ValueSet = ValueSetApiToken::from_iter([
+            (Uuid::new_v4(), token("live", SessionState::NeverExpires)),
+            (
+                Uuid::new_v4(),
+                token("revoked", SessionState::RevokedAt(Cid::new_zero())),
+            ),
+            (
+                Uuid::new_v4(),
+                token("expired", SessionState::ExpiresAt(issued_at)),
+            ),
+        ])
+        .expect("Unable to build valueset api token");
+
+        // A label held by a live token can't be reused, regardless of case.
+        for label in ["live", "LIVE"] {
+            assert_eq!(
+                vs.insert_checked(Value::ApiToken(
+                    Uuid::new_v4(),
+                    token(label, SessionState::NeverExpires)
+                )),
+                Err(OperationError::DuplicateLabel)
+            );
+        }
+
+        // Labels held only by revoked or expired tokens can be reused.
+        for label in ["revoked", "Expired", "new"] {
+            assert_eq!(
+                vs.insert_checked(Value::ApiToken(
+                    Uuid::new_v4(),
+                    token(label, SessionState::NeverExpires)
+                )),
+                Ok(true)
+            );
+        }
+
+        // Entries that already hold duplicate labels still load and validate, so they
+        // can still be written.
+        let vs_dup: ValueSet = ValueSetApiToken::from_iter([
+            (Uuid::new_v4(), token("dup", SessionState::NeverExpires)),
+            (Uuid::new_v4(), token("DUP", SessionState::NeverExpires)),
+        ])
+        .expect("Unable to build valueset api token");
+
+        let mut vs_loaded = crate::valueset::from_db_valueset_v2(vs_dup.to_db_valueset_v2())
+            .expect("Failed to load api tokens");
+        assert_eq!(vs_loaded.len(), 2);
+        assert!(vs_loaded.validate(&SchemaAttribute::default()));
+
+        // A revoked token never holds a label, so may be written with a duplicate label.
+        assert_eq!(
+            vs_loaded.insert_checked(Value::ApiToken(
+                Uuid::new_v4(),
+                token("Dup", SessionState::RevokedAt(Cid::new_zero()))
+            )),
+            Ok(true)
+        );
+
+        // Replication revokes the duplicates, retaining the earliest issued.
+        let tokens = vs_loaded
+            .as_apitoken_set_mut()
+            .expect("Not an api token set");
+        assert!(tokens.revoke_duplicate_labels(&Cid::new_zero()));
+        assert!(!tokens.revoke_duplicate_labels(&Cid::new_zero()));
+        assert_eq!(
+            vs_loaded
+                .as_apitoken_map()
+                .expect("Not an api token set")
+                .values()
+                .filter(|at| at.is_revoked())
+                .count(),
+            2
+        );
+        assert!(vs_loaded.validate(&SchemaAttribute::default()));
+    }
+
+    #[test]
+    fn test_valueset_session_service_account_auth_type() {
+        let s_uuid = uuid::uuid!("3a163ca0-4762-4620-a188-06b750c84c86");
+
+        let vs: ValueSet = ValueSetSession::new(
+            s_uuid,
+            Session {
+                label: "hacks".to_string(),
+                state: SessionState::NeverExpires,
+                issued_at: OffsetDateTime::UNIX_EPOCH,
+                issued_by: IdentityId::Internal(UUID_SYSTEM),
+                cred_id: s_uuid,
+                scope: SessionScope::ReadWrite,
+                type_: AuthType::ServiceAccount,
+                ext_metadata: Default::default(),
+                bound_to: None,
+                last_active: None,
+                pinned: false,
+                service_account: true,
+                disabled: None,
+            },
+        );
+
+        let vs2 = crate::valueset::from_db_valueset_v2(vs.to_db_valueset_v2())
+            .expect("Failed to construct vs2 from dbvalue");
+        assert_eq!(&vs, &vs2);
+
+        let data = r#"
+[
+  {
+    "authType": "service_account",
+    "credentialId": "3a163ca0-4762-4620-a188-06b750c84c86",
+    "issuedAt": "1970-01-01T00:00:00Z",
+    "issuedBy": "00000000-0000-0000-0000-ffffff000000",
+    "issuedByType": "internal",
+    "id": "3a163ca0-4762-4620-a188-06b750c84c86",
+    "label": "hacks",
+    "pinned": false,
+    "serviceAccount": true,
+    "sessionScope": "read_write"
+  }
+]
+        "#;
+        crate::valueset::scim_json_reflexive(&vs, data);
+    }
+
+    #[test]
+    fn test_valueset_api_token_by_label() {
+        let token = |label: &str, state: SessionState| ApiToken {
+            label: label.to_string(),
+            state,
+            ..test_api_token()
+        };
+
+        let dup_a = Uuid::new_v4();
+        let dup_b = Uuid::new_v4();
+        let revoked = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        let zero_cid = Cid::new_zero();
+        let one_cid = Cid::new_count(1);
+
+        // Legacy data may hold duplicate labels.
+        let mut vs: ValueSet = ValueSetApiToken::from_iter([
+            (dup_a, token("Backup", SessionState::NeverExpires)),
+            (dup_b, token("backup", SessionState::NeverExpires)),
+            (
+                revoked,
+                token("backup", SessionState::RevokedAt(zero_cid.clone())),
+            ),
+            (other, token("deploy", SessionState::NeverExpires)),
+        ])
+        .expect("Unable to build valueset api token");
+
+        let idx_keys = vs.generate_idx_eq_keys();
+        assert!(idx_keys.contains(&"backup".to_string()));
+        assert!(idx_keys.contains(&"deploy".to_string()));
+
+        assert!(vs.contains(&PartialValue::new_iutf8("BACKUP")));
+        assert!(!vs.contains(&PartialValue::new_iutf8("missing")));
+        assert!(vs.substring(&PartialValue::new_iutf8("PLO")));
+        assert!(!vs.substring(&PartialValue::new_iutf8("missing")));
+
+        // All the live tokens with the label are revoked.
+        assert!(vs.remove(&PartialValue::new_iutf8("backup"), &one_cid));
+        assert!(!vs.remove(&PartialValue::new_iutf8("backup"), &one_cid));
+        assert!(!vs.remove(&PartialValue::new_iutf8("missing"), &one_cid));
+
+        // Revoked tokens no longer hold their label.
+        assert!(!vs.contains(&PartialValue::new_iutf8("backup")));
+        assert!(!vs.generate_idx_eq_keys().contains(&"backup".to_string()));
+        assert!(vs.contains(&PartialValue::Refer(dup_a)));
+
+        let tokens = vs.as_apitoken_map().expect("Unable to locate tokens");
+        assert_eq!(
+            tokens.get(&dup_a).map(|at| &at.state),
+            Some(&SessionState::RevokedAt(one_cid.clone()))
+        );
+        assert_eq!(
+            tokens.get(&dup_b).map(|at| &at.state),
+            Some(&SessionState::RevokedAt(one_cid))
+        );
+        // Existing revocations are retained.
+        assert_eq!(
+            tokens.get(&revoked).map(|at| &at.state),
+            Some(&SessionState::RevokedAt(zero_cid))
+        );
+        assert_eq!(
+            tokens.get(&other).map(|at| &at.state),
+            Some(&SessionState::NeverExpires)
+        );
+    }
+
+    #[test]
+    fn test_valueset_session_limit_trim_identical_issued_at() {
+        // Bulk session creation can yield sessions with identical issue times. Every
+        // one of them must be considered for force trimming.
+        let issued_at = OffsetDateTime::UNIX_EPOCH;
+
+        let session_iter = (0..SESSION_MAXIMUM + 5).map(|_| {
+            (
+                Uuid::new_v4(),
+                Session {
+                    label: "hacks".to_string(),
+                    state: SessionState::NeverExpires,
+                    issued_at,
+                    issued_by: IdentityId::Internal(UUID_SYSTEM),
+                    cred_id: Uuid::new_v4(),
+                    scope: SessionScope::ReadOnly,
+                    type_: AuthType::Passkey,
+                    ext_metadata: Default::default(),
+                    bound_to: None,
+                    last_active: None,
+                    pinned: false,
+                    service_account: false,
+                    disabled: None,
+                },
+            )
+        });
+
+        let mut vs_a: ValueSet = ValueSetSession::from_iter(session_iter).unwrap();
+        assert_eq!(vs_a.len(), SESSION_MAXIMUM + 5);
+
+        // Replication trims only revoked sessions, the maximum is left to the schema.
+        vs_a.trim(&Cid::new_zero());
+        assert_eq!(vs_a.len(), SESSION_MAXIMUM + 5);
+
+        let zero_cid = Cid::new_zero();
+        assert_eq!(
+            vs_a.trim_to_maximum(SESSION_MAXIMUM, &zero_cid, &zero_cid),
+            5
+        );
+
+        assert_eq!(vs_a.len(), SESSION_MAXIMUM);
+    }
+
+    #[test]
+    fn test_valueset_session_limit_trim_retains_synchronise() {
+        let session = |scope: SessionScope, issued_at: OffsetDateTime| Session {
+            issued_at,
+            scope,
+            ..test_session()
+        };
+
+        // The sync session is the oldest, so would be the first trimmed by issuance.
+        let sync_uuid = Uuid::new_v4();
+        let session_iter = std::iter::once((
+            sync_uuid,
+            session(SessionScope::Synchronise, OffsetDateTime::UNIX_EPOCH),
+        ))
+        .chain((1..=SESSION_MAXIMUM + 5).map(|i| {
+            (
+                Uuid::new_v4(),
+                session(
+                    SessionScope::ReadOnly,
+                    OffsetDateTime::UNIX_EPOCH + Duration::from_secs(i as u64),
+                ),
+            )
+        }));
+
+        let mut vs_a: ValueSet = ValueSetSession::from_iter(session_iter).unwrap();
+        assert_eq!(vs_a.len(), SESSION_MAXIMUM + 6);
+
+        let mut vs_b = vs_a.clone();
+        let zero_cid = Cid::new_zero();
+
+        vs_a.trim_to_maximum(SESSION_MAXIMUM, &zero_cid, &zero_cid);
+        assert_eq!(vs_a.len(), SESSION_MAXIMUM);
+        assert!(vs_a.contains(&PartialValue::Refer(sync_uuid)));
+
+        // Replicas trimming the same content arrive at the same result.
+        vs_b.trim_to_maximum(SESSION_MAXIMUM, &zero_cid, &zero_cid);
+        assert!(vs_a == vs_b);
+
+        // Synchronise sessions are still trimmed if they alone exceed the limit.
+        let session_iter = (1..=SESSION_MAXIMUM + 1).map(|i| {
+            (
+                Uuid::new_v4(),
+                session(
+                    SessionScope::Synchronise,
+                    OffsetDateTime::UNIX_EPOCH + Duration::from_secs(i as u64),
+                ),
+            )
+        });
+        let mut vs_c: ValueSet = ValueSetSession::from_iter(session_iter).unwrap();
+        vs_c.trim_to_maximum(SESSION_MAXIMUM, &zero_cid, &zero_cid);
+        assert_eq!(vs_c.len(), SESSION_MAXIMUM);
+    }
+
+    #[test]
+    fn test_valueset_session_limit_trim_retains_pinned() {
+        let session = |pinned: bool, issued_at: OffsetDateTime| {
+            Session::builder(issued_at, AuthType::Passkey)
+                .pinned(pinned)
+                .build()
+        };
+
+        // The pinned session is the oldest, so would be the first trimmed by issuance.
+        let pinned_uuid = Uuid::new_v4();
+        let unpinned: Vec<_> = (1..=SESSION_MAXIMUM + 5)
+            .map(|i| {
+                (
+                    Uuid::new_v4(),
+                    session(
+                        false,
+                        OffsetDateTime::UNIX_EPOCH + Duration::from_secs(i as u64),
+                    ),
+                )
+            })
+            .collect();
+        let session_iter =
+            std::iter::once((pinned_uuid, session(true, OffsetDateTime::UNIX_EPOCH)))
+                .chain(unpinned.iter().cloned());
+
+        let zero_cid = Cid::new_zero();
+        let mut vs_a: ValueSet = ValueSetSession::from_iter(session_iter).unwrap();
+        vs_a.trim_to_maximum(SESSION_MAXIMUM, &zero_cid, &zero_cid);
+        assert_eq!(vs_a.len(), SESSION_MAXIMUM);
+        assert!(vs_a.contains(&PartialValue::Refer(pinned_uuid)));
+
+        // Only the oldest unpinned sessions made way for it.
+        for (session_id, _) in unpinned.iter().take(6) {
+            assert!(!vs_a.contains(&PartialValue::Refer(*session_id)));
+        }
+        for (session_id, _) in unpinned.iter().skip(6) {
+            assert!(vs_a.contains(&PartialValue::Refer(*session_id)));
+        }
+
+        // The pin survives a round trip through the database.
+        let DbValueSetV2::Session(dbvs) = vs_a.to_db_valueset_v2() else {
+            panic!("Unexpected db valueset");
+        };
+        let vs_reload = ValueSetSession::from_dbvs2(&dbvs).expect("Failed to reload");
+        assert!(vs_a.equal(&vs_reload));
+
+        // Once pinned sessions alone exceed the limit, the oldest of them are trimmed too.
+        let pinned: Vec<_> = (1..=SESSION_MAXIMUM + 1)
+            .map(|i| {
+                (
+                    Uuid::new_v4(),
+                    session(
+                        true,
+                        OffsetDateTime::UNIX_EPOCH + Duration::from_secs(i as u64),
+                    ),
+                )
+            })
+            .collect();
+        let mut vs_b: ValueSet = ValueSetSession::from_iter(pinned.iter().cloned()).unwrap();
+        vs_b.trim_to_maximum(SESSION_MAXIMUM, &zero_cid, &zero_cid);
+        assert_eq!(vs_b.len(), SESSION_MAXIMUM);
+        assert!(!vs_b.contains(&PartialValue::Refer(pinned[0].0)));
+        assert!(vs_b.contains(&PartialValue::Refer(pinned[1].0)));
+    }
+
+    #[test]
+    fn test_valueset_session_trim_retain_revoked() {
+        let revoked = |cid: Cid| Session {
+            state: SessionState::RevokedAt(cid),
+            issued_at: OffsetDateTime::UNIX_EPOCH + Duration::from_secs(2 * 86400),
+            ..test_session()
+        };
+
+        let zero_uuid = Uuid::new_v4();
+        let one_uuid = Uuid::new_v4();
+        let two_uuid = Uuid::new_v4();
+        let three_cid = Cid::new_count(3);
+
+        let vs = ValueSetSession::from_iter([
+            (zero_uuid, revoked(Cid::new_zero())),
+            (one_uuid, revoked(Cid::new_count(1))),
+            (two_uuid, revoked(Cid::new_count(2))),
+        ])
+        .unwrap();
+
+        // Without a floor, everything past the trim window is removed.
+        let mut vs_a = vs.clone();
+        vs_a.trim_with_policy(&three_cid, &TrimPolicy::default());
+        assert!(vs_a.is_empty());
+
+        // With a floor, revocations newer than the floor are retained.
+        let mut vs_b = vs;
+        let policy = TrimPolicy {
+            retain_revoked_until: Some(OffsetDateTime::from(&Cid::new_zero())),
+            ..Default::default()
+        };
+        vs_b.trim_with_policy(&three_cid, &policy);
+
+        let sessions = vs_b.as_session_map().expect("Unable to locate session");
+        assert!(!sessions.contains_key(&zero_uuid));
+        assert!(sessions.contains_key(&one_uuid));
+        assert!(sessions.contains_key(&two_uuid));
+
+        // Retained revocations are removed before live sessions when over the maximum,
+        // even though they are the most recently issued.
+        let live = |secs: u64| Session {
+            state: SessionState::NeverExpires,
+            issued_at: OffsetDateTime::UNIX_EPOCH + Duration::from_secs(secs),
+            ..revoked(Cid::new_zero())
+        };
+        let live_sessions: Vec<_> = (1..SESSION_MAXIMUM as u64)
+            .map(|secs| (Uuid::new_v4(), live(secs)))
+            .collect();
+        let mut vs_c = ValueSetSession::from_iter(live_sessions.iter().cloned().chain([
+            (
+                one_uuid,
+                Session {
+                    issued_at: OffsetDateTime::UNIX_EPOCH + Duration::from_secs(86400),
+                    ..revoked(Cid::new_count(1))
+                },
+            ),
+            (two_uuid, revoked(Cid::new_count(2))),
+        ]))
+        .unwrap();
+        let policy = TrimPolicy {
+            maximum: Some(SESSION_MAXIMUM),
+            ..policy
+        };
+        vs_c.trim_with_policy(&three_cid, &policy);
+
+        let sessions = vs_c.as_session_map().expect("Unable to locate session");
+        assert_eq!(sessions.len(), SESSION_MAXIMUM);
+        assert!(!sessions.contains_key(&one_uuid));
+        assert!(sessions.contains_key(&two_uuid));
+        assert!(live_sessions
+            .iter()
+            .all(|(session_id, _)| sessions.contains_key(session_id)));
+    }
+
+    #[test]
+    fn test_valueset_session_trim_scope_maximums() {
+        let session = |scope: SessionScope, secs: u64| {
+            (
+                Uuid::new_v4(),
+                Session {
+                    issued_at: OffsetDateTime::UNIX_EPOCH + Duration::from_secs(secs),
+                    scope,
+                    ..test_session()
+                },
+            )
+        };
+
+        // Interleave the scopes so that the oldest sessions of each are spread through
+        // the set.
+        let sessions: Vec<_> = (0..12)
+            .map(|i| {
+                let scope = match i % 3 {
+                    0 => SessionScope::PrivilegeCapable,
+                    1 => SessionScope::ReadWrite,
+                    _ => SessionScope::ReadOnly,
+                };
+                session(scope, i)
+            })
+            .collect();
+
+        let newest = |scope: SessionScope, count: usize| -> BTreeSet<Uuid> {
+            let mut ids: Vec<_> = sessions
+                .iter()
+                .filter(|(_, s)| s.scope == scope)
+                .map(|(u, s)| (s.issued_at, *u))
+                .collect();
+            ids.sort();
+            ids.into_iter().rev().take(count).map(|(_, u)| u).collect()
+        };
+
+        let mut vs = ValueSetSession::from_iter(sessions.iter().cloned()).unwrap();
+
+        let policy = TrimPolicy {
+            scope_maximums: BTreeMap::from([
+                (SessionScope::PrivilegeCapable, 1),
+                (SessionScope::ReadWrite, 3),
+            ]),
+            ..Default::default()
+        };
+        vs.trim_with_policy(&Cid::new_zero(), &policy);
+
+        let remaining = |scope: SessionScope| -> BTreeSet<Uuid> {
+            vs.as_session_map()
+                .into_iter()
+                .flatten()
+                .filter(|(_, s)| s.scope == scope)
+                .map(|(u, _)| *u)
+                .collect()
+        };
+
+        // Each scope is trimmed to its own limit, keeping its newest sessions.
+        assert_eq!(
+            remaining(SessionScope::PrivilegeCapable),
+            newest(SessionScope::PrivilegeCapable, 1)
+        );
+        assert_eq!(
+            remaining(SessionScope::ReadWrite),
+            newest(SessionScope::ReadWrite, 3)
+        );
+        // A scope without a limit is untouched.
+        assert_eq!(
+            remaining(SessionScope::ReadOnly),
+            newest(SessionScope::ReadOnly, 4)
+        );
+        assert_eq!(vs.len(), 8);
+
+        // The set maximum still applies as a ceiling over all scopes.
+        let mut vs = ValueSetSession::from_iter(
+            (0..SESSION_MAXIMUM as u64 + 4).map(|i| session(SessionScope::ReadOnly, i)),
+        )
+        .unwrap();
+        let policy = TrimPolicy {
+            scope_maximums: BTreeMap::from([(SessionScope::ReadOnly, SESSION_MAXIMUM + 2)]),
+            maximum: Some(SESSION_MAXIMUM),
+            ..Default::default()
+        };
+        vs.trim_with_policy(&Cid::new_zero(), &policy);
+        assert_eq!(vs.len(), SESSION_MAXIMUM);
+    }
+
+    #[qs_test]
+    async fn test_scim_api_token_parent_session(server: &QueryServer) {
+        let ct = Duration::from_secs(6000);
+        let mut write_txn = server.write(ct).await.unwrap();
+
+        let t_uuid = uuid::uuid!("3a163ca0-4762-4620-a188-06b750c84c86");
+        let p_uuid = uuid::uuid!("8b2a5cb4-4de5-4bd6-a6ef-2c1f5a8a4d7e");
+        let e_uuid = uuid::uuid!("4d21d04a-dc0e-42eb-b850-34dd180b107f");
+
+        let token = |state: SessionState, parent_session: Option<Uuid>| ApiToken {
+            state,
+            parent_session,
+            ..test_api_token()
+        };
+
+        let vs: ValueSet = ValueSetApiToken::from_iter([
+            (t_uuid, token(SessionState::NeverExpires, Some(p_uuid))),
+            (
+                e_uuid,
+                token(
+                    SessionState::ExpiresAt(OffsetDateTime::UNIX_EPOCH + ct),
+                    None,
+                ),
+            ),
+        ])
+        .unwrap();
+
+        let vs2 = crate::valueset::from_db_valueset_v2(vs.to_db_valueset_v2())
+            .expect("Failed to construct vs2 from dbvalue");
+        assert_eq!(&vs, &vs2);
+
+        assert!(vs
+            .generate_idx_eq_keys()
+            .contains(&p_uuid.as_hyphenated().to_string()));
+
+        let data = r#"
+[
+  {
+    "id": "3a163ca0-4762-4620-a188-06b750c84c86",
+    "label": "hacks",
+    "issuedAt": "1970-01-01T00:00:00Z",
+    "issuedBy": "00000000-0000-0000-0000-ffffff000000",
+    "issuedByType": "internal",
+    "scope": "read_only",
+    "parentSession": "8b2a5cb4-4de5-4bd6-a6ef-2c1f5a8a4d7e",
+    "generation": 0,
+    "expired": false
+  },
+  {
+    "id": "4d21d04a-dc0e-42eb-b850-34dd180b107f",
+    "label": "hacks",
+    "expires": "1970-01-01T01:40:00Z",
+    "issuedAt": "1970-01-01T00:00:00Z",
+    "issuedBy": "00000000-0000-0000-0000-ffffff000000",
+    "issuedByType": "internal",
+    "scope": "read_only",
+    "generation": 0,
+    "expired": true
+  }
+]
+        "#;
+        crate::valueset::scim_json_reflexive_unresolved(&mut write_txn, &vs, data);
+
+        assert!(write_txn.commit().is_ok());
+    }
+
+    #[test]
+    fn test_valueset_api_token_rotate() {
+        let t_uuid = Uuid::new_v4();
+        let r_uuid = Uuid::new_v4();
+        let one_sec = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(1);
+        let two_sec = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(2);
+
+        let token = |state: SessionState| ApiToken {
+            state,
+            ..test_api_token()
+        };
+
+        let mut vs = ValueSetApiToken::from_iter([
+            (t_uuid, token(SessionState::ExpiresAt(two_sec))),
+            (r_uuid, token(SessionState::RevokedAt(Cid::new_count(1)))),
+        ])
+        .unwrap();
+        let original = vs.clone();
+
+        // A missing or revoked token can't be rotated.
+        assert_eq!(
+            vs.rotate(Uuid::new_v4(), one_sec, None).unwrap_err(),
+            OperationError::NoMatchingEntries
+        );
+        assert_eq!(
+            vs.rotate(r_uuid, one_sec, None).unwrap_err(),
+            OperationError::InvalidValueState
+        );
+        // Nor may the rotation leave it expired.
+        assert_eq!(
+            vs.rotate(t_uuid, two_sec, None).unwrap_err(),
+            OperationError::InvalidValueState
+        );
+
+        let rotated = vs
+            .rotate(t_uuid, one_sec, Some(one_sec + Duration::from_secs(1)))
+            .expect("Failed to rotate token")
+            .clone();
+        assert_eq!(rotated.label, "hacks");
+        assert_eq!(rotated.generation, 1);
+        assert_eq!(rotated.issued_at, one_sec);
+        assert_eq!(rotated.state, SessionState::ExpiresAt(two_sec));
+
+        // A rotated token supersedes its earlier generation in either merge direction.
+        let vs: ValueSet = vs;
+        let original: ValueSet = original;
+        for (a, b) in [(&vs, &original), (&original, &vs)] {
+            let merged = b
+                .repl_merge_valueset(a, &Cid::new_zero())
+                .expect("Failed to merge");
+            let tokens = merged
+                .as_apitoken_map()
+                .expect("Unable to locate api tokens");
+            assert_eq!(tokens.get(&t_uuid), Some(&rotated));
+        }
+    }
+
+    #[test]
+    fn test_valueset_session_equal() {
+        let session = |label: &str| Session {
+            label: label.to_string(),
+            cred_id: Uuid::nil(),
+            ..test_session()
+        };
+
+        let a_uuid = Uuid::new_v4();
+        let b_uuid = Uuid::new_v4();
+
+        let vs_a: ValueSet =
+            ValueSetSession::from_iter([(a_uuid, session("a")), (b_uuid, session("b"))]).unwrap();
+        let vs_a2: ValueSet =
+            ValueSetSession::from_iter([(a_uuid, session("a")), (b_uuid, session("b"))]).unwrap();
+        // Same length, same keys, different content.
+        let vs_b: ValueSet =
+            ValueSetSession::from_iter([(a_uuid, session("a")), (b_uuid, session("c"))]).unwrap();
+        // Same length, different keys.
+        let vs_c: ValueSet =
+            ValueSetSession::from_iter([(a_uuid, session("a")), (Uuid::new_v4(), session("b"))])
+                .unwrap();
+        // Different length.
+        let vs_d: ValueSet = ValueSetSession::new(a_uuid, session("a"));
+
+        assert!(vs_a.equal(&vs_a2));
+        assert!(!vs_a.equal(&vs_b));
+        assert!(!vs_a.equal(&vs_c));
+        assert!(!vs_a.equal(&vs_d));
+        assert!(!vs_d.equal(&vs_a));
+    }
+
+    #[test]
+    fn test_valueset_session_recent_issue_count() {
+        let session = |secs: u64, state: SessionState| Session {
+            state,
+            issued_at: OffsetDateTime::UNIX_EPOCH + Duration::from_secs(secs),
+            ..test_session()
+        };
+
+        let vs = ValueSetSession::from_iter([
+            (Uuid::new_v4(), session(0, SessionState::NeverExpires)),
+            (Uuid::new_v4(), session(30, SessionState::NeverExpires)),
+            (Uuid::new_v4(), session(60, SessionState::NeverExpires)),
+            (
+                Uuid::new_v4(),
+                session(90, SessionState::RevokedAt(Cid::new_count(100))),
+            ),
+            (Uuid::new_v4(), session(120, SessionState::NeverExpires)),
+        ])
+        .unwrap();
+
+        let at = |secs: u64| OffsetDateTime::UNIX_EPOCH + Duration::from_secs(secs);
+
+        assert_eq!(vs.recent_issue_count(at(0)), 5);
+        // The boundary is inclusive, and revoked sessions still count as issued.
+        assert_eq!(vs.recent_issue_count(at(60)), 3);
+        assert_eq!(vs.recent_issue_count(at(61)), 2);
+        assert_eq!(vs.recent_issue_count(at(121)), 0);
+    }
+
+    #[qs_test]
+    async fn test_scim_api_token_expired(server: &QueryServer) {
+        let ct = Duration::from_secs(6000);
+        let mut write_txn = server.write(ct).await.unwrap();
+
+        let exact_uuid = uuid::uuid!("3a163ca0-4762-4620-a188-06b750c84c86");
+        let valid_uuid = uuid::uuid!("4d21d04a-dc0e-42eb-b850-34dd180b107f");
+        let revoked_uuid = uuid::uuid!("8b2a5cb4-4de5-4bd6-a6ef-2c1f5a8a4d7e");
+
+        let token = |state: SessionState| ApiToken {
+            state,
+            issued_by: IdentityId::User(exact_uuid),
+            ..test_api_token()
+        };
+
+        let now = OffsetDateTime::UNIX_EPOCH + ct;
+
+        let vs: ValueSet = ValueSetApiToken::from_iter([
+            // A token exactly at its expiry is no longer valid.
+            (exact_uuid, token(SessionState::ExpiresAt(now))),
+            (
+                valid_uuid,
+                token(SessionState::ExpiresAt(now + Duration::from_secs(1))),
+            ),
+            (
+                revoked_uuid,
+                token(SessionState::RevokedAt(Cid::new_count(1))),
+            ),
+        ])
+        .unwrap();
+
+        let scim_int_value = vs.to_scim_value().unwrap().assume_unresolved();
+        let Some(ScimValueKanidm::ApiToken(tokens)) = write_txn
+            .resolve_scim_interim(scim_int_value)
+            .expect("Failed to resolve api tokens")
+        else {
+            panic!("Unexpected scim value");
+        };
+
+        let revoked = |id: Uuid| tokens.iter().any(|t| t.id == id && t.revoked.is_some());
+
+        assert!(!revoked(exact_uuid));
+        assert!(!revoked(valid_uuid));
+        assert!(revoked(revoked_uuid));
+
+        let expired = |id: Uuid| tokens.iter().any(|t| t.id == id && t.expired);
+
+        assert!(expired(exact_uuid));
+        assert!(!expired(valid_uuid));
+        // A revoked token has no expiry to compare against.
+        assert!(!expired(revoked_uuid));
+        assert!(tokens.iter().all(|t| t.issued_by_type == "user"));
+
+        assert!(write_txn.commit().is_ok());
+    }
+
+    #[test]
+    fn test_valueset_api_token_merge_conflict_order() {
+        let t_uuid = Uuid::new_v4();
+
+        let base = ApiToken {
+            label: "hacks".to_string(),
+            state: SessionState::ExpiresAt(OffsetDateTime::UNIX_EPOCH + Duration::from_secs(10)),
+            issued_at: OffsetDateTime::UNIX_EPOCH,
+            issued_by: IdentityId::Internal(UUID_SYSTEM),
+            scope: ApiTokenScope::ReadOnly,
+            parent_session: None,
+            generation: 0,
+            constraints: None,
+        };
+
+        // Each pair is (winner, loser).
+        let cases = [
+            // A revocation proceeds a later generation.
+            (
+                ApiToken {
+                    state: SessionState::RevokedAt(Cid::new_count(1)),
+                    ..base.clone()
+                },
+                ApiToken {
+                    generation: 1,
+                    ..base.clone()
+                },
+            ),
+            // A later generation proceeds a later expiry.
+            (
+                ApiToken {
+                    generation: 1,
+                    ..base.clone()
+                },
+                ApiToken {
+                    state: SessionState::ExpiresAt(
+                        OffsetDateTime::UNIX_EPOCH + Duration::from_secs(20),
+                    ),
+                    ..base.clone()
+                },
+            ),
+            // A later expiry proceeds a later issue.
+            (
+                ApiToken {
+                    state: SessionState::ExpiresAt(
+                        OffsetDateTime::UNIX_EPOCH + Duration::from_secs(20),
+                    ),
+                    ..base.clone()
+                },
+                ApiToken {
+                    issued_at: OffsetDateTime::UNIX_EPOCH + Duration::from_secs(1),
+                    ..base.clone()
+                },
+            ),
+            // A later issue proceeds the content of the token.
+            (
+                ApiToken {
+                    issued_at: OffsetDateTime::UNIX_EPOCH + Duration::from_secs(1),
+                    ..base.clone()
+                },
+                ApiToken {
+                    label: "zzz".to_string(),
+                    ..base.clone()
+                },
+            ),
+            // Finally, the content is compared.
+            (
+                ApiToken {
+                    label: "zzz".to_string(),
+                    ..base.clone()
+                },
+                base.clone(),
+            ),
+            (
+                ApiToken {
+                    scope: ApiTokenScope::ReadWrite,
+                    ..base.clone()
+                },
+                base.clone(),
+            ),
+        ];
+
+        for (winner, loser) in cases {
+            let vs_w: ValueSet = ValueSetApiToken::new(t_uuid, winner.clone());
+            let vs_l: ValueSet = ValueSetApiToken::new(t_uuid, loser);
+
+            for (a, b) in [(&vs_w, &vs_l), (&vs_l, &vs_w)] {
+                let merged = a
+                    .repl_merge_valueset(b, &Cid::new_zero())
+                    .expect("Failed to merge");
+                assert_eq!(
+                    merged.as_apitoken_map().and_then(|map| map.get(&t_uuid)),
+                    Some(&winner)
+                );
+
+                let mut merged = a.clone();
+                merged.merge(b).expect("Failed to merge");
+                assert_eq!(
+                    merged.as_apitoken_map().and_then(|map| map.get(&t_uuid)),
+                    Some(&winner)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_valueset_session_purge_counted() {
+        let session = |state: SessionState| Session {
+            state,
+            ..test_session()
+        };
+
+        let revoked_uuid = Uuid::new_v4();
+        let one_cid = Cid::new_count(1);
+        let two_cid = Cid::new_count(2);
+
+        let mut vs = ValueSetSession::from_iter([
+            (
+                revoked_uuid,
+                session(SessionState::RevokedAt(one_cid.clone())),
+            ),
+            (Uuid::new_v4(), session(SessionState::NeverExpires)),
+            (
+                Uuid::new_v4(),
+                session(SessionState::ExpiresAt(OffsetDateTime::UNIX_EPOCH)),
+            ),
+        ])
+        .unwrap();
+
+        // Only the active sessions are counted, and the earlier revocation is retained.
+        assert_eq!(vs.purge_counted(&two_cid), 2);
+        assert_eq!(
+            vs.as_session_map()
+                .and_then(|map| map.get(&revoked_uuid))
+                .map(|s| &s.state),
+            Some(&SessionState::RevokedAt(one_cid))
+        );
+        assert!(vs
+            .as_session_map()
+            .expect("Unable to locate sessions")
+            .values()
+            .all(|s| matches!(s.state, SessionState::RevokedAt(_))));
+
+        // Nothing remains to be revoked.
+        assert_eq!(vs.purge_counted(&two_cid), 0);
+    }
+
+    #[test]
+    fn test_valueset_api_token_single_sync_token() {
+        let issued_at = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(10);
+
+        let token = |label: &str, scope: ApiTokenScope, state: SessionState| ApiToken {
+            label: label.to_string(),
+            state,
+            issued_at,
+            scope,
+            ..test_api_token()
+        };
+
+        let live_uuid = Uuid::new_v4();
+        let mut vs = ValueSetApiToken::new(
+            live_uuid,
+            token("a", ApiTokenScope::Synchronise, SessionState::NeverExpires),
+        );
+
+        // Rejected while the existing sync token is live.
+        assert_eq!(
+            vs.insert_checked(Value::ApiToken(
+                Uuid::new_v4(),
+                token("b", ApiTokenScope::Synchronise, SessionState::NeverExpires)
+            )),
+            Err(OperationError::SyncTokenAlreadyActive)
+        );
+
+        // Other scopes are unaffected.
+        assert_eq!(
+            vs.insert_checked(Value::ApiToken(
+                Uuid::new_v4(),
+                token("c", ApiTokenScope::ReadOnly, SessionState::NeverExpires)
+            )),
+            Ok(true)
+        );
+
+        // Once revoked, the sync token can be replaced.
+        assert!(vs.remove(&PartialValue::Refer(live_uuid), &Cid::new_count(1)));
+        let replacement_uuid = Uuid::new_v4();
+        assert_eq!(
+            vs.insert_checked(Value::ApiToken(
+                replacement_uuid,
+                token(
+                    "d",
+                    ApiTokenScope::Synchronise,
+                    SessionState::ExpiresAt(issued_at)
+                )
+            )),
+            Err(OperationError::InvalidValueState)
+        );
+        assert_eq!(
+            vs.insert_checked(Value::ApiToken(
+                replacement_uuid,
+                token(
+                    "d",
+                    ApiTokenScope::Synchronise,
+                    SessionState::ExpiresAt(issued_at + Duration::from_secs(10))
+                )
+            )),
+            Ok(true)
+        );
+
+        // And once that replacement has expired, it too can be replaced.
+        assert_eq!(
+            vs.insert_checked(Value::ApiToken(
+                Uuid::new_v4(),
+                ApiToken {
+                    issued_at: issued_at + Duration::from_secs(10),
+                    ..token("e", ApiTokenScope::Synchronise, SessionState::NeverExpires)
+                }
+            )),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn test_valueset_session_revoked_at_timestamp() {
+        let cid = Cid::new_count(6000);
+        assert_eq!(
+            revoked_at_timestamp(&cid),
+            OffsetDateTime::UNIX_EPOCH + Duration::from_secs(6000)
+        );
+
+        // A zero timestamp is suspicious, but must still resolve to a time.
+        let cid = Cid::new_zero();
+        assert_eq!(revoked_at_timestamp(&cid), OffsetDateTime::UNIX_EPOCH);
+    }
+
+    #[test]
+    fn test_valueset_api_token_lessthan() {
+        let issued_at = OffsetDateTime::UNIX_EPOCH;
+        let at = |secs| OffsetDateTime::UNIX_EPOCH + Duration::from_secs(secs);
+
+        let token = |label: &str, state: SessionState| ApiToken {
+            label: label.to_string(),
+            state,
+            issued_at,
+            ..test_api_token()
+        };
+
+        let mut vs =
+            ValueSetApiToken::new(Uuid::new_v4(), token("a", SessionState::ExpiresAt(at(100))));
+
+        let pv = PartialValue::DateTime(at(50));
+        assert!(!vs.lessthan(&pv));
+
+        let pv = PartialValue::DateTime(at(100));
+        assert!(!vs.lessthan(&pv));
+
+        let pv = PartialValue::DateTime(at(200));
+        assert!(vs.lessthan(&pv));
+
+        // A token that never expires doesn't hide one that has.
+        assert_eq!(
+            vs.insert_checked(Value::ApiToken(
+                Uuid::new_v4(),
+                token("b", SessionState::NeverExpires)
+            )),
+            Ok(true)
+        );
+        assert!(vs.lessthan(&pv));
+
+        // Revoked tokens no longer expire, whenever they were revoked.
+        let vs = ValueSetApiToken::new(
+            Uuid::new_v4(),
+            token("c", SessionState::RevokedAt(Cid::new_count(100))),
+        );
+        assert!(!vs.lessthan(&pv));
+
+        // Other partial values never match.
+        assert!(!vs.lessthan(&PartialValue::Uint32(200)));
+    }
+
+    #[qs_test]
+    async fn test_api_token_expiry_filter(server: &QueryServer) {
+        let mut write_txn = server.write(duration_from_epoch_now()).await.unwrap();
+
+        let at = |secs| OffsetDateTime::UNIX_EPOCH + Duration::from_secs(secs);
+
+        // Each account holds one token per state given.
+        let account = |name: &str, uuid: Uuid, states: &[SessionState]| {
+            let mut e = entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (Attribute::Class, EntryClass::Account.to_value()),
+                (Attribute::Class, EntryClass::ServiceAccount.to_value()),
+                (Attribute::Name, Value::new_iname(name)),
+                (Attribute::Uuid, Value::Uuid(uuid)),
+                (Attribute::DisplayName, Value::new_utf8s(name))
+            );
+            for (i, state) in states.iter().enumerate() {
+                let token = ApiToken {
+                    label: format!("{name}_{i}"),
+                    state: state.clone(),
+                    issued_at: at(0),
+                    ..test_api_token()
+                };
+                e.add_ava(
+                    Attribute::ApiTokenSession,
+                    Value::ApiToken(Uuid::new_v4(), token),
+                );
+            }
+            e
+        };
+
+        let early_uuid = Uuid::new_v4();
+        let late_uuid = Uuid::new_v4();
+        let never_uuid = Uuid::new_v4();
+        let mixed_uuid = Uuid::new_v4();
+
+        assert!(write_txn
+            .internal_create(vec![
+                account(
+                    "test_early",
+                    early_uuid,
+                    &[SessionState::ExpiresAt(at(100))]
+                ),
+                account(
+                    "test_late",
+                    late_uuid,
+                    &[
+                        SessionState::ExpiresAt(at(10_000)),
+                        SessionState::ExpiresAt(at(20_000))
+                    ]
+                ),
+                account("test_never", never_uuid, &[SessionState::NeverExpires]),
+                // Any one token ending before the time is enough to match.
+                account(
+                    "test_mixed",
+                    mixed_uuid,
+                    &[
+                        SessionState::NeverExpires,
+                        SessionState::ExpiresAt(at(500)),
+                        SessionState::ExpiresAt(at(10_000))
+                    ]
+                ),
+            ])
+            .is_ok());
+
+        let search = |write_txn: &mut QueryServerWriteTransaction, filter| {
+            let mut uuids: Vec<_> = write_txn
+                .internal_search(filter)
+                .expect("Failed to search")
+                .iter()
+                .map(|e| e.get_uuid())
+                .collect();
+            uuids.sort_unstable();
+            uuids
+        };
+
+        let pv = PartialValue::DateTime(at(1_000));
+
+        let mut expect = vec![early_uuid, mixed_uuid];
+        expect.sort_unstable();
+        assert_eq!(
+            search(
+                &mut write_txn,
+                filter!(f_lt(Attribute::ApiTokenSession, pv))
+            ),
+            expect
+        );
+
+        // Before every expiry, nothing matches.
+        assert!(search(
+            &mut write_txn,
+            filter!(f_lt(
+                Attribute::ApiTokenSession,
+                PartialValue::DateTime(at(50))
+            ))
+        )
+        .is_empty());
+
+        assert!(write_txn.commit().is_ok());
+    }
+
+    #[test]
+    fn test_valueset_session_get_value() {
+        let s_uuid = Uuid::new_v4();
+        let session = Session {
+            label: "label".to_string(),
+            state: SessionState::NeverExpires,
+            issued_at: OffsetDateTime::UNIX_EPOCH,
+            issued_by: IdentityId::Internal(UUID_SYSTEM),
+            cred_id: Uuid::new_v4(),
+            scope: SessionScope::ReadOnly,
+            type_: AuthType::Passkey,
+            ext_metadata: Default::default(),
+            bound_to: None,
+            last_active: None,
+            pinned: false,
+            service_account: false,
+            disabled: None,
+        };
+        let vs: ValueSet = ValueSetSession::new(s_uuid, session.clone());
+
+        assert!(matches!(
+            vs.get_value(&PartialValue::Refer(s_uuid)),
+            Some(Value::Session(u, got)) if u == s_uuid && got == session
+        ));
+        assert!(vs.get_value(&PartialValue::Refer(Uuid::new_v4())).is_none());
+        assert!(vs.get_value(&PartialValue::Uuid(s_uuid)).is_none());
+    }
+
+    #[test]
+    fn test_valueset_oauth2_session_get_value() {
+        let s_uuid = Uuid::new_v4();
+        let rs_uuid = Uuid::new_v4();
+        let session = Oauth2Session {
+            state: SessionState::NeverExpires,
+            issued_at: OffsetDateTime::UNIX_EPOCH,
+            parent: None,
+            rs_uuid,
+            granted_scopes: Default::default(),
+            has_refresh: false,
+            last_refreshed: None,
+            origin: None,
+        };
+        let vs: ValueSet = ValueSetOauth2Session::new(s_uuid, session.clone());
+
+        assert!(matches!(
+            vs.get_value(&PartialValue::Refer(s_uuid)),
+            Some(Value::Oauth2Session(u, got)) if u == s_uuid && got == session
+        ));
+        // The resource server is found by contains, but does not identify a single session.
+        assert!(vs.contains(&PartialValue::Refer(rs_uuid)));
+        assert!(vs.get_value(&PartialValue::Refer(rs_uuid)).is_none());
+    }
+
+    #[test]
+    fn test_valueset_api_token_get_value() {
+        let t_uuid = Uuid::new_v4();
+        let token = ApiToken {
+            label: "label".to_string(),
+            state: SessionState::NeverExpires,
+            issued_at: OffsetDateTime::UNIX_EPOCH,
+            issued_by: IdentityId::Internal(UUID_SYSTEM),
+            scope: ApiTokenScope::ReadOnly,
+            parent_session: None,
+            generation: 0,
+            constraints: None,
+        };
+        let vs: ValueSet = ValueSetApiToken::new(t_uuid, token.clone());
+
+        assert!(matches!(
+            vs.get_value(&PartialValue::Refer(t_uuid)),
+            Some(Value::ApiToken(u, got)) if u == t_uuid && got == token
+        ));
+        // Labels and issuers are secondary keys, not identities.
+        assert!(vs.get_value(&PartialValue::new_iutf8("label")).is_none());
+        assert!(vs.get_value(&PartialValue::Refer(UUID_SYSTEM)).is_none());
+    }
+
+    #[test]
+    fn test_valueset_api_token_scope() {
+        let token = |label: &str, scope: ApiTokenScope, state: SessionState| ApiToken {
+            label: label.to_string(),
+            state,
+            scope,
+            ..test_api_token()
+        };
+
+        let ro_uuid = Uuid::new_v4();
+        let rw_uuid = Uuid::new_v4();
+        let sync_uuid = Uuid::new_v4();
+        let revoked_uuid = Uuid::new_v4();
+
+        let mut vs = ValueSetApiToken::new(
+            ro_uuid,
+            token("ro", ApiTokenScope::ReadOnly, SessionState::NeverExpires),
+        );
+
+        assert!(vs.contains(&ApiTokenScope::ReadOnly.into()));
+        assert!(!vs.contains(&ApiTokenScope::ReadWrite.into()));
+        assert!(!vs.contains(&ApiTokenScope::Synchronise.into()));
+        assert!(vs.tokens_with_scope(ApiTokenScope::Synchronise).is_empty());
+
+        vs.push(
+            rw_uuid,
+            token("rw", ApiTokenScope::ReadWrite, SessionState::NeverExpires),
+        );
+        vs.push(
+            sync_uuid,
+            token(
+                "sync",
+                ApiTokenScope::Synchronise,
+                SessionState::NeverExpires,
+            ),
+        );
+        // Revoked tokens are still reported, as this is used for auditing.
+        vs.push(
+            revoked_uuid,
+            token(
+                "revoked",
+                ApiTokenScope::Synchronise,
+                SessionState::RevokedAt(Cid::new_count(1)),
+            ),
+        );
+
+        assert!(vs.contains(&ApiTokenScope::ReadOnly.into()));
+        assert!(vs.contains(&ApiTokenScope::ReadWrite.into()));
+        assert!(vs.contains(&ApiTokenScope::Synchronise.into()));
+
+        assert_eq!(vs.tokens_with_scope(ApiTokenScope::ReadOnly), vec![ro_uuid]);
+        assert_eq!(
+            vs.tokens_with_scope(ApiTokenScope::ReadWrite),
+            vec![rw_uuid]
+        );
+
+        let mut expect = vec![sync_uuid, revoked_uuid];
+        expect.sort_unstable();
+        assert_eq!(vs.tokens_with_scope(ApiTokenScope::Synchronise), expect);
+    }
+
+    #[test]
+    fn test_session_dbv_unix_nanos() {
+        let s_uuid = Uuid::new_v4();
+        // Sub-second precision must survive the round trip exactly.
+        let issued_at = OffsetDateTime::UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789);
+        let expiry = issued_at + Duration::new(300, 987_654_321);
+
+        let session = Session {
+            label: "hacks".to_string(),
+            state: SessionState::ExpiresAt(expiry),
+            issued_at,
+            issued_by: IdentityId::User(Uuid::new_v4()),
+            cred_id: Uuid::new_v4(),
+            scope: SessionScope::ReadWrite,
+            type_: AuthType::Passkey,
+            ext_metadata: Default::default(),
+            bound_to: None,
+            last_active: None,
+            pinned: false,
+            service_account: false,
+            disabled: None,
+        };
+
+        let dbv = session_to_dbv(s_uuid, &session);
+        match &dbv {
+            DbValueSession::V5 {
+                state: DbValueSessionStateV2::ExpiresAt(DbValueTimestampV1::UnixNanos(e_nanos)),
+                issued_at: DbValueTimestampV1::UnixNanos(i_nanos),
+                ..
+            } => {
+                assert_eq!(i128::from(*i_nanos), issued_at.unix_timestamp_nanos());
+                assert_eq!(i128::from(*e_nanos), expiry.unix_timestamp_nanos());
+            }
+            _ => panic!("Session was not stored in the integer form"),
+        }
+
+        let (_, session2) = dbv_to_session(&dbv).expect("Failed to parse session");
+        assert_eq!(session2.issued_at, issued_at);
+        assert_eq!(session2.state, SessionState::ExpiresAt(expiry));
+        assert_eq!(session, session2);
+
+        // The string form is still read.
+        let dbv = DbValueSession::V5 {
+            refer: s_uuid,
+            label: "hacks".to_string(),
+            state: DbValueSessionStateV2::ExpiresAt(DbValueTimestampV1::Rfc3339(
+                "2023-11-14T22:18:20.123456789Z".to_string(),
+            )),
+            issued_at: DbValueTimestampV1::Rfc3339("2023-11-14T22:13:20.123456789Z".to_string()),
+            issued_by: DbValueIdentityId::V1Uuid(Uuid::new_v4()),
+            cred_id: Uuid::new_v4(),
+            scope: DbValueAccessScopeV1::ReadWrite,
+            type_: DbValueAuthTypeV1::Passkey,
+            ext_metadata: Default::default(),
+            bound_to: None,
+            last_active: None,
+            pinned: false,
+            service_account: false,
+            disabled: None,
+        };
+        let (_, session3) = dbv_to_session(&dbv).expect("Failed to parse session");
+        assert_eq!(session3.issued_at, issued_at);
+        assert_eq!(
+            session3.state,
+            SessionState::ExpiresAt(issued_at + Duration::from_secs(300))
+        );
+
+        // Timestamps beyond the range of the integer form fall back to strings.
+        let far_future = time::macros::datetime!(2300-01-01 00:00 UTC);
+        let session = Session {
+            state: SessionState::ExpiresAt(far_future),
+            ..session
+        };
+        let dbv = session_to_dbv(s_uuid, &session);
+        assert!(matches!(
+            dbv,
+            DbValueSession::V5 {
+                state: DbValueSessionStateV2::ExpiresAt(DbValueTimestampV1::Rfc3339(_)),
+                issued_at: DbValueTimestampV1::UnixNanos(_),
+                ..
+            }
+        ));
+        let (_, session4) = dbv_to_session(&dbv).expect("Failed to parse session");
+        assert_eq!(session, session4);
+
+        // Pinned and disabled sessions keep those flags alongside the string form.
+        let session = Session {
+            pinned: true,
+            service_account: false,
+            disabled: Some(SessionDisabled::DisabledAt(Cid::new_count(1))),
+            ..session
+        };
+        let dbv = session_to_dbv(s_uuid, &session);
+        assert!(matches!(dbv, DbValueSession::V5 { pinned: true, .. }));
+        let (_, session5) = dbv_to_session(&dbv).expect("Failed to parse session");
+        assert!(session5.pinned);
+        assert!(session5.is_disabled());
+        assert_eq!(session, session5);
+    }
+
+    #[test]
+    fn test_valueset_session_from_dbvs2_lenient() {
+        let s_uuid = Uuid::new_v4();
+        let session = Session {
+            label: "hacks".to_string(),
+            state: SessionState::NeverExpires,
+            issued_at: OffsetDateTime::UNIX_EPOCH,
+            issued_by: IdentityId::Internal(UUID_SYSTEM),
+            cred_id: Uuid::new_v4(),
+            scope: SessionScope::ReadWrite,
+            type_: AuthType::Passkey,
+            ext_metadata: Default::default(),
+            bound_to: None,
+            last_active: None,
+            pinned: false,
+            service_account: false,
+            disabled: None,
+        };
+
+        let corrupt = |issued_at: &str, state: DbValueSessionStateV2| DbValueSession::V5 {
+            refer: Uuid::new_v4(),
+            label: "corrupt".to_string(),
+            state,
+            issued_at: DbValueTimestampV1::Rfc3339(issued_at.to_string()),
+            issued_by: DbValueIdentityId::V1Internal,
+            cred_id: Uuid::new_v4(),
+            scope: DbValueAccessScopeV1::ReadWrite,
+            type_: DbValueAuthTypeV1::Passkey,
+            ext_metadata: Default::default(),
+            bound_to: None,
+            last_active: None,
+            pinned: false,
+            service_account: false,
+            disabled: None,
+        };
+
+        let data = vec![
+            corrupt("not a timestamp", DbValueSessionStateV2::Never),
+            session_to_dbv(s_uuid, &session),
+            corrupt(
+                "2023-11-14T22:13:20Z",
+                DbValueSessionStateV2::ExpiresAt(DbValueTimestampV1::Rfc3339(
+                    "99999-99-99T99:99:99Z".to_string(),
+                )),
+            ),
+            corrupt("", DbValueSessionStateV2::Never),
+        ];
+
+        let (vs, dropped) = ValueSetSession::from_dbvs2_lenient(&data);
+        assert_eq!(dropped, 3);
+        assert_eq!(vs.len(), 1);
+        assert_eq!(
+            vs.as_session_map().and_then(|m| m.get(&s_uuid)),
+            Some(&session)
+        );
+
+        // Nothing is dropped when all records are valid.
+        let (vs, dropped) =
+            ValueSetSession::from_dbvs2_lenient(&[session_to_dbv(s_uuid, &session)]);
+        assert_eq!(dropped, 0);
+        assert_eq!(vs.len(), 1);
+    }
+
+    #[test]
+    fn test_valueset_session_partition_by_state() {
+        let now = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(6000);
+
+        let session = |state: SessionState| Session {
+            label: "label".to_string(),
+            state,
+            ..test_session()
+        };
+
+        let never_uuid = Uuid::new_v4();
+        let future_uuid = Uuid::new_v4();
+        let expired_uuid = Uuid::new_v4();
+        let revoked_uuid = Uuid::new_v4();
+
+        let vs = ValueSetSession::from_iter([
+            (never_uuid, session(SessionState::NeverExpires)),
+            (
+                future_uuid,
+                session(SessionState::ExpiresAt(now + Duration::from_secs(1))),
+            ),
+            // A session that expires at exactly now is no longer active.
+            (expired_uuid, session(SessionState::ExpiresAt(now))),
+            (
+                revoked_uuid,
+                session(SessionState::RevokedAt(Cid::new_count(1))),
+            ),
+        ])
+        .expect("Failed to create valueset");
+
+        let (active, inactive) = vs.partition_by_state(now);
+
+        let mut active: Vec<_> = active.into_iter().map(|(u, _)| u).collect();
+        let mut inactive: Vec<_> = inactive.into_iter().map(|(u, _)| u).collect();
+        active.sort_unstable();
+        inactive.sort_unstable();
+
+        let mut expect_active = vec![never_uuid, future_uuid];
+        let mut expect_inactive = vec![expired_uuid, revoked_uuid];
+        expect_active.sort_unstable();
+        expect_inactive.sort_unstable();
+
+        assert_eq!(active, expect_active);
+        assert_eq!(inactive, expect_inactive);
+    }
+
+    #[test]
+    fn test_valueset_oauth2_session_coalesce_lineage() {
+        let parent = Uuid::new_v4();
+        let rs_uuid = Uuid::new_v4();
+        let lineage = |issued_secs: u64| Oauth2Session {
+            issued_at: OffsetDateTime::UNIX_EPOCH + Duration::from_secs(issued_secs),
+            parent: Some(parent),
+            rs_uuid,
+            has_refresh: true,
+            ..test_oauth2_session()
+        };
+
+        let oldest_uuid = Uuid::new_v4();
+        let older_uuid = Uuid::new_v4();
+        let newest_uuid = Uuid::new_v4();
+        // A session of another resource server is a separate lineage.
+        let other_rs_uuid = Uuid::new_v4();
+
+        let mut vs = ValueSetOauth2Session::from_iter([
+            (oldest_uuid, lineage(60)),
+            (older_uuid, lineage(120)),
+            (newest_uuid, lineage(180)),
+            (
+                other_rs_uuid,
+                Oauth2Session {
+                    rs_uuid: Uuid::new_v4(),
+                    ..lineage(0)
+                },
+            ),
+        ])
+        .expect("Failed to build valueset");
+
+        let cid = Cid::new_count(1);
+        assert_eq!(vs.coalesce_lineage(&cid), 2);
+
+        let is_revoked = |vs: &ValueSetOauth2Session, u: &Uuid| {
+            matches!(
+                vs.as_oauth2session_map().and_then(|m| m.get(u)).map(|s| &s.state),
+                Some(SessionState::RevokedAt(c)) if *c == cid
+            )
+        };
+        assert!(is_revoked(&vs, &oldest_uuid));
+        assert!(is_revoked(&vs, &older_uuid));
+        assert!(!is_revoked(&vs, &newest_uuid));
+        assert!(!is_revoked(&vs, &other_rs_uuid));
+        assert_eq!(vs.count_by_rs(rs_uuid, OffsetDateTime::UNIX_EPOCH), 1);
+
+        // Coalescing again revokes nothing further.
+        assert_eq!(vs.coalesce_lineage(&Cid::new_count(2)), 0);
+    }
+
+    #[test]
+    fn test_valueset_oauth2_session_validate_nil() {
+        let session = Oauth2Session {
+            state: SessionState::NeverExpires,
+            issued_at: OffsetDateTime::UNIX_EPOCH,
+            parent: None,
+            rs_uuid: Uuid::new_v4(),
+            granted_scopes: Default::default(),
+            has_refresh: false,
+            last_refreshed: None,
+            origin: None,
+        };
+
+        let schema = SchemaAttribute::default();
+
+        let vs: ValueSet = ValueSetOauth2Session::new(Uuid::new_v4(), session.clone());
+        assert!(vs.validate(&schema));
+
+        let vs: ValueSet = ValueSetOauth2Session::new(
+            Uuid::new_v4(),
+            Oauth2Session {
+                rs_uuid: Uuid::nil(),
+                ..session.clone()
+            },
+        );
+        assert!(!vs.validate(&schema));
+
+        let vs: ValueSet = ValueSetOauth2Session::new(Uuid::nil(), session);
+        assert!(!vs.validate(&schema));
+    }
+
+    #[test]
+    fn test_valueset_session_by_recency_page() {
+        // Issue times repeat so that ordering of ties is exercised across page boundaries.
+        let vs = ValueSetSession::from_iter((0..500u64).map(|i| {
+            let state = if i % 5 == 0 {
+                SessionState::RevokedAt(Cid::new_count(i))
+            } else {
+                SessionState::NeverExpires
+            };
+            (
+                Uuid::new_v4(),
+                Session::builder(
+                    OffsetDateTime::UNIX_EPOCH + Duration::from_secs(i / 3),
+                    AuthType::Passkey,
+                )
+                .state(state)
+                .build(),
+            )
+        }))
+        .expect("Failed to build valueset");
+
+        let all: Vec<Uuid> = vs
+            .sessions_by_recency_page(false, None, 0, None)
+            .into_iter()
+            .map(|(u, _)| u)
+            .collect();
+        assert_eq!(all.len(), 500);
+
+        let expected: Vec<Uuid> = vs
+            .sessions_by_recency()
+            .into_iter()
+            .map(|(u, _)| u)
+            .collect();
+        assert_eq!(all, expected);
+
+        // Paging yields the same order, with no session repeated or skipped.
+        let paged: Vec<Uuid> = (0..500)
+            .step_by(64)
+            .flat_map(|offset| vs.sessions_by_recency_page(false, None, offset, Some(64)))
+            .map(|(u, _)| u)
+            .collect();
+        assert_eq!(paged, all);
+
+        // Revoked sessions are excluded before paging.
+        let active: Vec<Uuid> = vs
+            .sessions_by_recency_page(true, None, 0, None)
+            .into_iter()
+            .map(|(u, _)| u)
+            .collect();
+        assert_eq!(active.len(), 400);
+
+        let active_paged: Vec<_> = (0..400)
+            .step_by(64)
+            .flat_map(|offset| vs.sessions_by_recency_page(true, None, offset, Some(64)))
+            .collect();
+        assert!(active_paged
+            .iter()
+            .all(|(_, s)| !matches!(s.state, SessionState::RevokedAt(_))));
+        assert_eq!(
+            active_paged.into_iter().map(|(u, _)| u).collect::<Vec<_>>(),
+            active
+        );
+
+        // Past the end is empty.
+        assert!(vs
+            .sessions_by_recency_page(false, None, 500, Some(10))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_valueset_session_by_recency_page_auth_type() {
+        let vs = ValueSetSession::from_iter((0..30u64).map(|i| {
+            let type_ = if i % 3 == 0 {
+                AuthType::Password
+            } else {
+                AuthType::Passkey
+            };
+            (
+                Uuid::new_v4(),
+                Session::builder(OffsetDateTime::UNIX_EPOCH + Duration::from_secs(i), type_)
+                    .build(),
+            )
+        }))
+        .expect("Failed to build valueset");
+
+        let password = vs.sessions_by_recency_page(false, Some(AuthType::Password), 0, None);
+        assert_eq!(password.len(), 10);
+        assert!(password.iter().all(|(_, s)| s.type_ == AuthType::Password));
+
+        // The filter is applied before paging.
+        let page = vs.sessions_by_recency_page(false, Some(AuthType::Passkey), 15, Some(10));
+        assert_eq!(page.len(), 5);
+        assert!(page.iter().all(|(_, s)| s.type_ == AuthType::Passkey));
+
+        assert!(vs
+            .sessions_by_recency_page(false, Some(AuthType::AttestedPasskey), 0, None)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_valueset_session_estimated_db_size() {
+        let far_future = OffsetDateTime::UNIX_EPOCH + time::Duration::days(365 * 5000);
+
+        let vs = ValueSetSession::from_iter([
+            (
+                Uuid::new_v4(),
+                Session::builder(OffsetDateTime::UNIX_EPOCH, AuthType::Passkey).build(),
+            ),
+            (
+                Uuid::new_v4(),
+                Session::builder(OffsetDateTime::UNIX_EPOCH, AuthType::Passkey)
+                    .label("a \"quoted\" label with \\ and \u{7}")
+                    .state(SessionState::RevokedAt(Cid::new_count(u64::MAX / 2)))
+                    .issued_by(IdentityId::User(Uuid::new_v4()))
+                    .bound_to(Some("device-fingerprint".to_string()))
+                    .build(),
+            ),
+            (
+                Uuid::new_v4(),
+                // Out of range of unix nanoseconds, so stored with RFC3339 strings.
+                Session::builder(far_future, AuthType::Passkey)
+                    .label("far future")
+                    .state(SessionState::ExpiresAt(far_future))
+                    .ext_metadata(SessionExtMetadata::OAuth2 {
+                        access_expires_at: Duration::from_secs(u64::MAX / 2),
+                        access_token: "access".repeat(32),
+                        refresh_token: Some("refresh".repeat(32)),
+                    })
+                    .build(),
+            ),
+        ])
+        .expect("Failed to build valueset");
+
+        let serialized = serde_json::to_vec(&vs.to_db_valueset_v2())
+            .expect("Failed to serialize valueset")
+            .len();
+        let estimate = vs.estimated_db_size();
+
+        assert!(
+            estimate >= serialized,
+            "estimate {estimate} is less than serialized {serialized}"
+        );
+
+        // An empty set still has the cost of the set itself.
+        let empty = ValueSetSession::from_iter(std::iter::empty()).expect("Failed to build");
+        assert!(
+            empty.estimated_db_size()
+                >= serde_json::to_vec(&empty.to_db_valueset_v2())
+                    .expect("Failed to serialize valueset")
+                    .len()
+        );
+    }
+
+    #[test]
+    fn test_valueset_session_insert_lifetime_ceiling() {
+        let issued_at = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(6000);
+        let ceiling =
+            issued_at + Duration::from_secs(MAXIMUM_AUTH_SESSION_EXPIRY_SINGLE_FACTOR as u64);
+        let within = issued_at + Duration::from_secs(60);
+
+        let session = |type_, state| Session::builder(issued_at, type_).state(state).build();
+
+        // The first session of a set is clamped too.
+        let first_id = Uuid::new_v4();
+        let mut vs = ValueSetSession::new(
+            first_id,
+            session(AuthType::GeneratedPassword, SessionState::NeverExpires),
+        );
+
+        let never_id = Uuid::new_v4();
+        let beyond_id = Uuid::new_v4();
+        let within_id = Uuid::new_v4();
+        let passkey_id = Uuid::new_v4();
+
+        for (u, m) in [
+            (
+                never_id,
+                session(AuthType::Password, SessionState::NeverExpires),
+            ),
+            (
+                beyond_id,
+                session(
+                    AuthType::Password,
+                    SessionState::ExpiresAt(ceiling + Duration::from_secs(1)),
+                ),
+            ),
+            (
+                within_id,
+                session(AuthType::Password, SessionState::ExpiresAt(within)),
+            ),
+            (
+                passkey_id,
+                session(AuthType::Passkey, SessionState::NeverExpires),
+            ),
+        ] {
+            assert_eq!(vs.insert_checked(Value::Session(u, m)), Ok(true));
+        }
+
+        let state = |u: &Uuid| vs.map.get(u).map(|s| s.state.clone());
+
+        assert_eq!(state(&first_id), Some(SessionState::ExpiresAt(ceiling)));
+        assert_eq!(state(&never_id), Some(SessionState::ExpiresAt(ceiling)));
+        assert_eq!(state(&beyond_id), Some(SessionState::ExpiresAt(ceiling)));
+        assert_eq!(state(&within_id), Some(SessionState::ExpiresAt(within)));
+        assert_eq!(state(&passkey_id), Some(SessionState::NeverExpires));
+    }
+
+    #[test]
+    fn test_valueset_session_scim_effective_expiry() {
+        let issued_at = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(6000);
+        let ceiling =
+            issued_at + Duration::from_secs(MAXIMUM_AUTH_SESSION_EXPIRY_SINGLE_FACTOR as u64);
+
+        // Sessions loaded from before the ceiling applied are not clamped, but report the
+        // expiry that applies to them.
+        let session_id = Uuid::new_v4();
+        let vs = ValueSetSession::from_iter([(
+            session_id,
+            Session::builder(issued_at, AuthType::Password)
+                .state(SessionState::NeverExpires)
+                .build(),
+        )])
+        .expect("Unable to build valueset session");
+
+        let Some(ScimResolveStatus::Resolved(ScimValueKanidm::AuthSession(sessions))) =
+            vs.to_scim_value()
+        else {
+            panic!("Unexpected scim value");
+        };
+
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id, session_id);
+        assert_eq!(sessions[0].expires, Some(ceiling));
+    }
+
+    #[test]
+    fn test_valueset_session_insert_scope_policy() {
+        let mut vs = ValueSetSession::new(
+            Uuid::new_v4(),
+            Session::builder(OffsetDateTime::UNIX_EPOCH, AuthType::Passkey).build(),
+        );
+
+        for type_ in [
+            AuthType::Anonymous,
+            AuthType::GeneratedPassword,
+            AuthType::OAuth2Trust,
+            AuthType::ServiceAccount,
+            AuthType::LdapPassword,
+            AuthType::TokenExchange,
+        ] {
+            let session = Session::builder(OffsetDateTime::UNIX_EPOCH, type_)
+                .scope(SessionScope::PrivilegeCapable)
+                .build();
+            assert_eq!(
+                vs.insert_checked(Value::Session(Uuid::new_v4(), session)),
+                Err(OperationError::InvalidValueState),
+                "{type_} must not be privilege capable"
+            );
+        }
+        assert_eq!(vs.len(), 1);
+
+        // Permitted pairings are still inserted.
+        for (type_, scope) in [
+            (AuthType::Passkey, SessionScope::PrivilegeCapable),
+            (AuthType::GeneratedPassword, SessionScope::ReadWrite),
+            (AuthType::Anonymous, SessionScope::ReadOnly),
+        ] {
+            let session = Session::builder(OffsetDateTime::UNIX_EPOCH, type_)
+                .scope(scope)
+                .build();
+            assert_eq!(
+                vs.insert_checked(Value::Session(Uuid::new_v4(), session)),
+                Ok(true)
+            );
+        }
+        assert_eq!(vs.len(), 4);
+    }
+
+    #[test]
+    fn test_valueset_session_auth_type_round_trip() {
+        for (type_, db_tag, scim_str) in [
+            (AuthType::LdapPassword, "lp", "ldap_password"),
+            (AuthType::TokenExchange, "tx", "token_exchange"),
+            // Existing variants must keep their stored and displayed forms.
+            (AuthType::OAuth2Trust, "ot", "oauth2_trust"),
+            (AuthType::ServiceAccount, "sa", "service_account"),
+        ] {
+            let s_uuid = Uuid::new_v4();
+            let vs: ValueSet = ValueSetSession::new(
+                s_uuid,
+                Session::builder(OffsetDateTime::UNIX_EPOCH, type_)
+                    .scope(SessionScope::ReadOnly)
+                    .build(),
+            );
+
+            let DbValueSetV2::Session(dbvs) = vs.to_db_valueset_v2() else {
+                panic!("Unexpected db valueset");
+            };
+            let Some(DbValueSession::V5 { type_: db_type, .. }) = dbvs.first() else {
+                panic!("Unexpected db session");
+            };
+            assert_eq!(
+                serde_json::to_string(db_type).expect("Failed to serialise auth type"),
+                format!("\"{db_tag}\"")
+            );
+
+            let vs_reload = ValueSetSession::from_dbvs2(&dbvs).expect("Failed to reload");
+            assert!(vs.equal(&vs_reload));
+
+            let Some(ScimResolveStatus::Resolved(ScimValueKanidm::AuthSession(sessions))) =
+                vs_reload.to_scim_value()
+            else {
+                panic!("Unexpected scim value");
+            };
+            assert_eq!(sessions[0].auth_type, scim_str);
+            assert_eq!(AuthType::try_from(scim_str), Ok(type_));
+        }
+    }
+
+    #[test]
+    fn test_valueset_session_scope_round_trip() {
+        for (scope, db_tag, scim_str) in [
+            (SessionScope::ApiReadWrite, "a", "api_read_write"),
+            // Existing scopes must keep their stored and displayed forms.
+            (SessionScope::ReadOnly, "r", "read_only"),
+            (SessionScope::ReadWrite, "w", "read_write"),
+            (SessionScope::PrivilegeCapable, "p", "privilege_capable"),
+            (SessionScope::Synchronise, "s", "synchronise"),
+        ] {
+            let vs: ValueSet = ValueSetSession::new(
+                Uuid::new_v4(),
+                Session::builder(OffsetDateTime::UNIX_EPOCH, AuthType::Passkey)
+                    .scope(scope)
+                    .build(),
+            );
+
+            let DbValueSetV2::Session(dbvs) = vs.to_db_valueset_v2() else {
+                panic!("Unexpected db valueset");
+            };
+            let Some(DbValueSession::V5 {
+                scope: db_scope, ..
+            }) = dbvs.first()
+            else {
+                panic!("Unexpected db session");
+            };
+            assert_eq!(
+                serde_json::to_string(db_scope).expect("Failed to serialise scope"),
+                format!("\"{db_tag}\"")
+            );
+
+            let vs_reload = ValueSetSession::from_dbvs2(&dbvs).expect("Failed to reload");
+            assert!(vs.equal(&vs_reload));
+
+            let Some(ScimResolveStatus::Resolved(ScimValueKanidm::AuthSession(sessions))) =
+                vs_reload.to_scim_value()
+            else {
+                panic!("Unexpected scim value");
+            };
+            assert_eq!(sessions[0].session_scope, scim_str);
+        }
+    }
+
+    #[test]
+    fn test_valueset_session_content_hash() {
+        let s_uuid = Uuid::new_v4();
+        let session = Session::builder(OffsetDateTime::UNIX_EPOCH, AuthType::Passkey)
+            .cred_id(Uuid::new_v4())
+            .build();
+        let other = (
+            Uuid::new_v4(),
+            Session::builder(OffsetDateTime::UNIX_EPOCH, AuthType::Passkey).build(),
+        );
+
+        let vs = ValueSetSession::from_iter([(s_uuid, session.clone()), other.clone()])
+            .expect("Failed to build valueset");
+        let hash = vs.content_hash();
+
+        // Clones and sets built in another order hash the same.
+        assert_eq!(vs.clone().content_hash(), hash);
+        let vs_rev = ValueSetSession::from_iter([other.clone(), (s_uuid, session.clone())])
+            .expect("Failed to build valueset");
+        assert_eq!(vs_rev.content_hash(), hash);
+
+        // The hash is stable across runs.
+        let fixed = ValueSetSession::from_iter([(
+            uuid::uuid!("00000000-0000-0000-0000-000000000001"),
+            Session::builder(OffsetDateTime::UNIX_EPOCH, AuthType::Passkey).build(),
+        )])
+        .expect("Failed to build valueset");
+        assert_eq!(fixed.content_hash(), 0x062b_9b48_d022_0529);
+
+        // Changing any hashed part of a session changes the hash.
+        let mutations = [
+            Session {
+                state: SessionState::ExpiresAt(OffsetDateTime::UNIX_EPOCH),
+                ..session.clone()
+            },
+            Session {
+                state: SessionState::RevokedAt(Cid::new_zero()),
+                ..session.clone()
+            },
+            Session {
+                issued_at: OffsetDateTime::UNIX_EPOCH + Duration::from_secs(1),
+                ..session.clone()
+            },
+            Session {
+                cred_id: Uuid::new_v4(),
+                ..session.clone()
+            },
+        ];
+        for mutated in mutations {
+            let vs_mut = ValueSetSession::from_iter([(s_uuid, mutated), other.clone()])
+                .expect("Failed to build valueset");
+            assert_ne!(vs_mut.content_hash(), hash);
+        }
+
+        // As does adding or removing a session.
+        let vs_less =
+            ValueSetSession::from_iter([other.clone()]).expect("Failed to build valueset");
+        assert_ne!(vs_less.content_hash(), hash);
+        let vs_id = ValueSetSession::from_iter([(Uuid::new_v4(), session), other])
+            .expect("Failed to build valueset");
+        assert_ne!(vs_id.content_hash(), hash);
+    }
+
+    #[test]
+    fn test_valueset_session_by_issuer() {
+        let user_a = IdentityId::User(Uuid::new_v4());
+        let user_b = IdentityId::User(Uuid::new_v4());
+        let internal = IdentityId::Internal(UUID_SYSTEM);
+
+        let session = |issued_by: &IdentityId, state: SessionState| {
+            (
+                Uuid::new_v4(),
+                Session::builder(OffsetDateTime::UNIX_EPOCH, AuthType::Passkey)
+                    .issued_by(issued_by.clone())
+                    .state(state)
+                    .build(),
+            )
+        };
+
+        let sessions = [
+            session(&user_a, SessionState::NeverExpires),
+            session(&user_a, SessionState::NeverExpires),
+            session(&user_a, SessionState::RevokedAt(Cid::new_zero())),
+            session(&user_b, SessionState::NeverExpires),
+            session(&internal, SessionState::NeverExpires),
+            session(&internal, SessionState::RevokedAt(Cid::new_zero())),
+        ];
+
+        let ids = |range: std::ops::Range<usize>| {
+            let mut ids: Vec<_> = sessions[range].iter().map(|(u, _)| *u).collect();
+            ids.sort();
+            ids
+        };
+
+        let expect = BTreeMap::from([
+            (user_a.clone(), ids(0..2)),
+            (user_b.clone(), ids(3..4)),
+            (internal.clone(), ids(4..5)),
+        ]);
+
+        let vs = ValueSetSession::from_iter(sessions.iter().cloned())
+            .expect("Unable to build valueset session");
+
+        assert_eq!(vs.sessions_by_issuer(), expect);
+
+        // An issuer with only revoked sessions is absent.
+        let vs = ValueSetSession::from_iter([session(
+            &user_a,
+            SessionState::RevokedAt(Cid::new_zero()),
+        )])
+        .expect("Unable to build valueset session");
+        assert!(vs.sessions_by_issuer().is_empty());
+    }
+
+    #[test]
+    fn test_valueset_session_last_active() {
+        let s_uuid = Uuid::new_v4();
+        let issued_at = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let t1 = issued_at + Duration::from_secs(60);
+        let t2 = issued_at + Duration::from_secs(120);
+
+        let session = Session::builder(issued_at, AuthType::Passkey).build();
+        let mut vs = ValueSetSession::new(s_uuid, session.clone());
+
+        // Activity only moves forward.
+        assert!(vs.record_activity(&s_uuid, t2));
+        assert!(!vs.record_activity(&s_uuid, t1));
+        assert!(!vs.record_activity(&Uuid::new_v4(), t2));
+
+        // Inserting an existing session only updates its activity.
+        let touched = Session::builder(issued_at, AuthType::Passkey)
+            .scope(SessionScope::ReadWrite)
+            .last_active(Some(t2 + Duration::from_secs(1)))
+            .build();
+        assert_eq!(vs.insert_checked(Value::Session(s_uuid, touched)), Ok(true));
+        let stored = vs.as_session_map().and_then(|m| m.get(&s_uuid)).unwrap();
+        assert_eq!(stored.scope, SessionScope::ReadOnly);
+        assert_eq!(stored.last_active, Some(t2 + Duration::from_secs(1)));
+
+        // The activity survives storage.
+        let (_, session2) =
+            dbv_to_session(&session_to_dbv(s_uuid, stored)).expect("Failed to parse session");
+        assert_eq!(&session2, stored);
+        assert!(session2.is_idle_at(t2 + Duration::from_secs(301), Duration::from_secs(300)));
+        assert!(!session2.is_idle_at(t2 + Duration::from_secs(300), Duration::from_secs(300)));
+
+        // A revoked session records no further activity, but merging keeps the latest
+        // activity from either side.
+        let mut revoked = ValueSetSession::new(
+            s_uuid,
+            Session::builder(issued_at, AuthType::Passkey)
+                .state(SessionState::RevokedAt(Cid::new_count(1)))
+                .last_active(Some(t1))
+                .build(),
+        );
+        assert!(!revoked.record_activity(&s_uuid, t2));
+
+        let vs: ValueSet = vs;
+        revoked.merge(&vs).expect("Failed to merge");
+        let merged = revoked
+            .as_session_map()
+            .and_then(|m| m.get(&s_uuid))
+            .unwrap();
+        assert!(matches!(merged.state, SessionState::RevokedAt(_)));
+        assert_eq!(merged.last_active, Some(t2 + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_valueset_session_revoke_by_cred_ids() {
+        let (cred_a, cred_b, cred_c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let zero_cid = Cid::new_zero();
+        let one_cid = Cid::new_count(1);
+
+        let sessions: Vec<_> = [cred_a, cred_a, cred_b, cred_c, cred_c]
+            .into_iter()
+            .map(|cred_id| {
+                (
+                    Uuid::new_v4(),
+                    Session::builder(OffsetDateTime::UNIX_EPOCH, AuthType::Passkey)
+                        .cred_id(cred_id)
+                        .build(),
+                )
+            })
+            .collect();
+
+        let mut vs = ValueSetSession::from_iter(sessions.iter().cloned()).unwrap();
+
+        // A session of a compromised credential that was already revoked.
+        let revoked_id = Uuid::new_v4();
+        vs.push(
+            revoked_id,
+            Session::builder(OffsetDateTime::UNIX_EPOCH, AuthType::Passkey)
+                .cred_id(cred_a)
+                .state(SessionState::RevokedAt(zero_cid.clone()))
+                .build(),
+        );
+
+        let compromised = BTreeSet::from([cred_a, cred_c]);
+        assert_eq!(vs.revoke_by_cred_ids(&compromised, &one_cid), 4);
+
+        for (session_id, session) in sessions.iter() {
+            let state = vs.map.get(session_id).map(|s| &s.state);
+            if compromised.contains(&session.cred_id) {
+                assert_eq!(state, Some(&SessionState::RevokedAt(one_cid.clone())));
+            } else {
+                assert_eq!(state, Some(&SessionState::NeverExpires));
+            }
+        }
+
+        // Existing revocations are retained.
+        assert_eq!(
+            vs.map.get(&revoked_id).map(|s| &s.state),
+            Some(&SessionState::RevokedAt(zero_cid))
+        );
+
+        // Nothing remains to revoke.
+        assert_eq!(vs.revoke_by_cred_ids(&compromised, &one_cid), 0);
+        assert_eq!(vs.revoke_by_cred_ids(&BTreeSet::new(), &one_cid), 0);
+    }
+
+    #[test]
+    fn test_valueset_session_renew() {
+        let issued_at = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let max_lifetime = Duration::from_secs(3600);
+        let ceiling = issued_at + max_lifetime;
+        let expiry = issued_at + Duration::from_secs(600);
+
+        let s_uuid = Uuid::new_v4();
+        let mut vs = ValueSetSession::new(
+            s_uuid,
+            Session::builder(issued_at, AuthType::Passkey)
+                .expiry(Some(expiry))
+                .build(),
+        );
+        let state = |vs: &ValueSetSession| vs.map.get(&s_uuid).map(|s| s.state.clone());
+
+        // Extend within the bounds.
+        let renewed = expiry + Duration::from_secs(600);
+        assert_eq!(vs.renew(&s_uuid, renewed, max_lifetime), Ok(true));
+        assert_eq!(state(&vs), Some(SessionState::ExpiresAt(renewed)));
+
+        // An earlier expiry does not shorten the session.
+        assert_eq!(vs.renew(&s_uuid, expiry, max_lifetime), Ok(false));
+        assert_eq!(state(&vs), Some(SessionState::ExpiresAt(renewed)));
+
+        // Extend clamped to the ceiling, after which no further renewal is possible.
+        assert_eq!(
+            vs.renew(&s_uuid, ceiling + Duration::from_secs(600), max_lifetime),
+            Ok(true)
+        );
+        assert_eq!(state(&vs), Some(SessionState::ExpiresAt(ceiling)));
+        assert_eq!(
+            vs.renew(&s_uuid, ceiling + Duration::from_secs(1200), max_lifetime),
+            Ok(false)
+        );
+
+        // Unknown sessions can not be renewed.
+        assert_eq!(
+            vs.renew(&Uuid::new_v4(), renewed, max_lifetime),
+            Err(OperationError::NoMatchingEntries)
+        );
+
+        // Revoked and never expiring sessions are not renewed.
+        let revoked_uuid = Uuid::new_v4();
+        let never_uuid = Uuid::new_v4();
+        for (u, state) in [
+            (revoked_uuid, SessionState::RevokedAt(Cid::new_count(1))),
+            (never_uuid, SessionState::NeverExpires),
+        ] {
+            let session = Session::builder(issued_at, AuthType::Passkey)
+                .state(state.clone())
+                .build();
+            vs.push(u, session);
+            assert_eq!(vs.renew(&u, renewed, max_lifetime), Ok(false));
+            assert_eq!(vs.map.get(&u).map(|s| s.state.clone()), Some(state));
+        }
+    }
+
+    #[test]
+    fn test_valueset_session_repl_merge_incremental() {
+        use rand::{RngExt, SeedableRng};
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(330);
+        let base = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        // A small pool of ids so that the two sets overlap.
+        let ids: Vec<Uuid> = (0..24).map(|_| Uuid::new_v4()).collect();
+        let trim_cid = Cid::new_count(4);
+
+        let random_set = |rng: &mut rand::rngs::StdRng| {
+            let sessions: Vec<_> = ids
+                .iter()
+                .filter_map(|u| {
+                    if !rng.random_bool(0.6) {
+                        return None;
+                    }
+
+                    let state = match rng.random_range(0..3) {
+                        0 => SessionState::NeverExpires,
+                        1 => SessionState::ExpiresAt(
+                            base + Duration::from_secs(rng.random_range(0..4)),
+                        ),
+                        _ => SessionState::RevokedAt(Cid::new_count(rng.random_range(0..8))),
+                    };
+                    let last_active = rng
+                        .random_bool(0.5)
+                        .then(|| base + Duration::from_secs(rng.random_range(0..4)));
+                    let disabled = match rng.random_range(0..3) {
+                        0 => None,
+                        1 => Some(SessionDisabled::DisabledAt(Cid::new_count(
+                            rng.random_range(0..8),
+                        ))),
+                        _ => Some(SessionDisabled::EnabledAt(Cid::new_count(
+                            rng.random_range(0..8),
+                        ))),
+                    };
+                    let session = Session::builder(base, AuthType::Passkey)
+                        .label(format!("session {}", rng.random_range(0..2)))
+                        .state(state)
+                        .last_active(last_active)
+                        .disabled(disabled)
+                        .build();
+                    Some((*u, session))
+                })
+                .collect();
+            ValueSetSession::from_iter(sessions).expect("Unable to build valueset session")
+        };
+
+        for _ in 0..256 {
+            let newer = random_set(&mut rng);
+            let older: ValueSet = random_set(&mut rng);
+
+            let optimised = newer
+                .repl_merge_valueset(&older, &trim_cid)
+                .expect("failed to merge");
+
+            // The naive merge clones the whole set before merging.
+            let mut naive = newer.clone();
+            ValueSetSession::merge_map(&mut naive.map, older.as_session_map().unwrap(), None);
+            naive.trim(&trim_cid);
+
+            assert_eq!(
+                serde_json::to_vec(&optimised.to_db_valueset_v2()).unwrap(),
+                serde_json::to_vec(&naive.to_db_valueset_v2()).unwrap(),
+                "merge of {older:?} into {newer:?} differs"
+            );
+        }
+    }
+
+    #[test]
+    fn test_valueset_session_disabled_merge() {
+        let s_uuid = Uuid::new_v4();
+        let now = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(1000);
+        let trim_cid = Cid::new_zero();
+
+        let session = |state: SessionState, disabled: Option<SessionDisabled>| {
+            Session::builder(OffsetDateTime::UNIX_EPOCH, AuthType::Passkey)
+                .label("hacks")
+                .state(state)
+                .disabled(disabled)
+                .build()
+        };
+
+        let merged = |a: &Session, b: &Session| {
+            let mut vs_a: ValueSet = ValueSetSession::new(s_uuid, a.clone());
+            let vs_b: ValueSet = ValueSetSession::new(s_uuid, b.clone());
+            let repl = vs_a
+                .repl_merge_valueset(&vs_b, &trim_cid)
+                .expect("failed to merge");
+            vs_a.merge(&vs_b).expect("failed to merge");
+            // Replication must agree with a local merge.
+            assert!(vs_a.equal(&repl));
+            vs_a.as_session_map()
+                .and_then(|m| m.get(&s_uuid))
+                .cloned()
+                .expect("session missing")
+        };
+
+        let is_live = |s: &Session| ValueSetSession::new(s_uuid, s.clone()).is_live(&s_uuid, now);
+
+        let disabled_1 = Some(SessionDisabled::DisabledAt(Cid::new_count(1)));
+        let enabled_2 = Some(SessionDisabled::EnabledAt(Cid::new_count(2)));
+        let disabled_3 = Some(SessionDisabled::DisabledAt(Cid::new_count(3)));
+
+        let live = session(SessionState::NeverExpires, None);
+        let disabled = session(SessionState::NeverExpires, disabled_1.clone());
+        let enabled = session(SessionState::NeverExpires, enabled_2.clone());
+        let redisabled = session(SessionState::NeverExpires, disabled_3.clone());
+        let revoked = session(SessionState::RevokedAt(Cid::new_count(1)), None);
+        let revoked_enabled = session(
+            SessionState::RevokedAt(Cid::new_count(1)),
+            enabled_2.clone(),
+        );
+        let expiring_live = session(SessionState::ExpiresAt(now + Duration::from_secs(60)), None);
+
+        assert!(is_live(&live));
+        assert!(!is_live(&disabled));
+        assert!(is_live(&enabled));
+
+        // Disabled beats a live session that was never disabled.
+        assert_eq!(merged(&live, &disabled).disabled, disabled_1);
+        assert_eq!(merged(&disabled, &live).disabled, disabled_1);
+
+        // Enabling is a later change, so it beats the disable.
+        assert_eq!(merged(&disabled, &enabled).disabled, enabled_2);
+        assert_eq!(merged(&enabled, &disabled).disabled, enabled_2);
+
+        // And a later disable beats the enable.
+        assert_eq!(merged(&enabled, &redisabled).disabled, disabled_3);
+        assert_eq!(merged(&redisabled, &enabled).disabled, disabled_3);
+
+        // RevokedAt always wins, even over a later enable.
+        for (a, b) in [(&revoked, &enabled), (&disabled, &revoked_enabled)] {
+            let m = merged(a, b);
+            assert!(matches!(m.state, SessionState::RevokedAt(_)));
+            assert!(!is_live(&m));
+            let m = merged(b, a);
+            assert!(matches!(m.state, SessionState::RevokedAt(_)));
+            assert!(!is_live(&m));
+        }
+
+        // The dominant state brings the rest of the session, but not its disabled change.
+        let m = merged(&disabled, &expiring_live);
+        assert_eq!(m.state, expiring_live.state);
+        assert_eq!(m.disabled, disabled_1);
+        assert!(!is_live(&m));
+
+        // Every pair converges to the same result regardless of direction, and merging
+        // is idempotent.
+        let all = [
+            &live,
+            &disabled,
+            &enabled,
+            &redisabled,
+            &revoked,
+            &revoked_enabled,
+            &expiring_live,
+        ];
+        for a in all {
+            assert_eq!(&merged(a, a), a);
+            for b in all {
+                assert_eq!(merged(a, b), merged(b, a));
+            }
+        }
+    }
+
+    #[test]
+    fn test_valueset_session_disable_enable() {
+        let now = OffsetDateTime::UNIX_EPOCH + Duration::from_secs(1000);
+        let live_uuid = Uuid::new_v4();
+        let revoked_uuid = Uuid::new_v4();
+
+        let mut vs = ValueSetSession::from_iter([
+            (
+                live_uuid,
+                Session::builder(OffsetDateTime::UNIX_EPOCH, AuthType::Passkey).build(),
+            ),
+            (
+                revoked_uuid,
+                Session::builder(OffsetDateTime::UNIX_EPOCH, AuthType::Passkey)
+                    .state(SessionState::RevokedAt(Cid::new_count(1)))
+                    .build(),
+            ),
+        ])
+        .expect("Unable to build valueset session");
+
+        // Only the live session is disabled, and disabling again changes nothing.
+        assert_eq!(vs.disable_all(&Cid::new_count(2)), 1);
+        assert_eq!(vs.disable_all(&Cid::new_count(3)), 0);
+        assert!(!vs.is_live(&live_uuid, now));
+        assert!(!vs.is_active_at(&live_uuid, now, Duration::ZERO));
+        assert_eq!(
+            vs.map.get(&live_uuid).and_then(|s| s.disabled.clone()),
+            Some(SessionDisabled::DisabledAt(Cid::new_count(2)))
+        );
+        assert_eq!(
+            vs.map.get(&revoked_uuid).and_then(|s| s.disabled.clone()),
+            None
+        );
+
+        // The disabled session survives the db round trip, and is shown in scim.
+        let vs_2 = ValueSetSession::from_dbvs2(&vs.to_vec_dbvs()).expect("Failed to load");
+        assert!(vs.equal(&vs_2));
+
+        let Some(ScimResolveStatus::Resolved(ScimValueKanidm::AuthSession(scim_sessions))) =
+            vs.to_scim_value()
+        else {
+            panic!("Invalid scim value");
+        };
+        let scim_disabled: BTreeMap<_, _> =
+            scim_sessions.iter().map(|s| (s.id, s.disabled)).collect();
+        assert_eq!(
+            scim_disabled.get(&live_uuid),
+            Some(&Some(revoked_at_timestamp(&Cid::new_count(2))))
+        );
+        assert_eq!(scim_disabled.get(&revoked_uuid), Some(&None));
+
+        // Disabled sessions are force trimmed last.
+        let mut vs_trim = vs.clone();
+        assert_eq!(vs_trim.force_trim(1), 1);
+        assert!(vs_trim.map.contains_key(&live_uuid));
+
+        // Enabling the session makes it live again.
+        assert_eq!(vs.enable_all(&Cid::new_count(4)), 1);
+        assert_eq!(vs.enable_all(&Cid::new_count(5)), 0);
+        assert!(vs.is_live(&live_uuid, now));
+        assert!(vs.is_active_at(&live_uuid, now, Duration::ZERO));
+        assert_eq!(
+            vs.map.get(&live_uuid).and_then(|s| s.disabled.clone()),
+            Some(SessionDisabled::EnabledAt(Cid::new_count(4)))
+        );
+    }
+
+    #[test]
+    fn test_valueset_session_fractional_expiry_round_trip() {
+        // An expiry with a fractional second must survive the db round trip exactly,
+        // since short lived sessions can't afford to shift by up to a second.
+        let issued_at = OffsetDateTime::UNIX_EPOCH + Duration::new(1_700_000_000, 1);
+        let expiry = issued_at + Duration::new(30, 999_999_998);
+
+        let s_uuid = Uuid::new_v4();
+        let vs = ValueSetSession::new(
+            s_uuid,
+            Session::builder(issued_at, AuthType::Passkey)
+                .state(SessionState::ExpiresAt(expiry))
+                .build(),
+        );
+        let vs_2 = ValueSetSession::from_dbvs2(&vs.to_vec_dbvs()).expect("Failed to load");
+        assert_eq!(
+            vs_2.as_session_map()
+                .and_then(|m| m.get(&s_uuid))
+                .map(|s| s.state.clone()),
+            Some(SessionState::ExpiresAt(expiry))
+        );
+
+        let o_uuid = Uuid::new_v4();
+        let vs: ValueSet = ValueSetOauth2Session::new(
+            o_uuid,
+            Oauth2Session {
+                parent: None,
+                state: SessionState::ExpiresAt(expiry),
+                issued_at,
+                rs_uuid: Uuid::new_v4(),
+                granted_scopes: Default::default(),
+                has_refresh: false,
+                last_refreshed: None,
+                origin: None,
+            },
+        );
+        let DbValueSetV2::Oauth2Session(data) = vs.to_db_valueset_v2() else {
+            panic!("Invalid db valueset");
+        };
+        let vs_2 = ValueSetOauth2Session::from_dbvs2(data).expect("Failed to load");
+        assert_eq!(
+            vs_2.as_oauth2session_map()
+                .and_then(|m| m.get(&o_uuid))
+                .map(|s| s.state.clone()),
+            Some(SessionState::ExpiresAt(expiry))
+        );
+
+        let t_uuid = Uuid::new_v4();
+        let vs: ValueSet = ValueSetApiToken::new(
+            t_uuid,
+            ApiToken::builder(issued_at)
+                .state(SessionState::ExpiresAt(expiry))
+                .build(),
+        );
+        let DbValueSetV2::ApiToken(data) = vs.to_db_valueset_v2() else {
+            panic!("Invalid db valueset");
+        };
+        let vs_2 = ValueSetApiToken::from_dbvs2(data).expect("Failed to load");
+        assert_eq!(
+            vs_2.as_apitoken_map()
+                .and_then(|m| m.get(&t_uuid))
+                .map(|t| t.state.clone()),
+            Some(SessionState::ExpiresAt(expiry))
+        );
     }
 }
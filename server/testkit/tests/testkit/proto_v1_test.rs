@@ -5,11 +5,12 @@ use hyper::header::CONTENT_TYPE;
 use kanidm_client::{ClientError, KanidmClient};
 use kanidm_proto::constants::{ATTR_GIDNUMBER, KSESSIONID};
 use kanidm_proto::internal::{
-    ApiToken, CURegState, Filter, ImageValue, Modify, ModifyList, UatPurpose, UserAuthToken,
+    ApiToken, CURegState, Filter, ImageValue, Modify, ModifyList, OperationError, UatPurpose,
+    UserAuthToken,
 };
 use kanidm_proto::v1::{
     AuthCredential, AuthIssueSession, AuthMech, AuthRequest, AuthResponse, AuthState, AuthStep,
-    Entry,
+    Entry, UatStatusQuery, UatStatusState,
 };
 use kanidmd_core::config::ServerRole;
 use kanidmd_lib::constants::{NAME_IDM_ADMINS, NAME_SYSTEM_ADMINS};
@@ -1523,6 +1524,176 @@ async fn test_server_api_token_lifecycle(rsclient: &KanidmClient) {
     //     .is_ok());
 }
 
+#[kanidmd_testkit::test]
+async fn test_server_api_token_destroy_by_label(rsclient: &KanidmClient) {
+    let res = rsclient
+        .auth_simple_password(ADMIN_TEST_USER, ADMIN_TEST_PASSWORD)
+        .await;
+    assert!(res.is_ok());
+
+    let test_service_account_username = "test_service";
+
+    rsclient
+        .idm_service_account_create(
+            test_service_account_username,
+            "Test Service",
+            NAME_IDM_ADMINS,
+        )
+        .await
+        .expect("Failed to create service account");
+
+    for label in ["Deploy Token", "backup/token"] {
+        rsclient
+            .idm_service_account_generate_api_token(
+                test_service_account_username,
+                label,
+                None,
+                false,
+                false,
+            )
+            .await
+            .expect("Failed to create service account api token");
+    }
+
+    let tokens = rsclient
+        .idm_service_account_list_api_token(test_service_account_username)
+        .await
+        .expect("Failed to list service account api tokens");
+    assert_eq!(tokens.len(), 2);
+
+    // Labels are matched case insensitively, and may hold reserved url characters.
+    for label in ["deploy token", "backup/token"] {
+        rsclient
+            .idm_service_account_destroy_api_token_by_label(test_service_account_username, label)
+            .await
+            .expect("Failed to destroy service account api token by label");
+    }
+
+    let tokens = rsclient
+        .idm_service_account_list_api_token(test_service_account_username)
+        .await
+        .expect("Failed to list service account api tokens");
+    assert!(tokens.is_empty());
+
+    // The token is already destroyed, so the label no longer matches.
+    let res = rsclient
+        .idm_service_account_destroy_api_token_by_label(
+            test_service_account_username,
+            "deploy token",
+        )
+        .await;
+    assert!(matches!(
+        res,
+        Err(ClientError::Http(
+            _,
+            Some(OperationError::NoMatchingEntries),
+            _
+        ))
+    ));
+}
+
+#[kanidmd_testkit::test]
+async fn test_server_user_auth_token_query(rsclient: &KanidmClient) {
+    let res = rsclient
+        .auth_simple_password(ADMIN_TEST_USER, ADMIN_TEST_PASSWORD)
+        .await;
+    assert!(res.is_ok());
+
+    rsclient
+        .idm_person_account_create("demo_account", "Deeeeemo")
+        .await
+        .unwrap();
+    rsclient
+        .idm_person_account_primary_credential_set_password("demo_account", "eicieY7ahchaoCh0eeTa")
+        .await
+        .unwrap();
+
+    // Login as the account, which can read its own sessions.
+    let _ = rsclient.logout().await;
+    let res = rsclient
+        .auth_simple_password("demo_account", "eicieY7ahchaoCh0eeTa")
+        .await;
+    assert!(res.is_ok());
+
+    let password_query = UatStatusQuery {
+        auth_type: Some("password".to_string()),
+        ..Default::default()
+    };
+
+    let sessions = rsclient
+        .idm_account_query_user_auth_token("demo_account", password_query.clone())
+        .await
+        .expect("Failed to list user auth tokens");
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].auth_type, "password");
+    let first_session_id = sessions[0].session_id;
+
+    // Login again, leaving the first session in place.
+    let res = rsclient
+        .auth_simple_password("demo_account", "eicieY7ahchaoCh0eeTa")
+        .await;
+    assert!(res.is_ok());
+
+    let sessions = rsclient
+        .idm_account_query_user_auth_token("demo_account", password_query.clone())
+        .await
+        .expect("Failed to list user auth tokens");
+    assert_eq!(sessions.len(), 2);
+
+    // No sessions were created by other types of authentication.
+    let sessions = rsclient
+        .idm_account_query_user_auth_token(
+            "demo_account",
+            UatStatusQuery {
+                auth_type: Some("passkey".to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to list user auth tokens");
+    assert!(sessions.is_empty());
+
+    // An unknown type of authentication is rejected.
+    let res = rsclient
+        .idm_account_query_user_auth_token(
+            "demo_account",
+            UatStatusQuery {
+                auth_type: Some("carrier_pigeon".to_string()),
+                ..Default::default()
+            },
+        )
+        .await;
+    assert!(res.is_err());
+
+    rsclient
+        .idm_account_destroy_user_auth_token("demo_account", first_session_id)
+        .await
+        .expect("Failed to destroy user auth token");
+
+    // Once revoked the session is only listed when revoked sessions are included.
+    let sessions = rsclient
+        .idm_account_query_user_auth_token(
+            "demo_account",
+            UatStatusQuery {
+                exclude_revoked: true,
+                ..password_query.clone()
+            },
+        )
+        .await
+        .expect("Failed to list user auth tokens");
+    assert_eq!(sessions.len(), 1);
+    assert_ne!(sessions[0].session_id, first_session_id);
+
+    let sessions = rsclient
+        .idm_account_query_user_auth_token("demo_account", password_query)
+        .await
+        .expect("Failed to list user auth tokens");
+    assert_eq!(sessions.len(), 2);
+    assert!(sessions.iter().any(|session| {
+        session.session_id == first_session_id && matches!(session.state, UatStatusState::Revoked)
+    }));
+}
+
 #[kanidmd_testkit::test]
 async fn test_server_user_auth_token_lifecycle(rsclient: &KanidmClient) {
     let res = rsclient
@@ -1745,6 +1916,7 @@ async fn start_password_session(
             username: username.to_string(),
             issue: AuthIssueSession::Token,
             privileged,
+            api_only: false,
         },
     };
     let res = match client
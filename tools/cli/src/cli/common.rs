@@ -319,7 +319,7 @@ impl KanidmClientParser {
                         ..self.to_owned()
                     };
 
-                    let login_opt = LoginOpt {};
+                    let login_opt = LoginOpt { api_only: false };
 
                     // Okay, try again ...
                     login_opt.exec(copt).await;
@@ -1,9 +1,9 @@
 use crate::common::try_expire_at_from_string;
 use crate::OpType;
 use crate::{
-    handle_client_error, password_prompt, AccountCertificate, AccountCredential, AccountRadius,
-    AccountSsh, AccountUserAuthToken, AccountValidity, KanidmClientParser, OutputMode, PersonOpt,
-    PersonPosix,
+    handle_client_error, password_prompt, AccountCertificate, AccountCredential,
+    AccountOauth2Session, AccountRadius, AccountSsh, AccountUserAuthToken, AccountValidity,
+    KanidmClientParser, OutputMode, PersonOpt, PersonPosix,
 };
 use dialoguer::theme::ColorfulTheme;
 use dialoguer::{Confirm, Input, Password, Select};
@@ -22,6 +22,9 @@ use kanidm_proto::internal::{
 use kanidm_proto::internal::{CredentialDetail, CredentialDetailType};
 use kanidm_proto::messages::{AccountChangeMessage, ConsoleOutputMode, MessageStatus};
 use kanidm_proto::scim_v1::{client::ScimSshPublicKeys, ScimEntryGetQuery};
+use kanidm_proto::v1::{
+    Oauth2ClientSessions, Oauth2ClientSessionsQuery, UatStatus, UatStatusQuery, UatStatusState,
+};
 use qrcode::render::unicode;
 use qrcode::QrCode;
 use std::fmt::{self, Debug};
@@ -172,6 +175,46 @@ impl PersonOpt {
                         Err(e) => handle_client_error(e, opt.output_mode),
                     }
                 }
+                AccountUserAuthToken::Summary(apo) => {
+                    let client = opt.to_client(OpType::Read).await;
+                    match client
+                        .idm_account_session_summary(apo.aopts.account_id.as_str())
+                        .await
+                    {
+                        Ok(summary) => match opt.output_mode {
+                            OutputMode::Json => println!(
+                                "{}",
+                                serde_json::to_string(&summary).expect("Failed to serialise json")
+                            ),
+                            OutputMode::Text => print!("{summary}"),
+                        },
+                        Err(e) => handle_client_error(e, opt.output_mode),
+                    }
+                }
+                AccountUserAuthToken::List {
+                    aopts,
+                    all,
+                    auth_type,
+                } => {
+                    let client = opt.to_client(OpType::Read).await;
+                    let query = UatStatusQuery {
+                        exclude_revoked: !all,
+                        auth_type: auth_type.clone(),
+                        ..Default::default()
+                    };
+                    match client
+                        .idm_account_query_user_auth_token(aopts.account_id.as_str(), query)
+                        .await
+                    {
+                        Ok(tokens) => {
+                            #[allow(clippy::disallowed_methods)]
+                            // Allowed as this should represent the current time from the callers machine.
+                            let now = OffsetDateTime::now_utc();
+                            display_user_auth_tokens(&tokens, now, opt.output_mode);
+                        }
+                        Err(e) => handle_client_error(e, opt.output_mode),
+                    }
+                }
                 AccountUserAuthToken::Destroy { aopts, session_id } => {
                     let client = opt.to_client(OpType::Write).await;
                     match client
@@ -188,6 +231,47 @@ impl PersonOpt {
                     }
                 }
             }, // End PersonOpt::Session
+            PersonOpt::Oauth2Session { commands } => match commands {
+                AccountOauth2Session::List { aopts, all } => {
+                    let client = opt.to_client(OpType::Read).await;
+                    let query = Oauth2ClientSessionsQuery {
+                        include_revoked: *all,
+                    };
+                    match client
+                        .idm_account_list_oauth2_sessions(aopts.account_id.as_str(), query)
+                        .await
+                    {
+                        Ok(clients) => {
+                            #[allow(clippy::disallowed_methods)]
+                            // Allowed as this should represent the current time from the callers machine.
+                            let now = OffsetDateTime::now_utc();
+                            display_oauth2_client_sessions(&clients, now, opt.output_mode);
+                        }
+                        Err(e) => handle_client_error(e, opt.output_mode),
+                    }
+                }
+                AccountOauth2Session::Revoke {
+                    aopts,
+                    client: oauth2_client,
+                } => {
+                    let client = opt.to_client(OpType::Write).await;
+                    match client
+                        .idm_account_revoke_oauth2_client_sessions(
+                            aopts.account_id.as_str(),
+                            oauth2_client.as_str(),
+                        )
+                        .await
+                    {
+                        Ok(()) => {
+                            println!("Success");
+                        }
+                        Err(e) => {
+                            error!("Error revoking oauth2 sessions");
+                            handle_client_error(e, opt.output_mode);
+                        }
+                    }
+                }
+            }, // End PersonOpt::Oauth2Session
             PersonOpt::Ssh { commands } => match commands {
                 AccountSsh::List(aopt) => {
                     let client = opt.to_client(OpType::Read).await;
@@ -1778,3 +1862,141 @@ async fn credential_update_exec(
     }
     trace!("ended credential update exec");
 }
+
+/// Show the sessions of an account, one per row, as a table in text mode.
+pub(crate) fn display_user_auth_tokens(
+    tokens: &[UatStatus],
+    now: OffsetDateTime,
+    output_mode: OutputMode,
+) {
+    if let OutputMode::Json = output_mode {
+        println!(
+            "{}",
+            serde_json::to_string(tokens).expect("Failed to serialise json")
+        );
+        return;
+    }
+
+    if tokens.is_empty() {
+        println!("No sessions exist");
+        return;
+    }
+
+    let format_time = |odt: OffsetDateTime| {
+        odt.format(&Rfc3339)
+            .unwrap_or_else(|_| "invalid timestamp".to_string())
+    };
+
+    let header = [
+        "session id".to_string(),
+        "label".to_string(),
+        "auth type".to_string(),
+        "scope".to_string(),
+        "issued at".to_string(),
+        "status".to_string(),
+    ];
+
+    let rows: Vec<[String; 6]> = tokens
+        .iter()
+        .map(|token| {
+            let status = match token.state {
+                UatStatusState::ExpiresAt(exp) if exp <= now => {
+                    format!("expired {}", format_time(exp))
+                }
+                UatStatusState::ExpiresAt(exp) => format!("expires {}", format_time(exp)),
+                UatStatusState::NeverExpires => "never expires".to_string(),
+                UatStatusState::Revoked => "revoked".to_string(),
+            };
+            [
+                token.session_id.to_string(),
+                token.label.clone(),
+                token.auth_type.clone(),
+                token.purpose.to_string(),
+                format_time(token.issued_at),
+                status,
+            ]
+        })
+        .collect();
+
+    print_table(&header, &rows);
+}
+
+pub(crate) fn display_oauth2_client_sessions(
+    clients: &[Oauth2ClientSessions],
+    now: OffsetDateTime,
+    output_mode: OutputMode,
+) {
+    if let OutputMode::Json = output_mode {
+        println!(
+            "{}",
+            serde_json::to_string(clients).expect("Failed to serialise json")
+        );
+        return;
+    }
+
+    if clients.is_empty() {
+        println!("No oauth2 sessions exist");
+        return;
+    }
+
+    let format_time = |odt: OffsetDateTime| {
+        odt.format(&Rfc3339)
+            .unwrap_or_else(|_| "invalid timestamp".to_string())
+    };
+
+    let header = [
+        "client id".to_string(),
+        "name".to_string(),
+        "display name".to_string(),
+        "sessions".to_string(),
+        "newest issued".to_string(),
+        "status".to_string(),
+    ];
+
+    let rows: Vec<[String; 6]> = clients
+        .iter()
+        .map(|client| {
+            let status = match client.state {
+                UatStatusState::ExpiresAt(exp) if exp <= now => {
+                    format!("expired {}", format_time(exp))
+                }
+                UatStatusState::ExpiresAt(exp) => format!("expires {}", format_time(exp)),
+                UatStatusState::NeverExpires => "never expires".to_string(),
+                UatStatusState::Revoked => "revoked".to_string(),
+            };
+            [
+                client.client_id.to_string(),
+                client
+                    .client_name
+                    .clone()
+                    .unwrap_or_else(|| "(deleted)".to_string()),
+                client.client_displayname.clone().unwrap_or_default(),
+                client.sessions.to_string(),
+                format_time(client.newest_issued_at),
+                status,
+            ]
+        })
+        .collect();
+
+    print_table(&header, &rows);
+}
+
+/// Print rows as a table, with each column padded to its widest value.
+pub(crate) fn print_table<const N: usize>(header: &[String; N], rows: &[[String; N]]) {
+    let mut widths = header.each_ref().map(|column| column.len());
+    for row in rows.iter() {
+        for (width, column) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(column.len());
+        }
+    }
+
+    for row in std::iter::once(header).chain(rows.iter()) {
+        let line = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(column, &width)| format!("{column:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    }
+}
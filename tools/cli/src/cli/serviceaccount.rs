@@ -1,11 +1,17 @@
 use crate::common::try_expire_at_from_string;
 use crate::OpType;
+use kanidm_client::ClientError;
 use kanidm_proto::constants::{
     ATTR_ACCOUNT_EXPIRE, ATTR_ACCOUNT_VALID_FROM, ATTR_GIDNUMBER, ATTR_SSH_PUBLICKEY,
 };
+use kanidm_proto::internal::ApiToken;
+use kanidm_proto::internal::OperationError::AmbiguousLabel;
 use kanidm_proto::messages::{AccountChangeMessage, ConsoleOutputMode, MessageStatus};
+use kanidm_proto::v1::UatStatusQuery;
 use time::OffsetDateTime;
 
+use crate::person::{display_user_auth_tokens, print_table};
+
 use crate::{
     handle_client_error, AccountSsh, AccountUserAuthToken, AccountValidity, KanidmClientParser,
     OutputMode, ServiceAccountApiToken, ServiceAccountCredential, ServiceAccountOpt,
@@ -54,13 +60,7 @@ impl ServiceAccountOpt {
                         .await
                     {
                         Ok(tokens) => {
-                            if tokens.is_empty() {
-                                println!("No api tokens exist");
-                            } else {
-                                for token in tokens {
-                                    println!("token: {token}");
-                                }
-                            }
+                            display_api_tokens(&tokens, OffsetDateTime::now_utc(), opt.output_mode)
                         }
                         Err(e) => {
                             error!("Error listing service account api tokens -> {:?}", e);
@@ -132,15 +132,47 @@ impl ServiceAccountOpt {
                         }
                     }
                 }
-                ServiceAccountApiToken::Destroy { aopts, token_id } => {
+                ServiceAccountApiToken::Destroy {
+                    aopts,
+                    token_id,
+                    label,
+                } => {
                     let client = opt.to_client(OpType::Write).await;
-                    match client
-                        .idm_service_account_destroy_api_token(aopts.account_id.as_str(), *token_id)
-                        .await
-                    {
+                    let result = match (token_id, label) {
+                        (Some(token_id), _) => {
+                            client
+                                .idm_service_account_destroy_api_token(
+                                    aopts.account_id.as_str(),
+                                    *token_id,
+                                )
+                                .await
+                        }
+                        (None, Some(label)) => {
+                            client
+                                .idm_service_account_destroy_api_token_by_label(
+                                    aopts.account_id.as_str(),
+                                    label,
+                                )
+                                .await
+                        }
+                        (None, None) => {
+                            error!("A token id or label is required");
+                            return;
+                        }
+                    };
+
+                    match result {
                         Ok(()) => {
                             println!("Success");
                         }
+                        Err(ClientError::Http(_, Some(AmbiguousLabel(candidates)), _)) => {
+                            error!(
+                                "More than one api token holds this label, destroy one by its token id instead:"
+                            );
+                            for candidate in candidates {
+                                error!("  {candidate}");
+                            }
+                        }
                         Err(e) => {
                             error!("Error destroying service account token -> {:?}", e);
                         }
@@ -202,6 +234,46 @@ impl ServiceAccountOpt {
                         }
                     }
                 }
+                AccountUserAuthToken::Summary(apo) => {
+                    let client = opt.to_client(OpType::Read).await;
+                    match client
+                        .idm_account_session_summary(apo.aopts.account_id.as_str())
+                        .await
+                    {
+                        Ok(summary) => match opt.output_mode {
+                            OutputMode::Json => println!(
+                                "{}",
+                                serde_json::to_string(&summary).expect("Failed to serialise json")
+                            ),
+                            OutputMode::Text => print!("{summary}"),
+                        },
+                        Err(e) => handle_client_error(e, opt.output_mode),
+                    }
+                }
+                AccountUserAuthToken::List {
+                    aopts,
+                    all,
+                    auth_type,
+                } => {
+                    let client = opt.to_client(OpType::Read).await;
+                    let query = UatStatusQuery {
+                        exclude_revoked: !all,
+                        auth_type: auth_type.clone(),
+                        ..Default::default()
+                    };
+                    match client
+                        .idm_account_query_user_auth_token(aopts.account_id.as_str(), query)
+                        .await
+                    {
+                        Ok(tokens) => {
+                            #[allow(clippy::disallowed_methods)]
+                            // Allowed as this should represent the current time from the callers machine.
+                            let now = OffsetDateTime::now_utc();
+                            display_user_auth_tokens(&tokens, now, opt.output_mode);
+                        }
+                        Err(e) => handle_client_error(e, opt.output_mode),
+                    }
+                }
                 AccountUserAuthToken::Destroy { aopts, session_id } => {
                     let client = opt.to_client(OpType::Write).await;
                     match client
@@ -476,3 +548,52 @@ impl ServiceAccountOpt {
         }
     }
 }
+
+/// Print the api tokens of a service account, with their status at `now`.
+fn display_api_tokens(tokens: &[ApiToken], now: OffsetDateTime, output_mode: OutputMode) {
+    if let OutputMode::Json = output_mode {
+        println!(
+            "{}",
+            serde_json::to_string(tokens).expect("Failed to serialise json")
+        );
+        return;
+    }
+
+    if tokens.is_empty() {
+        println!("No api tokens exist");
+        return;
+    }
+
+    let format_time = |odt: OffsetDateTime| {
+        odt.format(&Rfc3339)
+            .unwrap_or_else(|_| "invalid timestamp".to_string())
+    };
+
+    let header = [
+        "token id".to_string(),
+        "label".to_string(),
+        "scope".to_string(),
+        "issued at".to_string(),
+        "status".to_string(),
+    ];
+
+    let rows: Vec<[String; 5]> = tokens
+        .iter()
+        .map(|token| {
+            let status = match token.expiry {
+                Some(exp) if exp <= now => format!("expired {}", format_time(exp)),
+                Some(exp) => format!("expires {}", format_time(exp)),
+                None => "never expires".to_string(),
+            };
+            [
+                token.token_id.to_string(),
+                token.label.clone(),
+                token.purpose.to_string(),
+                format_time(token.issued_at),
+                status,
+            ]
+        })
+        .collect();
+
+    print_table(&header, &rows);
+}
@@ -522,9 +522,12 @@ impl LoginOpt {
         };
 
         // What auth mechanisms exist?
-        let mut mechs: Vec<_> = client
-            .auth_step_init(username)
-            .await
+        let mechs = if self.api_only {
+            client.auth_step_init_api_only(username).await
+        } else {
+            client.auth_step_init(username).await
+        };
+        let mut mechs: Vec<_> = mechs
             .unwrap_or_else(|e| {
                 error!("Error during authentication init phase: {:?}", e);
                 std::process::exit(1);
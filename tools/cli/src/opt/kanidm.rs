@@ -537,9 +537,29 @@ pub enum AccountUserAuthToken {
     /// Show the status of logged in sessions associated to this account.
     #[clap(name = "status")]
     Status(AccountNamedOpt),
+    /// Show the number of sessions, oauth2 sessions and api tokens associated
+    /// to this account.
+    #[clap(name = "summary")]
+    Summary(AccountNamedOpt),
+    /// List the sessions associated to this account. Revoked sessions are only
+    /// listed if `--all` is set.
+    #[clap(name = "list")]
+    List {
+        #[clap(flatten)]
+        aopts: AccountCommonOpt,
+
+        /// Include revoked sessions.
+        #[clap(long)]
+        all: bool,
+
+        /// Only list sessions created by this type of authentication, such as
+        /// `password` or `passkey`.
+        #[clap(long = "auth-type")]
+        auth_type: Option<String>,
+    },
     /// Destroy / revoke a session for this account. Access to the
     /// session (user auth token) is NOT required, only the uuid of the session.
-    #[clap(name = "destroy")]
+    #[clap(name = "destroy", visible_aliases = &["revoke"])]
     Destroy {
         #[clap(flatten)]
         aopts: AccountCommonOpt,
@@ -550,6 +570,32 @@ pub enum AccountUserAuthToken {
     },
 }
 
+#[derive(Debug, Subcommand, Clone)]
+pub enum AccountOauth2Session {
+    /// List the oauth2 clients this account holds sessions with. Revoked
+    /// sessions are only listed if `--all` is set.
+    #[clap(name = "list")]
+    List {
+        #[clap(flatten)]
+        aopts: AccountCommonOpt,
+
+        /// Include revoked sessions.
+        #[clap(long)]
+        all: bool,
+    },
+    /// Revoke all of the oauth2 sessions this account holds with a client.
+    #[clap(name = "revoke")]
+    Revoke {
+        #[clap(flatten)]
+        aopts: AccountCommonOpt,
+
+        /// The name or uuid of the oauth2 client. A deleted client can only be
+        /// referred to by its uuid.
+        #[clap(long)]
+        client: String,
+    },
+}
+
 #[derive(Debug, Subcommand, Clone)]
 pub enum PersonOpt {
     /// Manage the credentials this person uses for authentication
@@ -576,6 +622,12 @@ pub enum PersonOpt {
         #[clap(subcommand)]
         commands: AccountUserAuthToken,
     },
+    /// Manage the oauth2 sessions this person holds with oauth2 clients.
+    #[clap(name = "oauth2-session")]
+    Oauth2Session {
+        #[clap(subcommand)]
+        commands: AccountOauth2Session,
+    },
     /// Manage ssh public key's associated to this person
     #[clap(name = "ssh")]
     Ssh {
@@ -626,8 +678,9 @@ pub enum ServiceAccountCredential {
 
 #[derive(Debug, Subcommand, Clone)]
 pub enum ServiceAccountApiToken {
-    /// Show the status of api tokens associated to this service account.
-    #[clap(name = "status")]
+    /// List the api tokens associated to this service account, with their scope,
+    /// issue time and expiry status.
+    #[clap(name = "status", visible_aliases = &["list"])]
     Status(AccountNamedOpt),
     /// Generate a new api token for this service account.
     #[clap(name = "generate", visible_aliases = &["create"])]
@@ -657,15 +710,19 @@ pub enum ServiceAccountApiToken {
         compact: bool,
     },
     /// Destroy / revoke an api token from this service account. Access to the
-    /// token is NOT required, only the tag/uuid of the token.
+    /// token is NOT required, only the label/uuid of the token.
     #[clap(name = "destroy")]
     Destroy {
         #[clap(flatten)]
         aopts: AccountCommonOpt,
 
         /// The UUID of the token to destroy.
-        #[clap(name = "token-id")]
-        token_id: Uuid,
+        #[clap(name = "token-id", required_unless_present = "label")]
+        token_id: Option<Uuid>,
+
+        /// The label of the token to destroy, instead of its UUID.
+        #[clap(long, conflicts_with = "token-id")]
+        label: Option<String>,
     },
 }
 
@@ -779,7 +836,12 @@ pub enum RecycleOpt {
 }
 
 #[derive(Debug, Args, Clone)]
-pub struct LoginOpt {}
+pub struct LoginOpt {
+    #[clap(long = "api-only")]
+    /// Request a read/write session for automation. This session can never
+    /// re-authenticate to access privileged functions.
+    api_only: bool,
+}
 
 #[derive(Debug, Args, Clone)]
 pub struct LogoutOpt {